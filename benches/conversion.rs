@@ -0,0 +1,130 @@
+use address_converter::application::generate::AddressGenerator;
+use address_converter::application::service::{AddressService, Format};
+use address_converter::domain::*;
+use address_converter::infrastructure::InMemoryAddressRepository;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+/// Same seed for every run, so that the measures are comparable.
+const SEED: u64 = 42;
+
+fn service() -> AddressService<InMemoryAddressRepository> {
+    AddressService::new(InMemoryAddressRepository::new())
+}
+
+fn french_inputs(count: usize) -> Vec<String> {
+    let mut generator = AddressGenerator::new(SEED);
+    (0..count)
+        .map(|_| serde_json::to_string(&generator.french(KindHint::Auto)).unwrap())
+        .collect()
+}
+
+fn iso_inputs(count: usize) -> Vec<String> {
+    let service = service();
+    french_inputs(count)
+        .iter()
+        .map(|input| {
+            let iso = service.convert(input, Format::Iso20022).unwrap();
+            serde_json::to_string(&iso.iso20022().unwrap()).unwrap()
+        })
+        .collect()
+}
+
+fn conversion(c: &mut Criterion) {
+    let service = service();
+    let french = french_inputs(100);
+    let iso = iso_inputs(100);
+
+    let mut group = c.benchmark_group("conversion");
+    group.bench_function("french_to_iso", |b| {
+        b.iter(|| {
+            for input in &french {
+                black_box(service.convert(input, Format::Iso20022).unwrap());
+            }
+        })
+    });
+    group.bench_function("french_to_iso_borrowed", |b| {
+        b.iter(|| {
+            for input in &french {
+                black_box(service.convert_borrowed(input, Format::Iso20022).unwrap());
+            }
+        })
+    });
+    group.bench_function("iso_to_french", |b| {
+        b.iter(|| {
+            for input in &iso {
+                black_box(service.convert(input, Format::French).unwrap());
+            }
+        })
+    });
+    group.bench_function("iso_to_french_borrowed", |b| {
+        b.iter(|| {
+            for input in &iso {
+                black_box(service.convert_borrowed(input, Format::French).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parsing");
+    group.bench_function("parse_street", |b| {
+        b.iter(|| FrenchAddressParser::parse_street(black_box("25 RUE DE L'EGLISE")))
+    });
+    group.bench_function("split_street", |b| {
+        b.iter(|| FrenchAddressParser::split_street(black_box("25 RUE DE L'EGLISE")))
+    });
+    group.bench_function("parse_postal", |b| {
+        b.iter(|| FrenchAddressParser::parse_postal(black_box("34092 MONTPELLIER CEDEX 5")))
+    });
+    group.bench_function("split_postal", |b| {
+        b.iter(|| FrenchAddressParser::split_postal(black_box("34092 MONTPELLIER CEDEX 5")))
+    });
+    #[cfg(feature = "fast-parse")]
+    {
+        let rules = FrenchParserRules::new().with_fast_parse();
+        group.bench_function("split_street_fast", |b| {
+            b.iter(|| rules.split_street(black_box("25 RUE DE L'EGLISE")))
+        });
+        group.bench_function("split_postal_fast", |b| {
+            b.iter(|| rules.split_postal(black_box("34092 MONTPELLIER CEDEX 5")))
+        });
+    }
+    group.bench_function("parse_business_name", |b| {
+        b.iter(|| {
+            FrenchAddressParser::parse_business_name(black_box(
+                "Société DUPONT SIRET 732 829 320 00074",
+            ))
+        })
+    });
+    group.bench_function("town_location", |b| {
+        b.iter(|| FrenchAddressParser::town_location(black_box("BP 90432 MONTFERRIER SUR LEZ")))
+    });
+    group.finish();
+}
+
+fn duplicates(c: &mut Criterion) {
+    let addresses: Vec<Address> = french_inputs(1000)
+        .iter()
+        .map(|input| {
+            let french: FrenchAddress = serde_json::from_str(input).unwrap();
+            Address::new(ConvertedAddress::from_french(french).unwrap())
+        })
+        .collect();
+
+    // The lookup done by the repositories before each save.
+    c.bench_function("duplicates/find_duplicate", |b| {
+        b.iter_batched(
+            || addresses[addresses.len() - 1].clone(),
+            |address| {
+                addresses
+                    .iter()
+                    .position(|existing| existing.is_duplicate_of(&address))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, conversion, parsing, duplicates);
+criterion_main!(benches);
@@ -1,8 +1,377 @@
-//! This is just an example file to demonstrate the case  
-//! of an API binary.
+use address_converter::application::service::BoxedAddressService;
+use address_converter::domain::repositories::{AddressRepository, Tenant};
+use address_converter::domain::{FrenchParserRules, ValidationPolicy};
+#[cfg(feature = "metrics")]
+use address_converter::infrastructure::MetricsRepository;
+#[cfg(feature = "mongodb")]
+use address_converter::infrastructure::MongoAddressRepository;
+use address_converter::infrastructure::{
+    ChangeLogRepository, Durability, JsonAddressRepository, Profile, StorageLayout,
+    TimeoutRepository,
+};
+use address_converter::presentation::api::auth::ApiAuth;
+use address_converter::presentation::api::routes::{router, router_with_auth};
+use address_converter::presentation::api::service_handle::ServiceHandle;
+#[cfg(feature = "metrics")]
+use metrics_exporter_prometheus::PrometheusBuilder;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::oneshot;
 
-fn main() {
-    println!("API entrypoint");
+/// How long the requests in flight are waited for once the shutdown starts.
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[tokio::main]
+async fn main() {
+    let storage_dir = env::var("STORAGE_DIR").unwrap_or_else(|_| "./json_storage".to_string());
+    let listen_addr = env::var("LISTEN_ADDR").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
+    // PORT, set by most platforms, replaces the port of the listen address.
+    let listen_addr = match env::var("PORT").map(|port| port.parse::<u16>()) {
+        Ok(Ok(port)) => {
+            let host = listen_addr
+                .rsplit_once(':')
+                .map_or(listen_addr.as_str(), |(host, _)| host);
+            format!("{host}:{port}")
+        }
+        Ok(Err(_)) => {
+            eprintln!("Error: PORT should be a port number");
+            std::process::exit(1);
+        }
+        Err(_) => listen_addr,
+    };
+    let shutdown_timeout = match env::var("SHUTDOWN_TIMEOUT").map(|secs| secs.parse()) {
+        Ok(Ok(secs)) => Duration::from_secs(secs),
+        Ok(Err(_)) => {
+            eprintln!("Error: SHUTDOWN_TIMEOUT should be a number of seconds");
+            std::process::exit(1);
+        }
+        Err(_) => DEFAULT_SHUTDOWN_TIMEOUT,
+    };
+    // The profile of AC_PROFILE, read from the config of AC_CONFIG.
+    let profile = match env::var("AC_PROFILE") {
+        Ok(name) => {
+            let profile = env::var("AC_CONFIG")
+                .map_err(|_| format!("AC_CONFIG should be set to read the profile `{name}`"))
+                .and_then(|path| {
+                    let content =
+                        fs::read_to_string(&path).map_err(|e| format!("Can't read {path}: {e}"))?;
+                    Profile::from_config(&content, &name, |var| env::var(var).ok())
+                        .map_err(|e| format!("{e} in {path}"))
+                });
+            match profile {
+                Ok(profile) => profile,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(_) => Profile::default(),
+    };
+    let tenant = match env::var("TENANT")
+        .ok()
+        .map(|tenant| tenant.parse::<Tenant>())
+    {
+        Some(Ok(tenant)) => Some(tenant),
+        Some(Err(e)) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        None => profile.tenant,
+    };
+    // Each tenant has its own change log, in a subdirectory.
+    let change_log_dir = env::var("CHANGE_LOG_DIR").ok().map(|dir| match &tenant {
+        Some(tenant) => PathBuf::from(dir).join(tenant.as_str()),
+        None => PathBuf::from(dir),
+    });
+    let layout = match env::var("STORAGE_LAYOUT").map(|layout| layout.parse::<StorageLayout>()) {
+        Ok(Ok(layout)) => layout,
+        Ok(Err(_)) => {
+            eprintln!("Error: STORAGE_LAYOUT should be `flat` or `sharded`");
+            std::process::exit(1);
+        }
+        Err(_) => StorageLayout::default(),
+    };
+    let durability = match env::var("STORAGE_DURABILITY").map(|value| value.parse::<Durability>()) {
+        Ok(Ok(durability)) => durability,
+        Ok(Err(_)) => {
+            eprintln!("Error: STORAGE_DURABILITY should be `fast`, `atomic` or `synced`");
+            std::process::exit(1);
+        }
+        Err(_) => Durability::default(),
+    };
+    let idempotency_retention = match env::var("IDEMPOTENCY_RETENTION").map(|secs| secs.parse()) {
+        Ok(Ok(secs)) => Duration::from_secs(secs),
+        Ok(Err(_)) => {
+            eprintln!("Error: IDEMPOTENCY_RETENTION should be a number of seconds");
+            std::process::exit(1);
+        }
+        Err(_) => BoxedAddressService::DEFAULT_IDEMPOTENCY_RETENTION,
+    };
+    let french_rules = match env::var("PARSER_CONFIG") {
+        Ok(path) => {
+            let rules = fs::read_to_string(&path)
+                .map_err(|e| format!("Can't read {path}: {e}"))
+                .and_then(|content| {
+                    FrenchParserRules::from_config(&content).map_err(|e| format!("{e} in {path}"))
+                });
+            match rules {
+                Ok(rules) => Some(rules),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(_) => None,
+    };
+    #[cfg(feature = "fast-parse")]
+    let french_rules = match env::var("FAST_PARSE") {
+        Ok(value) if value == "1" || value.eq_ignore_ascii_case("true") => {
+            Some(french_rules.unwrap_or_default().with_fast_parse())
+        }
+        _ => french_rules,
+    };
+    let validation_policy = match env::var("VALIDATION_CONFIG") {
+        Ok(path) => {
+            let policy = fs::read_to_string(&path)
+                .map_err(|e| format!("Can't read {path}: {e}"))
+                .and_then(|content| {
+                    ValidationPolicy::from_config(&content).map_err(|e| format!("{e} in {path}"))
+                });
+            match policy {
+                Ok(policy) => Some(policy),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(_) => None,
+    };
+
+    #[cfg(feature = "metrics")]
+    let metrics_handle = match PrometheusBuilder::new().install_recorder() {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("Error: can't install the metrics recorder: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mongodb_uri = env::var("MONGODB_URI").ok();
+
+    // The storage of the profile replaces the one of the environment.
+    let storage = profile.storage;
+
+    let service = ServiceHandle::spawn(move || match (storage, mongodb_uri) {
+        (Some(storage), _) => match storage.open(tenant.as_ref()) {
+            Ok(repository) => service(
+                repository,
+                change_log_dir,
+                idempotency_retention,
+                french_rules,
+                validation_policy,
+            ),
+            Err(e) => {
+                eprintln!("Error: can't open {storage}: {e}");
+                std::process::exit(1);
+            }
+        },
+        #[cfg(feature = "mongodb")]
+        (None, Some(uri)) => {
+            let database =
+                env::var("MONGODB_DATABASE").unwrap_or_else(|_| "address_converter".to_string());
+            let repository = match &tenant {
+                Some(tenant) => MongoAddressRepository::connect_for_tenant(&uri, &database, tenant),
+                None => MongoAddressRepository::connect(&uri, &database),
+            };
+            match repository {
+                Ok(repository) => service(
+                    repository,
+                    change_log_dir,
+                    idempotency_retention,
+                    french_rules,
+                    validation_policy,
+                ),
+                Err(e) => {
+                    eprintln!("Error: can't connect to MongoDB: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            let repository = match &tenant {
+                Some(tenant) => JsonAddressRepository::for_tenant(storage_dir, tenant),
+                None => JsonAddressRepository::new(storage_dir),
+            };
+            match repository.with_durability(durability).with_layout(layout) {
+                Ok(repository) => service(
+                    repository,
+                    change_log_dir,
+                    idempotency_retention,
+                    french_rules,
+                    validation_policy,
+                ),
+                Err(e) => {
+                    eprintln!("Error: can't migrate the storage to the {layout} layout: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+    });
+    let app = match env::var("AUTH_CONFIG") {
+        Ok(path) => {
+            let auth = match ApiAuth::load(&path) {
+                Ok(auth) => auth,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            #[cfg(unix)]
+            reload_on_hangup(auth.clone());
+            router_with_auth(service, auth)
+        }
+        Err(_) => router(service),
+    };
+    #[cfg(feature = "metrics")]
+    let app = app.merge(address_converter::presentation::api::metrics::router(
+        metrics_handle,
+    ));
+
+    let listener = match tokio::net::TcpListener::bind(&listen_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: can't listen on {listen_addr}: {e}");
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on http://{listen_addr}");
+
+    let (draining, drain_started) = oneshot::channel();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        println!("Shutting down, waiting for the requests in flight");
+        let _ = draining.send(());
+    });
+    let drain_timeout = async move {
+        match drain_started.await {
+            Ok(()) => tokio::time::sleep(shutdown_timeout).await,
+            // The server stopped on its own.
+            Err(_) => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        result = server => {
+            if let Err(e) = result {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        () = drain_timeout => {
+            eprintln!("Error: requests still in flight after {shutdown_timeout:?}, exiting");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Resolves on SIGTERM, sent by the orchestrators (e.g., Kubernetes) before
+/// stopping the server, or on Ctrl+C.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("Warning: can't listen to Ctrl+C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminations) => {
+                terminations.recv().await;
+            }
+            Err(e) => {
+                eprintln!("Warning: can't listen to SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => {}
+        () = terminate => {}
+    }
+}
+
+/// The service storing into `repository`, with every operation measured and
+/// bounded in time, and its changes logged in `change_log_dir` when set.
+fn service<R>(
+    repository: R,
+    change_log_dir: Option<PathBuf>,
+    idempotency_retention: Duration,
+    french_rules: Option<FrenchParserRules>,
+    validation_policy: Option<ValidationPolicy>,
+) -> BoxedAddressService
+where
+    R: AddressRepository + Send + 'static,
+{
+    let repository: Box<dyn AddressRepository + Send> = match change_log_dir {
+        Some(dir) => match ChangeLogRepository::open(repository, &dir) {
+            Ok(repository) => Box::new(repository),
+            Err(e) => {
+                eprintln!("Error: can't open the change log {}: {e}", dir.display());
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(repository),
+    };
+    #[cfg(feature = "metrics")]
+    let repository = MetricsRepository::new(repository);
+    let repository = TimeoutRepository::new(repository, TimeoutRepository::DEFAULT_TIMEOUT);
+
+    let mut service = BoxedAddressService::new(Box::new(repository))
+        .with_idempotency_retention(idempotency_retention);
+    if let Some(rules) = french_rules {
+        service = service.with_french_parser_rules(rules);
+    }
+    if let Some(policy) = validation_policy {
+        service = service.with_validation_policy(policy);
+    }
+
+    service
+}
+
+/// Reloads the authentication config on SIGHUP, to add or revoke keys
+/// without restarting the server.
+#[cfg(unix)]
+fn reload_on_hangup(auth: ApiAuth) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            eprintln!(
+                "Warning: can't listen to SIGHUP, the authentication config won't reload: {e}"
+            );
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match auth.reload() {
+                Ok(()) => println!("Reloaded the authentication config"),
+                Err(e) => eprintln!("Error: {e}, the previous config is kept"),
+            }
+        }
+    });
 }
 
 #[cfg(test)]
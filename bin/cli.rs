@@ -1,21 +1,241 @@
-use address_converter::application::service::AddressService;
-use address_converter::infrastructure::JsonAddressRepository;
+use address_converter::application::service::BoxedAddressService;
+use address_converter::domain::repositories::AddressRepository;
+#[cfg(feature = "mongodb")]
+use address_converter::domain::repositories::Tenant;
+use address_converter::domain::{IsoProfile, PostcodeValidators};
+#[cfg(feature = "mongodb")]
+use address_converter::infrastructure::MongoAddressRepository;
+#[cfg(feature = "fts")]
+use address_converter::infrastructure::SearchIndexRepository;
+use address_converter::infrastructure::{
+    CancellationToken, ChangeLogRepository, Durability, JsonAddressRepository, StorageLayout,
+    TimeoutRepository,
+};
 use address_converter::presentation::cli::commands::{run_command, Cli};
 use clap::Parser;
 use std::env;
+use std::time::Duration;
 
 #[cfg(feature = "cli")]
 fn main() {
-    let storage_dir = env::var("STORAGE_DIR").unwrap_or_else(|_| "./json_storage".to_string());
-    let service = AddressService::new(Box::new(JsonAddressRepository::new(storage_dir)));
+    let mut cli = Cli::parse();
+    let storage = cli.apply_profile().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
+    let cancellation = cancel_on_ctrl_c();
+    // The storage of the profile replaces the one of the environment.
+    let repository = match (storage, env::var("MONGODB_URI")) {
+        (Some(storage), _) => {
+            let repository = storage.open(cli.tenant.as_ref()).unwrap_or_else(|e| {
+                eprintln!("Error: can't open {storage}: {e}");
+                std::process::exit(1);
+            });
+            bounded(indexed(logged(repository, &cli), &cli), &cli)
+        }
+        #[cfg(feature = "mongodb")]
+        (None, Ok(uri)) => bounded(
+            indexed(
+                logged(mongo_repository(&uri, cli.tenant.as_ref()), &cli),
+                &cli,
+            ),
+            &cli,
+        ),
+        _ => bounded(indexed(logged(json_repository(&cli), &cli), &cli), &cli),
+    }
+    .with_cancellation(cancellation.clone());
+    let mut service = BoxedAddressService::new(Box::new(repository))
+        .with_cancellation(cancellation)
+        .with_mail_origin(cli.mail_origin)
+        .with_iso_profile(IsoProfile {
+            country_repr: cli.iso_country,
+            overseas: cli.iso_overseas,
+            number_ext: cli.iso_number_ext,
+            long_fields: cli.iso_long_fields,
+        })
+        .with_street_policy(cli.street_policy);
+    match cli.normalizer() {
+        Ok(Some(normalizer)) => service = service.with_normalizer(normalizer),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    match cli.french_parser_rules() {
+        Ok(Some(rules)) => service = service.with_french_parser_rules(rules),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    match cli.validation_policy() {
+        Ok(Some(policy)) => service = service.with_validation_policy(policy),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if cli.check_postcodes {
+        service = service.with_postcode_validators(PostcodeValidators::new());
+    }
+    match env::var("IDEMPOTENCY_RETENTION").map(|secs| secs.parse()) {
+        Ok(Ok(secs)) => {
+            service = service.with_idempotency_retention(Duration::from_secs(secs));
+        }
+        Ok(Err(_)) => {
+            eprintln!("Error: IDEMPOTENCY_RETENTION should be a number of seconds");
+            std::process::exit(1);
+        }
+        Err(_) => {}
+    }
 
-    let cli = Cli::parse();
     if let Err(e) = run_command(cli, &service) {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
 }
 
+#[cfg(feature = "cli")]
+fn json_repository(cli: &Cli) -> JsonAddressRepository {
+    let storage_dir = env::var("STORAGE_DIR").unwrap_or_else(|_| "./json_storage".to_string());
+    let layout = match env::var("STORAGE_LAYOUT").map(|layout| layout.parse::<StorageLayout>()) {
+        Ok(Ok(layout)) => layout,
+        Ok(Err(_)) => {
+            eprintln!("Error: STORAGE_LAYOUT should be `flat` or `sharded`");
+            std::process::exit(1);
+        }
+        Err(_) => StorageLayout::default(),
+    };
+    let durability = match env::var("STORAGE_DURABILITY").map(|value| value.parse::<Durability>()) {
+        Ok(Ok(durability)) => durability,
+        Ok(Err(_)) => {
+            eprintln!("Error: STORAGE_DURABILITY should be `fast`, `atomic` or `synced`");
+            std::process::exit(1);
+        }
+        Err(_) => Durability::default(),
+    };
+    let repository = match &cli.tenant {
+        Some(tenant) => JsonAddressRepository::for_tenant(storage_dir, tenant),
+        None => JsonAddressRepository::new(storage_dir),
+    };
+
+    match repository.with_durability(durability).with_layout(layout) {
+        Ok(repository) => repository,
+        Err(e) => {
+            eprintln!("Error: can't migrate the storage to the {layout} layout: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The repository appending its changes to the log of `CHANGE_LOG_DIR`, when
+/// set. Each tenant has its own log, in a subdirectory.
+#[cfg(feature = "cli")]
+fn logged<R>(repository: R, cli: &Cli) -> Box<dyn AddressRepository + Send>
+where
+    R: AddressRepository + Send + 'static,
+{
+    let Ok(dir) = env::var("CHANGE_LOG_DIR") else {
+        return Box::new(repository);
+    };
+    let dir = match &cli.tenant {
+        Some(tenant) => std::path::Path::new(&dir).join(tenant.as_str()),
+        None => dir.into(),
+    };
+
+    match ChangeLogRepository::open(repository, &dir) {
+        Ok(repository) => Box::new(repository),
+        Err(e) => {
+            eprintln!("Error: can't open the change log {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The repository whose operations are limited to `--timeout`, when given.
+/// There is no limit by default, the bulk operations (e.g., `export`, `dedupe`)
+/// of a large storage taking longer than a single one.
+#[cfg(feature = "cli")]
+fn bounded<R>(repository: R, cli: &Cli) -> TimeoutRepository
+where
+    R: AddressRepository + Send + 'static,
+{
+    match cli.timeout {
+        Some(secs) => TimeoutRepository::new(repository, Duration::from_secs(secs)),
+        None => TimeoutRepository::cancellable(repository),
+    }
+}
+
+/// The token cancelling the storage operations on Ctrl-C, so that the command
+/// stops at its next operation. A second Ctrl-C exits right away.
+#[cfg(feature = "cli")]
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancellation = CancellationToken::new();
+    let token = cancellation.clone();
+    let handled = ctrlc::set_handler(move || {
+        if token.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!("Cancelling, press Ctrl-C again to exit right away");
+        token.cancel();
+    });
+    if let Err(e) = handled {
+        eprintln!("Warning: Ctrl-C won't cancel the storage operations: {e}");
+    }
+
+    cancellation
+}
+
+/// The repository keeping the search index of `SEARCH_INDEX_DIR` up to date,
+/// when set. Each tenant has its own index, in a subdirectory.
+#[cfg(feature = "fts")]
+fn indexed<R>(repository: R, cli: &Cli) -> Box<dyn AddressRepository + Send>
+where
+    R: AddressRepository + Send + 'static,
+{
+    let Ok(dir) = env::var("SEARCH_INDEX_DIR") else {
+        return Box::new(repository);
+    };
+    let dir = match &cli.tenant {
+        Some(tenant) => std::path::Path::new(&dir).join(tenant.as_str()),
+        None => dir.into(),
+    };
+
+    match SearchIndexRepository::open(repository, &dir) {
+        Ok(repository) => Box::new(repository),
+        Err(e) => {
+            eprintln!("Error: can't open the search index {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "fts"))]
+fn indexed<R>(repository: R, _: &Cli) -> Box<dyn AddressRepository + Send>
+where
+    R: AddressRepository + Send + 'static,
+{
+    Box::new(repository)
+}
+
+#[cfg(feature = "mongodb")]
+fn mongo_repository(uri: &str, tenant: Option<&Tenant>) -> MongoAddressRepository {
+    let database = env::var("MONGODB_DATABASE").unwrap_or_else(|_| "address_converter".to_string());
+    let repository = match tenant {
+        Some(tenant) => MongoAddressRepository::connect_for_tenant(uri, &database, tenant),
+        None => MongoAddressRepository::connect(uri, &database),
+    };
+
+    repository.unwrap_or_else(|e| {
+        eprintln!("Error: can't connect to MongoDB: {e}");
+        std::process::exit(1);
+    })
+}
+
 #[cfg(not(feature = "cli"))]
 fn main() {
     eprintln!("CLI support is disabled. Enable the 'cli' feature to use this binary.");
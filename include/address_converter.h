@@ -0,0 +1,68 @@
+#ifndef ADDRESS_CONVERTER_H
+#define ADDRESS_CONVERTER_H
+
+/* Generated with cbindgen from src/presentation/ffi.rs, don't edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of a call, its value being stable across releases.
+typedef enum AcStatus {
+  // The buffer holds the result.
+  AC_STATUS_OK = 0,
+  // A string is NULL or isn't UTF-8, or a format is unknown.
+  AC_STATUS_INVALID_ARGUMENT = 1,
+  // The input can't be read (e.g., malformed JSON).
+  AC_STATUS_INVALID_INPUT = 2,
+  // The input is read, but isn't a valid address (e.g., missing field,
+  // invalid postal line).
+  AC_STATUS_INVALID_ADDRESS = 3,
+  // The buffer is too small for the result, whose length is written to
+  // `out_written`. The buffer receives an empty string.
+  AC_STATUS_BUFFER_TOO_SMALL = 4,
+  // Any other failure, the converter included.
+  AC_STATUS_INTERNAL = 5,
+} AcStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Converts the JSON `input` of the format `from` (e.g., `french`) to the
+// JSON of the format `to` (e.g., `iso20022`). The formats are read like the
+// CLI reads them, aliases included.
+//
+// # Safety
+//
+// `input`, `from` and `to` must be NUL-terminated strings. `out_buf` must be
+// valid for `out_len` bytes, or NULL with `out_len` being 0. `out_written`
+// must be NULL or valid for a write: it receives the length of the result,
+// without its NUL, so that a caller given [`AcStatus::BufferTooSmall`] can
+// retry with a large enough buffer.
+enum AcStatus ac_convert(const char *input,
+                         const char *from,
+                         const char *to,
+                         char *out_buf,
+                         size_t out_len,
+                         size_t *out_written);
+
+// Checks the JSON `input` of `format` as it would be before being saved.
+// The buffer receives an empty string when the address is valid.
+//
+// # Safety
+//
+// Same as [`ac_convert`].
+enum AcStatus ac_validate(const char *input,
+                          const char *format,
+                          char *out_buf,
+                          size_t out_len,
+                          size_t *out_written);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ADDRESS_CONVERTER_H */
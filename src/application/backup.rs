@@ -0,0 +1,208 @@
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+use crate::domain::Address;
+
+/// The version of the snapshot layout, stamped in the manifest. Restoring a
+/// snapshot written with a newer version is refused.
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+
+/// The first entry of a snapshot, describing its content.
+const MANIFEST_ENTRY: &str = "manifest.json";
+/// The directory of the address entries, one JSON file per address.
+const ADDRESSES_DIR: &str = "addresses";
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Snapshot I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid snapshot entry: {0}")]
+    InvalidEntry(#[from] serde_json::Error),
+    #[error("Invalid snapshot: the manifest must be the first entry")]
+    MissingManifest,
+    #[error(
+        "Unsupported snapshot schema version {0}, this version restores up to {SNAPSHOT_SCHEMA_VERSION}"
+    )]
+    UnsupportedSchema(u32),
+    #[error("Repository error: {0}")]
+    Repository(#[from] AddressRepositoryError),
+}
+
+/// Describes a snapshot: the layout version, when it was taken and how many
+/// addresses it holds.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub schema_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub addresses: usize,
+}
+
+/// The outcome of a restore.
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    /// Identifiers of the restored addresses.
+    pub restored: Vec<Uuid>,
+    /// Identifiers of the addresses skipped because they, or a duplicate, were
+    /// already stored.
+    pub skipped: Vec<Uuid>,
+}
+
+/// Writes every address of `repository` to a zstd compressed tar snapshot.
+/// Only [`AddressRepository::fetch_all`] is used, so that any backend can be
+/// backed up.
+pub fn backup(
+    repository: &dyn AddressRepository,
+    out: impl Write,
+) -> Result<SnapshotManifest, BackupError> {
+    let addresses = repository.fetch_all()?;
+    let manifest = SnapshotManifest {
+        schema_version: SNAPSHOT_SCHEMA_VERSION,
+        created_at: Utc::now(),
+        addresses: addresses.len(),
+    };
+
+    let encoder = zstd::Encoder::new(out, 0)?.auto_finish();
+    let mut archive = tar::Builder::new(encoder);
+    append_json(&mut archive, MANIFEST_ENTRY, &manifest)?;
+    for address in &addresses {
+        let path = format!("{ADDRESSES_DIR}/{}.json", address.id());
+        append_json(&mut archive, &path, address)?;
+    }
+    archive.into_inner()?.flush()?;
+
+    Ok(manifest)
+}
+
+/// Saves the addresses of a snapshot into `repository`, which may be a
+/// different backend than the one backed up. Addresses keep their identifier
+/// and modification date. Stored addresses, and their duplicates, are skipped.
+pub fn restore(
+    repository: &dyn AddressRepository,
+    snapshot: impl Read,
+) -> Result<(SnapshotManifest, RestoreReport), BackupError> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(snapshot)?);
+    let mut entries = archive.entries()?;
+
+    let mut manifest_entry = entries.next().ok_or(BackupError::MissingManifest)??;
+    if manifest_entry.path()?.to_str() != Some(MANIFEST_ENTRY) {
+        return Err(BackupError::MissingManifest);
+    }
+    let manifest: SnapshotManifest = read_json(&mut manifest_entry)?;
+    if manifest.schema_version > SNAPSHOT_SCHEMA_VERSION {
+        return Err(BackupError::UnsupportedSchema(manifest.schema_version));
+    }
+
+    let mut report = RestoreReport::default();
+    for entry in entries {
+        let mut entry = entry?;
+        if !entry.path()?.starts_with(ADDRESSES_DIR) {
+            continue;
+        }
+
+        let address: Address = read_json(&mut entry)?;
+        let id = address.id();
+        match repository.save(address) {
+            Ok(id) => report.restored.push(id),
+            Err(AddressRepositoryError::AlreadyExists(_)) => report.skipped.push(id),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok((manifest, report))
+}
+
+fn append_json<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    value: &impl Serialize,
+) -> Result<(), BackupError> {
+    let content = serde_json::to_vec_pretty(value)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    archive.append_data(&mut header, path, content.as_slice())?;
+
+    Ok(())
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(entry: &mut impl Read) -> Result<T, BackupError> {
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content)?;
+
+    Ok(serde_json::from_slice(&content)?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{backup, restore, BackupError, SnapshotManifest, SNAPSHOT_SCHEMA_VERSION};
+    use crate::application::service::{AddressService, Format};
+    use crate::domain::repositories::AddressRepository;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    const ADDRESSES: [&str; 2] = [
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        r#"{"business_name": "Société DUPONT", "street": "56 RUE EMILE ZOLA", "postal": "34092 MONTPELLIER CEDEX 5", "country": "FRANCE"}"#,
+    ];
+
+    #[test]
+    fn backup_and_restore_into_another_repository() -> Result<(), Box<dyn std::error::Error>> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        for address in ADDRESSES {
+            service.save(address, Format::French)?;
+        }
+
+        let mut snapshot = Vec::new();
+        let manifest = backup(&service.repository, &mut snapshot)?;
+        assert_eq!(manifest.schema_version, SNAPSHOT_SCHEMA_VERSION);
+        assert_eq!(manifest.addresses, 2);
+
+        let target = InMemoryAddressRepository::new();
+        let (restored_manifest, report) = restore(&target, snapshot.as_slice())?;
+        assert_eq!(restored_manifest, manifest);
+        assert_eq!(report.restored.len(), 2);
+        assert!(report.skipped.is_empty());
+
+        let mut original = service.repository.fetch_all()?;
+        let mut copy = target.fetch_all()?;
+        original.sort_by_key(|address| address.id());
+        copy.sort_by_key(|address| address.id());
+        assert_eq!(original, copy);
+
+        // Restoring twice doesn't duplicate the addresses.
+        let (_, report) = restore(&target, snapshot.as_slice())?;
+        assert!(report.restored.is_empty());
+        assert_eq!(report.skipped.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn restore_refuses_newer_schema() -> Result<(), Box<dyn std::error::Error>> {
+        let manifest = SnapshotManifest {
+            schema_version: SNAPSHOT_SCHEMA_VERSION + 1,
+            created_at: chrono::Utc::now(),
+            addresses: 0,
+        };
+        let mut snapshot = Vec::new();
+        {
+            let encoder = zstd::Encoder::new(&mut snapshot, 0)?.auto_finish();
+            let mut archive = tar::Builder::new(encoder);
+            super::append_json(&mut archive, super::MANIFEST_ENTRY, &manifest)?;
+            archive.finish()?;
+        }
+
+        let result = restore(&InMemoryAddressRepository::new(), snapshot.as_slice());
+        assert!(
+            matches!(result, Err(BackupError::UnsupportedSchema(version)) if version == SNAPSHOT_SCHEMA_VERSION + 1)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,885 @@
+#[cfg(feature = "xlsx")]
+use std::collections::HashMap;
+#[cfg(feature = "xlsx")]
+use std::io;
+use std::path::Path;
+use std::string::FromUtf8Error;
+
+use serde_json::{Map, Value};
+use strum::EnumString;
+use uuid::Uuid;
+
+use super::json_error::JsonError;
+use super::service::{
+    AddressService, AddressServiceError, Either, Format, SaveOutcome, ServiceResult,
+};
+use crate::domain::repositories::AddressRepository;
+use crate::domain::{Address, FrenchAddress, IsoAddress, Migration, UnstructuredToStructured};
+
+/// The kind of content handled by the batch pipeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BatchFileKind {
+    /// A single JSON address, a JSON array of addresses or newline delimited
+    /// JSON (one address per line).
+    Json,
+    /// A CSV file with a header row naming the fields of the input format.
+    Csv,
+}
+
+impl BatchFileKind {
+    /// Guesses the kind of content from the file extension. Returns `None` for
+    /// unsupported files.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+
+        match ext.as_str() {
+            "json" | "ndjson" => Some(BatchFileKind::Json),
+            "csv" => Some(BatchFileKind::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// The character encoding of the files read by the batch pipeline, which is
+/// transcoded to UTF-8 before parsing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, strum_macros::Display, EnumString)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum InputEncoding {
+    /// UTF-8 when the content is valid UTF-8, Windows-1252 otherwise.
+    #[default]
+    Auto,
+    #[strum(serialize = "utf-8", serialize = "utf8")]
+    Utf8,
+    /// Windows-1252, the superset of Latin-1 (ISO-8859-1) of the legacy
+    /// exports, with the typographic apostrophe and quotes of its 0x80-0x9F
+    /// range.
+    #[strum(
+        serialize = "latin1",
+        serialize = "latin-1",
+        serialize = "iso-8859-1",
+        serialize = "windows-1252",
+        serialize = "cp1252"
+    )]
+    Latin1,
+}
+
+/// The characters of the 0x80-0x9F range of Windows-1252, where Latin-1 has
+/// control characters. The 5 unassigned bytes are read as in Latin-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+impl InputEncoding {
+    /// Transcodes `bytes` to UTF-8. Only UTF-8 can fail, on an invalid byte
+    /// sequence.
+    pub fn decode(self, bytes: Vec<u8>) -> Result<String, FromUtf8Error> {
+        match self {
+            InputEncoding::Utf8 => String::from_utf8(bytes),
+            InputEncoding::Auto => {
+                String::from_utf8(bytes).or_else(|e| Ok(decode_windows_1252(e.as_bytes())))
+            }
+            InputEncoding::Latin1 => Ok(decode_windows_1252(&bytes)),
+        }
+    }
+}
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+            byte => char::from(byte),
+        })
+        .collect()
+}
+
+/// A record that couldn't be imported.
+#[derive(Debug)]
+pub struct BatchFailure {
+    /// The position of the record in the input, starting at 0.
+    pub record: usize,
+    /// The reason of the failure.
+    pub error: AddressServiceError,
+}
+
+/// The outcome of a batch import. Records are imported independently, so a
+/// failing record doesn't prevent the others from being saved.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Identifiers of the saved addresses, in input order.
+    pub saved: Vec<Uuid>,
+    /// Identifiers of the stored duplicates updated in upsert mode.
+    pub updated: Vec<Uuid>,
+    /// Records rejected during conversion or persistence.
+    pub failures: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    pub fn total(&self) -> usize {
+        self.saved.len() + self.updated.len() + self.failures.len()
+    }
+}
+
+/// A record duplicating a stored address.
+#[derive(Debug, PartialEq)]
+pub struct StoredDuplicate {
+    /// The position of the record in the input, starting at 0.
+    pub record: usize,
+    /// The identifier of the stored address.
+    pub existing: Uuid,
+}
+
+/// A record duplicating a previous record of the same input.
+#[derive(Debug, PartialEq)]
+pub struct FileDuplicate {
+    /// The position of the record in the input, starting at 0.
+    pub record: usize,
+    /// The position of the first record of the input with this address.
+    pub first_record: usize,
+}
+
+/// What an import would do, computed without writing anything.
+#[derive(Debug, Default)]
+pub struct ImportAnalysis {
+    /// Positions of the records that would be saved as new addresses.
+    pub new: Vec<usize>,
+    /// Records rejected, or updated in upsert mode, as duplicates of stored
+    /// addresses.
+    pub stored_duplicates: Vec<StoredDuplicate>,
+    /// Records rejected, or updated in upsert mode, as duplicates of a
+    /// previous record of the input.
+    pub file_duplicates: Vec<FileDuplicate>,
+    /// Records rejected during conversion.
+    pub invalid: Vec<BatchFailure>,
+}
+
+impl ImportAnalysis {
+    pub fn total(&self) -> usize {
+        self.new.len()
+            + self.stored_duplicates.len()
+            + self.file_duplicates.len()
+            + self.invalid.len()
+    }
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Saves every address contained in `content`. The content is split into
+    /// records according to its kind, then each record goes through the same
+    /// conversion and persistence rules as [`AddressService::save`].
+    ///
+    /// In upsert mode, duplicates of stored addresses are updated like with
+    /// [`AddressService::upsert`] instead of being rejected.
+    ///
+    /// An error is only returned when the content itself can't be read as
+    /// JSON or CSV, or when the import is cancelled, see
+    /// [`AddressService::with_cancellation`]. Invalid records are reported in
+    /// the [`BatchReport`].
+    pub fn import(
+        &self,
+        content: &str,
+        kind: BatchFileKind,
+        from_format: Format,
+        upsert: bool,
+    ) -> ServiceResult<BatchReport> {
+        self.import_with_progress(content, kind, from_format, upsert, &|_, _| {})
+    }
+
+    /// Same as [`AddressService::import`], calling `progress` with the number
+    /// of records imported and the total before the first record and after
+    /// each one, e.g., to draw a progress bar.
+    pub fn import_with_progress(
+        &self,
+        content: &str,
+        kind: BatchFileKind,
+        from_format: Format,
+        upsert: bool,
+        progress: &dyn Fn(usize, usize),
+    ) -> ServiceResult<BatchReport> {
+        let records = records(content, kind, from_format)?;
+
+        self.import_records(&records, from_format, upsert, progress)
+    }
+
+    /// Same as [`AddressService::import`], with the rows of a sheet of an
+    /// Excel workbook.
+    #[cfg(feature = "xlsx")]
+    pub fn import_xlsx(
+        &self,
+        workbook: &[u8],
+        options: &XlsxOptions,
+        from_format: Format,
+        upsert: bool,
+    ) -> ServiceResult<BatchReport> {
+        self.import_xlsx_with_progress(workbook, options, from_format, upsert, &|_, _| {})
+    }
+
+    /// Same as [`AddressService::import_xlsx`], calling `progress` like
+    /// [`AddressService::import_with_progress`] does.
+    #[cfg(feature = "xlsx")]
+    pub fn import_xlsx_with_progress(
+        &self,
+        workbook: &[u8],
+        options: &XlsxOptions,
+        from_format: Format,
+        upsert: bool,
+        progress: &dyn Fn(usize, usize),
+    ) -> ServiceResult<BatchReport> {
+        let records = xlsx_records(workbook, options, from_format)?;
+
+        self.import_records(&records, from_format, upsert, progress)
+    }
+
+    /// Imports a single record like [`AddressService::import`] does, for the
+    /// callers reading the records one by one (e.g., a streamed body).
+    pub fn import_record(
+        &self,
+        input: &str,
+        from_format: Format,
+        upsert: bool,
+    ) -> ServiceResult<SaveOutcome> {
+        if upsert {
+            self.upsert(input, from_format)
+        } else {
+            self.save(input, from_format).map(SaveOutcome::Created)
+        }
+    }
+
+    fn import_records(
+        &self,
+        records: &[String],
+        from_format: Format,
+        upsert: bool,
+        progress: &dyn Fn(usize, usize),
+    ) -> ServiceResult<BatchReport> {
+        let mut report = BatchReport::default();
+        progress(0, records.len());
+        for (record, input) in records.iter().enumerate() {
+            self.check_cancelled()?;
+            match self.import_record(input, from_format, upsert) {
+                Ok(SaveOutcome::Created(id) | SaveOutcome::Replayed(id)) => report.saved.push(id),
+                Ok(SaveOutcome::Updated(id)) => report.updated.push(id),
+                Err(error) => report.failures.push(BatchFailure {
+                    record,
+                    error: match error {
+                        AddressServiceError::InvalidJson(error) => {
+                            AddressServiceError::InvalidJson(error.at_record(record))
+                        }
+                        error => error,
+                    },
+                }),
+            }
+            progress(record + 1, records.len());
+        }
+
+        Ok(report)
+    }
+
+    /// Runs the parsing, conversion and duplicate detection of
+    /// [`AddressService::import`] over `content` without writing anything, to
+    /// review a load before running it.
+    ///
+    /// The stored addresses are read once, so the analysis is only accurate
+    /// while nothing else writes to the repository.
+    pub fn analyze_import(
+        &self,
+        content: &str,
+        kind: BatchFileKind,
+        from_format: Format,
+    ) -> ServiceResult<ImportAnalysis> {
+        let records = records(content, kind, from_format)?;
+
+        self.analyze_records(&records, from_format)
+    }
+
+    /// Same as [`AddressService::analyze_import`], with the rows of a sheet of
+    /// an Excel workbook.
+    #[cfg(feature = "xlsx")]
+    pub fn analyze_xlsx_import(
+        &self,
+        workbook: &[u8],
+        options: &XlsxOptions,
+        from_format: Format,
+    ) -> ServiceResult<ImportAnalysis> {
+        let records = xlsx_records(workbook, options, from_format)?;
+
+        self.analyze_records(&records, from_format)
+    }
+
+    fn analyze_records(
+        &self,
+        records: &[String],
+        from_format: Format,
+    ) -> ServiceResult<ImportAnalysis> {
+        let stored = self.repository.fetch_all()?;
+
+        let mut analysis = ImportAnalysis::default();
+        // The addresses of the input that would be saved, with their position.
+        let mut new_addresses: Vec<(usize, Address)> = Vec::new();
+        for (record, input) in records.iter().enumerate() {
+            let address = match self.parse(input, from_format) {
+                Ok(converted) => Address::new(converted),
+                Err(error) => {
+                    analysis.invalid.push(BatchFailure { record, error });
+                    continue;
+                }
+            };
+
+            if let Some(existing) = stored
+                .iter()
+                .find(|stored| stored.is_duplicate_of(&address))
+            {
+                analysis.stored_duplicates.push(StoredDuplicate {
+                    record,
+                    existing: existing.id(),
+                });
+            } else if let Some((first_record, _)) = new_addresses
+                .iter()
+                .find(|(_, new_address)| new_address.is_duplicate_of(&address))
+            {
+                analysis.file_duplicates.push(FileDuplicate {
+                    record,
+                    first_record: *first_record,
+                });
+            } else {
+                analysis.new.push(record);
+                new_addresses.push((record, address));
+            }
+        }
+
+        Ok(analysis)
+    }
+
+    /// Converts every address contained in `content` to `to_format` without
+    /// saving them, split into records like [`AddressService::import`] does.
+    /// The records are converted in parallel, see
+    /// [`AddressService::convert_batch_parallel`].
+    ///
+    /// An error is only returned when the content itself can't be read as
+    /// JSON or CSV, the result of each record being returned in input order.
+    pub fn reformat(
+        &self,
+        content: &str,
+        kind: BatchFileKind,
+        from_format: Format,
+        to_format: Format,
+    ) -> ServiceResult<Vec<ServiceResult<Either<FrenchAddress, IsoAddress>>>> {
+        let records = records(content, kind, from_format)?;
+        let inputs: Vec<&str> = records.iter().map(String::as_str).collect();
+
+        Ok(self
+            .convert_batch_parallel(&inputs, from_format, to_format)
+            .into_iter()
+            .enumerate()
+            .map(|(record, result)| {
+                result.map_err(|error| match error {
+                    AddressServiceError::InvalidJson(error) => {
+                        AddressServiceError::InvalidJson(error.at_record(record))
+                    }
+                    error => error,
+                })
+            })
+            .collect())
+    }
+
+    /// Migrates every unstructured ISO 20022 address of the JSON `content`
+    /// without saving them, see [`AddressService::migrate_iso`].
+    ///
+    /// An error is only returned when the content itself can't be read as
+    /// JSON, the result of each record being returned in input order.
+    pub fn migrate_iso_batch(
+        &self,
+        content: &str,
+        converter: &UnstructuredToStructured,
+    ) -> ServiceResult<Vec<ServiceResult<Migration>>> {
+        Ok(json_records(content)?
+            .iter()
+            .enumerate()
+            .map(|(record, input)| {
+                self.migrate_iso(input, converter)
+                    .map_err(|error| match error {
+                        AddressServiceError::InvalidJson(error) => {
+                            AddressServiceError::InvalidJson(error.at_record(record))
+                        }
+                        error => error,
+                    })
+            })
+            .collect())
+    }
+}
+
+/// Splits the content into raw address records according to its kind.
+fn records(content: &str, kind: BatchFileKind, from_format: Format) -> ServiceResult<Vec<String>> {
+    match kind {
+        BatchFileKind::Json => json_records(content),
+        BatchFileKind::Csv => csv_records(content, from_format),
+    }
+}
+
+/// Splits JSON content into raw address records. Top level arrays are
+/// flattened so that both `[{...}, {...}]` and newline delimited objects are
+/// accepted.
+fn json_records(content: &str) -> ServiceResult<Vec<String>> {
+    let mut records = Vec::new();
+
+    for value in serde_json::Deserializer::from_str(content).into_iter::<Value>() {
+        match value.map_err(|error| JsonError::from(error).with_snippet(content))? {
+            Value::Array(values) => records.extend(values.iter().map(Value::to_string)),
+            value => records.push(value.to_string()),
+        }
+    }
+
+    Ok(records)
+}
+
+/// Maps each CSV row to a JSON record of the given format, see
+/// [`table_records`].
+fn csv_records(content: &str, from_format: Format) -> ServiceResult<Vec<String>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers: Vec<String> = reader.headers()?.iter().map(str::to_string).collect();
+    let rows = reader
+        .records()
+        .map(|row| Ok(row?.iter().map(str::to_string).collect()))
+        .collect::<ServiceResult<Vec<Vec<String>>>>()?;
+
+    Ok(table_records(&headers, rows, from_format))
+}
+
+/// Maps each row of a table to a JSON record of the given format, the
+/// headers naming the fields. Empty cells are treated as missing fields. ISO
+/// 20022 rows are flat, so every column other than the name is moved under
+/// `postal_address`.
+fn table_records(headers: &[String], rows: Vec<Vec<String>>, from_format: Format) -> Vec<String> {
+    let mut records = Vec::new();
+
+    for row in rows {
+        let mut fields = Map::new();
+        let mut postal_address = Map::new();
+
+        for (header, cell) in headers.iter().zip(row) {
+            let cell = cell.trim();
+            if cell.is_empty() {
+                continue;
+            }
+
+            let value = Value::String(cell.to_string());
+            match from_format {
+                Format::Iso20022 if header != "name" && header != "business_name" => {
+                    postal_address.insert(header.to_string(), value);
+                }
+                _ => {
+                    fields.insert(header.to_string(), value);
+                }
+            }
+        }
+
+        if from_format == Format::Iso20022 {
+            fields.insert("postal_address".to_string(), Value::Object(postal_address));
+        }
+
+        records.push(Value::Object(fields).to_string());
+    }
+
+    records
+}
+
+/// Where the addresses are in an Excel workbook.
+#[cfg(feature = "xlsx")]
+#[derive(Clone, Debug, Default)]
+pub struct XlsxOptions {
+    /// The name of the sheet holding the addresses, the first sheet by
+    /// default.
+    pub sheet: Option<String>,
+    /// The field of the input format of each column header, for the headers
+    /// not named after a field (e.g., `Code postal` -> `postal`).
+    pub columns: HashMap<String, String>,
+}
+
+/// Maps each row of the sheet to a JSON record of the given format, the first
+/// row being the headers. Numbers are written without decimals when they have
+/// none, so that a postcode stored as a number reads `33380`.
+#[cfg(feature = "xlsx")]
+fn xlsx_records(
+    workbook: &[u8],
+    options: &XlsxOptions,
+    from_format: Format,
+) -> ServiceResult<Vec<String>> {
+    use calamine::{Reader, Xlsx, XlsxError};
+
+    let mut workbook: Xlsx<_> = Xlsx::new(io::Cursor::new(workbook))?;
+    let range = match &options.sheet {
+        Some(sheet) => workbook.worksheet_range(sheet)?,
+        None => workbook
+            .worksheet_range_at(0)
+            .ok_or_else(|| XlsxError::WorksheetNotFound("1".to_string()))??,
+    };
+
+    let mut rows = range
+        .rows()
+        .map(|row| row.iter().map(ToString::to_string).collect::<Vec<_>>());
+    let headers: Vec<String> = rows
+        .next()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|header| {
+            let header = header.trim();
+            options
+                .columns
+                .get(header)
+                .cloned()
+                .unwrap_or_else(|| header.to_string())
+        })
+        .collect();
+    let rows = rows
+        .filter(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+        .collect();
+
+    Ok(table_records(&headers, rows, from_format))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::cell::RefCell;
+    use std::path::Path;
+
+    use super::{BatchFileKind, BatchReport, FileDuplicate, InputEncoding, StoredDuplicate};
+    use crate::application::service::{AddressService, AddressServiceError, Either, Format};
+    use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+    use crate::domain::{IsoJsonKeys, UnstructuredToStructured};
+    use crate::infrastructure::{CancellationToken, InMemoryAddressRepository};
+
+    fn service() -> AddressService<InMemoryAddressRepository> {
+        let repo = InMemoryAddressRepository::new();
+        AddressService::new(repo)
+    }
+
+    #[test]
+    fn decode_legacy_exports() {
+        // "25 RUE DE L’EGLISE;Mérignac" in Windows-1252.
+        let latin1 = b"25 RUE DE L\x92EGLISE;M\xe9rignac".to_vec();
+        let expected = "25 RUE DE L’EGLISE;Mérignac";
+
+        assert_eq!(
+            InputEncoding::Latin1.decode(latin1.clone()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            InputEncoding::Auto.decode(latin1.clone()).unwrap(),
+            expected
+        );
+        assert!(InputEncoding::Utf8.decode(latin1).is_err());
+        // Valid UTF-8 is kept as is.
+        let utf8 = expected.as_bytes().to_vec();
+        assert_eq!(InputEncoding::Auto.decode(utf8).unwrap(), expected);
+        assert_eq!("CP1252".parse(), Ok(InputEncoding::Latin1));
+    }
+
+    #[test]
+    fn file_kind_from_extension() {
+        assert_eq!(
+            BatchFileKind::from_path(Path::new("in/addresses.JSON")),
+            Some(BatchFileKind::Json)
+        );
+        assert_eq!(
+            BatchFileKind::from_path(Path::new("addresses.ndjson")),
+            Some(BatchFileKind::Json)
+        );
+        assert_eq!(
+            BatchFileKind::from_path(Path::new("addresses.csv")),
+            Some(BatchFileKind::Csv)
+        );
+        assert_eq!(BatchFileKind::from_path(Path::new("addresses.txt")), None);
+        assert_eq!(BatchFileKind::from_path(Path::new("addresses")), None);
+    }
+
+    #[test]
+    fn import_json_array() -> Result<(), AddressServiceError> {
+        let service = service();
+        let input = r#"[
+            {"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"},
+            {"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}
+        ]"#;
+
+        let report = service.import(input, BatchFileKind::Json, Format::French, false)?;
+        assert_eq!(report.saved.len(), 2);
+        assert!(report.failures.is_empty());
+        assert_eq!(service.repository.fetch_all()?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_ndjson_with_invalid_record() -> Result<(), AddressServiceError> {
+        let service = service();
+        let input = concat!(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            "\n",
+            r#"{"name": "Madame Isabelle RICHARD", "postal": "AUTERIVE", "country": "FRANCE"}"#,
+            "\n",
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": 82500, "country": "FRANCE"}"#,
+            "\n",
+        );
+
+        let calls = RefCell::new(Vec::new());
+        let BatchReport {
+            saved, failures, ..
+        } = service.import_with_progress(
+            input,
+            BatchFileKind::Json,
+            Format::French,
+            false,
+            &|done, total| calls.borrow_mut().push((done, total)),
+        )?;
+        assert_eq!(saved.len(), 1);
+        assert_eq!(failures.len(), 2);
+        // The invalid records count as processed.
+        assert_eq!(calls.into_inner(), [(0, 3), (1, 3), (2, 3), (3, 3)]);
+        assert_eq!(failures[0].record, 1);
+        assert!(matches!(
+            failures[0].error,
+            AddressServiceError::ConversionError(_)
+        ));
+        let AddressServiceError::InvalidJson(error) = &failures[1].error else {
+            panic!("error was {:?}", failures[1].error);
+        };
+        assert_eq!(error.path(), Some("postal"));
+        assert_eq!(error.record(), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_stops_once_cancelled() {
+        let cancellation = CancellationToken::new();
+        let service = service().with_cancellation(cancellation.clone());
+        let input = r#"[
+            {"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"},
+            {"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}
+        ]"#;
+
+        let result = service.import_with_progress(
+            input,
+            BatchFileKind::Json,
+            Format::French,
+            false,
+            &|done, _| {
+                if done == 1 {
+                    cancellation.cancel();
+                }
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(AddressServiceError::PersistenceError(
+                AddressRepositoryError::Cancelled
+            ))
+        ));
+        // The first record stays saved
+        assert_eq!(service.repository.fetch_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn import_upsert_duplicates() -> Result<(), AddressServiceError> {
+        let service = service();
+        let input = r#"[
+            {"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"},
+            {"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}
+        ]"#;
+
+        let report = service.import(input, BatchFileKind::Json, Format::French, false)?;
+        assert_eq!(report.saved.len(), 1);
+        assert_eq!(report.failures.len(), 1);
+
+        let report = service.import(input, BatchFileKind::Json, Format::French, true)?;
+        assert!(report.saved.is_empty());
+        assert_eq!(report.updated.len(), 2);
+        assert!(report.failures.is_empty());
+        assert_eq!(service.repository.fetch_all()?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_iso_csv() -> Result<(), AddressServiceError> {
+        let service = service();
+        let input = "\
+business_name,street_name,building_number,department,postbox,postcode,town_name,country
+Société DUPONT,RUE EMILE ZOLA,56,Mademoiselle Lucie MARTIN,BP 90432,34092,MONTPELLIER CEDEX 5,FR
+";
+
+        let report = service.import(input, BatchFileKind::Csv, Format::Iso20022, false)?;
+        assert_eq!(report.total(), 1);
+        assert!(report.failures.is_empty(), "report was {report:#?}");
+
+        let saved = service.fetch(report.saved[0])?;
+        assert_eq!(saved.postal_details.postcode, "34092");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn import_iso_xlsx() -> Result<(), AddressServiceError> {
+        use rust_xlsxwriter::Workbook;
+
+        use super::XlsxOptions;
+
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("Notes").unwrap();
+        let sheet = workbook.add_worksheet().set_name("Clients").unwrap();
+        let headers = [
+            "name",
+            "building_number",
+            "street_name",
+            "Code postal",
+            "town_name",
+            "country",
+        ];
+        for (column, header) in (0..).zip(headers) {
+            sheet.write_string(0, column, header).unwrap();
+        }
+        sheet.write_string(1, 0, "Monsieur Jean DELHOURME").unwrap();
+        sheet.write_number(1, 1, 25).unwrap();
+        sheet.write_string(1, 2, "RUE DE L'EGLISE").unwrap();
+        sheet.write_number(1, 3, 33380).unwrap();
+        sheet.write_string(1, 4, "MIOS").unwrap();
+        sheet.write_string(1, 5, "FR").unwrap();
+        sheet.write_string(2, 0, "Madame Isabelle RICHARD").unwrap();
+        let workbook = workbook.save_to_buffer().unwrap();
+
+        let service = service();
+        let options = XlsxOptions {
+            sheet: Some("Clients".to_string()),
+            columns: [("Code postal".to_string(), "postcode".to_string())].into(),
+        };
+        let report = service.import_xlsx(&workbook, &options, Format::Iso20022, false)?;
+        assert_eq!(report.total(), 2);
+        assert_eq!(report.saved.len(), 1, "report was {report:#?}");
+        assert_eq!(report.failures[0].record, 1);
+
+        let saved = service.fetch(report.saved[0])?;
+        assert_eq!(saved.postal_details.postcode, "33380");
+        assert_eq!(saved.street.unwrap().number.as_deref(), Some("25"));
+
+        let options = XlsxOptions {
+            sheet: Some("Adresses".to_string()),
+            ..XlsxOptions::default()
+        };
+        let result = service.import_xlsx(&workbook, &options, Format::Iso20022, false);
+        assert!(
+            matches!(result, Err(AddressServiceError::InvalidXlsx(_))),
+            "result was {result:#?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_malformed_json() {
+        let service = service();
+        let result = service.import("{ not json", BatchFileKind::Json, Format::French, false);
+        assert!(
+            matches!(result, Err(AddressServiceError::InvalidJson(_))),
+            "result was {result:#?}"
+        );
+    }
+
+    #[test]
+    fn analyze_import_without_writing() -> Result<(), AddressServiceError> {
+        let service = service();
+        let stored = service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let input = r#"[
+            {"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"},
+            {"name": "M. Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"},
+            {"name": "Mme Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"},
+            {"name": "Madame Lucie MARTIN", "postal": "AUTERIVE", "country": "FRANCE"}
+        ]"#;
+
+        let analysis = service.analyze_import(input, BatchFileKind::Json, Format::French)?;
+        assert_eq!(analysis.total(), 4);
+        assert_eq!(analysis.new, vec![0]);
+        assert_eq!(
+            analysis.stored_duplicates,
+            vec![StoredDuplicate {
+                record: 1,
+                existing: stored
+            }]
+        );
+        assert_eq!(
+            analysis.file_duplicates,
+            vec![FileDuplicate {
+                record: 2,
+                first_record: 0
+            }]
+        );
+        assert_eq!(analysis.invalid.len(), 1);
+        assert_eq!(analysis.invalid[0].record, 3);
+
+        assert_eq!(service.repository.fetch_all()?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reformat_without_saving() -> Result<(), AddressServiceError> {
+        let service = service();
+        let input = "\
+name,street,postal,country
+Monsieur Jean DELHOURME,25 RUE DE L'EGLISE,33380 MIOS,FRANCE
+Madame Isabelle RICHARD,LE VILLAGE,AUTERIVE,FRANCE
+";
+
+        let results =
+            service.reformat(input, BatchFileKind::Csv, Format::French, Format::Iso20022)?;
+        assert_eq!(results.len(), 2);
+        let Ok(Either::Iso20022(iso)) = &results[0] else {
+            panic!("result was {:?}", results[0]);
+        };
+        assert_eq!(
+            iso.to_json_value(IsoJsonKeys::Snake)?["postal_address"]["postcode"],
+            "33380"
+        );
+        assert!(matches!(
+            results[1],
+            Err(AddressServiceError::ConversionError(_))
+        ));
+        assert!(service.repository.fetch_all()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_iso_without_saving() -> Result<(), AddressServiceError> {
+        let service = service();
+        let input = r#"
+{"Nm": "Monsieur Jean DELHOURME", "PstlAdr": {"AdrLine": ["25 RUE DE L'EGLISE", "33380 MIOS"], "Ctry": "FR"}}
+{"Nm": "Madame Isabelle RICHARD", "PstlAdr": {"AdrLine": ["LE VILLAGE"]}}
+{"Nm": "Madame Lucie MARTIN"}
+"#;
+
+        let results = service.migrate_iso_batch(input, &UnstructuredToStructured::new())?;
+        assert_eq!(results.len(), 3);
+        let Ok(migration) = &results[0] else {
+            panic!("result was {:?}", results[0]);
+        };
+        assert_eq!(
+            migration.address.to_json_value(IsoJsonKeys::Snake)?["postal_address"]["street_name"],
+            "RUE DE L'EGLISE"
+        );
+        assert!(!migration.report.needs_review);
+        assert!(matches!(
+            results[1],
+            Err(AddressServiceError::ConversionError(_))
+        ));
+        assert!(matches!(
+            &results[2],
+            Err(AddressServiceError::InvalidJson(error)) if error.record() == Some(2)
+        ));
+        assert!(service.repository.fetch_all()?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,444 @@
+use std::collections::{BTreeMap, HashMap};
+
+use strum::EnumString;
+use uuid::Uuid;
+
+use super::service::{AddressService, ServiceResult};
+use crate::domain::repositories::AddressRepository;
+use crate::domain::{Address, AddressStatus, ConvertedAddress, Fingerprint, Routing};
+
+/// Which address of a group of duplicates is kept by
+/// [`AddressService::dedupe`], the others being deleted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, strum_macros::Display, EnumString)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum DedupeStrategy {
+    /// The most recently updated address.
+    #[default]
+    KeepNewest,
+    /// The first stored address, e.g., when its ID is referenced elsewhere.
+    KeepOldest,
+    /// The most recently updated address, its empty fields, notes and
+    /// metadata filled by the other duplicates, the most recent first.
+    Merge,
+}
+
+/// Stored addresses similar to each other.
+#[derive(Debug, PartialEq)]
+pub struct DuplicateGroup {
+    /// The fingerprint of the kept address.
+    pub fingerprint: Fingerprint,
+    /// The address kept by the strategy.
+    pub kept: Uuid,
+    /// The other addresses, deleted when the deduplication is applied.
+    pub removed: Vec<Uuid>,
+}
+
+/// What [`AddressService::dedupe`] found, and did when applied.
+#[derive(Debug, Default, PartialEq)]
+pub struct DedupeReport {
+    /// The groups of duplicates, the largest first.
+    pub groups: Vec<DuplicateGroup>,
+    /// Whether the groups were resolved, or only reported.
+    pub applied: bool,
+}
+
+impl DedupeReport {
+    /// The addresses deleted, or to delete.
+    pub fn removed(&self) -> usize {
+        self.groups.iter().map(|group| group.removed.len()).sum()
+    }
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Groups the stored addresses [similar](Address::is_similar_to) to each
+    /// other, e.g., left by a large import, by typos or by a change of the
+    /// matching rules, and picks the address kept in each group according to
+    /// `strategy`. Nothing is written unless `apply` is set: the kept
+    /// addresses are then updated (merged) first, and the others deleted. Only the active addresses are
+    /// compared, the archived and invalid ones being kept for history.
+    pub fn dedupe(&self, strategy: DedupeStrategy, apply: bool) -> ServiceResult<DedupeReport> {
+        self.dedupe_with_progress(strategy, apply, &|_, _| {})
+    }
+
+    /// Same as [`AddressService::dedupe`], calling `progress` with the number
+    /// of groups resolved and the total before the first group and after each
+    /// one, e.g., to draw a progress bar.
+    pub fn dedupe_with_progress(
+        &self,
+        strategy: DedupeStrategy,
+        apply: bool,
+        progress: &dyn Fn(usize, usize),
+    ) -> ServiceResult<DedupeReport> {
+        let active: Vec<Address> = self
+            .repository
+            .fetch_all()?
+            .into_iter()
+            .filter(|address| *address.status() == AddressStatus::Active)
+            .collect();
+
+        let mut report = DedupeReport {
+            applied: apply,
+            ..Default::default()
+        };
+        let mut groups: Vec<(Fingerprint, Vec<Address>)> = similar_groups(active)
+            .into_iter()
+            .map(|mut addresses| {
+                // The most recent first, the ID breaking the ties.
+                addresses.sort_by(|a, b| {
+                    b.updated_at()
+                        .cmp(&a.updated_at())
+                        .then_with(|| a.id().cmp(&b.id()))
+                });
+                if strategy == DedupeStrategy::KeepOldest {
+                    addresses.reverse();
+                }
+
+                (addresses[0].fingerprint(), addresses)
+            })
+            .collect();
+        groups.sort_by(|(a_fingerprint, a), (b_fingerprint, b)| {
+            b.len()
+                .cmp(&a.len())
+                .then_with(|| a_fingerprint.to_string().cmp(&b_fingerprint.to_string()))
+        });
+
+        let total = groups.len();
+        progress(0, total);
+
+        for (done, (fingerprint, addresses)) in groups.into_iter().enumerate() {
+            self.check_cancelled()?;
+            let mut addresses = addresses.into_iter();
+            let Some(mut kept) = addresses.next() else {
+                continue;
+            };
+            let others: Vec<Address> = addresses.collect();
+
+            if apply {
+                if strategy == DedupeStrategy::Merge {
+                    // Merged in memory and written once, as a single version.
+                    let merged = merge(&kept, &others);
+                    let routing = merge_routing(&kept, &others);
+                    let (notes, metadata) = merge_annotations(&kept, &others);
+                    if merged != kept.as_converted_address()
+                        || routing != *kept.routing()
+                        || notes.as_deref() != kept.notes()
+                        || metadata != *kept.metadata()
+                    {
+                        kept.update_all(merged, routing, notes, metadata);
+                        self.repository.update(kept.clone())?;
+                    }
+                }
+                for other in &others {
+                    self.repository.delete(&other.id().to_string())?;
+                }
+            }
+
+            report.groups.push(DuplicateGroup {
+                fingerprint,
+                kept: kept.id(),
+                removed: others.iter().map(Address::id).collect(),
+            });
+            progress(done + 1, total);
+        }
+
+        Ok(report)
+    }
+}
+
+/// The addresses similar to each other, see [`Address::is_similar_to`], in
+/// groups of two or more. The similarity is transitive: an address similar to
+/// any address of a group joins it.
+fn similar_groups(addresses: Vec<Address>) -> Vec<Vec<Address>> {
+    let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, address) in addresses.iter().enumerate() {
+        blocks
+            .entry(address.similarity_block())
+            .or_default()
+            .push(i);
+    }
+
+    // Union-find of the indexes of the addresses, `root` finding the
+    // representative of a group.
+    let mut parents: Vec<usize> = (0..addresses.len()).collect();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    for block in blocks.values() {
+        for (k, &i) in block.iter().enumerate() {
+            for &j in &block[k + 1..] {
+                if addresses[i].is_similar_to(&addresses[j]) {
+                    let (i, j) = (root(&mut parents, i), root(&mut parents, j));
+                    parents[i] = j;
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<Address>> = HashMap::new();
+    for (i, address) in addresses.into_iter().enumerate() {
+        groups
+            .entry(root(&mut parents, i))
+            .or_default()
+            .push(address);
+    }
+
+    groups
+        .into_values()
+        .filter(|addresses| addresses.len() > 1)
+        .collect()
+}
+
+/// The kept address with its empty optional fields taken from the first of
+/// `others` having them. The fields compared to find the duplicates are the
+/// kept ones.
+fn merge(kept: &Address, others: &[Address]) -> ConvertedAddress {
+    let mut merged = kept.as_converted_address();
+
+    for other in others {
+        match (&mut merged.delivery_point, &other.delivery_point) {
+            (None, Some(delivery_point)) => merged.delivery_point = Some(delivery_point.clone()),
+            (Some(merged), Some(other)) => {
+                fill(&mut merged.care_of, &other.care_of);
+                fill(&mut merged.external, &other.external);
+                fill(&mut merged.internal, &other.internal);
+                fill(&mut merged.postbox, &other.postbox);
+            }
+            _ => {}
+        }
+        let postal_details = &mut merged.postal_details;
+        fill(
+            &mut postal_details.town_location,
+            &other.postal_details.town_location,
+        );
+        fill(&mut postal_details.locality, &other.postal_details.locality);
+        fill(
+            &mut postal_details.country_subdivision,
+            &other.postal_details.country_subdivision,
+        );
+
+        for (key, value) in &other.extras {
+            merged
+                .extras
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    merged
+}
+
+/// The routing of the kept address, completed by the others.
+fn merge_routing(kept: &Address, others: &[Address]) -> Routing {
+    let mut routing = kept.routing().clone();
+    for other in others {
+        fill(&mut routing.sort_code, &other.routing().sort_code);
+        fill(&mut routing.batch_id, &other.routing().batch_id);
+    }
+
+    routing
+}
+
+/// The notes and metadata of the kept address, completed by the others.
+fn merge_annotations(
+    kept: &Address,
+    others: &[Address],
+) -> (Option<String>, BTreeMap<String, String>) {
+    let mut notes = kept.notes().map(str::to_string);
+    let mut metadata = kept.metadata().clone();
+    for other in others {
+        fill(&mut notes, &other.notes().map(str::to_string));
+        for (key, value) in other.metadata() {
+            metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    (notes, metadata)
+}
+
+fn fill(field: &mut Option<String>, other: &Option<String>) {
+    if field.is_none() {
+        field.clone_from(other);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use super::{DedupeStrategy, DuplicateGroup};
+    use crate::application::service::{AddressService, Format};
+    use crate::domain::Routing;
+    use crate::infrastructure::{CancellationToken, InMemoryAddressRepository};
+
+    const ADDRESS: &str = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    /// Three stored duplicates, the most recent last, made by updates since
+    /// the saves refuse them.
+    fn service() -> (AddressService<InMemoryAddressRepository>, Vec<uuid::Uuid>) {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let others = [
+            r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "3 RUE DES LILAS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "LD LES GRANDS CHAMPS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        ];
+
+        let first = service.save(ADDRESS, Format::French).unwrap();
+        let mut ids = vec![first];
+        for other in others {
+            let id = service.save(other, Format::French).unwrap();
+            ids.push(id);
+        }
+        service
+            .set_routing(
+                ids[0],
+                Routing {
+                    sort_code: Some("33A12".to_string()),
+                    batch_id: None,
+                },
+            )
+            .unwrap();
+        service
+            .annotate(
+                ids[0],
+                Some("Ring twice".to_string()),
+                BTreeMap::from([("crm".to_string(), "C-1042".to_string())]),
+            )
+            .unwrap();
+        service
+            .update(
+                ids[1],
+                r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+                Format::French,
+            )
+            .unwrap();
+        service.update(ids[2], ADDRESS, Format::French).unwrap();
+
+        (service, ids)
+    }
+
+    #[test]
+    fn dry_run_reports_without_writing() {
+        let (service, ids) = service();
+        let report = service.dedupe(DedupeStrategy::KeepNewest, false).unwrap();
+
+        assert!(!report.applied);
+        assert_eq!(
+            report.groups,
+            vec![DuplicateGroup {
+                fingerprint: service.fetch(ids[0]).unwrap().fingerprint(),
+                kept: ids[2],
+                removed: vec![ids[1], ids[0]],
+            }]
+        );
+        assert_eq!(service.ids().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn progress_counts_the_groups() {
+        let (service, _) = service();
+        service
+            .save(
+                r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+                Format::French,
+            )
+            .unwrap();
+
+        let calls = RefCell::new(Vec::new());
+        service
+            .dedupe_with_progress(DedupeStrategy::KeepNewest, true, &|done, total| {
+                calls.borrow_mut().push((done, total))
+            })
+            .unwrap();
+        // The address without duplicates isn't a group.
+        assert_eq!(calls.take(), [(0, 1), (1, 1)]);
+
+        service
+            .dedupe_with_progress(DedupeStrategy::KeepNewest, true, &|done, total| {
+                calls.borrow_mut().push((done, total))
+            })
+            .unwrap();
+        assert_eq!(calls.take(), [(0, 0)]);
+    }
+
+    #[test]
+    fn stops_once_cancelled() {
+        let (service, _) = service();
+        let cancellation = CancellationToken::new();
+        let service = service.with_cancellation(cancellation.clone());
+        cancellation.cancel();
+
+        let error = service
+            .dedupe(DedupeStrategy::KeepNewest, true)
+            .unwrap_err();
+        assert_eq!(error.details().code, "E_CANCELLED");
+        assert_eq!(service.ids().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn keep_oldest_deletes_the_others() {
+        let (service, ids) = service();
+        let report = service.dedupe(DedupeStrategy::KeepOldest, true).unwrap();
+
+        assert_eq!(report.removed(), 2);
+        assert_eq!(service.ids().unwrap(), vec![ids[0]]);
+        assert!(service
+            .dedupe(DedupeStrategy::KeepOldest, true)
+            .unwrap()
+            .groups
+            .is_empty());
+    }
+
+    #[test]
+    fn near_duplicates_are_grouped() {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let first = service.save(ADDRESS, Format::French).unwrap();
+        let typo = service
+            .save(
+                r#"{"name": "M. Jean DEL HOURME", "street": "25 R. DE L EGLIZE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+                Format::French,
+            )
+            .unwrap();
+        service
+            .save(
+                r#"{"name": "Madame Isabelle RICHARD", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+                Format::French,
+            )
+            .unwrap();
+
+        let report = service.dedupe(DedupeStrategy::KeepOldest, false).unwrap();
+        assert_eq!(
+            report.groups,
+            vec![DuplicateGroup {
+                fingerprint: service.fetch(first).unwrap().fingerprint(),
+                kept: first,
+                removed: vec![typo],
+            }]
+        );
+    }
+
+    #[test]
+    fn merge_fills_the_kept_address() {
+        let (service, ids) = service();
+        let version = service.fetch(ids[2]).unwrap().version();
+        service.dedupe(DedupeStrategy::Merge, true).unwrap();
+
+        assert_eq!(service.ids().unwrap(), vec![ids[2]]);
+        let kept = service.fetch(ids[2]).unwrap();
+        assert_eq!(
+            kept.delivery_point.as_ref().unwrap().internal.as_deref(),
+            Some("Appartement 2")
+        );
+        assert_eq!(kept.routing().sort_code.as_deref(), Some("33A12"));
+        assert_eq!(kept.notes(), Some("Ring twice"));
+        assert_eq!(
+            kept.metadata().get("crm").map(String::as_str),
+            Some("C-1042")
+        );
+        // Written once
+        assert_eq!(kept.version(), version + 1);
+    }
+}
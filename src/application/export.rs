@@ -0,0 +1,425 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use strum::EnumString;
+
+use super::service::{AddressService, Either, Format, ServiceResult};
+use crate::domain::repositories::{AddressFilter, AddressRepository};
+use crate::domain::*;
+
+/// A stored address converted to the export format.
+#[derive(Debug, PartialEq)]
+pub struct ExportedAddress {
+    pub id: Uuid,
+    pub updated_at: DateTime<Utc>,
+    pub fingerprint: Fingerprint,
+    /// The name of the recipient split into its parts, when it is a person.
+    pub person_name: Option<PersonName>,
+    /// The routing metadata of bulk mailers, if any.
+    pub routing: Option<Routing>,
+    /// The free-form notes about the address, if any.
+    pub notes: Option<String>,
+    /// The free-form metadata of the address, empty if none.
+    pub metadata: BTreeMap<String, String>,
+    pub address: Either<FrenchAddress, IsoAddress>,
+}
+
+/// A column of the CSV export, named in snake case (e.g., `updated_at`).
+#[derive(Clone, Copy, Debug, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum ExportColumn {
+    Id,
+    UpdatedAt,
+    Fingerprint,
+    /// The name of the individual, or the business name.
+    Name,
+    /// The contact person of a business, empty for individuals.
+    Contact,
+    /// The service of a business, empty for individuals.
+    Service,
+    /// The delivery point lines, separated by commas.
+    Delivery,
+    /// The street number and name (e.g., `25 RUE DE L'EGLISE`).
+    Street,
+    Postcode,
+    Town,
+    /// The country as written on french labels (e.g., `ALLEMAGNE`).
+    Country,
+    /// The two-letter ISO code of the country.
+    CountryCode,
+    SortCode,
+    BatchId,
+    Notes,
+    /// The metadata as `key=value` pairs, separated by semicolons.
+    Metadata,
+}
+
+impl ExportColumn {
+    /// The columns exported when none is chosen, in this order.
+    pub const DEFAULT: [ExportColumn; 6] = [
+        ExportColumn::Id,
+        ExportColumn::Name,
+        ExportColumn::Street,
+        ExportColumn::Postcode,
+        ExportColumn::Town,
+        ExportColumn::Country,
+    ];
+
+    fn value(self, address: &Address) -> String {
+        match self {
+            ExportColumn::Id => address.id().to_string(),
+            ExportColumn::UpdatedAt => address.updated_at().to_rfc3339(),
+            ExportColumn::Fingerprint => address.fingerprint().to_string(),
+            ExportColumn::Name => match &address.recipient {
+                Recipient::Individual { name } => name.clone(),
+                Recipient::Business { company_name, .. } => company_name.clone(),
+            },
+            ExportColumn::Contact => match &address.recipient {
+                Recipient::Individual { .. } => String::new(),
+                Recipient::Business { contact_person, .. } => {
+                    contact_person.clone().unwrap_or_default()
+                }
+            },
+            ExportColumn::Service => match &address.recipient {
+                Recipient::Individual { .. } => String::new(),
+                Recipient::Business { service, .. } => service.clone().unwrap_or_default(),
+            },
+            ExportColumn::Delivery => address
+                .delivery_point
+                .iter()
+                .flat_map(|point| {
+                    [
+                        &point.care_of,
+                        &point.internal,
+                        &point.external,
+                        &point.postbox,
+                    ]
+                })
+                .flatten()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", "),
+            ExportColumn::Street => match &address.street {
+                Some(street) => match street.full_number(NumberExtStyle::Spaced) {
+                    Some(number) => format!("{number} {}", street.name),
+                    None => street.name.clone(),
+                },
+                None => String::new(),
+            },
+            ExportColumn::Postcode => address.postal_details.postcode.clone(),
+            ExportColumn::Town => address.postal_details.town.clone(),
+            ExportColumn::Country => address.country.to_string(),
+            ExportColumn::CountryCode => address.country.iso_code().to_string(),
+            ExportColumn::SortCode => address.routing().sort_code.clone().unwrap_or_default(),
+            ExportColumn::BatchId => address.routing().batch_id.clone().unwrap_or_default(),
+            ExportColumn::Notes => address.notes().unwrap_or_default().to_string(),
+            ExportColumn::Metadata => address
+                .metadata()
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Converts the stored addresses to `format`, the oldest update first.
+    /// With `since`, only the addresses created or updated at or after this
+    /// date are exported, so that downstream syncs only pull the changes.
+    pub fn export(
+        &self,
+        since: Option<DateTime<Utc>>,
+        format: Format,
+    ) -> ServiceResult<Vec<ExportedAddress>> {
+        self.export_with(since, &AddressFilter::default(), format)
+    }
+
+    /// Same as [`AddressService::export`], only exporting the addresses
+    /// matching `filter`.
+    pub fn export_with(
+        &self,
+        since: Option<DateTime<Utc>>,
+        filter: &AddressFilter,
+        format: Format,
+    ) -> ServiceResult<Vec<ExportedAddress>> {
+        self.export_with_progress(since, filter, format, &|_, _| {})
+    }
+
+    /// Same as [`AddressService::export_with`], calling `progress` with the
+    /// number of addresses converted and the total before the first address
+    /// and after each one, e.g., to draw a progress bar.
+    pub fn export_with_progress(
+        &self,
+        since: Option<DateTime<Utc>>,
+        filter: &AddressFilter,
+        format: Format,
+        progress: &dyn Fn(usize, usize),
+    ) -> ServiceResult<Vec<ExportedAddress>> {
+        let addresses = self.exported_addresses(since, filter)?;
+        let total = addresses.len();
+        progress(0, total);
+
+        addresses
+            .into_iter()
+            .enumerate()
+            .map(|(done, address)| {
+                let exported = ExportedAddress {
+                    id: address.id(),
+                    updated_at: address.updated_at(),
+                    fingerprint: address.fingerprint(),
+                    person_name: address.recipient.person_name(),
+                    routing: Some(address.routing().clone()).filter(|routing| !routing.is_empty()),
+                    notes: address.notes().map(str::to_string),
+                    metadata: address.metadata().clone(),
+                    address: self.format(&address, format)?,
+                };
+                progress(done + 1, total);
+
+                Ok(exported)
+            })
+            .collect()
+    }
+
+    /// Writes the addresses [`AddressService::export_with`] would export as
+    /// CSV, with a header and `columns` in this order, so that mail-merge and
+    /// label printing tools get a fixed layout.
+    pub fn export_csv(
+        &self,
+        since: Option<DateTime<Utc>>,
+        filter: &AddressFilter,
+        columns: &[ExportColumn],
+    ) -> ServiceResult<String> {
+        self.export_csv_with_progress(since, filter, columns, &|_, _| {})
+    }
+
+    /// Same as [`AddressService::export_csv`], calling `progress` like
+    /// [`AddressService::export_with_progress`] does.
+    pub fn export_csv_with_progress(
+        &self,
+        since: Option<DateTime<Utc>>,
+        filter: &AddressFilter,
+        columns: &[ExportColumn],
+        progress: &dyn Fn(usize, usize),
+    ) -> ServiceResult<String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        let addresses = self.exported_addresses(since, filter)?;
+        progress(0, addresses.len());
+
+        writer.write_record(columns.iter().map(ExportColumn::to_string))?;
+        for (done, address) in addresses.iter().enumerate() {
+            writer.write_record(columns.iter().map(|column| column.value(address)))?;
+            progress(done + 1, addresses.len());
+        }
+        let csv = writer
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()))?;
+
+        Ok(String::from_utf8_lossy(&csv).to_string())
+    }
+
+    /// The addresses to export, the oldest update first, and by ID for the
+    /// ones updated at the same time, so that two exports list them in the
+    /// same order.
+    fn exported_addresses(
+        &self,
+        since: Option<DateTime<Utc>>,
+        filter: &AddressFilter,
+    ) -> ServiceResult<Vec<Address>> {
+        let mut addresses = match since {
+            Some(since) if filter.is_empty() => self.repository.fetch_updated_since(since)?,
+            Some(since) => {
+                let mut addresses = self.repository.fetch_filtered(filter)?;
+                addresses.retain(|address| address.updated_at() >= since);
+                addresses
+            }
+            None => self.repository.fetch_filtered(filter)?,
+        };
+        addresses.sort_by_key(|address| (address.updated_at(), address.id()));
+
+        Ok(addresses)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use chrono::Utc;
+
+    use super::ExportColumn;
+    use crate::application::service::{AddressService, Format, ServiceResult};
+    use crate::domain::repositories::AddressFilter;
+    use crate::domain::Routing;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    #[test]
+    fn export_changed_since() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let old = service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let since = Utc::now();
+        let recent = service.save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+
+        let all = service.export(None, Format::Iso20022)?;
+        assert_eq!(
+            all.iter().map(|exported| exported.id).collect::<Vec<_>>(),
+            vec![old, recent]
+        );
+
+        let changed = service.export(Some(since), Format::French)?;
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].id, recent);
+        assert!(changed[0].updated_at >= since);
+        assert_eq!(
+            changed[0]
+                .person_name
+                .as_ref()
+                .map(|name| &name.surname[..]),
+            Some("RICHARD")
+        );
+
+        // An update brings the address back in the export
+        service.update(
+            old,
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "10 AVENUE DES CHAMPS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        assert_eq!(service.export(Some(since), Format::French)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_progress() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        service.save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let filter = AddressFilter::default();
+
+        let calls = RefCell::new(Vec::new());
+        let exported =
+            service.export_with_progress(None, &filter, Format::Iso20022, &|done, total| {
+                calls.borrow_mut().push((done, total))
+            })?;
+        assert_eq!(exported.len(), 2);
+        assert_eq!(calls.take(), [(0, 2), (1, 2), (2, 2)]);
+
+        service.export_csv_with_progress(
+            None,
+            &filter,
+            &ExportColumn::DEFAULT,
+            &|done, total| calls.borrow_mut().push((done, total)),
+        )?;
+        assert_eq!(calls.take(), [(0, 2), (1, 2), (2, 2)]);
+
+        // Only the filtered addresses count.
+        service.export_with_progress(
+            Some(Utc::now()),
+            &filter,
+            Format::French,
+            &|done, total| calls.borrow_mut().push((done, total)),
+        )?;
+        assert_eq!(calls.take(), [(0, 0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_routing() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let input = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+        let id = service.save(input, Format::French)?;
+        let fingerprint = service.fetch(id)?.fingerprint();
+        assert_eq!(service.export(None, Format::French)?[0].routing, None);
+
+        let routing = Routing {
+            sort_code: Some("33380A12".to_string()),
+            batch_id: Some("2024-06-MAILING".to_string()),
+        };
+        service.set_routing(id, routing.clone())?;
+        service.update(id, &input.replace("25 RUE", "10 RUE"), Format::French)?;
+
+        // The routing survives the update, and isn't part of the fingerprint.
+        let exported = service.export(None, Format::French)?;
+        assert_eq!(exported[0].routing.as_ref(), Some(&routing));
+        service.update(id, input, Format::French)?;
+        assert_eq!(service.fetch(id)?.fingerprint(), fingerprint);
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_annotations() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let input = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+        let id = service.save(input, Format::French)?;
+        let fingerprint = service.fetch(id)?.fingerprint();
+
+        let metadata = BTreeMap::from([
+            ("crm".to_string(), "C-1042".to_string()),
+            ("floor".to_string(), "2".to_string()),
+        ]);
+        service.annotate(id, Some("Ring twice".to_string()), metadata.clone())?;
+        service.update(id, &input.replace("25 RUE", "10 RUE"), Format::French)?;
+
+        // The annotations survive the update, and aren't part of the fingerprint.
+        let exported = service.export(None, Format::Iso20022)?;
+        assert_eq!(exported[0].notes.as_deref(), Some("Ring twice"));
+        assert_eq!(exported[0].metadata, metadata);
+        service.update(id, input, Format::French)?;
+        assert_eq!(service.fetch(id)?.fingerprint(), fingerprint);
+
+        let columns = [ExportColumn::Notes, ExportColumn::Metadata];
+        let csv = service.export_csv(None, &AddressFilter::default(), &columns)?;
+        assert_eq!(csv, "notes,metadata\nRing twice,crm=C-1042; floor=2\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_csv_columns() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let id = service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let filter = AddressFilter::default();
+
+        let csv = service.export_csv(None, &filter, &ExportColumn::DEFAULT)?;
+        assert_eq!(
+            csv,
+            format!(
+                "id,name,street,postcode,town,country\n\
+                 {id},Monsieur Jean DELHOURME,25 RUE DE L'EGLISE,33380,MIOS,FRANCE\n"
+            )
+        );
+
+        let columns = [
+            ExportColumn::Town,
+            ExportColumn::Delivery,
+            ExportColumn::CountryCode,
+            ExportColumn::SortCode,
+        ];
+        let csv = service.export_csv(None, &filter, &columns)?;
+        assert_eq!(
+            csv,
+            "town,delivery,country_code,sort_code\nMIOS,Appartement 2,FR,\n"
+        );
+        assert_eq!("updated_at".parse(), Ok(ExportColumn::UpdatedAt));
+
+        Ok(())
+    }
+}
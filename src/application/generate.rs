@@ -0,0 +1,286 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::service::{AddressService, Either, Format, ServiceResult};
+use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+use crate::domain::*;
+
+/// Civilities and their first names.
+const FIRST_NAMES: &[(&str, &[&str])] = &[
+    (
+        "Monsieur",
+        &[
+            "Jean", "Pierre", "Louis", "Hugo", "Paul", "Jacques", "Nicolas", "Antoine",
+        ],
+    ),
+    (
+        "Madame",
+        &[
+            "Marie", "Camille", "Léa", "Chloé", "Manon", "Inès", "Sarah", "Julie",
+        ],
+    ),
+];
+const LAST_NAMES: &[&str] = &[
+    "MARTIN", "BERNARD", "DUBOIS", "THOMAS", "ROBERT", "RICHARD", "PETIT", "DURAND", "LEROY",
+    "MOREAU", "SIMON", "LAURENT", "LEFEBVRE", "MICHEL", "GARCIA", "DAVID",
+];
+const STREET_TYPES: &[&str] = &[
+    "RUE",
+    "AVENUE",
+    "BOULEVARD",
+    "IMPASSE",
+    "CHEMIN",
+    "ALLEE",
+    "PLACE",
+    "QUAI",
+];
+const STREET_NAMES: &[&str] = &[
+    "DE LA REPUBLIQUE",
+    "VICTOR HUGO",
+    "JEAN JAURES",
+    "DES LILAS",
+    "DU GENERAL DE GAULLE",
+    "PASTEUR",
+    "DE LA GARE",
+    "DES ECOLES",
+    "DU MOULIN",
+    "EMILE ZOLA",
+    "DES TILLEULS",
+    "DE L'EGLISE",
+];
+/// Towns and one of their postcodes.
+const TOWNS: &[(&str, &str)] = &[
+    ("33380", "MIOS"),
+    ("44000", "NANTES"),
+    ("69001", "LYON"),
+    ("34000", "MONTPELLIER"),
+    ("67000", "STRASBOURG"),
+    ("13001", "MARSEILLE"),
+    ("31000", "TOULOUSE"),
+    ("59000", "LILLE"),
+    ("35000", "RENNES"),
+    ("21000", "DIJON"),
+    ("29200", "BREST"),
+    ("63000", "CLERMONT-FERRAND"),
+];
+const TRADES: &[&str] = &[
+    "Boulangerie",
+    "Transports",
+    "Garage",
+    "Cabinet",
+    "Atelier",
+    "Menuiserie",
+    "Librairie",
+    "Pharmacie",
+];
+const LEGAL_FORMS: &[&str] = &["SARL", "SAS", "EURL", "SA"];
+const SERVICES: &[&str] = &["Service achats", "Comptabilité", "Direction", "Accueil"];
+const DELIVERY_POINTS: &[&str] = &[
+    "Appartement 12",
+    "Bâtiment B",
+    "Résidence Les Pins",
+    "Entrée A",
+    "Escalier 3",
+];
+
+/// Generates synthetic french addresses, for load tests and demos. The names,
+/// streets and towns come from a small embedded corpus; the identifiers have
+/// a valid checksum. The same seed generates the same addresses.
+pub struct AddressGenerator {
+    /// The xorshift state, never zero.
+    state: u64,
+}
+
+impl AddressGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    /// A generator seeded from the current time.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |duration| duration.as_nanos() as u64);
+
+        Self::new(nanos)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn below(&mut self, max: u64) -> u64 {
+        self.next() % max
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+
+    /// One chance out of `n`.
+    fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+
+    /// A SIREN with a valid Luhn checksum.
+    fn siren(&mut self) -> String {
+        let prefix = format!("{:08}", self.below(100_000_000));
+
+        (0..10)
+            .map(|check| format!("{prefix}{check}"))
+            .find(|siren| OrgIdentifier::parse_scheme("SIREN", siren).is_ok())
+            .expect("one check digit satisfies the Luhn algorithm")
+    }
+
+    fn street(&mut self) -> String {
+        format!(
+            "{} {} {}",
+            1 + self.below(120),
+            self.pick(STREET_TYPES),
+            self.pick(STREET_NAMES)
+        )
+    }
+
+    fn postal(&mut self) -> String {
+        let (postcode, town) = TOWNS[self.below(TOWNS.len() as u64) as usize];
+
+        format!("{postcode} {town}")
+    }
+
+    /// A french address of `kind`, either kind for [`KindHint::Auto`].
+    pub fn french(&mut self, kind: KindHint) -> FrenchAddress {
+        let business = match kind {
+            KindHint::Individual => false,
+            KindHint::Business => true,
+            KindHint::Auto => self.one_in(2),
+        };
+
+        if business {
+            let business_name = format!(
+                "{} {} {}",
+                self.pick(TRADES),
+                self.pick(LAST_NAMES),
+                self.pick(LEGAL_FORMS)
+            );
+
+            return FrenchAddress::Business(BusinessFrenchAddress {
+                business_name,
+                identifiers: vec![self.siren()],
+                recipient: self.one_in(2).then(|| self.pick(SERVICES).to_string()),
+                external_delivery: None,
+                street: Some(self.street()),
+                distribution_info: None,
+                postal: self.postal(),
+                country: "FRANCE".to_string(),
+                extras: Default::default(),
+            });
+        }
+
+        let (civility, first_names) = FIRST_NAMES[self.below(FIRST_NAMES.len() as u64) as usize];
+        FrenchAddress::Individual(IndividualFrenchAddress {
+            name: format!(
+                "{civility} {} {}",
+                self.pick(first_names),
+                self.pick(LAST_NAMES)
+            ),
+            internal_delivery: self
+                .one_in(3)
+                .then(|| self.pick(DELIVERY_POINTS).to_string()),
+            external_delivery: None,
+            street: Some(self.street()),
+            distribution_info: None,
+            postal: self.postal(),
+            country: "FRANCE".to_string(),
+            extras: Default::default(),
+        })
+    }
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Generates `count` addresses of `kind` in `format`, saving them when
+    /// `save` is set. A generated address duplicating a stored one is
+    /// replaced by another one.
+    pub fn generate(
+        &self,
+        generator: &mut AddressGenerator,
+        count: usize,
+        format: Format,
+        kind: KindHint,
+        save: bool,
+    ) -> ServiceResult<Vec<Either<FrenchAddress, IsoAddress>>> {
+        let mut addresses = Vec::with_capacity(count);
+        // The corpus is small, the number of attempts is bounded in case it
+        // runs out of distinct addresses.
+        let mut attempts = count.saturating_mul(10);
+
+        while addresses.len() < count && attempts > 0 {
+            attempts -= 1;
+            let converted = ConvertedAddress::from_french(generator.french(kind))?;
+
+            if save {
+                match self.repository.save(Address::new(converted.clone())) {
+                    Err(AddressRepositoryError::AlreadyExists(_)) => continue,
+                    result => result?,
+                };
+            }
+            addresses.push(match format {
+                Format::Iso20022 => Either::Iso20022(converted.to_iso20022()?),
+                _ => Either::French(converted.to_french()?),
+            });
+        }
+
+        Ok(addresses)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::AddressGenerator;
+    use crate::application::service::{AddressService, Either, Format, ServiceResult};
+    use crate::domain::*;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    fn service() -> AddressService<InMemoryAddressRepository> {
+        AddressService::new(InMemoryAddressRepository::new())
+    }
+
+    #[test]
+    fn same_seed_same_addresses() {
+        let mut first = AddressGenerator::new(42);
+        let mut second = AddressGenerator::new(42);
+
+        for _ in 0..20 {
+            assert_eq!(first.french(KindHint::Auto), second.french(KindHint::Auto));
+        }
+    }
+
+    #[test]
+    fn generate_valid_business_addresses() -> ServiceResult<()> {
+        let service = service();
+        let mut generator = AddressGenerator::new(7);
+
+        let addresses = service.generate(
+            &mut generator,
+            25,
+            Format::Iso20022,
+            KindHint::Business,
+            true,
+        )?;
+        assert_eq!(addresses.len(), 25);
+        for address in addresses {
+            let Either::Iso20022(IsoAddress::BusinessIsoAddress {
+                organisation_identification,
+                ..
+            }) = address
+            else {
+                panic!("expected a business ISO 20022 address");
+            };
+            assert_eq!(organisation_identification[0].scheme_name, "SIREN");
+        }
+        assert_eq!(service.ids()?.len(), 25);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The characters of the input shown around the position of a syntax error.
+const SNIPPET_RADIUS: usize = 20;
+
+/// A JSON input that can't be read, with where it failed: the path of the
+/// offending field (e.g., `postal_address.postcode`), the line and column of
+/// a syntax error and the input around it, and the record of a batch.
+#[derive(Debug)]
+pub struct JsonError {
+    source: serde_json::Error,
+    path: Option<String>,
+    snippet: Option<String>,
+    record: Option<usize>,
+}
+
+impl JsonError {
+    /// The path of the offending field, none for the errors of the whole
+    /// input (e.g., a syntax error before the first field).
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// The line of a syntax error, starting at 1. None for the inputs read
+    /// from an already parsed value.
+    pub fn line(&self) -> Option<usize> {
+        Some(self.source.line()).filter(|line| *line > 0)
+    }
+
+    /// The column of a syntax error, starting at 1.
+    pub fn column(&self) -> Option<usize> {
+        self.line().map(|_| self.source.column())
+    }
+
+    /// The input around a syntax error (e.g., `"postal": 33380 MIOS`).
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+
+    /// The position of the record in a batch, starting at 0.
+    pub fn record(&self) -> Option<usize> {
+        self.record
+    }
+
+    /// Tells which record of a batch failed, see [`JsonError::record`].
+    pub fn at_record(mut self, record: usize) -> Self {
+        self.record = Some(record);
+        self
+    }
+
+    /// Keeps the input around the position of the error.
+    pub(crate) fn with_snippet(mut self, input: &str) -> Self {
+        let (Some(line), Some(column)) = (self.line(), self.column()) else {
+            return self;
+        };
+        let Some(text) = input.lines().nth(line - 1) else {
+            return self;
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let end = column.min(chars.len());
+        let start = end.saturating_sub(SNIPPET_RADIUS);
+        let end = (end + SNIPPET_RADIUS).min(chars.len());
+        let snippet: String = chars[start..end].iter().collect();
+        self.snippet = Some(snippet.trim().to_string()).filter(|s| !s.is_empty());
+        self
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if let Some(path) = &self.path {
+            write!(f, " at `{path}`")?;
+        }
+        if let Some(snippet) = &self.snippet {
+            write!(f, " near `{snippet}`")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(source: serde_json::Error) -> Self {
+        Self {
+            source,
+            path: None,
+            snippet: None,
+            record: None,
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for JsonError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        // The root is written `.`, and a key unknown when reading a value `?`.
+        let path = Some(error.path().to_string()).filter(|path| path != "." && path != "?");
+
+        Self {
+            path,
+            ..Self::from(error.into_inner())
+        }
+    }
+}
+
+/// Reads `input` as `T`, like `serde_json::from_str`, keeping where it failed.
+pub(crate) fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T, JsonError> {
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let value = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|error| JsonError::from(error).with_snippet(input))?;
+    // Only whitespace may follow.
+    deserializer
+        .end()
+        .map_err(|error| JsonError::from(error).with_snippet(input))?;
+
+    Ok(value)
+}
+
+/// Reads `value` as `T`, like `serde_json::from_value`, keeping the path of
+/// the offending field.
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, JsonError> {
+    Ok(serde_path_to_error::deserialize(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{from_str, from_value};
+    use crate::domain::CanonicalAddress;
+
+    #[test]
+    fn it_should_locate_the_errors() {
+        let error = from_value::<CanonicalAddress>(json!({
+            "kind": "individual",
+            "recipient": { "name": "Monsieur Jean DELHOURME" },
+            "street": { "number": 25, "name": "RUE DE L'EGLISE" },
+            "postal_details": { "postcode": "33380", "town": "MIOS" },
+            "country": "FR"
+        }))
+        .unwrap_err();
+        assert_eq!(error.path(), Some("street.number"));
+        assert_eq!(error.line(), None);
+        assert!(error.to_string().starts_with("invalid type: integer `25`"));
+
+        let input = "{\n  \"name\": \"Monsieur Jean DELHOURME\",\n  \"postal\": 33380 MIOS\n}";
+        let error = from_str::<serde_json::Value>(input).unwrap_err();
+        assert_eq!((error.line(), error.column()), (Some(3), Some(19)));
+        assert_eq!(error.snippet(), Some("\"postal\": 33380 MIOS"));
+        assert_eq!(error.path(), None);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, HashSet};
+use std::iter;
+
+use uuid::Uuid;
+
+use super::service::{AddressService, Either, Format, ServiceResult};
+use super::stats::ListFilter;
+use crate::domain::repositories::AddressRepository;
+use crate::domain::{Address, AddressStatus, Country, DuplicateScope, MailOrigin};
+
+/// The metadata key of the addresses not to mail, e.g., set with
+/// `annotate --set opted_out=yes`. Any value but `false` opts out.
+pub const OPT_OUT_KEY: &str = "opted_out";
+
+/// The columns of the mailing list CSV: the ID of the address, then its
+/// lines, the empty ones last.
+const MAILING_COLUMNS: [&str; 8] = [
+    "id", "line_1", "line_2", "line_3", "line_4", "line_5", "line_6", "line_7",
+];
+
+/// Why an address is left out of a mailing list.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Suppression {
+    Archived,
+    /// Marked invalid, e.g., when mail was returned.
+    Invalid,
+    /// Has the [`OPT_OUT_KEY`] metadata.
+    OptedOut,
+    /// Another recipient at the same location is mailed already.
+    Household,
+}
+
+/// The addresses selected by [`AddressService::mailing_list`].
+#[derive(Debug, Default)]
+pub struct MailingList {
+    /// One address per household, the most recently updated first.
+    pub recipients: Vec<Address>,
+    /// The addresses left out, and why.
+    pub suppressed: Vec<(Uuid, Suppression)>,
+}
+
+impl MailingList {
+    /// How many addresses were left out for each reason.
+    pub fn suppressed_counts(&self) -> BTreeMap<Suppression, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, reason) in &self.suppressed {
+            *counts.entry(*reason).or_default() += 1;
+        }
+
+        counts
+    }
+}
+
+fn suppression(address: &Address) -> Option<Suppression> {
+    match address.status() {
+        AddressStatus::Archived => return Some(Suppression::Archived),
+        AddressStatus::Invalid { .. } => return Some(Suppression::Invalid),
+        AddressStatus::Active => {}
+    }
+
+    address
+        .metadata()
+        .get(OPT_OUT_KEY)
+        .filter(|value| !value.trim().eq_ignore_ascii_case("false"))
+        .map(|_| Suppression::OptedOut)
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Selects the addresses of `filter` to mail: the archived, invalid and
+    /// opted out addresses are suppressed, even when the filter includes the
+    /// archived ones, and a single recipient is kept per household, i.e., per
+    /// location fingerprint (see [`DuplicateScope::Location`]), the most
+    /// recently updated.
+    pub fn mailing_list(&self, filter: ListFilter) -> ServiceResult<MailingList> {
+        let addresses = self.list_with(&ListFilter {
+            archived: true,
+            ..filter
+        })?;
+
+        let mut list = MailingList::default();
+        let mut households = HashSet::new();
+        for address in addresses {
+            let reason = suppression(&address).or_else(|| {
+                let household = address.fingerprint_in(DuplicateScope::Location);
+                (!households.insert(household)).then_some(Suppression::Household)
+            });
+
+            match reason {
+                Some(reason) => list.suppressed.push((address.id(), reason)),
+                None => list.recipients.push(address),
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Writes the recipients of `list` as a print-ready CSV: the ID, then the
+    /// french lines of each address as on the envelope. The country line is
+    /// left out of the addresses in France when the mail is sent from France.
+    pub fn mailing_list_csv(&self, list: &MailingList) -> ServiceResult<String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record(MAILING_COLUMNS)?;
+
+        for address in &list.recipients {
+            let Either::French(french) = self.format(address, Format::French)? else {
+                unreachable!("the french format gives a french address");
+            };
+            let mut lines = french.lines();
+            if address.country == Country::France && self.mail_origin() == MailOrigin::France {
+                lines.pop();
+            }
+
+            let id = address.id().to_string();
+            let empty = MAILING_COLUMNS.len() - 1 - lines.len();
+            writer.write_record(
+                [id.as_str()]
+                    .into_iter()
+                    .chain(lines.iter().map(String::as_str))
+                    .chain(iter::repeat_n("", empty)),
+            )?;
+        }
+        let csv = writer
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()))?;
+
+        Ok(String::from_utf8_lossy(&csv).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{Suppression, OPT_OUT_KEY};
+    use crate::application::service::{AddressService, Format, ServiceResult};
+    use crate::application::stats::ListFilter;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    #[test]
+    fn mailing_list_suppressions() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let save = |name: &str, street: &str| {
+            let input = format!(
+                r#"{{"name": "{name}", "street": "{street}", "postal": "33380 MIOS", "country": "FRANCE"}}"#
+            );
+            service.save(&input, Format::French)
+        };
+        let jean = save("Monsieur Jean DELHOURME", "25 RUE DE L'EGLISE")?;
+        let marie = save("Madame Marie DELHOURME", "25 RUE DE L'EGLISE")?;
+        let isabelle = save("Madame Isabelle RICHARD", "10 RUE DES PINS")?;
+        let opted_out = save("Monsieur Paul MARTIN", "3 RUE DES LILAS")?;
+        let returned = save("Madame Anne PETIT", "8 RUE DU MOULIN")?;
+        service.annotate(
+            opted_out,
+            None,
+            BTreeMap::from([(OPT_OUT_KEY.to_string(), "yes".to_string())]),
+        )?;
+        service.mark_invalid(returned, "NPAI")?;
+
+        let list = service.mailing_list(ListFilter::default())?;
+        let recipients: Vec<_> = list.recipients.iter().map(|address| address.id()).collect();
+        // Marie, saved after Jean, is kept for their household.
+        assert_eq!(recipients, [isabelle, marie]);
+        assert_eq!(
+            list.suppressed_counts(),
+            BTreeMap::from([
+                (Suppression::Invalid, 1),
+                (Suppression::OptedOut, 1),
+                (Suppression::Household, 1),
+            ])
+        );
+        assert!(list.suppressed.contains(&(jean, Suppression::Household)));
+
+        let csv = service.mailing_list_csv(&list)?;
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("id,line_1,line_2,line_3,line_4,line_5,line_6,line_7")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(
+                format!("{isabelle},Madame Isabelle RICHARD,10 RUE DES PINS,33380 MIOS,,,,")
+                    .as_str()
+            )
+        );
+
+        Ok(())
+    }
+}
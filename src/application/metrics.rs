@@ -0,0 +1,60 @@
+//! Names of the metrics recorded through the `metrics` facade when the
+//! `metrics` feature is enabled. Without it, the recording functions compile
+//! to nothing.
+
+#[cfg(feature = "metrics")]
+use super::service::AddressServiceError;
+use super::service::{SaveOutcome, ServiceResult};
+#[cfg(feature = "metrics")]
+use crate::domain::repositories::AddressRepositoryError;
+
+/// Counter of the addresses saved (created by a save, an upsert or an import).
+pub const SAVES_TOTAL: &str = "address_saves_total";
+/// Counter of the addresses updated (update, or upsert of a duplicate).
+pub const UPDATES_TOTAL: &str = "address_updates_total";
+/// Counter of the addresses fetched.
+pub const FETCHES_TOTAL: &str = "address_fetches_total";
+/// Counter of the addresses deleted.
+pub const DELETES_TOTAL: &str = "address_deletes_total";
+/// Counter of the saves rejected because the address was already stored.
+pub const DUPLICATES_REJECTED_TOTAL: &str = "address_duplicates_rejected_total";
+/// Counter of the repository calls, labelled with the `operation` and its
+/// `outcome` (`ok` or `error`).
+pub const REPOSITORY_OPERATIONS_TOTAL: &str = "address_repository_operations_total";
+/// Histogram of the duration of the repository calls in seconds, labelled with
+/// the `operation`.
+pub const REPOSITORY_OPERATION_DURATION_SECONDS: &str =
+    "address_repository_operation_duration_seconds";
+
+/// Counts a save, or the duplicate that rejected it.
+pub(crate) fn record_save<T>(result: &ServiceResult<T>) {
+    #[cfg(feature = "metrics")]
+    match result {
+        Ok(_) => metrics::counter!(SAVES_TOTAL).increment(1),
+        Err(AddressServiceError::PersistenceError(AddressRepositoryError::AlreadyExists(_))) => {
+            metrics::counter!(DUPLICATES_REJECTED_TOTAL).increment(1)
+        }
+        Err(_) => {}
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = result;
+}
+
+/// Counts the save or the update of an upsert.
+pub(crate) fn record_upsert(outcome: &ServiceResult<SaveOutcome>) {
+    match outcome {
+        Ok(SaveOutcome::Created(_)) => record_save(outcome),
+        Ok(SaveOutcome::Updated(_)) => record_success(UPDATES_TOTAL, outcome),
+        Ok(SaveOutcome::Replayed(_)) | Err(_) => {}
+    }
+}
+
+/// Counts a successful operation with the given counter.
+pub(crate) fn record_success<T>(counter: &'static str, result: &ServiceResult<T>) {
+    #[cfg(feature = "metrics")]
+    if result.is_ok() {
+        metrics::counter!(counter).increment(1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (counter, result);
+}
@@ -1 +1,15 @@
+#[cfg(feature = "backup")]
+pub mod backup;
+pub mod batch;
+pub mod dedupe;
+pub mod export;
+pub mod generate;
+pub mod json_error;
+pub mod mailing;
+pub mod metrics;
+pub mod search;
 pub mod service;
+pub mod simple;
+pub mod stats;
+pub mod suggest;
+pub mod sync;
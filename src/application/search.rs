@@ -0,0 +1,68 @@
+use super::service::{AddressService, ServiceResult};
+use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+use crate::domain::Address;
+
+/// An address found by [`AddressService::search`], with its relevance.
+#[derive(Debug)]
+pub struct SearchResult {
+    pub address: Address,
+    pub score: f32,
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Finds the `limit` stored addresses most relevant to `query`, the most
+    /// relevant first. Every word of the query must start a word of the
+    /// address (e.g., `delh mios`), the search index of the `fts` feature
+    /// also accepting a typo.
+    pub fn search(&self, query: &str, limit: usize) -> ServiceResult<Vec<SearchResult>> {
+        let mut results = Vec::new();
+
+        for hit in self.repository.search(query, limit)? {
+            // An index out of sync may still list a deleted address.
+            match self.repository.fetch(&hit.id.to_string()) {
+                Ok(address) => results.push(SearchResult {
+                    address,
+                    score: hit.score,
+                }),
+                Err(AddressRepositoryError::NotFound(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Rebuilds the search index from the stored addresses, returning how
+    /// many were indexed, none without index.
+    pub fn reindex(&self) -> ServiceResult<Option<usize>> {
+        Ok(self.repository.reindex()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::application::service::{AddressService, Format, ServiceResult};
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    #[test]
+    fn search_without_index() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+        let id = service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        service.save(
+            r#"{"name": "Madame Marie DURAND", "street": "8 RUE DE RIVOLI", "postal": "75001 PARIS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+
+        let results = service.search("église Mios", 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].address.id(), id);
+        assert_eq!(service.search("rue", 1)?.len(), 1);
+        assert!(service.search("", 10)?.is_empty());
+        assert_eq!(service.reindex()?, None);
+
+        Ok(())
+    }
+}
@@ -1,56 +1,489 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{panic, thread};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+use super::json_error::{self, JsonError};
+use super::metrics;
+use crate::domain::repositories::{
+    AddressFilter, AddressRepository, AddressRepositoryError, ChangeEvent, CompactionReport,
+};
 use crate::domain::*;
+use crate::error::ErrorKind;
+use crate::infrastructure::CancellationToken;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum AddressServiceError {
     #[error("Invalid json conversion: {0}")]
-    InvalidJson(#[from] serde_json::Error),
+    InvalidJson(#[from] JsonError),
+    #[error("Invalid csv input: {0}")]
+    InvalidCsv(#[from] csv::Error),
+    #[cfg(feature = "xlsx")]
+    #[error("Invalid xlsx input: {0}")]
+    InvalidXlsx(#[from] calamine::XlsxError),
+    #[error("Invalid xml input: {0}")]
+    InvalidXml(IsoXmlError),
     #[error("Address conversion error: {0}")]
     ConversionError(#[from] AddressConversionError),
     #[error("Repository error: {0}")]
     PersistenceError(#[from] AddressRepositoryError),
+    #[error("Status error: {0}")]
+    StatusError(#[from] AddressStatusError),
+    #[error("Ambiguous ID prefix `{prefix}`: {count} addresses match")]
+    AmbiguousId { prefix: String, count: usize },
+    #[error("Alias `{alias}` already names the address `{id}`")]
+    AliasInUse { alias: Alias, id: Uuid },
+    #[error("No version of the address `{id}` is kept from {as_of}")]
+    NoHistory { id: Uuid, as_of: DateTime<Utc> },
+}
+
+/// The machine readable form of an [`AddressServiceError`], written by the CLI
+/// JSON output and the HTTP API.
+#[derive(Debug, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorDetails {
+    /// The stable error code (e.g., `E_POSTAL_FORMAT`).
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "E_POSTAL_FORMAT"))]
+    pub code: &'static str,
+    /// The human readable message.
+    pub error: String,
+    /// The path of the offending input field, for conversion errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// The offending value, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+impl AddressServiceError {
+    /// The stable code of the error, e.g., `E_NOT_FOUND`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AddressServiceError::InvalidJson(_) => "E_INVALID_JSON",
+            AddressServiceError::InvalidCsv(_) => "E_INVALID_CSV",
+            #[cfg(feature = "xlsx")]
+            AddressServiceError::InvalidXlsx(_) => "E_INVALID_XLSX",
+            AddressServiceError::InvalidXml(_) => "E_INVALID_XML",
+            AddressServiceError::ConversionError(error) => error.code(),
+            AddressServiceError::StatusError(_) => "E_INVALID_STATUS",
+            AddressServiceError::AmbiguousId { .. } => "E_AMBIGUOUS_ID",
+            AddressServiceError::AliasInUse { .. } => "E_ALIAS_IN_USE",
+            AddressServiceError::NoHistory { .. } => "E_NO_HISTORY",
+            AddressServiceError::PersistenceError(error) => error.code(),
+        }
+    }
+
+    /// The category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AddressServiceError::InvalidJson(_)
+            | AddressServiceError::InvalidCsv(_)
+            | AddressServiceError::InvalidXml(_)
+            | AddressServiceError::AmbiguousId { .. } => ErrorKind::InvalidInput,
+            #[cfg(feature = "xlsx")]
+            AddressServiceError::InvalidXlsx(_) => ErrorKind::InvalidInput,
+            AddressServiceError::ConversionError(error) => error.kind(),
+            AddressServiceError::StatusError(_) | AddressServiceError::AliasInUse { .. } => {
+                ErrorKind::Conflict
+            }
+            AddressServiceError::NoHistory { .. } => ErrorKind::NotFound,
+            AddressServiceError::PersistenceError(error) => error.kind(),
+        }
+    }
+
+    pub fn details(&self) -> ErrorDetails {
+        let (field, value) = match self {
+            AddressServiceError::ConversionError(error) => (
+                Some(error.field().to_string()),
+                error.value().map(str::to_string),
+            ),
+            AddressServiceError::PersistenceError(
+                AddressRepositoryError::NotFound(id) | AddressRepositoryError::AlreadyExists(id),
+            ) => (None, Some(id.clone())),
+            AddressServiceError::PersistenceError(AddressRepositoryError::Conflict {
+                actual,
+                ..
+            }) => (Some("version".to_string()), Some(actual.to_string())),
+            AddressServiceError::StatusError(error) => {
+                (Some("status".to_string()), Some(error.status.to_string()))
+            }
+            AddressServiceError::AmbiguousId { prefix, .. } => {
+                (Some("id".to_string()), Some(prefix.clone()))
+            }
+            AddressServiceError::AliasInUse { alias, .. } => {
+                (Some("alias".to_string()), Some(alias.to_string()))
+            }
+            AddressServiceError::NoHistory { as_of, .. } => {
+                (Some("as_of".to_string()), Some(as_of.to_rfc3339()))
+            }
+            AddressServiceError::InvalidJson(error) => (
+                error.path().map(str::to_string),
+                error.snippet().map(str::to_string),
+            ),
+            _ => (None, None),
+        };
+
+        ErrorDetails {
+            code: self.code(),
+            error: self.to_string(),
+            field,
+            value,
+        }
+    }
+}
+
+impl From<serde_json::Error> for AddressServiceError {
+    fn from(error: serde_json::Error) -> Self {
+        AddressServiceError::InvalidJson(error.into())
+    }
+}
+
+impl From<IsoXmlError> for AddressServiceError {
+    fn from(error: IsoXmlError) -> Self {
+        match error {
+            // A missing element is reported like a missing JSON field.
+            IsoXmlError::Conversion(error) => AddressServiceError::ConversionError(error),
+            error => AddressServiceError::InvalidXml(error),
+        }
+    }
 }
 
 /// Short hand for `Result` type.
 pub type ServiceResult<T> = std::result::Result<T, AddressServiceError>;
 
-pub struct AddressService {
-    pub repository: Box<dyn AddressRepository>,
+/// Combines the stored address and the incoming one into the address written
+/// by an update or an upsert, see [`AddressService::with_update_policy`].
+pub type UpdatePolicy = Box<dyn Fn(&Address, ConvertedAddress) -> ConvertedAddress>;
+
+/// Converts the addresses and manages the ones stored in its repository `R`.
+///
+/// The service is generic so that the calls to the repository are resolved
+/// at compile time, and that its own methods stay reachable (e.g., to mock it
+/// in tests). [`BoxedAddressService`] picks the backend at runtime instead.
+pub struct AddressService<R> {
+    pub repository: R,
+    /// Where the mail is sent from, deciding how the country line of the
+    /// french addresses is written.
+    mail_origin: MailOrigin,
+    /// How the ISO 20022 addresses are written, e.g., their country.
+    iso_profile: IsoProfile,
+    /// Abbreviates the long lines of the parsed addresses when set.
+    normalizer: Option<Normalizer>,
+    /// Whether the business addresses read need a street.
+    street_policy: StreetPolicy,
+    /// Checks and normalizes the postcodes of the addresses read when set.
+    postcode_validators: Option<PostcodeValidators>,
+    /// Sets how the addresses read failing a validation are handled when
+    /// set, only the postcode validators checking them otherwise.
+    validation_policy: Option<ValidationPolicy>,
+    /// Merges the updates into the stored addresses when set, the update
+    /// replacing the stored address otherwise.
+    update_policy: Option<UpdatePolicy>,
+    /// How long the idempotency keys of the saves are honored.
+    idempotency_retention: Duration,
+    /// Reads the lines of the french addresses when set, the built-in rules
+    /// being used otherwise.
+    french_rules: Option<Arc<FrenchParserRules>>,
+    /// Stops the batch operations (e.g., an import) between two records once
+    /// cancelled.
+    cancellation: CancellationToken,
 }
 
-#[derive(Debug, PartialEq)]
+/// A service over any repository, chosen at runtime (e.g., from the
+/// environment by the CLI and the API).
+pub type BoxedAddressService = AddressService<Box<dyn AddressRepository>>;
+
+/// A converted address, in one of the supported formats.
+#[derive(Clone, Debug, PartialEq)]
 pub enum Either<F, I> {
     French(F),
     Iso20022(I),
+    Portuguese(PortugueseAddress),
+    Brazilian(BrazilianAddress),
+    Canonical(Box<CanonicalAddress>),
 }
 
 impl<F, I> Either<F, I> {
     pub fn french(self) -> Option<F> {
         match self {
             Either::French(f) => Some(f),
-            Either::Iso20022(_) => None,
+            _ => None,
         }
     }
 
     pub fn iso20022(self) -> Option<I> {
         match self {
-            Either::French(_) => None,
             Either::Iso20022(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn portuguese(self) -> Option<PortugueseAddress> {
+        match self {
+            Either::Portuguese(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn brazilian(self) -> Option<BrazilianAddress> {
+        match self {
+            Either::Brazilian(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn canonical(self) -> Option<CanonicalAddress> {
+        match self {
+            Either::Canonical(c) => Some(*c),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, strum::EnumIter)]
 pub enum Format {
     French,
     Iso20022,
+    Portuguese,
+    Brazilian,
+    /// The structured address of the domain, see [`CanonicalAddress`].
+    Canonical,
+}
+
+/// A format name matching none of [`Format::names`].
+#[derive(Debug, Error, PartialEq)]
+#[error("Invalid format `{0}`: must be {names}", names = Format::listing())]
+pub struct UnknownFormat(pub String);
+
+impl Format {
+    /// Every format, in declaration order.
+    pub fn all() -> impl Iterator<Item = Format> {
+        <Format as strum::IntoEnumIterator>::iter()
+    }
+
+    /// The name of the format, followed by the aliases read in its place
+    /// (e.g., `fr` and `nf-z10-011` for `french`).
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            Format::French => &["french", "fr", "nf-z10-011"],
+            Format::Iso20022 => &["iso20022", "iso", "iso-20022"],
+            Format::Portuguese => &["portuguese", "pt"],
+            Format::Brazilian => &["brazilian", "br"],
+            Format::Canonical => &["canonical"],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.names()[0]
+    }
+
+    /// The names of the formats, for the error messages: "'french', ... or
+    /// 'canonical'".
+    pub fn listing() -> String {
+        let names: Vec<String> = Format::all()
+            .map(|format| format!("'{}'", format.name()))
+            .collect();
+
+        match names.split_last() {
+            Some((last, [])) => last.clone(),
+            Some((last, names)) => format!("{} or {last}", names.join(", ")),
+            None => String::new(),
+        }
+    }
+    /// The input format of a conversion to this format. The french and ISO
+    /// 20022 formats convert to each other, the canonical format is converted
+    /// from french, and the other formats from ISO 20022.
+    pub fn conversion_source(self) -> Format {
+        match self {
+            Format::Iso20022 | Format::Canonical => Format::French,
+            Format::French | Format::Portuguese | Format::Brazilian => Format::Iso20022,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
-impl AddressService {
-    pub fn new(repository: Box<dyn AddressRepository>) -> Self {
-        Self { repository }
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    /// Reads a format from its name or one of its aliases, ignoring the case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::all()
+            .find(|format| {
+                format
+                    .names()
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(s))
+            })
+            .ok_or_else(|| UnknownFormat(s.to_string()))
+    }
+}
+
+/// The path taken by an upsert.
+#[derive(Debug, PartialEq)]
+pub enum SaveOutcome {
+    /// A new address has been created.
+    Created(Uuid),
+    /// A duplicate was found and has been updated.
+    Updated(Uuid),
+    /// The address was already saved with the same idempotency key, nothing
+    /// has been written.
+    Replayed(Uuid),
+}
+
+impl SaveOutcome {
+    pub fn id(&self) -> Uuid {
+        match self {
+            SaveOutcome::Created(id) | SaveOutcome::Updated(id) | SaveOutcome::Replayed(id) => *id,
+        }
+    }
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// The retention of the idempotency keys, a day.
+    pub const DEFAULT_IDEMPOTENCY_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+    pub fn new(repository: R) -> Self {
+        Self {
+            repository,
+            mail_origin: MailOrigin::default(),
+            iso_profile: IsoProfile::default(),
+            normalizer: None,
+            street_policy: StreetPolicy::default(),
+            postcode_validators: None,
+            validation_policy: None,
+            update_policy: None,
+            idempotency_retention: Self::DEFAULT_IDEMPOTENCY_RETENTION,
+            french_rules: None,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Writes the country line of the french addresses for mail sent from
+    /// `origin`, France by default.
+    pub fn with_mail_origin(mut self, origin: MailOrigin) -> Self {
+        self.mail_origin = origin;
+        self
+    }
+
+    /// Where the mail is sent from, see [`AddressService::with_mail_origin`].
+    pub fn mail_origin(&self) -> MailOrigin {
+        self.mail_origin
+    }
+
+    /// Writes the ISO 20022 addresses as set by `profile`, by the standard by
+    /// default.
+    pub fn with_iso_profile(mut self, profile: IsoProfile) -> Self {
+        self.iso_profile = profile;
+        self
+    }
+
+    /// Accepts the business addresses without street according to `policy`,
+    /// a postbox replacing the street by default.
+    pub fn with_street_policy(mut self, policy: StreetPolicy) -> Self {
+        self.street_policy = policy;
+        self
+    }
+
+    /// Rejects the addresses read whose postcode isn't valid for their
+    /// country according to `validators`, and writes the others in the
+    /// standard form of their country (e.g., `sw1a1aa` -> `SW1A 1AA`).
+    pub fn with_postcode_validators(mut self, validators: PostcodeValidators) -> Self {
+        self.postcode_validators = Some(validators);
+        self
+    }
+
+    /// Validates the postcode, the town and the country line of the addresses
+    /// read, rejecting them, warning about them or ignoring the failures
+    /// according to the severities of `policy` for their country. The
+    /// postcodes are checked with the postcode validators when set, the
+    /// built-in ones otherwise.
+    pub fn with_validation_policy(mut self, policy: ValidationPolicy) -> Self {
+        self.validation_policy = Some(policy);
+        self
+    }
+
+    /// Decides how [`AddressService::update`] and [`AddressService::upsert`]
+    /// combine the stored address and the incoming one. The policy gets both
+    /// and returns the address to store, e.g., to keep the original
+    /// recipient:
+    ///
+    /// ```
+    /// # use address_converter::application::service::AddressService;
+    /// # use address_converter::infrastructure::InMemoryAddressRepository;
+    /// let service = AddressService::new(InMemoryAddressRepository::new())
+    ///     .with_update_policy(|stored, mut incoming| {
+    ///         incoming.recipient = stored.recipient.clone();
+    ///         incoming
+    ///     });
+    /// ```
+    ///
+    /// By default, the incoming address replaces the stored one. The ID, the
+    /// status and the version are kept either way.
+    pub fn with_update_policy(
+        mut self,
+        policy: impl Fn(&Address, ConvertedAddress) -> ConvertedAddress + 'static,
+    ) -> Self {
+        self.update_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Honors the idempotency keys of [`AddressService::save_once`] for
+    /// `retention` after the save, [`Self::DEFAULT_IDEMPOTENCY_RETENTION`] by
+    /// default.
+    pub fn with_idempotency_retention(mut self, retention: Duration) -> Self {
+        self.idempotency_retention = retention;
+        self
+    }
+
+    /// Abbreviates the long lines of the addresses before they are saved or
+    /// converted.
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Reads the lines of the french addresses with `rules` instead of the
+    /// built-in ones, e.g., to accept the military postboxes.
+    pub fn with_french_parser_rules(mut self, rules: FrenchParserRules) -> Self {
+        self.french_rules = Some(Arc::new(rules));
+        self
+    }
+
+    /// Stops the imports, deduplications and syncs between two records once
+    /// `cancellation` is cancelled, e.g., on Ctrl-C or when the client of a
+    /// long request goes away. The records already written are kept.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// The token stopping the batch operations, see
+    /// [`AddressService::with_cancellation`].
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Fails with [`AddressRepositoryError::Cancelled`] once the batch
+    /// operations are cancelled.
+    pub(crate) fn check_cancelled(&self) -> ServiceResult<()> {
+        if self.cancellation.is_cancelled() {
+            return Err(AddressRepositoryError::Cancelled.into());
+        }
+
+        Ok(())
     }
 
     /// Converts a json raw string input into an internal representation of an
@@ -64,169 +497,1748 @@ impl AddressService {
         input: &str,
         to_format: Format,
     ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
-        let either_converted_addr = match to_format {
+        self.convert_as(input, to_format, KindHint::Auto)
+    }
+
+    /// Same as [`AddressService::convert`], with an explicit address kind.
+    pub fn convert_as(
+        &self,
+        input: &str,
+        to_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        self.convert_from(input, to_format.conversion_source(), to_format, kind)
+    }
+
+    /// Same as [`AddressService::convert_as`], from an input of `from_format`
+    /// instead of the usual source of `to_format`.
+    pub fn convert_from(
+        &self,
+        input: &str,
+        from_format: Format,
+        to_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        let converted_addr = self.converter().parse_as(input, from_format, kind)?;
+
+        self.converter().render(&converted_addr, to_format)
+    }
+
+    /// Same as [`AddressService::convert`], reading the input without copying
+    /// its lines: they are borrowed from `input` until the conversion. The
+    /// kind is detected from the name field only, for the large payloads
+    /// converted in a loop, and the `kind` tag is ignored.
+    pub fn convert_borrowed(
+        &self,
+        input: &str,
+        to_format: Format,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        let converter = self.converter();
+        let (mut converted_addr, fields) = match to_format.conversion_source() {
             Format::French => {
-                // Build from the ISO20022 input
-                let iso: IsoAddress = serde_json::from_str(input)?;
-                let iso_addr = ConvertedAddress::from_iso20022(iso)?;
-                // Convert to french
-                let fr_addr = iso_addr.to_french()?;
-                Either::French(fr_addr)
+                let french: FrenchAddressRef = json_error::from_str(input)?;
+                let country_line = match &french {
+                    FrenchAddressRef::Individual(french) => french.country.to_string(),
+                    FrenchAddressRef::Business(french) => french.country.to_string(),
+                };
+                (
+                    converter.with_french_rules(|| ConvertedAddress::from_french_ref(french))?,
+                    ("street", "postal", "postal", Some(country_line)),
+                )
             }
-            Format::Iso20022 => {
-                // Build from the french input
-                let french: FrenchAddress = serde_json::from_str(input)?;
-                let fr_addr = ConvertedAddress::from_french(french)?;
-                // Convert to ISO20022
-                let iso_addr = fr_addr.to_iso20022()?;
-                Either::Iso20022(iso_addr)
+            _ => {
+                let iso: IsoAddressRef = json_error::from_str(input)?;
+                (
+                    ConvertedAddress::from_iso20022_ref(iso)?,
+                    (
+                        "postal_address.street_name",
+                        "postal_address.postcode",
+                        "postal_address.town_name",
+                        None,
+                    ),
+                )
+            }
+        };
+        let (street_field, postcode_field, town_field, country_line) = fields;
+        converted_addr.extras.shift_remove(KIND_TAG);
+        self.street_policy.check(&converted_addr, street_field)?;
+        let fields = ValidatedFields {
+            postcode: postcode_field,
+            town: town_field,
+            country_line: country_line.as_deref().map(|line| ("country", line)),
+        };
+        converter.validate(&mut converted_addr, fields)?;
+        if let Some(normalizer) = &self.normalizer {
+            normalizer.normalize(&mut converted_addr);
+        }
+
+        converter.render(&converted_addr, to_format)
+    }
+
+    /// Same as [`AddressService::convert_as`], also returning the non-fatal
+    /// issues of the conversion, e.g., the fields the output has no room for.
+    pub fn convert_with_warnings(
+        &self,
+        input: &str,
+        to_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<(Either<FrenchAddress, IsoAddress>, Warnings)> {
+        self.convert_with_warnings_from(input, to_format.conversion_source(), to_format, kind)
+    }
+
+    /// Same as [`AddressService::convert_with_warnings`], from an input of
+    /// `from_format`.
+    pub fn convert_with_warnings_from(
+        &self,
+        input: &str,
+        from_format: Format,
+        to_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<(Either<FrenchAddress, IsoAddress>, Warnings)> {
+        let converter = self.converter();
+        let (converted_addr, mut warnings) =
+            converter.parse_as_with_warnings(input, from_format, kind)?;
+
+        let (converted, conversion_warnings) =
+            converter.render_with_warnings(&converted_addr, to_format)?;
+        warnings.extend(conversion_warnings);
+
+        Ok((converted, warnings))
+    }
+
+    /// Same as [`AddressService::convert_as`], also returning which input
+    /// field populated which output field, and the rules applied.
+    pub fn convert_with_trace(
+        &self,
+        input: &str,
+        to_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<(Either<FrenchAddress, IsoAddress>, ConversionTrace)> {
+        self.convert_with_trace_from(input, to_format.conversion_source(), to_format, kind)
+    }
+
+    /// Same as [`AddressService::convert_with_trace`], from an input of
+    /// `from_format`.
+    pub fn convert_with_trace_from(
+        &self,
+        input: &str,
+        from_format: Format,
+        to_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<(Either<FrenchAddress, IsoAddress>, ConversionTrace)> {
+        let converter = self.converter();
+        let source = converter.read_source(input, from_format, kind)?;
+        let (mut converted_addr, _) = converter.domain_address(source.clone())?;
+
+        let from = match &source {
+            Either::French(french) => {
+                converter.with_french_rules(|| converted_addr.trace_from_french(french))
+            }
+            Either::Iso20022(iso) => converted_addr.trace_from_iso20022(iso),
+            Either::Portuguese(portuguese) => converted_addr.trace_from_portuguese(portuguese),
+            Either::Brazilian(brazilian) => converted_addr.trace_from_brazilian(brazilian),
+            Either::Canonical(_) => converted_addr.trace_from_canonical(),
+        };
+        let abbreviated = match &self.normalizer {
+            Some(normalizer) => {
+                let before = converted_addr.clone();
+                normalizer.normalize(&mut converted_addr);
+                converted_addr.changed_fields(&before)
             }
+            None => Vec::new(),
         };
+        let to = match to_format {
+            Format::French => converted_addr.trace_to_french(),
+            Format::Iso20022 => converted_addr.trace_to_iso20022(),
+            Format::Portuguese => converted_addr.trace_to_portuguese(),
+            Format::Brazilian => converted_addr.trace_to_brazilian(),
+            Format::Canonical => converted_addr.trace_to_canonical(),
+        };
+
+        let mut trace = ConversionTrace::join(from, to);
+        for field in abbreviated {
+            trace.apply(field, MappingRule::Abbreviation);
+        }
+
+        Ok((converter.render(&converted_addr, to_format)?, trace))
+    }
+
+    /// Converts the address of every party of an ISO 20022 XML message, such
+    /// as a pain.001 or pacs.008 document, to `to_format`.
+    pub fn convert_xml(
+        &self,
+        xml: &str,
+        to_format: Format,
+    ) -> ServiceResult<Vec<Either<FrenchAddress, IsoAddress>>> {
+        let converter = self.converter();
+
+        IsoAddress::parties_from_xml(xml)?
+            .into_iter()
+            .map(|party| {
+                let converted_addr = converter.parse_source(Either::Iso20022(party))?;
+                converter.render(&converted_addr, to_format)
+            })
+            .collect()
+    }
+
+    /// Converts each json raw string input of `from_format` to `to_format`.
+    /// An invalid input only fails its own conversion, the results being in
+    /// the order of the inputs.
+    pub fn convert_batch(
+        &self,
+        inputs: &[&str],
+        from_format: Format,
+        to_format: Format,
+    ) -> Vec<ServiceResult<Either<FrenchAddress, IsoAddress>>> {
+        let converter = self.converter();
 
-        Ok(either_converted_addr)
+        inputs
+            .iter()
+            .map(|input| converter.convert(input, from_format, to_format))
+            .collect()
+    }
+
+    /// Same as [`AddressService::convert_batch`], splitting the inputs
+    /// between as many threads as the machine can run in parallel.
+    pub fn convert_batch_parallel(
+        &self,
+        inputs: &[&str],
+        from_format: Format,
+        to_format: Format,
+    ) -> Vec<ServiceResult<Either<FrenchAddress, IsoAddress>>> {
+        let converter = self.converter();
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_len = inputs.len().div_ceil(threads).max(1);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = inputs
+                .chunks(chunk_len)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|input| converter.convert(input, from_format, to_format))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
     }
 
     pub fn save(&self, input: &str, from_format: Format) -> ServiceResult<Uuid> {
-        let converted_addr = match from_format {
-            Format::French => {
-                let french: FrenchAddress = serde_json::from_str(input)?;
-                ConvertedAddress::from_french(french)?
+        self.save_as(input, from_format, KindHint::Auto)
+    }
+
+    /// Same as [`AddressService::save`], with an explicit address kind.
+    pub fn save_as(&self, input: &str, from_format: Format, kind: KindHint) -> ServiceResult<Uuid> {
+        self.save_with_alias(input, from_format, kind, None)
+    }
+
+    /// Same as [`AddressService::save_as`], the address being named `alias`,
+    /// when given, by the same write. Fails with
+    /// [`AddressServiceError::AliasInUse`], saving nothing, when another
+    /// address has the alias.
+    pub fn save_with_alias(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+        alias: Option<Alias>,
+    ) -> ServiceResult<Uuid> {
+        let converted_addr = self.converter().parse_as(input, from_format, kind)?;
+        self.check_alias_free(alias.as_ref())?;
+
+        let address = Address::new(converted_addr).with_alias(alias);
+        let result = self.repository.save(address).map_err(Into::into);
+        metrics::record_save(&result);
+
+        result
+    }
+
+    /// Same as [`AddressService::save_as`], once per idempotency `key` sent by
+    /// the client: a retried save returns the address saved with the key,
+    /// without reading the input again, instead of failing as a duplicate.
+    /// A key older than the retention is saved with the address again.
+    pub fn save_once(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+        key: &str,
+    ) -> ServiceResult<SaveOutcome> {
+        self.save_once_with_alias(input, from_format, kind, key, None)
+    }
+
+    /// Same as [`AddressService::save_once`], the saved address being named
+    /// `alias` as by [`AddressService::save_with_alias`]. A replayed save
+    /// returns the address saved with the key as it is.
+    pub fn save_once_with_alias(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+        key: &str,
+        alias: Option<Alias>,
+    ) -> ServiceResult<SaveOutcome> {
+        if let Some(saved) = self.repository.fetch_by_idempotency_key(key)? {
+            let retained = saved.idempotency_key().is_some_and(|saved| {
+                // A key from the future, after a clock change, is retained.
+                (Utc::now() - saved.received_at)
+                    .to_std()
+                    .map_or(true, |age| age < self.idempotency_retention)
+            });
+            if retained {
+                return Ok(SaveOutcome::Replayed(saved.id()));
             }
-            Format::Iso20022 => {
-                let iso: IsoAddress = serde_json::from_str(input)?;
-                ConvertedAddress::from_iso20022(iso)?
+        }
+
+        let converted_addr = self.converter().parse_as(input, from_format, kind)?;
+        self.check_alias_free(alias.as_ref())?;
+
+        let address = Address::new(converted_addr)
+            .with_idempotency_key(key)
+            .with_alias(alias);
+        let result = self.repository.save(address).map_err(Into::into);
+        metrics::record_save(&result);
+
+        result.map(SaveOutcome::Created)
+    }
+
+    /// Saves the address, or updates the stored duplicate when there is one.
+    /// The duplicate keeps its identifier. The returned outcome tells which
+    /// path was taken.
+    pub fn upsert(&self, input: &str, from_format: Format) -> ServiceResult<SaveOutcome> {
+        self.upsert_as(input, from_format, KindHint::Auto)
+    }
+
+    /// Same as [`AddressService::upsert`], with an explicit address kind.
+    pub fn upsert_as(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<SaveOutcome> {
+        self.upsert_with_alias(input, from_format, kind, None)
+    }
+
+    /// Same as [`AddressService::upsert_as`], the saved or updated address
+    /// being named `alias`, when given, by the same write. Fails with
+    /// [`AddressServiceError::AliasInUse`] when another address than the
+    /// stored duplicate has the alias, the address saved in the meantime
+    /// being deleted.
+    pub fn upsert_with_alias(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+        alias: Option<Alias>,
+    ) -> ServiceResult<SaveOutcome> {
+        let converted_addr = self.converter().parse_as(input, from_format, kind)?;
+        let owner = match &alias {
+            Some(alias) => self.alias_owner(alias)?,
+            None => None,
+        };
+
+        // A taken alias is only kept by its owner, when it is the duplicate.
+        let new_alias = if owner.is_none() { alias.clone() } else { None };
+        let outcome = match self
+            .repository
+            .save(Address::new(converted_addr.clone()).with_alias(new_alias))
+        {
+            Ok(id) => match (owner, alias) {
+                (Some(owner), Some(alias)) => {
+                    self.repository.delete(&id.to_string())?;
+                    Err(AddressServiceError::AliasInUse { alias, id: owner })
+                }
+                _ => Ok(SaveOutcome::Created(id)),
+            },
+            Err(AddressRepositoryError::AlreadyExists(existing_id)) => {
+                let mut fetched_addr = self.repository.fetch(&existing_id)?;
+                let id = fetched_addr.id();
+
+                match (owner, alias) {
+                    (Some(owner), Some(alias)) if owner != id => {
+                        Err(AddressServiceError::AliasInUse { alias, id: owner })
+                    }
+                    (_, alias) => {
+                        if alias.is_some() {
+                            fetched_addr = fetched_addr.with_alias(alias);
+                        }
+                        self.merge(&mut fetched_addr, converted_addr);
+                        self.repository.update(fetched_addr)?;
+
+                        Ok(SaveOutcome::Updated(id))
+                    }
+                }
             }
+            Err(e) => Err(e.into()),
         };
+        metrics::record_upsert(&outcome);
 
-        let address = Address::new(converted_addr);
-        let id = self.repository.save(address)?;
+        outcome
+    }
 
-        Ok(id)
+    /// Fails when an address has `alias`, if given.
+    fn check_alias_free(&self, alias: Option<&Alias>) -> ServiceResult<()> {
+        let Some(alias) = alias else {
+            return Ok(());
+        };
+
+        match self.alias_owner(alias)? {
+            Some(id) => Err(AddressServiceError::AliasInUse {
+                alias: alias.clone(),
+                id,
+            }),
+            None => Ok(()),
+        }
     }
 
-    pub fn update(&self, id: &str, input: &str, from_format: Format) -> ServiceResult<()> {
-        let converted_addr = match from_format {
-            Format::French => {
-                let french: FrenchAddress = serde_json::from_str(input)?;
-                ConvertedAddress::from_french(french)?
-            }
-            Format::Iso20022 => {
-                let iso: IsoAddress = serde_json::from_str(input)?;
-                ConvertedAddress::from_iso20022(iso)?
+    pub fn update(&self, id: Uuid, input: &str, from_format: Format) -> ServiceResult<()> {
+        self.update_if_version(id, input, from_format, None)
+            .map(|_| ())
+    }
+
+    /// Same as [`AddressService::update`], refused with a conflict when the
+    /// stored address is not at `expected_version`. Returns the new version.
+    pub fn update_if_version(
+        &self,
+        id: Uuid,
+        input: &str,
+        from_format: Format,
+        expected_version: Option<u64>,
+    ) -> ServiceResult<u64> {
+        let converted_addr = self.parse(input, from_format)?;
+
+        let mut fetched_addr = self.repository.fetch(&id.to_string())?;
+        if let Some(expected) = expected_version {
+            if fetched_addr.version() != expected {
+                return Err(AddressRepositoryError::Conflict {
+                    id: id.to_string(),
+                    expected,
+                    actual: fetched_addr.version(),
+                }
+                .into());
             }
+        }
+        self.merge(&mut fetched_addr, converted_addr);
+        let version = fetched_addr.version();
+
+        let result = self.repository.update(fetched_addr).map_err(Into::into);
+        metrics::record_success(metrics::UPDATES_TOTAL, &result);
+
+        result.map(|_| version)
+    }
+
+    /// Updates the stored address with the incoming one, combined by the
+    /// update policy when set.
+    fn merge(&self, stored: &mut Address, incoming: ConvertedAddress) {
+        let merged = match &self.update_policy {
+            Some(policy) => policy(stored, incoming),
+            None => incoming,
         };
 
-        let mut fetched_addr = self.repository.fetch(id)?;
-        fetched_addr.update(converted_addr);
+        stored.update(merged);
+    }
 
-        self.repository.update(fetched_addr)?;
+    pub fn fetch(&self, id: Uuid) -> ServiceResult<Address> {
+        let result = self.repository.fetch(&id.to_string()).map_err(Into::into);
+        metrics::record_success(metrics::FETCHES_TOTAL, &result);
 
-        Ok(())
+        result
     }
 
-    pub fn fetch(&self, id: &str) -> ServiceResult<Address> {
-        let addr = self.repository.fetch(id)?;
+    /// The address as it was stored at `as_of`, to audit what was on file
+    /// when a letter was sent: its last version updated at or before this
+    /// instant. Fails with not found when the address wasn't saved yet, and
+    /// when the repository kept no version that old (e.g., a backend keeping
+    /// no history).
+    pub fn fetch_as_of(&self, id: Uuid, as_of: DateTime<Utc>) -> ServiceResult<Address> {
+        let mut versions = self.repository.fetch_history(&id.to_string())?;
+
+        match versions
+            .iter()
+            .rposition(|version| version.updated_at() <= as_of)
+        {
+            Some(i) => Ok(versions.swap_remove(i)),
+            None if versions.first().is_some_and(|oldest| oldest.version() > 1) => {
+                Err(AddressServiceError::NoHistory { id, as_of })
+            }
+            None => Err(AddressRepositoryError::NotFound(id.to_string()).into()),
+        }
+    }
+
+    /// Lists the identifiers of the stored addresses.
+    pub fn ids(&self) -> ServiceResult<Vec<Uuid>> {
+        let ids = self.repository.fetch_ids()?;
+
+        Ok(ids)
+    }
+
+    /// Finds the stored address whose ID starts with `prefix`, like git
+    /// resolves abbreviated hashes. The hyphens and the case of the prefix
+    /// don't matter (`3F2A-B` matches `3f2ab...`). Fails when no address, or
+    /// more than one, matches.
+    pub fn resolve_prefix(&self, prefix: &str) -> ServiceResult<Uuid> {
+        let normalized = prefix.replace('-', "").to_lowercase();
+        if normalized.is_empty() || !normalized.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AddressRepositoryError::NotFound(prefix.to_string()).into());
+        }
+
+        let matches: Vec<Uuid> = self
+            .ids()?
+            .into_iter()
+            .filter(|id| id.simple().to_string().starts_with(&normalized))
+            .collect();
+
+        match matches[..] {
+            [id] => Ok(id),
+            [] => Err(AddressRepositoryError::NotFound(prefix.to_string()).into()),
+            _ => Err(AddressServiceError::AmbiguousId {
+                prefix: prefix.to_string(),
+                count: matches.len(),
+            }),
+        }
+    }
+
+    /// The stored addresses whose [fingerprint](Address::fingerprint) is
+    /// `fingerprint`, e.g., as kept by an external system.
+    pub fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> ServiceResult<Vec<Address>> {
+        Ok(self.repository.find_by_fingerprint(fingerprint)?)
+    }
+
+    /// The stored addresses at `street` (e.g., "25 RUE DE L'EGLISE") in
+    /// `postcode`, whoever their recipient is, to tell whether an address is
+    /// already stored without fetching them all. The street is compared like
+    /// the duplicates are, see [`DuplicateScope::Location`].
+    pub fn lookup(
+        &self,
+        street: &str,
+        postcode: &str,
+        country: Country,
+    ) -> ServiceResult<Vec<Address>> {
+        let street = FrenchAddressParser::parse_street_for(street.trim(), &country)?;
+        let fingerprint = Fingerprint::of_location(&country, postcode, &street);
+        let filter = AddressFilter {
+            postcode: Some(postcode.to_string()),
+            country: Some(country),
+            ..AddressFilter::default()
+        };
 
-        Ok(addr)
+        Ok(self
+            .repository
+            .fetch_filtered(&filter)?
+            .into_iter()
+            .filter(|address| address.fingerprint_in(DuplicateScope::Location) == fingerprint)
+            .collect())
     }
 
     pub fn fetch_format(
         &self,
-        id: &str,
+        id: Uuid,
         format: Format,
     ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
         let addr = self.fetch(id)?;
-        let converted = addr.as_converted_address();
 
-        match format {
-            Format::French => Ok(Either::French(converted.to_french()?)),
-            Format::Iso20022 => Ok(Either::Iso20022(converted.to_iso20022()?)),
-        }
+        self.format(&addr, format)
     }
 
-    pub fn delete(&self, id: &str) -> ServiceResult<()> {
-        self.repository.delete(id)?;
+    /// The address in each of `formats`, in their order and once each, read
+    /// from the repository once (e.g., to display its french and ISO 20022
+    /// representations side by side).
+    pub fn fetch_formats(
+        &self,
+        id: Uuid,
+        formats: &[Format],
+    ) -> ServiceResult<Vec<(Format, Either<FrenchAddress, IsoAddress>)>> {
+        let addr = self.fetch(id)?;
 
-        Ok(())
+        self.formats(&addr, formats)
     }
-}
 
-#[cfg(test)]
-pub mod tests {
-    use uuid::Uuid;
+    /// Converts a stored address to `format`.
+    pub fn format(
+        &self,
+        addr: &Address,
+        format: Format,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        self.converter()
+            .render(&addr.as_converted_address(), format)
+    }
+
+    /// Converts a stored address to each of `formats`, in their order and
+    /// once each.
+    pub fn formats(
+        &self,
+        addr: &Address,
+        formats: &[Format],
+    ) -> ServiceResult<Vec<(Format, Either<FrenchAddress, IsoAddress>)>> {
+        let converter = self.converter();
+        let converted = addr.as_converted_address();
+
+        let mut rendered: Vec<(Format, _)> = Vec::with_capacity(formats.len());
+        for &format in formats {
+            if rendered.iter().all(|(done, _)| *done != format) {
+                rendered.push((format, converter.render(&converted, format)?));
+            }
+        }
+
+        Ok(rendered)
+    }
+
+    /// Archives the address, hiding it from the listings.
+    pub fn archive(&self, id: Uuid) -> ServiceResult<()> {
+        let mut address = self.repository.fetch(&id.to_string())?;
+        address.archive()?;
+
+        Ok(self.repository.update(address)?)
+    }
+
+    /// Marks the address as invalid for `reason`.
+    pub fn mark_invalid(&self, id: Uuid, reason: &str) -> ServiceResult<()> {
+        let mut address = self.repository.fetch(&id.to_string())?;
+        address.mark_invalid(reason)?;
+
+        Ok(self.repository.update(address)?)
+    }
+
+    /// Replaces the routing metadata of the address, e.g., its sorting code
+    /// once a mailing house has sorted it.
+    pub fn set_routing(&self, id: Uuid, routing: Routing) -> ServiceResult<()> {
+        let mut address = self.repository.fetch(&id.to_string())?;
+        address.set_routing(routing);
+
+        Ok(self.repository.update(address)?)
+    }
+
+    /// Replaces the recipient of the address, e.g., the contact person of a
+    /// business, without parsing the address again nor looking for its
+    /// duplicates.
+    pub fn update_recipient(&self, id: Uuid, recipient: Recipient) -> ServiceResult<()> {
+        let mut address = self.repository.fetch(&id.to_string())?;
+        address.set_recipient(recipient);
+
+        let result = self.repository.update(address).map_err(Into::into);
+        metrics::record_success(metrics::UPDATES_TOTAL, &result);
+
+        result
+    }
+
+    /// Replaces the notes and metadata of the address, leaving the address
+    /// itself unchanged.
+    pub fn annotate(
+        &self,
+        id: Uuid,
+        notes: Option<String>,
+        metadata: BTreeMap<String, String>,
+    ) -> ServiceResult<()> {
+        let mut address = self.repository.fetch(&id.to_string())?;
+        address.annotate(notes, metadata);
+
+        Ok(self.repository.update(address)?)
+    }
+
+    /// Names the address with `alias`, replacing its previous alias. Fails
+    /// when another address has the alias.
+    pub fn set_alias(&self, id: Uuid, alias: Alias) -> ServiceResult<()> {
+        match self.alias_owner(&alias)? {
+            Some(owner) if owner == id => return Ok(()),
+            Some(owner) => return Err(AddressServiceError::AliasInUse { alias, id: owner }),
+            None => {}
+        }
+
+        let mut address = self.repository.fetch(&id.to_string())?;
+        address.set_alias(Some(alias));
+
+        Ok(self.repository.update(address)?)
+    }
+
+    /// Removes `alias` from the address it names, whose ID is returned.
+    pub fn remove_alias(&self, alias: &Alias) -> ServiceResult<Uuid> {
+        let mut address = self
+            .repository
+            .fetch_by_alias(alias.as_str())?
+            .ok_or_else(|| AddressRepositoryError::NotFound(alias.to_string()))?;
+        address.set_alias(None);
+        let id = address.id();
+
+        self.repository.update(address)?;
+
+        Ok(id)
+    }
+
+    /// The ID of the address named `alias`, if any.
+    pub fn alias_owner(&self, alias: &Alias) -> ServiceResult<Option<Uuid>> {
+        let address = self.repository.fetch_by_alias(alias.as_str())?;
+
+        Ok(address.as_ref().map(Address::id))
+    }
+
+    /// The ID of the address named `alias`. Fails when no address has it.
+    pub fn resolve_alias(&self, alias: &Alias) -> ServiceResult<Uuid> {
+        self.alias_owner(alias)?
+            .ok_or_else(|| AddressRepositoryError::NotFound(alias.to_string()).into())
+    }
+
+    /// Lists the aliases with the ID of the address they name, sorted by
+    /// alias.
+    pub fn aliases(&self) -> ServiceResult<Vec<(Alias, Uuid)>> {
+        let mut aliases: Vec<(Alias, Uuid)> = self
+            .repository
+            .fetch_all()?
+            .into_iter()
+            .filter_map(|address| Some((address.alias()?.clone(), address.id())))
+            .collect();
+        aliases.sort();
+
+        Ok(aliases)
+    }
+
+    /// Reclaims the space left by the previous writes in the storage.
+    pub fn compact(&self) -> ServiceResult<CompactionReport> {
+        Ok(self.repository.compact()?)
+    }
+
+    /// Checks that the storage answers, see [`AddressRepository::ping`].
+    pub fn ping(&self) -> ServiceResult<()> {
+        Ok(self.repository.ping()?)
+    }
+
+    /// The changes logged after the sequence number `after`, oldest first and
+    /// at most `limit`, none without change log, see
+    /// [`AddressRepository::changes`].
+    pub fn changes(&self, after: u64, limit: usize) -> ServiceResult<Option<Vec<ChangeEvent>>> {
+        Ok(self.repository.changes(after, limit)?)
+    }
+
+    pub fn delete(&self, id: Uuid) -> ServiceResult<()> {
+        let result = self.repository.delete(&id.to_string()).map_err(Into::into);
+        metrics::record_success(metrics::DELETES_TOTAL, &result);
+
+        result
+    }
+
+    /// Builds the domain representation of a json raw string input, with the
+    /// long lines abbreviated when a normalizer is set.
+    pub(crate) fn parse(
+        &self,
+        input: &str,
+        from_format: Format,
+    ) -> ServiceResult<ConvertedAddress> {
+        self.converter()
+            .parse_as(input, from_format, KindHint::Auto)
+    }
+
+    /// Checks the json raw string input as it would be before being saved,
+    /// without saving nor converting it. Returns the warnings of the
+    /// validations whose severity is warn, see
+    /// [`AddressService::with_validation_policy`].
+    pub fn validate(&self, input: &str, from_format: Format) -> ServiceResult<Warnings> {
+        self.validate_as(input, from_format, KindHint::Auto)
+    }
+
+    /// Same as [`AddressService::validate`], with an explicit address kind.
+    pub fn validate_as(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<Warnings> {
+        let (_, warnings) = self
+            .converter()
+            .parse_as_with_warnings(input, from_format, kind)?;
+
+        Ok(warnings)
+    }
+
+    /// Same as [`AddressService::validate_as`], also checking the commune and
+    /// the street of the address against the official streets of
+    /// `referential`, see [`StreetReferential::check`].
+    pub fn validate_against(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+        referential: &StreetReferential,
+    ) -> ServiceResult<(Warnings, Vec<ReferentialIssue>)> {
+        let (address, warnings) =
+            self.converter()
+                .parse_as_with_warnings(input, from_format, kind)?;
+
+        Ok((warnings, referential.check(&address)))
+    }
+
+    /// Cleanses the json raw string input without converting it: its lines
+    /// are uppercased, without accents nor punctuation, and abbreviated when
+    /// too long, see [`Normalizer::cleanse`]. The address is returned in
+    /// `format`, with the abbreviations of the normalizer of the service, or
+    /// the standard ones when none is set.
+    pub fn normalize(
+        &self,
+        input: &str,
+        format: Format,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        self.normalize_as(input, format, KindHint::Auto)
+    }
+
+    /// Same as [`AddressService::normalize`], with an explicit address kind.
+    pub fn normalize_as(
+        &self,
+        input: &str,
+        format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        let converter = self.converter();
+        let mut converted_addr = converter.parse_as(input, format, kind)?;
+        match &self.normalizer {
+            Some(normalizer) => normalizer.cleanse(&mut converted_addr),
+            None => Normalizer::new().cleanse(&mut converted_addr),
+        }
+
+        converter.render(&converted_addr, format)
+    }
+
+    /// Migrates an unstructured ISO 20022 address (`<AdrLine>`) to the
+    /// structured elements, see [`UnstructuredToStructured`]. The lines are
+    /// read with the french parser rules of the service, the postcodes
+    /// checked with its validators and the address written with its ISO
+    /// profile.
+    pub fn migrate_iso(
+        &self,
+        input: &str,
+        converter: &UnstructuredToStructured,
+    ) -> ServiceResult<Migration> {
+        let address: UnstructuredIsoAddress = json_error::from_str(input)?;
+        let mut converter = converter.clone().with_iso_profile(self.iso_profile);
+        if let Some(validators) = &self.postcode_validators {
+            converter = converter.with_postcode_validators(validators.clone());
+        }
+
+        Ok(self
+            .converter()
+            .with_french_rules(|| converter.convert(&address))?)
+    }
+
+    /// The conversion settings of the service.
+    fn converter(&self) -> Converter<'_> {
+        Converter {
+            mail_origin: self.mail_origin,
+            iso_profile: self.iso_profile,
+            normalizer: self.normalizer.as_ref(),
+            street_policy: self.street_policy,
+            postcode_validators: self.postcode_validators.as_ref(),
+            validation_policy: self.validation_policy.as_ref(),
+            french_rules: self.french_rules.as_ref(),
+        }
+    }
+}
+
+/// The settings of the service used to convert the addresses. Unlike the
+/// service, it can be shared between threads, see
+/// [`AddressService::convert_batch_parallel`].
+#[derive(Clone, Copy)]
+struct Converter<'a> {
+    mail_origin: MailOrigin,
+    iso_profile: IsoProfile,
+    normalizer: Option<&'a Normalizer>,
+    street_policy: StreetPolicy,
+    postcode_validators: Option<&'a PostcodeValidators>,
+    validation_policy: Option<&'a ValidationPolicy>,
+    french_rules: Option<&'a Arc<FrenchParserRules>>,
+}
+
+impl Converter<'_> {
+    /// Converts the json raw string input of `from_format` to `to_format`.
+    fn convert(
+        &self,
+        input: &str,
+        from_format: Format,
+        to_format: Format,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        let converted_addr = self.parse_as(input, from_format, KindHint::Auto)?;
+
+        self.render(&converted_addr, to_format)
+    }
+
+    /// Builds the domain representation of a json raw string input of the
+    /// given kind.
+    fn parse_as(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<ConvertedAddress> {
+        Ok(self.parse_as_with_warnings(input, from_format, kind)?.0)
+    }
+
+    /// Same as [`Converter::parse_as`], also returning the warnings of the
+    /// validations.
+    fn parse_as_with_warnings(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<(ConvertedAddress, Warnings)> {
+        let source = self.read_source(input, from_format, kind)?;
+
+        self.parse_source_with_warnings(source)
+    }
+
+    /// Builds the domain representation of `source`, with its long lines
+    /// abbreviated when a normalizer is set.
+    fn parse_source(
+        &self,
+        source: Either<FrenchAddress, IsoAddress>,
+    ) -> ServiceResult<ConvertedAddress> {
+        Ok(self.parse_source_with_warnings(source)?.0)
+    }
+
+    /// Same as [`Converter::parse_source`], also returning the warnings of
+    /// the validations.
+    fn parse_source_with_warnings(
+        &self,
+        source: Either<FrenchAddress, IsoAddress>,
+    ) -> ServiceResult<(ConvertedAddress, Warnings)> {
+        let (mut converted_addr, warnings) = self.domain_address(source)?;
+
+        if let Some(normalizer) = self.normalizer {
+            normalizer.normalize(&mut converted_addr);
+        }
+
+        Ok((converted_addr, warnings))
+    }
+
+    /// Reads the json raw string input as an address of `from_format`. The
+    /// name field of the input is adjusted to the kind, so that an explicit
+    /// kind, or the `kind` tag of the input, takes precedence over the fields
+    /// provided.
+    fn read_source(
+        &self,
+        input: &str,
+        from_format: Format,
+        kind: KindHint,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        // The canonical address holds its kind in its own field.
+        if from_format == Format::Canonical {
+            return Ok(Either::Canonical(Box::new(json_error::from_str(input)?)));
+        }
+
+        let mut value: serde_json::Value = json_error::from_str(input)?;
+        let tag = AddressKind::take_tag(&mut value)
+            .map_err(<serde_json::Error as serde::de::Error>::custom)?;
+        // The kind given by the caller takes precedence over the tag.
+        let kind = match (kind, tag) {
+            (KindHint::Auto, Some(tag)) => tag,
+            (kind, _) => kind.resolve(&value),
+        };
+        kind.rename_name_field(&mut value);
+
+        let source = match (from_format, kind) {
+            (Format::French, AddressKind::Individual) => {
+                let individual: IndividualFrenchAddress = json_error::from_value(value)?;
+                Either::French(FrenchAddress::Individual(individual))
+            }
+            (Format::French, AddressKind::Business) => {
+                let business: BusinessFrenchAddress = json_error::from_value(value)?;
+                Either::French(FrenchAddress::Business(business))
+            }
+            // Only the name field matching the kind is left, which selects the
+            // right variant.
+            (Format::Iso20022, _) => Either::Iso20022(json_error::from_value(value)?),
+            (Format::Portuguese, _) => Either::Portuguese(json_error::from_value(value)?),
+            (Format::Brazilian, _) => Either::Brazilian(json_error::from_value(value)?),
+            (Format::Canonical, _) => Either::Canonical(Box::new(json_error::from_value(value)?)),
+        };
+
+        Ok(source)
+    }
+
+    /// Builds the domain representation of `source`, checking its street
+    /// against the street policy, then validating it, see
+    /// [`Converter::validate`].
+    fn domain_address(
+        &self,
+        source: Either<FrenchAddress, IsoAddress>,
+    ) -> Result<(ConvertedAddress, Warnings), AddressConversionError> {
+        let country_line = match &source {
+            Either::French(FrenchAddress::Individual(french)) => Some(french.country.clone()),
+            Either::French(FrenchAddress::Business(french)) => Some(french.country.clone()),
+            _ => None,
+        };
+        let (mut converted_addr, street_field, postcode_field, town_field) = match source {
+            Either::French(french) => (
+                self.with_french_rules(|| ConvertedAddress::from_french(french))?,
+                "street",
+                "postal",
+                "postal",
+            ),
+            Either::Iso20022(iso) => (
+                ConvertedAddress::from_iso20022(iso)?,
+                "postal_address.street_name",
+                "postal_address.postcode",
+                "postal_address.town_name",
+            ),
+            Either::Portuguese(portuguese) => (
+                ConvertedAddress::from_portuguese(portuguese)?,
+                "street",
+                "postal_code",
+                "postal_designation",
+            ),
+            Either::Brazilian(brazilian) => (
+                ConvertedAddress::from_brazilian(brazilian)?,
+                "street",
+                "cep",
+                "city",
+            ),
+            Either::Canonical(canonical) => (
+                ConvertedAddress::from_canonical(*canonical)?,
+                "street.name",
+                "postal_details.postcode",
+                "postal_details.town",
+            ),
+        };
+        self.street_policy.check(&converted_addr, street_field)?;
+        let fields = ValidatedFields {
+            postcode: postcode_field,
+            town: town_field,
+            country_line: country_line.as_deref().map(|line| ("country", line)),
+        };
+        let warnings = self.validate(&mut converted_addr, fields)?;
+
+        Ok((converted_addr, warnings))
+    }
+
+    /// Validates the address read with the validation policy when set, or
+    /// only its postcode with the postcode validators when set. Returns the
+    /// warnings of the validations whose severity is warn.
+    fn validate(
+        &self,
+        address: &mut ConvertedAddress,
+        fields: ValidatedFields<'_>,
+    ) -> Result<Warnings, AddressConversionError> {
+        match (self.validation_policy, self.postcode_validators) {
+            (Some(policy), validators) => policy.check(
+                address,
+                fields,
+                validators.unwrap_or(PostcodeValidators::built_in()),
+            ),
+            (None, Some(validators)) => validators
+                .check(address, fields.postcode)
+                .map(|()| Warnings::new()),
+            (None, None) => Ok(Warnings::new()),
+        }
+    }
+
+    /// Runs `f` with the french parser rules of the service, if any.
+    fn with_french_rules<T>(&self, f: impl FnOnce() -> T) -> T {
+        match self.french_rules {
+            Some(rules) => FrenchParserRules::apply(rules, f),
+            None => f(),
+        }
+    }
+
+    fn render(
+        &self,
+        converted: &ConvertedAddress,
+        format: Format,
+    ) -> ServiceResult<Either<FrenchAddress, IsoAddress>> {
+        let rendered = match format {
+            Format::French => Either::French(converted.to_french_from(self.mail_origin)?),
+            Format::Iso20022 => Either::Iso20022(converted.to_iso20022_with(self.iso_profile)?),
+            Format::Portuguese => Either::Portuguese(converted.to_portuguese()?),
+            Format::Brazilian => Either::Brazilian(converted.to_brazilian()?),
+            Format::Canonical => Either::Canonical(Box::new(converted.to_canonical())),
+        };
+
+        Ok(rendered)
+    }
+
+    /// Same as [`Converter::render`], also returning the warnings of the
+    /// conversion.
+    fn render_with_warnings(
+        &self,
+        converted: &ConvertedAddress,
+        format: Format,
+    ) -> ServiceResult<(Either<FrenchAddress, IsoAddress>, Warnings)> {
+        let rendered = match format {
+            Format::French => {
+                let (french, warnings) = converted.to_french_with_warnings(self.mail_origin)?;
+                (Either::French(french), warnings)
+            }
+            Format::Iso20022 => {
+                let (iso, warnings) = converted.to_iso20022_with_warnings(self.iso_profile)?;
+                (Either::Iso20022(iso), warnings)
+            }
+            Format::Portuguese => (
+                Either::Portuguese(converted.to_portuguese()?),
+                converted.dropped_fields(&converted.trace_to_portuguese()),
+            ),
+            Format::Brazilian => (
+                Either::Brazilian(converted.to_brazilian()?),
+                converted.dropped_fields(&converted.trace_to_brazilian()),
+            ),
+            Format::Canonical => (
+                Either::Canonical(Box::new(converted.to_canonical())),
+                Warnings::new(),
+            ),
+        };
+
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use serde_json::Map;
+    use uuid::Uuid;
+
+    use super::ServiceResult;
+    use super::{AddressService, AddressServiceError, ErrorDetails, SaveOutcome};
+    use crate::application::service::Either;
+    use crate::application::service::Format;
+    use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+    use crate::domain::*;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    fn service() -> AddressService<InMemoryAddressRepository> {
+        let repo = InMemoryAddressRepository::new();
+        AddressService::new(repo)
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(Format::from_str("nf-z10-011"), Ok(Format::French));
+        assert_eq!(Format::from_str("ISO"), Ok(Format::Iso20022));
+        assert_eq!(Format::from_str("pt"), Ok(Format::Portuguese));
+        assert_eq!(Format::Brazilian.to_string(), "brazilian");
+        assert_eq!(
+            Format::from_str("german").unwrap_err().to_string(),
+            "Invalid format `german`: must be 'french', 'iso20022', 'portuguese', 'brazilian' or 'canonical'"
+        );
+        // Every name reads back the format it names, and no other
+        for format in Format::all() {
+            for name in format.names() {
+                assert_eq!(Format::from_str(name), Ok(format));
+            }
+        }
+    }
+
+    #[test]
+    fn individual_french_to_iso() {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "internal_delivery": "Chez Mireille COPEAU Appartement 2",
+            "external_delivery": "Entrée A Bâtiment Jonquille",
+            "street": "25 RUE DE L'EGLISE",
+            "distribution_info": "CAUDOS",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+        let expected = IsoAddress::IndividualIsoAddress {
+            name: "Monsieur Jean DELHOURME".to_string(),
+            postal_address: IsoPostalAddress {
+                care_of: Some("Mireille COPEAU".to_string()),
+                street_name: Some("RUE DE L'EGLISE".to_string()),
+                building_number: Some("25".to_string()),
+                floor: Some("Entrée A Bâtiment Jonquille".to_string()),
+                room: Some("Appartement 2".to_string()),
+                postbox: Some("CAUDOS".to_string()),
+                department: None,
+                sub_department: None,
+                postcode: "33380".to_string(),
+                town_name: "MIOS".to_string(),
+                town_location_name: None,
+                country_sub_division: None,
+                country: "FR".to_string(),
+            },
+            extras: Map::new(),
+        };
+        let result = service.convert(input, Format::Iso20022);
+        assert!(result.is_ok(), "result was {result:#?}");
+        assert_eq!(result.unwrap(), Either::Iso20022(expected));
+    }
+
+    #[test]
+    fn individual_iso_to_french() {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": {
+                "street_name": "RUE DE L'EGLISE",
+                "building_number": "25",
+                "room": "Chez Mireille COPEAU Appartement 2",
+                "postbox": "CAUDOS",
+                "postcode": "33380",
+                "town_name": "MIOS",
+                "country": "FR"
+            }
+        }"#;
+        let expected = FrenchAddress::Individual(IndividualFrenchAddress {
+            name: "Monsieur Jean DELHOURME".to_string(),
+            internal_delivery: Some("Chez Mireille COPEAU Appartement 2".to_string()),
+            external_delivery: None,
+            street: Some("25 RUE DE L'EGLISE".to_string()),
+            distribution_info: Some("CAUDOS".to_string()),
+            postal: "33380 MIOS".to_string(),
+            country: "FRANCE".to_string(),
+            extras: Map::new(),
+        });
+        let result = service.convert(input, Format::French);
+        assert!(result.is_ok(), "result was {result:#?}");
+        assert_eq!(result.unwrap(), Either::French(expected));
+    }
+
+    #[test]
+    fn individual_iso_with_street_and_town_location_to_french() {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": {
+                "street_name": "RUE DE L'EGLISE",
+                "building_number": "25",
+                "town_location_name": "CLAPIERS",
+                "postcode": "33380",
+                "town_name": "MIOS",
+                "country": "FR"
+            }
+        }"#;
+        // The town location isn't turned into a lieu-dit, the street being
+        // given.
+        let expected = FrenchAddress::Individual(IndividualFrenchAddress {
+            name: "Monsieur Jean DELHOURME".to_string(),
+            internal_delivery: None,
+            external_delivery: None,
+            street: Some("25 RUE DE L'EGLISE".to_string()),
+            distribution_info: Some("CLAPIERS".to_string()),
+            postal: "33380 MIOS".to_string(),
+            country: "FRANCE".to_string(),
+            extras: Map::new(),
+        });
+        let result = service.convert(input, Format::French);
+        assert!(result.is_ok(), "result was {result:#?}");
+        assert_eq!(result.unwrap(), Either::French(expected));
+    }
+
+    #[test]
+    fn care_of_round_trip() -> ServiceResult<()> {
+        let service = service();
+        let iso = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": {
+                "CareOf": "Mireille COPEAU",
+                "street_name": "RUE DE L'EGLISE",
+                "building_number": "25",
+                "room": "Appartement 2",
+                "postcode": "33380",
+                "town_name": "MIOS",
+                "country": "FR"
+            }
+        }"#;
+
+        let Either::French(FrenchAddress::Individual(french)) =
+            service.convert(iso, Format::French)?
+        else {
+            panic!("expected an individual french address");
+        };
+        assert_eq!(
+            french.internal_delivery.as_deref(),
+            Some("Chez Mireille COPEAU Appartement 2")
+        );
+
+        // "C/O" is read as well, and a care of alone leaves no room.
+        let french = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "internal_delivery": "c/o Mireille COPEAU",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+        let Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. }) =
+            service.convert(french, Format::Iso20022)?
+        else {
+            panic!("expected an individual ISO 20022 address");
+        };
+        assert_eq!(postal_address.care_of.as_deref(), Some("Mireille COPEAU"));
+        assert_eq!(postal_address.room, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn borrowed_conversions() -> ServiceResult<()> {
+        let service = service();
+        let inputs = [
+            (
+                r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Chez Mireille COPEAU Appartement 2", "external_delivery": null, "street": "25 RUE DE L\u0027EGLISE", "distribution_info": "CAUDOS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+                Format::Iso20022,
+            ),
+            (
+                r#"{"business_name": "Société DUPONT SIREN 732 829 320", "recipient": "Mademoiselle Lucie MARTIN", "external_delivery": "Résidence des Capucins Bâtiment Quater", "street": "56 RUE EMILE ZOLA", "distribution_info": "BP 90432 MONTFERRIER SUR LEZ", "postal": "34092 MONTPELLIER CEDEX 5", "country": "FRANCE", "reference": "C-42"}"#,
+                Format::Iso20022,
+            ),
+            (
+                r#"{"businessName": "Société DUPONT", "OrgId": [{"Id": "732829320", "SchmeNm": "SIREN"}], "PstlAdr": {"StrtNm": "RUE EMILE ZOLA", "BldgNb": "56", "PstBx": "BP 90432", "PstCd": "34092", "TwnNm": "MONTPELLIER CEDEX 5", "Ctry": "FR"}}"#,
+                Format::French,
+            ),
+        ];
+
+        for (input, to_format) in inputs {
+            assert_eq!(
+                service.convert_borrowed(input, to_format)?,
+                service.convert(input, to_format)?
+            );
+        }
+
+        let input = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380", "country": "FRANCE"}"#;
+        let Err(AddressServiceError::ConversionError(error)) =
+            service.convert_borrowed(input, Format::Iso20022)
+        else {
+            panic!("expected an invalid postal");
+        };
+        assert_eq!(error.field(), "postal");
+
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_iso_to_french_keeps_postal_conventions() {
+        let input = r#"{
+            "name": "Mr John SMITH",
+            "postal_address": {
+                "street_name": "DOWNING STREET",
+                "building_number": "10",
+                "postcode": "SW1A 2AA",
+                "town_name": "LONDON",
+                "country": "GB"
+            }
+        }"#;
+        let expected = |country: &str| {
+            Either::French(FrenchAddress::Individual(IndividualFrenchAddress {
+                name: "Mr John SMITH".to_string(),
+                internal_delivery: None,
+                external_delivery: None,
+                street: Some("10 DOWNING STREET".to_string()),
+                distribution_info: None,
+                postal: "LONDON SW1A 2AA".to_string(),
+                country: country.to_string(),
+                extras: Map::new(),
+            }))
+        };
+
+        let from_france = service().convert(input, Format::French);
+        assert_eq!(from_france.unwrap(), expected("ROYAUME-UNI"));
+
+        let international = service()
+            .with_mail_origin(MailOrigin::International)
+            .convert(input, Format::French);
+        assert_eq!(international.unwrap(), expected("UNITED KINGDOM"));
+    }
+
+    #[test]
+    fn foreign_french_to_iso() {
+        let input = r#"{
+            "name": "Herr Max MUSTERMANN",
+            "street": "12 UNTER DEN LINDEN",
+            "postal": "10117 BERLIN",
+            "country": "DE"
+        }"#;
+        let result = service().convert(input, Format::Iso20022).unwrap();
+        let Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. }) = result
+        else {
+            panic!("expected an individual ISO address, got {result:?}");
+        };
+        assert_eq!(postal_address.postcode, "10117");
+        assert_eq!(postal_address.town_name, "BERLIN");
+        assert_eq!(postal_address.country, "DE");
+
+        // Town first countries need a postcode after the town
+        let input = r#"{
+            "name": "Mr John SMITH",
+            "street": "10 DOWNING STREET",
+            "postal": "LONDON",
+            "country": "ROYAUME-UNI"
+        }"#;
+        assert!(service().convert(input, Format::Iso20022).is_err());
+    }
+
+    #[test]
+    fn germanic_street_number_last() {
+        let input = r#"{
+            "name": "Herr Max MUSTERMANN",
+            "street": "STEPHANSPLATZ 1",
+            "postal": "1010 WIEN",
+            "country": "AUTRICHE"
+        }"#;
+        let result = service().convert(input, Format::Iso20022).unwrap();
+        let Either::Iso20022(iso) = result else {
+            panic!("expected an ISO address, got {result:?}");
+        };
+        let IsoAddress::IndividualIsoAddress { postal_address, .. } = &iso else {
+            panic!("expected an individual ISO address, got {iso:?}");
+        };
+        assert_eq!(postal_address.street_name.as_deref(), Some("STEPHANSPLATZ"));
+        assert_eq!(postal_address.building_number.as_deref(), Some("1"));
+        assert_eq!(postal_address.postcode, "1010");
+        assert_eq!(postal_address.country, "AT");
+
+        // Written back with the number last
+        let iso = serde_json::to_string(&iso).unwrap();
+        let Either::French(french) = service().convert(&iso, Format::French).unwrap() else {
+            panic!("expected a french address");
+        };
+        assert_eq!(
+            serde_json::to_value(&french).unwrap()["street"],
+            "STEPHANSPLATZ 1"
+        );
+    }
+
+    #[test]
+    fn iso_country_representation() {
+        let input = r#"{
+            "name": "Herr Max MUSTERMANN",
+            "street": "12 UNTER DEN LINDEN",
+            "postal": "10117 BERLIN",
+            "country": "DE"
+        }"#;
+        let country = |country_repr| {
+            let service = service().with_iso_profile(IsoProfile {
+                country_repr,
+                ..Default::default()
+            });
+            let iso = service.convert(input, Format::Iso20022).unwrap();
+            let Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. }) = &iso
+            else {
+                panic!("expected an individual ISO address, got {iso:?}");
+            };
+            // The legacy representations are read back.
+            let json = serde_json::to_string(&iso.clone().iso20022().unwrap()).unwrap();
+            assert!(service.convert(&json, Format::French).is_ok());
+
+            postal_address.country.clone()
+        };
+
+        assert_eq!(country(CountryRepr::Alpha2), "DE");
+        assert_eq!(country(CountryRepr::Alpha3), "DEU");
+        assert_eq!(country(CountryRepr::FullName), "GERMANY");
+        assert_eq!("full-name".parse(), Ok(CountryRepr::FullName));
+    }
+
+    #[test]
+    fn postcode_validators() {
+        let input = r#"{
+            "name": "Mevrouw Anna DE VRIES",
+            "street": "12 DAMRAK",
+            "postal": "1012 AB AMSTERDAM",
+            "country": "NL"
+        }"#;
+        let result = service().convert(input, Format::Iso20022).unwrap();
+        let Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. }) = result
+        else {
+            panic!("expected an individual ISO address, got {result:?}");
+        };
+        assert_eq!(postal_address.postcode, "1012 AB");
+        assert_eq!(postal_address.town_name, "AMSTERDAM");
+
+        let iso = |postcode: &str, country: &str| {
+            format!(
+                r#"{{
+                    "name": "Mr John SMITH",
+                    "postal_address": {{
+                        "street_name": "DOWNING STREET",
+                        "building_number": "10",
+                        "postcode": "{postcode}",
+                        "town_name": "LONDON",
+                        "country": "{country}"
+                    }}
+                }}"#
+            )
+        };
+        let checked = service().with_postcode_validators(PostcodeValidators::new());
+        let Some(FrenchAddress::Individual(french)) = checked
+            .convert(&iso("sw1a2aa", "GB"), Format::French)
+            .unwrap()
+            .french()
+        else {
+            panic!("expected an individual french address");
+        };
+        assert_eq!(french.postal, "LONDON SW1A 2AA");
+
+        let error = checked
+            .convert(&iso("SW1A", "GB"), Format::French)
+            .unwrap_err();
+        assert_eq!(error.details().code, "E_POSTAL_FORMAT");
+        assert_eq!(
+            error.details().field.as_deref(),
+            Some("postal_address.postcode")
+        );
+        // Not checked by default
+        assert!(service()
+            .convert(&iso("SW1A", "GB"), Format::French)
+            .is_ok());
+    }
+
+    #[test]
+    fn validation_policy() -> ServiceResult<()> {
+        let french = |postal: &str, country: &str| {
+            format!(
+                r#"{{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "{postal}", "country": "{country}"}}"#
+            )
+        };
+        let lenient_uk = Severities {
+            postcode: Severity::Warn,
+            ..Severities::default()
+        };
+        let lenient = service()
+            .with_validation_policy(ValidationPolicy::new().with_country("GB", lenient_uk));
+
+        // Rejected in France, stored with a warning in the United Kingdom
+        let error = lenient
+            .validate(&french("3338 MIOS", "FRANCE"), Format::French)
+            .unwrap_err();
+        assert_eq!(error.code(), "E_POSTAL_FORMAT");
+        let warnings = lenient.validate(&french("LONDON SW1A", "ROYAUME-UNI"), Format::French)?;
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["postal: invalid postcode `SW1A`"]
+        );
+        let id = lenient.save(&french("LONDON SW1A", "ROYAUME-UNI"), Format::French)?;
+        assert_eq!(lenient.fetch(id)?.postal_details.postcode, "SW1A");
+
+        // The town and the country line are warned about by default
+        let input = french("33380 MIOS 2", "FR");
+        let (_, warnings) =
+            lenient.convert_with_warnings(&input, Format::Iso20022, KindHint::Auto)?;
+        assert_eq!(warnings.len(), 2);
+        assert!(service().validate(&input, Format::French)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_long_lines() -> ServiceResult<()> {
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "external_delivery": "RESIDENCE DES GRANDS CHENES BATIMENT A",
+            "street": "12 BOULEVARD DU GENERAL CHARLES DE GAULLE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+
+        let service = service().with_normalizer(Normalizer::new());
+        let Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. }) =
+            service.convert(input, Format::Iso20022)?
+        else {
+            panic!("expected an individual ISO address");
+        };
+        assert_eq!(
+            postal_address.street_name.as_deref(),
+            Some("BD DU GENERAL CHARLES DE GAULLE")
+        );
+        // 38 characters, within the limit
+        assert_eq!(
+            postal_address.floor.as_deref(),
+            Some("RESIDENCE DES GRANDS CHENES BATIMENT A")
+        );
+
+        let id = service.save(input, Format::French)?;
+        let saved = service.fetch(id)?;
+        assert_eq!(
+            saved.street.unwrap().name,
+            "BD DU GENERAL CHARLES DE GAULLE"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_in_the_same_format() -> ServiceResult<()> {
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": {
+                "street_name": "Rue de l'Église",
+                "building_number": "25",
+                "postcode": "42000",
+                "town_name": "Saint-Étienne",
+                "country": "FR"
+            }
+        }"#;
 
-    use super::ServiceResult;
-    use super::{AddressService, AddressServiceError};
-    use crate::application::service::Either;
-    use crate::application::service::Format;
-    use crate::domain::repositories::AddressRepositoryError;
-    use crate::domain::*;
-    use crate::infrastructure::InMemoryAddressRepository;
+        let Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. }) =
+            service().normalize(input, Format::Iso20022)?
+        else {
+            panic!("expected an individual ISO address");
+        };
+        assert_eq!(
+            postal_address.street_name.as_deref(),
+            Some("RUE DE L EGLISE")
+        );
+        assert_eq!(postal_address.town_name, "SAINT ETIENNE");
+        assert_eq!(postal_address.postcode, "42000");
 
-    fn service() -> AddressService {
-        let repo = InMemoryAddressRepository::new();
-        AddressService::new(Box::new(repo))
+        Ok(())
     }
 
     #[test]
-    fn individual_french_to_iso() {
-        let service = service();
+    fn convert_with_trace() -> ServiceResult<()> {
         let input = r#"{
             "name": "Monsieur Jean DELHOURME",
-            "internal_delivery": "Chez Mireille COPEAU Appartement 2",
-            "external_delivery": "Entrée A Bâtiment Jonquille",
-            "street": "25 RUE DE L'EGLISE",
-            "distribution_info": "CAUDOS",
+            "street": "12 BOULEVARD DU GENERAL CHARLES DE GAULLE",
             "postal": "33380 MIOS",
             "country": "FRANCE"
         }"#;
-        let expected = IsoAddress::IndividualIsoAddress {
-            name: "Monsieur Jean DELHOURME".to_string(),
-            postal_address: IsoPostalAddress {
-                street_name: Some("RUE DE L'EGLISE".to_string()),
-                building_number: Some("25".to_string()),
-                floor: Some("Entrée A Bâtiment Jonquille".to_string()),
-                room: Some("Chez Mireille COPEAU Appartement 2".to_string()),
-                postbox: Some("CAUDOS".to_string()),
-                department: None,
-                postcode: "33380".to_string(),
-                town_name: "MIOS".to_string(),
-                town_location_name: None,
-                country: "FR".to_string(),
-            },
-        };
-        let result = service.convert(input, Format::Iso20022);
-        assert!(result.is_ok(), "result was {result:#?}");
-        assert_eq!(result.unwrap(), Either::Iso20022(expected));
+
+        let service = service().with_normalizer(Normalizer::new());
+        let (converted, trace) =
+            service.convert_with_trace(input, Format::Iso20022, KindHint::Auto)?;
+        assert_eq!(converted, service.convert(input, Format::Iso20022)?);
+
+        let street_name = trace
+            .mappings
+            .iter()
+            .find(|mapping| mapping.field.as_deref() == Some("street.name"))
+            .unwrap();
+        assert_eq!(street_name.source, "street");
+        assert_eq!(
+            street_name.target.as_deref(),
+            Some("postal_address.street_name")
+        );
+        assert_eq!(street_name.rules, vec![MappingRule::Abbreviation]);
+
+        Ok(())
     }
 
     #[test]
-    fn individual_iso_to_french() {
-        let service = service();
+    fn conversion_warnings() -> ServiceResult<()> {
         let input = r#"{
             "name": "Monsieur Jean DELHOURME",
             "postal_address": {
-                "street_name": "RUE DE L'EGLISE",
-                "building_number": "25",
-                "room": "Chez Mireille COPEAU Appartement 2",
-                "postbox": "CAUDOS",
+                "street_name": "BOULEVARD DU GENERAL CHARLES DE GAULLE",
+                "building_number": "12",
                 "postcode": "33380",
                 "town_name": "MIOS",
+                "country_sub_division": "GIRONDE",
                 "country": "FR"
             }
         }"#;
-        let expected = FrenchAddress::Individual(IndividualFrenchAddress {
-            name: "Monsieur Jean DELHOURME".to_string(),
-            internal_delivery: Some("Chez Mireille COPEAU Appartement 2".to_string()),
-            external_delivery: None,
-            street: Some("25 RUE DE L'EGLISE".to_string()),
-            distribution_info: Some("CAUDOS".to_string()),
-            postal: "33380 MIOS".to_string(),
-            country: "FRANCE".to_string(),
+
+        let (converted, warnings) =
+            service().convert_with_warnings(input, Format::French, KindHint::Auto)?;
+        assert_eq!(converted, service().convert(input, Format::French)?);
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "postal_details.country_subdivision dropped in the target format",
+                "street exceeds 38 characters (41)",
+            ]
+        );
+
+        // The abbreviated street fits the line.
+        let service = service().with_normalizer(Normalizer::new());
+        let (_, warnings) = service.convert_with_warnings(input, Format::French, KindHint::Auto)?;
+        assert_eq!(warnings.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn french_parser_rules() -> ServiceResult<()> {
+        let input = r#"{
+            "business_name": "Régiment DUPONT",
+            "street": "QUARTIER GENERAL FRERE",
+            "distribution_info": "SECTEUR POSTAL 69001 LYON ARMEES",
+            "postal": "00100 ARMEES",
+            "country": "FRANCE"
+        }"#;
+        let postal_address = |service: &AddressService<InMemoryAddressRepository>| match service
+            .convert(input, Format::Iso20022)
+        {
+            Ok(Either::Iso20022(IsoAddress::BusinessIsoAddress { postal_address, .. })) => {
+                postal_address
+            }
+            result => panic!("result was {result:#?}"),
+        };
+
+        let built_in = postal_address(&service());
+        assert_eq!(built_in.postbox, None);
+        assert_eq!(
+            built_in.town_location_name.as_deref(),
+            Some("SECTEUR POSTAL 69001 LYON ARMEES")
+        );
+
+        let rules = FrenchParserRules::new()
+            .with_postbox(r"^(?:[A-Z]{2}|SECTEUR POSTAL)\s+\d+")
+            .and_then(|rules| {
+                rules.with_town_location(r"^(?:(?:[A-Z]{2}|SECTEUR POSTAL)\s+\d+(?:\s+|$))?(.*)$")
+            })
+            .unwrap();
+        let service = service().with_french_parser_rules(rules);
+        let military = postal_address(&service);
+        assert_eq!(military.postbox.as_deref(), Some("SECTEUR POSTAL 69001"));
+        assert_eq!(military.town_location_name.as_deref(), Some("LYON ARMEES"));
+        let borrowed = service.convert_borrowed(input, Format::Iso20022)?;
+        assert_eq!(borrowed, service.convert(input, Format::Iso20022)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn overseas_addresses() -> ServiceResult<()> {
+        let input = r#"{
+            "name": "Madame Marie HOARAU",
+            "street": "12 RUE DE PARIS",
+            "postal": "97400 SAINT-DENIS",
+            "country": "FRANCE"
+        }"#;
+        let postal_address = |service: &AddressService<InMemoryAddressRepository>| match service
+            .convert(input, Format::Iso20022)
+        {
+            Ok(Either::Iso20022(IsoAddress::IndividualIsoAddress { postal_address, .. })) => {
+                postal_address
+            }
+            result => panic!("result was {result:#?}"),
+        };
+
+        let france = postal_address(&service());
+        assert_eq!(france.country, "FR");
+        assert_eq!(france.country_sub_division.as_deref(), Some("FR-974"));
+
+        let service = service().with_iso_profile(IsoProfile {
+            overseas: OverseasCountry::Territory,
+            ..Default::default()
         });
-        let result = service.convert(input, Format::French);
-        assert!(result.is_ok(), "result was {result:#?}");
-        assert_eq!(result.unwrap(), Either::French(expected));
+        let territory = postal_address(&service);
+        assert_eq!(territory.country, "RE");
+        assert_eq!(territory.country_sub_division, None);
+
+        // The territory is read back as France, named by the international
+        // country line.
+        let iso = serde_json::to_string(&IsoAddress::IndividualIsoAddress {
+            name: "Madame Marie HOARAU".to_string(),
+            postal_address: territory,
+            extras: Map::new(),
+        })?;
+        let service = service.with_mail_origin(MailOrigin::International);
+        let french = service.convert(&iso, Format::French)?.french().unwrap();
+        assert_eq!(
+            serde_json::to_value(&french)?["country"],
+            serde_json::json!("REUNION")
+        );
+        let address = service.fetch(service.save(&iso, Format::Iso20022)?)?;
+        assert_eq!(address.country, Country::France);
+        assert_eq!(
+            address.overseas_territory(),
+            Some(OverseasTerritory::LaReunion)
+        );
+
+        Ok(())
     }
 
     #[test]
@@ -243,18 +2255,23 @@ pub mod tests {
         }"#;
         let expected = IsoAddress::BusinessIsoAddress {
             business_name: "Société DUPONT".to_string(),
+            organisation_identification: vec![],
             postal_address: IsoPostalAddress {
+                care_of: None,
                 street_name: Some("RUE EMILE ZOLA".to_string()),
                 building_number: Some("56".to_string()),
                 floor: Some("Résidence des Capucins Bâtiment Quater".to_string()),
                 room: None,
                 postbox: Some("BP 90432".to_string()),
-                department: Some("Mademoiselle Lucie MARTIN".to_string()),
+                department: None,
+                sub_department: Some("Mademoiselle Lucie MARTIN".to_string()),
                 postcode: "34092".to_string(),
                 town_name: "MONTPELLIER CEDEX 5".to_string(),
                 town_location_name: Some("MONTFERRIER SUR LEZ".to_string()),
+                country_sub_division: None,
                 country: "FR".to_string(),
             },
+            extras: Map::new(),
         };
         let result = service.convert(input, Format::Iso20022);
         assert!(result.is_ok(), "result was {result:#?}");
@@ -279,18 +2296,225 @@ pub mod tests {
         }"#;
         let expected = FrenchAddress::Business(BusinessFrenchAddress {
             business_name: "Société DUPONT".to_string(),
+            identifiers: vec![],
             recipient: Some("Mademoiselle Lucie MARTIN".to_string()),
             external_delivery: None,
-            street: "56 RUE EMILE ZOLA".to_string(),
+            street: Some("56 RUE EMILE ZOLA".to_string()),
             distribution_info: Some("BP 90432 MONTFERRIER SUR LEZ".to_string()),
             postal: "34092 MONTPELLIER CEDEX 5".to_string(),
             country: "FRANCE".to_string(),
+            extras: Map::new(),
         });
         let result = service.convert(input, Format::French);
         assert!(result.is_ok(), "result was {result:#?}");
         assert_eq!(result.unwrap(), Either::French(expected));
     }
 
+    #[test]
+    fn business_french_with_siret_to_iso() {
+        let service = service();
+        let input = r#"{
+            "business_name": "Société DUPONT SIRET 732 829 320 00074",
+            "identifiers": ["732829320"],
+            "street": "56 RUE EMILE ZOLA",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE"
+        }"#;
+        let result = service.convert(input, Format::Iso20022);
+        assert!(result.is_ok(), "result was {result:#?}");
+
+        let Some(IsoAddress::BusinessIsoAddress {
+            business_name,
+            organisation_identification,
+            ..
+        }) = result.unwrap().iso20022()
+        else {
+            panic!("expected a business ISO address");
+        };
+        assert_eq!(business_name, "Société DUPONT");
+        assert_eq!(
+            organisation_identification,
+            vec![
+                IsoOrgIdentification {
+                    id: "732829320".to_string(),
+                    scheme_name: "SIREN".to_string(),
+                },
+                IsoOrgIdentification {
+                    id: "73282932000074".to_string(),
+                    scheme_name: "SIRET".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn business_iso_with_siret_to_french() {
+        let service = service();
+        let input = r#"{
+            "business_name": "Société DUPONT",
+            "organisation_identification": [{"id": "73282932000074", "scheme_name": "SIRET"}],
+            "postal_address": {
+                "street_name": "RUE EMILE ZOLA",
+                "building_number": "56",
+                "postcode": "34092",
+                "town_name": "MONTPELLIER CEDEX 5",
+                "country": "FR"
+            }
+        }"#;
+        let result = service.convert(input, Format::French);
+        assert!(result.is_ok(), "result was {result:#?}");
+
+        let Some(FrenchAddress::Business(business)) = result.unwrap().french() else {
+            panic!("expected a business french address");
+        };
+        assert_eq!(business.identifiers, vec!["73282932000074".to_string()]);
+    }
+
+    #[test]
+    fn business_with_invalid_siret() {
+        let service = service();
+        let input = r#"{
+            "business_name": "Société DUPONT SIRET 732 829 320 00075",
+            "street": "56 RUE EMILE ZOLA",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE"
+        }"#;
+        let result = service.convert(input, Format::Iso20022);
+        assert!(
+            matches!(
+                result,
+                Err(AddressServiceError::ConversionError(
+                    AddressConversionError::InvalidIdentifier { ref field, .. }
+                )) if field == "business_name"
+            ),
+            "result was {result:#?}"
+        );
+    }
+
+    #[test]
+    fn detect_business_from_legal_form() {
+        let service = service();
+        let input = r#"{
+            "name": "DURAND SA",
+            "recipient": "Service achat",
+            "street": "56 RUE EMILE ZOLA",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE"
+        }"#;
+        let result = service.convert(input, Format::Iso20022);
+        assert!(result.is_ok(), "result was {result:#?}");
+        assert!(matches!(
+            result.unwrap(),
+            Either::Iso20022(IsoAddress::BusinessIsoAddress { business_name, .. })
+                if business_name == "DURAND SA"
+        ));
+    }
+
+    #[test]
+    fn convert_batches() {
+        let service = service();
+        let valid = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+        let missing_postal = r#"{"name": "Monsieur Jean DELHOURME", "country": "FRANCE"}"#;
+        let mut inputs = vec![valid, missing_postal, "not json"];
+        inputs.extend([valid; 20]);
+
+        let results = service.convert_batch(&inputs, Format::French, Format::Iso20022);
+        assert_eq!(results.len(), inputs.len());
+        assert!(matches!(results[0], Ok(Either::Iso20022(_))));
+        assert!(matches!(
+            results[1],
+            Err(AddressServiceError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            results[2],
+            Err(AddressServiceError::InvalidJson(_))
+        ));
+        assert!(results[3..].iter().all(Result::is_ok));
+
+        let parallel = service.convert_batch_parallel(&inputs, Format::French, Format::Iso20022);
+        assert_eq!(
+            parallel.iter().map(|r| r.as_ref().ok()).collect::<Vec<_>>(),
+            results.iter().map(|r| r.as_ref().ok()).collect::<Vec<_>>()
+        );
+        assert!(service
+            .convert_batch_parallel(&[], Format::French, Format::Iso20022)
+            .is_empty());
+    }
+
+    #[test]
+    fn tagged_inputs() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "kind": "business",
+            "name": "Jean DELHOURME",
+            "street": "56 RUE EMILE ZOLA",
+            "postal": "34092 MONTPELLIER",
+            "country": "FRANCE"
+        }"#;
+
+        let tagged = service.convert(input, Format::Iso20022)?;
+        assert!(matches!(
+            &tagged,
+            Either::Iso20022(iso) if iso.kind() == AddressKind::Business && iso.extras().is_empty()
+        ));
+        // The kind given by the caller takes precedence over the tag.
+        let individual = service.convert_as(input, Format::Iso20022, KindHint::Individual)?;
+        assert!(matches!(
+            individual,
+            Either::Iso20022(IsoAddress::IndividualIsoAddress { .. })
+        ));
+
+        let french: FrenchAddress =
+            serde_json::from_str(&input.replace("\"name\"", "\"business_name\""))?;
+        assert_eq!(french.kind(), AddressKind::Business);
+        let error = serde_json::from_str::<FrenchAddress>(
+            r#"{"kind": "individual", "name": "Jean DELHOURME", "country": "FRANCE"}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("missing field `postal`"));
+
+        let unknown = service.convert(&input.replace("business", "company"), Format::French);
+        assert!(
+            matches!(unknown, Err(AddressServiceError::InvalidJson(_))),
+            "Result was: {unknown:#?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn explicit_kind_overrides_detection() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Madame Lucie SAS",
+            "street": "56 RUE EMILE ZOLA",
+            "postal": "34092 MONTPELLIER",
+            "country": "FRANCE"
+        }"#;
+
+        let detected = service.convert_as(input, Format::Iso20022, KindHint::Auto)?;
+        assert!(matches!(
+            detected,
+            Either::Iso20022(IsoAddress::BusinessIsoAddress { .. })
+        ));
+
+        let individual = service.convert_as(input, Format::Iso20022, KindHint::Individual)?;
+        assert!(matches!(
+            individual,
+            Either::Iso20022(IsoAddress::IndividualIsoAddress { .. })
+        ));
+
+        let id = service.save_as(input, Format::French, KindHint::Individual)?;
+        assert_eq!(service.fetch(id)?.kind, AddressKind::Individual);
+
+        Ok(())
+    }
+
     #[test]
     fn invalid_raw_french_input() {
         let service = service();
@@ -353,6 +2577,35 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn extras_through_save_and_fetch() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE",
+            "crm_id": "C-42",
+            "tags": ["vip"]
+        }"#;
+
+        let id = service.save(input, Format::French)?;
+        let fetched = service.fetch(id)?;
+        assert_eq!(fetched.extras["crm_id"], "C-42");
+
+        let iso = service
+            .fetch_format(id, Format::Iso20022)?
+            .iso20022()
+            .unwrap();
+        assert_eq!(iso.extras()["tags"][0], "vip");
+
+        let french = service.fetch_format(id, Format::French)?.french().unwrap();
+        let json = serde_json::to_value(french).unwrap();
+        assert_eq!(json["crm_id"], "C-42");
+
+        Ok(())
+    }
+
     #[test]
     fn save_individual_duplicate() -> ServiceResult<()> {
         let service = service();
@@ -361,32 +2614,94 @@ pub mod tests {
             "internal_delivery": "Chez Mireille COPEAU Appartement 2",
             "external_delivery": "Entrée A Bâtiment Jonquille",
             "street": "25 RUE DE L'EGLISE",
-            "distribution_info": "CAUDOS",
+            "distribution_info": "CAUDOS",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+
+        let minimal_input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+
+        // Save
+        service.save(input, Format::French)?;
+
+        // Recognize duplicated data
+        let result = service.save(minimal_input, Format::French);
+        assert!(
+            matches!(
+                result,
+                Err(AddressServiceError::PersistenceError(
+                    AddressRepositoryError::AlreadyExists(_)
+                ))
+            ),
+            "result was: {result:#?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn upsert_individual_duplicate() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+        let duplicate_input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "internal_delivery": "Chez Mireille COPEAU Appartement 2",
+            "street": "25 RUE DE L'EGLISE",
             "postal": "33380 MIOS",
             "country": "FRANCE"
         }"#;
 
-        let minimal_input = r#"{
+        let created = service.upsert(input, Format::French)?;
+        assert!(matches!(created, SaveOutcome::Created(_)));
+
+        let updated = service.upsert(duplicate_input, Format::French)?;
+        assert_eq!(updated, SaveOutcome::Updated(created.id()));
+
+        let addresses = service.repository.fetch_all()?;
+        assert_eq!(addresses.len(), 1);
+        let delivery_point = addresses[0].delivery_point.as_ref().unwrap();
+        assert_eq!(delivery_point.care_of, Some("Mireille COPEAU".to_string()));
+        assert_eq!(delivery_point.internal, Some("Appartement 2".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_once_per_idempotency_key() -> ServiceResult<()> {
+        let input = r#"{
             "name": "Monsieur Jean DELHOURME",
             "street": "25 RUE DE L'EGLISE",
             "postal": "33380 MIOS",
             "country": "FRANCE"
         }"#;
 
-        // Save
-        service.save(input, Format::French)?;
-
-        // Recognize duplicated data
-        let result = service.save(minimal_input, Format::French);
-        assert!(
-            matches!(
-                result,
-                Err(AddressServiceError::PersistenceError(
-                    AddressRepositoryError::AlreadyExists(_)
-                ))
-            ),
-            "result was: {result:#?}"
-        );
+        let service = service();
+        let created = service.save_once(input, Format::French, KindHint::Auto, "order-42")?;
+        assert!(matches!(created, SaveOutcome::Created(_)));
+        let retried = service.save_once(input, Format::French, KindHint::Auto, "order-42")?;
+        assert_eq!(retried, SaveOutcome::Replayed(created.id()));
+        let stored = service.fetch(created.id())?;
+        assert_eq!(stored.idempotency_key().unwrap().key, "order-42");
+
+        // Expired, the key no longer answers for the stored duplicate.
+        let service = service.with_idempotency_retention(Duration::ZERO);
+        let result = service.save_once(input, Format::French, KindHint::Auto, "order-42");
+        assert!(matches!(
+            result,
+            Err(AddressServiceError::PersistenceError(
+                AddressRepositoryError::AlreadyExists(_)
+            ))
+        ));
 
         Ok(())
     }
@@ -427,7 +2742,7 @@ pub mod tests {
         }"#;
 
         let id = service.save(input, Format::French)?;
-        let addr = service.fetch(&id.to_string())?;
+        let addr = service.fetch(id)?;
 
         // Update with new street
         let update_input = r#"{
@@ -437,7 +2752,7 @@ pub mod tests {
             "country": "FRANCE"
         }"#;
 
-        service.update(&id.to_string(), update_input, Format::French)?;
+        service.update(id, update_input, Format::French)?;
 
         // Verify update
         let updated = service.repository.fetch(&id.to_string())?;
@@ -451,6 +2766,84 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn update_stale_version() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+        let id = service.save(input, Format::French)?;
+        assert_eq!(service.fetch(id)?.version(), 1);
+
+        // Fetched by two clients, the second update is based on a stale copy.
+        let mut first = service.fetch(id)?;
+        let mut second = service.fetch(id)?;
+        first.update(service.parse(input, Format::French)?);
+        second.update(service.parse(input, Format::French)?);
+        service.repository.update(first)?;
+        assert!(matches!(
+            service.repository.update(second),
+            Err(AddressRepositoryError::Conflict {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+
+        let version = service.update_if_version(id, input, Format::French, Some(2))?;
+        assert_eq!(version, 3);
+        let error = service
+            .update_if_version(id, input, Format::French, Some(2))
+            .unwrap_err();
+        assert_eq!(error.code(), "E_VERSION_CONFLICT");
+        assert_eq!(error.details().value.as_deref(), Some("3"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_as_of() -> ServiceResult<()> {
+        let service = service();
+        let before_save = chrono::Utc::now();
+        std::thread::sleep(Duration::from_millis(2));
+        let id = service.save(
+            r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#,
+            Format::French,
+        )?;
+        std::thread::sleep(Duration::from_millis(2));
+        let letter_sent = chrono::Utc::now();
+        std::thread::sleep(Duration::from_millis(2));
+        service.update(
+            id,
+            r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "10 AVENUE DES CHAMPS",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#,
+            Format::French,
+        )?;
+
+        let on_file = service.fetch_as_of(id, letter_sent)?;
+        assert_eq!(on_file.version(), 1);
+        assert_eq!(on_file.street.unwrap().name, "RUE DE L'EGLISE");
+        let current = service.fetch_as_of(id, chrono::Utc::now())?;
+        assert_eq!(current.version(), 2);
+
+        let error = service.fetch_as_of(id, before_save).unwrap_err();
+        assert_eq!(error.code(), "E_NOT_FOUND");
+
+        Ok(())
+    }
+
     #[test]
     fn update_non_existent() {
         let service = service();
@@ -461,7 +2854,7 @@ pub mod tests {
             "country": "FRANCE"
         }"#;
         let uuid = Uuid::new_v4();
-        let result = service.update(&uuid.to_string(), input, Format::French);
+        let result = service.update(uuid, input, Format::French);
         assert!(matches!(
             result,
             Err(AddressServiceError::PersistenceError(
@@ -491,7 +2884,7 @@ pub mod tests {
     fn fetch_non_existent() {
         let service = service();
         let uuid = Uuid::new_v4();
-        let result = service.fetch(&uuid.to_string());
+        let result = service.fetch(uuid);
         assert!(matches!(
             result,
             Err(AddressServiceError::PersistenceError(
@@ -500,6 +2893,78 @@ pub mod tests {
         ));
     }
 
+    #[test]
+    fn fetch_several_formats() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+        let id = service.save(input, Format::French)?;
+
+        let formats = [Format::Iso20022, Format::French, Format::Iso20022];
+        let rendered = service.fetch_formats(id, &formats)?;
+        assert_eq!(
+            rendered
+                .iter()
+                .map(|(format, _)| *format)
+                .collect::<Vec<_>>(),
+            [Format::Iso20022, Format::French]
+        );
+        let (_, french) = rendered.into_iter().nth(1).unwrap();
+        assert!(french
+            .french()
+            .unwrap()
+            .lines()
+            .contains(&"25 RUE DE L'EGLISE".to_string()));
+
+        assert!(service.fetch_formats(Uuid::new_v4(), &formats).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_the_recipient_only() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "business_name": "Société DUPONT",
+            "recipient": "Mademoiselle Lucie MARTIN",
+            "street": "56 RUE EMILE ZOLA",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE"
+        }"#;
+        let id = service.save(input, Format::French)?;
+        let saved = service.fetch(id)?;
+
+        let recipient = Recipient::Business {
+            company_name: "Société DUPONT".to_string(),
+            contact_person: Some("Monsieur Paul MARTIN".to_string()),
+            service: Some("Service achat".to_string()),
+            identifiers: vec![],
+        };
+        service.update_recipient(id, recipient.clone())?;
+        let updated = service.fetch(id)?;
+        assert_eq!(updated.recipient, recipient);
+        assert_eq!(updated.version(), saved.version() + 1);
+        assert_eq!(updated.street, saved.street);
+        assert_eq!(updated.postal_details, saved.postal_details);
+
+        // The kind follows the recipient.
+        let recipient = Recipient::Individual {
+            name: "Monsieur Paul MARTIN".to_string(),
+        };
+        service.update_recipient(id, recipient)?;
+        assert_eq!(service.fetch(id)?.kind, AddressKind::Individual);
+
+        assert!(service
+            .update_recipient(Uuid::new_v4(), saved.recipient)
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn fetch_all_individuals() -> ServiceResult<()> {
         let service = service();
@@ -549,26 +3014,484 @@ pub mod tests {
             }
         }"#;
         let saved = service.save(input, Format::Iso20022)?;
-        let fetched = service.fetch(&saved.to_string())?;
+        let fetched = service.fetch(saved)?;
         // assert that the resource is well saved
         assert_eq!(fetched.id().to_string(), saved.to_string());
 
         // assert that the delete op went well
-        let result = service.delete(&saved.to_string());
+        let result = service.delete(saved);
         assert!(result.is_ok());
 
         // assert that the ressource is deleted
-        let fetch_result = service.fetch(&saved.to_string());
+        let fetch_result = service.fetch(saved);
         assert!(fetch_result.is_err());
 
         Ok(())
     }
 
+    #[test]
+    fn brazilian_save_and_fetch() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Sra. Maria Souza",
+            "logradouro": "Rua da Consolação",
+            "number": "930",
+            "bairro": "Consolação",
+            "city": "São Paulo",
+            "state": "sp",
+            "cep": "01302000"
+        }"#;
+        let id = service.save(input, Format::Brazilian)?;
+
+        let brazilian = service
+            .fetch_format(id, Format::Brazilian)?
+            .brazilian()
+            .unwrap();
+        assert_eq!(brazilian.cep, "01302-000");
+        assert_eq!(brazilian.state, "SP");
+
+        let iso = service
+            .fetch_format(id, Format::Iso20022)?
+            .iso20022()
+            .unwrap();
+        let IsoAddress::IndividualIsoAddress { postal_address, .. } = iso else {
+            panic!("expected an individual ISO 20022 address");
+        };
+        assert_eq!(postal_address.country, "BR");
+        assert_eq!(
+            postal_address.town_location_name.as_deref(),
+            Some("Consolação")
+        );
+        assert_eq!(postal_address.country_sub_division.as_deref(), Some("SP"));
+
+        // Not in Portugal
+        assert!(service.fetch_format(id, Format::Portuguese).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn iso_to_portuguese() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "name": "Sr. João Silva",
+            "postal_address": {
+                "street_name": "Rua Augusta",
+                "building_number": "274",
+                "room": "2º Esq.",
+                "postcode": "1100-053",
+                "town_name": "LISBOA",
+                "country": "PT"
+            }
+        }"#;
+        let portuguese = service
+            .convert(input, Format::Portuguese)?
+            .portuguese()
+            .unwrap();
+
+        assert_eq!(
+            portuguese.lines(),
+            vec![
+                "Sr. João Silva",
+                "Rua Augusta 274, 2º Esq.",
+                "1100-053 LISBOA"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_details_with_field_path() {
+        let service = service();
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": {
+                "street_name": "RUE DE L'EGLISE",
+                "postcode": "33380",
+                "town_name": "MIOS",
+                "country": "ATLANTIS"
+            }
+        }"#;
+        let error = service.convert(input, Format::French).unwrap_err();
+
+        assert_eq!(
+            error.details(),
+            ErrorDetails {
+                code: "E_UNKNOWN_COUNTRY",
+                error: error.to_string(),
+                field: Some("postal_address.country".to_string()),
+                value: Some("ATLANTIS".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn business_postbox_without_street() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{
+            "business_name": "Société DUPONT",
+            "distribution_info": "BP 90432",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE"
+        }"#;
+
+        let Either::Iso20022(iso) = service.convert(input, Format::Iso20022)? else {
+            panic!("expected an ISO 20022 address");
+        };
+        let IsoAddress::BusinessIsoAddress { postal_address, .. } = &iso else {
+            panic!("expected a business ISO 20022 address");
+        };
+        assert_eq!(postal_address.street_name, None);
+        assert_eq!(postal_address.postbox.as_deref(), Some("BP 90432"));
+
+        let iso = serde_json::to_string(&iso)?;
+        let Either::French(FrenchAddress::Business(french)) =
+            service.convert(&iso, Format::French)?
+        else {
+            panic!("expected a french business address");
+        };
+        assert_eq!(french.street, None);
+        assert_eq!(french.distribution_info.as_deref(), Some("BP 90432"));
+        assert_eq!(french.postal, "34092 MONTPELLIER CEDEX 5");
+
+        Ok(())
+    }
+
+    #[test]
+    fn businesses_at_different_postboxes_are_saved() -> ServiceResult<()> {
+        let service = service();
+        let business = |name: &str, postbox: &str| {
+            format!(
+                r#"{{"business_name": "{name}", "distribution_info": "{postbox}", "postal": "33000 BORDEAUX CEDEX", "country": "FRANCE"}}"#
+            )
+        };
+
+        let first = service.save(&business("SOCIETE DUPONT", "BP 123"), Format::French)?;
+        let second = service.save(&business("SOCIETE DUPONT", "BP 456"), Format::French)?;
+        let other = service.save(&business("DURAND SA", "BP 123"), Format::French)?;
+        assert_eq!(service.ids()?.len(), 3);
+        assert!(first != second && first != other);
+
+        // The same business at the same postbox is still a duplicate
+        let error = service
+            .save(&business("Société Dupont", "BP 123"), Format::French)
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            AddressServiceError::PersistenceError(AddressRepositoryError::AlreadyExists(id)) if id == first.to_string()
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn addresses_at_different_lieux_dits_are_saved() -> ServiceResult<()> {
+        let service = service();
+        let address = |locality: &str| {
+            format!(
+                r#"{{"name": "Monsieur Jean DELHOURME", "street": "{locality}", "postal": "33380 MIOS", "country": "FRANCE"}}"#
+            )
+        };
+
+        service.save(&address("LD LES GRANDS CHAMPS"), Format::French)?;
+        service.save(&address("LD LE BOURG"), Format::French)?;
+        assert_eq!(service.ids()?.len(), 2);
+        assert!(service
+            .save(&address("LD Les Grands Champs"), Format::French)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn street_policies() {
+        let postbox_only = r#"{
+            "business_name": "Société DUPONT",
+            "postal_address": {
+                "postbox": "BP 90432",
+                "postcode": "34092",
+                "town_name": "MONTPELLIER CEDEX 5",
+                "country": "FR"
+            }
+        }"#;
+        let no_street = r#"{
+            "business_name": "Société DUPONT",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE"
+        }"#;
+
+        let required = service().with_street_policy(StreetPolicy::Required);
+        let error = required.convert(postbox_only, Format::French).unwrap_err();
+        assert_eq!(error.details().code, "E_MISSING_FIELD");
+        assert_eq!(
+            error.details().field.as_deref(),
+            Some("postal_address.street_name")
+        );
+
+        let error = service().convert(no_street, Format::Iso20022).unwrap_err();
+        assert_eq!(error.details().field.as_deref(), Some("street"));
+
+        let optional = service().with_street_policy(StreetPolicy::Optional);
+        assert!(optional.convert(no_street, Format::Iso20022).is_ok());
+    }
+
+    #[test]
+    fn resolve_id_prefixes() -> ServiceResult<()> {
+        let service = service();
+        // 17 addresses, so that at least two IDs share their first digit.
+        for number in 1..=17 {
+            service.save(
+                &format!(r#"{{"name": "Monsieur Jean DELHOURME", "street": "{number} RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}}"#),
+                Format::French,
+            )?;
+        }
+        let ids = service.ids()?;
+
+        let id = ids[0].to_string();
+        assert_eq!(service.resolve_prefix(&id)?, ids[0]);
+        assert_eq!(service.resolve_prefix(&id[..13].to_uppercase())?, ids[0]);
+
+        let first_digit = |id: &Uuid| id.to_string()[..1].to_string();
+        let shared = ids
+            .iter()
+            .map(first_digit)
+            .find(|digit| ids.iter().filter(|id| first_digit(id) == *digit).count() > 1)
+            .unwrap();
+        let error = service.resolve_prefix(&shared).unwrap_err();
+        assert_eq!(error.details().code, "E_AMBIGUOUS_ID");
+        assert_eq!(error.details().value, Some(shared));
+
+        let unknown = (0..16)
+            .map(|digit| format!("{digit:x}"))
+            .find(|digit| ids.iter().all(|id| first_digit(id) != *digit));
+        if let Some(unknown) = unknown {
+            let error = service.resolve_prefix(&unknown).unwrap_err();
+            assert_eq!(error.details().code, "E_NOT_FOUND");
+        }
+        let error = service.resolve_prefix("not-hex").unwrap_err();
+        assert_eq!(error.details().code, "E_NOT_FOUND");
+
+        Ok(())
+    }
+
+    #[test]
+    fn lookup_stored_addresses() -> ServiceResult<()> {
+        let service = service();
+        let id = service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let fingerprint = service.fetch(id)?.fingerprint();
+
+        let ids =
+            |addresses: Vec<Address>| -> Vec<Uuid> { addresses.iter().map(Address::id).collect() };
+        assert_eq!(ids(service.find_by_fingerprint(&fingerprint)?), [id]);
+        assert_eq!(
+            ids(service.lookup("25 Rue de l'Église", "33 380", Country::France)?),
+            [id]
+        );
+        assert!(service
+            .lookup("27 RUE DE L'EGLISE", "33380", Country::France)?
+            .is_empty());
+        assert!(service
+            .lookup("25 RUE DE L'EGLISE", "33380", Country::Belgium)?
+            .is_empty());
+        let error = service.lookup("", "33380", Country::France).unwrap_err();
+        assert_eq!(error.details().code, "E_STREET_FORMAT");
+
+        Ok(())
+    }
+
+    #[test]
+    fn address_aliases() -> ServiceResult<()> {
+        let service = service();
+        let first = service.save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let second = service.save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let hq: Alias = "hq-bordeaux".parse().unwrap();
+        assert!("cafe-1".parse::<Alias>().is_err());
+        assert!("hq bordeaux".parse::<Alias>().is_err());
+
+        service.set_alias(first, hq.clone())?;
+        // Naming the address again changes nothing
+        service.set_alias(first, hq.clone())?;
+        assert_eq!(service.fetch(first)?.version(), 2);
+        assert_eq!(service.resolve_alias(&hq)?, first);
+
+        let error = service.set_alias(second, hq.clone()).unwrap_err();
+        assert_eq!(error.details().code, "E_ALIAS_IN_USE");
+        assert_eq!(error.details().value.as_deref(), Some("hq-bordeaux"));
+
+        // The alias is kept by the updates of the address
+        service.update(
+            first,
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "27 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        assert_eq!(service.aliases()?, vec![(hq.clone(), first)]);
+
+        assert_eq!(service.remove_alias(&hq)?, first);
+        assert_eq!(service.fetch(first)?.alias(), None);
+        let error = service.resolve_alias(&hq).unwrap_err();
+        assert_eq!(error.details().code, "E_NOT_FOUND");
+
+        Ok(())
+    }
+
+    #[test]
+    fn saves_with_alias() -> ServiceResult<()> {
+        const DELHOURME: &str = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+        const RICHARD: &str = r#"{"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#;
+        let service = service();
+        let hq: Alias = "hq-bordeaux".parse().unwrap();
+        let first =
+            service.save_with_alias(DELHOURME, Format::French, KindHint::Auto, Some(hq.clone()))?;
+        // Named by the save itself
+        assert_eq!(service.fetch(first)?.version(), 1);
+        assert_eq!(service.resolve_alias(&hq)?, first);
+
+        // A taken alias saves nothing, so that the save can be retried
+        let error = service
+            .save_with_alias(RICHARD, Format::French, KindHint::Auto, Some(hq.clone()))
+            .unwrap_err();
+        assert_eq!(error.details().code, "E_ALIAS_IN_USE");
+        let error = service
+            .save_once_with_alias(
+                RICHARD,
+                Format::French,
+                KindHint::Auto,
+                "k-1",
+                Some(hq.clone()),
+            )
+            .unwrap_err();
+        assert_eq!(error.details().code, "E_ALIAS_IN_USE");
+        let error = service
+            .upsert_with_alias(RICHARD, Format::French, KindHint::Auto, Some(hq.clone()))
+            .unwrap_err();
+        assert_eq!(error.details().code, "E_ALIAS_IN_USE");
+        assert_eq!(service.ids()?, vec![first]);
+
+        // The duplicate keeps its alias when upserted
+        let outcome = service.upsert_with_alias(
+            DELHOURME,
+            Format::French,
+            KindHint::Auto,
+            Some(hq.clone()),
+        )?;
+        assert_eq!(outcome, SaveOutcome::Updated(first));
+        assert_eq!(service.fetch(first)?.version(), 2);
+        let other: Alias = "auterive".parse().unwrap();
+        let second = service.save_with_alias(
+            RICHARD,
+            Format::French,
+            KindHint::Auto,
+            Some(other.clone()),
+        )?;
+        let error = service
+            .upsert_with_alias(RICHARD, Format::French, KindHint::Auto, Some(hq.clone()))
+            .unwrap_err();
+        assert_eq!(error.details().code, "E_ALIAS_IN_USE");
+        assert_eq!(service.fetch(second)?.alias(), Some(&other));
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_policies() -> ServiceResult<()> {
+        // Keeps the stored recipient, and the stored extras missing from the
+        // update.
+        let service = service().with_update_policy(|stored, mut incoming| {
+            incoming.recipient = stored.recipient.clone();
+            for (key, value) in &stored.extras {
+                incoming
+                    .extras
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            incoming
+        });
+        let id = service
+            .save(
+                r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE", "customer_ref": "C-42"}"#,
+                Format::French,
+            )?;
+
+        service.update(
+            id,
+            r#"{"name": "Madame Isabelle RICHARD", "street": "12 AVENUE DES CHAMPS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        let updated = service.fetch(id)?;
+        assert_eq!(
+            updated.recipient,
+            Recipient::Individual {
+                name: "Monsieur Jean DELHOURME".to_string()
+            }
+        );
+        assert_eq!(updated.street.as_ref().unwrap().name, "AVENUE DES CHAMPS");
+        assert_eq!(updated.extras["customer_ref"], "C-42");
+        assert_eq!(updated.version(), 2);
+
+        let outcome = service.upsert(
+            r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "12 AVENUE DES CHAMPS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )?;
+        assert_eq!(outcome, SaveOutcome::Updated(updated.id()));
+        let upserted = service.fetch(id)?;
+        assert_eq!(upserted.recipient, updated.recipient);
+        assert!(upserted.delivery_point.is_some());
+        assert_eq!(upserted.version(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn status_transitions() -> ServiceResult<()> {
+        let service = service();
+        let input = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+        let id = service.save(input, Format::French)?;
+
+        service.mark_invalid(id, "returned mail")?;
+        let address = service.fetch(id)?;
+        assert_eq!(
+            address.status(),
+            &AddressStatus::Invalid {
+                reason: "returned mail".to_string()
+            }
+        );
+        assert_eq!(address.version(), 2);
+
+        service.archive(id)?;
+        assert_eq!(service.fetch(id)?.status(), &AddressStatus::Archived);
+        // Archived addresses still convert.
+        assert!(service.fetch_format(id, Format::Iso20022).is_ok());
+
+        let error = service.archive(id).unwrap_err();
+        assert_eq!(
+            error.details(),
+            ErrorDetails {
+                code: "E_INVALID_STATUS",
+                error: error.to_string(),
+                field: Some("status".to_string()),
+                value: Some("archived".to_string()),
+            }
+        );
+        assert!(service.mark_invalid(id, "unknown recipient").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn delete_non_existent() {
         let service = service();
         let uuid = Uuid::new_v4();
-        let result = service.delete(&uuid.to_string());
+        let result = service.delete(uuid);
         assert!(matches!(
             result,
             Err(AddressServiceError::PersistenceError(
@@ -576,4 +3499,49 @@ pub mod tests {
             ))
         ));
     }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn record_saves_and_duplicates() -> ServiceResult<()> {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        use crate::application::metrics::{DUPLICATES_REJECTED_TOTAL, FETCHES_TOTAL, SAVES_TOTAL};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let service = service();
+        let input = r#"{
+            "business_name": "Société DUPONT",
+            "postal_address": {
+                "street_name": "RUE EMILE ZOLA",
+                "building_number": "56",
+                "postcode": "34092",
+                "town_name": "MONTPELLIER CEDEX 5",
+                "country": "FR"
+            }
+        }"#;
+
+        metrics::with_local_recorder(&recorder, || -> ServiceResult<()> {
+            let saved = service.save(input, Format::Iso20022)?;
+            assert!(service.save(input, Format::Iso20022).is_err());
+            service.fetch(saved)?;
+            Ok(())
+        })?;
+
+        let snapshot = snapshotter.snapshot().into_hashmap();
+        let counter = |name: &str| {
+            snapshot
+                .iter()
+                .find(|(key, _)| key.key().name() == name)
+                .map(|(_, (_, _, value))| value)
+        };
+        assert_eq!(counter(SAVES_TOTAL), Some(&DebugValue::Counter(1)));
+        assert_eq!(
+            counter(DUPLICATES_REJECTED_TOTAL),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(counter(FETCHES_TOTAL), Some(&DebugValue::Counter(1)));
+
+        Ok(())
+    }
 }
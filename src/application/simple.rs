@@ -0,0 +1,73 @@
+//! One-call conversions, for the users who convert addresses without storing
+//! them: the service and its repository are built behind the scenes.
+use super::service::{AddressService, AddressServiceError, Either, Format};
+use crate::domain::{FrenchAddress, IsoAddress, KindHint};
+use crate::error::Result;
+use crate::infrastructure::InMemoryAddressRepository;
+
+/// A service with the default settings. Its repository is never used.
+fn service() -> AddressService<InMemoryAddressRepository> {
+    AddressService::new(InMemoryAddressRepository::new())
+}
+
+/// Converts the JSON `input` of `from_format` to the JSON of `to_format`,
+/// the kind of the address being detected from its fields.
+///
+/// ```
+/// use address_converter::{convert_str, Format};
+///
+/// let input = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+/// let iso = convert_str(input, Format::French, Format::Iso20022).unwrap();
+/// assert!(iso.contains(r#""town_name":"MIOS""#));
+/// ```
+pub fn convert_str(input: &str, from_format: Format, to_format: Format) -> Result<String> {
+    let converted = service().convert_from(input, from_format, to_format, KindHint::Auto)?;
+
+    to_json(converted)
+}
+
+/// The JSON of a converted address, whatever its format.
+pub(crate) fn to_json(converted: Either<FrenchAddress, IsoAddress>) -> Result<String> {
+    let json = match converted {
+        Either::French(french) => serde_json::to_string(&french),
+        Either::Iso20022(iso) => serde_json::to_string(&iso),
+        Either::Portuguese(portuguese) => serde_json::to_string(&portuguese),
+        Either::Brazilian(brazilian) => serde_json::to_string(&brazilian),
+        Either::Canonical(canonical) => serde_json::to_string(&canonical),
+    }
+    .map_err(AddressServiceError::from)?;
+
+    Ok(json)
+}
+
+/// Checks the JSON `input` of `format` as it would be before being saved,
+/// without converting it.
+pub fn validate_str(input: &str, format: Format) -> Result<()> {
+    service().validate(input, format)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{convert_str, validate_str};
+    use crate::application::service::Format;
+    use crate::Error;
+
+    const ADDRESS: &str = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    #[test]
+    fn convert_and_validate_strings() {
+        let iso = convert_str(ADDRESS, Format::French, Format::Iso20022).unwrap();
+        let french = convert_str(&iso, Format::Iso20022, Format::French).unwrap();
+        let french: serde_json::Value = serde_json::from_str(&french).unwrap();
+        assert_eq!(french["street"], "25 RUE DE L'EGLISE");
+
+        assert!(validate_str(ADDRESS, Format::French).is_ok());
+        let invalid = ADDRESS.replace("33380 MIOS", "MIOS");
+        assert!(matches!(
+            validate_str(&invalid, Format::French),
+            Err(Error::Conversion(_))
+        ));
+    }
+}
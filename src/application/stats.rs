@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use super::service::{AddressService, ServiceResult};
+use crate::domain::repositories::{AddressFilter, AddressRepository};
+use crate::domain::{Address, AddressKind, AddressStatus, Region};
+
+/// Quality score from which an address is considered clean.
+pub const GOOD_QUALITY: u8 = 80;
+/// Quality score under which an address should be fixed first.
+pub const POOR_QUALITY: u8 = 50;
+
+/// Overview of the stored addresses.
+#[derive(Debug, Default, PartialEq)]
+pub struct AddressStats {
+    pub total: usize,
+    pub individuals: usize,
+    pub businesses: usize,
+    /// Average quality score, `None` without stored address.
+    pub average_quality: Option<f64>,
+    /// Addresses scoring at least [`GOOD_QUALITY`].
+    pub good_quality: usize,
+    /// Addresses scoring under [`POOR_QUALITY`].
+    pub poor_quality: usize,
+    /// French addresses per region, derived from their postcode.
+    pub regions: BTreeMap<Region, usize>,
+}
+
+/// Which stored addresses are listed.
+#[derive(Debug, Default)]
+pub struct ListFilter<'a> {
+    /// Only the addresses scoring at least this quality.
+    pub min_quality: u8,
+    /// Only the french addresses of this department (e.g., `33`, `2A` or
+    /// `971`).
+    pub department: Option<&'a str>,
+    /// Also the archived addresses, hidden otherwise.
+    pub archived: bool,
+    /// Only the addresses matching this postcode, town, country or kind.
+    pub matching: AddressFilter,
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Lists the stored addresses scoring at least `min_quality`, the most
+    /// recently updated first. Archived addresses are left out.
+    pub fn list(&self, min_quality: u8) -> ServiceResult<Vec<Address>> {
+        self.list_with(&ListFilter {
+            min_quality,
+            ..Default::default()
+        })
+    }
+
+    /// Same as [`AddressService::list`], with the addresses kept by `filter`.
+    pub fn list_with(&self, filter: &ListFilter) -> ServiceResult<Vec<Address>> {
+        let department = filter
+            .department
+            .map(|department| format!("{:0>2}", department.to_uppercase()));
+        let mut addresses: Vec<Address> = self
+            .repository
+            .fetch_filtered(&filter.matching)?
+            .into_iter()
+            .filter(|address| filter.archived || *address.status() != AddressStatus::Archived)
+            .filter(|address| address.quality_score() >= filter.min_quality)
+            .filter(|address| department.is_none() || address.department_code() == department)
+            .collect();
+        addresses.sort_by_key(|address| std::cmp::Reverse(address.updated_at()));
+
+        Ok(addresses)
+    }
+
+    pub fn stats(&self) -> ServiceResult<AddressStats> {
+        let addresses = self.repository.fetch_all()?;
+        let mut stats = AddressStats {
+            total: addresses.len(),
+            ..Default::default()
+        };
+        let mut total_quality = 0u32;
+
+        for address in &addresses {
+            match address.kind {
+                AddressKind::Individual => stats.individuals += 1,
+                AddressKind::Business => stats.businesses += 1,
+            }
+
+            if let Some(region) = address.region() {
+                *stats.regions.entry(region).or_default() += 1;
+            }
+
+            let quality = address.quality_score();
+            total_quality += u32::from(quality);
+            if quality >= GOOD_QUALITY {
+                stats.good_quality += 1;
+            } else if quality < POOR_QUALITY {
+                stats.poor_quality += 1;
+            }
+        }
+
+        if stats.total > 0 {
+            stats.average_quality = Some(f64::from(total_quality) / stats.total as f64);
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{AddressStats, ListFilter};
+    use crate::application::service::{AddressService, Format, ServiceResult};
+    use crate::domain::repositories::AddressFilter;
+    use crate::domain::{AddressKind, Country, Region};
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    fn service() -> ServiceResult<AddressService<InMemoryAddressRepository>> {
+        let repo = InMemoryAddressRepository::new();
+        let service = AddressService::new(repo);
+        let inputs = [
+            // 100
+            r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            // 70
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            // 25
+            r#"{"business_name": "DURAND SA", "street": "zone des pins", "postal": "00100 MIOS", "country": "FRANCE"}"#,
+        ];
+        for input in inputs {
+            service.save(input, Format::French)?;
+        }
+
+        Ok(service)
+    }
+
+    #[test]
+    fn list_by_min_quality() -> ServiceResult<()> {
+        let service = service()?;
+
+        assert_eq!(service.list(0)?.len(), 3);
+        assert_eq!(service.list(70)?.len(), 2);
+
+        let good = service.list(80)?;
+        assert_eq!(good.len(), 1);
+        assert_eq!(good[0].quality_score(), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_by_department() -> ServiceResult<()> {
+        let service = service()?;
+
+        let in_department = |min_quality, department| ListFilter {
+            min_quality,
+            department: Some(department),
+            ..Default::default()
+        };
+
+        let gironde = service.list_with(&in_department(0, "33"))?;
+        assert_eq!(gironde.len(), 1);
+        assert_eq!(gironde[0].postal_details.town, "MIOS");
+        assert_eq!(service.list_with(&in_department(80, "82"))?.len(), 0);
+        assert_eq!(service.list_with(&in_department(0, "2a"))?.len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_matching_filter() -> ServiceResult<()> {
+        let service = service()?;
+        let matching = |matching| {
+            service.list_with(&ListFilter {
+                matching,
+                ..Default::default()
+            })
+        };
+
+        let mios = matching(AddressFilter {
+            town: Some("mios".to_string()),
+            ..Default::default()
+        })?;
+        assert_eq!(mios.len(), 2);
+        let gironde = matching(AddressFilter {
+            postcode_prefix: Some("33".to_string()),
+            country: Some(Country::France),
+            kind: Some(AddressKind::Individual),
+            ..Default::default()
+        })?;
+        assert_eq!(gironde.len(), 1);
+        assert_eq!(gironde[0].postal_details.postcode, "33380");
+        let businesses = matching(AddressFilter {
+            postcode: Some("00100".to_string()),
+            kind: Some(AddressKind::Business),
+            ..Default::default()
+        })?;
+        assert_eq!(businesses.len(), 1);
+        let abroad = matching(AddressFilter {
+            country: Some(Country::Belgium),
+            ..Default::default()
+        })?;
+        assert!(abroad.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_without_archived() -> ServiceResult<()> {
+        let service = service()?;
+        let archived = service.list(0)?[0].id();
+        service.archive(archived)?;
+
+        assert_eq!(service.list(0)?.len(), 2);
+        let all = service.list_with(&ListFilter {
+            archived: true,
+            ..Default::default()
+        })?;
+        assert_eq!(all.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats_quality() -> ServiceResult<()> {
+        let service = service()?;
+
+        assert_eq!(
+            service.stats()?,
+            AddressStats {
+                total: 3,
+                individuals: 2,
+                businesses: 1,
+                average_quality: Some(65.0),
+                good_quality: 1,
+                poor_quality: 1,
+                regions: BTreeMap::from([(Region::NouvelleAquitaine, 1), (Region::Occitanie, 1),]),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats_empty() -> ServiceResult<()> {
+        let service = AddressService::new(InMemoryAddressRepository::new());
+
+        assert_eq!(service.stats()?, AddressStats::default());
+
+        Ok(())
+    }
+}
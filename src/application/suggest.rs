@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use strum::EnumString;
+
+use super::service::{AddressService, ServiceResult};
+use crate::domain::repositories::AddressRepository;
+use crate::domain::{Address, Recipient};
+
+/// The stored values that can be suggested.
+#[derive(Clone, Copy, Debug, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum SuggestField {
+    /// Street names, without the street number.
+    Street,
+    /// Town names.
+    Town,
+    /// Individual names and business names.
+    Recipient,
+}
+
+impl SuggestField {
+    fn value(&self, address: &Address) -> Option<String> {
+        match self {
+            SuggestField::Street => address.street.as_ref().map(|street| street.name.clone()),
+            SuggestField::Town => Some(address.postal_details.town.clone()),
+            SuggestField::Recipient => match &address.recipient {
+                Recipient::Individual { name } => Some(name.clone()),
+                Recipient::Business { company_name, .. } => Some(company_name.clone()),
+            },
+        }
+    }
+}
+
+/// A stored value matching the prefix.
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub value: String,
+    /// The number of stored addresses using this value.
+    pub count: usize,
+}
+
+impl<R: AddressRepository> AddressService<R> {
+    /// Returns the stored values of `field` starting with `prefix` (case
+    /// insensitive), the most frequent first. Values with the same frequency
+    /// are sorted alphabetically.
+    pub fn suggest(&self, prefix: &str, field: SuggestField) -> ServiceResult<Vec<Suggestion>> {
+        let prefix = prefix.to_lowercase();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        for address in self.repository.fetch_all()? {
+            if let Some(value) = field.value(&address) {
+                if value.to_lowercase().starts_with(&prefix) {
+                    *counts.entry(value).or_default() += 1;
+                }
+            }
+        }
+
+        let mut suggestions: Vec<Suggestion> = counts
+            .into_iter()
+            .map(|(value, count)| Suggestion { value, count })
+            .collect();
+        suggestions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+
+        Ok(suggestions)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::str::FromStr;
+
+    use super::{SuggestField, Suggestion};
+    use crate::application::service::{AddressService, Format, ServiceResult};
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    fn service() -> AddressService<InMemoryAddressRepository> {
+        let repo = InMemoryAddressRepository::new();
+        AddressService::new(repo)
+    }
+
+    #[test]
+    fn it_should_parse_field() {
+        assert_eq!(SuggestField::from_str("street"), Ok(SuggestField::Street));
+        assert_eq!(SuggestField::from_str("Town"), Ok(SuggestField::Town));
+        assert_eq!(
+            SuggestField::from_str("RECIPIENT"),
+            Ok(SuggestField::Recipient)
+        );
+        assert!(SuggestField::from_str("postcode").is_err());
+    }
+
+    #[test]
+    fn suggest_ranked_by_frequency() -> ServiceResult<()> {
+        let service = service();
+        let inputs = [
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            r#"{"name": "Madame Isabelle RICHARD", "street": "10 RUE DE LA PAIX", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            r#"{"name": "Monsieur Paul MARTIN", "street": "3 RUE DE LA PAIX", "postal": "33000 BORDEAUX", "country": "FRANCE"}"#,
+            r#"{"name": "Madame Lucie MARTIN", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+        ];
+        for input in inputs {
+            service.save(input, Format::French)?;
+        }
+
+        let streets = service.suggest("rue de", SuggestField::Street)?;
+        assert_eq!(
+            streets,
+            vec![
+                Suggestion {
+                    value: "RUE DE LA PAIX".to_string(),
+                    count: 2
+                },
+                Suggestion {
+                    value: "RUE DE L'EGLISE".to_string(),
+                    count: 1
+                },
+            ]
+        );
+
+        let towns = service.suggest("m", SuggestField::Town)?;
+        assert_eq!(towns.len(), 1);
+        assert_eq!(towns[0].count, 2);
+
+        let recipients = service.suggest("Madame", SuggestField::Recipient)?;
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[0].value, "Madame Isabelle RICHARD");
+
+        assert!(service.suggest("AVENUE", SuggestField::Street)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::repositories::{AddressRepository, AddressRepositoryError, RepositoryResult};
+use crate::infrastructure::CancellationToken;
+
+/// The outcome of a sync from one storage to another.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Identifiers of the addresses saved in the target.
+    pub created: Vec<Uuid>,
+    /// Identifiers of the addresses of the target replaced by a more recent
+    /// version.
+    pub updated: Vec<Uuid>,
+    /// Identifiers of the addresses left as they were: up to date or more
+    /// recent in the target, or a duplicate of another address of the target.
+    pub skipped: Vec<Uuid>,
+}
+
+/// Copies the addresses of `from` missing in `to`, and the ones updated more
+/// recently than their copy in `to`, e.g., to migrate to another backend.
+///
+/// The addresses are matched by ID. An address whose fingerprint is the one
+/// of another address of `to` is a duplicate, and isn't copied. A copy keeps
+/// the dates of the address, so that syncing both ways copies each address
+/// once, the most recently updated side winning.
+pub fn sync(
+    from: &dyn AddressRepository,
+    to: &dyn AddressRepository,
+) -> RepositoryResult<SyncReport> {
+    sync_with_cancellation(from, to, &CancellationToken::new())
+}
+
+/// Same as [`sync`], stopped between two addresses with
+/// [`AddressRepositoryError::Cancelled`] once `cancellation` is cancelled.
+/// The addresses already copied are kept.
+pub fn sync_with_cancellation(
+    from: &dyn AddressRepository,
+    to: &dyn AddressRepository,
+    cancellation: &CancellationToken,
+) -> RepositoryResult<SyncReport> {
+    let mut targets: HashMap<Uuid, _> = to
+        .fetch_all()?
+        .into_iter()
+        .map(|address| (address.id(), address))
+        .collect();
+
+    let mut report = SyncReport::default();
+    for address in from.fetch_all()? {
+        if cancellation.is_cancelled() {
+            return Err(AddressRepositoryError::Cancelled);
+        }
+        let id = address.id();
+        match targets.remove(&id) {
+            Some(stored) if stored.updated_at() >= address.updated_at() => report.skipped.push(id),
+            Some(stored) => {
+                to.update(address.superseding(&stored))?;
+                report.updated.push(id);
+            }
+            None => match to.save(address) {
+                Ok(_) => report.created.push(id),
+                Err(AddressRepositoryError::AlreadyExists(_)) => report.skipped.push(id),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sync, sync_with_cancellation};
+    use crate::domain::repositories::{AddressRepository, AddressRepositoryError};
+    use crate::domain::{Address, AddressConvertible, ConvertedAddress, FrenchAddress};
+    use crate::infrastructure::{CancellationToken, InMemoryAddressRepository};
+
+    fn address(name: &str, street: &str) -> Address {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "street": street,
+            "postal": "33380 MIOS",
+            "country": "FRANCE",
+        }))
+        .unwrap();
+
+        Address::new(ConvertedAddress::from_french(french).unwrap())
+    }
+
+    #[test]
+    fn it_should_keep_the_most_recent_versions() {
+        let (old, new) = (
+            InMemoryAddressRepository::new(),
+            InMemoryAddressRepository::new(),
+        );
+        let jean = address("Monsieur Jean DELHOURME", "25 RUE DE L'EGLISE");
+        let isabelle = address("Madame Isabelle RICHARD", "10 RUE DES PINS");
+        old.save(jean.clone()).unwrap();
+        old.save(isabelle.clone()).unwrap();
+        // The same address under another ID is a duplicate.
+        new.save(address("Madame Isabelle RICHARD", "10 RUE DES PINS"))
+            .unwrap();
+
+        let report = sync(&old, &new).unwrap();
+        assert_eq!(report.created, [jean.id()]);
+        assert_eq!(report.skipped, [isabelle.id()]);
+
+        // Jean moves in the new storage, then both are synced.
+        let mut moved = new.fetch(&jean.id().to_string()).unwrap();
+        moved.update(address("Monsieur Jean DELHOURME", "3 RUE DES LILAS").as_converted_address());
+        new.update(moved).unwrap();
+        assert!(sync(&old, &new).unwrap().updated.is_empty());
+        let report = sync(&new, &old).unwrap();
+        assert_eq!(report.updated, [jean.id()]);
+
+        let synced = old.fetch(&jean.id().to_string()).unwrap();
+        assert_eq!(synced.version(), 2);
+        assert_eq!(synced.street.unwrap().name, "RUE DES LILAS");
+        let report = sync(&old, &new).unwrap();
+        assert_eq!((report.created.len(), report.updated.len()), (0, 0));
+    }
+
+    #[test]
+    fn it_should_stop_once_cancelled() {
+        let (old, new) = (
+            InMemoryAddressRepository::new(),
+            InMemoryAddressRepository::new(),
+        );
+        old.save(address("Monsieur Jean DELHOURME", "25 RUE DE L'EGLISE"))
+            .unwrap();
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let result = sync_with_cancellation(&old, &new, &cancellation);
+        assert!(matches!(result, Err(AddressRepositoryError::Cancelled)));
+        assert!(new.fetch_all().unwrap().is_empty());
+    }
+}
@@ -1,8 +1,22 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use strum::EnumString;
 use uuid::Uuid;
 
+use super::address_conversion::AddressConversionError;
+use super::address_status::{AddressStatus, AddressStatusError};
+use super::fingerprint::DuplicateScope;
+use super::french_address::FrenchAddressParser;
+use super::french_department::OverseasTerritory;
+use super::org_identifier::OrgIdentifier;
+use super::street_number::{NumberExtStyle, StreetNumberExt};
+use super::street_type::StreetType;
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Address {
     /// The unique identifier of the address.
@@ -10,6 +24,32 @@ pub struct Address {
     /// Datetime in UTC of the last modification. Both creation and update dates
     /// are tracked with this field.
     updated_at: DateTime<Utc>,
+    /// Incremented by each update, so that an update based on a stale
+    /// version can be detected. Addresses stored before versioning start at 0.
+    #[serde(default)]
+    version: u64,
+    /// Active, archived or invalid. Changed by the status transitions only.
+    #[serde(default)]
+    status: AddressStatus,
+    /// Routing metadata of bulk mailers. Changed by mailing-house workflows
+    /// only, kept by the updates of the address.
+    #[serde(default, skip_serializing_if = "Routing::is_empty")]
+    routing: Routing,
+    /// Free-form notes about the address (e.g., "ring twice"). Kept by the
+    /// updates of the address and left out of its fingerprint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    /// Free-form key-value metadata (e.g., a CRM reference), kept like the
+    /// notes.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    /// The key the client sent with the save of the address, so that a
+    /// retried save returns this address instead of creating another one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idempotency_key: Option<IdempotencyKey>,
+    /// The human-friendly name of the address, accepted in place of its ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<Alias>,
     /// The type of address. Can be an individual or a business. This
     /// information is used for specific conversion rules depending on the type.
     pub kind: AddressKind,
@@ -27,6 +67,10 @@ pub struct Address {
     pub postal_details: PostalDetails,
     /// The address country.
     pub country: Country,
+    /// Unknown fields of the input, written back when the address is
+    /// fetched or exported.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub extras: Map<String, Value>,
 }
 
 impl Address {
@@ -41,17 +85,26 @@ impl Address {
             street,
             postal_details,
             country,
+            extras,
         } = converted_address;
 
         Address {
             id,
             updated_at,
+            version: 1,
+            status: AddressStatus::Active,
+            routing: Routing::default(),
+            notes: None,
+            metadata: BTreeMap::new(),
+            idempotency_key: None,
+            alias: None,
             kind,
             recipient,
             delivery_point,
             street,
             postal_details,
             country,
+            extras,
         }
     }
 
@@ -63,6 +116,125 @@ impl Address {
         self.updated_at
     }
 
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn status(&self) -> &AddressStatus {
+        &self.status
+    }
+
+    pub fn routing(&self) -> &Routing {
+        &self.routing
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    pub fn idempotency_key(&self) -> Option<&IdempotencyKey> {
+        self.idempotency_key.as_ref()
+    }
+
+    /// The address saved with the idempotency key `key`, received now.
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(IdempotencyKey {
+            key: key.into(),
+            received_at: Utc::now(),
+        });
+        self
+    }
+
+    /// The address named `alias`, when given, saved with it.
+    pub fn with_alias(mut self, alias: Option<Alias>) -> Self {
+        self.alias = alias;
+        self
+    }
+
+    /// The address copied over `stored`, its copy in another storage: as it
+    /// is, dates included, at the version following the stored one.
+    pub fn superseding(mut self, stored: &Address) -> Self {
+        self.version = stored.version + 1;
+        self
+    }
+
+    pub fn alias(&self) -> Option<&Alias> {
+        self.alias.as_ref()
+    }
+
+    /// Names the address with `alias`, or removes its alias. The rest of the
+    /// address is unchanged.
+    pub fn set_alias(&mut self, alias: Option<Alias>) {
+        self.alias = alias;
+        self.updated_at = Utc::now();
+        self.version += 1;
+    }
+
+    /// Replaces the routing metadata. The rest of the address is unchanged,
+    /// as well as its fingerprint.
+    pub fn set_routing(&mut self, routing: Routing) {
+        self.routing = routing;
+        self.updated_at = Utc::now();
+        self.version += 1;
+    }
+
+    /// Replaces the recipient, the kind of the address following it. The
+    /// postal details are unchanged.
+    pub fn set_recipient(&mut self, recipient: Recipient) {
+        self.kind = match recipient {
+            Recipient::Individual { .. } => AddressKind::Individual,
+            Recipient::Business { .. } => AddressKind::Business,
+        };
+        self.recipient = recipient;
+        self.updated_at = Utc::now();
+        self.version += 1;
+    }
+
+    /// Replaces the notes and metadata. The rest of the address is unchanged,
+    /// as well as its fingerprint.
+    pub fn annotate(&mut self, notes: Option<String>, metadata: BTreeMap<String, String>) {
+        self.notes = notes;
+        self.metadata = metadata;
+        self.updated_at = Utc::now();
+        self.version += 1;
+    }
+
+    /// Archives the address, whether active or invalid.
+    pub fn archive(&mut self) -> Result<(), AddressStatusError> {
+        self.transition("archive", AddressStatus::Archived)
+    }
+
+    /// Marks the address as invalid for `reason`, replacing the reason of an
+    /// invalid address. Archived addresses stay archived.
+    pub fn mark_invalid(&mut self, reason: impl Into<String>) -> Result<(), AddressStatusError> {
+        let reason = reason.into();
+        self.transition("mark invalid", AddressStatus::Invalid { reason })
+    }
+
+    fn transition(
+        &mut self,
+        action: &'static str,
+        status: AddressStatus,
+    ) -> Result<(), AddressStatusError> {
+        if self.status == AddressStatus::Archived {
+            return Err(AddressStatusError {
+                id: self.id.to_string(),
+                action,
+                status: self.status.clone(),
+            });
+        }
+
+        self.status = status;
+        self.updated_at = Utc::now();
+        self.version += 1;
+
+        Ok(())
+    }
+
     pub fn as_converted_address(&self) -> ConvertedAddress {
         ConvertedAddress {
             kind: self.kind.clone(),
@@ -71,11 +243,25 @@ impl Address {
             street: self.street.clone(),
             postal_details: self.postal_details.clone(),
             country: self.country.clone(),
+            extras: self.extras.clone(),
         }
     }
 
+    /// Two addresses are duplicates when they share the street, the postcode,
+    /// the country and, for the individuals, the recipient: when they have
+    /// the same [fingerprint](Address::fingerprint).
+    pub fn is_duplicate_of(&self, other: &Address) -> bool {
+        self.is_duplicate_in(other, DuplicateScope::default())
+    }
+
+    /// Same as [`Address::is_duplicate_of`], comparing what `scope` does.
+    pub fn is_duplicate_in(&self, other: &Address, scope: DuplicateScope) -> bool {
+        self.fingerprint_in(scope) == other.fingerprint_in(scope)
+    }
+
     pub fn update(&mut self, update: ConvertedAddress) {
         self.updated_at = Utc::now();
+        self.version += 1;
 
         let ConvertedAddress {
             kind,
@@ -84,6 +270,7 @@ impl Address {
             street,
             postal_details,
             country,
+            extras,
         } = update;
 
         self.kind = kind;
@@ -92,6 +279,92 @@ impl Address {
         self.street = street;
         self.postal_details = postal_details;
         self.country = country;
+        self.extras = extras;
+    }
+
+    /// Same as [`Address::update`], also replacing the routing, notes and
+    /// metadata, as a single new version.
+    pub fn update_all(
+        &mut self,
+        update: ConvertedAddress,
+        routing: Routing,
+        notes: Option<String>,
+        metadata: BTreeMap<String, String>,
+    ) {
+        self.update(update);
+        self.routing = routing;
+        self.notes = notes;
+        self.metadata = metadata;
+    }
+}
+
+/// The routing metadata of a stored address, annotated by bulk mailers.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Routing {
+    /// The La Poste sorting code of the address (code ROUDIS, tri).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_code: Option<String>,
+    /// The mailing batch the address belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+}
+
+impl Routing {
+    pub fn is_empty(&self) -> bool {
+        self.sort_code.is_none() && self.batch_id.is_none()
+    }
+}
+
+/// The key a client sent with the save of an address, see
+/// [`Address::with_idempotency_key`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IdempotencyKey {
+    pub key: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// The human-friendly name of a stored address (e.g., `hq-bordeaux`), unique
+/// among the addresses, see [`Address::set_alias`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Alias(String);
+
+impl Alias {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Aliases are made of 1 to 64 ASCII letters, digits, `-`, `_` or `.`. They
+/// can't be read as the beginning of an ID, so that they are made of more
+/// than hexadecimal digits and hyphens (`cafe-1` is refused).
+impl FromStr for Alias {
+    type Err = String;
+
+    fn from_str(alias: &str) -> Result<Self, Self::Err> {
+        let valid = (1..=64).contains(&alias.len())
+            && alias
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(format!(
+                "Invalid alias `{alias}`: expected 1 to 64 letters, digits, '-', '_' or '.'"
+            ));
+        }
+        if alias.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Err(format!(
+                "Invalid alias `{alias}`: it could be mistaken for the beginning of an ID"
+            ));
+        }
+
+        Ok(Self(alias.to_string()))
+    }
+}
+
+impl fmt::Display for Alias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
@@ -114,6 +387,8 @@ pub struct ConvertedAddress {
     pub postal_details: PostalDetails,
     /// The address country.
     pub country: Country,
+    /// Unknown fields of the input, passed through to the output.
+    pub extras: Map<String, Value>,
 }
 
 impl ConvertedAddress {
@@ -132,22 +407,31 @@ impl ConvertedAddress {
             street,
             postal_details,
             country,
+            extras: Map::new(),
         }
     }
+
+    /// Keeps the unknown fields of the input.
+    pub fn with_extras(mut self, extras: Map<String, Value>) -> Self {
+        self.extras = extras;
+        self
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, EnumString, PartialEq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 pub enum AddressKind {
     Individual,
     Business,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RecipientRecord")]
 pub enum Recipient {
     /// An individual recipient (M. John Doe, Mirabelle Prune)
     Individual { name: String },
     /// The recipient information of a business. Can be composed of both
-    /// the business denomination (or brand) and service name or contact
+    /// the business denomination (or brand), a contact person and a service
     ///
     /// # Example 1
     ///
@@ -160,21 +444,115 @@ pub enum Recipient {
     /// Service achat
     Business {
         company_name: String,
+        /// The person the mail is addressed to (Mademoiselle Lucie MARTIN).
+        contact_person: Option<String>,
+        /// The department or service line of the business (Service achat).
+        service: Option<String>,
+        /// Official identifiers of the business (SIREN, SIRET).
+        identifiers: Vec<OrgIdentifier>,
+    },
+}
+
+/// The stored form of [`Recipient`]. The addresses stored before the contact
+/// person and the service were told apart have a single `contact`, split
+/// with [`FrenchAddressParser::split_contact`].
+#[derive(Deserialize)]
+enum RecipientRecord {
+    Individual {
+        name: String,
+    },
+    Business {
+        company_name: String,
+        #[serde(default)]
+        contact_person: Option<String>,
+        #[serde(default)]
+        service: Option<String>,
+        #[serde(default)]
         contact: Option<String>,
+        #[serde(default)]
+        identifiers: Vec<OrgIdentifier>,
     },
 }
 
+impl From<RecipientRecord> for Recipient {
+    fn from(record: RecipientRecord) -> Self {
+        match record {
+            RecipientRecord::Individual { name } => Recipient::Individual { name },
+            RecipientRecord::Business {
+                company_name,
+                contact_person: None,
+                service: None,
+                contact: Some(contact),
+                identifiers,
+            } => Recipient::business(company_name, Some(&contact), identifiers),
+            RecipientRecord::Business {
+                company_name,
+                contact_person,
+                service,
+                identifiers,
+                ..
+            } => Recipient::Business {
+                company_name,
+                contact_person,
+                service,
+                identifiers,
+            },
+        }
+    }
+}
+
 impl Recipient {
+    /// A business whose contact line (the second line of a french address)
+    /// is split into the contact person and the service, see
+    /// [`FrenchAddressParser::split_contact`].
+    pub fn business(
+        company_name: String,
+        contact_line: Option<&str>,
+        identifiers: Vec<OrgIdentifier>,
+    ) -> Self {
+        let (contact_person, service) =
+            contact_line.map_or((None, None), FrenchAddressParser::split_contact);
+
+        Recipient::Business {
+            company_name,
+            contact_person: contact_person.map(str::to_string),
+            service: service.map(str::to_string),
+            identifiers,
+        }
+    }
+
+    /// The name of an individual, or the contact line of a business: its
+    /// contact person followed by its service.
     pub fn denomination(&self) -> Option<String> {
         match self {
-            Recipient::Business { contact, .. } => contact.clone(),
+            Recipient::Business {
+                contact_person,
+                service,
+                ..
+            } => match (contact_person, service) {
+                (Some(person), Some(service)) => Some(format!("{person} {service}")),
+                (person, service) => person.clone().or_else(|| service.clone()),
+            },
             Recipient::Individual { name } => Some(name.clone()),
         }
     }
+
+    /// The official identifiers of the recipient. Always empty for
+    /// individuals.
+    pub fn identifiers(&self) -> &[OrgIdentifier] {
+        match self {
+            Recipient::Business { identifiers, .. } => identifiers,
+            Recipient::Individual { .. } => &[],
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DeliveryPoint {
+    /// The person the mail is delivered through ("Mireille COPEAU" for
+    /// "Chez Mireille COPEAU").
+    #[serde(default)]
+    pub care_of: Option<String>,
     /// The external delivery point (building, entry, ...).
     pub external: Option<String>,
     /// The internal delivery point (appartment, staircase, ...).
@@ -185,12 +563,58 @@ pub struct DeliveryPoint {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Street {
-    /// The street number (2, 2BIS, 2D).
+    /// The street number (2, 25). Addresses stored before the repetition
+    /// indexes were read may have one in the number (2BIS, 2D).
     pub number: Option<String>,
-    /// The street name ("LE VILLAGE", "RUE DE L'EGLISE").
+    /// The repetition index following the number (BIS for "2 BIS").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repetition_index: Option<StreetNumberExt>,
+    /// The type the street name starts with, when it is a french street type
+    /// (`Rue` for "RUE DE L'EGLISE"). Addresses stored before the street
+    /// types were read don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<StreetType>,
+    /// The street name as written, with its type ("LE VILLAGE", "RUE DE
+    /// L'EGLISE").
     pub name: String,
 }
 
+impl Street {
+    /// A street whose repetition index is read from the end of `number`, see
+    /// [`StreetNumberExt::split_number`], and type from the start of `name`,
+    /// see [`StreetType::split`].
+    pub fn new(number: Option<String>, name: String) -> Self {
+        let (number, repetition_index) = match number {
+            Some(number) => match StreetNumberExt::split_number(&number) {
+                Some((digits, index)) => (Some(digits.to_string()), index),
+                None => (Some(number), None),
+            },
+            None => (None, None),
+        };
+
+        Self {
+            number,
+            repetition_index,
+            kind: StreetType::split(&name).map(|(kind, _)| kind),
+            name,
+        }
+    }
+
+    /// The number followed by its repetition index written with `style`
+    /// (e.g., `2BIS` or `2 BIS`).
+    pub fn full_number(&self, style: NumberExtStyle) -> Option<String> {
+        self.number
+            .as_ref()
+            .map(|number| style.write(number, self.repetition_index))
+    }
+
+    /// The name without its type ("DE L'EGLISE" for "RUE DE L'EGLISE"), the
+    /// whole name when it doesn't start with a street type.
+    pub fn name_without_kind(&self) -> &str {
+        StreetType::split(&self.name).map_or(&self.name, |(_, rest)| rest)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PostalDetails {
     /// The zipcode or postcode of the postal address (56000, K1A 0A6)
@@ -199,19 +623,325 @@ pub struct PostalDetails {
     pub town: String,
     /// Complementary town information for distribution.
     pub town_location: Option<String>,
+    /// The hamlet or lieu-dit ("LD LES GRANDS CHAMPS"). Rural addresses
+    /// often have a locality instead of a street.
+    #[serde(default)]
+    pub locality: Option<String>,
+    /// The state, province or region, required by some foreign addresses
+    /// (e.g., "서울특별시" in South Korea).
+    #[serde(default)]
+    pub country_subdivision: Option<String>,
 }
 
+/// The destination country. Parsed from its french name, its english name or
+/// its ISO 3166 alpha-2 or alpha-3 code, and displayed with its french name as required by
+/// NF Z10-011.
 #[derive(Clone, Debug, strum_macros::Display, EnumString, PartialEq, Serialize, Deserialize)]
 #[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
 pub enum Country {
-    #[strum(serialize = "FRANCE", serialize = "FR")]
+    #[strum(serialize = "FRANCE", serialize = "FR", serialize = "FRA")]
     France,
+    #[strum(
+        to_string = "ALLEMAGNE",
+        serialize = "GERMANY",
+        serialize = "DE",
+        serialize = "DEU"
+    )]
+    Germany,
+    #[strum(
+        to_string = "AUTRICHE",
+        serialize = "AUSTRIA",
+        serialize = "AT",
+        serialize = "AUT"
+    )]
+    Austria,
+    #[strum(
+        to_string = "BELGIQUE",
+        serialize = "BELGIUM",
+        serialize = "BE",
+        serialize = "BEL"
+    )]
+    Belgium,
+    #[strum(
+        to_string = "BRESIL",
+        serialize = "BRÉSIL",
+        serialize = "BRAZIL",
+        serialize = "BRASIL",
+        serialize = "BR",
+        serialize = "BRA"
+    )]
+    Brazil,
+    #[strum(to_string = "CANADA", serialize = "CA", serialize = "CAN")]
+    Canada,
+    #[strum(
+        to_string = "ESPAGNE",
+        serialize = "SPAIN",
+        serialize = "ES",
+        serialize = "ESP"
+    )]
+    Spain,
+    #[strum(
+        to_string = "ETATS-UNIS",
+        serialize = "ÉTATS-UNIS",
+        serialize = "UNITED STATES",
+        serialize = "US",
+        serialize = "USA"
+    )]
+    UnitedStates,
+    #[strum(
+        to_string = "ITALIE",
+        serialize = "ITALY",
+        serialize = "IT",
+        serialize = "ITA"
+    )]
+    Italy,
+    #[strum(to_string = "LUXEMBOURG", serialize = "LU", serialize = "LUX")]
+    Luxembourg,
+    #[strum(
+        to_string = "PAYS-BAS",
+        serialize = "NETHERLANDS",
+        serialize = "NL",
+        serialize = "NLD"
+    )]
+    Netherlands,
+    #[strum(to_string = "PORTUGAL", serialize = "PT", serialize = "PRT")]
+    Portugal,
+    #[strum(
+        to_string = "ROYAUME-UNI",
+        serialize = "UNITED KINGDOM",
+        serialize = "GB",
+        serialize = "GBR",
+        serialize = "UK"
+    )]
+    UnitedKingdom,
+    #[strum(
+        to_string = "SUISSE",
+        serialize = "SWITZERLAND",
+        serialize = "CH",
+        serialize = "CHE"
+    )]
+    Switzerland,
+    #[strum(
+        to_string = "COREE DU SUD",
+        serialize = "CORÉE DU SUD",
+        serialize = "SOUTH KOREA",
+        serialize = "REPUBLIC OF KOREA",
+        serialize = "KR",
+        serialize = "KOR"
+    )]
+    SouthKorea,
 }
 
 impl Country {
     pub fn iso_code(&self) -> &'static str {
         match self {
             Country::France => "FR",
+            Country::Germany => "DE",
+            Country::Austria => "AT",
+            Country::Belgium => "BE",
+            Country::Brazil => "BR",
+            Country::Canada => "CA",
+            Country::Spain => "ES",
+            Country::UnitedStates => "US",
+            Country::Italy => "IT",
+            Country::Luxembourg => "LU",
+            Country::Netherlands => "NL",
+            Country::Portugal => "PT",
+            Country::UnitedKingdom => "GB",
+            Country::Switzerland => "CH",
+            Country::SouthKorea => "KR",
+        }
+    }
+
+    pub fn iso_alpha3_code(&self) -> &'static str {
+        match self {
+            Country::France => "FRA",
+            Country::Germany => "DEU",
+            Country::Austria => "AUT",
+            Country::Belgium => "BEL",
+            Country::Brazil => "BRA",
+            Country::Canada => "CAN",
+            Country::Spain => "ESP",
+            Country::UnitedStates => "USA",
+            Country::Italy => "ITA",
+            Country::Luxembourg => "LUX",
+            Country::Netherlands => "NLD",
+            Country::Portugal => "PRT",
+            Country::UnitedKingdom => "GBR",
+            Country::Switzerland => "CHE",
+            Country::SouthKorea => "KOR",
+        }
+    }
+
+    pub fn english_name(&self) -> &'static str {
+        match self {
+            Country::France => "FRANCE",
+            Country::Germany => "GERMANY",
+            Country::Austria => "AUSTRIA",
+            Country::Belgium => "BELGIUM",
+            Country::Brazil => "BRAZIL",
+            Country::Canada => "CANADA",
+            Country::Spain => "SPAIN",
+            Country::UnitedStates => "UNITED STATES",
+            Country::Italy => "ITALY",
+            Country::Luxembourg => "LUXEMBOURG",
+            Country::Netherlands => "NETHERLANDS",
+            Country::Portugal => "PORTUGAL",
+            Country::UnitedKingdom => "UNITED KINGDOM",
+            Country::Switzerland => "SWITZERLAND",
+            Country::SouthKorea => "REPUBLIC OF KOREA",
+        }
+    }
+
+    /// Whether the postcode is written before the town on the postal line
+    /// ("33380 MIOS"), or after it ("LONDON SW1A 1AA").
+    pub fn postcode_first(&self) -> bool {
+        !matches!(
+            self,
+            Country::Canada | Country::UnitedStates | Country::UnitedKingdom | Country::SouthKorea
+        )
+    }
+
+    /// The country line of the address, written in uppercase. Mail sent from
+    /// France names the country in french (NF Z10-011), international mail in
+    /// english.
+    pub fn destination_line(&self, origin: MailOrigin) -> String {
+        match origin {
+            MailOrigin::France => self.to_string(),
+            MailOrigin::International => self.english_name().to_string(),
+        }
+    }
+}
+
+/// Where the mail is sent from, which decides the language of the country
+/// line.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum MailOrigin {
+    #[default]
+    France,
+    International,
+}
+
+/// How the country of the ISO 20022 addresses is written. ISO 20022 expects
+/// the alpha-2 code, some legacy consumers the alpha-3 code or the name.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum CountryRepr {
+    /// `FR`
+    #[default]
+    Alpha2,
+    /// `FRA`
+    Alpha3,
+    /// `FRANCE`, the english name.
+    FullName,
+}
+
+impl CountryRepr {
+    pub fn write(self, country: &Country) -> &'static str {
+        match self {
+            CountryRepr::Alpha2 => country.iso_code(),
+            CountryRepr::Alpha3 => country.iso_alpha3_code(),
+            CountryRepr::FullName => country.english_name(),
+        }
+    }
+
+    /// Same as [`CountryRepr::write`], for an overseas territory written as
+    /// the country, see [`OverseasCountry::Territory`].
+    pub fn write_territory(self, territory: OverseasTerritory) -> &'static str {
+        match self {
+            CountryRepr::Alpha2 => territory.iso_code(),
+            CountryRepr::Alpha3 => territory.iso_alpha3_code(),
+            CountryRepr::FullName => territory.english_name(),
+        }
+    }
+}
+
+/// The country of the french addresses overseas in ISO 20022. They are part
+/// of France, and have their own ISO 3166 code as well (e.g., `RE` for La
+/// Réunion), expected by some consumers.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum OverseasCountry {
+    /// `FR`, the territory being the subdivision (e.g., `FR-974`).
+    #[default]
+    France,
+    /// The code of the territory (e.g., `RE`).
+    Territory,
+}
+
+/// What becomes of the ISO 20022 elements longer than their type allows
+/// (e.g., 70 characters for the street name), the french delivery lines
+/// having no limit until they are printed.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum LongFieldPolicy {
+    /// Kept whole, with a warning.
+    #[default]
+    Keep,
+    /// The conversion fails.
+    Error,
+    /// Cut at the limit, with a warning.
+    Truncate,
+    /// Cut at the last space before the limit, the rest moved to the next
+    /// element when it's empty: the floor into the room, the department
+    /// into the sub-department. The other elements are truncated.
+    Wrap,
+}
+
+/// The settings of the ISO 20022 output, for the consumers departing from
+/// the standard.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IsoProfile {
+    pub country_repr: CountryRepr,
+    /// The country of the french addresses overseas.
+    pub overseas: OverseasCountry,
+    /// How the repetition index is written in the building number (`BldgNb`).
+    pub number_ext: NumberExtStyle,
+    /// What becomes of the elements too long for their type.
+    pub long_fields: LongFieldPolicy,
+}
+
+/// Whether a business address needs a street. A lieu-dit always replaces the
+/// street.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum StreetPolicy {
+    /// A street, or a lieu-dit, is required.
+    Required,
+    /// The street can be left out.
+    Optional,
+    /// A postbox replaces the street, as in the BP-only addresses of CEDEX
+    /// businesses.
+    #[default]
+    PostboxSubstitutes,
+}
+
+impl StreetPolicy {
+    /// Checks the street of a business address, reporting a missing street
+    /// at `field`. Individual addresses are not concerned.
+    pub fn check(
+        self,
+        address: &ConvertedAddress,
+        field: &str,
+    ) -> Result<(), AddressConversionError> {
+        let located = address.street.is_some() || address.postal_details.locality.is_some();
+        let postbox = address
+            .delivery_point
+            .as_ref()
+            .is_some_and(|delivery_point| delivery_point.postbox.is_some());
+
+        let allowed = match self {
+            _ if address.kind == AddressKind::Individual || located => true,
+            StreetPolicy::Required => false,
+            StreetPolicy::Optional => true,
+            StreetPolicy::PostboxSubstitutes => postbox,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(AddressConversionError::missing(field))
         }
     }
 }
@@ -231,6 +961,96 @@ pub mod tests {
         assert_eq!(Country::from_str("FR"), Ok(Country::France));
         assert_eq!(Country::France.to_string(), "FRANCE");
         assert_eq!(Country::France.iso_code(), "FR");
+
+        assert_eq!(Country::from_str("de"), Ok(Country::Germany));
+        assert_eq!(Country::from_str("Allemagne"), Ok(Country::Germany));
+        assert_eq!(Country::from_str("GERMANY"), Ok(Country::Germany));
+        assert_eq!(Country::from_str("UK"), Ok(Country::UnitedKingdom));
+        assert_eq!(Country::Germany.to_string(), "ALLEMAGNE");
+        assert_eq!(Country::UnitedKingdom.iso_code(), "GB");
+        assert_eq!(Country::from_str("coree du sud"), Ok(Country::SouthKorea));
+        assert_eq!(Country::from_str("KR"), Ok(Country::SouthKorea));
+        assert_eq!(Country::from_str("Autriche"), Ok(Country::Austria));
+        assert_eq!(Country::Austria.iso_alpha3_code(), "AUT");
+        assert!(Country::from_str("XX").is_err());
+    }
+
+    #[test]
+    fn it_should_write_destination_line() {
+        assert_eq!(
+            Country::Germany.destination_line(MailOrigin::France),
+            "ALLEMAGNE"
+        );
+        assert_eq!(
+            Country::Germany.destination_line(MailOrigin::International),
+            "GERMANY"
+        );
+        assert_eq!(
+            Country::France.destination_line(MailOrigin::International),
+            "FRANCE"
+        );
+    }
+
+    #[test]
+    fn it_should_split_lines() {
+        assert_eq!(
+            FrenchAddressParser::split_street("25 RUE DE L'EGLISE"),
+            Some((Some("25"), "RUE DE L'EGLISE"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_street("LE VILLAGE"),
+            Some((None, "LE VILLAGE"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_contact("Mademoiselle Lucie MARTIN - Service achat"),
+            (Some("Mademoiselle Lucie MARTIN"), Some("Service achat"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_contact("Direction des achats Monsieur DUPONT"),
+            (Some("Monsieur DUPONT"), Some("Direction des achats"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_contact("Service achat"),
+            (None, Some("Service achat"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_contact("Mademoiselle Lucie MARTIN"),
+            (Some("Mademoiselle Lucie MARTIN"), None)
+        );
+        assert_eq!(
+            FrenchAddressParser::split_street("2 BIS RUE DES TERRASSES"),
+            Some((Some("2 BIS"), "RUE DES TERRASSES"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_street("2 TERRASSES DU PORT"),
+            Some((Some("2"), "TERRASSES DU PORT"))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_postal("33380 MIOS"),
+            Some(("33380", "MIOS"))
+        );
+        assert_eq!(FrenchAddressParser::split_postal("MIOS"), None);
+        assert_eq!(
+            FrenchAddressParser::postbox("BP 90432 MONTFERRIER SUR LEZ"),
+            Some("BP 90432")
+        );
+        assert_eq!(
+            FrenchAddressParser::town_location("BP 90432 MONTFERRIER SUR LEZ"),
+            Some("MONTFERRIER SUR LEZ")
+        );
+        assert_eq!(FrenchAddressParser::town_location("BP 90432"), None);
+        assert_eq!(
+            FrenchAddressParser::split_business_name("Société DUPONT SIREN 732 829 320"),
+            ("Société DUPONT", Some(("SIREN", "732 829 320")))
+        );
+        assert_eq!(
+            FrenchAddressParser::split_business_name("Société DUPONT"),
+            ("Société DUPONT", None)
+        );
+        assert_eq!(
+            FrenchAddressParser::locality("LD LES GRANDS CHAMPS"),
+            Some("LES GRANDS CHAMPS")
+        );
     }
 
     mod individual_tests {
@@ -245,20 +1065,26 @@ pub mod tests {
                     name: "Monsieur Jean DELHOURME".to_string(),
                 },
                 delivery_point: Some(DeliveryPoint {
+                    care_of: None,
                     internal: Some("Chez Mireille COPEAU Appartement 2".to_string()),
                     external: Some("Entrée A Bâtiment Jonquille".to_string()),
                     postbox: Some("CAUDOS".to_string()),
                 }),
                 street: Some(Street {
                     number: Some("25".to_string()),
+                    kind: Some(StreetType::Rue),
+                    repetition_index: None,
                     name: "RUE DE L'EGLISE".to_string(),
                 }),
                 postal_details: PostalDetails {
                     postcode: "33380".to_string(),
                     town: "MIOS".to_string(),
                     town_location: None,
+                    locality: None,
+                    country_subdivision: None,
                 },
                 country: Country::France,
+                extras: Map::new(),
             };
 
             let expected = FrenchAddress::Individual(IndividualFrenchAddress {
@@ -269,6 +1095,7 @@ pub mod tests {
                 distribution_info: Some("CAUDOS".to_string()),
                 postal: "33380 MIOS".to_string(),
                 country: "FRANCE".to_string(),
+                extras: Map::new(),
             });
 
             assert!(address.to_french().is_ok());
@@ -283,36 +1110,46 @@ pub mod tests {
                     name: "Monsieur Jean DELHOURME".to_string(),
                 },
                 delivery_point: Some(DeliveryPoint {
+                    care_of: None,
                     internal: Some("Chez Mireille COPEAU Appartement 2".to_string()),
                     external: Some("Entrée A Bâtiment Jonquille".to_string()),
                     postbox: Some("CAUDOS".to_string()),
                 }),
                 street: Some(Street {
                     number: Some("25".to_string()),
+                    kind: Some(StreetType::Rue),
+                    repetition_index: None,
                     name: "RUE DE L'EGLISE".to_string(),
                 }),
                 postal_details: PostalDetails {
                     postcode: "33380".to_string(),
                     town: "MIOS".to_string(),
                     town_location: None,
+                    locality: None,
+                    country_subdivision: None,
                 },
                 country: Country::France,
+                extras: Map::new(),
             };
 
             let expected = IsoAddress::IndividualIsoAddress {
                 name: "Monsieur Jean DELHOURME".to_string(),
                 postal_address: IsoPostalAddress {
+                    care_of: None,
                     street_name: Some("RUE DE L'EGLISE".to_string()),
                     building_number: Some("25".to_string()),
                     floor: Some("Entrée A Bâtiment Jonquille".to_string()),
                     room: Some("Chez Mireille COPEAU Appartement 2".to_string()),
                     postbox: Some("CAUDOS".to_string()),
                     department: None,
+                    sub_department: None,
                     postcode: "33380".to_string(),
                     town_name: "MIOS".to_string(),
                     town_location_name: None,
+                    country_sub_division: None,
                     country: "FR".to_string(),
                 },
+                extras: Map::new(),
             };
 
             assert!(address.to_iso20022().is_ok());
@@ -327,20 +1164,26 @@ pub mod tests {
                     name: "Madame Isabelle RICHARD".to_string(),
                 },
                 delivery_point: Some(DeliveryPoint {
+                    care_of: None,
                     internal: None,
                     external: Some("VILLA BEAU SOLEIL".to_string()),
                     postbox: None,
                 }),
                 street: Some(Street {
                     number: None,
+                    kind: None,
+                    repetition_index: None,
                     name: "LE VILLAGE".to_string(),
                 }),
                 postal_details: PostalDetails {
                     postcode: "82500".to_string(),
                     town: "AUTERIVE".to_string(),
                     town_location: None,
+                    locality: None,
+                    country_subdivision: None,
                 },
                 country: Country::France,
+                extras: Map::new(),
             };
 
             let expected = FrenchAddress::Individual(IndividualFrenchAddress {
@@ -351,6 +1194,7 @@ pub mod tests {
                 distribution_info: None,
                 postal: "82500 AUTERIVE".to_string(),
                 country: "FRANCE".to_string(),
+                extras: Map::new(),
             });
 
             assert!(address.to_french().is_ok());
@@ -365,36 +1209,46 @@ pub mod tests {
                     name: "Madame Isabelle RICHARD".to_string(),
                 },
                 delivery_point: Some(DeliveryPoint {
+                    care_of: None,
                     internal: None,
                     external: Some("VILLA BEAU SOLEIL".to_string()),
                     postbox: None,
                 }),
                 street: Some(Street {
                     number: None,
+                    kind: None,
+                    repetition_index: None,
                     name: "LE VILLAGE".to_string(),
                 }),
                 postal_details: PostalDetails {
                     postcode: "82500".to_string(),
                     town: "AUTERIVE".to_string(),
                     town_location: None,
+                    locality: None,
+                    country_subdivision: None,
                 },
                 country: Country::France,
+                extras: Map::new(),
             };
 
             let expected = IsoAddress::IndividualIsoAddress {
                 name: "Madame Isabelle RICHARD".to_string(),
                 postal_address: IsoPostalAddress {
+                    care_of: None,
                     street_name: Some("LE VILLAGE".to_string()),
                     building_number: None,
                     floor: Some("VILLA BEAU SOLEIL".to_string()),
                     room: None,
                     postbox: None,
                     department: None,
+                    sub_department: None,
                     postcode: "82500".to_string(),
                     town_name: "AUTERIVE".to_string(),
                     town_location_name: None,
+                    country_sub_division: None,
                     country: "FR".to_string(),
                 },
+                extras: Map::new(),
             };
 
             assert!(address.to_iso20022().is_ok());
@@ -413,33 +1267,43 @@ pub mod tests {
                 kind: AddressKind::Business,
                 recipient: Recipient::Business {
                     company_name: "Société DUPONT".to_string(),
-                    contact: Some("Mademoiselle Lucie MARTIN".to_string()),
+                    contact_person: Some("Mademoiselle Lucie MARTIN".to_string()),
+                    service: None,
+                    identifiers: vec![],
                 },
                 delivery_point: Some(DeliveryPoint {
+                    care_of: None,
                     internal: None,
                     external: Some("Résidence des Capucins Bâtiment Quater".to_string()),
                     postbox: Some("BP 90432".to_string()),
                 }),
                 street: Some(Street {
                     number: Some("56".to_string()),
+                    kind: Some(StreetType::Rue),
+                    repetition_index: None,
                     name: "RUE EMILE ZOLA".to_string(),
                 }),
                 postal_details: PostalDetails {
                     postcode: "34092".to_string(),
                     town: "MONTPELLIER CEDEX 5".to_string(),
                     town_location: Some("MONTFERRIER SUR LEZ".to_string()),
+                    locality: None,
+                    country_subdivision: None,
                 },
                 country: Country::France,
+                extras: Map::new(),
             };
 
             let expected = FrenchAddress::Business(BusinessFrenchAddress {
                 business_name: "Société DUPONT".to_string(),
+                identifiers: vec![],
                 recipient: Some("Mademoiselle Lucie MARTIN".to_string()),
                 external_delivery: Some("Résidence des Capucins Bâtiment Quater".to_string()),
-                street: "56 RUE EMILE ZOLA".to_string(),
+                street: Some("56 RUE EMILE ZOLA".to_string()),
                 distribution_info: Some("BP 90432 MONTFERRIER SUR LEZ".to_string()),
                 postal: "34092 MONTPELLIER CEDEX 5".to_string(),
                 country: "FRANCE".to_string(),
+                extras: Map::new(),
             });
 
             assert!(address.to_french().is_ok());
@@ -452,43 +1316,381 @@ pub mod tests {
                 kind: AddressKind::Business,
                 recipient: Recipient::Business {
                     company_name: "Société DUPONT".to_string(),
-                    contact: Some("Mademoiselle Lucie MARTIN".to_string()),
+                    contact_person: Some("Mademoiselle Lucie MARTIN".to_string()),
+                    service: None,
+                    identifiers: vec![],
                 },
                 delivery_point: Some(DeliveryPoint {
+                    care_of: None,
                     internal: None,
                     external: Some("Résidence des Capucins Bâtiment Quater".to_string()),
                     postbox: Some("BP 90432".to_string()),
                 }),
                 street: Some(Street {
                     number: Some("56".to_string()),
+                    kind: Some(StreetType::Rue),
+                    repetition_index: None,
                     name: "RUE EMILE ZOLA".to_string(),
                 }),
                 postal_details: PostalDetails {
                     postcode: "34092".to_string(),
                     town: "MONTPELLIER CEDEX 5".to_string(),
                     town_location: Some("MONTFERRIER SUR LEZ".to_string()),
+                    locality: None,
+                    country_subdivision: None,
                 },
                 country: Country::France,
+                extras: Map::new(),
             };
 
             let expected = IsoAddress::BusinessIsoAddress {
                 business_name: "Société DUPONT".to_string(),
+                organisation_identification: vec![],
                 postal_address: IsoPostalAddress {
+                    care_of: None,
                     street_name: Some("RUE EMILE ZOLA".to_string()),
                     building_number: Some("56".to_string()),
                     floor: Some("Résidence des Capucins Bâtiment Quater".to_string()),
                     room: None,
                     postbox: Some("BP 90432".to_string()),
-                    department: Some("Mademoiselle Lucie MARTIN".to_string()),
+                    department: None,
+                    sub_department: Some("Mademoiselle Lucie MARTIN".to_string()),
                     postcode: "34092".to_string(),
                     town_name: "MONTPELLIER CEDEX 5".to_string(),
                     town_location_name: Some("MONTFERRIER SUR LEZ".to_string()),
+                    country_sub_division: None,
                     country: "FR".to_string(),
                 },
+                extras: Map::new(),
             };
 
             assert!(address.to_iso20022().is_ok());
             assert_eq!(address.to_iso20022().unwrap(), expected);
         }
     }
+
+    mod locality_tests {
+        use crate::domain::iso20022_address::{IsoAddress, IsoPostalAddress};
+
+        use super::*;
+
+        fn locality_address() -> ConvertedAddress {
+            ConvertedAddress {
+                kind: AddressKind::Individual,
+                recipient: Recipient::Individual {
+                    name: "Madame Lucie MARTIN".to_string(),
+                },
+                delivery_point: None,
+                street: None,
+                postal_details: PostalDetails {
+                    postcode: "82500".to_string(),
+                    town: "AUTERIVE".to_string(),
+                    town_location: None,
+                    locality: Some("LES GRANDS CHAMPS".to_string()),
+                    country_subdivision: None,
+                },
+                country: Country::France,
+                extras: Map::new(),
+            }
+        }
+
+        #[test]
+        fn it_should_parse_locality() {
+            for line in [
+                "LD LES GRANDS CHAMPS",
+                "L.D. LES GRANDS CHAMPS",
+                "LIEU DIT LES GRANDS CHAMPS",
+                "Lieu-dit LES GRANDS CHAMPS",
+            ] {
+                assert_eq!(
+                    FrenchAddressParser::parse_locality(line),
+                    Some("LES GRANDS CHAMPS".to_string())
+                );
+            }
+            assert_eq!(FrenchAddressParser::parse_locality("LE VILLAGE"), None);
+            assert_eq!(FrenchAddressParser::parse_locality("LDLES CHAMPS"), None);
+        }
+
+        #[test]
+        fn it_should_parse_care_of() {
+            assert_eq!(
+                FrenchAddressParser::care_of("Chez Mireille COPEAU Appartement 2"),
+                Some(("Mireille COPEAU", Some("Appartement 2")))
+            );
+            assert_eq!(
+                FrenchAddressParser::care_of("C/O Mireille COPEAU"),
+                Some(("Mireille COPEAU", None))
+            );
+            assert_eq!(FrenchAddressParser::care_of("Appartement 2"), None);
+        }
+
+        #[test]
+        fn locality_from_french() {
+            let french = FrenchAddress::Individual(IndividualFrenchAddress {
+                name: "Madame Lucie MARTIN".to_string(),
+                internal_delivery: None,
+                external_delivery: None,
+                street: Some("LD LES GRANDS CHAMPS".to_string()),
+                distribution_info: None,
+                postal: "82500 AUTERIVE".to_string(),
+                country: "FRANCE".to_string(),
+                extras: Map::new(),
+            });
+
+            assert_eq!(
+                ConvertedAddress::from_french(french).unwrap(),
+                locality_address()
+            );
+        }
+
+        #[test]
+        fn locality_with_street_from_french() {
+            let french = FrenchAddress::Individual(IndividualFrenchAddress {
+                name: "Madame Lucie MARTIN".to_string(),
+                internal_delivery: None,
+                external_delivery: None,
+                street: Some("2 CHEMIN DES VIGNES".to_string()),
+                distribution_info: Some("LIEU DIT LES GRANDS CHAMPS".to_string()),
+                postal: "82500 AUTERIVE".to_string(),
+                country: "FRANCE".to_string(),
+                extras: Map::new(),
+            });
+
+            let address = ConvertedAddress::from_french(french).unwrap();
+            assert_eq!(address.street.unwrap().name, "CHEMIN DES VIGNES");
+            assert_eq!(address.delivery_point, None);
+            assert_eq!(
+                address.postal_details.locality.unwrap(),
+                "LES GRANDS CHAMPS"
+            );
+        }
+
+        #[test]
+        fn locality_to_french() {
+            let expected = FrenchAddress::Individual(IndividualFrenchAddress {
+                name: "Madame Lucie MARTIN".to_string(),
+                internal_delivery: None,
+                external_delivery: None,
+                street: Some("LIEU DIT LES GRANDS CHAMPS".to_string()),
+                distribution_info: None,
+                postal: "82500 AUTERIVE".to_string(),
+                country: "FRANCE".to_string(),
+                extras: Map::new(),
+            });
+
+            assert_eq!(locality_address().to_french().unwrap(), expected);
+        }
+
+        #[test]
+        fn locality_iso20022_round_trip() {
+            let expected = IsoAddress::IndividualIsoAddress {
+                name: "Madame Lucie MARTIN".to_string(),
+                postal_address: IsoPostalAddress {
+                    care_of: None,
+                    street_name: None,
+                    building_number: None,
+                    floor: None,
+                    room: None,
+                    postbox: None,
+                    department: None,
+                    sub_department: None,
+                    postcode: "82500".to_string(),
+                    town_name: "AUTERIVE".to_string(),
+                    town_location_name: Some("LES GRANDS CHAMPS".to_string()),
+                    country_sub_division: None,
+                    country: "FR".to_string(),
+                },
+                extras: Map::new(),
+            };
+
+            let iso = locality_address().to_iso20022().unwrap();
+            assert_eq!(iso, expected);
+
+            let address = ConvertedAddress::from_iso20022(iso).unwrap();
+            assert_eq!(address.street, None);
+            assert_eq!(
+                address.postal_details.locality.unwrap(),
+                "LES GRANDS CHAMPS"
+            );
+        }
+    }
+
+    mod repetition_index_tests {
+        use crate::domain::iso20022_address::IsoAddress;
+
+        use super::*;
+
+        fn french_address(street: &str) -> FrenchAddress {
+            FrenchAddress::Individual(IndividualFrenchAddress {
+                name: "Monsieur Jean DELAFONTAINE".to_string(),
+                internal_delivery: None,
+                external_delivery: None,
+                street: Some(street.to_string()),
+                distribution_info: None,
+                postal: "33380 MIOS".to_string(),
+                country: "FRANCE".to_string(),
+                extras: Map::new(),
+            })
+        }
+
+        fn building_number(iso: IsoAddress) -> Option<String> {
+            match iso {
+                IsoAddress::IndividualIsoAddress { postal_address, .. } => {
+                    postal_address.building_number
+                }
+                IsoAddress::BusinessIsoAddress { postal_address, .. } => {
+                    postal_address.building_number
+                }
+            }
+        }
+
+        #[test]
+        fn it_should_parse_the_repetition_index() {
+            for line in ["2BIS RUE DES LILAS", "2 bis RUE DES LILAS"] {
+                let address = ConvertedAddress::from_french(french_address(line)).unwrap();
+                let street = address.street.unwrap();
+
+                assert_eq!(street.number.as_deref(), Some("2"));
+                assert_eq!(street.repetition_index, Some(StreetNumberExt::Bis));
+                assert_eq!(street.name, "RUE DES LILAS");
+            }
+        }
+
+        #[test]
+        fn it_should_write_the_repetition_index() {
+            let address =
+                ConvertedAddress::from_french(french_address("2BIS RUE DES LILAS")).unwrap();
+
+            assert_eq!(
+                address.to_french().unwrap(),
+                french_address("2 BIS RUE DES LILAS")
+            );
+            assert_eq!(
+                building_number(address.to_iso20022().unwrap()).as_deref(),
+                Some("2BIS")
+            );
+            let profile = IsoProfile {
+                number_ext: NumberExtStyle::Spaced,
+                ..Default::default()
+            };
+            assert_eq!(
+                building_number(address.to_iso20022_with(profile).unwrap()).as_deref(),
+                Some("2 BIS")
+            );
+        }
+
+        #[test]
+        fn it_should_read_the_repetition_index_from_iso20022() {
+            let address =
+                ConvertedAddress::from_french(french_address("14C AVENUE DES PINS")).unwrap();
+            let iso = address.to_iso20022().unwrap();
+            let street = ConvertedAddress::from_iso20022(iso)
+                .unwrap()
+                .street
+                .unwrap();
+
+            assert_eq!(street.number.as_deref(), Some("14"));
+            assert_eq!(street.repetition_index, Some(StreetNumberExt::Letter('C')));
+        }
+    }
+
+    mod contact_tests {
+        use crate::domain::iso20022_address::IsoAddress;
+
+        use super::*;
+
+        fn french_address(recipient: &str) -> FrenchAddress {
+            FrenchAddress::Business(BusinessFrenchAddress {
+                business_name: "DURAND SA".to_string(),
+                identifiers: vec![],
+                recipient: Some(recipient.to_string()),
+                external_delivery: None,
+                street: Some("56 RUE EMILE ZOLA".to_string()),
+                distribution_info: None,
+                postal: "34092 MONTPELLIER CEDEX 5".to_string(),
+                country: "FRANCE".to_string(),
+                extras: Map::new(),
+            })
+        }
+
+        #[test]
+        fn it_should_split_the_contact_line() {
+            let french = french_address("Mademoiselle Lucie MARTIN Service achat");
+            let address = ConvertedAddress::from_french(french.clone()).unwrap();
+            let Recipient::Business {
+                contact_person,
+                service,
+                ..
+            } = &address.recipient
+            else {
+                panic!("expected a business recipient");
+            };
+            assert_eq!(contact_person.as_deref(), Some("Mademoiselle Lucie MARTIN"));
+            assert_eq!(service.as_deref(), Some("Service achat"));
+            assert_eq!(address.to_french().unwrap(), french);
+
+            let IsoAddress::BusinessIsoAddress { postal_address, .. } =
+                address.to_iso20022().unwrap()
+            else {
+                panic!("expected a business ISO 20022 address");
+            };
+            assert_eq!(postal_address.department.as_deref(), Some("Service achat"));
+            assert_eq!(
+                postal_address.sub_department.as_deref(),
+                Some("Mademoiselle Lucie MARTIN")
+            );
+        }
+
+        #[test]
+        fn it_should_read_the_department_alone_as_the_service() {
+            let iso: IsoAddress = serde_json::from_value(serde_json::json!({
+                "business_name": "DURAND SA",
+                "postal_address": {
+                    "department": "Comptabilité",
+                    "postcode": "34092",
+                    "town_name": "MONTPELLIER CEDEX 5",
+                    "country": "FR"
+                }
+            }))
+            .unwrap();
+            let address = ConvertedAddress::from_iso20022(iso).unwrap();
+
+            assert_eq!(
+                address.recipient,
+                Recipient::Business {
+                    company_name: "DURAND SA".to_string(),
+                    contact_person: None,
+                    service: Some("Comptabilité".to_string()),
+                    identifiers: vec![],
+                }
+            );
+        }
+
+        #[test]
+        fn it_should_read_the_contact_of_stored_addresses() {
+            let recipient: Recipient = serde_json::from_value(serde_json::json!({
+                "Business": {
+                    "company_name": "DURAND SA",
+                    "contact": "Service achat Monsieur DUPONT",
+                    "identifiers": []
+                }
+            }))
+            .unwrap();
+
+            assert_eq!(
+                recipient,
+                Recipient::Business {
+                    company_name: "DURAND SA".to_string(),
+                    contact_person: Some("Monsieur DUPONT".to_string()),
+                    service: Some("Service achat".to_string()),
+                    identifiers: vec![],
+                }
+            );
+            let stored = serde_json::to_value(&recipient).unwrap();
+            assert_eq!(
+                serde_json::from_value::<Recipient>(stored).unwrap(),
+                recipient
+            );
+        }
+    }
 }
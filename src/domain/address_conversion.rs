@@ -1,16 +1,126 @@
+use std::borrow::Cow;
 use std::str::FromStr;
 use thiserror::Error;
 
 use super::address::*;
+use super::borrowed_address::*;
+use super::conversion_warnings::fit_iso_lengths;
+use super::country_profile::CountryProfile;
 use super::french_address::*;
+use super::french_department::OverseasTerritory;
 use super::iso20022_address::*;
-
-#[derive(Debug, Error)]
+use super::org_identifier::OrgIdentifier;
+use super::person_name::PersonName;
+use crate::error::ErrorKind;
+
+/// An address conversion failure. Every variant carries the path of the
+/// offending field in the input (e.g., `postal_address.country`) and has a
+/// stable [code](AddressConversionError::code) for programmatic handling.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[non_exhaustive]
 pub enum AddressConversionError {
-    #[error("Missing required field `{0}`")]
-    MissingField(String),
-    #[error("Invalid format: `{0}`")]
-    InvalidFormat(String),
+    #[error("Missing required field `{field}`")]
+    MissingField { field: String },
+    #[error("Invalid street `{value}` in `{field}`: {reason}")]
+    InvalidStreet {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    #[error("Invalid postal information `{value}` in `{field}`: {reason}")]
+    InvalidPostal {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    #[error("Unknown country `{value}` in `{field}`")]
+    UnknownCountry { field: String, value: String },
+    #[error("Invalid organisation identifier `{value}` in `{field}`: {reason}")]
+    InvalidIdentifier {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    #[error("Invalid value `{value}` in `{field}`: {reason}")]
+    InvalidValue {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    #[error("Value `{value}` of `{field}` exceeds {max} characters")]
+    TooLong {
+        field: String,
+        value: String,
+        max: usize,
+    },
+}
+
+impl AddressConversionError {
+    pub fn missing(field: impl Into<String>) -> Self {
+        Self::MissingField {
+            field: field.into(),
+        }
+    }
+
+    /// The stable code of the error, e.g., `E_POSTAL_FORMAT`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingField { .. } => "E_MISSING_FIELD",
+            Self::InvalidStreet { .. } => "E_STREET_FORMAT",
+            Self::InvalidPostal { .. } => "E_POSTAL_FORMAT",
+            Self::UnknownCountry { .. } => "E_UNKNOWN_COUNTRY",
+            Self::InvalidIdentifier { .. } => "E_ORG_IDENTIFIER",
+            Self::InvalidValue { .. } => "E_INVALID_VALUE",
+            Self::TooLong { .. } => "E_TOO_LONG",
+        }
+    }
+
+    /// The category of the error, always [`ErrorKind::InvalidAddress`].
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::InvalidAddress
+    }
+
+    /// The path of the offending field in the input.
+    pub fn field(&self) -> &str {
+        match self {
+            Self::MissingField { field }
+            | Self::InvalidStreet { field, .. }
+            | Self::InvalidPostal { field, .. }
+            | Self::UnknownCountry { field, .. }
+            | Self::InvalidIdentifier { field, .. }
+            | Self::InvalidValue { field, .. }
+            | Self::TooLong { field, .. } => field,
+        }
+    }
+
+    /// The offending value, if the field was given.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            Self::MissingField { .. } => None,
+            Self::InvalidStreet { value, .. }
+            | Self::InvalidPostal { value, .. }
+            | Self::UnknownCountry { value, .. }
+            | Self::InvalidIdentifier { value, .. }
+            | Self::InvalidValue { value, .. }
+            | Self::TooLong { value, .. } => Some(value),
+        }
+    }
+
+    /// Replaces the field path, for parsers unaware of where their input
+    /// comes from (e.g., `identifiers[1]`).
+    pub fn at(mut self, path: impl Into<String>) -> Self {
+        match &mut self {
+            Self::MissingField { field }
+            | Self::InvalidStreet { field, .. }
+            | Self::InvalidPostal { field, .. }
+            | Self::UnknownCountry { field, .. }
+            | Self::InvalidIdentifier { field, .. }
+            | Self::InvalidValue { field, .. }
+            | Self::TooLong { field, .. } => *field = path.into(),
+        }
+
+        self
+    }
 }
 
 /// A trait representing the conversion rules for any convertible address.
@@ -23,65 +133,96 @@ pub trait AddressConvertible {
     fn from_iso20022(address: IsoAddress) -> Result<Self, AddressConversionError>
     where
         Self: Sized;
-    /// Converts the address into the french standard NF Z10-011.
-    fn to_french(&self) -> Result<FrenchAddress, AddressConversionError>;
+    /// Converts the address into the french standard NF Z10-011, for mail
+    /// sent from France.
+    fn to_french(&self) -> Result<FrenchAddress, AddressConversionError> {
+        self.to_french_from(MailOrigin::France)
+    }
+    /// Converts the address into the french standard NF Z10-011, for mail
+    /// sent from `origin`. Only the country line depends on the origin.
+    fn to_french_from(&self, origin: MailOrigin) -> Result<FrenchAddress, AddressConversionError>;
     /// Converts the address into the ISO 20022 standard.
-    fn to_iso20022(&self) -> Result<IsoAddress, AddressConversionError>;
+    fn to_iso20022(&self) -> Result<IsoAddress, AddressConversionError> {
+        self.to_iso20022_with(IsoProfile::default())
+    }
+    /// Converts the address into the ISO 20022 standard, departing from it as
+    /// set by `profile`.
+    fn to_iso20022_with(&self, profile: IsoProfile) -> Result<IsoAddress, AddressConversionError>;
 }
 
 impl AddressConvertible for ConvertedAddress {
-    fn to_french(&self) -> Result<FrenchAddress, AddressConversionError> {
+    fn to_french_from(&self, origin: MailOrigin) -> Result<FrenchAddress, AddressConversionError> {
+        let street_line = || {
+            self.street
+                .as_ref()
+                .map(|street| CountryProfile::of(&self.country).street_line(street))
+        };
+        let locality_line = || {
+            self.postal_details
+                .locality
+                .as_ref()
+                .map(|locality| format!("{LOCALITY_PREFIX} {locality}"))
+        };
+
         let distribution_info = || {
-            self.delivery_point.as_ref().map_or_else(
-                || None,
-                |delivery_point| {
-                    let (town_location, postbox) = (
-                        self.postal_details.town_location.clone(),
-                        delivery_point.postbox.clone(),
-                    );
-
-                    match (postbox, town_location) {
-                        (None, None) => None,
-                        (None, Some(town_location)) => Some(town_location),
-                        (Some(postbox), None) => Some(postbox),
-                        (Some(postbox), Some(town_location)) => {
-                            Some(format!("{postbox} {town_location}"))
-                        }
-                    }
-                },
-            )
+            let postbox = self
+                .delivery_point
+                .as_ref()
+                .and_then(|delivery_point| delivery_point.postbox.clone());
+            // The lieu-dit takes the street line when there is no street.
+            let locality = self.street.as_ref().and_then(|_| locality_line());
+            let details: Vec<String> =
+                [postbox, locality, self.postal_details.town_location.clone()]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+
+            (!details.is_empty()).then(|| details.join(" "))
+        };
+
+        // The care of is written first on the delivery line it shares.
+        let with_care_of = |line: Option<String>| {
+            let care_of = self
+                .delivery_point
+                .as_ref()
+                .and_then(|delivery_point| delivery_point.care_of.as_ref())
+                .map(|care_of| format!("{CARE_OF_PREFIX} {care_of}"));
+
+            match (care_of, line) {
+                (Some(care_of), Some(line)) => Some(format!("{care_of} {line}")),
+                (care_of, line) => care_of.or(line),
+            }
         };
 
         let postal_info = || {
-            format!(
-                "{} {}",
-                self.postal_details.postcode, self.postal_details.town
-            )
+            let PostalDetails { postcode, town, .. } = &self.postal_details;
+
+            if self.country.postcode_first() {
+                format!("{postcode} {town}")
+            } else {
+                format!("{town} {postcode}")
+            }
         };
 
         match &self.kind {
             AddressKind::Individual => {
                 let name = match self.recipient.denomination() {
                     Some(name) if !name.is_empty() => name,
-                    _ => return Err(AddressConversionError::MissingField("name".to_string())),
+                    _ => return Err(AddressConversionError::missing("name")),
                 };
 
-                let internal_delivery = self
-                    .delivery_point
-                    .as_ref()
-                    .map_or_else(|| None, |delivery_point| delivery_point.internal.clone());
+                let internal_delivery = with_care_of(
+                    self.delivery_point
+                        .as_ref()
+                        .map_or_else(|| None, |delivery_point| delivery_point.internal.clone()),
+                );
 
                 let external_delivery = self
                     .delivery_point
                     .as_ref()
                     .map_or_else(|| None, |delivery_point| delivery_point.external.clone());
 
-                let street = self.street.as_ref().map(|street| {
-                    match (street.number.clone(), street.name.clone()) {
-                        (Some(number), name) => format!("{number} {name}"),
-                        (None, name) => name,
-                    }
-                });
+                let street = street_line().or_else(locality_line);
 
                 let distribution_info = distribution_info();
                 let postal = postal_info();
@@ -93,7 +234,8 @@ impl AddressConvertible for ConvertedAddress {
                     street,
                     distribution_info,
                     postal,
-                    country: self.country.to_string(),
+                    country: self.destination_line(origin),
+                    extras: self.extras.clone(),
                 }))
             }
             AddressKind::Business => {
@@ -101,58 +243,85 @@ impl AddressConvertible for ConvertedAddress {
                     Recipient::Business { company_name, .. } if !company_name.is_empty() => {
                         company_name.to_string()
                     }
-                    _ => {
-                        return Err(AddressConversionError::MissingField(
-                            "company_name".to_string(),
-                        ))
-                    }
+                    _ => return Err(AddressConversionError::missing("company_name")),
                 };
 
                 let recipient = self.recipient.denomination().map_or_else(|| None, Some);
+                let identifiers = self
+                    .recipient
+                    .identifiers()
+                    .iter()
+                    .map(|identifier| identifier.value().to_string())
+                    .collect();
+
+                // Business addresses have no internal delivery line.
+                let external_delivery = with_care_of(
+                    self.delivery_point
+                        .as_ref()
+                        .map_or_else(|| None, |delivery_point| delivery_point.external.clone()),
+                );
 
-                let external_delivery = self
-                    .delivery_point
-                    .as_ref()
-                    .map_or_else(|| None, |delivery_point| delivery_point.external.clone());
-
-                // For the moment it has been decided that businesses should have
-                // a street line information.
-                let street = self
-                    .street
-                    .as_ref()
-                    .map(
-                        |street| match (street.number.clone(), street.name.clone()) {
-                            (Some(number), name) => format!("{number} {name}"),
-                            (None, name) => name,
-                        },
-                    )
-                    .ok_or(AddressConversionError::MissingField(
-                        "Street information is required for french business addresses".to_string(),
-                    ))?;
+                // Whether the postbox can replace the street is decided by the
+                // street policy, when the address is read.
+                let street = street_line().or_else(locality_line);
 
                 let distribution_info = distribution_info();
                 let postal = postal_info();
 
                 Ok(FrenchAddress::Business(BusinessFrenchAddress {
                     business_name,
+                    identifiers,
                     recipient,
                     external_delivery,
                     street,
                     distribution_info,
                     postal,
-                    country: self.country.to_string(),
+                    country: self.destination_line(origin),
+                    extras: self.extras.clone(),
                 }))
             }
         }
     }
 
-    fn to_iso20022(&self) -> Result<IsoAddress, AddressConversionError> {
+    fn to_iso20022_with(&self, profile: IsoProfile) -> Result<IsoAddress, AddressConversionError> {
+        let mut iso = self.to_iso20022_unfitted(profile)?;
+        fit_iso_lengths(&mut iso, profile.long_fields)?;
+
+        Ok(iso)
+    }
+
+    fn from_french(address: FrenchAddress) -> Result<Self, AddressConversionError>
+    where
+        Self: Sized,
+    {
+        Self::from_french_ref(address.into())
+    }
+
+    fn from_iso20022(address: IsoAddress) -> Result<Self, AddressConversionError>
+    where
+        Self: Sized,
+    {
+        Self::from_iso20022_ref(address.into())
+    }
+}
+
+impl ConvertedAddress {
+    /// Same as [`AddressConvertible::to_iso20022_with`], the elements longer
+    /// than their ISO 20022 type allows being left as they are.
+    pub(super) fn to_iso20022_unfitted(
+        &self,
+        profile: IsoProfile,
+    ) -> Result<IsoAddress, AddressConversionError> {
         let mut iso_address = IsoPostalAddress {
+            care_of: self
+                .delivery_point
+                .as_ref()
+                .and_then(|delivery_point| delivery_point.care_of.clone()),
             street_name: self.street.as_ref().map(|street| street.name.clone()),
             building_number: self
                 .street
                 .as_ref()
-                .and_then(|street| street.number.clone()),
+                .and_then(|street| street.full_number(profile.number_ext)),
             floor: self
                 .delivery_point
                 .as_ref()
@@ -166,21 +335,40 @@ impl AddressConvertible for ConvertedAddress {
                 .as_ref()
                 .and_then(|delivery_point| delivery_point.postbox.clone()),
             department: None,
+            sub_department: None,
             postcode: self.postal_details.postcode.clone(),
             town_name: self.postal_details.town.clone(),
-            town_location_name: self.postal_details.town_location.clone(),
-            country: self.country.iso_code().to_string(),
+            // ISO 20022 has a single town location, the lieu-dit comes first.
+            town_location_name: self
+                .postal_details
+                .locality
+                .clone()
+                .or_else(|| self.postal_details.town_location.clone()),
+            country_sub_division: self.postal_details.country_subdivision.clone(),
+            country: profile.country_repr.write(&self.country).to_string(),
         };
+        if let (OverseasCountry::Territory, Some(territory)) =
+            (profile.overseas, self.overseas_territory())
+        {
+            // The territory written as the country isn't repeated as a
+            // subdivision of France.
+            iso_address.country = profile.country_repr.write_territory(territory).to_string();
+            iso_address.country_sub_division = iso_address
+                .country_sub_division
+                .take()
+                .filter(|subdivision| *subdivision != territory.subdivision_code());
+        }
 
         match &self.kind {
             AddressKind::Individual => {
                 let name = match &self.recipient {
                     Recipient::Individual { name } if !name.is_empty() => name.clone(),
-                    _ => return Err(AddressConversionError::MissingField("name".to_string())),
+                    _ => return Err(AddressConversionError::missing("name")),
                 };
                 Ok(IsoAddress::IndividualIsoAddress {
                     name,
                     postal_address: iso_address,
+                    extras: self.extras.clone(),
                 })
             }
             AddressKind::Business => {
@@ -188,67 +376,112 @@ impl AddressConvertible for ConvertedAddress {
                     Recipient::Business { company_name, .. } if !company_name.is_empty() => {
                         company_name.clone()
                     }
-                    _ => {
-                        return Err(AddressConversionError::MissingField(
-                            "company_name".to_string(),
-                        ))
-                    }
+                    _ => return Err(AddressConversionError::missing("company_name")),
                 };
-                iso_address.department = self.recipient.denomination();
+                // The service is the department, the contact person the
+                // sub-department within it.
+                if let Recipient::Business {
+                    contact_person,
+                    service,
+                    ..
+                } = &self.recipient
+                {
+                    iso_address.department = service.clone();
+                    iso_address.sub_department = contact_person.clone();
+                }
+                let organisation_identification = self
+                    .recipient
+                    .identifiers()
+                    .iter()
+                    .map(|identifier| IsoOrgIdentification {
+                        id: identifier.value().to_string(),
+                        scheme_name: identifier.scheme().to_string(),
+                    })
+                    .collect();
 
                 Ok(IsoAddress::BusinessIsoAddress {
                     business_name: org_id,
+                    organisation_identification,
                     postal_address: iso_address,
+                    extras: self.extras.clone(),
                 })
             }
         }
     }
 
-    fn from_french(address: FrenchAddress) -> Result<Self, AddressConversionError>
-    where
-        Self: Sized,
-    {
+    /// Same as [`AddressConvertible::from_french`], from an address
+    /// borrowing its lines from the input.
+    pub fn from_french_ref(address: FrenchAddressRef<'_>) -> Result<Self, AddressConversionError> {
         match address {
-            FrenchAddress::Individual(individual) => {
-                let street = match individual.street {
-                    Some(street) => Some(FrenchAddressParser::parse_street(&street)?),
-                    None => None,
+            FrenchAddressRef::Individual(individual) => {
+                let country = parse_country(&individual.country, "country")?;
+                let (street, street_locality) = match individual.street {
+                    Some(street) => match FrenchAddressParser::parse_locality(&street) {
+                        Some(locality) => (None, Some(locality)),
+                        None => (
+                            Some(FrenchAddressParser::parse_street_for(&street, &country)?),
+                            None,
+                        ),
+                    },
+                    None => (None, None),
+                };
+                let (postbox, info_locality) = match individual.distribution_info {
+                    Some(info) => match FrenchAddressParser::parse_locality(&info) {
+                        Some(locality) => (None, Some(locality)),
+                        None => (Some(info.into()), None),
+                    },
+                    None => (None, None),
                 };
 
-                let postal = FrenchAddressParser::parse_postal(&individual.postal)?;
+                let mut postal =
+                    FrenchAddressParser::parse_postal_for(&individual.postal, &country)?;
+                postal.locality = street_locality.or(info_locality);
+                postal.country_subdivision = overseas_subdivision(&country, &postal.postcode);
 
+                let (care_of, internal) = split_care_of(individual.internal_delivery);
                 let individual_delivery = (
-                    individual.external_delivery,
-                    individual.internal_delivery,
-                    individual.distribution_info,
+                    care_of,
+                    individual.external_delivery.map(String::from),
+                    internal,
+                    postbox,
                 );
                 let delivery_point = match individual_delivery {
-                    (None, None, None) => None,
+                    (None, None, None, None) => None,
                     _ => Some(DeliveryPoint {
-                        external: individual_delivery.0,
-                        internal: individual_delivery.1,
-                        postbox: individual_delivery.2,
+                        care_of: individual_delivery.0,
+                        external: individual_delivery.1,
+                        internal: individual_delivery.2,
+                        postbox: individual_delivery.3,
                     }),
                 };
-                let country = Country::from_str(&individual.country)
-                    .map_err(|err| AddressConversionError::InvalidFormat(err.to_string()))?;
-
                 let individual_address = ConvertedAddress::new(
                     AddressKind::Individual,
                     Recipient::Individual {
-                        name: individual.name,
+                        name: individual.name.into(),
                     },
                     delivery_point,
                     street,
                     postal,
                     country,
-                );
+                )
+                .with_extras(individual.extras);
 
                 Ok(individual_address)
             }
-            FrenchAddress::Business(business) => {
-                let street = Some(FrenchAddressParser::parse_street(&business.street)?);
-                let mut postal = FrenchAddressParser::parse_postal(&business.postal)?;
+            FrenchAddressRef::Business(business) => {
+                let country = parse_country(&business.country, "country")?;
+                let (street, street_locality) = match business.street {
+                    Some(street) => match FrenchAddressParser::parse_locality(&street) {
+                        Some(locality) => (None, Some(locality)),
+                        None => (
+                            Some(FrenchAddressParser::parse_street_for(&street, &country)?),
+                            None,
+                        ),
+                    },
+                    None => (None, None),
+                };
+                let mut postal = FrenchAddressParser::parse_postal_for(&business.postal, &country)?;
+                postal.country_subdivision = overseas_subdivision(&country, &postal.postcode);
 
                 let postbox = business
                     .distribution_info
@@ -263,103 +496,240 @@ impl AddressConvertible for ConvertedAddress {
                     .transpose()?
                     .flatten();
 
-                postal.town_location = town_location;
+                let info_locality = town_location
+                    .as_deref()
+                    .and_then(FrenchAddressParser::parse_locality);
+                if info_locality.is_none() {
+                    postal.town_location = town_location;
+                }
+                postal.locality = street_locality.or(info_locality);
+
+                // Identifiers can be given in the dedicated field and after the
+                // business name on the first line.
+                let (company_name, line_identifier) =
+                    FrenchAddressParser::parse_business_name(&business.business_name)
+                        .map_err(|e| e.at("business_name"))?;
+                let mut identifiers = business
+                    .identifiers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, identifier)| {
+                        OrgIdentifier::parse(identifier)
+                            .map_err(|e| e.at(format!("identifiers[{i}]")))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(identifier) = line_identifier {
+                    if !identifiers.contains(&identifier) {
+                        identifiers.push(identifier);
+                    }
+                }
+
+                let (care_of, external) = split_care_of(business.external_delivery);
 
                 let address = ConvertedAddress::new(
                     AddressKind::Business,
-                    Recipient::Business {
-                        company_name: business.business_name,
-                        contact: business.recipient,
-                    },
+                    Recipient::business(company_name, business.recipient.as_deref(), identifiers),
                     Some(DeliveryPoint {
-                        external: business.external_delivery,
+                        care_of,
+                        external,
                         internal: None,
                         postbox,
                     }),
                     street,
                     postal,
-                    Country::France,
-                );
+                    country,
+                )
+                .with_extras(business.extras);
 
                 Ok(address)
             }
         }
     }
 
-    fn from_iso20022(address: IsoAddress) -> Result<Self, AddressConversionError>
-    where
-        Self: Sized,
-    {
+    /// Same as [`AddressConvertible::from_iso20022`], from an address
+    /// borrowing its fields from the input.
+    pub fn from_iso20022_ref(address: IsoAddressRef<'_>) -> Result<Self, AddressConversionError> {
         match address {
-            IsoAddress::IndividualIsoAddress {
+            IsoAddressRef::IndividualIsoAddress {
                 name,
                 postal_address: iso_address,
+                extras,
             } => {
-                let street_name = match iso_address.street_name {
-                    Some(name) if !name.is_empty() => name,
+                // The town location of an individual is a lieu-dit, which can
+                // replace the street.
+                let street = match iso_address.street_name {
+                    Some(name) if !name.is_empty() => Some(Street::new(
+                        iso_address.building_number.map(String::from),
+                        name.into(),
+                    )),
+                    _ if iso_address.town_location_name.is_some() => None,
                     _ => {
-                        return Err(AddressConversionError::MissingField(
-                            "street_name".to_string(),
+                        return Err(AddressConversionError::missing(
+                            "postal_address.street_name",
                         ))
                     }
                 };
-                let country = Country::from_str(&iso_address.country)
-                    .map_err(|err| AddressConversionError::InvalidFormat(err.to_string()))?;
+                let country = parse_country(&iso_address.country, "postal_address.country")?;
+                let country_subdivision = iso_address
+                    .country_sub_division
+                    .map(String::from)
+                    .or_else(|| overseas_subdivision(&country, &iso_address.postcode));
+                // With a street, the town location is kept as such.
+                let town_location_name = iso_address.town_location_name.map(String::from);
+                let (town_location, locality) = match street {
+                    Some(_) => (town_location_name, None),
+                    None => (None, town_location_name),
+                };
 
                 let address = ConvertedAddress::new(
                     AddressKind::Individual,
-                    Recipient::Individual { name },
+                    Recipient::Individual { name: name.into() },
                     Some(DeliveryPoint {
-                        external: iso_address.floor,
-                        internal: iso_address.room,
-                        postbox: iso_address.postbox,
-                    }),
-                    Some(Street {
-                        number: iso_address.building_number,
-                        name: street_name,
+                        care_of: iso_address.care_of.map(String::from),
+                        external: iso_address.floor.map(String::from),
+                        internal: iso_address.room.map(String::from),
+                        postbox: iso_address.postbox.map(String::from),
                     }),
+                    street,
                     PostalDetails {
-                        postcode: iso_address.postcode,
-                        town: iso_address.town_name,
-                        town_location: iso_address.town_location_name,
+                        postcode: iso_address.postcode.into(),
+                        town: iso_address.town_name.into(),
+                        town_location,
+                        locality,
+                        country_subdivision,
                     },
                     country,
-                );
+                )
+                .with_extras(extras);
 
                 Ok(address)
             }
-            IsoAddress::BusinessIsoAddress {
+            IsoAddressRef::BusinessIsoAddress {
                 business_name: company_name,
+                organisation_identification,
                 postal_address: iso_address,
+                extras,
             } => {
-                let country = Country::from_str(&iso_address.country)
-                    .map_err(|err| AddressConversionError::InvalidFormat(err.to_string()))?;
+                let country = parse_country(&iso_address.country, "postal_address.country")?;
+                let country_subdivision = iso_address
+                    .country_sub_division
+                    .map(String::from)
+                    .or_else(|| overseas_subdivision(&country, &iso_address.postcode));
+                let identifiers = organisation_identification
+                    .iter()
+                    .enumerate()
+                    .map(|(i, org_id)| {
+                        OrgIdentifier::parse_scheme(&org_id.scheme_name, &org_id.id)
+                            .map_err(|e| e.at(format!("organisation_identification[{i}]")))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Without street, the town location is the lieu-dit of the
+                // business.
+                let street = iso_address
+                    .street_name
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        Street::new(iso_address.building_number.map(String::from), name.into())
+                    });
+                let town_location_name = iso_address.town_location_name.map(String::from);
+                let (town_location, locality) = match street {
+                    Some(_) => (town_location_name, None),
+                    None => (None, town_location_name),
+                };
 
                 let address = ConvertedAddress::new(
                     AddressKind::Business,
-                    Recipient::Business {
-                        company_name,
-                        contact: iso_address.department,
-                    },
+                    business_recipient(
+                        company_name.into(),
+                        iso_address.department.as_deref(),
+                        iso_address.sub_department.as_deref(),
+                        identifiers,
+                    ),
                     Some(DeliveryPoint {
-                        external: iso_address.floor,
+                        care_of: iso_address.care_of.map(String::from),
+                        external: iso_address.floor.map(String::from),
                         internal: None,
-                        postbox: iso_address.postbox,
-                    }),
-                    Some(Street {
-                        number: iso_address.building_number,
-                        name: iso_address.street_name.unwrap_or_default(),
+                        postbox: iso_address.postbox.map(String::from),
                     }),
+                    street,
                     PostalDetails {
-                        postcode: iso_address.postcode,
-                        town: iso_address.town_name,
-                        town_location: iso_address.town_location_name,
+                        postcode: iso_address.postcode.into(),
+                        town: iso_address.town_name.into(),
+                        town_location,
+                        locality,
+                        country_subdivision,
                     },
                     country,
-                );
+                )
+                .with_extras(extras);
 
                 Ok(address)
             }
         }
     }
 }
+
+/// The recipient of an ISO 20022 business address: the department is the
+/// service, and the sub-department the contact person. A department alone,
+/// as written before the contact person had its own element, is split like
+/// the contact line of a french address, but stays the service unless a
+/// civility tells a person.
+fn business_recipient(
+    company_name: String,
+    department: Option<&str>,
+    sub_department: Option<&str>,
+    identifiers: Vec<OrgIdentifier>,
+) -> Recipient {
+    let (contact_person, service) = match (department, sub_department) {
+        (department, Some(contact_person)) => (Some(contact_person), department),
+        (Some(department), None) => match FrenchAddressParser::split_contact(department) {
+            (Some(person), None)
+                if PersonName::parse(person)
+                    .and_then(|name| name.civility)
+                    .is_none() =>
+            {
+                (None, Some(person))
+            }
+            split => split,
+        },
+        (None, None) => (None, None),
+    };
+
+    Recipient::Business {
+        company_name,
+        contact_person: contact_person.map(str::to_string),
+        service: service.map(str::to_string),
+        identifiers,
+    }
+}
+
+/// Splits the care of from a french delivery line, the rest of the line
+/// staying the delivery point.
+fn split_care_of(line: Option<Cow<'_, str>>) -> (Option<String>, Option<String>) {
+    match line {
+        Some(line) => match FrenchAddressParser::parse_care_of(&line) {
+            Some((care_of, rest)) => (Some(care_of), rest),
+            None => (None, Some(line.into())),
+        },
+        None => (None, None),
+    }
+}
+
+/// Parses the country name or code of the field at `path`.
+/// Reads the country at `path`. The overseas territories (e.g., `RE`,
+/// `GUADELOUPE`) are read as France, the territory being told by the postcode.
+fn parse_country(value: &str, path: &str) -> Result<Country, AddressConversionError> {
+    Country::from_str(value)
+        .or_else(|_| OverseasTerritory::from_str(value).map(|_| Country::France))
+        .map_err(|_| AddressConversionError::UnknownCountry {
+            field: path.to_string(),
+            value: value.to_string(),
+        })
+}
+
+/// The ISO 3166-2 subdivision of a french address overseas (e.g., `FR-974`).
+fn overseas_subdivision(country: &Country, postcode: &str) -> Option<String> {
+    country
+        .overseas_territory(postcode)
+        .map(OverseasTerritory::subdivision_code)
+}
@@ -0,0 +1,221 @@
+use serde_json::{Map, Value};
+use strum::EnumString;
+
+use super::address::AddressKind;
+
+/// Abbreviations of the French company legal forms, usually written after the
+/// business name (e.g., "DURAND SA").
+const LEGAL_FORMS: &[&str] = &[
+    "SA", "SAS", "SASU", "SARL", "EURL", "SNC", "SCI", "SCOP", "SCA", "SCS", "SELARL", "GIE",
+];
+/// Words introducing a business name (e.g., "Société DUPONT").
+const BUSINESS_WORDS: &[&str] = &[
+    "SOCIETE",
+    "SOCIÉTÉ",
+    "ETS",
+    "ETABLISSEMENTS",
+    "ÉTABLISSEMENTS",
+];
+/// Fields only found in business inputs.
+const BUSINESS_FIELDS: &[&str] = &[
+    "business_name",
+    "recipient",
+    "identifiers",
+    "organisation_identification",
+];
+
+/// The field giving the kind of an address in the tagged representation,
+/// e.g., `{"kind": "business", "business_name": "DURAND SA", ...}`.
+pub const KIND_TAG: &str = "kind";
+
+impl AddressKind {
+    /// Removes the kind tag of a raw JSON input. `None` for an untagged
+    /// input, an error for an unknown kind.
+    pub fn take_tag(input: &mut Value) -> Result<Option<AddressKind>, String> {
+        let Some(tag) = input
+            .as_object_mut()
+            .and_then(|fields| fields.shift_remove(KIND_TAG))
+        else {
+            return Ok(None);
+        };
+
+        match tag.as_str().map(str::to_lowercase).as_deref() {
+            Some("individual") => Ok(Some(AddressKind::Individual)),
+            Some("business") => Ok(Some(AddressKind::Business)),
+            _ => Err(format!(
+                "unknown {KIND_TAG} {tag}, expected \"individual\" or \"business\""
+            )),
+        }
+    }
+
+    /// Moves the name of the input to the name field of the kind, `name` for
+    /// an individual and `business_name` for a business, so that the kind
+    /// takes precedence over the fields provided.
+    pub fn rename_name_field(&self, input: &mut Value) {
+        let Some(fields) = input.as_object_mut() else {
+            return;
+        };
+        let (from_key, to_key) = match self {
+            AddressKind::Individual => ("business_name", "name"),
+            AddressKind::Business => ("name", "business_name"),
+        };
+
+        if let Some(name) = fields.shift_remove(from_key) {
+            fields.entry(to_key).or_insert(name);
+        }
+    }
+
+    /// Writes the kind tag first in a serialized address.
+    pub fn tag(&self, value: Value) -> Value {
+        let Value::Object(fields) = value else {
+            return value;
+        };
+        let kind = match self {
+            AddressKind::Individual => "individual",
+            AddressKind::Business => "business",
+        };
+
+        let mut tagged = Map::new();
+        tagged.insert(KIND_TAG.to_string(), Value::from(kind));
+        tagged.extend(fields.into_iter().filter(|(key, _)| key != KIND_TAG));
+
+        Value::Object(tagged)
+    }
+}
+
+/// The address kind requested by the user.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum KindHint {
+    /// Let the [`AddressKindDetector`] decide.
+    #[default]
+    Auto,
+    Individual,
+    Business,
+}
+
+impl KindHint {
+    /// The requested kind, or the detected one in auto mode.
+    pub fn resolve(&self, input: &Value) -> AddressKind {
+        match self {
+            KindHint::Auto => AddressKindDetector::detect(input),
+            KindHint::Individual => AddressKind::Individual,
+            KindHint::Business => AddressKind::Business,
+        }
+    }
+}
+
+/// Classifies raw french or ISO 20022 inputs as individual or business
+/// addresses from their content, instead of relying on which fields happen to
+/// deserialize.
+pub struct AddressKindDetector;
+
+impl AddressKindDetector {
+    /// Detects the kind of a raw JSON input. An input is a business when it
+    /// has a business only field, or when its name looks like a company name.
+    pub fn detect(input: &Value) -> AddressKind {
+        let has_business_field = BUSINESS_FIELDS
+            .iter()
+            .any(|field| input.get(field).is_some_and(|value| !value.is_null()));
+        let has_company_name = input
+            .get("name")
+            .and_then(Value::as_str)
+            .is_some_and(Self::is_company_name);
+
+        if has_business_field || has_company_name {
+            AddressKind::Business
+        } else {
+            AddressKind::Individual
+        }
+    }
+
+    /// Looks for a legal form (SA, SARL, S.A.S., ...) or a word like
+    /// "Société" in the name.
+    pub fn is_company_name(name: &str) -> bool {
+        name.split(|c: char| c.is_whitespace() || c == ',')
+            .map(|word| word.replace('.', ""))
+            .any(|word| {
+                LEGAL_FORMS.contains(&word.as_str())
+                    || BUSINESS_WORDS.contains(&word.to_uppercase().as_str())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{AddressKindDetector, KindHint};
+    use crate::domain::AddressKind;
+
+    #[test]
+    fn it_should_detect_company_names() {
+        assert!(AddressKindDetector::is_company_name("DURAND SA"));
+        assert!(AddressKindDetector::is_company_name("DURAND S.A.R.L."));
+        assert!(AddressKindDetector::is_company_name("Société DUPONT"));
+        assert!(AddressKindDetector::is_company_name("ETS MARTIN, SAS"));
+        assert!(!AddressKindDetector::is_company_name(
+            "Monsieur Jean DELHOURME"
+        ));
+        // Legal forms are only recognized in uppercase
+        assert!(!AddressKindDetector::is_company_name("Madame Sa LEE"));
+    }
+
+    #[test]
+    fn it_should_detect_kind() {
+        let individual = json!({"name": "Monsieur Jean DELHOURME", "postal": "33380 MIOS"});
+        let company_name = json!({"name": "DURAND SA", "postal": "33380 MIOS"});
+        let business = json!({"business_name": "DURAND", "postal": "33380 MIOS"});
+        let siret = json!({"name": "DURAND", "identifiers": ["73282932000074"]});
+
+        assert_eq!(
+            AddressKindDetector::detect(&individual),
+            AddressKind::Individual
+        );
+        assert_eq!(
+            AddressKindDetector::detect(&company_name),
+            AddressKind::Business
+        );
+        assert_eq!(
+            AddressKindDetector::detect(&business),
+            AddressKind::Business
+        );
+        assert_eq!(AddressKindDetector::detect(&siret), AddressKind::Business);
+    }
+
+    #[test]
+    fn kind_tags() {
+        let mut tagged = json!({"kind": "Business", "name": "DURAND"});
+        assert_eq!(
+            AddressKind::take_tag(&mut tagged),
+            Ok(Some(AddressKind::Business))
+        );
+        assert_eq!(tagged, json!({"name": "DURAND"}));
+        assert_eq!(AddressKind::take_tag(&mut tagged), Ok(None));
+        assert!(AddressKind::take_tag(&mut json!({"kind": "person"})).is_err());
+
+        let mut renamed = json!({"name": "DURAND SA", "country": "FR"});
+        AddressKind::Business.rename_name_field(&mut renamed);
+        assert_eq!(
+            renamed,
+            json!({"country": "FR", "business_name": "DURAND SA"})
+        );
+
+        let value = AddressKind::Individual.tag(json!({"name": "Mirabelle Prune"}));
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"kind":"individual","name":"Mirabelle Prune"}"#
+        );
+    }
+
+    #[test]
+    fn hint_overrides_detection() {
+        let company_name = json!({"name": "DURAND SA"});
+
+        assert_eq!(
+            KindHint::Individual.resolve(&company_name),
+            AddressKind::Individual
+        );
+        assert_eq!(KindHint::Auto.resolve(&company_name), AddressKind::Business);
+    }
+}
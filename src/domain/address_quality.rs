@@ -0,0 +1,182 @@
+use super::address::{Address, PostalDetails, Street};
+use super::validators::PostcodeValidators;
+
+/// Points given when the street has a number, or only a name (e.g., "LE
+/// VILLAGE") or a lieu-dit.
+const STREET_NUMBER_POINTS: u8 = 25;
+const STREET_NAME_POINTS: u8 = 10;
+/// Points given when any delivery point detail is known.
+const DELIVERY_POINT_POINTS: u8 = 15;
+const POSTCODE_POINTS: u8 = 25;
+const TOWN_POINTS: u8 = 15;
+/// Points given when the street and town lines are uppercase, as required by
+/// the french postal norm (NF Z10-011).
+const CASING_POINTS: u8 = 20;
+
+/// The CEDEX mention of business towns (e.g., "PARIS CEDEX 08").
+const CEDEX: &str = " CEDEX";
+
+impl Address {
+    /// A score from 0 to 100 rating the completeness and the consistency of
+    /// the address:
+    ///
+    /// - street number and name: 25 (10 without number, or for a lieu-dit)
+    /// - delivery point (building, appartment, postbox): 15
+    /// - postcode valid for the country, see [`PostcodeValidators`]: 25
+    /// - town name without stray characters: 15
+    /// - uppercase street and town: 20
+    ///
+    /// There is no reference dataset, so the postcode and the town are checked
+    /// separately, not against each other.
+    pub fn quality_score(&self) -> u8 {
+        let street = match &self.street {
+            Some(Street {
+                number: Some(number),
+                name,
+                ..
+            }) if !number.is_empty() && !name.is_empty() => STREET_NUMBER_POINTS,
+            Some(Street { name, .. }) if !name.is_empty() => STREET_NAME_POINTS,
+            _ if self.postal_details.locality.is_some() => STREET_NAME_POINTS,
+            _ => 0,
+        };
+        let delivery_point = match &self.delivery_point {
+            Some(delivery_point)
+                if [
+                    &delivery_point.external,
+                    &delivery_point.internal,
+                    &delivery_point.postbox,
+                ]
+                .iter()
+                .any(|detail| detail.as_ref().is_some_and(|detail| !detail.is_empty())) =>
+            {
+                DELIVERY_POINT_POINTS
+            }
+            _ => 0,
+        };
+        let valid_postcode =
+            PostcodeValidators::built_in().is_valid(&self.country, &self.postal_details.postcode);
+        let postcode = if valid_postcode { POSTCODE_POINTS } else { 0 };
+        let town = if is_valid_town(&self.postal_details.town) {
+            TOWN_POINTS
+        } else {
+            0
+        };
+        let casing = if is_uppercase(self.street.as_ref(), &self.postal_details) {
+            CASING_POINTS
+        } else {
+            0
+        };
+
+        street + delivery_point + postcode + town + casing
+    }
+}
+
+/// Town names only contain letters, spaces, hyphens and apostrophes, with an
+/// optional CEDEX mention.
+pub(super) fn is_valid_town(town: &str) -> bool {
+    let name = match town.find(CEDEX) {
+        Some(cedex) => {
+            let office = town[cedex + CEDEX.len()..].trim();
+            if !office.chars().all(|c| c.is_ascii_digit()) {
+                return false;
+            }
+            &town[..cedex]
+        }
+        None => town,
+    };
+
+    !name.trim().is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphabetic() || matches!(c, ' ' | '-' | '\'' | '’'))
+}
+
+fn is_uppercase(street: Option<&Street>, postal_details: &PostalDetails) -> bool {
+    let street_name = street
+        .map(|street| street.name.as_str())
+        .unwrap_or_default();
+    let town_location = postal_details.town_location.as_deref().unwrap_or_default();
+    let locality = postal_details.locality.as_deref().unwrap_or_default();
+
+    [street_name, &postal_details.town, town_location, locality]
+        .iter()
+        .all(|line| *line == line.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_town;
+    use crate::domain::*;
+
+    fn address(street: Option<Street>, postcode: &str, town: &str) -> Address {
+        Address::new(ConvertedAddress::new(
+            AddressKind::Individual,
+            Recipient::Individual {
+                name: "Monsieur Jean DELHOURME".to_string(),
+            },
+            None,
+            street,
+            PostalDetails {
+                postcode: postcode.to_string(),
+                town: town.to_string(),
+                town_location: None,
+                locality: None,
+                country_subdivision: None,
+            },
+            Country::France,
+        ))
+    }
+
+    #[test]
+    fn it_should_validate_towns() {
+        assert!(is_valid_town("MIOS"));
+        assert!(is_valid_town("SAINT-ÉTIENNE"));
+        assert!(is_valid_town("L'ÎLE-ROUSSE"));
+        assert!(is_valid_town("PARIS CEDEX 08"));
+        assert!(!is_valid_town("MIOS 2"));
+        assert!(!is_valid_town(" "));
+    }
+
+    #[test]
+    fn it_should_score_quality() {
+        let mut complete = address(
+            Some(Street::new(
+                Some("25".to_string()),
+                "RUE DE L'EGLISE".to_string(),
+            )),
+            "33380",
+            "MIOS",
+        );
+        complete.delivery_point = Some(DeliveryPoint {
+            care_of: None,
+            external: None,
+            internal: Some("Appartement 2".to_string()),
+            postbox: None,
+        });
+        assert_eq!(complete.quality_score(), 100);
+
+        let without_number = address(
+            Some(Street::new(None, "LE VILLAGE".to_string())),
+            "82500",
+            "AUTERIVE",
+        );
+        assert_eq!(without_number.quality_score(), 70);
+
+        let lowercase = address(
+            Some(Street::new(
+                Some("25".to_string()),
+                "rue de l'église".to_string(),
+            )),
+            "33380",
+            "Mios",
+        );
+        assert_eq!(lowercase.quality_score(), 65);
+
+        let mut locality = address(None, "82500", "AUTERIVE");
+        locality.postal_details.locality = Some("LES GRANDS CHAMPS".to_string());
+        assert_eq!(locality.quality_score(), 70);
+
+        let invalid_postal = address(None, "00000", "MIOS 33");
+        assert_eq!(invalid_postal.quality_score(), 20);
+    }
+}
@@ -0,0 +1,87 @@
+use uuid::Uuid;
+
+use super::address::Address;
+use super::fingerprint::normalize;
+
+/// An address found by [`AddressRepository::search`], the most relevant ones
+/// scoring the highest.
+///
+/// [`AddressRepository::search`]: super::repositories::AddressRepository::search
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub id: Uuid,
+    pub score: f32,
+}
+
+/// The words searched for in `query`, normalized as the addresses are (e.g.,
+/// `Église` -> `EGLISE`).
+pub fn search_terms(query: &str) -> Vec<String> {
+    normalize(query)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+impl Address {
+    /// The words an address is found by: its alias, recipient, delivery
+    /// point, street, postal details and country, uppercase without accents
+    /// nor punctuation.
+    pub fn search_text(&self) -> String {
+        let values = self.as_converted_address().field_values();
+        let words: Vec<&str> = self
+            .alias()
+            .map(|alias| alias.as_str())
+            .into_iter()
+            .chain(values.iter().map(|(_, value)| value.as_str()))
+            .chain([self.country.english_name()])
+            .collect();
+
+        normalize(&words.join(" "))
+    }
+
+    /// How relevant the address is to the `terms` of a search, none when a
+    /// term matches no word. A term equal to a word scores 2, a term starting
+    /// a word 1.
+    pub fn match_score(&self, terms: &[String]) -> Option<f32> {
+        let text = self.search_text();
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        terms.iter().try_fold(0.0, |score, term| {
+            if words.contains(&term.as_str()) {
+                Some(score + 2.0)
+            } else if words.iter().any(|word| word.starts_with(term.as_str())) {
+                Some(score + 1.0)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::search_terms;
+    use crate::domain::{Address, AddressConvertible, ConvertedAddress, FrenchAddress};
+
+    #[test]
+    fn it_should_score_the_matching_words() {
+        let french: FrenchAddress = serde_json::from_str(
+            r#"{
+                "name": "Monsieur Jean DELHOURME",
+                "street": "25 RUE DE L'ÉGLISE",
+                "postal": "33380 MIOS",
+                "country": "FRANCE"
+            }"#,
+        )
+        .unwrap();
+        let address = Address::new(ConvertedAddress::from_french(french).unwrap());
+
+        assert_eq!(
+            search_terms("rue de l'Église"),
+            ["RUE", "DE", "L", "EGLISE"]
+        );
+        assert_eq!(address.match_score(&search_terms("eglise mios")), Some(4.0));
+        assert_eq!(address.match_score(&search_terms("delh 33")), Some(2.0));
+        assert_eq!(address.match_score(&search_terms("eglise paris")), None);
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Where an address stands in its lifecycle. Archived addresses are hidden
+/// from the listings, but can still be fetched and converted.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum AddressStatus {
+    #[default]
+    Active,
+    Archived,
+    /// The address is known to be wrong, e.g., mail was returned.
+    Invalid {
+        reason: String,
+    },
+}
+
+impl fmt::Display for AddressStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressStatus::Active => f.write_str("active"),
+            AddressStatus::Archived => f.write_str("archived"),
+            AddressStatus::Invalid { reason } => write!(f, "invalid ({reason})"),
+        }
+    }
+}
+
+/// A status change not allowed from the current status of the address.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("Can't {action} the address `{id}`: it is {status}")]
+pub struct AddressStatusError {
+    pub id: String,
+    /// The refused change, e.g., `archive`.
+    pub action: &'static str,
+    pub status: AddressStatus,
+}
@@ -0,0 +1,338 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use super::french_address::*;
+use super::iso20022_address::*;
+
+/// A french address borrowing its lines from the input it was read from.
+/// Only the escaped lines are allocated, the lines parsed during the
+/// conversion (street, postal, country...) are never copied.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FrenchAddressRef<'a> {
+    #[serde(borrow)]
+    Individual(IndividualFrenchAddressRef<'a>),
+    #[serde(borrow)]
+    Business(BusinessFrenchAddressRef<'a>),
+}
+
+/// Same as [`IndividualFrenchAddress`], with borrowed lines.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct IndividualFrenchAddressRef<'a> {
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub internal_delivery: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub external_delivery: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub street: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub distribution_info: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub postal: Cow<'a, str>,
+    #[serde(borrow)]
+    pub country: Cow<'a, str>,
+    #[serde(flatten, default)]
+    pub extras: Map<String, Value>,
+}
+
+/// Same as [`BusinessFrenchAddress`], with borrowed lines.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct BusinessFrenchAddressRef<'a> {
+    #[serde(borrow)]
+    pub business_name: Cow<'a, str>,
+    #[serde(default, borrow, deserialize_with = "borrow_vec")]
+    pub identifiers: Vec<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub recipient: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub external_delivery: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub street: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option")]
+    pub distribution_info: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub postal: Cow<'a, str>,
+    #[serde(borrow)]
+    pub country: Cow<'a, str>,
+    #[serde(flatten, default)]
+    pub extras: Map<String, Value>,
+}
+
+/// Same as [`IsoAddress`], with borrowed fields.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum IsoAddressRef<'a> {
+    IndividualIsoAddress {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        #[serde(borrow, alias = "PstlAdr", alias = "postalAddress")]
+        postal_address: IsoPostalAddressRef<'a>,
+        #[serde(flatten, default)]
+        extras: Map<String, Value>,
+    },
+    BusinessIsoAddress {
+        #[serde(borrow, alias = "businessName")]
+        business_name: Cow<'a, str>,
+        #[serde(default, borrow, alias = "OrgId", alias = "organisationIdentification")]
+        organisation_identification: Vec<IsoOrgIdentificationRef<'a>>,
+        #[serde(borrow, alias = "PstlAdr", alias = "postalAddress")]
+        postal_address: IsoPostalAddressRef<'a>,
+        #[serde(flatten, default)]
+        extras: Map<String, Value>,
+    },
+}
+
+/// Same as [`IsoOrgIdentification`], with borrowed fields.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct IsoOrgIdentificationRef<'a> {
+    #[serde(borrow, alias = "Id")]
+    pub id: Cow<'a, str>,
+    #[serde(borrow, alias = "SchmeNm", alias = "schemeName")]
+    pub scheme_name: Cow<'a, str>,
+}
+
+/// Same as [`IsoPostalAddress`], with borrowed fields.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct IsoPostalAddressRef<'a> {
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "borrow_option",
+        alias = "CareOf",
+        alias = "careOf"
+    )]
+    pub care_of: Option<Cow<'a, str>>,
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "borrow_option",
+        alias = "StrtNm",
+        alias = "streetName"
+    )]
+    pub street_name: Option<Cow<'a, str>>,
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "borrow_option",
+        alias = "BldgNb",
+        alias = "buildingNumber"
+    )]
+    pub building_number: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option", alias = "Flr")]
+    pub floor: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option", alias = "Room")]
+    pub room: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option", alias = "PstBx")]
+    pub postbox: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_option", alias = "Dept")]
+    pub department: Option<Cow<'a, str>>,
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "borrow_option",
+        alias = "SubDept",
+        alias = "subDepartment"
+    )]
+    pub sub_department: Option<Cow<'a, str>>,
+    #[serde(borrow, alias = "PstCd")]
+    pub postcode: Cow<'a, str>,
+    #[serde(borrow, alias = "TwnNm", alias = "townName")]
+    pub town_name: Cow<'a, str>,
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "borrow_option",
+        alias = "TwnLctnNm",
+        alias = "townLocationName"
+    )]
+    pub town_location_name: Option<Cow<'a, str>>,
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "borrow_option",
+        alias = "CtrySubDvsn",
+        alias = "countrySubDivision"
+    )]
+    pub country_sub_division: Option<Cow<'a, str>>,
+    #[serde(borrow, alias = "Ctry")]
+    pub country: Cow<'a, str>,
+}
+
+/// A line borrowed from the input when it has no escape sequence. Serde only
+/// borrows a bare `Cow<str>`, not one nested in an `Option` or a `Vec`.
+#[derive(Deserialize)]
+struct Line<'a>(#[serde(borrow)] Cow<'a, str>);
+
+fn borrow_option<'de: 'a, 'a, D>(deserializer: D) -> Result<Option<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<Line>::deserialize(deserializer)?.map(|line| line.0))
+}
+
+fn borrow_vec<'de: 'a, 'a, D>(deserializer: D) -> Result<Vec<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<Line>::deserialize(deserializer)?
+        .into_iter()
+        .map(|line| line.0)
+        .collect())
+}
+
+impl From<FrenchAddress> for FrenchAddressRef<'static> {
+    fn from(address: FrenchAddress) -> Self {
+        match address {
+            FrenchAddress::Individual(individual) => {
+                FrenchAddressRef::Individual(IndividualFrenchAddressRef {
+                    name: individual.name.into(),
+                    internal_delivery: individual.internal_delivery.map(Cow::from),
+                    external_delivery: individual.external_delivery.map(Cow::from),
+                    street: individual.street.map(Cow::from),
+                    distribution_info: individual.distribution_info.map(Cow::from),
+                    postal: individual.postal.into(),
+                    country: individual.country.into(),
+                    extras: individual.extras,
+                })
+            }
+            FrenchAddress::Business(business) => {
+                FrenchAddressRef::Business(BusinessFrenchAddressRef {
+                    business_name: business.business_name.into(),
+                    identifiers: business.identifiers.into_iter().map(Cow::from).collect(),
+                    recipient: business.recipient.map(Cow::from),
+                    external_delivery: business.external_delivery.map(Cow::from),
+                    street: business.street.map(Cow::from),
+                    distribution_info: business.distribution_info.map(Cow::from),
+                    postal: business.postal.into(),
+                    country: business.country.into(),
+                    extras: business.extras,
+                })
+            }
+        }
+    }
+}
+
+impl From<IsoPostalAddress> for IsoPostalAddressRef<'static> {
+    fn from(address: IsoPostalAddress) -> Self {
+        IsoPostalAddressRef {
+            care_of: address.care_of.map(Cow::from),
+            street_name: address.street_name.map(Cow::from),
+            building_number: address.building_number.map(Cow::from),
+            floor: address.floor.map(Cow::from),
+            room: address.room.map(Cow::from),
+            postbox: address.postbox.map(Cow::from),
+            department: address.department.map(Cow::from),
+            sub_department: address.sub_department.map(Cow::from),
+            postcode: address.postcode.into(),
+            town_name: address.town_name.into(),
+            town_location_name: address.town_location_name.map(Cow::from),
+            country_sub_division: address.country_sub_division.map(Cow::from),
+            country: address.country.into(),
+        }
+    }
+}
+
+impl From<IsoAddress> for IsoAddressRef<'static> {
+    fn from(address: IsoAddress) -> Self {
+        match address {
+            IsoAddress::IndividualIsoAddress {
+                name,
+                postal_address,
+                extras,
+            } => IsoAddressRef::IndividualIsoAddress {
+                name: name.into(),
+                postal_address: postal_address.into(),
+                extras,
+            },
+            IsoAddress::BusinessIsoAddress {
+                business_name,
+                organisation_identification,
+                postal_address,
+                extras,
+            } => IsoAddressRef::BusinessIsoAddress {
+                business_name: business_name.into(),
+                organisation_identification: organisation_identification
+                    .into_iter()
+                    .map(|org_id| IsoOrgIdentificationRef {
+                        id: org_id.id.into(),
+                        scheme_name: org_id.scheme_name.into(),
+                    })
+                    .collect(),
+                postal_address: postal_address.into(),
+                extras,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+
+    #[test]
+    fn it_should_borrow_unescaped_lines() {
+        let input = r#"{
+            "name": "Monsieur Jean DELHOURME",
+            "internal_delivery": "Chez Mireille COPEAU Appartement 2",
+            "external_delivery": null,
+            "street": "25 RUE DE L\u0027EGLISE",
+            "distribution_info": "CAUDOS",
+            "postal": "33380 MIOS",
+            "country": "FRANCE"
+        }"#;
+
+        let FrenchAddressRef::Individual(individual) = serde_json::from_str(input).unwrap() else {
+            panic!("expected an individual address");
+        };
+        assert!(matches!(
+            individual.name,
+            Cow::Borrowed("Monsieur Jean DELHOURME")
+        ));
+        assert!(matches!(individual.postal, Cow::Borrowed("33380 MIOS")));
+        assert!(matches!(
+            individual.distribution_info,
+            Some(Cow::Borrowed("CAUDOS"))
+        ));
+        assert_eq!(individual.external_delivery, None);
+        // The escaped line can't be borrowed.
+        assert_eq!(individual.street.as_deref(), Some("25 RUE DE L'EGLISE"));
+        assert!(matches!(individual.street, Some(Cow::Owned(_))));
+    }
+
+    #[test]
+    fn it_should_borrow_iso_aliases() {
+        let input = r#"{
+            "businessName": "Société DUPONT",
+            "OrgId": [{"Id": "732829320", "SchmeNm": "SIREN"}],
+            "PstlAdr": {"PstCd": "34092", "TwnNm": "MONTPELLIER CEDEX 5", "Ctry": "FR"},
+            "reference": "C-42"
+        }"#;
+
+        let IsoAddressRef::BusinessIsoAddress {
+            business_name,
+            organisation_identification,
+            postal_address,
+            extras,
+        } = serde_json::from_str(input).unwrap()
+        else {
+            panic!("expected a business address");
+        };
+        assert!(matches!(business_name, Cow::Borrowed("Société DUPONT")));
+        assert!(matches!(
+            organisation_identification[0].id,
+            Cow::Borrowed("732829320")
+        ));
+        assert!(matches!(
+            postal_address.town_name,
+            Cow::Borrowed("MONTPELLIER CEDEX 5")
+        ));
+        assert_eq!(postal_address.street_name, None);
+        assert_eq!(extras["reference"], "C-42");
+    }
+}
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::address::*;
+use super::address_conversion::AddressConversionError;
+use super::portuguese_address::recipient;
+use super::street_number::NumberExtStyle;
+
+/// The abbreviations of the Brazilian federative units (UF).
+const STATES: [&str; 27] = [
+    "AC", "AL", "AM", "AP", "BA", "CE", "DF", "ES", "GO", "MA", "MG", "MS", "MT", "PA", "PB", "PE",
+    "PI", "PR", "RJ", "RN", "RO", "RR", "RS", "SC", "SE", "SP", "TO",
+];
+
+/// The number written when the building has none (sem número).
+const NO_NUMBER: &str = "S/N";
+
+/// A Brazilian address (Correios).
+///
+/// # Example
+///
+/// Avenida Paulista, 1578 - Apto 12
+/// Bela Vista
+/// São Paulo - SP
+/// 01310-200
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BrazilianAddress {
+    /// The recipient, or the contact of a business.
+    pub name: Option<String>,
+    /// The business name, for business addresses.
+    #[serde(default)]
+    pub business_name: Option<String>,
+    /// The street type and name (Avenida Paulista, Rua da Consolação).
+    pub logradouro: String,
+    /// The building number, "S/N" when there is none.
+    pub number: String,
+    /// The apartment, block or room (Apto 12, Bloco B).
+    #[serde(default)]
+    pub complement: Option<String>,
+    /// The neighbourhood (Bela Vista).
+    pub bairro: String,
+    /// The city (São Paulo).
+    pub city: String,
+    /// The two letters federative unit (SP).
+    pub state: String,
+    /// The 8 digits postal code, written with a dash (01310-200).
+    pub cep: String,
+    /// Unknown fields, kept as is through conversions and storage.
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub extras: Map<String, Value>,
+}
+
+impl BrazilianAddress {
+    /// The address lines for domestic mail.
+    pub fn lines(&self) -> Vec<String> {
+        let street = match &self.complement {
+            Some(complement) => format!("{}, {} - {complement}", self.logradouro, self.number),
+            None => format!("{}, {}", self.logradouro, self.number),
+        };
+
+        [
+            self.business_name.clone(),
+            self.name.clone(),
+            Some(street),
+            Some(self.bairro.clone()),
+            Some(format!("{} - {}", self.city, self.state)),
+            Some(self.cep.clone()),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|line| !line.trim().is_empty())
+        .collect()
+    }
+}
+
+/// Parses an 8 digits CEP, written with or without the dash, and writes it
+/// with the dash ("01310200" -> "01310-200").
+pub fn parse_cep(cep: &str) -> Result<String, AddressConversionError> {
+    let digits: String = cep.chars().filter(|c| !matches!(c, '-' | '.')).collect();
+
+    if digits.len() != 8 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AddressConversionError::InvalidPostal {
+            field: "cep".to_string(),
+            value: cep.to_string(),
+            reason: "A CEP has 8 digits (e.g., '01310-200')".to_string(),
+        });
+    }
+
+    Ok(format!("{}-{}", &digits[..5], &digits[5..]))
+}
+
+impl ConvertedAddress {
+    /// Converts a Brazilian address. The bairro is the town location and the
+    /// state the country subdivision, as in ISO 20022.
+    pub fn from_brazilian(address: BrazilianAddress) -> Result<Self, AddressConversionError> {
+        let required = |field: &str, value: String| {
+            if value.trim().is_empty() {
+                Err(AddressConversionError::missing(field))
+            } else {
+                Ok(value.trim().to_string())
+            }
+        };
+
+        let postcode = parse_cep(&address.cep)?;
+        let state = address.state.trim().to_uppercase();
+        if !STATES.contains(&state.as_str()) {
+            return Err(AddressConversionError::InvalidValue {
+                field: "state".to_string(),
+                value: address.state,
+                reason: "Not a Brazilian federative unit (e.g., 'SP')".to_string(),
+            });
+        }
+        let number = required("number", address.number)?;
+        let (kind, recipient) = recipient(address.name, address.business_name)?;
+
+        Ok(ConvertedAddress::new(
+            kind,
+            recipient,
+            address
+                .complement
+                .filter(|complement| !complement.trim().is_empty())
+                .map(|complement| DeliveryPoint {
+                    care_of: None,
+                    external: None,
+                    internal: Some(complement),
+                    postbox: None,
+                }),
+            Some(Street {
+                number: (!number.eq_ignore_ascii_case(NO_NUMBER)).then_some(number),
+                kind: None,
+                repetition_index: None,
+                name: required("logradouro", address.logradouro)?,
+            }),
+            PostalDetails {
+                postcode,
+                town: required("city", address.city)?,
+                town_location: Some(required("bairro", address.bairro)?),
+                locality: None,
+                country_subdivision: Some(state),
+            },
+            Country::Brazil,
+        )
+        .with_extras(address.extras))
+    }
+
+    /// Converts the address to a Brazilian address. Only addresses in Brazil
+    /// with a street, a bairro and a state convert.
+    pub fn to_brazilian(&self) -> Result<BrazilianAddress, AddressConversionError> {
+        if self.country != Country::Brazil {
+            return Err(AddressConversionError::InvalidValue {
+                field: "country".to_string(),
+                value: self.country.iso_code().to_string(),
+                reason: format!(
+                    "{} address can't be written as a brazilian address",
+                    self.country.english_name()
+                ),
+            });
+        }
+
+        let (name, business_name) = self.recipient_lines();
+        let street = self
+            .street
+            .as_ref()
+            .ok_or_else(|| AddressConversionError::missing("logradouro"))?;
+        let bairro = self
+            .postal_details
+            .town_location
+            .clone()
+            .or_else(|| self.postal_details.locality.clone())
+            .ok_or_else(|| AddressConversionError::missing("bairro"))?;
+        let state = self
+            .postal_details
+            .country_subdivision
+            .clone()
+            .ok_or_else(|| AddressConversionError::missing("state"))?;
+
+        Ok(BrazilianAddress {
+            name,
+            business_name,
+            logradouro: street.name.clone(),
+            number: street
+                .full_number(NumberExtStyle::Attached)
+                .unwrap_or_else(|| NO_NUMBER.to_string()),
+            complement: self
+                .delivery_point
+                .as_ref()
+                .and_then(|delivery_point| delivery_point.internal.clone()),
+            bairro,
+            city: self.postal_details.town.clone(),
+            state,
+            cep: self.postal_details.postcode.clone(),
+            extras: self.extras.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cep, BrazilianAddress};
+    use crate::domain::*;
+    use serde_json::Map;
+
+    fn address() -> BrazilianAddress {
+        BrazilianAddress {
+            name: None,
+            business_name: Some("Padaria Central Ltda".to_string()),
+            logradouro: "Avenida Paulista".to_string(),
+            number: "1578".to_string(),
+            complement: Some("Loja 2".to_string()),
+            bairro: "Bela Vista".to_string(),
+            city: "São Paulo".to_string(),
+            state: "SP".to_string(),
+            cep: "01310-200".to_string(),
+            extras: Map::new(),
+        }
+    }
+
+    #[test]
+    fn it_should_parse_ceps() {
+        assert_eq!(parse_cep("01310200").unwrap(), "01310-200");
+        assert_eq!(parse_cep("01.310-200").unwrap(), "01310-200");
+        assert!(matches!(
+            parse_cep("1310-200"),
+            Err(AddressConversionError::InvalidPostal { field, .. }) if field == "cep"
+        ));
+    }
+
+    #[test]
+    fn it_should_render_lines() {
+        assert_eq!(
+            address().lines(),
+            vec![
+                "Padaria Central Ltda",
+                "Avenida Paulista, 1578 - Loja 2",
+                "Bela Vista",
+                "São Paulo - SP",
+                "01310-200"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_convert_back_and_forth() {
+        let converted = ConvertedAddress::from_brazilian(address()).unwrap();
+
+        assert_eq!(converted.kind, AddressKind::Business);
+        assert_eq!(converted.country, Country::Brazil);
+        assert_eq!(
+            converted.postal_details.country_subdivision.as_deref(),
+            Some("SP")
+        );
+        assert_eq!(converted.to_brazilian().unwrap(), address());
+    }
+
+    #[test]
+    fn it_should_write_missing_numbers() {
+        let without_number = BrazilianAddress {
+            number: "s/n".to_string(),
+            ..address()
+        };
+        let converted = ConvertedAddress::from_brazilian(without_number).unwrap();
+
+        assert_eq!(converted.street.as_ref().unwrap().number, None);
+        assert_eq!(converted.to_brazilian().unwrap().number, "S/N");
+    }
+
+    #[test]
+    fn it_should_reject_unknown_states() {
+        let invalid = BrazilianAddress {
+            state: "XX".to_string(),
+            ..address()
+        };
+
+        assert!(matches!(
+            ConvertedAddress::from_brazilian(invalid),
+            Err(AddressConversionError::InvalidValue { field, .. }) if field == "state"
+        ));
+    }
+}
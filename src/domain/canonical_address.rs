@@ -0,0 +1,324 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::address::*;
+use super::address_conversion::AddressConversionError;
+use super::org_identifier::OrgIdentifier;
+use super::street_number::StreetNumberExt;
+use super::street_type::StreetType;
+
+/// The structured address of the domain as an interchange format, for the
+/// integrators persisting it rather than a postal format. Every field read
+/// by the conversions is kept, so that the conversion is lossless, without
+/// the storage bookkeeping (ID, timestamps, version, status).
+///
+/// The fields follow the internal field paths (`street.number`,
+/// `postal_details.town`). They are only added to, never renamed or removed,
+/// the format being stable across releases.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CanonicalAddress {
+    /// `individual` or `business`.
+    pub kind: CanonicalKind,
+    pub recipient: CanonicalRecipient,
+    #[serde(default)]
+    pub delivery_point: Option<CanonicalDeliveryPoint>,
+    #[serde(default)]
+    pub street: Option<CanonicalStreet>,
+    pub postal_details: CanonicalPostalDetails,
+    /// The ISO 3166 alpha-2 code of the country (`FR`).
+    pub country: String,
+    /// Unknown fields, kept as is through conversions and storage.
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub extras: Map<String, Value>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CanonicalKind {
+    Individual,
+    Business,
+}
+
+/// The recipient: the name of an individual, or the company name, contact
+/// person, service and identifiers of a business.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CanonicalRecipient {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub company_name: Option<String>,
+    /// The contact line of a business, its contact person followed by its
+    /// service. Only read when neither of them is given.
+    #[serde(default)]
+    pub contact: Option<String>,
+    #[serde(default)]
+    pub contact_person: Option<String>,
+    #[serde(default)]
+    pub service: Option<String>,
+    #[serde(default)]
+    pub identifiers: Vec<CanonicalIdentifier>,
+}
+
+/// An official identifier of a business (`SIREN`, `SIRET`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CanonicalIdentifier {
+    pub scheme: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CanonicalDeliveryPoint {
+    #[serde(default)]
+    pub care_of: Option<String>,
+    #[serde(default)]
+    pub external: Option<String>,
+    #[serde(default)]
+    pub internal: Option<String>,
+    #[serde(default)]
+    pub postbox: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CanonicalStreet {
+    /// The digits of the number (`2` for `2 BIS`).
+    #[serde(default)]
+    pub number: Option<String>,
+    /// `BIS`, `TER`, `QUATER` or a letter.
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub repetition_index: Option<StreetNumberExt>,
+    /// The french street type (`RUE`, `AVENUE`).
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub kind: Option<StreetType>,
+    /// The street name as written, with its type.
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CanonicalPostalDetails {
+    pub postcode: String,
+    pub town: String,
+    #[serde(default)]
+    pub town_location: Option<String>,
+    #[serde(default)]
+    pub locality: Option<String>,
+    #[serde(default)]
+    pub country_subdivision: Option<String>,
+}
+
+impl CanonicalAddress {
+    pub fn kind(&self) -> AddressKind {
+        match self.kind {
+            CanonicalKind::Individual => AddressKind::Individual,
+            CanonicalKind::Business => AddressKind::Business,
+        }
+    }
+}
+
+impl ConvertedAddress {
+    /// Reads a canonical address. Only the recipient and the country are
+    /// checked, the other fields being taken as they are.
+    pub fn from_canonical(address: CanonicalAddress) -> Result<Self, AddressConversionError> {
+        let kind = address.kind();
+        let CanonicalRecipient {
+            name,
+            company_name,
+            contact,
+            contact_person,
+            service,
+            identifiers,
+        } = address.recipient;
+
+        let recipient = match kind {
+            AddressKind::Individual => Recipient::Individual {
+                name: name.ok_or_else(|| AddressConversionError::missing("recipient.name"))?,
+            },
+            AddressKind::Business => {
+                let company_name = company_name
+                    .ok_or_else(|| AddressConversionError::missing("recipient.company_name"))?;
+                let identifiers = identifiers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, identifier)| {
+                        OrgIdentifier::parse_scheme(&identifier.scheme, &identifier.value)
+                            .map_err(|e| e.at(format!("recipient.identifiers[{i}]")))
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                if contact_person.is_none() && service.is_none() {
+                    Recipient::business(company_name, contact.as_deref(), identifiers)
+                } else {
+                    Recipient::Business {
+                        company_name,
+                        contact_person,
+                        service,
+                        identifiers,
+                    }
+                }
+            }
+        };
+        let country = Country::from_str(&address.country).map_err(|_| {
+            AddressConversionError::UnknownCountry {
+                field: "country".to_string(),
+                value: address.country.clone(),
+            }
+        })?;
+
+        Ok(ConvertedAddress::new(
+            kind,
+            recipient,
+            address.delivery_point.map(|point| DeliveryPoint {
+                care_of: point.care_of,
+                external: point.external,
+                internal: point.internal,
+                postbox: point.postbox,
+            }),
+            address.street.map(|street| Street {
+                number: street.number,
+                repetition_index: street.repetition_index,
+                kind: street.kind,
+                name: street.name,
+            }),
+            PostalDetails {
+                postcode: address.postal_details.postcode,
+                town: address.postal_details.town,
+                town_location: address.postal_details.town_location,
+                locality: address.postal_details.locality,
+                country_subdivision: address.postal_details.country_subdivision,
+            },
+            country,
+        )
+        .with_extras(address.extras))
+    }
+
+    /// Writes the address in the canonical format. Every address converts.
+    pub fn to_canonical(&self) -> CanonicalAddress {
+        let recipient = match &self.recipient {
+            Recipient::Individual { name } => CanonicalRecipient {
+                name: Some(name.clone()),
+                company_name: None,
+                contact: None,
+                contact_person: None,
+                service: None,
+                identifiers: Vec::new(),
+            },
+            Recipient::Business {
+                company_name,
+                contact_person,
+                service,
+                identifiers,
+            } => CanonicalRecipient {
+                name: None,
+                company_name: Some(company_name.clone()),
+                contact: self.recipient.denomination(),
+                contact_person: contact_person.clone(),
+                service: service.clone(),
+                identifiers: identifiers
+                    .iter()
+                    .map(|identifier| CanonicalIdentifier {
+                        scheme: identifier.scheme().to_string(),
+                        value: identifier.value().to_string(),
+                    })
+                    .collect(),
+            },
+        };
+
+        CanonicalAddress {
+            kind: match self.kind {
+                AddressKind::Individual => CanonicalKind::Individual,
+                AddressKind::Business => CanonicalKind::Business,
+            },
+            recipient,
+            delivery_point: self
+                .delivery_point
+                .clone()
+                .map(|point| CanonicalDeliveryPoint {
+                    care_of: point.care_of,
+                    external: point.external,
+                    internal: point.internal,
+                    postbox: point.postbox,
+                }),
+            street: self.street.clone().map(|street| CanonicalStreet {
+                number: street.number,
+                repetition_index: street.repetition_index,
+                kind: street.kind,
+                name: street.name,
+            }),
+            postal_details: CanonicalPostalDetails {
+                postcode: self.postal_details.postcode.clone(),
+                town: self.postal_details.town.clone(),
+                town_location: self.postal_details.town_location.clone(),
+                locality: self.postal_details.locality.clone(),
+                country_subdivision: self.postal_details.country_subdivision.clone(),
+            },
+            country: self.country.iso_code().to_string(),
+            extras: self.extras.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::address_conversion::AddressConvertible;
+    use crate::domain::FrenchAddress;
+
+    #[test]
+    fn it_should_convert_losslessly() {
+        let french: FrenchAddress = serde_json::from_value(json!({
+            "kind": "business",
+            "business_name": "Société DUPONT SIRET 732 829 320 00074",
+            "recipient": "Mademoiselle Lucie MARTIN",
+            "external_delivery": "Chez Mireille COPEAU Bâtiment B",
+            "street": "2BIS AV DES CHAMPS",
+            "distribution_info": "BP 90432",
+            "postal": "34092 MONTPELLIER CEDEX 5",
+            "country": "FRANCE",
+            "crm_id": 42
+        }))
+        .unwrap();
+        let address = ConvertedAddress::from_french(french).unwrap();
+
+        let canonical = serde_json::to_value(address.to_canonical()).unwrap();
+        assert_eq!(canonical["kind"], "business");
+        assert_eq!(canonical["recipient"]["identifiers"][0]["scheme"], "SIRET");
+        assert_eq!(canonical["street"]["number"], "2");
+        assert_eq!(canonical["street"]["repetition_index"], "BIS");
+        assert_eq!(canonical["street"]["kind"], "AVENUE");
+        assert_eq!(canonical["country"], "FR");
+        assert_eq!(canonical["crm_id"], 42);
+
+        let canonical: CanonicalAddress = serde_json::from_value(canonical).unwrap();
+        assert_eq!(
+            ConvertedAddress::from_canonical(canonical).unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn it_should_require_the_recipient() {
+        let canonical: CanonicalAddress = serde_json::from_value(json!({
+            "kind": "individual",
+            "recipient": { "company_name": "DURAND SA" },
+            "postal_details": { "postcode": "33380", "town": "MIOS" },
+            "country": "FR"
+        }))
+        .unwrap();
+
+        let error = ConvertedAddress::from_canonical(canonical).unwrap_err();
+        assert_eq!(error.field(), "recipient.name");
+    }
+}
@@ -0,0 +1,969 @@
+use serde::Serialize;
+
+use super::address::*;
+use super::brazilian_address::BrazilianAddress;
+use super::french_address::{FrenchAddress, FrenchAddressParser};
+use super::iso20022_address::IsoAddress;
+use super::portuguese_address::PortugueseAddress;
+
+/// The rules deciding where a field goes during a conversion.
+#[derive(Clone, Copy, Debug, strum_macros::Display, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingRule {
+    /// The building number is extracted from the street line.
+    #[strum(serialize = "street number extraction")]
+    StreetNumberExtraction,
+    /// The lieu-dit is recognized by its prefix, or written with it.
+    #[strum(serialize = "lieu-dit")]
+    LieuDit,
+    /// The post box (BP, CS, TSA) is split from the distribution information.
+    #[strum(serialize = "postbox split")]
+    PostboxSplit,
+    /// The rest of the distribution information is the town location.
+    #[strum(serialize = "town location split")]
+    TownLocationSplit,
+    /// The distribution information of an individual is kept as a post box.
+    #[strum(serialize = "distribution information as postbox")]
+    DistributionAsPostbox,
+    /// The person introduced by "CHEZ" or "C/O" is split from the delivery
+    /// line, or written first on it.
+    #[strum(serialize = "care of")]
+    CareOf,
+    /// The postal line is split into the postcode and the town.
+    #[strum(serialize = "postal line split")]
+    PostalSplit,
+    /// The SIREN or SIRET is read after the business name.
+    #[strum(serialize = "identifier on the name line")]
+    NameLineIdentifier,
+    /// The country is read from its name or code.
+    #[strum(serialize = "country lookup")]
+    CountryLookup,
+    /// Written on the street line, with the number first.
+    #[strum(serialize = "street line")]
+    StreetLine,
+    /// Written on the distribution information line.
+    #[strum(serialize = "distribution line")]
+    DistributionLine,
+    /// Written on the postal line, in the order of the country.
+    #[strum(serialize = "postal line")]
+    PostalLine,
+    /// Written as the country line of the mail origin.
+    #[strum(serialize = "country line")]
+    CountryLine,
+    /// Written as the ISO 3166 code of the country.
+    #[strum(serialize = "country code")]
+    CountryCode,
+    /// A building without number is written "S/N".
+    #[strum(serialize = "no number")]
+    NoNumber,
+    /// The line has been abbreviated to fit 38 characters.
+    #[strum(serialize = "abbreviation")]
+    Abbreviation,
+    /// Unknown fields are copied as they are.
+    #[strum(serialize = "custom field")]
+    CustomField,
+}
+
+/// A field mapped by one side of a conversion: from the input to the
+/// internal address, or from the internal address to the output. A field
+/// mapped to nothing is dropped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep {
+    pub from: String,
+    pub to: Option<String>,
+    pub rule: Option<MappingRule>,
+}
+
+fn step(from: &str, to: &str, rule: Option<MappingRule>) -> TraceStep {
+    TraceStep {
+        from: from.to_string(),
+        to: Some(to.to_string()),
+        rule,
+    }
+}
+
+fn dropped(from: &str) -> TraceStep {
+    TraceStep {
+        from: from.to_string(),
+        to: None,
+        rule: None,
+    }
+}
+
+/// How a field of the input ended up in the output.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldMapping {
+    /// The field of the input.
+    pub source: String,
+    /// The field of the internal address it populated, none when dropped.
+    pub field: Option<String>,
+    /// The field of the output, none when the output format has no room for
+    /// it.
+    pub target: Option<String>,
+    /// The rules applied along the way.
+    pub rules: Vec<MappingRule>,
+}
+
+/// The field mappings of a conversion, in the order of the input fields.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ConversionTrace {
+    pub mappings: Vec<FieldMapping>,
+}
+
+impl ConversionTrace {
+    /// Joins the steps from the input to the internal address with the steps
+    /// from the internal address to the output.
+    pub fn join(from: Vec<TraceStep>, to: Vec<TraceStep>) -> Self {
+        let mut mappings = Vec::new();
+
+        for source in from {
+            let Some(field) = source.to.clone() else {
+                mappings.push(FieldMapping {
+                    source: source.from,
+                    field: None,
+                    target: None,
+                    rules: source.rule.into_iter().collect(),
+                });
+                continue;
+            };
+
+            let targets: Vec<&TraceStep> = to.iter().filter(|step| step.from == field).collect();
+            if targets.is_empty() {
+                mappings.push(FieldMapping {
+                    source: source.from.clone(),
+                    field: Some(field.clone()),
+                    target: None,
+                    rules: source.rule.into_iter().collect(),
+                });
+            }
+            for target in targets {
+                mappings.push(FieldMapping {
+                    source: source.from.clone(),
+                    field: Some(field.clone()),
+                    target: target.to.clone(),
+                    rules: match (source.rule, target.rule) {
+                        (Some(from), Some(to)) if from != to => vec![from, to],
+                        (from, to) => from.or(to).into_iter().collect(),
+                    },
+                });
+            }
+        }
+
+        Self { mappings }
+    }
+
+    /// Records `rule` on the mappings through the internal `field`.
+    pub fn apply(&mut self, field: &str, rule: MappingRule) {
+        for mapping in &mut self.mappings {
+            if mapping.field.as_deref() == Some(field) && !mapping.rules.contains(&rule) {
+                mapping.rules.push(rule);
+            }
+        }
+    }
+
+    /// One line per mapping: `source -> field -> target [rules]`.
+    pub fn lines(&self) -> Vec<String> {
+        self.mappings
+            .iter()
+            .map(|mapping| {
+                let field = mapping.field.as_deref().unwrap_or("(dropped)");
+                let target = match (&mapping.field, &mapping.target) {
+                    (None, _) => String::new(),
+                    (Some(_), Some(target)) => format!(" -> {target}"),
+                    (Some(_), None) => " -> (dropped)".to_string(),
+                };
+                let rules = if mapping.rules.is_empty() {
+                    String::new()
+                } else {
+                    let rules: Vec<String> =
+                        mapping.rules.iter().map(|rule| rule.to_string()).collect();
+                    format!(" [{}]", rules.join(", "))
+                };
+
+                format!("{} -> {field}{target}{rules}", mapping.source)
+            })
+            .collect()
+    }
+}
+
+impl ConvertedAddress {
+    /// The internal fields holding a value, with their value.
+    pub(crate) fn field_values(&self) -> Vec<(&'static str, String)> {
+        let delivery_point = self.delivery_point.clone().unwrap_or(DeliveryPoint {
+            care_of: None,
+            external: None,
+            internal: None,
+            postbox: None,
+        });
+        let (name, company_name, contact_person, service) = match &self.recipient {
+            Recipient::Individual { name } => (Some(name.clone()), None, None, None),
+            Recipient::Business {
+                company_name,
+                contact_person,
+                service,
+                ..
+            } => (
+                None,
+                Some(company_name.clone()),
+                contact_person.clone(),
+                service.clone(),
+            ),
+        };
+        let identifiers = self
+            .recipient
+            .identifiers()
+            .iter()
+            .map(|identifier| identifier.value().to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        [
+            ("recipient.name", name),
+            ("recipient.company_name", company_name),
+            ("recipient.contact_person", contact_person),
+            ("recipient.service", service),
+            (
+                "recipient.identifiers",
+                (!identifiers.is_empty()).then_some(identifiers),
+            ),
+            ("delivery_point.care_of", delivery_point.care_of),
+            ("delivery_point.external", delivery_point.external),
+            ("delivery_point.internal", delivery_point.internal),
+            ("delivery_point.postbox", delivery_point.postbox),
+            (
+                "street.number",
+                self.street
+                    .as_ref()
+                    .and_then(|street| street.number.clone()),
+            ),
+            (
+                "street.name",
+                self.street.as_ref().map(|street| street.name.clone()),
+            ),
+            (
+                "postal_details.postcode",
+                Some(self.postal_details.postcode.clone()),
+            ),
+            (
+                "postal_details.town",
+                Some(self.postal_details.town.clone()),
+            ),
+            (
+                "postal_details.town_location",
+                self.postal_details.town_location.clone(),
+            ),
+            (
+                "postal_details.locality",
+                self.postal_details.locality.clone(),
+            ),
+            (
+                "postal_details.country_subdivision",
+                self.postal_details.country_subdivision.clone(),
+            ),
+            ("country", Some(self.country.iso_code().to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| value.map(|value| (field, value)))
+        .collect()
+    }
+
+    fn has(&self, field: &str) -> bool {
+        self.field_values().iter().any(|(name, _)| *name == field)
+    }
+
+    /// The internal fields whose value differs from `before`.
+    pub fn changed_fields(&self, before: &ConvertedAddress) -> Vec<&'static str> {
+        let before = before.field_values();
+
+        self.field_values()
+            .into_iter()
+            .filter(|field| !before.contains(field))
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    /// The custom fields, copied from the input to the output.
+    fn extras_steps(&self) -> Vec<TraceStep> {
+        self.extras
+            .keys()
+            .map(|key| {
+                step(
+                    key,
+                    &format!("extras.{key}"),
+                    Some(MappingRule::CustomField),
+                )
+            })
+            .collect()
+    }
+
+    fn extras_targets(&self) -> Vec<TraceStep> {
+        self.extras
+            .keys()
+            .map(|key| step(&format!("extras.{key}"), key, None))
+            .collect()
+    }
+
+    /// The steps from the french `source` to this address, converted from it.
+    pub fn trace_from_french(&self, source: &FrenchAddress) -> Vec<TraceStep> {
+        let mut steps = Vec::new();
+        let (street, distribution_info) = match source {
+            FrenchAddress::Individual(individual) => {
+                steps.push(step("name", "recipient.name", None));
+                if self.has("delivery_point.care_of") {
+                    steps.push(step(
+                        "internal_delivery",
+                        "delivery_point.care_of",
+                        Some(MappingRule::CareOf),
+                    ));
+                }
+                if self.has("delivery_point.internal") {
+                    steps.push(step("internal_delivery", "delivery_point.internal", None));
+                }
+                if individual.external_delivery.is_some() {
+                    steps.push(step("external_delivery", "delivery_point.external", None));
+                }
+                (individual.street.as_deref(), &individual.distribution_info)
+            }
+            FrenchAddress::Business(business) => {
+                steps.push(step("business_name", "recipient.company_name", None));
+                if let Ok((_, Some(_))) =
+                    FrenchAddressParser::parse_business_name(&business.business_name)
+                {
+                    steps.push(step(
+                        "business_name",
+                        "recipient.identifiers",
+                        Some(MappingRule::NameLineIdentifier),
+                    ));
+                }
+                if !business.identifiers.is_empty() {
+                    steps.push(step("identifiers", "recipient.identifiers", None));
+                }
+                if self.has("recipient.contact_person") {
+                    steps.push(step("recipient", "recipient.contact_person", None));
+                }
+                if self.has("recipient.service") {
+                    steps.push(step("recipient", "recipient.service", None));
+                }
+                if self.has("delivery_point.care_of") {
+                    steps.push(step(
+                        "external_delivery",
+                        "delivery_point.care_of",
+                        Some(MappingRule::CareOf),
+                    ));
+                }
+                if self.has("delivery_point.external") {
+                    steps.push(step("external_delivery", "delivery_point.external", None));
+                }
+                (business.street.as_deref(), &business.distribution_info)
+            }
+        };
+
+        let street_locality = street.and_then(FrenchAddressParser::parse_locality);
+        match (street, &street_locality, &self.street) {
+            (Some(_), Some(_), _) => steps.push(step(
+                "street",
+                "postal_details.locality",
+                Some(MappingRule::LieuDit),
+            )),
+            (Some(_), None, Some(parsed)) => {
+                if parsed.number.is_some() {
+                    steps.push(step(
+                        "street",
+                        "street.number",
+                        Some(MappingRule::StreetNumberExtraction),
+                    ));
+                }
+                steps.push(step("street", "street.name", None));
+            }
+            _ => {}
+        }
+
+        if distribution_info.is_some() {
+            let business = matches!(source, FrenchAddress::Business(_));
+            let postbox_rule = if business {
+                MappingRule::PostboxSplit
+            } else {
+                MappingRule::DistributionAsPostbox
+            };
+            let mut info_steps = Vec::new();
+
+            if self.has("delivery_point.postbox") {
+                info_steps.push(step(
+                    "distribution_info",
+                    "delivery_point.postbox",
+                    Some(postbox_rule),
+                ));
+            }
+            if self.has("postal_details.town_location") {
+                info_steps.push(step(
+                    "distribution_info",
+                    "postal_details.town_location",
+                    Some(MappingRule::TownLocationSplit),
+                ));
+            }
+            if street_locality.is_none() && self.has("postal_details.locality") {
+                info_steps.push(step(
+                    "distribution_info",
+                    "postal_details.locality",
+                    Some(MappingRule::LieuDit),
+                ));
+            }
+            if info_steps.is_empty() {
+                info_steps.push(dropped("distribution_info"));
+            }
+            steps.extend(info_steps);
+        }
+
+        steps.push(step(
+            "postal",
+            "postal_details.postcode",
+            Some(MappingRule::PostalSplit),
+        ));
+        steps.push(step(
+            "postal",
+            "postal_details.town",
+            Some(MappingRule::PostalSplit),
+        ));
+        steps.push(step("country", "country", Some(MappingRule::CountryLookup)));
+        steps.extend(self.extras_steps());
+
+        steps
+    }
+
+    /// The steps from the ISO 20022 `source` to this address, converted from
+    /// it.
+    pub fn trace_from_iso20022(&self, source: &IsoAddress) -> Vec<TraceStep> {
+        let mut steps = Vec::new();
+        let postal_address = match source {
+            IsoAddress::IndividualIsoAddress { postal_address, .. } => {
+                steps.push(step("name", "recipient.name", None));
+                postal_address
+            }
+            IsoAddress::BusinessIsoAddress {
+                organisation_identification,
+                postal_address,
+                ..
+            } => {
+                steps.push(step("business_name", "recipient.company_name", None));
+                if !organisation_identification.is_empty() {
+                    steps.push(step(
+                        "organisation_identification",
+                        "recipient.identifiers",
+                        None,
+                    ));
+                }
+                postal_address
+            }
+        };
+        // Without street, the town location is a lieu-dit.
+        let town_location = if self.has("postal_details.locality") {
+            ("postal_details.locality", Some(MappingRule::LieuDit))
+        } else {
+            ("postal_details.town_location", None)
+        };
+
+        let fields = [
+            (
+                "care_of",
+                postal_address.care_of.is_some(),
+                "delivery_point.care_of",
+                None,
+            ),
+            (
+                "department",
+                postal_address.department.is_some(),
+                // A department alone may be a contact person.
+                if self.has("recipient.service") {
+                    "recipient.service"
+                } else {
+                    "recipient.contact_person"
+                },
+                None,
+            ),
+            (
+                "sub_department",
+                postal_address.sub_department.is_some(),
+                "recipient.contact_person",
+                None,
+            ),
+            (
+                "street_name",
+                postal_address.street_name.is_some(),
+                "street.name",
+                None,
+            ),
+            (
+                "building_number",
+                postal_address.building_number.is_some(),
+                "street.number",
+                None,
+            ),
+            (
+                "floor",
+                postal_address.floor.is_some(),
+                "delivery_point.external",
+                None,
+            ),
+            (
+                "room",
+                postal_address.room.is_some(),
+                "delivery_point.internal",
+                None,
+            ),
+            (
+                "postbox",
+                postal_address.postbox.is_some(),
+                "delivery_point.postbox",
+                None,
+            ),
+            ("postcode", true, "postal_details.postcode", None),
+            ("town_name", true, "postal_details.town", None),
+            (
+                "town_location_name",
+                postal_address.town_location_name.is_some(),
+                town_location.0,
+                town_location.1,
+            ),
+            (
+                "country_sub_division",
+                postal_address.country_sub_division.is_some(),
+                "postal_details.country_subdivision",
+                None,
+            ),
+            ("country", true, "country", Some(MappingRule::CountryLookup)),
+        ];
+        steps.extend(
+            fields
+                .into_iter()
+                .filter(|(_, present, _, _)| *present)
+                .map(|(from, _, to, rule)| {
+                    self.mapped(&format!("postal_address.{from}"), to, rule)
+                }),
+        );
+        steps.extend(self.extras_steps());
+
+        steps
+    }
+
+    /// The steps from the portuguese `source` to this address, converted from
+    /// it.
+    pub fn trace_from_portuguese(&self, source: &PortugueseAddress) -> Vec<TraceStep> {
+        let fields = [
+            ("name", source.name.is_some(), self.name_field()),
+            (
+                "business_name",
+                source.business_name.is_some(),
+                "recipient.company_name",
+            ),
+            ("street", true, "street.name"),
+            ("door_number", source.door_number.is_some(), "street.number"),
+            ("floor", source.floor.is_some(), "delivery_point.internal"),
+            (
+                "locality",
+                source.locality.is_some(),
+                "postal_details.town_location",
+            ),
+            ("postal_code", true, "postal_details.postcode"),
+            ("postal_designation", true, "postal_details.town"),
+        ];
+
+        self.mapped_fields(&fields)
+    }
+
+    /// The steps from the brazilian `source` to this address, converted from
+    /// it.
+    pub fn trace_from_brazilian(&self, source: &BrazilianAddress) -> Vec<TraceStep> {
+        let fields = [
+            ("name", source.name.is_some(), self.name_field()),
+            (
+                "business_name",
+                source.business_name.is_some(),
+                "recipient.company_name",
+            ),
+            ("logradouro", true, "street.name"),
+            ("number", true, "street.number"),
+            (
+                "complement",
+                source.complement.is_some(),
+                "delivery_point.internal",
+            ),
+            ("bairro", true, "postal_details.town_location"),
+            ("city", true, "postal_details.town"),
+            ("state", true, "postal_details.country_subdivision"),
+            ("cep", true, "postal_details.postcode"),
+        ];
+
+        self.mapped_fields(&fields)
+            .into_iter()
+            .map(|mut step| {
+                if step.from == "number" && step.to.is_none() {
+                    step.rule = Some(MappingRule::NoNumber);
+                }
+                step
+            })
+            .collect()
+    }
+
+    /// The name of a business is its contact person, or its service when the
+    /// name only holds a service.
+    fn name_field(&self) -> &'static str {
+        match self.kind {
+            AddressKind::Individual => "recipient.name",
+            AddressKind::Business if self.has("recipient.contact_person") => {
+                "recipient.contact_person"
+            }
+            AddressKind::Business => "recipient.service",
+        }
+    }
+
+    /// The step from `from` to the internal field `to`, dropped when the
+    /// conversion left the field empty.
+    fn mapped(&self, from: &str, to: &str, rule: Option<MappingRule>) -> TraceStep {
+        if self.has(to) {
+            step(from, to, rule)
+        } else {
+            dropped(from)
+        }
+    }
+
+    fn mapped_fields(&self, fields: &[(&str, bool, &str)]) -> Vec<TraceStep> {
+        fields
+            .iter()
+            .filter(|(_, present, _)| *present)
+            .map(|(from, _, to)| self.mapped(from, to, None))
+            .chain(self.extras_steps())
+            .collect()
+    }
+
+    /// The steps from the fields of this address holding a value to the
+    /// `targets`, none when the output has no room for the field.
+    fn targets(&self, targets: &[(&str, Option<&str>, Option<MappingRule>)]) -> Vec<TraceStep> {
+        targets
+            .iter()
+            .filter(|(from, _, _)| self.has(from))
+            .map(|(from, to, rule)| TraceStep {
+                from: from.to_string(),
+                to: to.map(str::to_string),
+                rule: *rule,
+            })
+            .chain(self.extras_targets())
+            .collect()
+    }
+
+    /// The steps from a canonical address to this address, each field to the
+    /// internal field of the same path.
+    pub fn trace_from_canonical(&self) -> Vec<TraceStep> {
+        self.field_values()
+            .into_iter()
+            .map(|(field, _)| step(field, field, None))
+            .chain(self.extras_steps())
+            .collect()
+    }
+
+    /// The steps from this address to the canonical address it converts to.
+    pub fn trace_to_canonical(&self) -> Vec<TraceStep> {
+        self.field_values()
+            .into_iter()
+            .map(|(field, _)| step(field, field, None))
+            .chain(self.extras_targets())
+            .collect()
+    }
+
+    /// The steps from this address to the french address it converts to.
+    pub fn trace_to_french(&self) -> Vec<TraceStep> {
+        let business = self.kind == AddressKind::Business;
+        // The lieu-dit takes the street line when there is no street.
+        let locality = if self.street.is_some() {
+            "distribution_info"
+        } else {
+            "street"
+        };
+
+        self.targets(&[
+            ("recipient.name", Some("name"), None),
+            ("recipient.company_name", Some("business_name"), None),
+            ("recipient.identifiers", Some("identifiers"), None),
+            ("recipient.contact_person", Some("recipient"), None),
+            ("recipient.service", Some("recipient"), None),
+            (
+                "delivery_point.care_of",
+                Some(if business {
+                    "external_delivery"
+                } else {
+                    "internal_delivery"
+                }),
+                Some(MappingRule::CareOf),
+            ),
+            (
+                "delivery_point.internal",
+                (!business).then_some("internal_delivery"),
+                None,
+            ),
+            ("delivery_point.external", Some("external_delivery"), None),
+            (
+                "street.number",
+                Some("street"),
+                Some(MappingRule::StreetLine),
+            ),
+            ("street.name", Some("street"), Some(MappingRule::StreetLine)),
+            (
+                "delivery_point.postbox",
+                Some("distribution_info"),
+                Some(MappingRule::DistributionLine),
+            ),
+            (
+                "postal_details.locality",
+                Some(locality),
+                Some(MappingRule::LieuDit),
+            ),
+            (
+                "postal_details.town_location",
+                Some("distribution_info"),
+                Some(MappingRule::DistributionLine),
+            ),
+            (
+                "postal_details.postcode",
+                Some("postal"),
+                Some(MappingRule::PostalLine),
+            ),
+            (
+                "postal_details.town",
+                Some("postal"),
+                Some(MappingRule::PostalLine),
+            ),
+            ("postal_details.country_subdivision", None, None),
+            ("country", Some("country"), Some(MappingRule::CountryLine)),
+        ])
+    }
+
+    /// The steps from this address to the ISO 20022 address it converts to.
+    pub fn trace_to_iso20022(&self) -> Vec<TraceStep> {
+        // ISO 20022 has a single town location, the lieu-dit comes first.
+        let town_location =
+            (!self.has("postal_details.locality")).then_some("postal_address.town_location_name");
+
+        self.targets(&[
+            ("recipient.name", Some("name"), None),
+            ("recipient.company_name", Some("business_name"), None),
+            (
+                "recipient.identifiers",
+                Some("organisation_identification"),
+                None,
+            ),
+            (
+                "recipient.contact_person",
+                Some("postal_address.sub_department"),
+                None,
+            ),
+            ("recipient.service", Some("postal_address.department"), None),
+            (
+                "delivery_point.care_of",
+                Some("postal_address.care_of"),
+                None,
+            ),
+            ("street.name", Some("postal_address.street_name"), None),
+            (
+                "street.number",
+                Some("postal_address.building_number"),
+                None,
+            ),
+            (
+                "delivery_point.external",
+                Some("postal_address.floor"),
+                None,
+            ),
+            ("delivery_point.internal", Some("postal_address.room"), None),
+            (
+                "delivery_point.postbox",
+                Some("postal_address.postbox"),
+                None,
+            ),
+            (
+                "postal_details.postcode",
+                Some("postal_address.postcode"),
+                None,
+            ),
+            (
+                "postal_details.town",
+                Some("postal_address.town_name"),
+                None,
+            ),
+            (
+                "postal_details.locality",
+                Some("postal_address.town_location_name"),
+                Some(MappingRule::LieuDit),
+            ),
+            ("postal_details.town_location", town_location, None),
+            (
+                "postal_details.country_subdivision",
+                Some("postal_address.country_sub_division"),
+                None,
+            ),
+            (
+                "country",
+                Some("postal_address.country"),
+                Some(MappingRule::CountryCode),
+            ),
+        ])
+    }
+
+    /// The steps from this address to the portuguese address it converts to.
+    pub fn trace_to_portuguese(&self) -> Vec<TraceStep> {
+        let locality = (!self.has("postal_details.town_location")).then_some("locality");
+
+        self.targets(&[
+            ("recipient.name", Some("name"), None),
+            ("recipient.contact_person", Some("name"), None),
+            ("recipient.service", Some("name"), None),
+            ("recipient.company_name", Some("business_name"), None),
+            ("recipient.identifiers", None, None),
+            ("street.name", Some("street"), None),
+            ("street.number", Some("door_number"), None),
+            ("delivery_point.internal", Some("floor"), None),
+            ("delivery_point.care_of", None, None),
+            ("delivery_point.external", None, None),
+            ("delivery_point.postbox", None, None),
+            ("postal_details.town_location", Some("locality"), None),
+            ("postal_details.locality", locality, None),
+            ("postal_details.postcode", Some("postal_code"), None),
+            ("postal_details.town", Some("postal_designation"), None),
+            ("postal_details.country_subdivision", None, None),
+        ])
+    }
+
+    /// The steps from this address to the brazilian address it converts to.
+    pub fn trace_to_brazilian(&self) -> Vec<TraceStep> {
+        let bairro = (!self.has("postal_details.town_location")).then_some("bairro");
+
+        self.targets(&[
+            ("recipient.name", Some("name"), None),
+            ("recipient.contact_person", Some("name"), None),
+            ("recipient.service", Some("name"), None),
+            ("recipient.company_name", Some("business_name"), None),
+            ("recipient.identifiers", None, None),
+            ("street.name", Some("logradouro"), None),
+            ("street.number", Some("number"), None),
+            ("delivery_point.internal", Some("complement"), None),
+            ("delivery_point.care_of", None, None),
+            ("delivery_point.external", None, None),
+            ("delivery_point.postbox", None, None),
+            ("postal_details.town_location", Some("bairro"), None),
+            ("postal_details.locality", bairro, None),
+            ("postal_details.postcode", Some("cep"), None),
+            ("postal_details.town", Some("city"), None),
+            ("postal_details.country_subdivision", Some("state"), None),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AddressConvertible, BusinessFrenchAddress, IndividualFrenchAddress};
+    use serde_json::Map;
+
+    fn mapping<'a>(trace: &'a ConversionTrace, source: &str, field: &str) -> &'a FieldMapping {
+        trace
+            .mappings
+            .iter()
+            .find(|mapping| mapping.source == source && mapping.field.as_deref() == Some(field))
+            .unwrap_or_else(|| panic!("no mapping from {source} to {field}: {trace:?}"))
+    }
+
+    #[test]
+    fn it_should_trace_the_distribution_info_split() {
+        let french = FrenchAddress::Business(BusinessFrenchAddress {
+            business_name: "Société DUPONT".to_string(),
+            identifiers: Vec::new(),
+            recipient: None,
+            external_delivery: None,
+            street: Some("56 RUE EMILE ZOLA".to_string()),
+            distribution_info: Some("BP 90432 MONTFERRIER SUR LEZ".to_string()),
+            postal: "34092 MONTPELLIER CEDEX 5".to_string(),
+            country: "FRANCE".to_string(),
+            extras: Map::new(),
+        });
+        let converted = ConvertedAddress::from_french(french.clone()).unwrap();
+        let trace = ConversionTrace::join(
+            converted.trace_from_french(&french),
+            converted.trace_to_iso20022(),
+        );
+
+        let postbox = mapping(&trace, "distribution_info", "delivery_point.postbox");
+        assert_eq!(postbox.target.as_deref(), Some("postal_address.postbox"));
+        assert_eq!(postbox.rules, vec![MappingRule::PostboxSplit]);
+
+        let town_location = mapping(&trace, "distribution_info", "postal_details.town_location");
+        assert_eq!(
+            town_location.target.as_deref(),
+            Some("postal_address.town_location_name")
+        );
+
+        let number = mapping(&trace, "street", "street.number");
+        assert_eq!(number.rules, vec![MappingRule::StreetNumberExtraction]);
+    }
+
+    #[test]
+    fn it_should_trace_dropped_fields() {
+        let french = FrenchAddress::Individual(IndividualFrenchAddress {
+            name: "Monsieur Jean DELHOURME".to_string(),
+            internal_delivery: None,
+            external_delivery: None,
+            street: Some("LIEU DIT LES AUBIERS".to_string()),
+            distribution_info: Some("LIEU DIT CAUDOS".to_string()),
+            postal: "33380 MIOS".to_string(),
+            country: "FRANCE".to_string(),
+            extras: Map::new(),
+        });
+        let converted = ConvertedAddress::from_french(french.clone()).unwrap();
+        let trace = ConversionTrace::join(
+            converted.trace_from_french(&french),
+            converted.trace_to_iso20022(),
+        );
+
+        let street = mapping(&trace, "street", "postal_details.locality");
+        assert_eq!(
+            street.target.as_deref(),
+            Some("postal_address.town_location_name")
+        );
+        // The street lieu-dit comes first, the other one has no room.
+        assert!(trace
+            .lines()
+            .contains(&"distribution_info -> (dropped)".to_string()));
+    }
+
+    #[test]
+    fn it_should_trace_missing_numbers() {
+        let brazilian = BrazilianAddress {
+            name: Some("Sra. Maria Souza".to_string()),
+            business_name: None,
+            logradouro: "Rua da Consolação".to_string(),
+            number: "S/N".to_string(),
+            complement: None,
+            bairro: "Consolação".to_string(),
+            city: "São Paulo".to_string(),
+            state: "SP".to_string(),
+            cep: "01302-000".to_string(),
+            extras: Map::new(),
+        };
+        let converted = ConvertedAddress::from_brazilian(brazilian.clone()).unwrap();
+        let trace = ConversionTrace::join(
+            converted.trace_from_brazilian(&brazilian),
+            converted.trace_to_iso20022(),
+        );
+
+        let number = trace
+            .mappings
+            .iter()
+            .find(|mapping| mapping.source == "number")
+            .unwrap();
+        assert_eq!(number.field, None);
+        assert_eq!(number.rules, vec![MappingRule::NoNumber]);
+        assert_eq!(
+            mapping(&trace, "bairro", "postal_details.town_location")
+                .target
+                .as_deref(),
+            Some("postal_address.town_location_name")
+        );
+    }
+}
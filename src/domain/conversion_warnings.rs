@@ -0,0 +1,528 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::address::*;
+use super::address_conversion::{AddressConversionError, AddressConvertible};
+use super::conversion_trace::TraceStep;
+use super::french_address::FrenchAddress;
+use super::iso20022_address::IsoAddress;
+use super::normalizer::MAX_LINE_LENGTH;
+use super::validation_policy::ValidationCheck;
+
+/// The longest ISO 20022 names (Max140Text).
+const ISO_MAX_NAME_LENGTH: usize = 140;
+/// The longest ISO 20022 postal address fields, after their type (Max16Text,
+/// Max35Text, Max70Text, Max140Text).
+const ISO_MAX_LENGTHS: &[(&str, usize)] = &[
+    ("postal_address.care_of", 140),
+    ("postal_address.street_name", 70),
+    ("postal_address.building_number", 16),
+    ("postal_address.floor", 70),
+    ("postal_address.room", 70),
+    ("postal_address.postbox", 16),
+    ("postal_address.department", 70),
+    ("postal_address.sub_department", 70),
+    ("postal_address.postcode", 16),
+    ("postal_address.town_name", 35),
+    ("postal_address.town_location_name", 35),
+    ("postal_address.country_sub_division", 35),
+];
+
+/// A non-fatal issue of a conversion: the address is converted, but not as
+/// read.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "warning", rename_all = "snake_case")]
+pub enum ConversionWarning {
+    /// The output format has no room for the field of the internal address
+    /// (e.g., `postal_details.town_location` beside a lieu-dit in ISO 20022).
+    DroppedField { field: String },
+    /// The field of the output is longer than its format allows, 38
+    /// characters for the lines of the french addresses.
+    LongLine {
+        field: String,
+        length: usize,
+        max: usize,
+    },
+    /// The ISO 20022 element was cut at its limit, see
+    /// [`LongFieldPolicy::Truncate`].
+    TruncatedField {
+        field: String,
+        length: usize,
+        max: usize,
+    },
+    /// The end of the ISO 20022 element was moved to the next one, see
+    /// [`LongFieldPolicy::Wrap`].
+    WrappedField {
+        field: String,
+        into: String,
+        max: usize,
+    },
+    /// The field failed a validation whose severity is warn, see
+    /// [`ValidationPolicy`](super::ValidationPolicy).
+    FailedValidation {
+        check: ValidationCheck,
+        field: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ConversionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionWarning::DroppedField { field } => {
+                write!(f, "{field} dropped in the target format")
+            }
+            ConversionWarning::LongLine { field, length, max } => {
+                write!(f, "{field} exceeds {max} characters ({length})")
+            }
+            ConversionWarning::TruncatedField { field, length, max } => {
+                write!(f, "{field} truncated to {max} characters ({length})")
+            }
+            ConversionWarning::WrappedField { field, into, max } => {
+                write!(f, "{field} exceeds {max} characters, wrapped into {into}")
+            }
+            ConversionWarning::FailedValidation {
+                check,
+                field,
+                value,
+            } => write!(f, "{field}: invalid {check} `{value}`"),
+        }
+    }
+}
+
+/// The warnings of a conversion, in the order of the fields.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Warnings(Vec<ConversionWarning>);
+
+impl Warnings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, warning: ConversionWarning) {
+        self.0.push(warning);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ConversionWarning> {
+        self.0.iter()
+    }
+
+    /// Appends the `other` warnings after these ones.
+    pub fn extend(&mut self, other: Warnings) {
+        self.0.extend(other.0);
+    }
+
+    /// Warns about the `field` of the output longer than `max` characters.
+    fn check_length(&mut self, field: &str, value: &str, max: usize) {
+        let length = value.chars().count();
+        if length > max {
+            self.push(ConversionWarning::LongLine {
+                field: field.to_string(),
+                length,
+                max,
+            });
+        }
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = ConversionWarning;
+    type IntoIter = std::vec::IntoIter<ConversionWarning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Warnings {
+    type Item = &'a ConversionWarning;
+    type IntoIter = std::slice::Iter<'a, ConversionWarning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl ConvertedAddress {
+    /// Same as [`AddressConvertible::to_french_from`], also returning the
+    /// fields dropped and the lines longer than 38 characters.
+    pub fn to_french_with_warnings(
+        &self,
+        origin: MailOrigin,
+    ) -> Result<(FrenchAddress, Warnings), AddressConversionError> {
+        let french = self.to_french_from(origin)?;
+        let mut warnings = self.dropped_fields(&self.trace_to_french());
+
+        for (field, line) in french_lines(&french) {
+            warnings.check_length(field, line, MAX_LINE_LENGTH);
+        }
+
+        Ok((french, warnings))
+    }
+
+    /// Same as [`AddressConvertible::to_iso20022_with`], also returning the
+    /// fields dropped and what became of the ones longer than their ISO 20022
+    /// type allows.
+    pub fn to_iso20022_with_warnings(
+        &self,
+        profile: IsoProfile,
+    ) -> Result<(IsoAddress, Warnings), AddressConversionError> {
+        let mut iso = self.to_iso20022_unfitted(profile)?;
+        let mut warnings = self.dropped_fields(&self.trace_to_iso20022());
+        warnings.extend(fit_iso_lengths(&mut iso, profile.long_fields)?);
+
+        Ok((iso, warnings))
+    }
+
+    /// The fields of this address dropped by the `steps` to an output, see
+    /// [`ConvertedAddress::trace_to_french`] and the other formats. The
+    /// subdivision of an overseas territory isn't lost, its postcode tells
+    /// it.
+    pub fn dropped_fields(&self, steps: &[TraceStep]) -> Warnings {
+        let overseas_subdivision = self
+            .overseas_territory()
+            .map(|territory| territory.subdivision_code());
+        let implied = |field: &str| {
+            field == "postal_details.country_subdivision"
+                && overseas_subdivision.is_some()
+                && self.postal_details.country_subdivision == overseas_subdivision
+        };
+
+        Warnings(
+            steps
+                .iter()
+                .filter(|step| step.to.is_none() && !implied(&step.from))
+                .map(|step| ConversionWarning::DroppedField {
+                    field: step.from.clone(),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Applies `policy` to the elements of `iso` longer than their type allows,
+/// returning what was done.
+pub(super) fn fit_iso_lengths(
+    iso: &mut IsoAddress,
+    policy: LongFieldPolicy,
+) -> Result<Warnings, AddressConversionError> {
+    let (name_field, name, postal) = match iso {
+        IsoAddress::IndividualIsoAddress {
+            name,
+            postal_address,
+            ..
+        } => ("name", name, postal_address),
+        IsoAddress::BusinessIsoAddress {
+            business_name,
+            postal_address,
+            ..
+        } => ("business_name", business_name, postal_address),
+    };
+    let mut fitter = LengthFitter {
+        policy,
+        warnings: Warnings::new(),
+    };
+    fitter.fit(name_field, name, ISO_MAX_NAME_LENGTH, None)?;
+
+    // An element is only wrapped into the next one when the latter is empty.
+    let wraps_into = |field: &'static str, next: &Option<String>| next.is_none().then_some(field);
+    let room = wraps_into("postal_address.room", &postal.room);
+    let sub_department = wraps_into("postal_address.sub_department", &postal.sub_department);
+    let elements = [
+        ("postal_address.care_of", &mut postal.care_of, None),
+        ("postal_address.street_name", &mut postal.street_name, None),
+        (
+            "postal_address.building_number",
+            &mut postal.building_number,
+            None,
+        ),
+        ("postal_address.floor", &mut postal.floor, room),
+        ("postal_address.room", &mut postal.room, None),
+        ("postal_address.postbox", &mut postal.postbox, None),
+        (
+            "postal_address.department",
+            &mut postal.department,
+            sub_department,
+        ),
+        (
+            "postal_address.sub_department",
+            &mut postal.sub_department,
+            None,
+        ),
+    ];
+    // The end of a wrapped element goes to the next one, checked in turn.
+    let mut wrapped = None;
+    for (field, value, next) in elements {
+        if let Some(end) = wrapped.take() {
+            *value = Some(end);
+        }
+        if let Some(value) = value {
+            wrapped = fitter.fit(field, value, iso_max_length(field), next)?;
+        }
+    }
+
+    fitter.fit(
+        "postal_address.postcode",
+        &mut postal.postcode,
+        iso_max_length("postal_address.postcode"),
+        None,
+    )?;
+    fitter.fit(
+        "postal_address.town_name",
+        &mut postal.town_name,
+        iso_max_length("postal_address.town_name"),
+        None,
+    )?;
+    for (field, value) in [
+        (
+            "postal_address.town_location_name",
+            &mut postal.town_location_name,
+        ),
+        (
+            "postal_address.country_sub_division",
+            &mut postal.country_sub_division,
+        ),
+    ] {
+        if let Some(value) = value {
+            fitter.fit(field, value, iso_max_length(field), None)?;
+        }
+    }
+
+    Ok(fitter.warnings)
+}
+
+fn iso_max_length(field: &str) -> usize {
+    ISO_MAX_LENGTHS
+        .iter()
+        .find(|(name, _)| *name == field)
+        .map_or(usize::MAX, |(_, max)| *max)
+}
+
+/// Applies a [`LongFieldPolicy`] to the elements one by one.
+struct LengthFitter {
+    policy: LongFieldPolicy,
+    warnings: Warnings,
+}
+
+impl LengthFitter {
+    /// Fits `value` in `max` characters, returning its end when it's wrapped
+    /// into the element `next`.
+    fn fit(
+        &mut self,
+        field: &str,
+        value: &mut String,
+        max: usize,
+        next: Option<&str>,
+    ) -> Result<Option<String>, AddressConversionError> {
+        let length = value.chars().count();
+        if length <= max {
+            return Ok(None);
+        }
+        let limit = value
+            .char_indices()
+            .nth(max)
+            .map_or(value.len(), |(i, _)| i);
+
+        match (self.policy, next) {
+            (LongFieldPolicy::Keep, _) => self.warnings.check_length(field, value, max),
+            (LongFieldPolicy::Error, _) => {
+                return Err(AddressConversionError::TooLong {
+                    field: field.to_string(),
+                    value: value.clone(),
+                    max,
+                })
+            }
+            (LongFieldPolicy::Wrap, Some(next)) => {
+                // At the last space within the limit, if any.
+                let split = if value[limit..].starts_with(' ') {
+                    limit
+                } else {
+                    value[..limit]
+                        .rfind(' ')
+                        .filter(|&i| i > 0)
+                        .unwrap_or(limit)
+                };
+                let end = value[split..].trim_start().to_string();
+                value.truncate(split);
+                value.truncate(value.trim_end().len());
+                self.warnings.push(ConversionWarning::WrappedField {
+                    field: field.to_string(),
+                    into: next.to_string(),
+                    max,
+                });
+
+                return Ok(Some(end));
+            }
+            (LongFieldPolicy::Truncate | LongFieldPolicy::Wrap, _) => {
+                value.truncate(limit);
+                value.truncate(value.trim_end().len());
+                self.warnings.push(ConversionWarning::TruncatedField {
+                    field: field.to_string(),
+                    length,
+                    max,
+                });
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The lines of a french address, with their field.
+fn french_lines(french: &FrenchAddress) -> Vec<(&'static str, &str)> {
+    let (name, lines, postal, country) = match french {
+        FrenchAddress::Individual(address) => (
+            ("name", address.name.as_str()),
+            [
+                ("internal_delivery", &address.internal_delivery),
+                ("external_delivery", &address.external_delivery),
+                ("street", &address.street),
+                ("distribution_info", &address.distribution_info),
+            ],
+            &address.postal,
+            &address.country,
+        ),
+        FrenchAddress::Business(address) => (
+            ("business_name", address.business_name.as_str()),
+            [
+                ("recipient", &address.recipient),
+                ("external_delivery", &address.external_delivery),
+                ("street", &address.street),
+                ("distribution_info", &address.distribution_info),
+            ],
+            &address.postal,
+            &address.country,
+        ),
+    };
+
+    let mut named = vec![name];
+    named.extend(
+        lines
+            .into_iter()
+            .filter_map(|(field, line)| line.as_deref().map(|line| (field, line))),
+    );
+    named.extend([("postal", postal.as_str()), ("country", country.as_str())]);
+    named
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConversionWarning;
+    use crate::domain::{
+        AddressConversionError, AddressConvertible, ConvertedAddress, FrenchAddress, IsoAddress,
+        IsoProfile, LongFieldPolicy,
+    };
+
+    #[test]
+    fn it_should_warn_about_the_dropped_fields_and_long_lines() {
+        let french: FrenchAddress = serde_json::from_str(
+            r#"{
+                "business_name": "SOCIETE DUPONT",
+                "street": "LIEU DIT LES GRANDS CHAMPS",
+                "distribution_info": "HAMEAU DU VIEUX MOULIN DE LA GRANDE PRAIRIE",
+                "postal": "33380 MIOS",
+                "country": "FRANCE"
+            }"#,
+        )
+        .unwrap();
+        let address = ConvertedAddress::from_french(french).unwrap();
+
+        let (_, warnings) = address
+            .to_iso20022_with_warnings(IsoProfile::default())
+            .unwrap();
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["postal_details.town_location dropped in the target format"]
+        );
+
+        let (_, warnings) = address.to_french_with_warnings(Default::default()).unwrap();
+        assert_eq!(
+            warnings.into_iter().collect::<Vec<_>>(),
+            [ConversionWarning::LongLine {
+                field: "distribution_info".to_string(),
+                length: 43,
+                max: 38,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_should_fit_the_long_iso_elements() {
+        let floor =
+            "RESIDENCE DES CAPUCINS BATIMENT QUATER ESCALIER C PORTE GAUCHE APRES LE PORCHE";
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": "Monsieur Jean DELHOURME",
+            "external_delivery": floor,
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE",
+        }))
+        .unwrap();
+        let address = ConvertedAddress::from_french(french).unwrap();
+        let convert = |long_fields| {
+            let profile = IsoProfile {
+                long_fields,
+                ..Default::default()
+            };
+            address
+                .to_iso20022_with_warnings(profile)
+                .map(|(iso, warnings)| match iso {
+                    IsoAddress::IndividualIsoAddress { postal_address, .. } => {
+                        (postal_address.floor, postal_address.room, warnings)
+                    }
+                    iso => panic!("expected an individual address, got {iso:?}"),
+                })
+        };
+
+        let (kept, _, warnings) = convert(LongFieldPolicy::Keep).unwrap();
+        assert_eq!(kept.as_deref(), Some(floor));
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["postal_address.floor exceeds 70 characters (78)"]
+        );
+
+        let (truncated, _, warnings) = convert(LongFieldPolicy::Truncate).unwrap();
+        assert_eq!(truncated.as_deref(), Some(&floor[..70]));
+        assert_eq!(
+            warnings.into_iter().collect::<Vec<_>>(),
+            [ConversionWarning::TruncatedField {
+                field: "postal_address.floor".to_string(),
+                length: 78,
+                max: 70,
+            }]
+        );
+
+        let (floor_start, room, warnings) = convert(LongFieldPolicy::Wrap).unwrap();
+        assert_eq!(
+            floor_start.as_deref(),
+            Some("RESIDENCE DES CAPUCINS BATIMENT QUATER ESCALIER C PORTE GAUCHE APRES")
+        );
+        assert_eq!(room.as_deref(), Some("LE PORCHE"));
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["postal_address.floor exceeds 70 characters, wrapped into postal_address.room"]
+        );
+
+        let error = convert(LongFieldPolicy::Error).unwrap_err();
+        assert!(matches!(
+            &error,
+            AddressConversionError::TooLong { field, max: 70, .. } if field == "postal_address.floor"
+        ));
+        assert_eq!(error.code(), "E_TOO_LONG");
+        let profile = IsoProfile {
+            long_fields: LongFieldPolicy::Error,
+            ..Default::default()
+        };
+        assert!(address.to_iso20022_with(profile).is_err());
+    }
+}
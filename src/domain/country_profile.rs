@@ -0,0 +1,128 @@
+//! The postal conventions departing from the french ones, per country. The
+//! countries missing from [`COUNTRY_PROFILES`] follow the french conventions.
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::address::{Country, Street};
+use super::french_address::FrenchAddressParser;
+use super::street_number::NumberExtStyle;
+
+/// A street name followed by its number: digits with an optional letter
+/// ("12A"), and the staircase and door of the austrian addresses ("45/3/12").
+static NUMBER_LAST_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+?)\s+(\d+[a-zA-Z]?(?:\s*[/-]\s*\d+[a-zA-Z]?)*)$").unwrap());
+
+/// Where the number is written on the street line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StreetOrder {
+    /// Before the name ("25 RUE DE L'EGLISE").
+    #[default]
+    NumberFirst,
+    /// After the name, as in the germanic countries ("STEPHANSPLATZ 1").
+    NumberLast,
+}
+
+/// The postal conventions of a country.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CountryProfile {
+    pub street_order: StreetOrder,
+}
+
+/// The germanic conventions (DIN 5008), shared by Germany and Austria.
+const GERMANIC: CountryProfile = CountryProfile {
+    street_order: StreetOrder::NumberLast,
+};
+
+/// The profiles of the countries, by ISO code. Luxembourg writes the number
+/// first, like France.
+pub const COUNTRY_PROFILES: &[(&str, CountryProfile)] = &[("DE", GERMANIC), ("AT", GERMANIC)];
+
+impl CountryProfile {
+    /// The profile of `country`, the french conventions when it has none.
+    pub fn of(country: &Country) -> Self {
+        COUNTRY_PROFILES
+            .iter()
+            .find(|(code, _)| *code == country.iso_code())
+            .map_or_else(Self::default, |(_, profile)| *profile)
+    }
+
+    /// Splits a street line into its optional number and its name. A line
+    /// written in the french order is still read when the number comes last
+    /// ("1 STEPHANSPLATZ").
+    pub fn split_street<'a>(&self, line: &'a str) -> Option<(Option<&'a str>, &'a str)> {
+        if self.street_order == StreetOrder::NumberLast {
+            if let Some(caps) = NUMBER_LAST_REGEX.captures(line) {
+                return Some((
+                    caps.get(2).map(|m| m.as_str()),
+                    caps.get(1).map_or("", |m| m.as_str()),
+                ));
+            }
+        }
+
+        FrenchAddressParser::split_street(line)
+    }
+
+    /// Writes the street line, the french labels writing the repetition index
+    /// apart from the number ("2 BIS RUE DES TERRASSES").
+    pub fn street_line(&self, street: &Street) -> String {
+        match self.street_order {
+            StreetOrder::NumberFirst => match street.full_number(NumberExtStyle::Spaced) {
+                Some(number) => format!("{number} {}", street.name),
+                None => street.name.clone(),
+            },
+            StreetOrder::NumberLast => match street.full_number(NumberExtStyle::Attached) {
+                Some(number) => format!("{} {number}", street.name),
+                None => street.name.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn germanic_streets_have_the_number_last() {
+        let austria = CountryProfile::of(&Country::Austria);
+        assert_eq!(austria.street_order, StreetOrder::NumberLast);
+        assert_eq!(CountryProfile::of(&Country::Germany), austria);
+        assert_eq!(
+            CountryProfile::of(&Country::Luxembourg).street_order,
+            StreetOrder::NumberFirst
+        );
+
+        assert_eq!(
+            austria.split_street("STEPHANSPLATZ 1"),
+            Some((Some("1"), "STEPHANSPLATZ"))
+        );
+        assert_eq!(
+            austria.split_street("MARIAHILFER STRASSE 45/3/12"),
+            Some((Some("45/3/12"), "MARIAHILFER STRASSE"))
+        );
+        assert_eq!(
+            austria.split_street("STRASSE DES 17. JUNI 135A"),
+            Some((Some("135A"), "STRASSE DES 17. JUNI"))
+        );
+        // The french order is still read
+        assert_eq!(
+            austria.split_street("1 STEPHANSPLATZ"),
+            Some((Some("1"), "STEPHANSPLATZ"))
+        );
+        assert_eq!(austria.split_street("GRABEN"), Some((None, "GRABEN")));
+    }
+
+    #[test]
+    fn street_lines_follow_the_order() {
+        let street = Street::new(Some("12A".to_string()), "HAUPTSTRASSE".to_string());
+        assert_eq!(
+            CountryProfile::of(&Country::Austria).street_line(&street),
+            "HAUPTSTRASSE 12A"
+        );
+        let street = Street::new(Some("2BIS".to_string()), "RUE DES TERRASSES".to_string());
+        assert_eq!(
+            CountryProfile::of(&Country::France).street_line(&street),
+            "2 BIS RUE DES TERRASSES"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Hand-rolled readers of the street and postal lines, matching what the
+//! built-in regexes of [`FrenchParserRules`] capture without running a regex
+//! engine, see [`FrenchParserRules::with_fast_parse`].
+//!
+//! The regexes read Unicode digits, spaces and case folding. The readers
+//! handle the ASCII digits and the Unicode spaces, and give up on the few
+//! lines where a non-ASCII character could change the captures (e.g., an
+//! Arabic-Indic digit after the number), which are read by the regex.
+//!
+//! [`FrenchParserRules`]: super::FrenchParserRules
+//! [`FrenchParserRules::with_fast_parse`]: super::FrenchParserRules::with_fast_parse
+
+/// The line can't be read without the regex.
+#[derive(Debug, PartialEq)]
+pub(super) struct Undecided;
+
+/// The repetition indexes written after a spaced number (e.g., "2 BIS").
+const SPACED_INDEXES: [&str; 3] = ["BIS", "TER", "QUATER"];
+
+/// Whether `c` may be a `\d` of the regexes, which are the Unicode decimal
+/// digits.
+fn is_non_ascii_digit(c: char) -> bool {
+    !c.is_ascii() && c.is_numeric()
+}
+
+/// Same as the built-in `^(?:(\d+(?:[a-zA-Z]+|\s+(?i:bis|ter|quater)\b)?) )?(.+)$`:
+/// the optional number and its repetition index, then the name.
+pub(super) fn split_street(line: &str) -> Result<Option<(Option<&str>, &str)>, Undecided> {
+    if line.contains('\n') {
+        return Err(Undecided);
+    }
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if line[digits..].starts_with(is_non_ascii_digit) {
+        return Err(Undecided);
+    }
+    if digits == 0 {
+        return Ok(Some((None, line)));
+    }
+
+    // The name follows the number and a single space.
+    let split_at = |number_len: usize| {
+        let name = line[number_len..].strip_prefix(' ')?;
+        (!name.is_empty()).then(|| (Some(&line[..number_len]), name))
+    };
+    let after = &line[digits..];
+
+    // An attached index ("2BIS"), the letters being all taken.
+    let letters = after.bytes().take_while(u8::is_ascii_alphabetic).count();
+    if letters > 0 {
+        if let Some(split) = split_at(digits + letters) {
+            return Ok(Some(split));
+        }
+    }
+
+    // A spaced index ("2 BIS"), the spaces being all taken.
+    let index = after.trim_start();
+    if index.len() < after.len() {
+        for spaced in SPACED_INDEXES {
+            if starts_with_ignore_case(index, spaced)? {
+                let number_len = line.len() - index.len() + spaced.len();
+                if let Some(split) = split_at(number_len) {
+                    return Ok(Some(split));
+                }
+            }
+        }
+    }
+
+    Ok(Some(split_at(digits).unwrap_or((None, line))))
+}
+
+/// Whether `text` starts with the ASCII `prefix`, regardless of the case.
+fn starts_with_ignore_case(text: &str, prefix: &str) -> Result<bool, Undecided> {
+    let mut chars = text.chars();
+    for expected in prefix.chars() {
+        match chars.next() {
+            // The long s is folded to `s` by the case insensitive regex.
+            Some('ſ') => return Err(Undecided),
+            Some(c) if c.eq_ignore_ascii_case(&expected) => {}
+            _ => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+/// Same as the built-in `^(\d{5})\s+(.+)$`: the postcode, then the town.
+pub(super) fn split_postal(line: &str) -> Result<Option<(&str, &str)>, Undecided> {
+    let mut chars = line.char_indices();
+    for _ in 0..5 {
+        match chars.next() {
+            Some((_, c)) if c.is_ascii_digit() => {}
+            Some((_, c)) if is_non_ascii_digit(c) => return Err(Undecided),
+            _ => return Ok(None),
+        }
+    }
+    let (postcode, after) = line.split_at(5);
+
+    let town = after.trim_start();
+    let spaces = &after[..after.len() - town.len()];
+    if spaces.is_empty() {
+        return Ok(None);
+    }
+    if !town.is_empty() {
+        return Ok((!town.contains('\n')).then_some((postcode, town)));
+    }
+
+    // Only spaces: the town is the last one, the others separating it.
+    let last = spaces.chars().next_back().map_or(0, char::len_utf8);
+    let town = &after[after.len() - last..];
+    Ok((spaces.len() > last && town != "\n").then_some((postcode, town)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_postal, split_street, Undecided};
+    use crate::application::generate::AddressGenerator;
+    use crate::domain::french_parser_rules::{POSTAL_VECTORS, STREET_VECTORS};
+    use crate::domain::{FrenchAddress, FrenchParserRules, KindHint};
+
+    #[test]
+    fn it_should_read_the_street_vectors() {
+        for (line, expected) in STREET_VECTORS {
+            assert_eq!(split_street(line), Ok(*expected), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn it_should_read_the_postal_vectors() {
+        for (line, expected) in POSTAL_VECTORS {
+            assert_eq!(split_postal(line), Ok(*expected), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn it_should_leave_the_unicode_digits_to_the_regex() {
+        assert_eq!(split_street("2٣ RUE DES LILAS"), Err(Undecided));
+        assert_eq!(split_street("2 BIſ RUE DES LILAS"), Err(Undecided));
+        assert_eq!(split_postal("3338٠ MIOS"), Err(Undecided));
+    }
+
+    #[test]
+    fn it_should_read_the_generated_addresses_like_the_regexes() {
+        let rules = FrenchParserRules::new();
+        let mut generator = AddressGenerator::new(42);
+
+        for _ in 0..1000 {
+            let (street, postal) = match generator.french(KindHint::Auto) {
+                FrenchAddress::Individual(address) => (address.street, address.postal),
+                FrenchAddress::Business(address) => (address.street, address.postal),
+            };
+            if let Some(street) = street {
+                assert_eq!(split_street(&street), Ok(rules.split_street(&street)));
+            }
+            assert_eq!(split_postal(&postal), Ok(rules.split_postal(&postal)));
+        }
+    }
+}
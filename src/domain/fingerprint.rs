@@ -0,0 +1,444 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use strum::EnumString;
+
+use super::address::{Address, AddressKind, Country, Recipient, Street};
+use super::person_name::PersonName;
+use super::street_number::NumberExtStyle;
+use super::street_referential::distance;
+use super::street_type::StreetType;
+
+/// A hash of where an address is: its country, postcode, street number and
+/// street name, normalized so that the case, the accents, the punctuation and
+/// the abbreviation of the street type don't matter, and by default the name
+/// of the individual receiving it, see [`DuplicateScope`]. Without a street,
+/// the postbox, the locality and the name of a business count too. Two
+/// addresses with the same fingerprint are duplicates.
+///
+/// The hash is the 64 bits FNV-1a of `FR|33380|25|RUE DE L EGLISE|JEAN
+/// DELHOURME`, written as 16 hexadecimal digits. It changes with the matching
+/// rules (see the README), the fingerprints stored by external systems having
+/// to be computed again after an upgrade.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn hash(key: &str) -> Self {
+        let hash = key.bytes().fold(Self::OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(Self::PRIME)
+        });
+
+        Fingerprint(hash)
+    }
+
+    /// The fingerprint of the addresses at `street` in `postcode`, whoever
+    /// the recipient is, as [`Address::fingerprint_in`] computes it with
+    /// [`DuplicateScope::Location`].
+    pub fn of_location(country: &Country, postcode: &str, street: &Street) -> Self {
+        Self::hash(&location_key(country, postcode, Some(street)))
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Fingerprints are read as written, 16 hexadecimal digits.
+impl FromStr for Fingerprint {
+    type Err = String;
+
+    fn from_str(fingerprint: &str) -> Result<Self, Self::Err> {
+        if fingerprint.len() != 16 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "Invalid fingerprint `{fingerprint}`: expected 16 hexadecimal digits"
+            ));
+        }
+
+        u64::from_str_radix(fingerprint, 16)
+            .map(Fingerprint)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Serialize for Fingerprint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// What two addresses must share to be duplicates.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum DuplicateScope {
+    /// The location, whoever the recipient is.
+    Location,
+    /// The location and, for the individuals, the name of the recipient
+    /// without its civility, so that flatmates or the neighbours of a
+    /// building aren't duplicates. Businesses are compared by location.
+    #[default]
+    Recipient,
+}
+
+/// Uppercases the line, removes its accents and replaces its punctuation with
+/// single spaces (e.g., `Rue de l'Église` -> `RUE DE L EGLISE`).
+pub(super) fn normalize(line: &str) -> String {
+    let folded: String = line
+        .chars()
+        .flat_map(char::to_uppercase)
+        .map(|c| match c {
+            'À' | 'Â' | 'Ä' => 'A',
+            'É' | 'È' | 'Ê' | 'Ë' => 'E',
+            'Î' | 'Ï' => 'I',
+            'Ô' | 'Ö' => 'O',
+            'Ù' | 'Û' | 'Ü' => 'U',
+            'Ÿ' => 'Y',
+            'Ç' => 'C',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The normalized name of the street. The street type is compared in full,
+/// "AV DES CHAMPS" being a duplicate of "AVENUE DES CHAMPS".
+fn street_name_key(street: &Street) -> String {
+    normalize(&match StreetType::split(&street.name) {
+        Some((kind, rest)) => format!("{} {rest}", kind.name()),
+        None => street.name.clone(),
+    })
+}
+
+/// The key hashed for the location of an address.
+fn location_key(country: &Country, postcode: &str, street: Option<&Street>) -> String {
+    let (number, name) = match street {
+        Some(street) => (
+            street
+                .full_number(NumberExtStyle::Attached)
+                .unwrap_or_default(),
+            street_name_key(street),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    [
+        country.iso_code().to_string(),
+        normalize(postcode).replace(' ', ""),
+        normalize(&number).replace(' ', ""),
+        name,
+    ]
+    .join("|")
+}
+
+/// Whether `a` and `b` are the same but for a few typos, their spacing being
+/// ignored: a fifth of their letters at most are different.
+fn is_close(a: &str, b: &str) -> bool {
+    let a = a.replace(' ', "");
+    let b = b.replace(' ', "");
+    let max_distance = (a.chars().count().max(b.chars().count()) / 5).max(1);
+
+    distance(&a, &b) <= max_distance
+}
+
+impl Address {
+    /// The fingerprint of the address, see [`Fingerprint`].
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint_in(DuplicateScope::default())
+    }
+
+    /// The fingerprint of the address, including what `scope` compares.
+    pub fn fingerprint_in(&self, scope: DuplicateScope) -> Fingerprint {
+        let mut key = location_key(
+            &self.country,
+            &self.postal_details.postcode,
+            self.street.as_ref(),
+        );
+
+        // Without a street, the postcode is shared by the postboxes of a
+        // CEDEX and by the lieux-dits of a commune: the postbox, the locality
+        // and the business receiving it tell them apart.
+        if self.street.is_none() {
+            let postbox = self
+                .delivery_point
+                .as_ref()
+                .and_then(|delivery_point| delivery_point.postbox.as_deref());
+            key.push('|');
+            key.push_str(&normalize(postbox.unwrap_or_default()));
+            key.push('|');
+            key.push_str(&normalize(
+                self.postal_details.locality.as_deref().unwrap_or_default(),
+            ));
+
+            if let Recipient::Business { company_name, .. } = &self.recipient {
+                key.push('|');
+                key.push_str(&normalize(company_name));
+            }
+        }
+
+        if let Some(name) = self.recipient_key(scope) {
+            key.push('|');
+            key.push_str(&name);
+        }
+
+        Fingerprint::hash(&key)
+    }
+
+    /// Whether the address is a duplicate of `other` with a few typos, see
+    /// [`Address::is_duplicate_of`]. The street names ("RUE DE L EGLISE" and
+    /// "RUE DE L EGLIZE") and the names of the individuals ("JEAN DELHOURME"
+    /// and "JEAN DEL HOURME") may differ by a fifth of their letters, the
+    /// other fields of the fingerprint must be the same.
+    pub fn is_similar_to(&self, other: &Address) -> bool {
+        if self.is_duplicate_of(other) {
+            return true;
+        }
+        if self.similarity_block() != other.similarity_block() {
+            return false;
+        }
+        let (Some(street), Some(other_street)) = (&self.street, &other.street) else {
+            return false;
+        };
+        if !is_close(&street_name_key(street), &street_name_key(other_street)) {
+            return false;
+        }
+
+        let scope = DuplicateScope::default();
+        match (self.recipient_key(scope), other.recipient_key(scope)) {
+            (Some(name), Some(other_name)) => is_close(&name, &other_name),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// What the similar addresses share: their country, postcode and street
+    /// number. Only the addresses with the same block are compared by
+    /// [`Address::is_similar_to`].
+    pub(crate) fn similarity_block(&self) -> String {
+        let number = self
+            .street
+            .as_ref()
+            .and_then(|street| street.full_number(NumberExtStyle::Attached))
+            .unwrap_or_default();
+
+        [
+            self.country.iso_code().to_string(),
+            normalize(&self.postal_details.postcode).replace(' ', ""),
+            normalize(&number).replace(' ', ""),
+        ]
+        .join("|")
+    }
+
+    /// The normalized name of the individual receiving the address, without
+    /// its civility, when `scope` compares it.
+    fn recipient_key(&self, scope: DuplicateScope) -> Option<String> {
+        let (DuplicateScope::Recipient, AddressKind::Individual, Recipient::Individual { name }) =
+            (scope, &self.kind, &self.recipient)
+        else {
+            return None;
+        };
+
+        let name = match PersonName::parse(name) {
+            Some(PersonName {
+                given_name: Some(given_name),
+                surname,
+                ..
+            }) => format!("{given_name} {surname}"),
+            Some(PersonName { surname, .. }) => surname,
+            None => name.clone(),
+        };
+
+        Some(normalize(&name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AddressConvertible, ConvertedAddress, FrenchAddress};
+
+    fn stored_address(recipient: &str, street: &str, postal: &str) -> Address {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": recipient,
+            "street": street,
+            "postal": postal,
+            "country": "FRANCE",
+        }))
+        .unwrap();
+
+        Address::new(ConvertedAddress::from_french(french).unwrap())
+    }
+
+    #[test]
+    fn duplicates_share_the_fingerprint() {
+        let address = stored_address(
+            "Monsieur Jean DELHOURME",
+            "25 RUE DE L'EGLISE",
+            "33380 MIOS",
+        );
+        let duplicate = stored_address(
+            "Madame Isabelle RICHARD",
+            "25 Rue de l’Église",
+            "33380 MIOS",
+        );
+        let other = stored_address(
+            "Monsieur Jean DELHOURME",
+            "25 RUE DE L'EGLISE",
+            "33000 BORDEAUX",
+        );
+
+        let location = DuplicateScope::Location;
+        assert_eq!(
+            address.fingerprint_in(location),
+            duplicate.fingerprint_in(location)
+        );
+        assert!(address.is_duplicate_in(&duplicate, location));
+        assert!(!address.is_duplicate_in(&other, location));
+        let abbreviated = stored_address("M. Jean DELHOURME", "25 R. DE L'EGLISE", "33380 MIOS");
+        assert!(address.is_duplicate_of(&abbreviated));
+        // Hash of `FR|33380|25|RUE DE L EGLISE`.
+        assert_eq!(
+            address.fingerprint_in(location),
+            Fingerprint::hash("FR|33380|25|RUE DE L EGLISE")
+        );
+        let street = address.street.as_ref().unwrap();
+        assert_eq!(
+            Fingerprint::of_location(&Country::France, "33 380", street),
+            address.fingerprint_in(location)
+        );
+    }
+
+    #[test]
+    fn individuals_are_told_apart_by_recipient() {
+        let address = stored_address(
+            "Monsieur Jean DELHOURME",
+            "25 RUE DE L'EGLISE",
+            "33380 MIOS",
+        );
+        let same_person = stored_address("M. Jean Delhourme", "25 rue de l'Église", "33380 MIOS");
+        let flatmate = stored_address(
+            "Madame Isabelle RICHARD",
+            "25 RUE DE L'EGLISE",
+            "33380 MIOS",
+        );
+
+        assert!(address.is_duplicate_of(&same_person));
+        assert!(!address.is_duplicate_of(&flatmate));
+        // Hash of `FR|33380|25|RUE DE L EGLISE|JEAN DELHOURME`.
+        assert_eq!(
+            address.fingerprint(),
+            Fingerprint::hash("FR|33380|25|RUE DE L EGLISE|JEAN DELHOURME")
+        );
+        assert_eq!("location".parse(), Ok(DuplicateScope::Location));
+    }
+
+    #[test]
+    fn postboxes_are_told_apart() {
+        let business = |name: &str, postbox: &str| {
+            let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+                "business_name": name,
+                "distribution_info": postbox,
+                "postal": "33000 BORDEAUX CEDEX",
+                "country": "FRANCE",
+            }))
+            .unwrap();
+            Address::new(ConvertedAddress::from_french(french).unwrap())
+        };
+        let dupont = business("Société DUPONT", "BP 123");
+
+        let location = DuplicateScope::Location;
+        assert!(dupont.is_duplicate_in(&business("SOCIETE DUPONT", "BP 123"), location));
+        assert!(!dupont.is_duplicate_in(&business("Société DUPONT", "BP 456"), location));
+        assert!(!dupont.is_duplicate_in(&business("DURAND SA", "BP 123"), location));
+        // Hash of `FR|33000|||BP 123||SOCIETE DUPONT`.
+        assert_eq!(
+            dupont.fingerprint(),
+            Fingerprint::hash("FR|33000|||BP 123||SOCIETE DUPONT")
+        );
+    }
+
+    #[test]
+    fn localities_are_told_apart() {
+        let address = stored_address(
+            "Monsieur Jean DELHOURME",
+            "LD LES GRANDS CHAMPS",
+            "33380 MIOS",
+        );
+        assert_eq!(
+            address.postal_details.locality.as_deref(),
+            Some("LES GRANDS CHAMPS")
+        );
+        let bourg = stored_address("Monsieur Jean DELHOURME", "LD LE BOURG", "33380 MIOS");
+
+        assert!(!address.is_duplicate_of(&bourg));
+        assert!(!address.is_duplicate_in(&bourg, DuplicateScope::Location));
+        assert!(address.is_duplicate_of(&stored_address(
+            "M. Jean Delhourme",
+            "LD Les Grands Champs",
+            "33380 MIOS"
+        )));
+    }
+
+    #[test]
+    fn near_duplicates_are_similar() {
+        let address = stored_address(
+            "Monsieur Jean DELHOURME",
+            "25 RUE DE L'EGLISE",
+            "33380 MIOS",
+        );
+        let typo = stored_address("M. Jean DEL HOURME", "25 R. DE L EGLIZE", "33380 MIOS");
+        assert!(!address.is_duplicate_of(&typo));
+
+        assert!(address.is_similar_to(&typo));
+        assert!(address.is_similar_to(&address.clone()));
+        // Neither the flatmates, the neighbours nor the other streets
+        for other in [
+            stored_address(
+                "Madame Isabelle RICHARD",
+                "25 RUE DE L'EGLISE",
+                "33380 MIOS",
+            ),
+            stored_address(
+                "Monsieur Jean DELHOURME",
+                "27 RUE DE L'EGLISE",
+                "33380 MIOS",
+            ),
+            stored_address("Monsieur Jean DELHOURME", "25 RUE DES LILAS", "33380 MIOS"),
+            stored_address(
+                "Monsieur Jean DELHOURME",
+                "25 RUE DE L'EGLISE",
+                "33000 BORDEAUX",
+            ),
+        ] {
+            assert!(!address.is_similar_to(&other));
+        }
+    }
+
+    #[test]
+    fn normalized_lines() {
+        assert_eq!(normalize("Rue de l'Église"), "RUE DE L EGLISE");
+        assert_eq!(normalize("  RUE  DE L’EGLISE "), "RUE DE L EGLISE");
+        assert_eq!(normalize("Allée des Cœurs"), "ALLEE DES CŒURS");
+    }
+
+    #[test]
+    fn stable_hash() {
+        // Reference values of the 64 bits FNV-1a.
+        assert_eq!(Fingerprint::hash("").to_string(), "cbf29ce484222325");
+        assert_eq!(Fingerprint::hash("a").to_string(), "af63dc4c8601ec8c");
+        assert_eq!(
+            serde_json::to_value(Fingerprint::hash("a")).unwrap(),
+            "af63dc4c8601ec8c"
+        );
+        assert_eq!("AF63DC4C8601EC8C".parse(), Ok(Fingerprint::hash("a")));
+        assert!("af63dc4c8601ec8".parse::<Fingerprint>().is_err());
+        assert!("+f63dc4c8601ec8c".parse::<Fingerprint>().is_err());
+    }
+}
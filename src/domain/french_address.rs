@@ -1,26 +1,51 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-use super::address::{PostalDetails, Street};
+use super::address::{AddressKind, Country, PostalDetails, Street};
 use super::address_conversion::AddressConversionError;
+use super::address_kind_detector::KIND_TAG;
+use super::country_profile::CountryProfile;
+use super::french_parser_rules::FrenchParserRules;
+use super::org_identifier::OrgIdentifier;
+use super::person_name::Civility;
+use super::validators::PostcodeValidators;
 
-/// Regex to capture the optional street number (e.g., 25, 2BIS) and the mandatory
-/// street name. Capture group indexes will be conserved.
-static STREET_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:(\d+[a-zA-Z]*) )?(.+)$").unwrap());
-/// Regex to capture the mandatory postalcode/zipcode and town information.
-static POSTAL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d{5})\s+(.+)$").unwrap());
-/// Regex to capture poxbox details. Here we consider that two letter followed
-/// by a suite of digits correspond to the postbox details (e.g., PO 1234, BP 123).
-static POSTBOX_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Z]{2}\s+\d+").unwrap());
-/// Regex to capture the town location information. There are two groups, the
-/// first for the postbox (ignored), the second for the townlocation.
-/// (e.g., BP 90432 MONTFERRIER SUR LEZ -> MONTFERRIER SUR LEZ)
-static TOWN_LOCATION_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^(?:[A-Z]{2}\s+\d+\s+)?(.+)$").unwrap());
-
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-#[serde(untagged)]
+/// Regex to capture the person a delivery line is addressed through, introduced
+/// by "CHEZ" or "C/O". The first group is the person, the second the optional
+/// internal delivery point following it
+/// (e.g., Chez Mireille COPEAU Appartement 2 -> Mireille COPEAU, Appartement 2).
+static CARE_OF_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?i)(?:CHEZ|C/O)\s+(.+?)(?:\s+((?:APPARTEMENT|APPT|APT|ESCALIER|ESC|ETAGE|ÉTAGE|CHAMBRE|PORTE|BAL|BOITE|BOÎTE)\b.*))?$",
+    )
+    .unwrap()
+});
+/// Regex to capture a SIREN or SIRET written after the business name. The
+/// first group is the business name, the second the scheme and the third the
+/// identifier (e.g., Société DUPONT SIRET 732 829 320 00074).
+static BUSINESS_IDENTIFIER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+?)\s+(SIRE[NT])\s*:?\s*(\d[\d ]*\d)$").unwrap());
+
+/// Regex to capture the service written on the contact line of a business,
+/// introduced by "SERVICE", "DIRECTION", "DÉPARTEMENT"... The first group is
+/// the optional contact person before it, the second the service
+/// (e.g., Mademoiselle Lucie MARTIN - Service achat -> Mademoiselle Lucie
+/// MARTIN, Service achat).
+static SERVICE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?i)(?:(.+?)[\s,/-]+)?((?:SERVICES?|DIRECTION|D[EÉ]PARTEMENT|DEPT|P[OÔ]LE|DIVISION|CELLULE)\b.*)$",
+    )
+    .unwrap()
+});
+
+/// A french address, read from its tagged representation when the input has
+/// a `kind` field (e.g., `{"kind": "business", "business_name": ...}`), or
+/// from the fields provided otherwise.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(remote = "Self", untagged)]
 pub enum FrenchAddress {
     /// An individual french address.
     Individual(IndividualFrenchAddress),
@@ -28,7 +53,76 @@ pub enum FrenchAddress {
     Business(BusinessFrenchAddress),
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+impl FrenchAddress {
+    pub fn kind(&self) -> AddressKind {
+        match self {
+            FrenchAddress::Individual(_) => AddressKind::Individual,
+            FrenchAddress::Business(_) => AddressKind::Business,
+        }
+    }
+
+    /// The lines of the address as printed on the envelope (NF Z10-011),
+    /// from the recipient to the country, without the empty ones.
+    pub fn lines(&self) -> Vec<String> {
+        let lines = match self {
+            FrenchAddress::Individual(address) => [
+                Some(&address.name),
+                address.internal_delivery.as_ref(),
+                address.external_delivery.as_ref(),
+                address.street.as_ref(),
+                address.distribution_info.as_ref(),
+                Some(&address.postal),
+                Some(&address.country),
+            ],
+            FrenchAddress::Business(address) => [
+                Some(&address.business_name),
+                address.recipient.as_ref(),
+                address.external_delivery.as_ref(),
+                address.street.as_ref(),
+                address.distribution_info.as_ref(),
+                Some(&address.postal),
+                Some(&address.country),
+            ],
+        };
+
+        lines
+            .into_iter()
+            .flatten()
+            .filter(|line| !line.trim().is_empty())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Serialize for FrenchAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FrenchAddress::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FrenchAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = Value::deserialize(deserializer)?;
+
+        match AddressKind::take_tag(&mut value).map_err(de::Error::custom)? {
+            Some(AddressKind::Individual) => {
+                serde_json::from_value(value).map(FrenchAddress::Individual)
+            }
+            Some(AddressKind::Business) => {
+                serde_json::from_value(value).map(FrenchAddress::Business)
+            }
+            None => FrenchAddress::deserialize(value).map_err(|e| {
+                de::Error::custom(format!(
+                    "{e}, give its {KIND_TAG} (\"individual\" or \"business\") for details"
+                ))
+            }),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct IndividualFrenchAddress {
     /// The individual identity
     /// (Civility - title / quality - firstname lastname).
@@ -39,7 +133,8 @@ pub struct IndividualFrenchAddress {
     /// Additional information of the external delivery point
     /// (Building, residence, entrance, ...).
     pub external_delivery: Option<String>,
-    /// Route number and label.
+    /// Route number and label, or the lieu-dit when there is no street
+    /// (e.g., "LIEU DIT LES GRANDS CHAMPS").
     pub street: Option<String>,
     /// Additional distribution information (hamlet, postal box, ...).
     pub distribution_info: Option<String>,
@@ -47,19 +142,30 @@ pub struct IndividualFrenchAddress {
     pub postal: String,
     /// The country name.
     pub country: String,
+    /// Unknown fields, kept as is through conversions and storage.
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub extras: Map<String, Value>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BusinessFrenchAddress {
-    /// The business name or trade name.
+    /// The business name or trade name. A SIREN or SIRET can follow the name
+    /// (e.g., "Société DUPONT SIRET 732 829 320 00074").
     pub business_name: String,
+    /// Official identifiers of the business (SIREN or SIRET).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifiers: Vec<String>,
     /// Identity of the recipient and/or service
     pub recipient: Option<String>,
     /// Additional information of the external delivery point
     /// (Building, residence, entrance, ...).
     pub external_delivery: Option<String>,
-    /// Route number and label.
-    pub street: String,
+    /// Route number and label, or the lieu-dit when there is no street. Left
+    /// out when the postbox is enough, as allowed by the [`StreetPolicy`].
+    #[serde(default)]
+    pub street: Option<String>,
     /// Additional distribution information (BP, Sorting Arrival Department)
     /// and the commune where the company is located if different from the CEDEX
     /// distributor office.
@@ -69,91 +175,292 @@ pub struct BusinessFrenchAddress {
     pub postal: String,
     /// The country name.
     pub country: String,
+    /// Unknown fields, kept as is through conversions and storage.
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub extras: Map<String, Value>,
 }
 
+/// Prefix written before the lieu-dit when converting to the french format.
+pub const LOCALITY_PREFIX: &str = "LIEU DIT";
+
+/// Prefix written before the care of when converting to the french format.
+pub const CARE_OF_PREFIX: &str = "Chez";
+
+/// Reads the lines of the french addresses, with the built-in rules or the
+/// ones applied by [`FrenchParserRules::apply`].
 pub struct FrenchAddressParser;
 
 impl FrenchAddressParser {
-    pub fn parse_street(street: &str) -> Result<Street, AddressConversionError> {
-        if street.is_empty() {
-            return Err(AddressConversionError::InvalidFormat(
-                "Street cannot be empty".to_string(),
-            ));
+    /// Splits the first line of a business address into the business name and
+    /// the optional SIREN or SIRET written after it.
+    pub fn parse_business_name(
+        line: &str,
+    ) -> Result<(String, Option<OrgIdentifier>), AddressConversionError> {
+        let (business_name, identifier) = Self::split_business_name(line);
+        let identifier = identifier
+            .map(|(scheme, value)| OrgIdentifier::parse_scheme(scheme, value))
+            .transpose()?;
+
+        Ok((business_name.to_string(), identifier))
+    }
+
+    /// Same as [`FrenchAddressParser::parse_business_name`], borrowing the
+    /// business name, the scheme and the identifier from the line.
+    pub fn split_business_name(line: &str) -> (&str, Option<(&str, &str)>) {
+        match BUSINESS_IDENTIFIER_REGEX.captures(line) {
+            Some(caps) => {
+                let group = |i| caps.get(i).map_or("", |m| m.as_str());
+                (group(1), Some((group(2), group(3))))
+            }
+            None => (line, None),
         }
-        if let Some(caps) = STREET_REGEX.captures(street) {
-            let number = caps.get(1).map(|m| m.as_str().to_string());
-            let name = caps
-                .get(2)
-                .map_or("".to_string(), |m| m.as_str().to_string());
-            if name.is_empty() {
-                return Err(AddressConversionError::InvalidFormat(
-                    "Street name cannot be empty".to_string(),
-                ));
+    }
+
+    /// Splits the contact line of a business into the contact person and the
+    /// service, which may come in either order (e.g., Mademoiselle Lucie
+    /// MARTIN Service achat, or Service achat Monsieur DUPONT). A line without
+    /// service is the contact person.
+    pub fn split_contact(line: &str) -> (Option<&str>, Option<&str>) {
+        let line = line.trim();
+        fn non_empty(part: &str) -> Option<&str> {
+            Some(part).filter(|part| !part.is_empty())
+        }
+
+        let Some(caps) = SERVICE_REGEX.captures(line) else {
+            return (non_empty(line), None);
+        };
+
+        let service = caps.get(2).map_or("", |m| m.as_str());
+        if let Some(person) = caps.get(1) {
+            return (non_empty(person.as_str()), non_empty(service));
+        }
+        // The contact person following the service starts with a civility.
+        let person_at = service.match_indices(' ').map(|(i, _)| i + 1).find(|&i| {
+            service[i..]
+                .split_whitespace()
+                .next()
+                .and_then(Civility::parse)
+                .is_some()
+        });
+        match person_at {
+            Some(i) => {
+                (
+                    non_empty(service[i..].trim()),
+                    non_empty(service[..i].trim_end_matches(|c: char| {
+                        c.is_whitespace() || matches!(c, ',' | '/' | '-')
+                    })),
+                )
             }
+            None => (None, non_empty(service)),
+        }
+    }
 
-            return Ok(Street { number, name });
+    pub fn parse_street(street: &str) -> Result<Street, AddressConversionError> {
+        Self::street_from(street, Self::split_street(street))
+    }
+
+    /// Parses the street line of an address sent to `country`, whose number
+    /// comes after the name in the germanic countries ("STEPHANSPLATZ 1"),
+    /// see [`CountryProfile`].
+    pub fn parse_street_for(
+        street: &str,
+        country: &Country,
+    ) -> Result<Street, AddressConversionError> {
+        Self::street_from(street, CountryProfile::of(country).split_street(street))
+    }
+
+    fn street_from(
+        street: &str,
+        split: Option<(Option<&str>, &str)>,
+    ) -> Result<Street, AddressConversionError> {
+        let invalid = |reason: &str| AddressConversionError::InvalidStreet {
+            field: "street".to_string(),
+            value: street.to_string(),
+            reason: reason.to_string(),
+        };
+
+        if street.is_empty() {
+            return Err(invalid("Street cannot be empty"));
+        }
+        match split {
+            Some((_, "")) => Err(invalid("Street name cannot be empty")),
+            Some((number, name)) => Ok(Street::new(number.map(str::to_string), name.to_string())),
+            None => Err(invalid("Invalid street format")),
         }
+    }
+
+    /// Same as [`FrenchAddressParser::parse_street`], borrowing the optional
+    /// number and the name from the line, without validation.
+    pub fn split_street(street: &str) -> Option<(Option<&str>, &str)> {
+        FrenchParserRules::with_current(|rules| rules.split_street(street))
+    }
+
+    /// Extracts the lieu-dit of a street or distribution line, when the line
+    /// starts with "LD", "L.D." or "LIEU DIT".
+    pub fn parse_locality(line: &str) -> Option<String> {
+        Self::locality(line).map(str::to_string)
+    }
+
+    /// Same as [`FrenchAddressParser::parse_locality`], borrowing the lieu-dit
+    /// from the line.
+    pub fn locality(line: &str) -> Option<&str> {
+        FrenchParserRules::with_current(|rules| rules.locality().captures(line.trim()))
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str())
+    }
 
-        Err(AddressConversionError::InvalidFormat(
-            "Invalid street format".to_string(),
+    /// Splits a delivery line starting with "CHEZ" or "C/O" into the care of
+    /// and the delivery point following it, if any.
+    pub fn parse_care_of(line: &str) -> Option<(String, Option<String>)> {
+        Self::care_of(line).map(|(care_of, rest)| (care_of.to_string(), rest.map(str::to_string)))
+    }
+
+    /// Same as [`FrenchAddressParser::parse_care_of`], borrowing the care of
+    /// and the delivery point from the line.
+    pub fn care_of(line: &str) -> Option<(&str, Option<&str>)> {
+        let caps = CARE_OF_REGEX.captures(line.trim())?;
+
+        Some((
+            caps.get(1).map_or("", |m| m.as_str()),
+            caps.get(2).map(|m| m.as_str()),
         ))
     }
 
     pub fn parse_postal(postal: &str) -> Result<PostalDetails, AddressConversionError> {
-        const POSTAL_ERROR: &str = "Postal information should contain a postcode/zipcode and a town (e.g., '44000 NANTES')";
-
-        if let Some(caps) = POSTAL_REGEX.captures(postal) {
-            let postcode = caps.get(1).map(|m| m.as_str().to_string()).ok_or(
-                AddressConversionError::InvalidFormat(POSTAL_ERROR.to_string()),
-            )?;
-            let town = caps.get(2).map(|m| m.as_str().to_string()).ok_or(
-                AddressConversionError::InvalidFormat(POSTAL_ERROR.to_string()),
-            )?;
-
-            Ok(PostalDetails {
-                postcode,
-                town,
-                town_location: None,
-            })
+        let invalid = || {
+            AddressConversionError::InvalidPostal {
+            field: "postal".to_string(),
+            value: postal.to_string(),
+            reason: "Postal information should contain a postcode/zipcode and a town (e.g., '44000 NANTES')".to_string(),
+        }
+        };
+
+        let (postcode, town) = Self::split_postal(postal).ok_or_else(invalid)?;
+
+        Ok(PostalDetails {
+            postcode: postcode.to_string(),
+            town: town.to_string(),
+            town_location: None,
+            locality: None,
+            country_subdivision: None,
+        })
+    }
+
+    /// Same as [`FrenchAddressParser::parse_postal`], borrowing the postcode
+    /// and the town from the line.
+    pub fn split_postal(postal: &str) -> Option<(&str, &str)> {
+        FrenchParserRules::with_current(|rules| rules.split_postal(postal))
+    }
+
+    /// Parses the postal line of an address sent to `country`. French postal
+    /// lines must have a 5 digits postcode. Foreign postal lines keep their own
+    /// conventions: the postcode is the group of words before the town
+    /// ("1012 AB AMSTERDAM"), or after it ("NEW YORK NY 10001") depending on
+    /// the country, so that the line is written back unchanged. The words
+    /// forming a valid postcode of the country are taken, or the words with
+    /// digits otherwise.
+    pub fn parse_postal_for(
+        postal: &str,
+        country: &Country,
+    ) -> Result<PostalDetails, AddressConversionError> {
+        if *country == Country::France {
+            return Self::parse_postal(postal);
+        }
+
+        let words: Vec<&str> = postal.split_whitespace().collect();
+        let has_digit = |word: &str| word.chars().any(|c| c.is_ascii_digit());
+        let validators = PostcodeValidators::built_in();
+        // The longest postcode of the built-in validators has two words
+        // ("SW1A 1AA").
+        let valid_len = (1..=2.min(words.len().saturating_sub(1)))
+            .rev()
+            .find(|&len| {
+                let candidate = if country.postcode_first() {
+                    &words[..len]
+                } else {
+                    &words[words.len() - len..]
+                };
+                validators.is_valid(country, &candidate.join(" "))
+            });
+        let split = match valid_len {
+            Some(len) if country.postcode_first() => len,
+            Some(len) => words.len() - len,
+            None if country.postcode_first() => {
+                words.iter().take_while(|word| has_digit(word)).count()
+            }
+            None => words
+                .iter()
+                .position(|word| has_digit(word))
+                .unwrap_or(words.len()),
+        };
+        let (first, last) = words.split_at(split);
+        let (postcode, town) = if country.postcode_first() {
+            (first, last)
         } else {
-            Err(AddressConversionError::InvalidFormat(
-                POSTAL_ERROR.to_string(),
-            ))
+            (last, first)
+        };
+
+        if postcode.is_empty() || town.is_empty() {
+            return Err(AddressConversionError::InvalidPostal {
+                field: "postal".to_string(),
+                value: postal.to_string(),
+                reason: format!(
+                    "Postal information should contain a postcode and a town for {country}"
+                ),
+            });
         }
+
+        Ok(PostalDetails {
+            postcode: postcode.join(" "),
+            town: town.join(" "),
+            town_location: None,
+            locality: None,
+            country_subdivision: None,
+        })
     }
 
     pub fn parse_postbox(
         distribution_info: &str,
     ) -> Result<Option<String>, AddressConversionError> {
         if distribution_info.is_empty() {
-            return Err(AddressConversionError::InvalidFormat(
-                "Distribution info cannot be empty if provided".to_string(),
-            ));
+            return Err(AddressConversionError::InvalidValue {
+                field: "distribution_info".to_string(),
+                value: distribution_info.to_string(),
+                reason: "Distribution info cannot be empty if provided".to_string(),
+            });
         }
 
-        if let Some(caps) = POSTBOX_REGEX.captures(distribution_info) {
-            let postbox = caps.get(0).map(|m| m.as_str().to_string());
-            Ok(postbox)
-        } else {
-            Ok(None)
-        }
+        Ok(Self::postbox(distribution_info).map(str::to_string))
+    }
+
+    /// Same as [`FrenchAddressParser::parse_postbox`], borrowing the postbox
+    /// from the distribution line.
+    pub fn postbox(distribution_info: &str) -> Option<&str> {
+        FrenchParserRules::with_current(|rules| rules.postbox().find(distribution_info))
+            .map(|m| m.as_str())
     }
 
     pub fn parse_town_location(
         distribution_info: &str,
     ) -> Result<Option<String>, AddressConversionError> {
         if distribution_info.is_empty() {
-            return Err(AddressConversionError::InvalidFormat(
-                "Distribution info cannot be empty if provided".to_string(),
-            ));
+            return Err(AddressConversionError::InvalidValue {
+                field: "distribution_info".to_string(),
+                value: distribution_info.to_string(),
+                reason: "Distribution info cannot be empty if provided".to_string(),
+            });
         }
 
-        if let Some(caps) = TOWN_LOCATION_REGEX.captures(distribution_info) {
-            let town_location = caps.get(1).map(|m| m.as_str().to_string());
+        Ok(Self::town_location(distribution_info).map(str::to_string))
+    }
 
-            Ok(town_location)
-        } else {
-            Ok(None)
-        }
+    /// Same as [`FrenchAddressParser::parse_town_location`], borrowing the
+    /// town location from the distribution line.
+    pub fn town_location(distribution_info: &str) -> Option<&str> {
+        FrenchParserRules::with_current(|rules| rules.town_location().captures(distribution_info))
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str())
+            .filter(|town_location| !town_location.is_empty())
     }
 }
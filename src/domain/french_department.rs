@@ -0,0 +1,420 @@
+use strum::EnumString;
+
+use super::address::{Address, ConvertedAddress, Country, MailOrigin};
+
+/// The regions of France, metropolitan and overseas, since the 2016 reform.
+#[derive(Clone, Copy, Debug, strum_macros::Display, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Region {
+    #[strum(serialize = "Auvergne-Rhône-Alpes")]
+    AuvergneRhoneAlpes,
+    #[strum(serialize = "Bourgogne-Franche-Comté")]
+    BourgogneFrancheComte,
+    #[strum(serialize = "Bretagne")]
+    Bretagne,
+    #[strum(serialize = "Centre-Val de Loire")]
+    CentreValDeLoire,
+    #[strum(serialize = "Corse")]
+    Corse,
+    #[strum(serialize = "Grand Est")]
+    GrandEst,
+    #[strum(serialize = "Hauts-de-France")]
+    HautsDeFrance,
+    #[strum(serialize = "Île-de-France")]
+    IleDeFrance,
+    #[strum(serialize = "Normandie")]
+    Normandie,
+    #[strum(serialize = "Nouvelle-Aquitaine")]
+    NouvelleAquitaine,
+    #[strum(serialize = "Occitanie")]
+    Occitanie,
+    #[strum(serialize = "Pays de la Loire")]
+    PaysDeLaLoire,
+    #[strum(serialize = "Provence-Alpes-Côte d'Azur")]
+    ProvenceAlpesCoteDAzur,
+    #[strum(serialize = "Guadeloupe")]
+    Guadeloupe,
+    #[strum(serialize = "Martinique")]
+    Martinique,
+    #[strum(serialize = "Guyane")]
+    Guyane,
+    #[strum(serialize = "La Réunion")]
+    LaReunion,
+    #[strum(serialize = "Mayotte")]
+    Mayotte,
+}
+
+/// The departments of each region.
+const REGIONS: &[(Region, &[&str])] = &[
+    (
+        Region::AuvergneRhoneAlpes,
+        &[
+            "01", "03", "07", "15", "26", "38", "42", "43", "63", "69", "73", "74",
+        ],
+    ),
+    (
+        Region::BourgogneFrancheComte,
+        &["21", "25", "39", "58", "70", "71", "89", "90"],
+    ),
+    (Region::Bretagne, &["22", "29", "35", "56"]),
+    (
+        Region::CentreValDeLoire,
+        &["18", "28", "36", "37", "41", "45"],
+    ),
+    (Region::Corse, &["2A", "2B"]),
+    (
+        Region::GrandEst,
+        &["08", "10", "51", "52", "54", "55", "57", "67", "68", "88"],
+    ),
+    (Region::HautsDeFrance, &["02", "59", "60", "62", "80"]),
+    (
+        Region::IleDeFrance,
+        &["75", "77", "78", "91", "92", "93", "94", "95"],
+    ),
+    (Region::Normandie, &["14", "27", "50", "61", "76"]),
+    (
+        Region::NouvelleAquitaine,
+        &[
+            "16", "17", "19", "23", "24", "33", "40", "47", "64", "79", "86", "87",
+        ],
+    ),
+    (
+        Region::Occitanie,
+        &[
+            "09", "11", "12", "30", "31", "32", "34", "46", "48", "65", "66", "81", "82",
+        ],
+    ),
+    (Region::PaysDeLaLoire, &["44", "49", "53", "72", "85"]),
+    (
+        Region::ProvenceAlpesCoteDAzur,
+        &["04", "05", "06", "13", "83", "84"],
+    ),
+    (Region::Guadeloupe, &["971"]),
+    (Region::Martinique, &["972"]),
+    (Region::Guyane, &["973"]),
+    (Region::LaReunion, &["974"]),
+    (Region::Mayotte, &["976"]),
+];
+
+/// The department code of a french postcode: its first two digits, `2A` or
+/// `2B` in Corsica, and its first three digits overseas (`971`, `987`).
+/// Monaco (980) has no department.
+pub fn department_code(postcode: &str) -> Option<String> {
+    if postcode.len() != 5 || !postcode.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let overseas: u16 = postcode[..3].parse().unwrap_or_default();
+    match &postcode[..2] {
+        "20" => Some(if postcode < "20200" { "2A" } else { "2B" }.to_string()),
+        "97" | "98" => matches!(overseas, 971..=978 | 984..=988).then(|| overseas.to_string()),
+        department => region(department).map(|_| department.to_string()),
+    }
+}
+
+/// The region of a department. The overseas collectivities (Saint-Pierre-et-
+/// Miquelon, French Polynesia...) are not part of a region.
+pub fn region(department_code: &str) -> Option<Region> {
+    REGIONS
+        .iter()
+        .find(|(_, departments)| departments.contains(&department_code))
+        .map(|(region, _)| *region)
+}
+
+/// The french departments and collectivities overseas (DOM-TOM), which have
+/// their own ISO 3166 code besides being part of France. Parsed from their
+/// french name, their english name or their ISO 3166 alpha-2 or alpha-3 code,
+/// and displayed with their french name.
+#[derive(
+    Clone, Copy, Debug, strum_macros::Display, EnumString, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum OverseasTerritory {
+    #[strum(serialize = "GUADELOUPE", serialize = "GP", serialize = "GLP")]
+    Guadeloupe,
+    #[strum(serialize = "MARTINIQUE", serialize = "MQ", serialize = "MTQ")]
+    Martinique,
+    #[strum(
+        to_string = "GUYANE",
+        serialize = "FRENCH GUIANA",
+        serialize = "GF",
+        serialize = "GUF"
+    )]
+    Guyane,
+    #[strum(
+        to_string = "LA REUNION",
+        serialize = "LA RÉUNION",
+        serialize = "REUNION",
+        serialize = "RE",
+        serialize = "REU"
+    )]
+    LaReunion,
+    #[strum(
+        to_string = "SAINT-PIERRE-ET-MIQUELON",
+        serialize = "SAINT PIERRE AND MIQUELON",
+        serialize = "PM",
+        serialize = "SPM"
+    )]
+    SaintPierreEtMiquelon,
+    #[strum(serialize = "MAYOTTE", serialize = "YT", serialize = "MYT")]
+    Mayotte,
+    #[strum(
+        to_string = "SAINT-BARTHELEMY",
+        serialize = "SAINT-BARTHÉLEMY",
+        serialize = "SAINT BARTHELEMY",
+        serialize = "BL",
+        serialize = "BLM"
+    )]
+    SaintBarthelemy,
+    #[strum(
+        to_string = "SAINT-MARTIN",
+        serialize = "SAINT MARTIN",
+        serialize = "MF",
+        serialize = "MAF"
+    )]
+    SaintMartin,
+    #[strum(
+        to_string = "TERRES AUSTRALES ET ANTARCTIQUES FRANCAISES",
+        serialize = "FRENCH SOUTHERN TERRITORIES",
+        serialize = "TF",
+        serialize = "ATF"
+    )]
+    TerresAustrales,
+    #[strum(
+        to_string = "WALLIS-ET-FUTUNA",
+        serialize = "WALLIS AND FUTUNA",
+        serialize = "WF",
+        serialize = "WLF"
+    )]
+    WallisEtFutuna,
+    #[strum(
+        to_string = "POLYNESIE FRANCAISE",
+        serialize = "POLYNÉSIE FRANÇAISE",
+        serialize = "FRENCH POLYNESIA",
+        serialize = "PF",
+        serialize = "PYF"
+    )]
+    PolynesieFrancaise,
+    #[strum(
+        to_string = "NOUVELLE-CALEDONIE",
+        serialize = "NOUVELLE-CALÉDONIE",
+        serialize = "NEW CALEDONIA",
+        serialize = "NC",
+        serialize = "NCL"
+    )]
+    NouvelleCaledonie,
+}
+
+impl OverseasTerritory {
+    /// The territory of a french postcode: its first three digits, except
+    /// for Saint-Barthélemy (`97133`) and Saint-Martin (`97150`), which share
+    /// the postcodes of Guadeloupe.
+    pub fn from_postcode(postcode: &str) -> Option<Self> {
+        if postcode.len() != 5 || !postcode.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let territory = match (&postcode[..3], postcode) {
+            (_, "97133") => OverseasTerritory::SaintBarthelemy,
+            (_, "97150") => OverseasTerritory::SaintMartin,
+            ("971", _) => OverseasTerritory::Guadeloupe,
+            ("972", _) => OverseasTerritory::Martinique,
+            ("973", _) => OverseasTerritory::Guyane,
+            ("974", _) => OverseasTerritory::LaReunion,
+            ("975", _) => OverseasTerritory::SaintPierreEtMiquelon,
+            ("976", _) => OverseasTerritory::Mayotte,
+            ("984", _) => OverseasTerritory::TerresAustrales,
+            ("986", _) => OverseasTerritory::WallisEtFutuna,
+            ("987", _) => OverseasTerritory::PolynesieFrancaise,
+            ("988", _) => OverseasTerritory::NouvelleCaledonie,
+            _ => return None,
+        };
+
+        Some(territory)
+    }
+
+    /// The ISO 3166 alpha-2 code of the territory (e.g., `RE`).
+    pub fn iso_code(self) -> &'static str {
+        match self {
+            OverseasTerritory::Guadeloupe => "GP",
+            OverseasTerritory::Martinique => "MQ",
+            OverseasTerritory::Guyane => "GF",
+            OverseasTerritory::LaReunion => "RE",
+            OverseasTerritory::SaintPierreEtMiquelon => "PM",
+            OverseasTerritory::Mayotte => "YT",
+            OverseasTerritory::SaintBarthelemy => "BL",
+            OverseasTerritory::SaintMartin => "MF",
+            OverseasTerritory::TerresAustrales => "TF",
+            OverseasTerritory::WallisEtFutuna => "WF",
+            OverseasTerritory::PolynesieFrancaise => "PF",
+            OverseasTerritory::NouvelleCaledonie => "NC",
+        }
+    }
+
+    /// The ISO 3166 alpha-3 code of the territory (e.g., `REU`).
+    pub fn iso_alpha3_code(self) -> &'static str {
+        match self {
+            OverseasTerritory::Guadeloupe => "GLP",
+            OverseasTerritory::Martinique => "MTQ",
+            OverseasTerritory::Guyane => "GUF",
+            OverseasTerritory::LaReunion => "REU",
+            OverseasTerritory::SaintPierreEtMiquelon => "SPM",
+            OverseasTerritory::Mayotte => "MYT",
+            OverseasTerritory::SaintBarthelemy => "BLM",
+            OverseasTerritory::SaintMartin => "MAF",
+            OverseasTerritory::TerresAustrales => "ATF",
+            OverseasTerritory::WallisEtFutuna => "WLF",
+            OverseasTerritory::PolynesieFrancaise => "PYF",
+            OverseasTerritory::NouvelleCaledonie => "NCL",
+        }
+    }
+
+    pub fn english_name(self) -> &'static str {
+        match self {
+            OverseasTerritory::Guadeloupe => "GUADELOUPE",
+            OverseasTerritory::Martinique => "MARTINIQUE",
+            OverseasTerritory::Guyane => "FRENCH GUIANA",
+            OverseasTerritory::LaReunion => "REUNION",
+            OverseasTerritory::SaintPierreEtMiquelon => "SAINT PIERRE AND MIQUELON",
+            OverseasTerritory::Mayotte => "MAYOTTE",
+            OverseasTerritory::SaintBarthelemy => "SAINT BARTHELEMY",
+            OverseasTerritory::SaintMartin => "SAINT MARTIN",
+            OverseasTerritory::TerresAustrales => "FRENCH SOUTHERN TERRITORIES",
+            OverseasTerritory::WallisEtFutuna => "WALLIS AND FUTUNA",
+            OverseasTerritory::PolynesieFrancaise => "FRENCH POLYNESIA",
+            OverseasTerritory::NouvelleCaledonie => "NEW CALEDONIA",
+        }
+    }
+
+    /// The ISO 3166-2 code of the territory as a subdivision of France: the
+    /// department number for the overseas departments (`FR-974`), the
+    /// alpha-2 code for the collectivities (`FR-PF`).
+    pub fn subdivision_code(self) -> String {
+        match self {
+            OverseasTerritory::Guadeloupe => "FR-971".to_string(),
+            OverseasTerritory::Martinique => "FR-972".to_string(),
+            OverseasTerritory::Guyane => "FR-973".to_string(),
+            OverseasTerritory::LaReunion => "FR-974".to_string(),
+            OverseasTerritory::Mayotte => "FR-976".to_string(),
+            collectivity => format!("FR-{}", collectivity.iso_code()),
+        }
+    }
+}
+
+impl Country {
+    /// The overseas territory of a french address, derived from its
+    /// postcode.
+    pub fn overseas_territory(&self, postcode: &str) -> Option<OverseasTerritory> {
+        match self {
+            Country::France => OverseasTerritory::from_postcode(postcode),
+            _ => None,
+        }
+    }
+}
+
+impl ConvertedAddress {
+    /// The overseas territory of a french address, derived from its
+    /// postcode.
+    pub fn overseas_territory(&self) -> Option<OverseasTerritory> {
+        self.country
+            .overseas_territory(&self.postal_details.postcode)
+    }
+
+    /// The country line of the address, see [`Country::destination_line`].
+    /// International mail to the overseas territories names the territory,
+    /// mail sent from France keeps `FRANCE`, the postcode routing it.
+    pub fn destination_line(&self, origin: MailOrigin) -> String {
+        match (origin, self.overseas_territory()) {
+            (MailOrigin::International, Some(territory)) => territory.english_name().to_string(),
+            _ => self.country.destination_line(origin),
+        }
+    }
+}
+
+impl Address {
+    /// The overseas territory of a french address, derived from its
+    /// postcode.
+    pub fn overseas_territory(&self) -> Option<OverseasTerritory> {
+        self.country
+            .overseas_territory(&self.postal_details.postcode)
+    }
+
+    /// The department of a french address, derived from its postcode.
+    pub fn department_code(&self) -> Option<String> {
+        match self.country {
+            Country::France => department_code(&self.postal_details.postcode),
+            _ => None,
+        }
+    }
+
+    /// The region of a french address, derived from its postcode.
+    pub fn region(&self) -> Option<Region> {
+        self.department_code().as_deref().and_then(region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn department_codes() {
+        assert_eq!(department_code("33380").as_deref(), Some("33"));
+        assert_eq!(department_code("01000").as_deref(), Some("01"));
+        assert_eq!(department_code("20000").as_deref(), Some("2A"));
+        assert_eq!(department_code("20200").as_deref(), Some("2B"));
+        assert_eq!(department_code("97400").as_deref(), Some("974"));
+        assert_eq!(department_code("98714").as_deref(), Some("987"));
+        assert_eq!(department_code("98000"), None);
+        assert_eq!(department_code("00100"), None);
+        assert_eq!(department_code("96100"), None);
+        assert_eq!(department_code("3338"), None);
+    }
+
+    #[test]
+    fn overseas_territories() {
+        let territory = OverseasTerritory::from_postcode;
+        assert_eq!(territory("97400"), Some(OverseasTerritory::LaReunion));
+        assert_eq!(territory("97100"), Some(OverseasTerritory::Guadeloupe));
+        assert_eq!(territory("97133"), Some(OverseasTerritory::SaintBarthelemy));
+        assert_eq!(
+            territory("98714"),
+            Some(OverseasTerritory::PolynesieFrancaise)
+        );
+        assert_eq!(territory("98000"), None);
+        assert_eq!(territory("33380"), None);
+
+        assert_eq!(OverseasTerritory::LaReunion.subdivision_code(), "FR-974");
+        assert_eq!(
+            OverseasTerritory::PolynesieFrancaise.subdivision_code(),
+            "FR-PF"
+        );
+        assert_eq!(
+            "reunion".parse::<OverseasTerritory>(),
+            Ok(OverseasTerritory::LaReunion)
+        );
+        assert_eq!("NCL".parse(), Ok(OverseasTerritory::NouvelleCaledonie));
+        assert_eq!(
+            Country::Spain.overseas_territory("97400"),
+            None,
+            "only french postcodes are overseas"
+        );
+    }
+
+    #[test]
+    fn regions() {
+        assert_eq!(region("33"), Some(Region::NouvelleAquitaine));
+        assert_eq!(region("2B"), Some(Region::Corse));
+        assert_eq!(region("974"), Some(Region::LaReunion));
+        assert_eq!(region("987"), None);
+        assert_eq!(
+            Region::ProvenceAlpesCoteDAzur.to_string(),
+            "Provence-Alpes-Côte d'Azur"
+        );
+
+        let departments: usize = REGIONS
+            .iter()
+            .map(|(_, departments)| departments.len())
+            .sum();
+        assert_eq!(departments, 101);
+    }
+}
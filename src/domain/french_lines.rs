@@ -0,0 +1,361 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Map;
+
+use super::address::Country;
+use super::address_conversion::AddressConversionError;
+use super::address_kind_detector::AddressKindDetector;
+use super::french_address::*;
+
+/// Words introducing an internal delivery point (e.g., "Appartement 2").
+const INTERNAL_WORDS: &[&str] = &[
+    "APPARTEMENT",
+    "APPT",
+    "APT",
+    "ESCALIER",
+    "ESC",
+    "ETAGE",
+    "ÉTAGE",
+    "CHAMBRE",
+    "PORTE",
+    "BAL",
+    "BOITE",
+    "BOÎTE",
+    "CHEZ",
+    "C/O",
+];
+/// Words introducing an external delivery point (e.g., "Entrée A Bâtiment
+/// Jonquille").
+const EXTERNAL_WORDS: &[&str] = &[
+    "BATIMENT",
+    "BÂTIMENT",
+    "BAT",
+    "BÂT",
+    "IMMEUBLE",
+    "IMM",
+    "RESIDENCE",
+    "RÉSIDENCE",
+    "RES",
+    "RÉS",
+    "ENTREE",
+    "ENTRÉE",
+    "TOUR",
+    "VILLA",
+];
+/// Street types, for the streets written without number (e.g., "RUE DE LA
+/// PAIX").
+const STREET_WORDS: &[&str] = &[
+    "RUE",
+    "AVENUE",
+    "AV",
+    "BOULEVARD",
+    "BD",
+    "CHEMIN",
+    "CHE",
+    "PLACE",
+    "PL",
+    "IMPASSE",
+    "IMP",
+    "ALLEE",
+    "ALLÉE",
+    "ALL",
+    "ROUTE",
+    "RTE",
+    "QUAI",
+    "COURS",
+    "SQUARE",
+    "PASSAGE",
+    "SENTIER",
+    "ZONE",
+    "ZI",
+    "ZA",
+    "ZAC",
+];
+
+/// The role of a line in a french address, in the NF Z10-011 order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Line {
+    Contact,
+    Internal,
+    External,
+    Street,
+    Distribution,
+    Unknown,
+}
+
+impl Line {
+    fn classify(line: &str) -> Self {
+        let first_word = line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(['.', ','])
+            .to_uppercase();
+        let starts_with = |words: &[&str]| words.contains(&first_word.as_str());
+
+        if FrenchAddressParser::care_of(line).is_some() || starts_with(INTERNAL_WORDS) {
+            Line::Internal
+        } else if FrenchAddressParser::postbox(line).is_some() {
+            Line::Distribution
+        } else if starts_with(EXTERNAL_WORDS) {
+            Line::External
+        } else if FrenchAddressParser::locality(line).is_some()
+            || first_word.starts_with(|c: char| c.is_ascii_digit())
+            || starts_with(STREET_WORDS)
+        {
+            Line::Street
+        } else {
+            Line::Unknown
+        }
+    }
+}
+
+/// The role given to a line by [`FrenchAddress::classify_lines`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, strum_macros::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "lowercase")]
+pub enum LineRole {
+    Recipient,
+    Contact,
+    #[strum(serialize = "internal delivery point")]
+    InternalDelivery,
+    #[strum(serialize = "external delivery point")]
+    ExternalDelivery,
+    Street,
+    #[strum(serialize = "distribution information")]
+    DistributionInfo,
+    #[strum(serialize = "postal line")]
+    Postal,
+    #[strum(serialize = "country line")]
+    Country,
+}
+
+impl From<Line> for LineRole {
+    fn from(line: Line) -> Self {
+        match line {
+            Line::Contact => LineRole::Contact,
+            Line::Internal => LineRole::InternalDelivery,
+            Line::External => LineRole::ExternalDelivery,
+            Line::Street => LineRole::Street,
+            // The unknown lines are given a role by their place before.
+            Line::Distribution | Line::Unknown => LineRole::DistributionInfo,
+        }
+    }
+}
+
+/// A line of an address and the role it was given.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ClassifiedLine {
+    pub line: String,
+    pub role: LineRole,
+    /// The line wasn't recognized by its first word, and took the role left
+    /// by its place in the address.
+    pub guessed: bool,
+}
+
+impl FrenchAddress {
+    /// Reads a french address from its raw lines, as printed on an envelope
+    /// (e.g., "Monsieur Jean DELHOURME\n25 RUE DE L'EGLISE\n33380 MIOS").
+    ///
+    /// The first line is the recipient, and the last ones the postal line and
+    /// the optional country line, "FRANCE" when missing. The lines in between
+    /// are recognized by their first word: a lieu-dit or a number for the
+    /// street, "BP" or "CS" for the distribution information, "Appartement"
+    /// or "Chez" for the internal delivery point, "Bâtiment" or "Résidence"
+    /// for the external one. Other lines take the place left by the NF
+    /// Z10-011 order: the contact of a business, then the delivery points
+    /// before the street and the distribution information after it.
+    pub fn from_lines(text: &str) -> Result<FrenchAddress, AddressConversionError> {
+        Self::classify_lines(text).map(|(address, _)| address)
+    }
+
+    /// Same as [`FrenchAddress::from_lines`], also returning the role given
+    /// to each line, in the order of the text.
+    pub fn classify_lines(
+        text: &str,
+    ) -> Result<(FrenchAddress, Vec<ClassifiedLine>), AddressConversionError> {
+        let mut lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let country_line = match lines.last() {
+            Some(line) if lines.len() > 2 && Country::from_str(line).is_ok() => {
+                lines.pop().map(str::to_string)
+            }
+            _ => None,
+        };
+        let country = country_line
+            .clone()
+            .unwrap_or_else(|| Country::France.to_string());
+        let postal = match lines.pop() {
+            Some(postal) if !lines.is_empty() => postal.to_string(),
+            _ => return Err(AddressConversionError::missing("postal")),
+        };
+        let recipient = lines.remove(0);
+        let business = AddressKindDetector::is_company_name(recipient)
+            || matches!(
+                FrenchAddressParser::split_business_name(recipient),
+                (_, Some(_))
+            );
+
+        let mut kinds: Vec<Line> = lines.iter().map(|line| Line::classify(line)).collect();
+        let guessed: Vec<bool> = kinds.iter().map(|kind| *kind == Line::Unknown).collect();
+        // The first unknown line of a business is its contact.
+        if business && kinds.first() == Some(&Line::Unknown) {
+            kinds[0] = Line::Contact;
+        }
+        let street_at = kinds.iter().position(|kind| *kind == Line::Street);
+        let last = kinds.len().saturating_sub(1);
+        for (i, kind) in kinds.iter_mut().enumerate() {
+            if *kind != Line::Unknown {
+                continue;
+            }
+            *kind = match street_at {
+                Some(street_at) if i > street_at => Line::Distribution,
+                Some(_) => Line::External,
+                // Without recognized street, the last line is the street.
+                None if i == last => Line::Street,
+                None => Line::External,
+            };
+        }
+
+        let join = |wanted: Line| {
+            let joined: Vec<&str> = lines
+                .iter()
+                .zip(&kinds)
+                .filter(|(_, kind)| **kind == wanted)
+                .map(|(line, _)| *line)
+                .collect();
+
+            (!joined.is_empty()).then(|| joined.join(" "))
+        };
+        let street = join(Line::Street);
+        let distribution_info = join(Line::Distribution);
+
+        let classified = |line: &str, role: LineRole, guessed: bool| ClassifiedLine {
+            line: line.to_string(),
+            role,
+            guessed,
+        };
+        let mut classified_lines = vec![classified(recipient, LineRole::Recipient, false)];
+        classified_lines.extend(
+            lines
+                .iter()
+                .zip(&kinds)
+                .zip(&guessed)
+                .map(|((line, kind), guessed)| classified(line, (*kind).into(), *guessed)),
+        );
+        classified_lines.push(classified(&postal, LineRole::Postal, false));
+        if let Some(line) = &country_line {
+            classified_lines.push(classified(line, LineRole::Country, false));
+        }
+
+        let address = if business {
+            // Business addresses have a single delivery point line.
+            let external_delivery = match (join(Line::Internal), join(Line::External)) {
+                (Some(internal), Some(external)) => Some(format!("{internal} {external}")),
+                (internal, external) => internal.or(external),
+            };
+
+            FrenchAddress::Business(BusinessFrenchAddress {
+                business_name: recipient.to_string(),
+                identifiers: Vec::new(),
+                recipient: join(Line::Contact),
+                external_delivery,
+                street,
+                distribution_info,
+                postal,
+                country,
+                extras: Map::new(),
+            })
+        } else {
+            FrenchAddress::Individual(IndividualFrenchAddress {
+                name: recipient.to_string(),
+                internal_delivery: join(Line::Internal),
+                external_delivery: join(Line::External),
+                street,
+                distribution_info,
+                postal,
+                country,
+                extras: Map::new(),
+            })
+        };
+
+        Ok((address, classified_lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_read_individual_lines() {
+        let text = "Monsieur Jean DELHOURME\nChez Mireille COPEAU Appartement 2\nEntrée A Bâtiment Jonquille\n25 RUE DE L'EGLISE\nCAUDOS\n33380 MIOS\nFRANCE";
+
+        let FrenchAddress::Individual(address) = FrenchAddress::from_lines(text).unwrap() else {
+            panic!("expected an individual address");
+        };
+        assert_eq!(address.name, "Monsieur Jean DELHOURME");
+        assert_eq!(
+            address.internal_delivery.as_deref(),
+            Some("Chez Mireille COPEAU Appartement 2")
+        );
+        assert_eq!(
+            address.external_delivery.as_deref(),
+            Some("Entrée A Bâtiment Jonquille")
+        );
+        assert_eq!(address.street.as_deref(), Some("25 RUE DE L'EGLISE"));
+        assert_eq!(address.distribution_info.as_deref(), Some("CAUDOS"));
+        assert_eq!(address.postal, "33380 MIOS");
+        assert_eq!(address.country, "FRANCE");
+    }
+
+    #[test]
+    fn it_should_read_business_lines() {
+        let text = "  Société DUPONT\n\nMademoiselle Lucie MARTIN\nRésidence des Capucins Bâtiment Quater\n56 RUE EMILE ZOLA\nBP 90432 MONTFERRIER SUR LEZ\n34092 MONTPELLIER CEDEX 5\n";
+
+        let FrenchAddress::Business(address) = FrenchAddress::from_lines(text).unwrap() else {
+            panic!("expected a business address");
+        };
+        assert_eq!(address.business_name, "Société DUPONT");
+        assert_eq!(
+            address.recipient.as_deref(),
+            Some("Mademoiselle Lucie MARTIN")
+        );
+        assert_eq!(
+            address.external_delivery.as_deref(),
+            Some("Résidence des Capucins Bâtiment Quater")
+        );
+        assert_eq!(address.street.as_deref(), Some("56 RUE EMILE ZOLA"));
+        assert_eq!(
+            address.distribution_info.as_deref(),
+            Some("BP 90432 MONTFERRIER SUR LEZ")
+        );
+        // The country defaults to France.
+        assert_eq!(address.country, "FRANCE");
+    }
+
+    #[test]
+    fn it_should_take_the_last_unknown_line_as_street() {
+        let text = "Madame Lucie MARTIN\nLE VILLAGE\n82500 AUTERIVE";
+
+        let FrenchAddress::Individual(address) = FrenchAddress::from_lines(text).unwrap() else {
+            panic!("expected an individual address");
+        };
+        assert_eq!(address.street.as_deref(), Some("LE VILLAGE"));
+        assert_eq!(address.external_delivery, None);
+    }
+
+    #[test]
+    fn it_should_require_a_recipient_and_a_postal_line() {
+        assert_eq!(
+            FrenchAddress::from_lines("33380 MIOS"),
+            Err(AddressConversionError::missing("postal"))
+        );
+        assert!(FrenchAddress::from_lines("").is_err());
+    }
+}
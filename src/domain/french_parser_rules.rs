@@ -0,0 +1,456 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[cfg(feature = "fast-parse")]
+use super::fast_parse;
+
+/// The french address lines read by a rule, named after their key in the
+/// config (e.g., `postbox` for `postbox_regex`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrenchParserRule {
+    /// The street line, see [`FrenchParserRules::with_street`].
+    Street,
+    /// The postal line, see [`FrenchParserRules::with_postal`].
+    Postal,
+    /// The postbox of a distribution line, see
+    /// [`FrenchParserRules::with_postbox`].
+    Postbox,
+    /// The town location of a distribution line, see
+    /// [`FrenchParserRules::with_town_location`].
+    TownLocation,
+    /// The lieu-dit of a street or distribution line, see
+    /// [`FrenchParserRules::with_locality`].
+    Locality,
+}
+
+impl FrenchParserRule {
+    /// The name of the rule in the config.
+    pub fn key(self) -> &'static str {
+        match self {
+            FrenchParserRule::Street => "street_regex",
+            FrenchParserRule::Postal => "postal_regex",
+            FrenchParserRule::Postbox => "postbox_regex",
+            FrenchParserRule::TownLocation => "town_location_regex",
+            FrenchParserRule::Locality => "locality_regex",
+        }
+    }
+
+    /// The number of capture groups read from the matches of the rule.
+    fn groups(self) -> usize {
+        match self {
+            FrenchParserRule::Street | FrenchParserRule::Postal => 2,
+            FrenchParserRule::TownLocation | FrenchParserRule::Locality => 1,
+            FrenchParserRule::Postbox => 0,
+        }
+    }
+
+    fn built_in(self) -> &'static str {
+        match self {
+            // The optional street number (e.g., 25, 2BIS) and the mandatory
+            // street name. The number may be followed by its repetition
+            // index, attached ("2BIS", "2A") or spaced ("2 BIS").
+            FrenchParserRule::Street => r"^(?:(\d+(?:[a-zA-Z]+|\s+(?i:bis|ter|quater)\b)?) )?(.+)$",
+            // The mandatory postalcode/zipcode and town information.
+            FrenchParserRule::Postal => r"^(\d{5})\s+(.+)$",
+            // Two letters followed by a suite of digits correspond to the
+            // postbox details (e.g., PO 1234, BP 123).
+            FrenchParserRule::Postbox => r"^[A-Z]{2}\s+\d+",
+            // The postbox (ignored), then the town location, empty for a
+            // postbox alone (e.g., BP 90432 MONTFERRIER SUR LEZ ->
+            // MONTFERRIER SUR LEZ).
+            FrenchParserRule::TownLocation => r"^(?:[A-Z]{2}\s+\d+(?:\s+|$))?(.*)$",
+            // A lieu-dit introduced by "LD", "L.D." or "LIEU DIT" (e.g., LD
+            // LES GRANDS CHAMPS -> LES GRANDS CHAMPS).
+            FrenchParserRule::Locality => r"^(?i)(?:LD|L\.D\.|LIEU[ -]DIT)\s+(.+)$",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum FrenchParserRulesError {
+    #[error("Invalid parser config: {0}")]
+    Invalid(String),
+    #[error("Invalid `{}`: {source}", rule.key())]
+    InvalidRegex {
+        rule: FrenchParserRule,
+        source: regex::Error,
+    },
+    #[error("Invalid `{}`: {expected} capture groups are required, found {found}", rule.key())]
+    MissingGroups {
+        rule: FrenchParserRule,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// The regular expressions reading the lines of the french addresses. The
+/// built-in ones can be replaced for the formats they don't cover, e.g., the
+/// military postboxes:
+///
+/// ```
+/// use address_converter::domain::FrenchParserRules;
+///
+/// let rules = FrenchParserRules::new()
+///     .with_postbox(r"^(?:[A-Z]{2}|SECTEUR POSTAL)\s+\d+")
+///     .unwrap();
+/// ```
+///
+/// An override must have the capture groups of the rule it replaces, see
+/// [`FrenchParserRules::with_street`] and the other setters.
+#[derive(Clone, Debug)]
+pub struct FrenchParserRules {
+    street: Regex,
+    postal: Regex,
+    postbox: Regex,
+    town_location: Regex,
+    locality: Regex,
+    /// Reads the lines of the built-in street and postal rules without their
+    /// regex, see [`FrenchParserRules::with_fast_parse`].
+    #[cfg(feature = "fast-parse")]
+    fast_parse: bool,
+}
+
+/// The overrides of the `parser.french` section of the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrenchParserOverrides {
+    street_regex: Option<String>,
+    postal_regex: Option<String>,
+    postbox_regex: Option<String>,
+    town_location_regex: Option<String>,
+    locality_regex: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ParserConfig {
+    #[serde(default)]
+    parser: ParserSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ParserSection {
+    #[serde(default)]
+    french: FrenchParserOverrides,
+}
+
+static BUILT_IN_RULES: Lazy<FrenchParserRules> = Lazy::new(FrenchParserRules::new);
+
+thread_local! {
+    /// The rules set by [`FrenchParserRules::apply`] on this thread.
+    static CURRENT_RULES: RefCell<Option<Arc<FrenchParserRules>>> = const { RefCell::new(None) };
+}
+
+impl Default for FrenchParserRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrenchParserRules {
+    /// The built-in rules.
+    pub fn new() -> Self {
+        let built_in = |rule: FrenchParserRule| Regex::new(rule.built_in()).unwrap();
+
+        Self {
+            street: built_in(FrenchParserRule::Street),
+            postal: built_in(FrenchParserRule::Postal),
+            postbox: built_in(FrenchParserRule::Postbox),
+            town_location: built_in(FrenchParserRule::TownLocation),
+            locality: built_in(FrenchParserRule::Locality),
+            #[cfg(feature = "fast-parse")]
+            fast_parse: false,
+        }
+    }
+
+    /// Reads the overrides of the `parser.french` section of a JSON config,
+    /// the built-in rules being kept for the others:
+    ///
+    /// ```json
+    /// { "parser": { "french": { "postbox_regex": "^(?:[A-Z]{2}|SECTEUR POSTAL)\\s+\\d+" } } }
+    /// ```
+    pub fn from_config(json: &str) -> Result<Self, FrenchParserRulesError> {
+        let config: ParserConfig = serde_json::from_str(json)
+            .map_err(|e| FrenchParserRulesError::Invalid(e.to_string()))?;
+        let overrides = config.parser.french;
+
+        let mut rules = Self::new();
+        if let Some(pattern) = &overrides.street_regex {
+            rules = rules.with_street(pattern)?;
+        }
+        if let Some(pattern) = &overrides.postal_regex {
+            rules = rules.with_postal(pattern)?;
+        }
+        if let Some(pattern) = &overrides.postbox_regex {
+            rules = rules.with_postbox(pattern)?;
+        }
+        if let Some(pattern) = &overrides.town_location_regex {
+            rules = rules.with_town_location(pattern)?;
+        }
+        if let Some(pattern) = &overrides.locality_regex {
+            rules = rules.with_locality(pattern)?;
+        }
+
+        Ok(rules)
+    }
+
+    /// Reads the street lines with `pattern`, capturing the optional number
+    /// then the name.
+    pub fn with_street(mut self, pattern: &str) -> Result<Self, FrenchParserRulesError> {
+        self.street = compile(FrenchParserRule::Street, pattern)?;
+        Ok(self)
+    }
+
+    /// Reads the french postal lines with `pattern`, capturing the postcode
+    /// then the town.
+    pub fn with_postal(mut self, pattern: &str) -> Result<Self, FrenchParserRulesError> {
+        self.postal = compile(FrenchParserRule::Postal, pattern)?;
+        Ok(self)
+    }
+
+    /// Finds the postbox of the distribution lines with `pattern`, the whole
+    /// match being the postbox.
+    pub fn with_postbox(mut self, pattern: &str) -> Result<Self, FrenchParserRulesError> {
+        self.postbox = compile(FrenchParserRule::Postbox, pattern)?;
+        Ok(self)
+    }
+
+    /// Reads the distribution lines with `pattern`, capturing the town
+    /// location after the postbox.
+    pub fn with_town_location(mut self, pattern: &str) -> Result<Self, FrenchParserRulesError> {
+        self.town_location = compile(FrenchParserRule::TownLocation, pattern)?;
+        Ok(self)
+    }
+
+    /// Reads the lieux-dits with `pattern`, capturing the lieu-dit.
+    pub fn with_locality(mut self, pattern: &str) -> Result<Self, FrenchParserRulesError> {
+        self.locality = compile(FrenchParserRule::Locality, pattern)?;
+        Ok(self)
+    }
+
+    /// Reads the street and postal lines with hand-rolled parsers capturing
+    /// what the built-in regexes do, for the throughput of the bulk loads.
+    /// The rules replaced by a pattern are still read with their regex, and
+    /// so are the rare lines whose Unicode digits or case folding only the
+    /// regex reads.
+    #[cfg(feature = "fast-parse")]
+    pub fn with_fast_parse(mut self) -> Self {
+        self.fast_parse = true;
+        self
+    }
+
+    /// Splits a street line into its optional number and its name.
+    pub fn split_street<'a>(&self, line: &'a str) -> Option<(Option<&'a str>, &'a str)> {
+        #[cfg(feature = "fast-parse")]
+        if self.fast_parse && self.street.as_str() == FrenchParserRule::Street.built_in() {
+            if let Ok(split) = fast_parse::split_street(line) {
+                return split;
+            }
+        }
+        let caps = self.street.captures(line)?;
+
+        Some((
+            caps.get(1).map(|m| m.as_str()),
+            caps.get(2).map_or("", |m| m.as_str()),
+        ))
+    }
+
+    /// Splits a french postal line into its postcode and its town.
+    pub fn split_postal<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        #[cfg(feature = "fast-parse")]
+        if self.fast_parse && self.postal.as_str() == FrenchParserRule::Postal.built_in() {
+            if let Ok(split) = fast_parse::split_postal(line) {
+                return split;
+            }
+        }
+        let caps = self.postal.captures(line)?;
+
+        Some((caps.get(1)?.as_str(), caps.get(2)?.as_str()))
+    }
+
+    pub fn street(&self) -> &Regex {
+        &self.street
+    }
+
+    pub fn postal(&self) -> &Regex {
+        &self.postal
+    }
+
+    pub fn postbox(&self) -> &Regex {
+        &self.postbox
+    }
+
+    pub fn town_location(&self) -> &Regex {
+        &self.town_location
+    }
+
+    pub fn locality(&self) -> &Regex {
+        &self.locality
+    }
+
+    /// Runs `f` with the [`FrenchAddressParser`](super::FrenchAddressParser)
+    /// of this thread reading the lines with `rules`, the previous rules being
+    /// restored afterwards.
+    pub fn apply<T>(rules: &Arc<FrenchParserRules>, f: impl FnOnce() -> T) -> T {
+        struct Restore(Option<Arc<FrenchParserRules>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                CURRENT_RULES.with(|current| *current.borrow_mut() = previous);
+            }
+        }
+
+        let previous = CURRENT_RULES.with(|current| current.replace(Some(Arc::clone(rules))));
+        let _restore = Restore(previous);
+
+        f()
+    }
+
+    /// Runs `f` with the rules applied on this thread, the built-in ones
+    /// otherwise.
+    pub(crate) fn with_current<T>(f: impl FnOnce(&FrenchParserRules) -> T) -> T {
+        let current = CURRENT_RULES.with(|current| current.borrow().clone());
+
+        match current {
+            Some(rules) => f(&rules),
+            None => f(&BUILT_IN_RULES),
+        }
+    }
+}
+
+/// Compiles the override of `rule`, checking that it has the capture groups
+/// read from its matches.
+fn compile(rule: FrenchParserRule, pattern: &str) -> Result<Regex, FrenchParserRulesError> {
+    let regex = Regex::new(pattern)
+        .map_err(|source| FrenchParserRulesError::InvalidRegex { rule, source })?;
+    // The first group is the whole match.
+    let found = regex.captures_len() - 1;
+    if found < rule.groups() {
+        return Err(FrenchParserRulesError::MissingGroups {
+            rule,
+            expected: rule.groups(),
+            found,
+        });
+    }
+
+    Ok(regex)
+}
+
+/// The optional number and the name of a street line.
+#[cfg(test)]
+type StreetSplit = (Option<&'static str>, &'static str);
+
+/// The street lines and what the built-in rule captures, shared with the
+/// hand-rolled parser.
+#[cfg(test)]
+pub(crate) const STREET_VECTORS: &[(&str, Option<StreetSplit>)] = &[
+    ("25 RUE DE L'EGLISE", Some((Some("25"), "RUE DE L'EGLISE"))),
+    ("25 RUE DE L'ÉGLISE", Some((Some("25"), "RUE DE L'ÉGLISE"))),
+    ("2BIS RUE DES LILAS", Some((Some("2BIS"), "RUE DES LILAS"))),
+    (
+        "2 bis RUE DES LILAS",
+        Some((Some("2 bis"), "RUE DES LILAS")),
+    ),
+    ("2  TER AVENUE FOCH", Some((Some("2  TER"), "AVENUE FOCH"))),
+    ("12\u{a0}QUATER RUE", Some((Some("12\u{a0}QUATER"), "RUE"))),
+    ("2 BISCUITERIE", Some((Some("2"), "BISCUITERIE"))),
+    ("2 QUATER", Some((Some("2"), "QUATER"))),
+    ("3 ÉTAGE", Some((Some("3"), "ÉTAGE"))),
+    ("25  RUE", Some((Some("25"), " RUE"))),
+    ("2B-RUE", Some((None, "2B-RUE"))),
+    ("2B", Some((None, "2B"))),
+    ("25", Some((None, "25"))),
+    ("25 ", Some((None, "25 "))),
+    ("LE VILLAGE", Some((None, "LE VILLAGE"))),
+    (" 25 RUE", Some((None, " 25 RUE"))),
+    ("", None),
+];
+
+/// The postal lines and what the built-in rule captures, shared with the
+/// hand-rolled parser.
+#[cfg(test)]
+pub(crate) const POSTAL_VECTORS: &[(&str, Option<(&str, &str)>)] = &[
+    ("33380 MIOS", Some(("33380", "MIOS"))),
+    (
+        "34092 MONTPELLIER CEDEX 5",
+        Some(("34092", "MONTPELLIER CEDEX 5")),
+    ),
+    ("42000 SAINT-ÉTIENNE", Some(("42000", "SAINT-ÉTIENNE"))),
+    ("33380\tMIOS", Some(("33380", "MIOS"))),
+    ("33380   MIOS", Some(("33380", "MIOS"))),
+    ("33380\nMIOS", Some(("33380", "MIOS"))),
+    ("33380  ", Some(("33380", " "))),
+    ("33380 ", None),
+    ("33380 \n", None),
+    ("33380 MIOS\nCEDEX", None),
+    ("33380MIOS", None),
+    ("333800 MIOS", None),
+    ("3338 MIOS", None),
+    ("MIOS 33380", None),
+    ("", None),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        FrenchParserRule, FrenchParserRules, FrenchParserRulesError, POSTAL_VECTORS, STREET_VECTORS,
+    };
+    use crate::domain::FrenchAddressParser;
+
+    #[test]
+    fn it_should_read_the_vectors() {
+        let rules = FrenchParserRules::new();
+
+        for (line, expected) in STREET_VECTORS {
+            assert_eq!(rules.split_street(line), *expected, "{line:?}");
+        }
+        for (line, expected) in POSTAL_VECTORS {
+            assert_eq!(rules.split_postal(line), *expected, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn it_should_validate_the_overrides() {
+        assert!(matches!(
+            FrenchParserRules::new().with_postal(r"^(\d{5}"),
+            Err(FrenchParserRulesError::InvalidRegex {
+                rule: FrenchParserRule::Postal,
+                ..
+            })
+        ));
+        let error = FrenchParserRules::new().with_street(r"^(.+)$").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid `street_regex`: 2 capture groups are required, found 1"
+        );
+
+        let error = FrenchParserRules::from_config(r#"{"parser": {"french": {"postbox": "^SP"}}}"#)
+            .unwrap_err();
+        assert!(matches!(error, FrenchParserRulesError::Invalid(_)));
+    }
+
+    #[test]
+    fn it_should_apply_the_overrides() {
+        let rules = FrenchParserRules::from_config(
+            r#"{"parser": {"french": {"postbox_regex": "^(?:[A-Z]{2}|SECTEUR POSTAL)\\s+\\d+"}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(FrenchAddressParser::postbox("SECTEUR POSTAL 69001"), None);
+        let postbox = FrenchParserRules::apply(&Arc::new(rules), || {
+            FrenchAddressParser::postbox("SECTEUR POSTAL 69001").map(str::to_string)
+        });
+        assert_eq!(postbox.as_deref(), Some("SECTEUR POSTAL 69001"));
+        // The built-in rules are restored.
+        assert_eq!(FrenchAddressParser::postbox("SECTEUR POSTAL 69001"), None);
+        assert_eq!(FrenchAddressParser::postbox("BP 90432"), Some("BP 90432"));
+    }
+}
@@ -1,38 +1,430 @@
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-#[serde(untagged)]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use strum::EnumString;
+
+use super::address::{AddressKind, Country, CountryRepr};
+use super::address_kind_detector::KIND_TAG;
+
+/// Mapping of the snake case keys to the ISO 20022 XML element names.
+const ISO_TAGS: &[(&str, &str)] = &[
+    ("name", "Nm"),
+    ("business_name", "Nm"),
+    ("organisation_identification", "OrgId"),
+    ("id", "Id"),
+    ("scheme_name", "SchmeNm"),
+    ("postal_address", "PstlAdr"),
+    ("care_of", "CareOf"),
+    ("street_name", "StrtNm"),
+    ("building_number", "BldgNb"),
+    ("floor", "Flr"),
+    ("room", "Room"),
+    ("postbox", "PstBx"),
+    ("department", "Dept"),
+    ("sub_department", "SubDept"),
+    ("postcode", "PstCd"),
+    ("town_name", "TwnNm"),
+    ("town_location_name", "TwnLctnNm"),
+    ("country_sub_division", "CtrySubDvsn"),
+    ("country", "Ctry"),
+];
+
+/// The naming of the keys in the JSON representation of an ISO 20022
+/// address.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(ascii_case_insensitive)]
+pub enum IsoJsonKeys {
+    /// The field names (`street_name`).
+    #[default]
+    #[strum(serialize = "snake")]
+    Snake,
+    /// The ISO 20022 XML element names (`StrtNm`).
+    #[strum(serialize = "iso-tags")]
+    IsoTags,
+    /// The camel case field names (`streetName`).
+    #[strum(serialize = "camel")]
+    Camel,
+}
+
+impl IsoJsonKeys {
+    fn rename(&self, key: &str) -> String {
+        match self {
+            IsoJsonKeys::Snake => key.to_string(),
+            IsoJsonKeys::IsoTags => ISO_TAGS
+                .iter()
+                .find(|(snake, _)| *snake == key)
+                .map_or_else(|| key.to_string(), |(_, tag)| tag.to_string()),
+            IsoJsonKeys::Camel => {
+                let mut parts = key.split('_');
+                let first = parts.next().unwrap_or_default().to_string();
+                parts.fold(first, |mut camel, part| {
+                    let mut chars = part.chars();
+                    if let Some(c) = chars.next() {
+                        camel.extend(c.to_uppercase());
+                        camel.push_str(chars.as_str());
+                    }
+                    camel
+                })
+            }
+        }
+    }
+
+    fn rename_all(&self, value: Value) -> Value {
+        match value {
+            Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (self.rename(&key), self.rename_all(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|v| self.rename_all(v)).collect())
+            }
+            value => value,
+        }
+    }
+}
+
+/// An ISO 20022 address, read from its tagged representation when the input
+/// has a `kind` field, or from the name field provided otherwise.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(remote = "Self", untagged)]
 pub enum IsoAddress {
     IndividualIsoAddress {
         name: String,
+        #[serde(alias = "PstlAdr", alias = "postalAddress")]
         postal_address: IsoPostalAddress,
+        /// Unknown fields, kept as is through conversions and storage.
+        #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+        #[cfg_attr(feature = "openapi", schema(ignore))]
+        extras: Map<String, Value>,
     },
     BusinessIsoAddress {
+        #[serde(alias = "businessName")]
         business_name: String,
+        /// <Id><OrgId><Othr>
+        #[serde(
+            default,
+            skip_serializing_if = "Vec::is_empty",
+            alias = "OrgId",
+            alias = "organisationIdentification"
+        )]
+        organisation_identification: Vec<IsoOrgIdentification>,
+        #[serde(alias = "PstlAdr", alias = "postalAddress")]
         postal_address: IsoPostalAddress,
+        /// Unknown fields, kept as is through conversions and storage.
+        #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+        #[cfg_attr(feature = "openapi", schema(ignore))]
+        extras: Map<String, Value>,
     },
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+impl Serialize for IsoAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        IsoAddress::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IsoAddress {
+    /// The variants only differ by their name field: the tag moves the name
+    /// to the field of the kind, which selects the variant.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = Value::deserialize(deserializer)?;
+        let kind = AddressKind::take_tag(&mut value).map_err(de::Error::custom)?;
+        rename_iso_name_tag(&mut value, kind.as_ref());
+        let Some(kind) = kind else {
+            return IsoAddress::deserialize(value).map_err(|e| {
+                de::Error::custom(format!(
+                    "{e}, give its {KIND_TAG} (\"individual\" or \"business\") for details"
+                ))
+            });
+        };
+
+        kind.rename_name_field(&mut value);
+        let name_fields: &[&'static str] = match kind {
+            AddressKind::Individual => &["name"],
+            AddressKind::Business => &["business_name", "businessName"],
+        };
+        if !name_fields.iter().any(|field| value.get(field).is_some()) {
+            return Err(de::Error::missing_field(name_fields[0]));
+        }
+
+        IsoAddress::deserialize(value).map_err(de::Error::custom)
+    }
+}
+
+/// Reads `Nm`, the ISO 20022 tag of both name fields, as the name of the kind
+/// given. Untagged, it is the name of a business when there is an
+/// organisation identification, of an individual otherwise.
+fn rename_iso_name_tag(value: &mut Value, kind: Option<&AddressKind>) {
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+    let Some(name) = fields.shift_remove("Nm") else {
+        return;
+    };
+
+    let is_business = match kind {
+        Some(AddressKind::Business) => true,
+        Some(AddressKind::Individual) => false,
+        None => [
+            "organisation_identification",
+            "organisationIdentification",
+            "OrgId",
+        ]
+        .iter()
+        .any(|field| fields.contains_key(*field)),
+    };
+    let name_field = if is_business { "business_name" } else { "name" };
+    fields.entry(name_field).or_insert(name);
+}
+
+impl IsoAddress {
+    pub fn kind(&self) -> AddressKind {
+        match self {
+            IsoAddress::IndividualIsoAddress { .. } => AddressKind::Individual,
+            IsoAddress::BusinessIsoAddress { .. } => AddressKind::Business,
+        }
+    }
+
+    /// Serializes the address with the given key naming. The extra fields
+    /// are written as they were received.
+    pub fn to_json_value(&self, keys: IsoJsonKeys) -> serde_json::Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        let extras = self.extras();
+        if let Some(fields) = value.as_object_mut() {
+            fields.retain(|key, _| !extras.contains_key(key));
+        }
+
+        let mut renamed = keys.rename_all(value);
+        if let Some(fields) = renamed.as_object_mut() {
+            fields.extend(extras.clone());
+        }
+
+        Ok(renamed)
+    }
+
+    /// Writes the country as set by `repr`, for an address already converted
+    /// (e.g., to answer a consumer wanting the country name). An unknown
+    /// country is left as is.
+    pub fn set_country_repr(&mut self, repr: CountryRepr) {
+        let (IsoAddress::IndividualIsoAddress { postal_address, .. }
+        | IsoAddress::BusinessIsoAddress { postal_address, .. }) = self;
+
+        if let Ok(country) = Country::from_str(&postal_address.country) {
+            postal_address.country = repr.write(&country).to_string();
+        }
+    }
+
+    /// The unknown fields of the address.
+    pub fn extras(&self) -> &Map<String, Value> {
+        match self {
+            IsoAddress::IndividualIsoAddress { extras, .. }
+            | IsoAddress::BusinessIsoAddress { extras, .. } => extras,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IsoOrgIdentification {
+    /// <Id>
+    #[serde(alias = "Id")]
+    pub id: String,
+    /// <SchmeNm><Prtry> = "SIREN" | "SIRET"
+    #[serde(alias = "SchmeNm", alias = "schemeName")]
+    pub scheme_name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct IsoPostalAddress {
+    /// <CareOf>
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        alias = "CareOf",
+        alias = "careOf"
+    )]
+    pub care_of: Option<String>,
     /// <StrtNm>
+    #[serde(alias = "StrtNm", alias = "streetName")]
     pub street_name: Option<String>,
     /// <BldgNb>
+    #[serde(alias = "BldgNb", alias = "buildingNumber")]
     pub building_number: Option<String>,
     /// <Flr>
+    #[serde(alias = "Flr")]
     pub floor: Option<String>,
     /// <Room>
+    #[serde(alias = "Room")]
     pub room: Option<String>,
     /// <PstBx>
+    #[serde(alias = "PstBx")]
     pub postbox: Option<String>,
-    /// <Dept>
+    /// <Dept>, the service of a business.
+    #[serde(alias = "Dept")]
     pub department: Option<String>,
+    /// <SubDept>, the contact person of a business within its service.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        alias = "SubDept",
+        alias = "subDepartment"
+    )]
+    pub sub_department: Option<String>,
     /// <PstCd>
+    #[serde(alias = "PstCd")]
     pub postcode: String,
     /// <TwnNm>
+    #[serde(alias = "TwnNm", alias = "townName")]
     pub town_name: String,
     /// <TwnLctnNm>
+    #[serde(alias = "TwnLctnNm", alias = "townLocationName")]
     pub town_location_name: Option<String>,
+    /// <CtrySubDvsn>, the state or province of foreign addresses.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        alias = "CtrySubDvsn",
+        alias = "countrySubDivision"
+    )]
+    pub country_sub_division: Option<String>,
     /// <Ctry> = "FR"
+    #[serde(alias = "Ctry")]
     pub country: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn address() -> IsoAddress {
+        IsoAddress::IndividualIsoAddress {
+            name: "Monsieur Jean DELHOURME".to_string(),
+            postal_address: IsoPostalAddress {
+                care_of: None,
+                street_name: Some("RUE DE L'EGLISE".to_string()),
+                building_number: Some("25".to_string()),
+                floor: None,
+                room: None,
+                postbox: None,
+                department: None,
+                sub_department: None,
+                postcode: "33380".to_string(),
+                town_name: "MIOS".to_string(),
+                town_location_name: None,
+                country_sub_division: None,
+                country: "FR".to_string(),
+            },
+            extras: Map::new(),
+        }
+    }
+
+    #[test]
+    fn tagged_addresses() {
+        let postal_address = serde_json::json!({
+            "postcode": "33380",
+            "town_name": "MIOS",
+            "country": "FR",
+        });
+
+        let business: IsoAddress = serde_json::from_value(serde_json::json!({
+            "kind": "business",
+            "name": "Jean DELHOURME",
+            "postal_address": postal_address,
+        }))
+        .unwrap();
+        assert_eq!(business.kind(), AddressKind::Business);
+        assert!(business.extras().is_empty());
+
+        let error = serde_json::from_value::<IsoAddress>(serde_json::json!({
+            "kind": "business",
+            "postal_address": postal_address,
+        }))
+        .unwrap_err();
+        assert!(error.to_string().contains("missing field `business_name`"));
+
+        let error =
+            serde_json::from_value::<IsoAddress>(serde_json::json!({"name": "Jean DELHOURME"}))
+                .unwrap_err();
+        assert!(error.to_string().contains("give its kind"));
+
+        // The tag is only written on demand.
+        let value = serde_json::to_value(&business).unwrap();
+        assert_eq!(value.get(KIND_TAG), None);
+    }
+
+    #[test]
+    fn it_should_parse_json_keys() {
+        assert_eq!(IsoJsonKeys::from_str("snake"), Ok(IsoJsonKeys::Snake));
+        assert_eq!(IsoJsonKeys::from_str("ISO-TAGS"), Ok(IsoJsonKeys::IsoTags));
+        assert_eq!(IsoJsonKeys::from_str("camel"), Ok(IsoJsonKeys::Camel));
+        assert!(IsoJsonKeys::from_str("kebab").is_err());
+    }
+
+    #[test]
+    fn iso_tags_keys() {
+        let value = address().to_json_value(IsoJsonKeys::IsoTags).unwrap();
+        assert_eq!(value["Nm"], "Monsieur Jean DELHOURME");
+        assert_eq!(value["PstlAdr"]["StrtNm"], "RUE DE L'EGLISE");
+        assert_eq!(value["PstlAdr"]["TwnLctnNm"], serde_json::Value::Null);
+        assert_eq!(value["PstlAdr"]["Ctry"], "FR");
+
+        // The renamed keys are accepted back as input
+        let parsed: IsoAddress = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, address());
+
+        let business = IsoAddress::BusinessIsoAddress {
+            business_name: "Société DUPONT".to_string(),
+            organisation_identification: vec![IsoOrgIdentification {
+                id: "123456789".to_string(),
+                scheme_name: "SIREN".to_string(),
+            }],
+            postal_address: match address() {
+                IsoAddress::IndividualIsoAddress { postal_address, .. } => postal_address,
+                _ => unreachable!(),
+            },
+            extras: Map::new(),
+        };
+        let value = business.to_json_value(IsoJsonKeys::IsoTags).unwrap();
+        assert_eq!(value["Nm"], "Société DUPONT");
+        let parsed: IsoAddress = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(parsed, business);
+
+        // The kind tag takes precedence over the organisation identification
+        let mut value = business.kind().tag(value);
+        value["OrgId"] = serde_json::json!([]);
+        let parsed: IsoAddress = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.kind(), AddressKind::Business);
+    }
+
+    #[test]
+    fn camel_keys() {
+        let value = address().to_json_value(IsoJsonKeys::Camel).unwrap();
+        assert_eq!(value["postalAddress"]["buildingNumber"], "25");
+        assert_eq!(value["postalAddress"]["townName"], "MIOS");
+
+        let parsed: IsoAddress = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, address());
+    }
+
+    #[test]
+    fn extras_keep_their_keys() {
+        let value = serde_json::json!({
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": address().to_json_value(IsoJsonKeys::Snake).unwrap()["postal_address"],
+            "crm_id": "C-42",
+        });
+        let parsed: IsoAddress = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.extras()["crm_id"], "C-42");
+
+        let value = parsed.to_json_value(IsoJsonKeys::IsoTags).unwrap();
+        assert_eq!(value["crm_id"], "C-42");
+        assert_eq!(value["Nm"], "Monsieur Jean DELHOURME");
+    }
+}
@@ -0,0 +1,328 @@
+use roxmltree::{Document, Node};
+use serde_json::Map;
+use thiserror::Error;
+
+use super::address_conversion::AddressConversionError;
+use super::iso20022_address::*;
+use super::person_name::{Civility, PersonName};
+
+/// Failure to read the parties of an ISO 20022 XML message.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum IsoXmlError {
+    #[error("Invalid XML document: {0}")]
+    Syntax(#[from] roxmltree::Error),
+    #[error("No party with a postal address in the XML document")]
+    NoParty,
+    #[error(transparent)]
+    Conversion(#[from] AddressConversionError),
+}
+
+impl IsoAddress {
+    /// Writes the address as a party block (`PartyIdentification135`),
+    /// ready to be embedded in a pain.001 or pacs.008 message:
+    /// `<Pty><Nm>...</Nm><PstlAdr>...</PstlAdr><Id>...</Id></Pty>`. The
+    /// civility of an individual is written as its name prefix code
+    /// (`<CtctDtls><NmPrfx>MIST</NmPrfx></CtctDtls>`).
+    pub fn to_party_xml(&self) -> String {
+        let (name, identifiers, postal_address) = match self {
+            IsoAddress::IndividualIsoAddress {
+                name,
+                postal_address,
+                ..
+            } => (name, &[][..], postal_address),
+            IsoAddress::BusinessIsoAddress {
+                business_name,
+                organisation_identification,
+                postal_address,
+                ..
+            } => (
+                business_name,
+                &organisation_identification[..],
+                postal_address,
+            ),
+        };
+
+        let mut xml = String::from("<Pty>\n");
+        xml.push_str(&element(1, "Nm", name));
+        xml.push_str("  <PstlAdr>\n");
+        // The elements follow the order of the `PostalAddress27` sequence.
+        let elements = [
+            ("CareOf", &postal_address.care_of),
+            ("Dept", &postal_address.department),
+            ("SubDept", &postal_address.sub_department),
+            ("StrtNm", &postal_address.street_name),
+            ("BldgNb", &postal_address.building_number),
+            ("Flr", &postal_address.floor),
+            ("PstBx", &postal_address.postbox),
+            ("Room", &postal_address.room),
+        ];
+        for (tag, value) in elements {
+            if let Some(value) = value {
+                xml.push_str(&element(2, tag, value));
+            }
+        }
+        xml.push_str(&element(2, "PstCd", &postal_address.postcode));
+        xml.push_str(&element(2, "TwnNm", &postal_address.town_name));
+        let elements = [
+            ("TwnLctnNm", &postal_address.town_location_name),
+            ("CtrySubDvsn", &postal_address.country_sub_division),
+        ];
+        for (tag, value) in elements {
+            if let Some(value) = value {
+                xml.push_str(&element(2, tag, value));
+            }
+        }
+        xml.push_str(&element(2, "Ctry", &postal_address.country));
+        xml.push_str("  </PstlAdr>\n");
+
+        if !identifiers.is_empty() {
+            xml.push_str("  <Id>\n    <OrgId>\n");
+            for identifier in identifiers {
+                xml.push_str("      <Othr>\n");
+                xml.push_str(&element(4, "Id", &identifier.id));
+                xml.push_str("        <SchmeNm>\n");
+                xml.push_str(&element(5, "Prtry", &identifier.scheme_name));
+                xml.push_str("        </SchmeNm>\n      </Othr>\n");
+            }
+            xml.push_str("    </OrgId>\n  </Id>\n");
+        }
+        let name_prefix = match self {
+            IsoAddress::IndividualIsoAddress { name, .. } => PersonName::parse(name)
+                .and_then(|name| name.civility)
+                .and_then(Civility::name_prefix),
+            IsoAddress::BusinessIsoAddress { .. } => None,
+        };
+        if let Some(name_prefix) = name_prefix {
+            xml.push_str("  <CtctDtls>\n");
+            xml.push_str(&element(2, "NmPrfx", name_prefix));
+            xml.push_str("  </CtctDtls>\n");
+        }
+        xml.push_str("</Pty>\n");
+
+        xml
+    }
+
+    /// Reads the address of every party of an ISO 20022 XML document, such
+    /// as a full pain.001 or pacs.008 message: each element holding a
+    /// `<PstlAdr>` (`<Dbtr>`, `<Cdtr>`, `<InitgPty>`...), in document order.
+    /// The parties with an organisation identifier are businesses.
+    pub fn parties_from_xml(xml: &str) -> Result<Vec<IsoAddress>, IsoXmlError> {
+        let document = Document::parse(xml)?;
+        let parties = document
+            .descendants()
+            .filter(|node| node.is_element() && child(*node, "PstlAdr").is_some())
+            .map(party)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if parties.is_empty() {
+            return Err(IsoXmlError::NoParty);
+        }
+
+        Ok(parties)
+    }
+}
+
+/// One `<tag>value</tag>` line, indented by `depth` levels.
+fn element(depth: usize, tag: &str, value: &str) -> String {
+    format!("{}<{tag}>{}</{tag}>\n", "  ".repeat(depth), escape(value))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == tag)
+}
+
+/// The trimmed text of the `path` descendant of `node`.
+fn text(node: Node, path: &[&str]) -> Option<String> {
+    path.iter()
+        .try_fold(node, |node, tag| child(node, tag))
+        .and_then(|node| node.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn party(node: Node) -> Result<IsoAddress, AddressConversionError> {
+    let tag = node.tag_name().name();
+    let required = |path: &[&str]| {
+        text(node, path)
+            .ok_or_else(|| AddressConversionError::missing(format!("{tag}.{}", path.join("."))))
+    };
+    let postal = |element: &str| text(node, &["PstlAdr", element]);
+
+    let name = required(&["Nm"])?;
+    let postal_address = IsoPostalAddress {
+        care_of: postal("CareOf"),
+        street_name: postal("StrtNm"),
+        building_number: postal("BldgNb"),
+        floor: postal("Flr"),
+        room: postal("Room"),
+        postbox: postal("PstBx"),
+        department: postal("Dept"),
+        sub_department: postal("SubDept"),
+        postcode: required(&["PstlAdr", "PstCd"])?,
+        town_name: required(&["PstlAdr", "TwnNm"])?,
+        town_location_name: postal("TwnLctnNm"),
+        country_sub_division: postal("CtrySubDvsn"),
+        country: required(&["PstlAdr", "Ctry"])?,
+    };
+
+    let Some(org_id) = child(node, "Id").and_then(|id| child(id, "OrgId")) else {
+        return Ok(IsoAddress::IndividualIsoAddress {
+            name,
+            postal_address,
+            extras: Map::new(),
+        });
+    };
+    let organisation_identification = org_id
+        .children()
+        .filter(|other| other.tag_name().name() == "Othr")
+        .filter_map(|other| {
+            Some(IsoOrgIdentification {
+                id: text(other, &["Id"])?,
+                scheme_name: text(other, &["SchmeNm", "Prtry"])
+                    .or_else(|| text(other, &["SchmeNm", "Cd"]))?,
+            })
+        })
+        .collect();
+
+    Ok(IsoAddress::BusinessIsoAddress {
+        business_name: name,
+        organisation_identification,
+        postal_address,
+        extras: Map::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Map;
+
+    use super::*;
+
+    fn business() -> IsoAddress {
+        IsoAddress::BusinessIsoAddress {
+            business_name: "Société DUPONT & Fils".to_string(),
+            organisation_identification: vec![IsoOrgIdentification {
+                id: "732829320".to_string(),
+                scheme_name: "SIREN".to_string(),
+            }],
+            postal_address: IsoPostalAddress {
+                care_of: None,
+                street_name: Some("RUE EMILE ZOLA".to_string()),
+                building_number: Some("56".to_string()),
+                floor: None,
+                room: None,
+                postbox: Some("BP 90432".to_string()),
+                department: Some("Service achats".to_string()),
+                sub_department: None,
+                postcode: "34092".to_string(),
+                town_name: "MONTPELLIER CEDEX 5".to_string(),
+                town_location_name: None,
+                country_sub_division: None,
+                country: "FR".to_string(),
+            },
+            extras: Map::new(),
+        }
+    }
+
+    #[test]
+    fn party_xml_round_trip() {
+        let party = business().to_party_xml();
+        assert!(party.starts_with("<Pty>\n  <Nm>Société DUPONT &amp; Fils</Nm>\n  <PstlAdr>\n"));
+        assert!(party.contains("<SchmeNm>\n          <Prtry>SIREN</Prtry>"));
+
+        let parties = IsoAddress::parties_from_xml(&party).unwrap();
+        assert_eq!(parties, vec![business()]);
+    }
+
+    #[test]
+    fn party_xml_name_prefix() {
+        let IsoAddress::BusinessIsoAddress { postal_address, .. } = business() else {
+            unreachable!()
+        };
+        let individual = IsoAddress::IndividualIsoAddress {
+            name: "Madame Isabelle RICHARD".to_string(),
+            postal_address,
+            extras: Map::new(),
+        };
+
+        let party = individual.to_party_xml();
+        assert!(party.ends_with(
+            "  </PstlAdr>\n  <CtctDtls>\n    <NmPrfx>MADM</NmPrfx>\n  </CtctDtls>\n</Pty>\n"
+        ));
+        assert_eq!(
+            IsoAddress::parties_from_xml(&party).unwrap(),
+            vec![individual]
+        );
+    }
+
+    #[test]
+    fn parties_of_a_payment_message() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:pain.001.001.09">
+  <CstmrCdtTrfInitn>
+    <GrpHdr><MsgId>MSG-1</MsgId><NbOfTxs>1</NbOfTxs></GrpHdr>
+    <PmtInf>
+      <Dbtr>
+        <Nm>Monsieur Jean DELHOURME</Nm>
+        <PstlAdr>
+          <StrtNm>RUE DE L'EGLISE</StrtNm>
+          <BldgNb>25</BldgNb>
+          <PstCd>33380</PstCd>
+          <TwnNm>MIOS</TwnNm>
+          <Ctry>FR</Ctry>
+        </PstlAdr>
+      </Dbtr>
+      <CdtTrfTxInf>
+        <Cdtr>
+          <Nm>Mr John SMITH</Nm>
+          <PstlAdr><PstCd>SW1A 2AA</PstCd><Ctry>GB</Ctry></PstlAdr>
+        </Cdtr>
+      </CdtTrfTxInf>
+    </PmtInf>
+  </CstmrCdtTrfInitn>
+</Document>"#;
+
+        let Err(IsoXmlError::Conversion(error)) = IsoAddress::parties_from_xml(xml) else {
+            panic!("expected a missing town name");
+        };
+        assert_eq!(error.field(), "Cdtr.PstlAdr.TwnNm");
+
+        let xml = xml.replace("<Ctry>GB</Ctry>", "<TwnNm>LONDON</TwnNm><Ctry>GB</Ctry>");
+        let parties = IsoAddress::parties_from_xml(&xml).unwrap();
+        assert_eq!(parties.len(), 2);
+        let IsoAddress::IndividualIsoAddress {
+            name,
+            postal_address,
+            ..
+        } = &parties[0]
+        else {
+            panic!("expected an individual debtor");
+        };
+        assert_eq!(name, "Monsieur Jean DELHOURME");
+        assert_eq!(
+            postal_address.street_name.as_deref(),
+            Some("RUE DE L'EGLISE")
+        );
+        assert_eq!(postal_address.town_name, "MIOS");
+
+        assert!(matches!(
+            IsoAddress::parties_from_xml("<Document/>"),
+            Err(IsoXmlError::NoParty)
+        ));
+        assert!(matches!(
+            IsoAddress::parties_from_xml("<Document>"),
+            Err(IsoXmlError::Syntax(_))
+        ));
+    }
+}
@@ -0,0 +1,390 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::address::{ConvertedAddress, Country, IsoProfile};
+use super::address_conversion::{AddressConversionError, AddressConvertible};
+use super::address_quality::is_valid_town;
+use super::french_address::FrenchAddress;
+use super::french_lines::{ClassifiedLine, LineRole};
+use super::iso20022_address::IsoAddress;
+use super::validators::PostcodeValidators;
+
+/// The confidence under which a migrated address is flagged for review.
+const DEFAULT_REVIEW_THRESHOLD: u8 = 80;
+
+/// An ISO 20022 party whose postal address is only written with unstructured
+/// lines (`<AdrLine>`), as the usage guidelines allowed until November 2026.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct UnstructuredIsoAddress {
+    /// <Nm>, the name of the individual or of the business.
+    #[serde(alias = "Nm", alias = "business_name", alias = "businessName")]
+    pub name: String,
+    #[serde(alias = "PstlAdr", alias = "postalAddress")]
+    pub postal_address: UnstructuredPostalAddress,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct UnstructuredPostalAddress {
+    /// <AdrLine>
+    #[serde(alias = "AdrLine", alias = "addressLine")]
+    pub address_line: Vec<String>,
+    /// <PstCd>, when a hybrid address gives it apart from the lines.
+    #[serde(default, alias = "PstCd")]
+    pub postcode: Option<String>,
+    /// <TwnNm>, when a hybrid address gives it apart from the lines.
+    #[serde(default, alias = "TwnNm", alias = "townName")]
+    pub town_name: Option<String>,
+    /// <Ctry>
+    #[serde(default, alias = "Ctry")]
+    pub country: Option<String>,
+}
+
+/// A reason to doubt a migrated address, lowering its confidence.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum MigrationIssue {
+    /// The line wasn't recognized, and took the role left by its place.
+    GuessedLine { line: String, role: LineRole },
+    /// The postcode isn't valid for the country, see [`PostcodeValidators`].
+    InvalidPostcode { postcode: String, country: String },
+    /// The town has stray characters (digits, punctuation).
+    InvalidTown { town: String },
+    /// The street has a name, but no number.
+    NoStreetNumber { street: String },
+    /// There is neither street, lieu-dit nor postbox.
+    NoStreet,
+    /// Neither `<Ctry>` nor a country line is given, France is assumed.
+    DefaultCountry,
+}
+
+impl MigrationIssue {
+    /// The points the issue takes from the confidence.
+    pub fn penalty(&self) -> u8 {
+        match self {
+            MigrationIssue::GuessedLine { .. } => 25,
+            MigrationIssue::InvalidPostcode { .. } => 30,
+            MigrationIssue::InvalidTown { .. } => 15,
+            MigrationIssue::NoStreetNumber { .. } => 10,
+            MigrationIssue::NoStreet => 20,
+            MigrationIssue::DefaultCountry => 10,
+        }
+    }
+}
+
+impl fmt::Display for MigrationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationIssue::GuessedLine { line, role } => {
+                write!(f, "`{line}` taken as the {role} by its place")
+            }
+            MigrationIssue::InvalidPostcode { postcode, country } => {
+                write!(f, "invalid postcode `{postcode}` for {country}")
+            }
+            MigrationIssue::InvalidTown { town } => write!(f, "invalid town `{town}`"),
+            MigrationIssue::NoStreetNumber { street } => {
+                write!(f, "no number for the street `{street}`")
+            }
+            MigrationIssue::NoStreet => write!(f, "no street, lieu-dit or postbox"),
+            MigrationIssue::DefaultCountry => write!(f, "no country, France assumed"),
+        }
+    }
+}
+
+/// How an unstructured address was read, for a manual review.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MigrationReport {
+    /// From 0 to 100, each issue taking its penalty.
+    pub confidence: u8,
+    /// The confidence is under the review threshold of the converter.
+    pub needs_review: bool,
+    /// The lines of the address, with the role each one was given.
+    pub lines: Vec<ClassifiedLine>,
+    pub issues: Vec<MigrationIssue>,
+}
+
+/// A structured address and how its lines were read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    pub address: IsoAddress,
+    pub report: MigrationReport,
+}
+
+/// Migrates the unstructured ISO 20022 addresses to the structured elements:
+/// the lines are classified and parsed as a french address (see
+/// [`FrenchAddress::classify_lines`]), then written in ISO 20022.
+#[derive(Clone)]
+pub struct UnstructuredToStructured {
+    review_threshold: u8,
+    postcode_validators: PostcodeValidators,
+    iso_profile: IsoProfile,
+}
+
+impl Default for UnstructuredToStructured {
+    fn default() -> Self {
+        Self {
+            review_threshold: DEFAULT_REVIEW_THRESHOLD,
+            postcode_validators: PostcodeValidators::built_in().clone(),
+            iso_profile: IsoProfile::default(),
+        }
+    }
+}
+
+impl UnstructuredToStructured {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flags for review the addresses whose confidence is under `threshold`,
+    /// 80 by default.
+    pub fn with_review_threshold(mut self, threshold: u8) -> Self {
+        self.review_threshold = threshold;
+        self
+    }
+
+    /// Checks the postcodes with `validators` instead of the built-in ones.
+    pub fn with_postcode_validators(mut self, validators: PostcodeValidators) -> Self {
+        self.postcode_validators = validators;
+        self
+    }
+
+    /// Writes the structured addresses as set by `profile`.
+    pub fn with_iso_profile(mut self, profile: IsoProfile) -> Self {
+        self.iso_profile = profile;
+        self
+    }
+
+    pub fn convert(
+        &self,
+        address: &UnstructuredIsoAddress,
+    ) -> Result<Migration, AddressConversionError> {
+        let postal_address = &address.postal_address;
+        let country = postal_address
+            .country
+            .as_deref()
+            .map(|country| {
+                Country::from_str(country).map_err(|_| AddressConversionError::UnknownCountry {
+                    field: "postal_address.country".to_string(),
+                    value: country.to_string(),
+                })
+            })
+            .transpose()?;
+
+        let mut lines = vec![address.name.clone()];
+        lines.extend(postal_address.address_line.iter().cloned());
+        if let Some(town) = &postal_address.town_name {
+            let postcode = postal_address.postcode.as_deref().unwrap_or_default();
+            lines.push(match &country {
+                Some(country) if !country.postcode_first() => format!("{town} {postcode}"),
+                _ => format!("{postcode} {town}"),
+            });
+        }
+
+        let (mut french, classified_lines) = FrenchAddress::classify_lines(&lines.join("\n"))?;
+        let has_country_line = classified_lines
+            .iter()
+            .any(|line| line.role == LineRole::Country);
+        // <Ctry> prevails over the country line.
+        if let Some(ctry) = &postal_address.country {
+            match &mut french {
+                FrenchAddress::Individual(french) => french.country.clone_from(ctry),
+                FrenchAddress::Business(french) => french.country.clone_from(ctry),
+            }
+        }
+
+        let converted = ConvertedAddress::from_french(french)?;
+        let mut issues: Vec<MigrationIssue> = classified_lines
+            .iter()
+            .filter(|line| line.guessed)
+            .map(|line| MigrationIssue::GuessedLine {
+                line: line.line.clone(),
+                role: line.role,
+            })
+            .collect();
+        issues.extend(self.check(&converted));
+        if country.is_none() && !has_country_line {
+            issues.push(MigrationIssue::DefaultCountry);
+        }
+
+        let penalty: u32 = issues.iter().map(|issue| u32::from(issue.penalty())).sum();
+        let confidence = 100u32.saturating_sub(penalty) as u8;
+
+        Ok(Migration {
+            address: converted.to_iso20022_with(self.iso_profile)?,
+            report: MigrationReport {
+                confidence,
+                needs_review: confidence < self.review_threshold,
+                lines: classified_lines,
+                issues,
+            },
+        })
+    }
+
+    /// The issues of the parsed address itself.
+    fn check(&self, address: &ConvertedAddress) -> Vec<MigrationIssue> {
+        let mut issues = Vec::new();
+        let postal_details = &address.postal_details;
+
+        if !self
+            .postcode_validators
+            .is_valid(&address.country, &postal_details.postcode)
+        {
+            issues.push(MigrationIssue::InvalidPostcode {
+                postcode: postal_details.postcode.clone(),
+                country: address.country.to_string(),
+            });
+        }
+        if !is_valid_town(&postal_details.town) {
+            issues.push(MigrationIssue::InvalidTown {
+                town: postal_details.town.clone(),
+            });
+        }
+
+        let postbox = address
+            .delivery_point
+            .as_ref()
+            .is_some_and(|delivery_point| delivery_point.postbox.is_some());
+        match &address.street {
+            Some(street) if street.number.is_none() => {
+                issues.push(MigrationIssue::NoStreetNumber {
+                    street: street.name.clone(),
+                })
+            }
+            Some(_) => {}
+            None if postal_details.locality.is_some() || postbox => {}
+            None => issues.push(MigrationIssue::NoStreet),
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unstructured(name: &str, lines: &[&str], country: Option<&str>) -> UnstructuredIsoAddress {
+        UnstructuredIsoAddress {
+            name: name.to_string(),
+            postal_address: UnstructuredPostalAddress {
+                address_line: lines.iter().map(|line| line.to_string()).collect(),
+                postcode: None,
+                town_name: None,
+                country: country.map(str::to_string),
+            },
+        }
+    }
+
+    #[test]
+    fn it_should_structure_the_address_lines() {
+        let address = unstructured(
+            "Monsieur Jean DELHOURME",
+            &[
+                "Entrée A Bâtiment Jonquille",
+                "25 RUE DE L'EGLISE",
+                "33380 MIOS",
+            ],
+            Some("FR"),
+        );
+
+        let migration = UnstructuredToStructured::new().convert(&address).unwrap();
+        let IsoAddress::IndividualIsoAddress { postal_address, .. } = migration.address else {
+            panic!("expected an individual address");
+        };
+        assert_eq!(postal_address.building_number.as_deref(), Some("25"));
+        assert_eq!(
+            postal_address.street_name.as_deref(),
+            Some("RUE DE L'EGLISE")
+        );
+        assert_eq!(postal_address.postcode, "33380");
+        assert_eq!(postal_address.town_name, "MIOS");
+        assert_eq!(postal_address.country, "FR");
+
+        assert_eq!(migration.report.confidence, 100);
+        assert!(!migration.report.needs_review);
+        let roles: Vec<LineRole> = migration
+            .report
+            .lines
+            .iter()
+            .map(|line| line.role)
+            .collect();
+        assert_eq!(
+            roles,
+            [
+                LineRole::Recipient,
+                LineRole::ExternalDelivery,
+                LineRole::Street,
+                LineRole::Postal
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_report_the_doubtful_lines() {
+        let address = unstructured(
+            "Madame Lucie MARTIN",
+            &["LE VILLAGE", "00500 AUTERIVE"],
+            None,
+        );
+
+        let migration = UnstructuredToStructured::new().convert(&address).unwrap();
+        let report = migration.report;
+        assert_eq!(
+            report.issues,
+            [
+                MigrationIssue::GuessedLine {
+                    line: "LE VILLAGE".to_string(),
+                    role: LineRole::Street
+                },
+                MigrationIssue::InvalidPostcode {
+                    postcode: "00500".to_string(),
+                    country: "FRANCE".to_string()
+                },
+                MigrationIssue::NoStreetNumber {
+                    street: "LE VILLAGE".to_string()
+                },
+                MigrationIssue::DefaultCountry,
+            ]
+        );
+        assert_eq!(report.confidence, 25);
+        assert!(report.needs_review);
+        assert_eq!(
+            report.issues[0].to_string(),
+            "`LE VILLAGE` taken as the street by its place"
+        );
+
+        let lenient = UnstructuredToStructured::new().with_review_threshold(20);
+        assert!(!lenient.convert(&address).unwrap().report.needs_review);
+    }
+
+    #[test]
+    fn it_should_read_the_hybrid_addresses() {
+        let address: UnstructuredIsoAddress = serde_json::from_str(
+            r#"{
+                "Nm": "Société DUPONT",
+                "PstlAdr": {
+                    "AdrLine": ["Service comptabilité", "56 RUE EMILE ZOLA"],
+                    "PstCd": "34092",
+                    "TwnNm": "MONTPELLIER CEDEX 5",
+                    "Ctry": "FR"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let migration = UnstructuredToStructured::new().convert(&address).unwrap();
+        let IsoAddress::BusinessIsoAddress { postal_address, .. } = migration.address else {
+            panic!("expected a business address");
+        };
+        assert_eq!(postal_address.postcode, "34092");
+        assert_eq!(postal_address.town_name, "MONTPELLIER CEDEX 5");
+        assert_eq!(migration.report.lines.len(), 4);
+
+        let address = unstructured("Jean DUPONT", &["1 RUE X", "33380 MIOS"], Some("XX"));
+        assert!(matches!(
+            UnstructuredToStructured::new().convert(&address),
+            Err(AddressConversionError::UnknownCountry { .. })
+        ));
+    }
+}
@@ -0,0 +1,540 @@
+use serde::{Deserialize, Serialize};
+
+use super::address::*;
+use super::address_conversion::AddressConversionError;
+use super::street_number::NumberExtStyle;
+
+/// The first Hangul syllable (가) and the number of syllables.
+const HANGUL_START: u32 = 0xAC00;
+const HANGUL_COUNT: u32 = 11172;
+/// The number of medial vowels and final consonants of a syllable.
+const MEDIAL_COUNT: u32 = 21;
+const FINAL_COUNT: u32 = 28;
+
+/// Revised Romanization of the initial consonants, the medial vowels and the
+/// final consonants, in Unicode order.
+const INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const MEDIALS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+/// Indices of the initials changed by the following rules.
+const INITIAL_N: usize = 2;
+const INITIAL_R: usize = 5;
+const INITIAL_M: usize = 6;
+const INITIAL_SILENT: usize = 11;
+/// Index of the final ㅎ, silent before a vowel.
+const FINAL_H: usize = 27;
+
+/// The official english names of the provinces and metropolitan cities, in
+/// their full and short forms.
+const SIDO_NAMES: &[(&str, &str)] = &[
+    ("서울특별시", "Seoul"),
+    ("서울", "Seoul"),
+    ("부산광역시", "Busan"),
+    ("부산", "Busan"),
+    ("대구광역시", "Daegu"),
+    ("대구", "Daegu"),
+    ("인천광역시", "Incheon"),
+    ("인천", "Incheon"),
+    ("광주광역시", "Gwangju"),
+    ("광주", "Gwangju"),
+    ("대전광역시", "Daejeon"),
+    ("대전", "Daejeon"),
+    ("울산광역시", "Ulsan"),
+    ("울산", "Ulsan"),
+    ("세종특별자치시", "Sejong"),
+    ("세종", "Sejong"),
+    ("경기도", "Gyeonggi-do"),
+    ("경기", "Gyeonggi-do"),
+    ("강원특별자치도", "Gangwon-do"),
+    ("강원도", "Gangwon-do"),
+    ("강원", "Gangwon-do"),
+    ("충청북도", "Chungcheongbuk-do"),
+    ("충북", "Chungcheongbuk-do"),
+    ("충청남도", "Chungcheongnam-do"),
+    ("충남", "Chungcheongnam-do"),
+    ("전북특별자치도", "Jeonbuk-do"),
+    ("전라북도", "Jeollabuk-do"),
+    ("전북", "Jeonbuk-do"),
+    ("전라남도", "Jeollanam-do"),
+    ("전남", "Jeollanam-do"),
+    ("경상북도", "Gyeongsangbuk-do"),
+    ("경북", "Gyeongsangbuk-do"),
+    ("경상남도", "Gyeongsangnam-do"),
+    ("경남", "Gyeongsangnam-do"),
+    ("제주특별자치도", "Jeju-do"),
+    ("제주도", "Jeju-do"),
+    ("제주", "Jeju-do"),
+];
+
+/// Suffixes of the road names (boulevard, road, street) and of the districts
+/// (district, city, county), hyphenated in the romanized names.
+const ROAD_SUFFIXES: &[(&str, &str)] = &[("대로", "daero"), ("로", "ro"), ("길", "gil")];
+const DISTRICT_SUFFIXES: &[(&str, &str)] = &[("구", "gu"), ("시", "si"), ("군", "gun")];
+
+/// A South Korean road name address (도로명주소).
+///
+/// # Example
+///
+/// 03154 서울특별시 종로구 세종대로 209, 101동 202호
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KoreanAddress {
+    /// The recipient, or the contact of a business.
+    pub name: Option<String>,
+    /// The business name, for business addresses.
+    #[serde(default)]
+    pub business_name: Option<String>,
+    /// The province or metropolitan city (서울특별시, 경기도).
+    pub sido: String,
+    /// The district, city or county (종로구, 성남시 분당구).
+    pub sigungu: String,
+    /// The road name (세종대로, 테헤란로).
+    pub road_name: String,
+    /// The building number on the road (209, 14-3).
+    pub building_number: String,
+    /// The building, floor or unit (101동 202호).
+    pub detailed_address: Option<String>,
+    /// The 5 digits postal code.
+    pub postal_code: String,
+}
+
+/// The script of the rendered lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KoreanScript {
+    /// Domestic mail, from the largest area to the building.
+    Hangul,
+    /// International mail, from the building to the largest area, followed
+    /// by the country line.
+    Romanized,
+}
+
+impl KoreanAddress {
+    /// The address lines, in the order of the given script.
+    pub fn lines(&self, script: KoreanScript) -> Vec<String> {
+        let recipient = [self.business_name.clone(), self.name.clone()];
+
+        let lines = match script {
+            KoreanScript::Hangul => [
+                Some(format!(
+                    "{} {} {} {}",
+                    self.sido, self.sigungu, self.road_name, self.building_number
+                )),
+                self.detailed_address.clone(),
+                Some(self.postal_code.clone()),
+            ]
+            .to_vec(),
+            KoreanScript::Romanized => {
+                let sido = SIDO_NAMES
+                    .iter()
+                    .find(|(hangul, _)| *hangul == self.sido)
+                    .map_or_else(
+                        || romanize_name(&self.sido, &[]),
+                        |(_, english)| english.to_string(),
+                    );
+
+                [
+                    self.detailed_address.as_deref().map(romanize),
+                    Some(format!(
+                        "{} {}",
+                        self.building_number,
+                        romanize_name(&self.road_name, ROAD_SUFFIXES)
+                    )),
+                    Some(format!(
+                        "{}, {sido} {}",
+                        romanize_name(&self.sigungu, DISTRICT_SUFFIXES),
+                        self.postal_code
+                    )),
+                    Some(Country::SouthKorea.english_name().to_string()),
+                ]
+                .to_vec()
+            }
+        };
+
+        recipient
+            .into_iter()
+            .chain(lines)
+            .flatten()
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    }
+}
+
+impl ConvertedAddress {
+    /// Converts a korean address. The district is the town and the province
+    /// the country subdivision, as in ISO 20022.
+    pub fn from_korean(address: KoreanAddress) -> Result<Self, AddressConversionError> {
+        let required = |field: &str, value: String| {
+            if value.trim().is_empty() {
+                Err(AddressConversionError::missing(field))
+            } else {
+                Ok(value.trim().to_string())
+            }
+        };
+
+        let postal_code = address.postal_code.trim();
+        if postal_code.len() != 5 || !postal_code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(AddressConversionError::InvalidPostal {
+                field: "postal_code".to_string(),
+                value: address.postal_code,
+                reason: "Korean postal code should have 5 digits".to_string(),
+            });
+        }
+
+        let name = address.name.filter(|name| !name.trim().is_empty());
+        let (kind, recipient) = match address.business_name {
+            Some(company_name) if !company_name.trim().is_empty() => (
+                AddressKind::Business,
+                Recipient::business(company_name, name.as_deref(), Vec::new()),
+            ),
+            _ => (
+                AddressKind::Individual,
+                Recipient::Individual {
+                    name: name.ok_or_else(|| AddressConversionError::missing("name"))?,
+                },
+            ),
+        };
+        let delivery_point = address
+            .detailed_address
+            .filter(|detail| !detail.trim().is_empty())
+            .map(|detail| DeliveryPoint {
+                care_of: None,
+                external: None,
+                internal: Some(detail),
+                postbox: None,
+            });
+
+        Ok(ConvertedAddress::new(
+            kind,
+            recipient,
+            delivery_point,
+            Some(Street {
+                number: Some(required("building_number", address.building_number)?),
+                kind: None,
+                repetition_index: None,
+                name: required("road_name", address.road_name)?,
+            }),
+            PostalDetails {
+                postcode: postal_code.to_string(),
+                town: required("sigungu", address.sigungu)?,
+                town_location: None,
+                locality: None,
+                country_subdivision: Some(required("sido", address.sido)?),
+            },
+            Country::SouthKorea,
+        ))
+    }
+
+    /// Converts the address to a korean road name address. Only addresses in
+    /// South Korea with a road, a building number and a province convert.
+    pub fn to_korean(&self) -> Result<KoreanAddress, AddressConversionError> {
+        if self.country != Country::SouthKorea {
+            return Err(AddressConversionError::InvalidValue {
+                field: "country".to_string(),
+                value: self.country.iso_code().to_string(),
+                reason: format!(
+                    "{} address can't be written as a korean address",
+                    self.country.english_name()
+                ),
+            });
+        }
+
+        let (name, business_name) = match &self.recipient {
+            Recipient::Individual { name } => (Some(name.clone()), None),
+            Recipient::Business { company_name, .. } => {
+                (self.recipient.denomination(), Some(company_name.clone()))
+            }
+        };
+        let street = self
+            .street
+            .as_ref()
+            .ok_or_else(|| AddressConversionError::missing("road_name"))?;
+        let building_number = street
+            .full_number(NumberExtStyle::Attached)
+            .ok_or_else(|| AddressConversionError::missing("building_number"))?;
+        let sido = self
+            .postal_details
+            .country_subdivision
+            .clone()
+            .ok_or_else(|| AddressConversionError::missing("sido"))?;
+        let detailed_address = self.delivery_point.as_ref().and_then(|delivery_point| {
+            let details: Vec<&str> = [&delivery_point.external, &delivery_point.internal]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+
+            (!details.is_empty()).then(|| details.join(" "))
+        });
+
+        Ok(KoreanAddress {
+            name,
+            business_name,
+            sido,
+            sigungu: self.postal_details.town.clone(),
+            road_name: street.name.clone(),
+            building_number,
+            detailed_address,
+            postal_code: self.postal_details.postcode.clone(),
+        })
+    }
+}
+
+/// Romanizes the words of a road or district name, hyphenating the known
+/// suffixes ("세종대로" -> "Sejong-daero", "성남시 분당구" -> "Seongnam-si
+/// Bundang-gu").
+fn romanize_name(name: &str, suffixes: &[(&str, &str)]) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            suffixes
+                .iter()
+                .find_map(|(suffix, romanized)| {
+                    word.strip_suffix(suffix)
+                        .filter(|stem| !stem.is_empty())
+                        .map(|stem| format!("{}-{romanized}", capitalize(&romanize(stem))))
+                })
+                .unwrap_or_else(|| capitalize(&romanize(word)))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// Transliterates Hangul with the Revised Romanization of Korean. Only the
+/// consonant assimilations around ㄹ and before ㄴ/ㅁ, and the liaison before a
+/// vowel, are applied: other sound changes are written as spelled. Other
+/// characters are kept, and a syllable following a digit is hyphenated
+/// ("101동" -> "101-dong").
+pub fn romanize(text: &str) -> String {
+    let mut romanized = String::new();
+    // The final consonant of the previous syllable, written once the next
+    // initial is known.
+    let mut pending_final: Option<usize> = None;
+    let mut after_digit = false;
+
+    for c in text.chars() {
+        let offset = (c as u32).wrapping_sub(HANGUL_START);
+        if offset >= HANGUL_COUNT {
+            if let Some(last) = pending_final.take() {
+                romanized.push_str(FINALS[last]);
+            }
+            romanized.push(c);
+            after_digit = c.is_ascii_digit();
+            continue;
+        }
+
+        let initial = (offset / (MEDIAL_COUNT * FINAL_COUNT)) as usize;
+        let medial = ((offset / FINAL_COUNT) % MEDIAL_COUNT) as usize;
+        let last = (offset % FINAL_COUNT) as usize;
+
+        if after_digit {
+            romanized.push('-');
+        }
+        let (previous_final, initial) = match pending_final.take() {
+            Some(previous) => join(previous, initial),
+            None => ("", INITIALS[initial]),
+        };
+        romanized.push_str(previous_final);
+        romanized.push_str(initial);
+        romanized.push_str(MEDIALS[medial]);
+
+        pending_final = Some(last);
+        after_digit = false;
+    }
+
+    if let Some(last) = pending_final {
+        romanized.push_str(FINALS[last]);
+    }
+
+    romanized
+}
+
+/// The romanization of a final consonant followed by an initial one.
+fn join(previous: usize, initial: usize) -> (&'static str, &'static str) {
+    let final_sound = FINALS[previous];
+
+    match initial {
+        // The final consonant moves to the next syllable.
+        INITIAL_SILENT => match previous {
+            FINAL_H => ("", ""),
+            1 => ("", "g"),
+            2 => ("", "kk"),
+            4 => ("", "n"),
+            7 => ("", "d"),
+            8 => ("", "r"),
+            16 => ("", "m"),
+            17 => ("", "b"),
+            19 => ("", "s"),
+            20 => ("", "ss"),
+            22 => ("", "j"),
+            23 => ("", "ch"),
+            24 => ("", "k"),
+            25 => ("", "t"),
+            26 => ("", "p"),
+            _ => (final_sound, ""),
+        },
+        INITIAL_R => match final_sound {
+            "" => ("", "r"),
+            "n" | "l" => ("l", "l"),
+            "k" => ("ng", "n"),
+            "t" => ("n", "n"),
+            "p" => ("m", "n"),
+            _ => (final_sound, "n"),
+        },
+        INITIAL_N | INITIAL_M => match final_sound {
+            "k" => ("ng", INITIALS[initial]),
+            "t" => ("n", INITIALS[initial]),
+            "p" => ("m", INITIALS[initial]),
+            "l" if initial == INITIAL_N => ("l", "l"),
+            _ => (final_sound, INITIALS[initial]),
+        },
+        _ => (final_sound, INITIALS[initial]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AddressConvertible, IsoAddress};
+
+    fn address() -> KoreanAddress {
+        KoreanAddress {
+            name: Some("홍길동".to_string()),
+            business_name: None,
+            sido: "서울특별시".to_string(),
+            sigungu: "종로구".to_string(),
+            road_name: "세종대로".to_string(),
+            building_number: "209".to_string(),
+            detailed_address: Some("101동 202호".to_string()),
+            postal_code: "03154".to_string(),
+        }
+    }
+
+    #[test]
+    fn it_should_romanize_hangul() {
+        assert_eq!(romanize("세종"), "sejong");
+        assert_eq!(romanize("종로"), "jongno");
+        assert_eq!(romanize("신라"), "silla");
+        assert_eq!(romanize("강남"), "gangnam");
+        assert_eq!(romanize("국민"), "gungmin");
+        assert_eq!(romanize("목요일"), "mogyoil");
+        assert_eq!(romanize("101동 202호"), "101-dong 202-ho");
+        assert_eq!(
+            romanize_name("성남시 분당구", DISTRICT_SUFFIXES),
+            "Seongnam-si Bundang-gu"
+        );
+        assert_eq!(romanize_name("테헤란로", ROAD_SUFFIXES), "Teheran-ro");
+    }
+
+    #[test]
+    fn it_should_render_lines() {
+        let address = address();
+
+        assert_eq!(
+            address.lines(KoreanScript::Hangul),
+            vec![
+                "홍길동",
+                "서울특별시 종로구 세종대로 209",
+                "101동 202호",
+                "03154"
+            ]
+        );
+        assert_eq!(
+            address.lines(KoreanScript::Romanized),
+            vec![
+                "홍길동",
+                "101-dong 202-ho",
+                "209 Sejong-daero",
+                "Jongno-gu, Seoul 03154",
+                "REPUBLIC OF KOREA"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_convert_to_iso20022() {
+        let converted = ConvertedAddress::from_korean(address()).unwrap();
+        let IsoAddress::IndividualIsoAddress {
+            name,
+            postal_address,
+            extras,
+        } = converted.to_iso20022().unwrap()
+        else {
+            panic!("expected an individual ISO address");
+        };
+
+        assert_eq!(name, "홍길동");
+        assert_eq!(postal_address.street_name.as_deref(), Some("세종대로"));
+        assert_eq!(postal_address.building_number.as_deref(), Some("209"));
+        assert_eq!(postal_address.room.as_deref(), Some("101동 202호"));
+        assert_eq!(postal_address.postcode, "03154");
+        assert_eq!(postal_address.town_name, "종로구");
+        assert_eq!(
+            postal_address.country_sub_division.as_deref(),
+            Some("서울특별시")
+        );
+        assert_eq!(postal_address.country, "KR");
+
+        let iso = IsoAddress::IndividualIsoAddress {
+            name,
+            postal_address,
+            extras,
+        };
+        let back = ConvertedAddress::from_iso20022(iso).unwrap();
+        assert_eq!(back.to_korean().unwrap(), address());
+    }
+
+    #[test]
+    fn it_should_convert_businesses() {
+        let business = KoreanAddress {
+            name: None,
+            business_name: Some("주식회사 한빛".to_string()),
+            detailed_address: None,
+            ..address()
+        };
+        let converted = ConvertedAddress::from_korean(business.clone()).unwrap();
+
+        assert_eq!(converted.kind, AddressKind::Business);
+        assert_eq!(converted.to_korean().unwrap(), business);
+    }
+
+    #[test]
+    fn it_should_reject_invalid_addresses() {
+        let invalid_postal_code = KoreanAddress {
+            postal_code: "110-050".to_string(),
+            ..address()
+        };
+        assert!(matches!(
+            ConvertedAddress::from_korean(invalid_postal_code),
+            Err(AddressConversionError::InvalidPostal { field, .. }) if field == "postal_code"
+        ));
+
+        let missing_sido = KoreanAddress {
+            sido: " ".to_string(),
+            ..address()
+        };
+        assert!(matches!(
+            ConvertedAddress::from_korean(missing_sido),
+            Err(AddressConversionError::MissingField { field }) if field == "sido"
+        ));
+
+        let french = ConvertedAddress {
+            country: Country::France,
+            ..ConvertedAddress::from_korean(address()).unwrap()
+        };
+        assert!(french.to_korean().is_err());
+    }
+}
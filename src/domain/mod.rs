@@ -1,11 +1,68 @@
 mod address;
 mod address_conversion;
+mod address_kind_detector;
+mod address_quality;
+mod address_search;
+mod address_status;
+mod borrowed_address;
+mod brazilian_address;
+mod canonical_address;
+mod conversion_trace;
+mod conversion_warnings;
+mod country_profile;
+#[cfg(feature = "fast-parse")]
+mod fast_parse;
+mod fingerprint;
 mod french_address;
+mod french_department;
+mod french_lines;
+mod french_parser_rules;
 mod iso20022_address;
+mod iso20022_xml;
+mod iso_migration;
+mod korean_address;
+mod normalizer;
+mod null_fields;
+mod org_identifier;
+mod person_name;
+mod portuguese_address;
 pub mod repositories;
+mod street_number;
+mod street_referential;
+mod street_type;
+mod transliteration;
+mod validation_policy;
+mod validators;
 
 pub use self::address::*;
 pub use self::address_conversion::*;
+pub use self::address_kind_detector::*;
+pub use self::address_search::*;
+pub use self::address_status::*;
+pub use self::borrowed_address::*;
+pub use self::brazilian_address::*;
+pub use self::canonical_address::*;
+pub use self::conversion_trace::*;
+pub use self::conversion_warnings::*;
+pub use self::country_profile::*;
+pub use self::fingerprint::*;
 pub use self::french_address::*;
+pub use self::french_department::*;
+pub use self::french_lines::*;
+pub use self::french_parser_rules::*;
 pub use self::iso20022_address::*;
+pub use self::iso20022_xml::*;
+pub use self::iso_migration::*;
+pub use self::korean_address::*;
+pub use self::normalizer::*;
+pub use self::null_fields::*;
+pub use self::org_identifier::*;
+pub use self::person_name::*;
+pub use self::portuguese_address::*;
+pub use self::street_number::*;
+pub use self::street_referential::*;
+pub use self::street_type::*;
+pub use self::transliteration::*;
+pub use self::validation_policy::*;
+pub use self::validators::*;
 pub use uuid::Uuid;
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use super::address::{ConvertedAddress, DeliveryPoint};
+use super::fingerprint::normalize;
+use super::street_number::NumberExtStyle;
+use super::street_type::StreetType;
+
+/// The maximum length of an address line (NF Z10-011). Longer lines are
+/// abbreviated.
+pub const MAX_LINE_LENGTH: usize = 38;
+
+/// The standard abbreviations of the route types and delivery points
+/// (NF Z10-011, La Poste). Keys are uppercase and may span several words.
+const STANDARD_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("ALLEE", "ALL"),
+    ("ALLÉE", "ALL"),
+    ("APPARTEMENT", "APP"),
+    ("AVENUE", "AV"),
+    ("BATIMENT", "BAT"),
+    ("BÂTIMENT", "BAT"),
+    ("BOULEVARD", "BD"),
+    ("CHAUSSEE", "CHS"),
+    ("CHAUSSÉE", "CHS"),
+    ("CHEMIN", "CHE"),
+    ("COURS", "CRS"),
+    ("ESCALIER", "ESC"),
+    ("ETAGE", "ETG"),
+    ("ÉTAGE", "ETG"),
+    ("FAUBOURG", "FG"),
+    ("GRAND", "GD"),
+    ("GRANDE", "GDE"),
+    ("IMMEUBLE", "IMM"),
+    ("IMPASSE", "IMP"),
+    ("LOTISSEMENT", "LOT"),
+    ("PLACE", "PL"),
+    ("QUARTIER", "QUA"),
+    ("RESIDENCE", "RES"),
+    ("RÉSIDENCE", "RES"),
+    ("ROUTE", "RTE"),
+    ("SAINT", "ST"),
+    ("SAINTE", "STE"),
+    ("SQUARE", "SQ"),
+    ("ZONE ARTISANALE", "ZA"),
+    ("ZONE D'AMENAGEMENT CONCERTE", "ZAC"),
+    ("ZONE D'AMÉNAGEMENT CONCERTÉ", "ZAC"),
+    ("ZONE INDUSTRIELLE", "ZI"),
+];
+
+/// Shortens the address lines exceeding [`MAX_LINE_LENGTH`] with a dictionary
+/// of abbreviations. Lines within the limit are left untouched, as required by
+/// NF Z10-011.
+///
+/// Only the street, delivery point and lieu-dit lines are abbreviated: the
+/// recipient, the postcode and the town are never changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Normalizer {
+    /// Uppercase words (or group of words) and their abbreviation.
+    dictionary: HashMap<String, String>,
+    /// The number of words of the longest key, bounding the lookups.
+    max_key_words: usize,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Normalizer {
+    /// A normalizer with the standard abbreviations.
+    pub fn new() -> Self {
+        Self {
+            dictionary: HashMap::new(),
+            max_key_words: 0,
+        }
+        .with_dictionary(
+            STANDARD_ABBREVIATIONS
+                .iter()
+                .map(|(word, abbreviation)| (word.to_string(), abbreviation.to_string()))
+                .collect(),
+        )
+    }
+
+    /// Adds custom entries (e.g., `"ZONE DES PINS" => "ZDP"`) to the
+    /// dictionary. Keys are case insensitive and override the standard
+    /// abbreviations.
+    pub fn with_dictionary(mut self, dictionary: HashMap<String, String>) -> Self {
+        for (word, abbreviation) in dictionary {
+            let word = word.split_whitespace().collect::<Vec<_>>().join(" ");
+            if word.is_empty() {
+                continue;
+            }
+
+            self.max_key_words = self.max_key_words.max(word.split(' ').count());
+            self.dictionary.insert(word.to_uppercase(), abbreviation);
+        }
+
+        self
+    }
+
+    /// Abbreviates the lines of the address exceeding [`MAX_LINE_LENGTH`].
+    pub fn normalize(&self, address: &mut ConvertedAddress) {
+        if let Some(street) = &mut address.street {
+            // The number is written on the same line as the name.
+            let number_len = street
+                .full_number(NumberExtStyle::Spaced)
+                .map_or(0, |number| number.chars().count() + 1);
+            street.name =
+                self.abbreviate_street(&street.name, MAX_LINE_LENGTH.saturating_sub(number_len));
+        }
+
+        if let Some(DeliveryPoint {
+            external, internal, ..
+        }) = &mut address.delivery_point
+        {
+            for line in [external, internal].into_iter().flatten() {
+                *line = self.abbreviate(line, MAX_LINE_LENGTH);
+            }
+        }
+
+        if let Some(locality) = &mut address.postal_details.locality {
+            *locality = self.abbreviate(locality, MAX_LINE_LENGTH);
+        }
+    }
+
+    /// Cleanses the address as La Poste writes it (NF Z10-011): the street,
+    /// delivery point, town and lieu-dit lines are uppercased, without accents
+    /// nor punctuation (e.g., `Rue de l'Église` -> `RUE DE L EGLISE`), then
+    /// abbreviated as [`Normalizer::normalize`] does. The recipient, the
+    /// postbox and the postcode are kept as written.
+    pub fn cleanse(&self, address: &mut ConvertedAddress) {
+        if let Some(street) = &mut address.street {
+            street.name = normalize(&street.name);
+        }
+        if let Some(DeliveryPoint {
+            external, internal, ..
+        }) = &mut address.delivery_point
+        {
+            for line in [external, internal].into_iter().flatten() {
+                *line = normalize(line);
+            }
+        }
+        let postal_details = &mut address.postal_details;
+        postal_details.town = normalize(&postal_details.town);
+        for line in [
+            &mut postal_details.town_location,
+            &mut postal_details.locality,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *line = normalize(line);
+        }
+
+        self.normalize(address);
+    }
+
+    /// Abbreviates a street name longer than `max_len` characters: its type
+    /// with the official abbreviation of the [`StreetType`], even when the
+    /// dictionary doesn't know it, and the rest of the name with the
+    /// dictionary.
+    fn abbreviate_street(&self, name: &str, max_len: usize) -> String {
+        let split = StreetType::split(name).filter(|_| name.chars().count() > max_len);
+        let Some((kind, rest)) = split else {
+            return self.abbreviate(name, max_len);
+        };
+        let written = name[..name.len() - rest.len()].trim();
+        // The custom entries override the official abbreviations.
+        if self.dictionary.contains_key(&written.to_uppercase()) {
+            return self.abbreviate(name, max_len);
+        }
+
+        // "R. DE LA PAIX" is already shorter than "RUE".
+        let prefix = match kind.abbreviation() {
+            abbreviation if abbreviation.len() < written.chars().count() => abbreviation,
+            _ => written,
+        };
+        let rest_len = max_len.saturating_sub(prefix.chars().count() + 1);
+
+        format!("{prefix} {}", self.abbreviate(rest, rest_len))
+    }
+
+    /// Abbreviates every known word of `line` when it is longer than
+    /// `max_len` characters. Longest keys are matched first, so that
+    /// "ZONE INDUSTRIELLE" wins over a custom "ZONE" entry.
+    pub fn abbreviate(&self, line: &str, max_len: usize) -> String {
+        if line.chars().count() <= max_len {
+            return line.to_string();
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let mut abbreviated = Vec::with_capacity(words.len());
+        let mut i = 0;
+        while i < words.len() {
+            let longest = self.max_key_words.min(words.len() - i);
+            let matched = (1..=longest).rev().find_map(|len| {
+                self.dictionary
+                    .get(&words[i..i + len].join(" ").to_uppercase())
+                    .map(|abbreviation| (len, abbreviation.as_str()))
+            });
+
+            match matched {
+                Some((len, abbreviation)) => {
+                    abbreviated.push(abbreviation);
+                    i += len;
+                }
+                None => {
+                    abbreviated.push(words[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        abbreviated.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Normalizer, MAX_LINE_LENGTH};
+    use crate::domain::{AddressConvertible, ConvertedAddress, FrenchAddress, Recipient};
+
+    #[test]
+    fn it_should_keep_short_lines() {
+        let normalizer = Normalizer::new();
+
+        assert_eq!(
+            normalizer.abbreviate("25 AVENUE DES CHAMPS", MAX_LINE_LENGTH),
+            "25 AVENUE DES CHAMPS"
+        );
+    }
+
+    #[test]
+    fn it_should_abbreviate_long_lines() {
+        let normalizer = Normalizer::new();
+
+        assert_eq!(
+            normalizer.abbreviate(
+                "BOULEVARD DU GENERAL CHARLES DE GAULLE PROLONGE",
+                MAX_LINE_LENGTH
+            ),
+            "BD DU GENERAL CHARLES DE GAULLE PROLONGE"
+        );
+        assert_eq!(
+            normalizer.abbreviate(
+                "Zone Industrielle de la Grande Vallée Nord",
+                MAX_LINE_LENGTH
+            ),
+            "ZI de la GDE Vallée Nord"
+        );
+    }
+
+    #[test]
+    fn it_should_abbreviate_the_street_types() {
+        let normalizer = Normalizer::new();
+
+        assert_eq!(
+            normalizer.abbreviate_street("PROMENADE DU GENERAL CHARLES DE GAULLE", 30),
+            "PROM DU GENERAL CHARLES DE GAULLE"
+        );
+        assert_eq!(
+            normalizer.abbreviate_street("R. DU GENERAL CHARLES DE GAULLE PROLONGEE", 30),
+            "R. DU GENERAL CHARLES DE GAULLE PROLONGEE"
+        );
+        assert_eq!(
+            normalizer.abbreviate_street("PROMENADE DU LAC", 30),
+            "PROMENADE DU LAC"
+        );
+    }
+
+    #[test]
+    fn it_should_use_custom_entries() {
+        let normalizer = Normalizer::new().with_dictionary(HashMap::from([
+            ("zone  des pins".to_string(), "ZDP".to_string()),
+            ("BOULEVARD".to_string(), "BOUL".to_string()),
+            ("TOUR HORIZON".to_string(), "TH".to_string()),
+        ]));
+
+        assert_eq!(
+            normalizer.abbreviate("ENTREE B TOUR HORIZON ZONE DES PINS NORD", MAX_LINE_LENGTH),
+            "ENTREE B TH ZDP NORD"
+        );
+        assert_eq!(
+            normalizer.abbreviate("BOULEVARD DU GENERAL CHARLES DE GAULLE", 20),
+            "BOUL DU GENERAL CHARLES DE GAULLE"
+        );
+    }
+
+    #[test]
+    fn it_should_cleanse_the_lines() {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": "Monsieur Jean DELHOURME",
+            "external_delivery": "Entrée A, Bâtiment Jonquille",
+            "street": "25 Boulevard du Général Charles de Gaulle prolongé",
+            "postal": "42000 Saint-Étienne",
+            "country": "FRANCE",
+        }))
+        .unwrap();
+        let mut address = ConvertedAddress::from_french(french).unwrap();
+
+        Normalizer::new().cleanse(&mut address);
+        let delivery_point = address.delivery_point.unwrap();
+        assert_eq!(
+            delivery_point.external.as_deref(),
+            Some("ENTREE A BATIMENT JONQUILLE")
+        );
+        assert_eq!(
+            address.street.unwrap().name,
+            "BD DU GENERAL CHARLES DE GAULLE PROLONGE"
+        );
+        assert_eq!(address.postal_details.town, "SAINT ETIENNE");
+        assert_eq!(
+            address.recipient,
+            Recipient::Individual {
+                name: "Monsieur Jean DELHOURME".to_string()
+            }
+        );
+    }
+}
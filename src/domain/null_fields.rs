@@ -0,0 +1,60 @@
+use serde_json::Value;
+use strum::EnumString;
+
+/// Whether the absent optional fields of the addresses are written as `null`
+/// in the JSON outputs, or left out for the validators rejecting them.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum NullFields {
+    /// `"floor": null`
+    #[default]
+    Include,
+    /// No `floor` key.
+    Omit,
+}
+
+impl NullFields {
+    /// Applies the policy to a serialized address, at any depth.
+    pub fn apply(self, value: Value) -> Value {
+        match (self, value) {
+            (NullFields::Include, value) => value,
+            (NullFields::Omit, Value::Object(fields)) => Value::Object(
+                fields
+                    .into_iter()
+                    .filter(|(_, value)| !value.is_null())
+                    .map(|(key, value)| (key, self.apply(value)))
+                    .collect(),
+            ),
+            (NullFields::Omit, Value::Array(values)) => {
+                Value::Array(values.into_iter().map(|v| self.apply(v)).collect())
+            }
+            (NullFields::Omit, value) => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn omit_nested_nulls() {
+        let value = json!({
+            "name": "Monsieur Jean DELHOURME",
+            "postal_address": {"floor": null, "postcode": "33380"},
+            "identifiers": [{"id": "732829320", "scheme": null}],
+        });
+
+        assert_eq!(NullFields::Include.apply(value.clone()), value);
+        assert_eq!(
+            NullFields::Omit.apply(value),
+            json!({
+                "name": "Monsieur Jean DELHOURME",
+                "postal_address": {"postcode": "33380"},
+                "identifiers": [{"id": "732829320"}],
+            })
+        );
+    }
+}
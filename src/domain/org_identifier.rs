@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use super::address_conversion::AddressConversionError;
+
+/// SIRET numbers of La Poste establishments don't follow the Luhn algorithm,
+/// the sum of their digits is a multiple of 5 instead.
+const LA_POSTE_SIREN: &str = "356000000";
+
+/// An official identifier of a French organisation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrgIdentifier {
+    /// The 9 digits company identifier.
+    Siren(String),
+    /// The 14 digits establishment identifier (SIREN + NIC).
+    Siret(String),
+}
+
+impl OrgIdentifier {
+    /// Parses a SIREN or SIRET depending on its number of digits. Spaces are
+    /// ignored so that formatted values like "732 829 320" are accepted.
+    pub fn parse(value: &str) -> Result<Self, AddressConversionError> {
+        let digits = Self::digits(value)?;
+
+        match digits.len() {
+            9 => Self::parse_scheme("SIREN", &digits),
+            14 => Self::parse_scheme("SIRET", &digits),
+            _ => Err(invalid(
+                value,
+                "Neither a SIREN (9 digits) nor a SIRET (14 digits)".to_string(),
+            )),
+        }
+    }
+
+    /// Parses an identifier of the given scheme ("SIREN" or "SIRET", case
+    /// insensitive) and validates its checksum.
+    pub fn parse_scheme(scheme: &str, value: &str) -> Result<Self, AddressConversionError> {
+        let digits = Self::digits(value)?;
+
+        let (identifier, expected_len) = match scheme.to_uppercase().as_str() {
+            "SIREN" => (OrgIdentifier::Siren(digits.clone()), 9),
+            "SIRET" => (OrgIdentifier::Siret(digits.clone()), 14),
+            _ => {
+                return Err(invalid(
+                    value,
+                    format!("Unsupported organisation identifier scheme `{scheme}`"),
+                ))
+            }
+        };
+
+        if digits.len() != expected_len {
+            return Err(invalid(
+                value,
+                format!(
+                    "A {} should contain {expected_len} digits",
+                    identifier.scheme()
+                ),
+            ));
+        }
+
+        let checksum_ok = match &identifier {
+            OrgIdentifier::Siret(siret) if siret.starts_with(LA_POSTE_SIREN) => {
+                digit_sum(siret).is_multiple_of(5)
+            }
+            _ => luhn_valid(&digits),
+        };
+
+        if !checksum_ok {
+            return Err(invalid(
+                value,
+                format!("Invalid {} checksum", identifier.scheme()),
+            ));
+        }
+
+        Ok(identifier)
+    }
+
+    /// The name of the identification scheme.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            OrgIdentifier::Siren(_) => "SIREN",
+            OrgIdentifier::Siret(_) => "SIRET",
+        }
+    }
+
+    /// The identifier digits.
+    pub fn value(&self) -> &str {
+        match self {
+            OrgIdentifier::Siren(value) | OrgIdentifier::Siret(value) => value,
+        }
+    }
+
+    /// The company SIREN, also available from a SIRET.
+    pub fn siren(&self) -> &str {
+        &self.value()[..9]
+    }
+
+    fn digits(value: &str) -> Result<String, AddressConversionError> {
+        let digits: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid(
+                value,
+                "Organisation identifier should only contain digits".to_string(),
+            ));
+        }
+
+        Ok(digits)
+    }
+}
+
+/// An invalid identifier error. The field is refined by the callers, which
+/// know where the identifier comes from.
+fn invalid(value: &str, reason: String) -> AddressConversionError {
+    AddressConversionError::InvalidIdentifier {
+        field: "identifiers".to_string(),
+        value: value.to_string(),
+        reason,
+    }
+}
+
+fn digit_sum(digits: &str) -> u32 {
+    digits.chars().filter_map(|c| c.to_digit(10)).sum()
+}
+
+fn luhn_valid(digits: &str) -> bool {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| match (i % 2, digit * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => digit,
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrgIdentifier;
+
+    #[test]
+    fn it_should_parse_siren() {
+        let siren = OrgIdentifier::parse("732 829 320").unwrap();
+        assert_eq!(siren, OrgIdentifier::Siren("732829320".to_string()));
+        assert_eq!(siren.scheme(), "SIREN");
+        assert_eq!(siren.siren(), "732829320");
+    }
+
+    #[test]
+    fn it_should_parse_siret() {
+        let siret = OrgIdentifier::parse("732 829 320 00074").unwrap();
+        assert_eq!(siret, OrgIdentifier::Siret("73282932000074".to_string()));
+        assert_eq!(siret.siren(), "732829320");
+        assert!(OrgIdentifier::parse_scheme("siret", "73282932000074").is_ok());
+    }
+
+    #[test]
+    fn it_should_accept_la_poste_siret() {
+        assert!(OrgIdentifier::parse("35600000049837").is_ok());
+    }
+
+    #[test]
+    fn it_should_reject_invalid_identifiers() {
+        // Wrong checksum
+        assert!(OrgIdentifier::parse("732829321").is_err());
+        assert!(OrgIdentifier::parse("73282932000075").is_err());
+        // Wrong length for the scheme
+        assert!(OrgIdentifier::parse_scheme("SIRET", "732829320").is_err());
+        assert!(OrgIdentifier::parse("1234").is_err());
+        // Not digits
+        assert!(OrgIdentifier::parse("ABC829320").is_err());
+        // Unknown scheme
+        assert!(OrgIdentifier::parse_scheme("LEI", "732829320").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::address::Recipient;
+
+/// The civility written before the name of a person in french addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Civility {
+    Monsieur,
+    Madame,
+    Mademoiselle,
+    Maitre,
+    Docteur,
+}
+
+impl Civility {
+    /// Reads a civility, in full or abbreviated (e.g., `M.`, `Mme`, `Dr`).
+    pub fn parse(word: &str) -> Option<Self> {
+        let civility = match word.to_lowercase().trim_end_matches('.') {
+            "monsieur" | "m" => Civility::Monsieur,
+            "madame" | "mme" => Civility::Madame,
+            "mademoiselle" | "mlle" => Civility::Mademoiselle,
+            "maître" | "maitre" | "me" => Civility::Maitre,
+            "docteur" | "dr" => Civility::Docteur,
+            _ => return None,
+        };
+
+        Some(civility)
+    }
+
+    /// The ISO 20022 `NamePrefix2Code` of the civility, written in the
+    /// `<CtctDtls><NmPrfx>` element. There is no code for `Maître`.
+    pub fn name_prefix(self) -> Option<&'static str> {
+        match self {
+            Civility::Monsieur => Some("MIST"),
+            Civility::Madame => Some("MADM"),
+            Civility::Mademoiselle => Some("MISS"),
+            Civility::Docteur => Some("DOCT"),
+            Civility::Maitre => None,
+        }
+    }
+}
+
+impl fmt::Display for Civility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Civility::Monsieur => "Monsieur",
+            Civility::Madame => "Madame",
+            Civility::Mademoiselle => "Mademoiselle",
+            Civility::Maitre => "Maître",
+            Civility::Docteur => "Docteur",
+        })
+    }
+}
+
+/// The name of a person split into its parts, as written in the first line
+/// of a french address: civility, given name and surname in capitals
+/// (e.g., `Monsieur Jean DELHOURME`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PersonName {
+    pub civility: Option<Civility>,
+    pub given_name: Option<String>,
+    pub surname: String,
+}
+
+impl PersonName {
+    /// Splits a name line. The surname is made of the last words written in
+    /// capitals, or of the last word when none is. `None` for a line without
+    /// name, e.g., a lone civility.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        let civility = words.first().and_then(|word| Civility::parse(word));
+        if civility.is_some() {
+            words.remove(0);
+        }
+
+        let is_capitalized = |word: &str| {
+            word.chars().any(char::is_alphabetic) && !word.chars().any(char::is_lowercase)
+        };
+        let surname_len = words
+            .iter()
+            .rev()
+            .take_while(|word| is_capitalized(word))
+            .count()
+            .max(1);
+        let split = words.len().checked_sub(surname_len)?;
+        let (given_name, surname) = words.split_at(split);
+
+        Some(PersonName {
+            civility,
+            given_name: (!given_name.is_empty()).then(|| given_name.join(" ")),
+            surname: surname.join(" "),
+        })
+    }
+}
+
+impl fmt::Display for PersonName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(civility) = self.civility {
+            write!(f, "{civility} ")?;
+        }
+        if let Some(given_name) = &self.given_name {
+            write!(f, "{given_name} ")?;
+        }
+        f.write_str(&self.surname)
+    }
+}
+
+impl Recipient {
+    /// The name of the individual, or of the contact person of a business
+    /// when it starts with a civility (e.g., `Mademoiselle Lucie MARTIN`).
+    pub fn person_name(&self) -> Option<PersonName> {
+        match self {
+            Recipient::Individual { name } => PersonName::parse(name),
+            Recipient::Business { contact_person, .. } => contact_person
+                .as_deref()
+                .and_then(PersonName::parse)
+                .filter(|name| name.civility.is_some()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_person_names() {
+        let name = PersonName::parse("Monsieur Jean DELHOURME").unwrap();
+        assert_eq!(
+            name,
+            PersonName {
+                civility: Some(Civility::Monsieur),
+                given_name: Some("Jean".to_string()),
+                surname: "DELHOURME".to_string(),
+            }
+        );
+        assert_eq!(name.to_string(), "Monsieur Jean DELHOURME");
+
+        let name = PersonName::parse("Mme Marie-Claire DE LA FONTAINE").unwrap();
+        assert_eq!(name.civility, Some(Civility::Madame));
+        assert_eq!(name.given_name.as_deref(), Some("Marie-Claire"));
+        assert_eq!(name.surname, "DE LA FONTAINE");
+        assert_eq!(name.to_string(), "Madame Marie-Claire DE LA FONTAINE");
+
+        let name = PersonName::parse("Mirabelle Prune").unwrap();
+        assert_eq!(name.civility, None);
+        assert_eq!(name.given_name.as_deref(), Some("Mirabelle"));
+        assert_eq!(name.surname, "Prune");
+
+        assert_eq!(PersonName::parse("M. DUPONT").unwrap().given_name, None);
+        assert_eq!(PersonName::parse("Docteur"), None);
+        assert_eq!(PersonName::parse(""), None);
+    }
+
+    #[test]
+    fn civility_name_prefixes() {
+        assert_eq!(Civility::parse("Mlle"), Some(Civility::Mademoiselle));
+        assert_eq!(Civility::parse("DR."), Some(Civility::Docteur));
+        assert_eq!(Civility::parse("Jean"), None);
+        assert_eq!(Civility::Madame.name_prefix(), Some("MADM"));
+        assert_eq!(Civility::Maitre.name_prefix(), None);
+
+        let contact = |contact: &str| {
+            Recipient::business("Société DUPONT".to_string(), Some(contact), vec![])
+        };
+        assert!(contact("Mademoiselle Lucie MARTIN").person_name().is_some());
+        assert_eq!(contact("Service achats").person_name(), None);
+        assert_eq!(
+            contact("Service achats Monsieur Jean DUPONT")
+                .person_name()
+                .map(|name| name.surname),
+            Some("DUPONT".to_string())
+        );
+    }
+}
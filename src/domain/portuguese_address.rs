@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::address::*;
+use super::address_conversion::AddressConversionError;
+use super::street_number::NumberExtStyle;
+
+/// A Portuguese address (CTT).
+///
+/// # Example
+///
+/// Rua Augusta 274, 2º Esq.
+/// 1100-053 LISBOA
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PortugueseAddress {
+    /// The recipient, or the contact of a business.
+    pub name: Option<String>,
+    /// The business name, for business addresses.
+    #[serde(default)]
+    pub business_name: Option<String>,
+    /// The street type and name (Rua Augusta, Avenida da Liberdade).
+    pub street: String,
+    /// The door number (número de polícia), absent in rural areas.
+    #[serde(default)]
+    pub door_number: Option<String>,
+    /// The floor and side (2º Esq., R/C).
+    #[serde(default)]
+    pub floor: Option<String>,
+    /// The locality, when it differs from the postal designation.
+    #[serde(default)]
+    pub locality: Option<String>,
+    /// The 4+3 digits postal code (1100-053).
+    pub postal_code: String,
+    /// The postal designation, the name of the delivering post office
+    /// (LISBOA).
+    pub postal_designation: String,
+    /// Unknown fields, kept as is through conversions and storage.
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub extras: Map<String, Value>,
+}
+
+impl PortugueseAddress {
+    /// The address lines for domestic mail.
+    pub fn lines(&self) -> Vec<String> {
+        let street = match (&self.door_number, &self.floor) {
+            (Some(number), Some(floor)) => format!("{} {number}, {floor}", self.street),
+            (Some(number), None) => format!("{} {number}", self.street),
+            (None, Some(floor)) => format!("{}, {floor}", self.street),
+            (None, None) => self.street.clone(),
+        };
+
+        [
+            self.business_name.clone(),
+            self.name.clone(),
+            Some(street),
+            self.locality.clone(),
+            Some(format!(
+                "{} {}",
+                self.postal_code,
+                self.postal_designation.to_uppercase()
+            )),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|line| !line.trim().is_empty())
+        .collect()
+    }
+}
+
+/// Parses a 4+3 digits postal code, written with a dash ("1100-053"), a space
+/// or without separator.
+pub fn parse_portuguese_postal_code(code: &str) -> Result<String, AddressConversionError> {
+    let digits: String = code.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+
+    if digits.len() != 7 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AddressConversionError::InvalidPostal {
+            field: "postal_code".to_string(),
+            value: code.to_string(),
+            reason: "Portuguese postal codes have 4+3 digits (e.g., '1100-053')".to_string(),
+        });
+    }
+
+    Ok(format!("{}-{}", &digits[..4], &digits[4..]))
+}
+
+impl ConvertedAddress {
+    /// Converts a Portuguese address. The floor is the internal delivery point
+    /// and the locality the town location, as in ISO 20022.
+    pub fn from_portuguese(address: PortugueseAddress) -> Result<Self, AddressConversionError> {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        let required = |field: &str, value: String| {
+            if value.trim().is_empty() {
+                Err(AddressConversionError::missing(field))
+            } else {
+                Ok(value.trim().to_string())
+            }
+        };
+
+        let postcode = parse_portuguese_postal_code(&address.postal_code)?;
+        let (kind, recipient) = recipient(address.name, address.business_name)?;
+
+        Ok(ConvertedAddress::new(
+            kind,
+            recipient,
+            non_empty(address.floor).map(|floor| DeliveryPoint {
+                care_of: None,
+                external: None,
+                internal: Some(floor),
+                postbox: None,
+            }),
+            Some(Street {
+                number: non_empty(address.door_number),
+                kind: None,
+                repetition_index: None,
+                name: required("street", address.street)?,
+            }),
+            PostalDetails {
+                postcode,
+                town: required("postal_designation", address.postal_designation)?,
+                town_location: non_empty(address.locality),
+                locality: None,
+                country_subdivision: None,
+            },
+            Country::Portugal,
+        )
+        .with_extras(address.extras))
+    }
+
+    /// Converts the address to a Portuguese address. Only addresses in
+    /// Portugal with a street convert.
+    pub fn to_portuguese(&self) -> Result<PortugueseAddress, AddressConversionError> {
+        if self.country != Country::Portugal {
+            return Err(AddressConversionError::InvalidValue {
+                field: "country".to_string(),
+                value: self.country.iso_code().to_string(),
+                reason: format!(
+                    "{} address can't be written as a portuguese address",
+                    self.country.english_name()
+                ),
+            });
+        }
+
+        let (name, business_name) = self.recipient_lines();
+        let street = self
+            .street
+            .as_ref()
+            .ok_or_else(|| AddressConversionError::missing("street"))?;
+
+        Ok(PortugueseAddress {
+            name,
+            business_name,
+            street: street.name.clone(),
+            door_number: street.full_number(NumberExtStyle::Attached),
+            floor: self
+                .delivery_point
+                .as_ref()
+                .and_then(|delivery_point| delivery_point.internal.clone()),
+            locality: self
+                .postal_details
+                .town_location
+                .clone()
+                .or_else(|| self.postal_details.locality.clone()),
+            postal_code: self.postal_details.postcode.clone(),
+            postal_designation: self.postal_details.town.clone(),
+            extras: self.extras.clone(),
+        })
+    }
+
+    /// The name and business name fields of the lusophone formats.
+    pub(super) fn recipient_lines(&self) -> (Option<String>, Option<String>) {
+        match &self.recipient {
+            Recipient::Individual { name } => (Some(name.clone()), None),
+            Recipient::Business { company_name, .. } => {
+                (self.recipient.denomination(), Some(company_name.clone()))
+            }
+        }
+    }
+}
+
+/// The kind and recipient of an address with a name and an optional business
+/// name, the business name making it a business address.
+pub(super) fn recipient(
+    name: Option<String>,
+    business_name: Option<String>,
+) -> Result<(AddressKind, Recipient), AddressConversionError> {
+    let name = name.filter(|name| !name.trim().is_empty());
+
+    match business_name {
+        Some(company_name) if !company_name.trim().is_empty() => Ok((
+            AddressKind::Business,
+            Recipient::business(company_name, name.as_deref(), Vec::new()),
+        )),
+        _ => Ok((
+            AddressKind::Individual,
+            Recipient::Individual {
+                name: name.ok_or_else(|| AddressConversionError::missing("name"))?,
+            },
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_portuguese_postal_code, PortugueseAddress};
+    use crate::domain::*;
+    use serde_json::Map;
+
+    fn address() -> PortugueseAddress {
+        PortugueseAddress {
+            name: Some("Sr. João Silva".to_string()),
+            business_name: None,
+            street: "Rua Augusta".to_string(),
+            door_number: Some("274".to_string()),
+            floor: Some("2º Esq.".to_string()),
+            locality: None,
+            postal_code: "1100-053".to_string(),
+            postal_designation: "Lisboa".to_string(),
+            extras: Map::new(),
+        }
+    }
+
+    #[test]
+    fn it_should_parse_postal_codes() {
+        assert_eq!(
+            parse_portuguese_postal_code("1100-053").unwrap(),
+            "1100-053"
+        );
+        assert_eq!(parse_portuguese_postal_code("1100053").unwrap(), "1100-053");
+        assert!(matches!(
+            parse_portuguese_postal_code("1100"),
+            Err(AddressConversionError::InvalidPostal { field, .. }) if field == "postal_code"
+        ));
+    }
+
+    #[test]
+    fn it_should_render_lines() {
+        assert_eq!(
+            address().lines(),
+            vec![
+                "Sr. João Silva",
+                "Rua Augusta 274, 2º Esq.",
+                "1100-053 LISBOA"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_convert_back_and_forth() {
+        let converted = ConvertedAddress::from_portuguese(address()).unwrap();
+
+        assert_eq!(converted.country, Country::Portugal);
+        assert_eq!(converted.postal_details.postcode, "1100-053");
+        assert_eq!(converted.postal_details.town, "Lisboa");
+        assert_eq!(converted.to_portuguese().unwrap(), address());
+
+        let iso = converted.to_iso20022().unwrap();
+        let back = ConvertedAddress::from_iso20022(iso).unwrap();
+        assert_eq!(back.to_portuguese().unwrap(), address());
+    }
+}
@@ -1,20 +1,79 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::address::Address;
+use super::address::{Address, AddressKind, Country};
+use super::address_search::{search_terms, SearchHit};
+use super::fingerprint::Fingerprint;
+use crate::error::ErrorKind;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum AddressRepositoryError {
     #[error("Resource not found: `{0}`")]
     NotFound(String),
     #[error("Resource already exists: `{0}`")]
     AlreadyExists(String),
+    #[error("Version conflict on `{id}`: expected version {expected}, found {actual}")]
+    Conflict {
+        id: String,
+        expected: u64,
+        actual: u64,
+    },
     #[error("Invalid uuid")]
     InvalidUuid(#[from] uuid::Error),
     #[error("Underlying I/O operation failed")]
     IOFailure(#[from] std::io::Error),
     #[error("Underlying serialization or deserialization operation failed")]
     SerializationFailure(#[from] serde_json::Error),
+    #[error("Operation timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Repository unavailable")]
+    Unavailable,
+    #[error("Storage backend failure: {0}")]
+    Backend(String),
+}
+
+impl AddressRepositoryError {
+    /// The stable code of the error, e.g., `E_NOT_FOUND`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AddressRepositoryError::NotFound(_) => "E_NOT_FOUND",
+            AddressRepositoryError::AlreadyExists(_) => "E_ALREADY_EXISTS",
+            AddressRepositoryError::Conflict { .. } => "E_VERSION_CONFLICT",
+            AddressRepositoryError::InvalidUuid(_) => "E_INVALID_ID",
+            AddressRepositoryError::Timeout(_) => "E_TIMEOUT",
+            AddressRepositoryError::Cancelled => "E_CANCELLED",
+            AddressRepositoryError::Unavailable => "E_UNAVAILABLE",
+            AddressRepositoryError::IOFailure(_)
+            | AddressRepositoryError::SerializationFailure(_)
+            | AddressRepositoryError::Backend(_) => "E_STORAGE",
+        }
+    }
+
+    /// The category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AddressRepositoryError::NotFound(_) => ErrorKind::NotFound,
+            AddressRepositoryError::AlreadyExists(_) | AddressRepositoryError::Conflict { .. } => {
+                ErrorKind::Conflict
+            }
+            AddressRepositoryError::InvalidUuid(_) => ErrorKind::InvalidInput,
+            AddressRepositoryError::Timeout(_)
+            | AddressRepositoryError::Cancelled
+            | AddressRepositoryError::Unavailable => ErrorKind::Unavailable,
+            AddressRepositoryError::IOFailure(_)
+            | AddressRepositoryError::SerializationFailure(_)
+            | AddressRepositoryError::Backend(_) => ErrorKind::Storage,
+        }
+    }
 }
 
 /// Short hand for `Result` type.
@@ -24,6 +83,341 @@ pub trait AddressRepository {
     fn save(&self, addr: Address) -> RepositoryResult<Uuid>;
     fn fetch(&self, id: &str) -> RepositoryResult<Address>;
     fn fetch_all(&self) -> RepositoryResult<Vec<Address>>;
+    /// Lists the identifiers of the stored addresses. Backends able to do so
+    /// without loading the addresses should override this method.
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        Ok(self.fetch_all()?.iter().map(Address::id).collect())
+    }
+    /// Fetches the addresses created or updated at or after `since`. Backends
+    /// able to filter on `updated_at` (e.g., with an index) should override
+    /// this method.
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        Ok(self
+            .fetch_all()?
+            .into_iter()
+            .filter(|address| address.updated_at() >= since)
+            .collect())
+    }
+    /// Fetches the addresses matching `filter`. Backends able to filter on
+    /// the postcode, the town, the country or the kind (e.g., SQL backends
+    /// with a `WHERE` clause) should override this method.
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        Ok(self
+            .fetch_all()?
+            .into_iter()
+            .filter(|address| filter.matches(address))
+            .collect())
+    }
+    /// Fetches the address saved with the idempotency key `key`, the most
+    /// recently received one if several were. Backends able to look the key
+    /// up (e.g., with an index) should override this method.
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        Ok(self
+            .fetch_all()?
+            .into_iter()
+            .filter(|address| address.idempotency_key().is_some_and(|k| k.key == key))
+            .max_by_key(|address| address.idempotency_key().map(|k| k.received_at)))
+    }
+    /// Fetches the address named `alias`, see [`Address::set_alias`].
+    /// Backends able to look the alias up (e.g., with an index) should
+    /// override this method.
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        Ok(self
+            .fetch_all()?
+            .into_iter()
+            .find(|address| address.alias().is_some_and(|a| a.as_str() == alias)))
+    }
+    /// Fetches the addresses whose [fingerprint](Address::fingerprint) is
+    /// `fingerprint`, usually one since the duplicates are refused when
+    /// saved. Backends storing the fingerprint (e.g., with an index) should
+    /// override this method.
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        Ok(self
+            .fetch_all()?
+            .into_iter()
+            .filter(|address| address.fingerprint() == *fingerprint)
+            .collect())
+    }
+    /// Replaces the stored address. The update is refused with a conflict
+    /// when the stored address is no longer at the version `addr` was updated
+    /// from, see [`check_version`].
     fn update(&self, addr: Address) -> RepositoryResult<()>;
     fn delete(&self, id: &str) -> RepositoryResult<()>;
+    /// The versions of the address, oldest first, the stored one last.
+    /// Backends keeping no history keep this method, which only returns the
+    /// stored version.
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        Ok(vec![self.fetch(id)?])
+    }
+    /// Reclaims the space left by the previous writes, see
+    /// [`CompactionReport`]. Backends with nothing to reclaim keep this
+    /// method, which reports nothing done.
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        Ok(CompactionReport::default())
+    }
+    /// Finds the `limit` addresses most relevant to `query`, each of its
+    /// words starting a word of the address, see [`Address::match_score`].
+    /// Backends with a search index (e.g., the `SearchIndexRepository` of the
+    /// `fts` feature) should override this method.
+    fn search(&self, query: &str, limit: usize) -> RepositoryResult<Vec<SearchHit>> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits: Vec<SearchHit> = self
+            .fetch_all()?
+            .iter()
+            .filter_map(|address| {
+                address.match_score(&terms).map(|score| SearchHit {
+                    id: address.id(),
+                    score,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+
+        Ok(hits)
+    }
+    /// Rebuilds the search index from the stored addresses, returning how
+    /// many were indexed. Backends without index keep this method, which
+    /// returns none.
+    fn reindex(&self) -> RepositoryResult<Option<usize>> {
+        Ok(None)
+    }
+    /// Checks that the storage answers, e.g., for a readiness probe.
+    /// Backends always reachable (e.g., in memory) keep this method, which
+    /// succeeds.
+    fn ping(&self) -> RepositoryResult<()> {
+        Ok(())
+    }
+    /// The changes logged after the sequence number `after`, oldest first
+    /// and at most `limit`, see [`ChangeEvent`]. Backends without change log
+    /// keep this method, which returns none.
+    fn changes(&self, after: u64, limit: usize) -> RepositoryResult<Option<Vec<ChangeEvent>>> {
+        let _ = (after, limit);
+        Ok(None)
+    }
+}
+
+/// A repository chosen at runtime, e.g., by [`BoxedAddressService`]. Every
+/// method is forwarded, so that the overrides of the backend are kept.
+///
+/// [`BoxedAddressService`]: crate::application::service::BoxedAddressService
+impl<R: AddressRepository + ?Sized> AddressRepository for Box<R> {
+    fn save(&self, addr: Address) -> RepositoryResult<Uuid> {
+        (**self).save(addr)
+    }
+
+    fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+        (**self).fetch(id)
+    }
+
+    fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+        (**self).fetch_all()
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        (**self).fetch_ids()
+    }
+
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        (**self).fetch_updated_since(since)
+    }
+
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        (**self).fetch_filtered(filter)
+    }
+
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        (**self).fetch_by_idempotency_key(key)
+    }
+
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        (**self).fetch_by_alias(alias)
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        (**self).find_by_fingerprint(fingerprint)
+    }
+
+    fn update(&self, addr: Address) -> RepositoryResult<()> {
+        (**self).update(addr)
+    }
+
+    fn delete(&self, id: &str) -> RepositoryResult<()> {
+        (**self).delete(id)
+    }
+
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        (**self).fetch_history(id)
+    }
+
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        (**self).compact()
+    }
+
+    fn search(&self, query: &str, limit: usize) -> RepositoryResult<Vec<SearchHit>> {
+        (**self).search(query, limit)
+    }
+
+    fn reindex(&self) -> RepositoryResult<Option<usize>> {
+        (**self).reindex()
+    }
+
+    fn ping(&self) -> RepositoryResult<()> {
+        (**self).ping()
+    }
+
+    fn changes(&self, after: u64, limit: usize) -> RepositoryResult<Option<Vec<ChangeEvent>>> {
+        (**self).changes(after, limit)
+    }
+}
+
+/// What [`AddressRepository::compact`] did to the storage.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompactionReport {
+    /// The stored addresses written again in a smaller form.
+    pub rewritten: usize,
+    /// The addresses left behind by an interrupted layout change, moved back
+    /// where they are read from.
+    pub orphans_restored: usize,
+    /// The stale copies of stored addresses, removed.
+    pub orphans_removed: usize,
+    /// The emptied directories, removed.
+    pub dirs_removed: usize,
+    /// The files which can't be read as an address. They are left untouched
+    /// for an operator to look at.
+    pub unreadable: Vec<String>,
+    /// The bytes freed on the storage.
+    pub reclaimed_bytes: u64,
+}
+
+/// A change of the stored addresses, as logged for the downstream consumers
+/// replicating them, see [`AddressRepository::changes`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// The position of the event in the log, starting at 1. Consumers resume
+    /// from the last one they applied.
+    pub sequence: u64,
+    /// When the change was logged.
+    pub at: DateTime<Utc>,
+    pub change: Change,
+    pub id: Uuid,
+    /// The address as stored by the change, none once deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Saved,
+    Updated,
+    Deleted,
+}
+
+/// Which addresses [`AddressRepository::fetch_filtered`] returns: the ones
+/// matching every criterion set. The postcodes and the towns are compared
+/// regardless of the case and the spaces (e.g., `sw1a1aa` matches
+/// `SW1A 1AA`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressFilter {
+    /// The exact postcode.
+    pub postcode: Option<String>,
+    /// The start of the postcode (e.g., `33` for the Gironde).
+    pub postcode_prefix: Option<String>,
+    /// The exact town.
+    pub town: Option<String>,
+    pub country: Option<Country>,
+    pub kind: Option<AddressKind>,
+}
+
+impl AddressFilter {
+    /// Whether every address matches, no criterion being set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, address: &Address) -> bool {
+        let postcode = compact(&address.postal_details.postcode);
+        let town = compact(&address.postal_details.town);
+
+        self.postcode
+            .as_ref()
+            .is_none_or(|expected| postcode == compact(expected))
+            && self
+                .postcode_prefix
+                .as_ref()
+                .is_none_or(|prefix| postcode.starts_with(&compact(prefix)))
+            && self
+                .town
+                .as_ref()
+                .is_none_or(|expected| town == compact(expected))
+            && self
+                .country
+                .as_ref()
+                .is_none_or(|country| address.country == *country)
+            && self.kind.as_ref().is_none_or(|kind| address.kind == *kind)
+    }
+}
+
+/// The text in uppercase without its spaces, as compared by the filters.
+pub(crate) fn compact(text: &str) -> String {
+    text.split_whitespace().collect::<String>().to_uppercase()
+}
+
+/// The namespace of a team sharing the storage of a deployment. The
+/// repositories of a tenant, set on construction, only see its addresses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tenant(String);
+
+impl Tenant {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Tenants are made of 1 to 64 ASCII letters, digits, `-` or `_`, so that they
+/// can name a directory or a table prefix as is.
+impl FromStr for Tenant {
+    type Err = String;
+
+    fn from_str(tenant: &str) -> Result<Self, Self::Err> {
+        let valid = (1..=64).contains(&tenant.len())
+            && tenant
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(format!(
+                "Invalid tenant `{tenant}`: expected 1 to 64 letters, digits, '-' or '_'"
+            ));
+        }
+
+        Ok(Self(tenant.to_string()))
+    }
+}
+
+impl fmt::Display for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Checks that `stored` is at the version `addr` was updated from, so that an
+/// update based on a stale fetch doesn't overwrite a concurrent one.
+pub fn check_version(stored: &Address, addr: &Address) -> RepositoryResult<()> {
+    let expected = addr.version().saturating_sub(1);
+
+    if stored.version() != expected {
+        return Err(AddressRepositoryError::Conflict {
+            id: addr.id().to_string(),
+            expected,
+            actual: stored.version(),
+        });
+    }
+
+    Ok(())
 }
@@ -0,0 +1,141 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use strum::EnumString;
+
+/// The repetition index of a street number, telling apart the buildings
+/// sharing a number (2, 2 BIS, 2 TER, or 2A, 2B).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StreetNumberExt {
+    Bis,
+    Ter,
+    Quater,
+    /// A letter, uppercase.
+    Letter(char),
+}
+
+impl StreetNumberExt {
+    /// Splits the digits of a street number from its repetition index
+    /// (e.g., `2BIS` or `2 bis` -> `("2", Some(Bis))`). `None` when the number
+    /// doesn't start with digits or ends with anything else than an index,
+    /// such numbers being kept as written.
+    pub fn split_number(number: &str) -> Option<(&str, Option<Self>)> {
+        let number = number.trim();
+        let digits_len = number
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(number.len());
+        if digits_len == 0 {
+            return None;
+        }
+
+        let (digits, index) = number.split_at(digits_len);
+        match index.trim_start() {
+            "" => Some((digits, None)),
+            index => index.parse().ok().map(|index| (digits, Some(index))),
+        }
+    }
+}
+
+impl FromStr for StreetNumberExt {
+    type Err = String;
+
+    fn from_str(index: &str) -> Result<Self, Self::Err> {
+        let upper = index.to_uppercase();
+        let mut chars = upper.chars();
+
+        match (upper.as_str(), chars.next(), chars.next()) {
+            ("BIS", ..) => Ok(StreetNumberExt::Bis),
+            ("TER", ..) => Ok(StreetNumberExt::Ter),
+            ("QUATER", ..) => Ok(StreetNumberExt::Quater),
+            (_, Some(letter), None) if letter.is_ascii_alphabetic() => {
+                Ok(StreetNumberExt::Letter(letter))
+            }
+            _ => Err(format!("Invalid repetition index: {index}")),
+        }
+    }
+}
+
+impl fmt::Display for StreetNumberExt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreetNumberExt::Bis => f.write_str("BIS"),
+            StreetNumberExt::Ter => f.write_str("TER"),
+            StreetNumberExt::Quater => f.write_str("QUATER"),
+            StreetNumberExt::Letter(letter) => write!(f, "{letter}"),
+        }
+    }
+}
+
+impl Serialize for StreetNumberExt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for StreetNumberExt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// How the repetition index is written after the street number.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum NumberExtStyle {
+    /// Next to the number (`2BIS`, `2A`).
+    #[default]
+    Attached,
+    /// After a space (`2 BIS`, `2 A`), as on the french labels.
+    Spaced,
+}
+
+impl NumberExtStyle {
+    /// Writes `number` followed by its repetition index, if any.
+    pub fn write(self, number: &str, index: Option<StreetNumberExt>) -> String {
+        match (index, self) {
+            (None, _) => number.to_string(),
+            (Some(index), NumberExtStyle::Attached) => format!("{number}{index}"),
+            (Some(index), NumberExtStyle::Spaced) => format!("{number} {index}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_split_the_repetition_index() {
+        assert_eq!(
+            StreetNumberExt::split_number("2BIS"),
+            Some(("2", Some(StreetNumberExt::Bis)))
+        );
+        assert_eq!(
+            StreetNumberExt::split_number("12 ter"),
+            Some(("12", Some(StreetNumberExt::Ter)))
+        );
+        assert_eq!(
+            StreetNumberExt::split_number("2d"),
+            Some(("2", Some(StreetNumberExt::Letter('D'))))
+        );
+        assert_eq!(StreetNumberExt::split_number("25"), Some(("25", None)));
+        assert_eq!(StreetNumberExt::split_number("2AB"), None);
+        assert_eq!(StreetNumberExt::split_number("BIS"), None);
+    }
+
+    #[test]
+    fn it_should_write_the_repetition_index() {
+        let bis = Some(StreetNumberExt::Bis);
+
+        assert_eq!(NumberExtStyle::Attached.write("2", bis), "2BIS");
+        assert_eq!(NumberExtStyle::Spaced.write("2", bis), "2 BIS");
+        assert_eq!(NumberExtStyle::Spaced.write("25", None), "25");
+        assert_eq!(
+            serde_json::to_value(StreetNumberExt::Letter('A')).unwrap(),
+            "A"
+        );
+    }
+}
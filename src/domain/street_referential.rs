@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Read};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use super::address::ConvertedAddress;
+use super::fingerprint::normalize;
+use super::street_type::StreetType;
+
+/// The accepted headers of the columns, La Poste and BAN namings included.
+const POSTCODE_COLUMNS: &[&str] = &["code_postal", "co_postal", "postcode"];
+const COMMUNE_COLUMNS: &[&str] = &[
+    "nom_commune",
+    "libelle_acheminement",
+    "lb_ach_nn",
+    "commune",
+    "town",
+];
+const STREET_COLUMNS: &[&str] = &["nom_voie", "libelle_voie", "lb_voie", "voie", "street"];
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum StreetReferentialError {
+    #[error("Invalid street referential: {0}")]
+    Invalid(#[from] csv::Error),
+    #[error("Missing column in the street referential: expected one of {}", .0.join(", "))]
+    MissingColumn(&'static [&'static str]),
+}
+
+/// The official streets of the french communes, as published by La Poste
+/// (SNA, Hexaligne) or the Base Adresse Nationale, indexed by postcode and
+/// commune to check that the street of an address exists.
+///
+/// The names are compared like the fingerprints do, regardless of the case,
+/// the accents, the punctuation and the abbreviation of the street type, and
+/// `ST` standing for `SAINT` in the communes.
+#[derive(Debug, Default)]
+pub struct StreetReferential {
+    /// The communes of each postcode.
+    communes: HashMap<String, Vec<ReferentialCommune>>,
+}
+
+#[derive(Debug)]
+struct ReferentialCommune {
+    /// The official name of the commune.
+    name: String,
+    key: String,
+    /// The official names of the streets, by their normalized name.
+    streets: HashMap<String, String>,
+}
+
+/// Why an address isn't in the [`StreetReferential`], with the closest
+/// official spelling when one is near enough.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum ReferentialIssue {
+    /// No commune has the postcode.
+    UnknownPostcode { postcode: String },
+    /// The town isn't a commune of the postcode.
+    UnknownCommune {
+        postcode: String,
+        town: String,
+        suggestion: Option<String>,
+    },
+    /// The street isn't one of the commune.
+    UnknownStreet {
+        town: String,
+        street: String,
+        suggestion: Option<String>,
+    },
+}
+
+impl fmt::Display for ReferentialIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suggestion = match self {
+            ReferentialIssue::UnknownPostcode { postcode } => {
+                return write!(f, "postcode `{postcode}` isn't in the referential");
+            }
+            ReferentialIssue::UnknownCommune {
+                postcode,
+                town,
+                suggestion,
+            } => {
+                write!(f, "town `{town}` isn't a commune of {postcode}")?;
+                suggestion
+            }
+            ReferentialIssue::UnknownStreet {
+                town,
+                street,
+                suggestion,
+            } => {
+                write!(f, "street `{street}` isn't in {town}")?;
+                suggestion
+            }
+        };
+
+        match suggestion {
+            Some(suggestion) => write!(f, " (did you mean `{suggestion}`?)"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl StreetReferential {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a CSV file with a header, separated by semicolons, tabs or
+    /// commas, having a postcode (`code_postal`), commune (`nom_commune`,
+    /// `libelle_acheminement`) and street (`nom_voie`, `libelle_voie`)
+    /// column. The other columns are ignored, and so are the repeated streets,
+    /// e.g., the BAN having one row per street number.
+    pub fn from_reader(mut reader: impl BufRead) -> Result<Self, StreetReferentialError> {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(csv::Error::from)?;
+        let delimiter = [b';', b'\t', b',']
+            .into_iter()
+            .find(|delimiter| header.as_bytes().contains(delimiter))
+            .unwrap_or(b',');
+
+        let mut csv = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(header.as_bytes().chain(reader));
+        let headers = csv.headers()?.clone();
+        let column = |names: &'static [&'static str]| {
+            headers
+                .iter()
+                .position(|header| {
+                    let header = header.trim_start_matches('\u{feff}').trim();
+                    names.iter().any(|name| header.eq_ignore_ascii_case(name))
+                })
+                .ok_or(StreetReferentialError::MissingColumn(names))
+        };
+        let (postcode, commune, street) = (
+            column(POSTCODE_COLUMNS)?,
+            column(COMMUNE_COLUMNS)?,
+            column(STREET_COLUMNS)?,
+        );
+
+        let mut referential = Self::new();
+        for record in csv.records() {
+            let record = record?;
+            let field = |i: usize| record.get(i).unwrap_or_default();
+            referential.insert(field(postcode), field(commune), field(street));
+        }
+
+        Ok(referential)
+    }
+
+    /// Adds the street of the commune, or only the commune when `street` is
+    /// empty.
+    pub fn insert(&mut self, postcode: &str, commune: &str, street: &str) {
+        let communes = self.communes.entry(postcode_key(postcode)).or_default();
+        let key = commune_key(commune);
+        let commune = match communes.iter().position(|commune| commune.key == key) {
+            Some(i) => &mut communes[i],
+            None => {
+                communes.push(ReferentialCommune {
+                    name: commune.trim().to_string(),
+                    key,
+                    streets: HashMap::new(),
+                });
+                communes.last_mut().expect("a commune was just added")
+            }
+        };
+
+        if !street.trim().is_empty() {
+            commune
+                .streets
+                .entry(street_key(street))
+                .or_insert_with(|| street.trim().to_string());
+        }
+    }
+
+    /// Checks that the town of the address is a commune of its postcode, and
+    /// its street one of the commune's. The addresses outside France aren't
+    /// checked.
+    pub fn check(&self, address: &ConvertedAddress) -> Vec<ReferentialIssue> {
+        if address.country.iso_code() != "FR" {
+            return Vec::new();
+        }
+        let postcode = &address.postal_details.postcode;
+        let town = &address.postal_details.town;
+
+        let Some(communes) = self.communes.get(&postcode_key(postcode)) else {
+            return vec![ReferentialIssue::UnknownPostcode {
+                postcode: postcode.clone(),
+            }];
+        };
+        let key = commune_key(town);
+        let Some(commune) = communes.iter().find(|commune| commune.key == key) else {
+            let names = communes
+                .iter()
+                .map(|commune| (commune.key.as_str(), commune.name.as_str()));
+            return vec![ReferentialIssue::UnknownCommune {
+                postcode: postcode.clone(),
+                town: town.clone(),
+                suggestion: closest(&key, names),
+            }];
+        };
+
+        match &address.street {
+            Some(street) => {
+                let key = street_key(&street.name);
+                if commune.streets.contains_key(&key) {
+                    return Vec::new();
+                }
+                let names = commune
+                    .streets
+                    .iter()
+                    .map(|(key, name)| (key.as_str(), name.as_str()));
+
+                vec![ReferentialIssue::UnknownStreet {
+                    town: town.clone(),
+                    street: street.name.clone(),
+                    suggestion: closest(&key, names),
+                }]
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+fn postcode_key(postcode: &str) -> String {
+    postcode.split_whitespace().collect()
+}
+
+/// The normalized commune, without its `CEDEX` suffix and with `SAINT`
+/// abbreviated as La Poste does (e.g., `Saint-Étienne Cedex 1` ->
+/// `ST ETIENNE`).
+fn commune_key(commune: &str) -> String {
+    let commune = normalize(commune);
+    let words: Vec<&str> = commune.split(' ').collect();
+    let end = words
+        .iter()
+        .position(|word| *word == "CEDEX")
+        .unwrap_or(words.len());
+
+    words[..end]
+        .iter()
+        .map(|word| match *word {
+            "SAINT" => "ST",
+            "SAINTE" => "STE",
+            word => word,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The normalized street, its type written in full (e.g., `Av. des Champs` ->
+/// `AVENUE DES CHAMPS`).
+fn street_key(street: &str) -> String {
+    let street = normalize(street);
+
+    match StreetType::split(&street) {
+        Some((kind, rest)) => format!("{} {rest}", kind.name()),
+        None => street,
+    }
+}
+
+/// The official name whose key is the closest to `key`, a third of its
+/// letters at most being different.
+fn closest<'a>(key: &str, names: impl Iterator<Item = (&'a str, &'a str)>) -> Option<String> {
+    let max_distance = (key.chars().count() / 3).max(1);
+
+    names
+        .map(|(candidate, name)| (distance(key, candidate), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, name)| name.to_string())
+}
+
+/// The Levenshtein distance between `a` and `b`.
+pub(super) fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AddressConvertible, FrenchAddress};
+
+    const REFERENTIAL: &str = "\
+code_postal;nom_commune;nom_voie;numero
+33380;Mios;Rue de l'Église;25
+33380;Mios;Rue de l'Église;27
+33380;Mios;Avenue de la Libération;1
+33380;Marcheprime;Rue des Pins;3
+42000;Saint-Étienne;Cours Fauriel;10
+";
+
+    fn address(street: &str, postal: &str) -> ConvertedAddress {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": "Monsieur Jean DELHOURME",
+            "street": street,
+            "postal": postal,
+            "country": "FRANCE",
+        }))
+        .unwrap();
+
+        ConvertedAddress::from_french(french).unwrap()
+    }
+
+    #[test]
+    fn it_should_check_the_streets_of_the_commune() {
+        let referential = StreetReferential::from_reader(REFERENTIAL.as_bytes()).unwrap();
+
+        let check = |street: &str, postal: &str| referential.check(&address(street, postal));
+        assert_eq!(check("25 RUE DE L'EGLISE", "33380 MIOS"), []);
+        assert_eq!(check("1 AV DE LA LIBERATION", "33380 MIOS"), []);
+        assert_eq!(check("10 CRS FAURIEL", "42000 ST ETIENNE CEDEX 1"), []);
+
+        assert_eq!(
+            check("25 RUE DE L'EGLIZE", "33380 MIOS"),
+            [ReferentialIssue::UnknownStreet {
+                town: "MIOS".to_string(),
+                street: "RUE DE L'EGLIZE".to_string(),
+                suggestion: Some("Rue de l'Église".to_string()),
+            }]
+        );
+        // The streets of the other communes of the postcode don't count.
+        let issues = check("3 RUE DES PINS", "33380 MIOS");
+        assert!(matches!(
+            &issues[..],
+            [ReferentialIssue::UnknownStreet {
+                suggestion: None,
+                ..
+            }]
+        ));
+        assert_eq!(
+            check("25 RUE DE L'EGLISE", "33380 MIOSS")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["town `MIOSS` isn't a commune of 33380 (did you mean `Mios`?)"]
+        );
+        assert_eq!(
+            check("25 RUE DE L'EGLISE", "33000 BORDEAUX"),
+            [ReferentialIssue::UnknownPostcode {
+                postcode: "33000".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn it_should_require_the_columns() {
+        let error = StreetReferential::from_reader("code_postal,commune\n33380,Mios\n".as_bytes())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            StreetReferentialError::MissingColumn(STREET_COLUMNS)
+        ));
+        assert_eq!(distance("EGLISE", "EGLIZE"), 1);
+    }
+}
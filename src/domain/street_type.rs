@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+/// The official types of french streets (La Poste, FANTOIR), written at the
+/// start of the street name ("RUE DE L'EGLISE", "IMP DES LILAS").
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StreetType {
+    Allee,
+    Avenue,
+    Boulevard,
+    Chaussee,
+    Chemin,
+    Cite,
+    Clos,
+    Cours,
+    Esplanade,
+    Faubourg,
+    Hameau,
+    Impasse,
+    Lotissement,
+    Montee,
+    Passage,
+    Place,
+    Promenade,
+    Quai,
+    Quartier,
+    Residence,
+    RondPoint,
+    Route,
+    Rue,
+    Sentier,
+    Square,
+    Traverse,
+    Villa,
+    Voie,
+}
+
+/// The street types with their full name, their official abbreviation, and
+/// the other spellings read in the addresses.
+const LEXICON: &[(StreetType, &str, &str, &[&str])] = &[
+    (StreetType::Allee, "ALLEE", "ALL", &["ALLÉE"]),
+    (StreetType::Avenue, "AVENUE", "AV", &["AVE"]),
+    (
+        StreetType::Boulevard,
+        "BOULEVARD",
+        "BD",
+        &["BLD", "BLVD", "BOUL"],
+    ),
+    (StreetType::Chaussee, "CHAUSSEE", "CHS", &["CHAUSSÉE"]),
+    (StreetType::Chemin, "CHEMIN", "CHE", &["CHEM", "CH"]),
+    (StreetType::Cite, "CITE", "CITE", &["CITÉ"]),
+    (StreetType::Clos, "CLOS", "CLOS", &[]),
+    (StreetType::Cours, "COURS", "CRS", &[]),
+    (StreetType::Esplanade, "ESPLANADE", "ESP", &[]),
+    (StreetType::Faubourg, "FAUBOURG", "FG", &["FBG"]),
+    (StreetType::Hameau, "HAMEAU", "HAM", &[]),
+    (StreetType::Impasse, "IMPASSE", "IMP", &[]),
+    (StreetType::Lotissement, "LOTISSEMENT", "LOT", &[]),
+    (StreetType::Montee, "MONTEE", "MTE", &["MONTÉE"]),
+    (StreetType::Passage, "PASSAGE", "PAS", &["PASS"]),
+    (StreetType::Place, "PLACE", "PL", &[]),
+    (StreetType::Promenade, "PROMENADE", "PROM", &[]),
+    (StreetType::Quai, "QUAI", "QUAI", &[]),
+    (StreetType::Quartier, "QUARTIER", "QUA", &["QRT"]),
+    (
+        StreetType::Residence,
+        "RESIDENCE",
+        "RES",
+        &["RÉSIDENCE", "RÉS"],
+    ),
+    (StreetType::RondPoint, "ROND-POINT", "RPT", &["ROND POINT"]),
+    (StreetType::Route, "ROUTE", "RTE", &[]),
+    (StreetType::Rue, "RUE", "RUE", &["R"]),
+    (StreetType::Sentier, "SENTIER", "SEN", &[]),
+    (StreetType::Square, "SQUARE", "SQ", &[]),
+    (StreetType::Traverse, "TRAVERSE", "TRA", &[]),
+    (StreetType::Villa, "VILLA", "VLA", &[]),
+    (StreetType::Voie, "VOIE", "VOIE", &[]),
+];
+
+impl StreetType {
+    fn entry(
+        self,
+    ) -> &'static (
+        StreetType,
+        &'static str,
+        &'static str,
+        &'static [&'static str],
+    ) {
+        LEXICON
+            .iter()
+            .find(|(kind, ..)| *kind == self)
+            .expect("every street type is in the lexicon")
+    }
+
+    /// The full name of the type (e.g., `AVENUE`).
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    /// The abbreviation of the type written on the lines too long for
+    /// NF Z10-011 (e.g., `AV`).
+    pub fn abbreviation(self) -> &'static str {
+        self.entry().2
+    }
+
+    /// Splits the type the street name starts with from the rest of the name,
+    /// whether it is written in full, abbreviated or with a trailing dot
+    /// (e.g., `Av. des Champs` -> `(Avenue, "des Champs")`). `None` when the
+    /// name doesn't start with a known type, or is only made of it.
+    pub fn split(name: &str) -> Option<(Self, &str)> {
+        let name = name.trim_start();
+
+        LEXICON
+            .iter()
+            .flat_map(|(kind, full, abbreviation, others)| {
+                [*full, *abbreviation]
+                    .into_iter()
+                    .chain(others.iter().copied())
+                    .map(move |spelling| (*kind, spelling))
+            })
+            .filter_map(|(kind, spelling)| {
+                let head = name.get(..spelling.len())?;
+                let rest = &name[spelling.len()..];
+                let rest = rest.strip_prefix('.').unwrap_or(rest);
+                let separated = rest.starts_with(char::is_whitespace);
+
+                (head.to_uppercase() == spelling && separated && !rest.trim().is_empty())
+                    .then(|| (kind, rest.trim_start(), spelling.len()))
+            })
+            // "ROND POINT DU LAC" isn't a "ROND" street.
+            .max_by_key(|(.., len)| *len)
+            .map(|(kind, rest, _)| (kind, rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_split_the_street_type() {
+        assert_eq!(
+            StreetType::split("RUE DE L'EGLISE"),
+            Some((StreetType::Rue, "DE L'EGLISE"))
+        );
+        assert_eq!(
+            StreetType::split("Av. des Champs"),
+            Some((StreetType::Avenue, "des Champs"))
+        );
+        assert_eq!(
+            StreetType::split("IMP DES LILAS"),
+            Some((StreetType::Impasse, "DES LILAS"))
+        );
+        assert_eq!(
+            StreetType::split("ROND POINT DU LAC"),
+            Some((StreetType::RondPoint, "DU LAC"))
+        );
+        assert_eq!(
+            StreetType::split("ALLÉE DES PINS"),
+            Some((StreetType::Allee, "DES PINS"))
+        );
+        assert_eq!(StreetType::split("LE VILLAGE"), None);
+        assert_eq!(StreetType::split("RUELLE DU FOUR"), None);
+        assert_eq!(StreetType::split("RUE"), None);
+    }
+
+    #[test]
+    fn it_should_abbreviate_the_street_type() {
+        assert_eq!(StreetType::Avenue.name(), "AVENUE");
+        assert_eq!(StreetType::Avenue.abbreviation(), "AV");
+        assert_eq!(StreetType::RondPoint.abbreviation(), "RPT");
+    }
+}
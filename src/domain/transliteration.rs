@@ -0,0 +1,170 @@
+#[cfg(feature = "translit")]
+use std::borrow::Cow;
+
+#[cfg(feature = "translit")]
+use super::french_address::FrenchAddress;
+#[cfg(feature = "translit")]
+use super::korean_address::romanize;
+
+/// The writing system of a character, to tell which lines of an address
+/// can't be printed on a La Poste label, limited to Latin-1.
+#[derive(Clone, Copy, Debug, strum_macros::Display, PartialEq)]
+pub enum Script {
+    /// The latin letters outside Latin-1 (e.g., "Ł", "Ő").
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Hangul,
+    Kana,
+    Han,
+    Other,
+}
+
+impl Script {
+    pub fn of(c: char) -> Self {
+        match c as u32 {
+            0x0000..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+            0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x0400..=0x052F => Script::Cyrillic,
+            0x0590..=0x05FF => Script::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F => Script::Arabic,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7A3 => Script::Hangul,
+            0x3040..=0x30FF => Script::Kana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+            _ => Script::Other,
+        }
+    }
+
+    /// The script of the first character of `text` outside Latin-1, `None`
+    /// when the whole text can be printed on a label.
+    pub fn detect(text: &str) -> Option<Self> {
+        text.chars().find(|c| !is_latin1(*c)).map(Script::of)
+    }
+}
+
+fn is_latin1(c: char) -> bool {
+    (c as u32) < 0x100
+}
+
+/// Writes `text` with Latin-1 characters only: Hangul with the Revised
+/// Romanization, the other scripts and the symbols with their closest ASCII
+/// spelling (e.g., "Москва" -> "Moskva"). The Latin-1 characters, such as
+/// the french accents, are kept.
+#[cfg(feature = "translit")]
+pub fn transliterate(text: &str) -> Cow<'_, str> {
+    if Script::detect(text).is_none() {
+        return Cow::Borrowed(text);
+    }
+
+    let romanized = match Script::detect(text) {
+        Some(Script::Hangul) => romanize(text),
+        _ => text.to_string(),
+    };
+    let transliterated = romanized
+        .chars()
+        .map(|c| match c {
+            c if is_latin1(c) => c.to_string(),
+            c => deunicode::deunicode_char(c).unwrap_or("?").to_string(),
+        })
+        .collect();
+
+    Cow::Owned(transliterated)
+}
+
+#[cfg(feature = "translit")]
+impl FrenchAddress {
+    fn lines_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            FrenchAddress::Individual(address) => {
+                let mut lines = vec![&mut address.name];
+                lines.extend(
+                    [
+                        &mut address.internal_delivery,
+                        &mut address.external_delivery,
+                        &mut address.street,
+                        &mut address.distribution_info,
+                    ]
+                    .into_iter()
+                    .flatten(),
+                );
+                lines.extend([&mut address.postal, &mut address.country]);
+                lines
+            }
+            FrenchAddress::Business(address) => {
+                let mut lines = vec![&mut address.business_name];
+                lines.extend(address.identifiers.iter_mut());
+                lines.extend(
+                    [
+                        &mut address.recipient,
+                        &mut address.external_delivery,
+                        &mut address.street,
+                        &mut address.distribution_info,
+                    ]
+                    .into_iter()
+                    .flatten(),
+                );
+                lines.extend([&mut address.postal, &mut address.country]);
+                lines
+            }
+        }
+    }
+
+    /// Transliterates the lines to Latin-1, as required on the labels by La
+    /// Poste, see [`transliterate`]. Returns the script of the first line
+    /// transliterated, `None` when the address was already printable. The
+    /// unknown fields are kept as is.
+    pub fn transliterate(&mut self) -> Option<Script> {
+        let mut script = None;
+
+        for line in self.lines_mut() {
+            script = script.or_else(|| Script::detect(line));
+            if let Cow::Owned(transliterated) = transliterate(line) {
+                *line = transliterated;
+            }
+        }
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_detect_the_scripts_outside_latin1() {
+        assert_eq!(Script::detect("Rue de l'Église"), None);
+        assert_eq!(Script::detect("서울특별시 강남구"), Some(Script::Hangul));
+        assert_eq!(Script::detect("MOSCOU Москва"), Some(Script::Cyrillic));
+        assert_eq!(Script::detect("Łódź"), Some(Script::Latin));
+    }
+
+    #[cfg(feature = "translit")]
+    #[test]
+    fn it_should_transliterate_to_latin1() {
+        assert!(matches!(
+            transliterate("Rue de l'Église"),
+            Cow::Borrowed("Rue de l'Église")
+        ));
+        assert_eq!(transliterate("Москва"), "Moskva");
+        assert_eq!(transliterate("Łódź"), "Lódz");
+        assert_eq!(transliterate("강남구 152"), "gangnamgu 152");
+
+        let mut address: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": "Monsieur Ivan PETROV",
+            "street": "ул. Тверская 7",
+            "postal": "125009 MOSCOU",
+            "country": "RUSSIE",
+            "réf": "Москва",
+        }))
+        .unwrap();
+        assert_eq!(address.transliterate(), Some(Script::Cyrillic));
+        assert_eq!(address.clone().transliterate(), None);
+        let FrenchAddress::Individual(address) = address else {
+            panic!("expected an individual address");
+        };
+        assert_eq!(address.street.as_deref(), Some("ul. Tverskaia 7"));
+        assert_eq!(address.extras["réf"], "Москва");
+    }
+}
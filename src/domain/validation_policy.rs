@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::address::{ConvertedAddress, Country};
+use super::address_conversion::AddressConversionError;
+use super::address_quality::is_valid_town;
+use super::conversion_warnings::{ConversionWarning, Warnings};
+use super::french_department::OverseasTerritory;
+use super::validators::PostcodeValidators;
+
+/// How a failed validation is handled.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Deserialize,
+    strum_macros::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Severity {
+    /// The address is rejected.
+    #[default]
+    Error,
+    /// The address is accepted with a warning.
+    Warn,
+    /// The address is accepted silently.
+    Ignore,
+}
+
+/// The validations whose severity can be set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationCheck {
+    /// The postcode is valid for the country, see [`PostcodeValidators`].
+    Postcode,
+    /// The town only has letters, spaces, hyphens and apostrophes, and an
+    /// optional CEDEX mention.
+    Town,
+    /// The country line of a french address is the name of the country in
+    /// uppercase (e.g., `ALLEMAGNE` or `GERMANY`), not its code.
+    CountryLine,
+}
+
+impl fmt::Display for ValidationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValidationCheck::Postcode => "postcode",
+            ValidationCheck::Town => "town",
+            ValidationCheck::CountryLine => "country line",
+        })
+    }
+}
+
+/// The severity of each validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Severities {
+    pub postcode: Severity,
+    pub town: Severity,
+    pub country_line: Severity,
+}
+
+impl Default for Severities {
+    /// Invalid postcodes are rejected, the towns and the country lines only
+    /// warned about.
+    fn default() -> Self {
+        Self {
+            postcode: Severity::Error,
+            town: Severity::Warn,
+            country_line: Severity::Warn,
+        }
+    }
+}
+
+impl Severities {
+    pub fn get(&self, check: ValidationCheck) -> Severity {
+        match check {
+            ValidationCheck::Postcode => self.postcode,
+            ValidationCheck::Town => self.town,
+            ValidationCheck::CountryLine => self.country_line,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ValidationPolicyError {
+    #[error("Invalid validation config: {0}")]
+    Invalid(String),
+    #[error("Unknown country `{0}` in the validation config")]
+    UnknownCountry(String),
+}
+
+/// The severities set in the config, the unset ones being inherited.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeverityOverrides {
+    postcode: Option<Severity>,
+    town: Option<Severity>,
+    country_line: Option<Severity>,
+}
+
+impl SeverityOverrides {
+    fn apply(&self, severities: Severities) -> Severities {
+        Severities {
+            postcode: self.postcode.unwrap_or(severities.postcode),
+            town: self.town.unwrap_or(severities.town),
+            country_line: self.country_line.unwrap_or(severities.country_line),
+        }
+    }
+}
+
+/// The `validation` section of the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ValidationSection {
+    postcode: Option<Severity>,
+    town: Option<Severity>,
+    country_line: Option<Severity>,
+    #[serde(default)]
+    countries: HashMap<String, SeverityOverrides>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ValidationConfig {
+    #[serde(default)]
+    validation: ValidationSection,
+}
+
+/// The fields of an input holding the validated values, to report them.
+#[derive(Clone, Copy, Debug)]
+pub struct ValidatedFields<'a> {
+    pub postcode: &'a str,
+    pub town: &'a str,
+    /// The country line of a french address as read, `None` for the formats
+    /// writing the country as a code.
+    pub country_line: Option<(&'a str, &'a str)>,
+}
+
+/// The severity of the postcode, town and country line validations of the
+/// addresses read, per destination country. Legacy data can be stored while
+/// still being warned about, or checked strictly for some countries only:
+///
+/// ```
+/// use address_converter::domain::{Country, Severities, Severity, ValidationCheck, ValidationPolicy};
+///
+/// let policy = ValidationPolicy::new().with_country(
+///     "GB",
+///     Severities { postcode: Severity::Warn, ..Severities::default() },
+/// );
+/// assert_eq!(policy.severity(&Country::UnitedKingdom, ValidationCheck::Postcode), Severity::Warn);
+/// assert_eq!(policy.severity(&Country::France, ValidationCheck::Postcode), Severity::Error);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationPolicy {
+    default: Severities,
+    /// The severities by ISO 3166 alpha-2 country code.
+    countries: HashMap<String, Severities>,
+}
+
+impl ValidationPolicy {
+    /// The default severities for every country, see [`Severities::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the severities of the countries without their own.
+    pub fn with_default(mut self, severities: Severities) -> Self {
+        self.default = severities;
+        self
+    }
+
+    /// Sets the severities of the country with the ISO 3166 alpha-2
+    /// `iso_code`.
+    pub fn with_country(mut self, iso_code: &str, severities: Severities) -> Self {
+        self.countries.insert(iso_code.to_uppercase(), severities);
+        self
+    }
+
+    /// Reads the `validation` section of a JSON config. The severities not
+    /// set are the default ones, and the ones of a country not set are
+    /// inherited from the section:
+    ///
+    /// ```json
+    /// { "validation": { "town": "ignore", "countries": { "GB": { "postcode": "warn" } } } }
+    /// ```
+    ///
+    /// The countries are given by name or code, as the country lines.
+    pub fn from_config(json: &str) -> Result<Self, ValidationPolicyError> {
+        let config: ValidationConfig = serde_json::from_str(json)
+            .map_err(|e| ValidationPolicyError::Invalid(e.to_string()))?;
+        let section = config.validation;
+
+        let defaults = SeverityOverrides {
+            postcode: section.postcode,
+            town: section.town,
+            country_line: section.country_line,
+        };
+
+        let mut policy = Self::new().with_default(defaults.apply(Severities::default()));
+        for (country, overrides) in &section.countries {
+            let iso_code = Country::from_str(country)
+                .map_err(|_| ValidationPolicyError::UnknownCountry(country.clone()))?
+                .iso_code();
+            let severities = overrides.apply(policy.default);
+            policy = policy.with_country(iso_code, severities);
+        }
+
+        Ok(policy)
+    }
+
+    /// The severity of the `check` of the addresses sent to `country`.
+    pub fn severity(&self, country: &Country, check: ValidationCheck) -> Severity {
+        self.severities(country).get(check)
+    }
+
+    /// The severities of the addresses sent to `country`.
+    pub fn severities(&self, country: &Country) -> Severities {
+        *self
+            .countries
+            .get(country.iso_code())
+            .unwrap_or(&self.default)
+    }
+
+    /// Runs the validations of the address, rejecting it on the first failed
+    /// validation whose severity is error, and returning the warnings of the
+    /// ones whose severity is warn. A valid postcode is written in the
+    /// standard form of its country.
+    pub fn check(
+        &self,
+        address: &mut ConvertedAddress,
+        fields: ValidatedFields<'_>,
+        validators: &PostcodeValidators,
+    ) -> Result<Warnings, AddressConversionError> {
+        let severities = self.severities(&address.country);
+        let mut warnings = Warnings::new();
+        let mut report = |check: ValidationCheck, error: AddressConversionError| {
+            match severities.get(check) {
+                Severity::Error => return Err(error),
+                Severity::Warn => warnings.push(ConversionWarning::FailedValidation {
+                    check,
+                    field: error.field().to_string(),
+                    value: error.value().unwrap_or_default().to_string(),
+                }),
+                Severity::Ignore => {}
+            }
+            Ok(())
+        };
+
+        if let Some(validator) = validators.get(address.country.iso_code()) {
+            let postcode = validator.normalize(&address.postal_details.postcode);
+            if validator.is_valid(&postcode) {
+                address.postal_details.postcode = postcode;
+            } else {
+                report(
+                    ValidationCheck::Postcode,
+                    AddressConversionError::InvalidPostal {
+                        field: fields.postcode.to_string(),
+                        value: address.postal_details.postcode.clone(),
+                        reason: format!("Invalid postcode for {}", address.country),
+                    },
+                )?;
+            }
+        }
+
+        if !is_valid_town(&address.postal_details.town) {
+            report(
+                ValidationCheck::Town,
+                AddressConversionError::InvalidPostal {
+                    field: fields.town.to_string(),
+                    value: address.postal_details.town.clone(),
+                    reason: "The town should only have letters, spaces, hyphens and apostrophes"
+                        .to_string(),
+                },
+            )?;
+        }
+
+        if let Some((field, line)) = fields.country_line {
+            if !is_country_line(line, &address.country) {
+                report(
+                    ValidationCheck::CountryLine,
+                    AddressConversionError::InvalidValue {
+                        field: field.to_string(),
+                        value: line.to_string(),
+                        reason: format!(
+                            "The country line should be the name of the country in uppercase (e.g., '{}')",
+                            address.country
+                        ),
+                    },
+                )?;
+            }
+        }
+
+        Ok(warnings)
+    }
+}
+
+/// Whether `line` is the french or english name of the country, or the
+/// french name of an overseas territory of France.
+fn is_country_line(line: &str, country: &Country) -> bool {
+    let line = line.trim();
+    let overseas = *country == Country::France
+        && OverseasTerritory::from_str(line).is_ok_and(|territory| territory.to_string() == line);
+
+    line == country.to_string() || line == country.english_name() || overseas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+
+    fn address(postcode: &str, town: &str, country: Country) -> ConvertedAddress {
+        ConvertedAddress::new(
+            AddressKind::Individual,
+            Recipient::Individual {
+                name: "Monsieur Jean DELHOURME".to_string(),
+            },
+            None,
+            None,
+            PostalDetails {
+                postcode: postcode.to_string(),
+                town: town.to_string(),
+                town_location: None,
+                locality: None,
+                country_subdivision: None,
+            },
+            country,
+        )
+    }
+
+    const FIELDS: ValidatedFields = ValidatedFields {
+        postcode: "postal",
+        town: "postal",
+        country_line: Some(("country", "France")),
+    };
+
+    #[test]
+    fn it_should_apply_the_severities() {
+        let policy = ValidationPolicy::new();
+        let validators = PostcodeValidators::built_in();
+
+        let warnings = policy
+            .check(
+                &mut address("33380", "MIOS 2", Country::France),
+                FIELDS,
+                validators,
+            )
+            .unwrap();
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "postal: invalid town `MIOS 2`",
+                "country: invalid country line `France`"
+            ]
+        );
+        let error = policy
+            .check(
+                &mut address("3338", "MIOS", Country::France),
+                FIELDS,
+                validators,
+            )
+            .unwrap_err();
+        assert_eq!(error.code(), "E_POSTAL_FORMAT");
+
+        let lenient = policy.with_default(Severities {
+            postcode: Severity::Warn,
+            town: Severity::Ignore,
+            country_line: Severity::Ignore,
+        });
+        let warnings = lenient
+            .check(
+                &mut address("3338", "MIOS 2", Country::France),
+                FIELDS,
+                validators,
+            )
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn it_should_read_the_config() {
+        let policy = ValidationPolicy::from_config(
+            r#"{"validation": {"town": "ignore", "countries": {"ROYAUME-UNI": {"postcode": "warn"}}}}"#,
+        )
+        .unwrap();
+
+        let uk = Country::UnitedKingdom;
+        assert_eq!(
+            policy.severity(&uk, ValidationCheck::Postcode),
+            Severity::Warn
+        );
+        assert_eq!(
+            policy.severity(&uk, ValidationCheck::Town),
+            Severity::Ignore
+        );
+        assert_eq!(
+            policy.severity(&Country::France, ValidationCheck::Postcode),
+            Severity::Error
+        );
+        let mut london = address("SW1A1AA", "LONDON", uk);
+        policy
+            .check(&mut london, FIELDS, PostcodeValidators::built_in())
+            .unwrap();
+        assert_eq!(london.postal_details.postcode, "SW1A 1AA");
+
+        assert!(matches!(
+            ValidationPolicy::from_config(r#"{"validation": {"countries": {"XX": {}}}}"#),
+            Err(ValidationPolicyError::UnknownCountry(_))
+        ));
+        assert!(matches!(
+            ValidationPolicy::from_config(r#"{"validation": {"postcode": "strict"}}"#),
+            Err(ValidationPolicyError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn it_should_check_the_country_lines() {
+        assert!(is_country_line("FRANCE", &Country::France));
+        assert!(is_country_line("LA REUNION", &Country::France));
+        assert!(is_country_line("ALLEMAGNE", &Country::Germany));
+        assert!(is_country_line("GERMANY", &Country::Germany));
+        assert!(!is_country_line("DE", &Country::Germany));
+        assert!(!is_country_line("Allemagne", &Country::Germany));
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::address::{ConvertedAddress, Country};
+use super::address_conversion::AddressConversionError;
+
+/// The validators of the supported countries, shared by the parsing and the
+/// quality score.
+static BUILT_IN: Lazy<PostcodeValidators> = Lazy::new(PostcodeValidators::new);
+
+/// Checks the postcodes of a country.
+pub trait PostcodeValidator: Send + Sync {
+    /// Whether the normalized postcode is valid.
+    fn is_valid(&self, postcode: &str) -> bool;
+
+    /// Writes the postcode in its standard form, in uppercase with single
+    /// spaces by default.
+    fn normalize(&self, postcode: &str) -> String {
+        standard_form(postcode)
+    }
+}
+
+/// The postcode in uppercase with single spaces.
+fn standard_form(postcode: &str) -> String {
+    postcode
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+/// Any function checking a postcode is a validator, e.g.,
+/// `|postcode: &str| postcode.len() == 7`.
+impl<F> PostcodeValidator for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn is_valid(&self, postcode: &str) -> bool {
+        self(postcode)
+    }
+}
+
+/// The french postcodes: 5 digits starting with the number of a metropolitan
+/// department (01 to 95), or of an overseas one (971 to 976, 980 and 984 to
+/// 988).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrenchPostcodeValidator;
+
+impl PostcodeValidator for FrenchPostcodeValidator {
+    fn is_valid(&self, postcode: &str) -> bool {
+        if postcode.len() != 5 || !postcode.chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+
+        let department: u16 = postcode[..2].parse().unwrap_or_default();
+        let overseas: u16 = postcode[..3].parse().unwrap_or_default();
+
+        matches!(department, 1..=95) || matches!(overseas, 971..=976 | 980 | 984..=988)
+    }
+}
+
+/// Postcodes matching a regular expression (e.g., `^\d{4}$`).
+#[derive(Clone, Debug)]
+pub struct PatternValidator {
+    pattern: Regex,
+    /// The length of the inward code written apart, see
+    /// [`PatternValidator::with_inward_code`].
+    inward_len: Option<usize>,
+}
+
+impl PatternValidator {
+    pub fn new(pattern: Regex) -> Self {
+        Self {
+            pattern,
+            inward_len: None,
+        }
+    }
+
+    /// Writes the last `len` characters of the postcode apart when they are
+    /// stuck to the rest, as the inward code of the british and canadian
+    /// postcodes (e.g., `SW1A1AA` -> `SW1A 1AA`).
+    pub fn with_inward_code(mut self, len: usize) -> Self {
+        self.inward_len = Some(len);
+        self
+    }
+
+    fn built_in(pattern: &str) -> Self {
+        Self::new(Regex::new(pattern).unwrap())
+    }
+}
+
+impl PostcodeValidator for PatternValidator {
+    fn is_valid(&self, postcode: &str) -> bool {
+        self.pattern.is_match(postcode)
+    }
+
+    fn normalize(&self, postcode: &str) -> String {
+        let compact: String = postcode
+            .split_whitespace()
+            .collect::<String>()
+            .to_uppercase();
+
+        match self.inward_len {
+            Some(len) if compact.len() > len && compact.is_ascii() => {
+                let (outward, inward) = compact.split_at(compact.len() - len);
+                format!("{outward} {inward}")
+            }
+            _ => standard_form(postcode),
+        }
+    }
+}
+
+/// The postcode validators by ISO 3166 alpha-2 country code. Every supported
+/// [`Country`] has a built-in validator, which can be replaced, and
+/// validators can be registered for the other countries:
+///
+/// ```
+/// use address_converter::domain::PostcodeValidators;
+///
+/// let validators = PostcodeValidators::new()
+///     .with_validator("JP", |postcode: &str| {
+///         postcode.len() == 8 && postcode.chars().nth(3) == Some('-')
+///     });
+/// assert!(validators.get("jp").unwrap().is_valid("100-0001"));
+/// ```
+#[derive(Clone)]
+pub struct PostcodeValidators {
+    validators: HashMap<String, Arc<dyn PostcodeValidator>>,
+}
+
+impl Default for PostcodeValidators {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for PostcodeValidators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut countries: Vec<_> = self.validators.keys().collect();
+        countries.sort();
+
+        f.debug_struct("PostcodeValidators")
+            .field("countries", &countries)
+            .finish()
+    }
+}
+
+impl PostcodeValidators {
+    /// The validators of the supported countries.
+    pub fn new() -> Self {
+        let letter = "[ABCEGHJ-NPRSTV-Z]";
+        let canadian = format!(r"^[ABCEGHJ-NPRSTVXY]\d{letter} \d{letter}\d$");
+
+        Self::empty()
+            .with_validator("FR", FrenchPostcodeValidator)
+            .with_validator("DE", PatternValidator::built_in(r"^\d{5}$"))
+            .with_validator("AT", PatternValidator::built_in(r"^[1-9]\d{3}$"))
+            .with_validator("BE", PatternValidator::built_in(r"^[1-9]\d{3}$"))
+            .with_validator("BR", PatternValidator::built_in(r"^\d{5}-?\d{3}$"))
+            .with_validator(
+                "CA",
+                PatternValidator::built_in(&canadian).with_inward_code(3),
+            )
+            .with_validator(
+                "ES",
+                PatternValidator::built_in(r"^(0[1-9]|[1-4]\d|5[0-2])\d{3}$"),
+            )
+            .with_validator("US", PatternValidator::built_in(r"^\d{5}(-\d{4})?$"))
+            .with_validator("IT", PatternValidator::built_in(r"^\d{5}$"))
+            .with_validator("LU", PatternValidator::built_in(r"^(L-)?\d{4}$"))
+            .with_validator(
+                "NL",
+                PatternValidator::built_in(r"^[1-9]\d{3} [A-Z]{2}$").with_inward_code(2),
+            )
+            .with_validator("PT", PatternValidator::built_in(r"^\d{4}-\d{3}$"))
+            .with_validator(
+                "GB",
+                PatternValidator::built_in(r"^(GIR 0AA|[A-Z]{1,2}\d[A-Z\d]? \d[A-Z]{2})$")
+                    .with_inward_code(3),
+            )
+            .with_validator("CH", PatternValidator::built_in(r"^[1-9]\d{3}$"))
+            .with_validator("KR", PatternValidator::built_in(r"^\d{5}$"))
+    }
+
+    /// No validator: every postcode is accepted.
+    pub fn empty() -> Self {
+        Self {
+            validators: HashMap::new(),
+        }
+    }
+
+    /// The shared built-in validators, see [`PostcodeValidators::new`].
+    pub fn built_in() -> &'static Self {
+        &BUILT_IN
+    }
+
+    /// Registers the validator of the country with the ISO 3166 alpha-2
+    /// `iso_code`, replacing the built-in one.
+    pub fn with_validator(
+        mut self,
+        iso_code: &str,
+        validator: impl PostcodeValidator + 'static,
+    ) -> Self {
+        self.validators
+            .insert(iso_code.to_uppercase(), Arc::new(validator));
+        self
+    }
+
+    /// The validator of the country with the ISO 3166 alpha-2 `iso_code`.
+    pub fn get(&self, iso_code: &str) -> Option<&dyn PostcodeValidator> {
+        self.validators
+            .get(&iso_code.to_uppercase())
+            .map(|validator| validator.as_ref())
+    }
+
+    /// Whether the postcode, once normalized, is valid for the country. Any
+    /// postcode is valid for the countries without validator, as long as it
+    /// isn't empty.
+    pub fn is_valid(&self, country: &Country, postcode: &str) -> bool {
+        match self.get(country.iso_code()) {
+            Some(validator) => validator.is_valid(&validator.normalize(postcode)),
+            None => !postcode.trim().is_empty(),
+        }
+    }
+
+    /// Normalizes the postcode of the address, then checks it, reporting an
+    /// invalid postcode at `field`.
+    pub fn check(
+        &self,
+        address: &mut ConvertedAddress,
+        field: &str,
+    ) -> Result<(), AddressConversionError> {
+        let Some(validator) = self.get(address.country.iso_code()) else {
+            return Ok(());
+        };
+
+        let postcode = validator.normalize(&address.postal_details.postcode);
+        if !validator.is_valid(&postcode) {
+            return Err(AddressConversionError::InvalidPostal {
+                field: field.to_string(),
+                value: address.postal_details.postcode.clone(),
+                reason: format!("Invalid postcode for {}", address.country),
+            });
+        }
+
+        address.postal_details.postcode = postcode;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn french_postcodes() {
+        let validator = FrenchPostcodeValidator;
+
+        assert!(validator.is_valid("33380"));
+        assert!(validator.is_valid("01000"));
+        assert!(validator.is_valid("97400"));
+        assert!(!validator.is_valid("00100"));
+        assert!(!validator.is_valid("96000"));
+        assert!(!validator.is_valid("3338"));
+        assert!(!validator.is_valid("3338A"));
+    }
+
+    #[test]
+    fn built_in_postcodes() {
+        let validators = PostcodeValidators::built_in();
+        let valid = |country: Country, postcode: &str| validators.is_valid(&country, postcode);
+
+        assert!(valid(Country::UnitedKingdom, "SW1A 1AA"));
+        assert!(valid(Country::UnitedKingdom, "ec1a1bb"));
+        assert!(valid(Country::UnitedKingdom, "M1 1AE"));
+        assert!(!valid(Country::UnitedKingdom, "SW1A 1A"));
+        assert!(valid(Country::Canada, "K1A 0B1"));
+        assert!(!valid(Country::Canada, "D1A 0B1"));
+        assert!(valid(Country::Germany, "10115"));
+        assert!(!valid(Country::Germany, "1011"));
+        assert!(valid(Country::Netherlands, "1012 ab"));
+        assert!(valid(Country::Portugal, "1100-148"));
+        assert!(!valid(Country::Spain, "53001"));
+        assert!(valid(Country::UnitedStates, "10001-1234"));
+        assert!(valid(Country::Luxembourg, "L-1111"));
+        assert!(valid(Country::Austria, "1010"));
+        assert!(!valid(Country::Austria, "0100"));
+    }
+
+    #[test]
+    fn normalize_and_check() {
+        let validator = PostcodeValidators::built_in().get("gb").unwrap();
+        assert_eq!(validator.normalize(" sw1a1aa "), "SW1A 1AA");
+        assert_eq!(validator.normalize("M1 1AE"), "M1 1AE");
+        assert_eq!(
+            PostcodeValidators::built_in()
+                .get("NL")
+                .unwrap()
+                .normalize("1012ab"),
+            "1012 AB"
+        );
+
+        let validators = PostcodeValidators::empty()
+            .with_validator("fr", |postcode: &str| postcode.starts_with("33"));
+        assert!(validators.is_valid(&Country::France, "33380"));
+        assert!(!validators.is_valid(&Country::France, "75001"));
+        // No validator for Germany.
+        assert!(validators.is_valid(&Country::Germany, "ABC"));
+        assert!(!validators.is_valid(&Country::Germany, " "));
+    }
+}
@@ -0,0 +1,132 @@
+//! The single error type of the library, for the embedders handling the
+//! failures of every layer the same way.
+use thiserror::Error;
+
+use crate::application::service::AddressServiceError;
+use crate::domain::repositories::AddressRepositoryError;
+use crate::domain::{
+    AddressConversionError, FrenchParserRulesError, StreetReferentialError, ValidationPolicyError,
+};
+
+/// The category of an [`Error`], stable across releases. New categories may
+/// be added, so matches need a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input can't be read (malformed JSON, CSV or XML, invalid ID).
+    InvalidInput,
+    /// The input is read, but isn't a valid address (missing field, invalid
+    /// postal line, unknown country).
+    InvalidAddress,
+    /// No address has the given ID or alias.
+    NotFound,
+    /// The request conflicts with the stored state (duplicate, stale
+    /// version, alias in use, invalid status change).
+    Conflict,
+    /// The storage didn't answer in time or is down, retrying may succeed.
+    Unavailable,
+    /// The storage failed (I/O, corrupted record, backend error).
+    Storage,
+    /// The configuration is invalid (e.g., the french parser rules, the
+    /// validation policy, the street referential).
+    Config,
+}
+
+/// Any failure of the library. The errors of the service are flattened: a
+/// conversion failure is always [`Error::Conversion`] and a storage failure
+/// [`Error::Repository`], whichever layer raised them.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    Conversion(AddressConversionError),
+    #[error(transparent)]
+    Repository(AddressRepositoryError),
+    #[error(transparent)]
+    Service(AddressServiceError),
+    #[error(transparent)]
+    ParserRules(#[from] FrenchParserRulesError),
+    #[error(transparent)]
+    ValidationPolicy(#[from] ValidationPolicyError),
+    #[error(transparent)]
+    StreetReferential(#[from] StreetReferentialError),
+}
+
+/// Short hand for a `Result` failing with an [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// The category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Conversion(error) => error.kind(),
+            Error::Repository(error) => error.kind(),
+            Error::Service(error) => error.kind(),
+            Error::ParserRules(_) | Error::ValidationPolicy(_) | Error::StreetReferential(_) => {
+                ErrorKind::Config
+            }
+        }
+    }
+
+    /// The stable code of the error, e.g., `E_POSTAL_FORMAT`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Conversion(error) => error.code(),
+            Error::Repository(error) => error.code(),
+            Error::Service(error) => error.code(),
+            Error::ParserRules(_) | Error::ValidationPolicy(_) | Error::StreetReferential(_) => {
+                "E_INVALID_CONFIG"
+            }
+        }
+    }
+}
+
+impl From<AddressServiceError> for Error {
+    fn from(error: AddressServiceError) -> Self {
+        match error {
+            AddressServiceError::ConversionError(error) => Error::Conversion(error),
+            AddressServiceError::PersistenceError(error) => Error::Repository(error),
+            error => Error::Service(error),
+        }
+    }
+}
+
+impl From<AddressConversionError> for Error {
+    fn from(error: AddressConversionError) -> Self {
+        Error::Conversion(error)
+    }
+}
+
+impl From<AddressRepositoryError> for Error {
+    fn from(error: AddressRepositoryError) -> Self {
+        Error::Repository(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate_str, Format};
+
+    #[test]
+    fn service_errors_are_flattened() {
+        let error = Error::from(AddressServiceError::from(AddressConversionError::missing(
+            "postal",
+        )));
+        assert!(matches!(error, Error::Conversion(_)));
+        assert_eq!(error.kind(), ErrorKind::InvalidAddress);
+        assert_eq!(error.code(), "E_MISSING_FIELD");
+
+        let error = Error::from(AddressServiceError::from(AddressRepositoryError::NotFound(
+            "ea8bf423".to_string(),
+        )));
+        assert!(matches!(error, Error::Repository(_)));
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+
+        let error = validate_str("{", Format::French).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(error.code(), "E_INVALID_JSON");
+        assert_eq!(ErrorKind::InvalidInput.to_string(), "invalid_input");
+    }
+}
@@ -0,0 +1,278 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::repositories::{
+    AddressFilter, AddressRepository, AddressRepositoryError, Change, ChangeEvent,
+    CompactionReport, RepositoryResult,
+};
+use crate::domain::{Address, Fingerprint, SearchHit};
+
+/// A repository decorator appending every save, update and delete to a change
+/// log, so that downstream consumers replicate the addresses incrementally
+/// instead of exporting them all.
+///
+/// The log is a newline delimited JSON file, one [`ChangeEvent`] per line,
+/// numbered from 1 in the order of the changes. Consumers read it with
+/// [`AddressRepository::changes`], resuming after the sequence number of the
+/// last event they applied. Only one process should write to a log, the
+/// sequence numbers being kept in memory.
+pub struct ChangeLogRepository<R> {
+    repository: R,
+    path: PathBuf,
+    /// The log opened for appending, and the sequence number of its last
+    /// event. Held during the changes, so that they are logged in the order
+    /// they are stored.
+    log: Mutex<(File, u64)>,
+}
+
+fn backend(error: impl ToString) -> AddressRepositoryError {
+    AddressRepositoryError::Backend(error.to_string())
+}
+
+impl<R: AddressRepository> ChangeLogRepository<R> {
+    /// The name of the log in its directory.
+    pub const FILE_NAME: &'static str = "changes.ndjson";
+
+    /// Opens the log of the directory `dir`, created if needed, the next
+    /// events following the ones already logged.
+    pub fn open(repository: R, dir: impl AsRef<Path>) -> RepositoryResult<Self> {
+        fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(Self::FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let last = read_events(&path, 0, usize::MAX)?
+            .last()
+            .map_or(0, |event| event.sequence);
+
+        Ok(Self {
+            repository,
+            path,
+            log: Mutex::new((file, last)),
+        })
+    }
+
+    /// Applies `operation` to the wrapped repository and logs the change it
+    /// made, the log staying locked in between.
+    fn logged<T>(
+        &self,
+        operation: impl FnOnce(&R) -> RepositoryResult<(T, Change, Uuid, Option<Address>)>,
+    ) -> RepositoryResult<T> {
+        let mut log = self.log.lock().map_err(|_| backend("poisoned lock"))?;
+        let (result, change, id, address) = operation(&self.repository)?;
+
+        let event = ChangeEvent {
+            sequence: log.1 + 1,
+            at: Utc::now(),
+            change,
+            id,
+            address,
+        };
+        let mut line = serde_json::to_vec(&event)?;
+        line.push(b'\n');
+        // A single write, so that a reader never sees half an event followed
+        // by another one.
+        log.0.write_all(&line)?;
+        log.1 = event.sequence;
+
+        Ok(result)
+    }
+}
+
+/// The events of the log at `path` after the sequence number `after`, at most
+/// `limit`. A last line without newline is being written, and is left for the
+/// next read.
+fn read_events(path: &Path, after: u64, limit: usize) -> RepositoryResult<Vec<ChangeEvent>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut reader = BufReader::new(file);
+    let mut events = Vec::new();
+    let mut line = String::new();
+
+    while events.len() < limit {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+            break;
+        }
+        let event: ChangeEvent = serde_json::from_str(&line)?;
+        if event.sequence > after {
+            events.push(event);
+        }
+    }
+
+    Ok(events)
+}
+
+impl<R: AddressRepository> AddressRepository for ChangeLogRepository<R> {
+    fn save(&self, addr: Address) -> RepositoryResult<Uuid> {
+        self.logged(|repository| {
+            let id = repository.save(addr.clone())?;
+            Ok((id, Change::Saved, id, Some(addr)))
+        })
+    }
+
+    fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+        self.repository.fetch(id)
+    }
+
+    fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_all()
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        self.repository.fetch_ids()
+    }
+
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_updated_since(since)
+    }
+
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_filtered(filter)
+    }
+
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        self.repository.fetch_by_idempotency_key(key)
+    }
+
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        self.repository.fetch_by_alias(alias)
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        self.repository.find_by_fingerprint(fingerprint)
+    }
+
+    fn update(&self, addr: Address) -> RepositoryResult<()> {
+        self.logged(|repository| {
+            repository.update(addr.clone())?;
+            Ok(((), Change::Updated, addr.id(), Some(addr)))
+        })
+    }
+
+    fn delete(&self, id: &str) -> RepositoryResult<()> {
+        let uuid = Uuid::parse_str(id)?;
+        self.logged(|repository| {
+            repository.delete(id)?;
+            Ok(((), Change::Deleted, uuid, None))
+        })
+    }
+
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_history(id)
+    }
+
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        self.repository.compact()
+    }
+
+    fn search(&self, query: &str, limit: usize) -> RepositoryResult<Vec<SearchHit>> {
+        self.repository.search(query, limit)
+    }
+
+    fn reindex(&self) -> RepositoryResult<Option<usize>> {
+        self.repository.reindex()
+    }
+
+    fn ping(&self) -> RepositoryResult<()> {
+        self.repository.ping()
+    }
+
+    fn changes(&self, after: u64, limit: usize) -> RepositoryResult<Option<Vec<ChangeEvent>>> {
+        read_events(&self.path, after, limit).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::ChangeLogRepository;
+    use crate::domain::repositories::{AddressRepository, Change};
+    use crate::domain::{Address, AddressConvertible, ConvertedAddress, FrenchAddress};
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    fn address(street: &str) -> Address {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": "Monsieur Jean DELHOURME",
+            "street": street,
+            "postal": "33380 MIOS",
+            "country": "FRANCE",
+        }))
+        .unwrap();
+
+        Address::new(ConvertedAddress::from_french(french).unwrap())
+    }
+
+    #[test]
+    fn it_should_log_the_changes_in_order() {
+        let dir = TempDir::new().unwrap();
+        let store = InMemoryAddressRepository::new();
+        let repository = ChangeLogRepository::open(store, dir.path()).unwrap();
+
+        let id = repository.save(address("25 RUE DE L'EGLISE")).unwrap();
+        let mut updated = repository.fetch(&id.to_string()).unwrap();
+        updated.update(address("10 AVENUE DES CHAMPS").as_converted_address());
+        repository.update(updated.clone()).unwrap();
+        // A refused change isn't logged.
+        assert!(repository.update(updated).is_err());
+        repository.delete(&id.to_string()).unwrap();
+
+        let events = repository.changes(0, 100).unwrap().unwrap();
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.sequence, event.change, event.id))
+                .collect::<Vec<_>>(),
+            [
+                (1, Change::Saved, id),
+                (2, Change::Updated, id),
+                (3, Change::Deleted, id),
+            ]
+        );
+        assert_eq!(
+            events[1]
+                .address
+                .as_ref()
+                .unwrap()
+                .street
+                .as_ref()
+                .unwrap()
+                .name,
+            "AVENUE DES CHAMPS"
+        );
+        assert_eq!(events[2].address, None);
+
+        // Resumed after the last applied event, with the numbering kept on
+        // reopening.
+        let repository =
+            ChangeLogRepository::open(InMemoryAddressRepository::new(), dir.path()).unwrap();
+        let other = repository.save(address("3 RUE DES LILAS")).unwrap();
+        let events = repository.changes(2, 100).unwrap().unwrap();
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.sequence, event.id))
+                .collect::<Vec<_>>(),
+            [(3, id), (4, other)]
+        );
+        assert_eq!(repository.changes(0, 1).unwrap().unwrap().len(), 1);
+
+        // An event being written is left for the next read.
+        let log = dir
+            .path()
+            .join(ChangeLogRepository::<InMemoryAddressRepository>::FILE_NAME);
+        let mut content = fs::read_to_string(&log).unwrap();
+        content.push_str(r#"{"sequence": 5, "at""#);
+        fs::write(&log, content).unwrap();
+        assert_eq!(repository.changes(4, 100).unwrap().unwrap(), []);
+    }
+}
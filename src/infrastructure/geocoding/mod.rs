@@ -0,0 +1,527 @@
+//! Client of the BAN geocoder (Base Adresse Nationale,
+//! <https://adresse.data.gouv.fr>), used to enrich the stored addresses with
+//! their coordinates.
+//!
+//! The public API is rate limited, so every request goes through a
+//! [`RateLimiter`], and the requests failing with a network error, a `429` or a
+//! `5xx` status are retried following a [`RetryPolicy`]. Bulk enrichment uses
+//! the CSV endpoint, geocoding up to `batch_size` addresses per request.
+
+mod policy;
+mod transport;
+
+use std::collections::HashMap;
+use std::thread;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::domain::{Address, NumberExtStyle};
+
+pub use self::policy::{RateLimiter, RetryPolicy};
+pub use self::transport::{HttpResponse, HttpTransport, UreqTransport};
+
+/// The BAN allows 50 requests per second and per IP, stay well under.
+pub const DEFAULT_REQUESTS_PER_SECOND: u32 = 10;
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+const BAN_URL: &str = "https://api-adresse.data.gouv.fr";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum GeocodingError {
+    #[error("Geocoder unreachable: {0}")]
+    Transport(String),
+    #[error("Geocoder responded with status {0}")]
+    Status(u16),
+    #[error("Invalid geocoder response: {0}")]
+    InvalidResponse(String),
+}
+
+impl GeocodingError {
+    /// Network failures, throttling and server errors are worth retrying.
+    fn is_retryable(&self) -> bool {
+        match self {
+            GeocodingError::Transport(_) => true,
+            GeocodingError::Status(status) => *status == 429 || *status >= 500,
+            GeocodingError::InvalidResponse(_) => false,
+        }
+    }
+}
+
+/// Short hand for `Result` type.
+pub type GeocodingResult<T> = std::result::Result<T, GeocodingError>;
+
+/// The best match of the geocoder for an address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeocodedAddress {
+    /// The BAN identifier of the matched address.
+    pub ban_id: String,
+    /// The matched address (e.g., "25 Rue de l'Eglise 33380 Mios").
+    pub label: String,
+    /// Confidence of the match, from 0 to 1.
+    pub score: f64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    features: Vec<Feature>,
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    geometry: Geometry,
+    properties: FeatureProperties,
+}
+
+#[derive(Deserialize)]
+struct Geometry {
+    /// Longitude then latitude.
+    coordinates: (f64, f64),
+}
+
+#[derive(Deserialize)]
+struct FeatureProperties {
+    id: String,
+    label: String,
+    score: f64,
+}
+
+pub struct BanGeocoder {
+    base_url: String,
+    transport: Box<dyn HttpTransport>,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    batch_size: usize,
+}
+
+impl Default for BanGeocoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BanGeocoder {
+    pub fn new() -> Self {
+        Self {
+            base_url: BAN_URL.to_string(),
+            transport: Box::new(UreqTransport::default()),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: RateLimiter::new(DEFAULT_REQUESTS_PER_SECOND),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// Targets another instance of the BAN API, e.g. a self-hosted one.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Box<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limiter = RateLimiter::new(requests_per_second);
+        self
+    }
+
+    /// Number of addresses sent in each request of [`Self::geocode_batch`].
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Geocodes a single address. Returns `None` when nothing matches.
+    pub fn geocode(&self, address: &Address) -> GeocodingResult<Option<GeocodedAddress>> {
+        let url = format!("{}/search/", self.base_url);
+        let query = Self::query(address);
+        let params = [
+            ("q", query.as_str()),
+            ("postcode", address.postal_details.postcode.as_str()),
+            ("limit", "1"),
+        ];
+
+        let body = self.send(|| self.transport.get(&url, &params))?;
+        let response: SearchResponse = serde_json::from_str(&body)
+            .map_err(|e| GeocodingError::InvalidResponse(e.to_string()))?;
+
+        Ok(response.features.into_iter().next().map(|feature| {
+            let (longitude, latitude) = feature.geometry.coordinates;
+
+            GeocodedAddress {
+                ban_id: feature.properties.id,
+                label: feature.properties.label,
+                score: feature.properties.score,
+                latitude,
+                longitude,
+            }
+        }))
+    }
+
+    /// Geocodes the addresses through the CSV endpoint. The results are in the
+    /// order of the addresses, `None` when nothing matches.
+    pub fn geocode_batch(
+        &self,
+        addresses: &[Address],
+    ) -> GeocodingResult<Vec<Option<GeocodedAddress>>> {
+        let url = format!("{}/search/csv/", self.base_url);
+        let fields = [("columns", "q"), ("postcode", "postcode")];
+        let mut results = Vec::with_capacity(addresses.len());
+
+        for chunk in addresses.chunks(self.batch_size) {
+            let csv = Self::batch_csv(chunk)?;
+            let body = self.send(|| self.transport.post_csv(&url, &csv, &fields))?;
+            let mut geocoded = Self::parse_batch(&body)?;
+
+            results.extend(
+                chunk
+                    .iter()
+                    .map(|address| geocoded.remove(&address.id().to_string())),
+            );
+        }
+
+        Ok(results)
+    }
+
+    /// Sends a request, waiting for the rate limiter, until it succeeds or the
+    /// retries are exhausted.
+    fn send<F>(&self, request: F) -> GeocodingResult<String>
+    where
+        F: Fn() -> GeocodingResult<HttpResponse>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            self.rate_limiter.acquire();
+
+            let (error, retry_after) = match request() {
+                Ok(response) if (200..300).contains(&response.status) => return Ok(response.body),
+                Ok(response) => (
+                    GeocodingError::Status(response.status),
+                    response.retry_after,
+                ),
+                Err(e) => (e, None),
+            };
+
+            if attempt >= self.retry_policy.max_retries || !error.is_retryable() {
+                return Err(error);
+            }
+
+            // The delay requested by the server prevails over the backoff.
+            thread::sleep(retry_after.unwrap_or_else(|| self.retry_policy.backoff(attempt)));
+            attempt += 1;
+        }
+    }
+
+    /// The street (or lieu-dit), postcode and town of the address.
+    fn query(address: &Address) -> String {
+        let street = address.street.as_ref().map(|street| {
+            match street.full_number(NumberExtStyle::Spaced) {
+                Some(number) => format!("{number} {}", street.name),
+                None => street.name.clone(),
+            }
+        });
+        let postal = format!(
+            "{} {}",
+            address.postal_details.postcode, address.postal_details.town
+        );
+
+        [
+            street,
+            address.postal_details.locality.clone(),
+            Some(postal),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+
+    fn batch_csv(addresses: &[Address]) -> GeocodingResult<String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        let invalid = |e: csv::Error| GeocodingError::InvalidResponse(e.to_string());
+
+        writer
+            .write_record(["id", "q", "postcode"])
+            .map_err(invalid)?;
+        for address in addresses {
+            writer
+                .write_record([
+                    address.id().to_string(),
+                    Self::query(address),
+                    address.postal_details.postcode.clone(),
+                ])
+                .map_err(invalid)?;
+        }
+
+        let csv = writer
+            .into_inner()
+            .map_err(|e| GeocodingError::InvalidResponse(e.to_string()))?;
+
+        Ok(String::from_utf8_lossy(&csv).to_string())
+    }
+
+    /// Reads the results of the CSV endpoint, by address ID. The unmatched
+    /// addresses have empty result columns.
+    fn parse_batch(body: &str) -> GeocodingResult<HashMap<String, GeocodedAddress>> {
+        let invalid = |e: &dyn std::fmt::Display| GeocodingError::InvalidResponse(e.to_string());
+        let mut reader = csv::Reader::from_reader(body.as_bytes());
+        let mut results = HashMap::new();
+
+        for record in reader.deserialize::<HashMap<String, String>>() {
+            let record = record.map_err(|e| invalid(&e))?;
+            let column = |name: &str| {
+                record
+                    .get(name)
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| invalid(&format!("missing column `{name}`")))
+            };
+
+            let id = column("id")?.clone();
+            // An unmatched address has no coordinates.
+            let Ok(latitude) = column("latitude") else {
+                continue;
+            };
+
+            let geocoded = GeocodedAddress {
+                ban_id: column("result_id")?.clone(),
+                label: column("result_label")?.clone(),
+                score: column("result_score")?.parse().map_err(|e| invalid(&e))?,
+                latitude: latitude.parse().map_err(|e| invalid(&e))?,
+                longitude: column("longitude")?.parse().map_err(|e| invalid(&e))?,
+            };
+            results.insert(id, geocoded);
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{
+        BanGeocoder, GeocodedAddress, GeocodingError, HttpResponse, HttpTransport, RetryPolicy,
+    };
+    use crate::domain::*;
+
+    /// Replays the given responses and records the requests.
+    #[derive(Clone, Default)]
+    struct FakeTransport {
+        responses: Arc<Mutex<Vec<Result<HttpResponse, GeocodingError>>>>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeTransport {
+        fn new(responses: Vec<Result<HttpResponse, GeocodingError>>) -> Self {
+            Self {
+                responses: Arc::new(Mutex::new(responses)),
+                ..Default::default()
+            }
+        }
+
+        fn next(&self, request: String) -> Result<HttpResponse, GeocodingError> {
+            self.requests.lock().unwrap().push(request);
+            self.responses.lock().unwrap().remove(0)
+        }
+    }
+
+    impl HttpTransport for FakeTransport {
+        fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<HttpResponse, GeocodingError> {
+            self.next(format!("GET {url} {query:?}"))
+        }
+
+        fn post_csv(
+            &self,
+            url: &str,
+            csv: &str,
+            _fields: &[(&str, &str)],
+        ) -> Result<HttpResponse, GeocodingError> {
+            self.next(format!("POST {url}\n{csv}"))
+        }
+    }
+
+    fn ok(body: &str) -> Result<HttpResponse, GeocodingError> {
+        Ok(HttpResponse {
+            status: 200,
+            retry_after: None,
+            body: body.to_string(),
+        })
+    }
+
+    fn status(status: u16) -> Result<HttpResponse, GeocodingError> {
+        Ok(HttpResponse {
+            status,
+            retry_after: None,
+            body: String::new(),
+        })
+    }
+
+    fn geocoder(transport: &FakeTransport) -> BanGeocoder {
+        BanGeocoder::new()
+            .with_transport(Box::new(transport.clone()))
+            .with_rate_limit(1000)
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(10),
+            })
+    }
+
+    fn address(street: &str, postal: &str) -> Address {
+        let (postcode, town) = postal.split_once(' ').unwrap();
+
+        Address::new(ConvertedAddress::new(
+            AddressKind::Individual,
+            Recipient::Individual {
+                name: "Monsieur Jean DELHOURME".to_string(),
+            },
+            None,
+            Some(FrenchAddressParser::parse_street(street).unwrap()),
+            PostalDetails {
+                postcode: postcode.to_string(),
+                town: town.to_string(),
+                town_location: None,
+                locality: None,
+                country_subdivision: None,
+            },
+            Country::France,
+        ))
+    }
+
+    const SEARCH_RESPONSE: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [{
+            "type": "Feature",
+            "geometry": {"type": "Point", "coordinates": [-0.93, 44.6]},
+            "properties": {"id": "33284_0270_00025", "label": "25 Rue de l'Eglise 33380 Mios", "score": 0.97}
+        }]
+    }"#;
+
+    #[test]
+    fn geocode_address() {
+        let transport = FakeTransport::new(vec![ok(SEARCH_RESPONSE)]);
+        let address = address("25 RUE DE L'EGLISE", "33380 MIOS");
+
+        let geocoded = geocoder(&transport).geocode(&address).unwrap();
+
+        assert_eq!(
+            geocoded,
+            Some(GeocodedAddress {
+                ban_id: "33284_0270_00025".to_string(),
+                label: "25 Rue de l'Eglise 33380 Mios".to_string(),
+                score: 0.97,
+                latitude: 44.6,
+                longitude: -0.93,
+            })
+        );
+        assert!(transport.requests.lock().unwrap()[0]
+            .contains(r#"("q", "25 RUE DE L'EGLISE 33380 MIOS")"#));
+    }
+
+    #[test]
+    fn geocode_without_match() {
+        let transport = FakeTransport::new(vec![ok(r#"{"features": []}"#)]);
+        let address = address("25 RUE DE L'EGLISE", "33380 MIOS");
+
+        assert_eq!(geocoder(&transport).geocode(&address), Ok(None));
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let transport = FakeTransport::new(vec![
+            status(503),
+            Err(GeocodingError::Transport("connection reset".to_string())),
+            ok(SEARCH_RESPONSE),
+        ]);
+        let address = address("25 RUE DE L'EGLISE", "33380 MIOS");
+
+        assert!(geocoder(&transport).geocode(&address).unwrap().is_some());
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let transport = FakeTransport::new(vec![status(429), status(429), status(429)]);
+        let address = address("25 RUE DE L'EGLISE", "33380 MIOS");
+
+        assert_eq!(
+            geocoder(&transport).geocode(&address),
+            Err(GeocodingError::Status(429))
+        );
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let transport = FakeTransport::new(vec![status(400)]);
+        let address = address("25 RUE DE L'EGLISE", "33380 MIOS");
+
+        assert_eq!(
+            geocoder(&transport).geocode(&address),
+            Err(GeocodingError::Status(400))
+        );
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn geocode_batch_in_chunks() {
+        let addresses = [
+            address("25 RUE DE L'EGLISE", "33380 MIOS"),
+            address("LE VILLAGE", "82500 AUTERIVE"),
+            address("3 RUE DE LA PAIX", "33000 BORDEAUX"),
+        ];
+        let response = |rows: &[(&Address, Option<&str>)]| {
+            let mut body = "id,q,postcode,latitude,longitude,result_label,result_score,result_id\n"
+                .to_string();
+            for (address, result) in rows {
+                body.push_str(&format!(
+                    "{},query,{},{}\n",
+                    address.id(),
+                    address.postal_details.postcode,
+                    result.unwrap_or(",,,,")
+                ));
+            }
+            ok(&body)
+        };
+        let transport = FakeTransport::new(vec![
+            response(&[
+                (&addresses[1], None),
+                (
+                    &addresses[0],
+                    Some("44.6,-0.93,25 Rue de l'Eglise 33380 Mios,0.97,ban-1"),
+                ),
+            ]),
+            response(&[(
+                &addresses[2],
+                Some("44.84,-0.57,3 Rue de la Paix 33000 Bordeaux,0.9,ban-3"),
+            )]),
+        ]);
+
+        let results = geocoder(&transport)
+            .with_batch_size(2)
+            .geocode_batch(&addresses)
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().ban_id, "ban-1");
+        assert_eq!(results[1], None);
+        assert_eq!(results[2].as_ref().unwrap().latitude, 44.84);
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]
+            .starts_with("POST https://api-adresse.data.gouv.fr/search/csv/\nid,q,postcode\n"));
+        assert!(requests[0].contains("LE VILLAGE 82500 AUTERIVE"));
+    }
+}
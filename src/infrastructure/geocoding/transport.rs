@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use super::GeocodingError;
+
+/// Timeout of a single HTTP request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Boundary of the multipart bodies, not expected in the uploaded CSV.
+const MULTIPART_BOUNDARY: &str = "----address-converter-geocoding-boundary";
+
+/// A raw HTTP response. Error statuses are returned as responses, only
+/// network failures are errors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// The delay requested by the server in the `Retry-After` header.
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+/// The HTTP calls of the geocoder, so that they can be replaced in tests.
+pub trait HttpTransport: Send + Sync {
+    fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<HttpResponse, GeocodingError>;
+
+    /// Uploads a CSV file as the `data` field of a multipart form, along with
+    /// the other form fields.
+    fn post_csv(
+        &self,
+        url: &str,
+        csv: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<HttpResponse, GeocodingError>;
+}
+
+/// The default blocking transport.
+pub struct UreqTransport {
+    agent: ureq::Agent,
+}
+
+impl Default for UreqTransport {
+    fn default() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+}
+
+impl UreqTransport {
+    fn response(
+        result: Result<ureq::Response, ureq::Error>,
+    ) -> Result<HttpResponse, GeocodingError> {
+        let response = match result {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(e)) => return Err(GeocodingError::Transport(e.to_string())),
+        };
+
+        let status = response.status();
+        let retry_after = response
+            .header("Retry-After")
+            .and_then(|seconds| seconds.trim().parse().ok())
+            .map(Duration::from_secs);
+        let body = response
+            .into_string()
+            .map_err(|e| GeocodingError::Transport(e.to_string()))?;
+
+        Ok(HttpResponse {
+            status,
+            retry_after,
+            body,
+        })
+    }
+}
+
+impl HttpTransport for UreqTransport {
+    fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<HttpResponse, GeocodingError> {
+        let request = query
+            .iter()
+            .fold(self.agent.get(url), |request, (key, value)| {
+                request.query(key, value)
+            });
+
+        Self::response(request.call())
+    }
+
+    fn post_csv(
+        &self,
+        url: &str,
+        csv: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<HttpResponse, GeocodingError> {
+        let mut body = String::new();
+        for (name, value) in fields {
+            body.push_str(&format!(
+                "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            ));
+        }
+        body.push_str(&format!(
+            "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"data\"; filename=\"addresses.csv\"\r\nContent-Type: text/csv\r\n\r\n{csv}\r\n--{MULTIPART_BOUNDARY}--\r\n"
+        ));
+
+        let request = self.agent.post(url).set(
+            "Content-Type",
+            &format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}"),
+        );
+
+        Self::response(request.send_string(&body))
+    }
+}
@@ -1,20 +1,34 @@
 use uuid::Uuid;
 
-use crate::domain::repositories::{AddressRepository, AddressRepositoryError, RepositoryResult};
-use crate::domain::Address;
+use crate::domain::repositories::{
+    check_version, AddressRepository, AddressRepositoryError, RepositoryResult,
+};
+use crate::domain::{Address, DuplicateScope};
 use std::cell::RefCell;
 use std::collections::HashMap;
 
 pub struct InMemoryAddressRepository {
     addresses: RefCell<HashMap<String, Address>>,
+    /// The versions replaced by the updates of each address, oldest first.
+    history: RefCell<HashMap<String, Vec<Address>>>,
+    duplicate_scope: DuplicateScope,
 }
 
 impl InMemoryAddressRepository {
     pub fn new() -> Self {
         Self {
             addresses: RefCell::new(HashMap::new()),
+            history: RefCell::new(HashMap::new()),
+            duplicate_scope: DuplicateScope::default(),
         }
     }
+
+    /// Refuses the addresses duplicating a stored one within `scope`,
+    /// [`DuplicateScope::Recipient`] by default.
+    pub fn with_duplicate_scope(mut self, scope: DuplicateScope) -> Self {
+        self.duplicate_scope = scope;
+        self
+    }
 }
 
 impl Default for InMemoryAddressRepository {
@@ -36,11 +50,9 @@ impl AddressRepository for InMemoryAddressRepository {
 
         // Check for address duplicates
         let all_addresses = self.fetch_all()?;
-        let duplication_check = all_addresses.iter().find(|existing| {
-            existing.street == addr.street
-                && existing.postal_details.postcode == addr.postal_details.postcode
-                && existing.country == addr.country
-        });
+        let duplication_check = all_addresses
+            .iter()
+            .find(|existing| existing.is_duplicate_in(&addr, self.duplicate_scope));
 
         if let Some(duplicated_addr) = duplication_check {
             return Err(AddressRepositoryError::AlreadyExists(
@@ -71,11 +83,18 @@ impl AddressRepository for InMemoryAddressRepository {
         let mut addresses = self.addresses.borrow_mut();
         let id = addr.id().to_string();
 
-        if addresses.get(&id).is_none() {
-            return Err(AddressRepositoryError::NotFound(id));
+        match addresses.get(&id) {
+            Some(stored) => check_version(stored, &addr)?,
+            None => return Err(AddressRepositoryError::NotFound(id)),
         }
 
-        addresses.insert(id, addr);
+        if let Some(previous) = addresses.insert(id.clone(), addr) {
+            self.history
+                .borrow_mut()
+                .entry(id)
+                .or_default()
+                .push(previous);
+        }
 
         Ok(())
     }
@@ -89,7 +108,15 @@ impl AddressRepository for InMemoryAddressRepository {
         }
 
         addresses.remove(&id);
+        self.history.borrow_mut().remove(&id);
 
         Ok(())
     }
+
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        let mut versions = self.history.borrow().get(id).cloned().unwrap_or_default();
+        versions.push(self.fetch(id)?);
+
+        Ok(versions)
+    }
 }
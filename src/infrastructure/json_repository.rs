@@ -1,9 +1,14 @@
-use crate::domain::repositories::{AddressRepository, AddressRepositoryError, RepositoryResult};
-use crate::domain::Address;
+use crate::domain::repositories::{
+    check_version, AddressRepository, AddressRepositoryError, CompactionReport, RepositoryResult,
+    Tenant,
+};
+use crate::domain::{Address, DuplicateScope};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io;
-use std::path::PathBuf;
+use std::fs::{self, DirEntry, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use strum::EnumString;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,20 +17,252 @@ struct StoredAddress {
     address: Address,
 }
 
+/// The directory of the history files, `<uuid>.jsonl` holding the versions
+/// replaced by the updates of an address, one per line, oldest first. The
+/// dot keeps it apart from the tenants.
+const HISTORY_DIR: &str = ".history";
+
+/// How the address files are laid out in the storage directory.
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum StorageLayout {
+    /// `<uuid>.json`, all the files in the storage directory.
+    #[default]
+    Flat,
+    /// `ab/cd/<uuid>.json`, in subdirectories named after the first four
+    /// hexadecimal digits of the UUID, for the large storages.
+    Sharded,
+}
+
+impl StorageLayout {
+    fn other(self) -> Self {
+        match self {
+            StorageLayout::Flat => StorageLayout::Sharded,
+            StorageLayout::Sharded => StorageLayout::Flat,
+        }
+    }
+}
+
+/// How safely the address files are written, a crash or a power loss during
+/// a write leaving a truncated file with [`Durability::Fast`].
+#[derive(Clone, Copy, Debug, Default, strum_macros::Display, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Durability {
+    /// The file is written in place, for the batch loads preferring speed.
+    Fast,
+    /// The file is written next to its final path, flushed to the disk, then
+    /// renamed over it: the file holds either the previous or the new address.
+    #[default]
+    Atomic,
+    /// Same as [`Durability::Atomic`], the directory also flushed so that the
+    /// rename itself survives a power loss.
+    Synced,
+}
+
+impl Durability {
+    fn write(self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if self == Durability::Fast {
+            return fs::write(path, contents);
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        let written = File::create(&temp_path).and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        // Windows can't open a directory to flush it.
+        #[cfg(unix)]
+        if self == Durability::Synced {
+            if let Some(parent) = path.parent() {
+                File::open(parent)?.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct JsonAddressRepository {
     dir: PathBuf,
+    layout: StorageLayout,
+    durability: Durability,
+    duplicate_scope: DuplicateScope,
 }
 
 impl JsonAddressRepository {
     pub fn new(dir: impl Into<PathBuf>) -> Self {
         let dir = dir.into();
         fs::create_dir_all(&dir).expect("Failed to create JSON storage directory");
-        Self { dir }
+        Self {
+            dir,
+            layout: StorageLayout::default(),
+            durability: Durability::default(),
+            duplicate_scope: DuplicateScope::default(),
+        }
+    }
+
+    /// Refuses the addresses duplicating a stored one within `scope`,
+    /// [`DuplicateScope::Recipient`] by default.
+    pub fn with_duplicate_scope(mut self, scope: DuplicateScope) -> Self {
+        self.duplicate_scope = scope;
+        self
+    }
+
+    /// Writes the address files with `durability`, [`Durability::Atomic`] by
+    /// default.
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Lays the files out according to `layout`, moving the files stored with
+    /// the other layout, so that a storage can switch between both.
+    pub fn with_layout(mut self, layout: StorageLayout) -> RepositoryResult<Self> {
+        self.layout = layout;
+        let previous = layout.other();
+
+        for dir_entry in json_entries(&self.dir, previous)? {
+            let path = dir_entry.path();
+            if let Some(id) = file_id(&path) {
+                let new_path = self.file_path(&id);
+                if let Some(parent) = new_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&path, new_path)?;
+            }
+        }
+        if previous == StorageLayout::Sharded {
+            remove_empty_shards(&self.dir)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Stores the addresses of `tenant` in its own subdirectory of `dir`,
+    /// apart from the addresses stored without tenant and the other tenants.
+    pub fn for_tenant(dir: impl Into<PathBuf>, tenant: &Tenant) -> Self {
+        Self::new(dir.into().join(tenant.as_str()))
     }
 
     fn file_path(&self, id: &Uuid) -> PathBuf {
-        self.dir.join(format!("{id}.json"))
+        let file_name = format!("{id}.json");
+        match self.layout {
+            StorageLayout::Flat => self.dir.join(file_name),
+            StorageLayout::Sharded => {
+                let hex = id.simple().to_string();
+                self.dir.join(&hex[..2]).join(&hex[2..4]).join(file_name)
+            }
+        }
+    }
+
+    fn history_path(&self, id: &Uuid) -> PathBuf {
+        self.dir.join(HISTORY_DIR).join(format!("{id}.jsonl"))
+    }
+
+    /// Appends the version replaced by an update to the history file of the
+    /// address.
+    fn append_history(&self, stored: &StoredAddress) -> RepositoryResult<()> {
+        let path = self.history_path(&stored.id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_vec(stored)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(&line)?;
+        if self.durability != Durability::Fast {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the file of the address, and creates its shard if needed.
+    fn write_file(&self, stored: &StoredAddress) -> RepositoryResult<()> {
+        let path = self.file_path(&stored.id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(self.durability.write(&path, &serde_json::to_vec(stored)?)?)
+    }
+}
+
+/// The identifier of an address file, named after it.
+fn file_id(path: &Path) -> Option<Uuid> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| Uuid::parse_str(stem).ok())
+}
+
+fn read_stored(path: &Path) -> RepositoryResult<StoredAddress> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Removes the emptied shards of `dir`, returning how many directories were
+/// removed. The shards still holding files are kept.
+fn remove_empty_shards(dir: &Path) -> io::Result<usize> {
+    let mut removed = 0;
+
+    for shard in shards(dir)? {
+        for sub_shard in shards(&shard)? {
+            removed += usize::from(fs::remove_dir(sub_shard).is_ok());
+        }
+        removed += usize::from(fs::remove_dir(shard).is_ok());
+    }
+    Ok(removed)
+}
+
+/// The subdirectories of `dir` named like a shard, two hexadecimal digits.
+/// The other subdirectories, such as the tenants, are left out.
+fn shards(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut shards = Vec::new();
+
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        let is_shard = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit()));
+
+        if is_shard && path.is_dir() {
+            shards.push(path);
+        }
     }
+    Ok(shards)
+}
+
+/// The JSON files of `dir` stored with `layout`.
+fn json_entries(dir: &Path, layout: StorageLayout) -> io::Result<Vec<DirEntry>> {
+    let dirs = match layout {
+        StorageLayout::Flat => vec![dir.to_path_buf()],
+        StorageLayout::Sharded => {
+            let mut dirs = Vec::new();
+            for shard in shards(dir)? {
+                dirs.extend(shards(&shard)?);
+            }
+            dirs
+        }
+    };
+
+    let mut entries = Vec::new();
+    for dir in dirs {
+        for dir_entry in fs::read_dir(dir)? {
+            let dir_entry = dir_entry?;
+            if dir_entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "json")
+            {
+                entries.push(dir_entry);
+            }
+        }
+    }
+    Ok(entries)
 }
 
 impl AddressRepository for JsonAddressRepository {
@@ -41,11 +278,9 @@ impl AddressRepository for JsonAddressRepository {
 
         // Prevent address duplication
         let all_addresses = self.fetch_all()?;
-        let duplication_check = all_addresses.iter().find(|existing| {
-            existing.street == addr.street
-                && existing.postal_details.postcode == addr.postal_details.postcode
-                && existing.country == addr.country
-        });
+        let duplication_check = all_addresses
+            .iter()
+            .find(|existing| existing.is_duplicate_in(&addr, self.duplicate_scope));
 
         if let Some(duplicated_addr) = duplication_check {
             return Err(AddressRepositoryError::AlreadyExists(
@@ -53,8 +288,7 @@ impl AddressRepository for JsonAddressRepository {
             ));
         }
 
-        let file = File::create(self.file_path(&id))?;
-        serde_json::to_writer(file, &StoredAddress { id, address: addr })?;
+        self.write_file(&StoredAddress { id, address: addr })?;
 
         Ok(id)
     }
@@ -79,12 +313,38 @@ impl AddressRepository for JsonAddressRepository {
     fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
         let mut addresses = Vec::new();
 
-        for dir_entry in fs::read_dir(&self.dir)? {
-            let path = dir_entry?.path();
+        for dir_entry in json_entries(&self.dir, self.layout)? {
+            let file = File::open(dir_entry.path())?;
+            let stored: StoredAddress = serde_json::from_reader(file)?;
+            addresses.push(stored.address);
+        }
+        Ok(addresses)
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        let mut ids = Vec::new();
+
+        for dir_entry in json_entries(&self.dir, self.layout)? {
+            ids.extend(file_id(&dir_entry.path()));
+        }
+        Ok(ids)
+    }
 
-            if path.extension().is_some_and(|ext| ext == "json") {
-                let file = File::open(&path)?;
-                let stored: StoredAddress = serde_json::from_reader(file)?;
+    /// Files are written after the update date is set, so the files last
+    /// modified before `since` are skipped without being read.
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        let mut addresses = Vec::new();
+
+        for dir_entry in json_entries(&self.dir, self.layout)? {
+            let path = dir_entry.path();
+            let modified = DateTime::<Utc>::from(dir_entry.metadata()?.modified()?);
+            if modified < since {
+                continue;
+            }
+
+            let file = File::open(&path)?;
+            let stored: StoredAddress = serde_json::from_reader(file)?;
+            if stored.address.updated_at() >= since {
                 addresses.push(stored.address);
             }
         }
@@ -93,9 +353,13 @@ impl AddressRepository for JsonAddressRepository {
 
     fn update(&self, addr: Address) -> RepositoryResult<()> {
         let id = addr.id();
-        let stored = StoredAddress { id, address: addr };
-        let file = File::create(self.file_path(&id))?;
-        serde_json::to_writer(file, &stored)?;
+        let stored = self.fetch(&id.to_string())?;
+        check_version(&stored, &addr)?;
+        self.append_history(&StoredAddress {
+            id,
+            address: stored,
+        })?;
+        self.write_file(&StoredAddress { id, address: addr })?;
 
         Ok(())
     }
@@ -109,7 +373,104 @@ impl AddressRepository for JsonAddressRepository {
                 Err(AddressRepositoryError::NotFound(id.to_string()))
             }
             Err(e) => Err(AddressRepositoryError::IOFailure(e)),
-            Ok(_) => Ok(()),
+            Ok(_) => match fs::remove_file(self.history_path(&id)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    Err(AddressRepositoryError::IOFailure(e))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// A version appended to the history by an update whose address file
+    /// then failed to be written is left out, the stored address being at
+    /// this version.
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        let current = self.fetch(id)?;
+        let mut versions = Vec::new();
+
+        match File::open(self.history_path(&current.id())) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(AddressRepositoryError::IOFailure(e)),
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let stored: StoredAddress = serde_json::from_str(&line?)?;
+                    if stored.address.version() < current.version() {
+                        versions.push(stored.address);
+                    }
+                }
+            }
+        }
+        versions.push(current);
+
+        Ok(versions)
+    }
+
+    /// Moves back the files left with the other layout by an interrupted
+    /// migration, keeping the last updated copy of an address stored twice,
+    /// then writes the address files again without their whitespaces and
+    /// unknown fields, and removes the emptied shards.
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        let mut report = CompactionReport::default();
+
+        for dir_entry in json_entries(&self.dir, self.layout.other())? {
+            let path = dir_entry.path();
+            let Some(id) = file_id(&path) else {
+                continue;
+            };
+            let Ok(orphan) = read_stored(&path) else {
+                report.unreadable.push(path.display().to_string());
+                continue;
+            };
+            let target = self.file_path(&id);
+
+            if !target.exists() {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&path, &target)?;
+                report.orphans_restored += 1;
+                continue;
+            }
+            // The unreadable copy is reported with the stored files below.
+            let Ok(stored) = read_stored(&target) else {
+                continue;
+            };
+            if stored.address.updated_at() >= orphan.address.updated_at() {
+                report.reclaimed_bytes += dir_entry.metadata()?.len();
+                fs::remove_file(&path)?;
+            } else {
+                report.reclaimed_bytes += fs::metadata(&target)?.len();
+                fs::rename(&path, &target)?;
+            }
+            report.orphans_removed += 1;
         }
+
+        for dir_entry in json_entries(&self.dir, self.layout)? {
+            let path = dir_entry.path();
+            let bytes = fs::read(&path)?;
+            let Ok(stored) = serde_json::from_slice::<StoredAddress>(&bytes) else {
+                report.unreadable.push(path.display().to_string());
+                continue;
+            };
+
+            let compacted = serde_json::to_vec(&stored)?;
+            if compacted.len() < bytes.len() {
+                self.durability.write(&path, &compacted)?;
+                report.rewritten += 1;
+                report.reclaimed_bytes += (bytes.len() - compacted.len()) as u64;
+            }
+        }
+
+        report.dirs_removed = remove_empty_shards(&self.dir)?;
+
+        Ok(report)
+    }
+
+    /// The storage answers as long as its directory can be listed, e.g.,
+    /// once a network volume is mounted again.
+    fn ping(&self) -> RepositoryResult<()> {
+        fs::read_dir(&self.dir)?;
+        Ok(())
     }
 }
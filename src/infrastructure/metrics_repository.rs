@@ -0,0 +1,166 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::application::metrics::{
+    REPOSITORY_OPERATIONS_TOTAL, REPOSITORY_OPERATION_DURATION_SECONDS,
+};
+use crate::domain::repositories::{
+    AddressFilter, AddressRepository, ChangeEvent, CompactionReport, RepositoryResult,
+};
+use crate::domain::{Address, Fingerprint, SearchHit};
+
+/// A repository decorator recording, for every operation, a counter labelled
+/// with its outcome and a histogram of its duration.
+pub struct MetricsRepository<R> {
+    repository: R,
+}
+
+impl<R: AddressRepository> MetricsRepository<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    fn record<T>(
+        &self,
+        operation: &'static str,
+        call: impl FnOnce(&R) -> RepositoryResult<T>,
+    ) -> RepositoryResult<T> {
+        let start = Instant::now();
+        let result = call(&self.repository);
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+
+        metrics::counter!(
+            REPOSITORY_OPERATIONS_TOTAL,
+            "operation" => operation,
+            "outcome" => outcome
+        )
+        .increment(1);
+        metrics::histogram!(REPOSITORY_OPERATION_DURATION_SECONDS, "operation" => operation)
+            .record(start.elapsed().as_secs_f64());
+
+        result
+    }
+}
+
+impl<R: AddressRepository> AddressRepository for MetricsRepository<R> {
+    fn save(&self, addr: Address) -> RepositoryResult<Uuid> {
+        self.record("save", |repository| repository.save(addr))
+    }
+
+    fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+        self.record("fetch", |repository| repository.fetch(id))
+    }
+
+    fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+        self.record("fetch_all", |repository| repository.fetch_all())
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        self.record("fetch_ids", |repository| repository.fetch_ids())
+    }
+
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        self.record("fetch_updated_since", |repository| {
+            repository.fetch_updated_since(since)
+        })
+    }
+
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        self.record("fetch_filtered", |repository| {
+            repository.fetch_filtered(filter)
+        })
+    }
+
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        self.record("fetch_by_idempotency_key", |repository| {
+            repository.fetch_by_idempotency_key(key)
+        })
+    }
+
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        self.record("fetch_by_alias", |repository| {
+            repository.fetch_by_alias(alias)
+        })
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        self.record("find_by_fingerprint", |repository| {
+            repository.find_by_fingerprint(fingerprint)
+        })
+    }
+
+    fn update(&self, addr: Address) -> RepositoryResult<()> {
+        self.record("update", |repository| repository.update(addr))
+    }
+
+    fn delete(&self, id: &str) -> RepositoryResult<()> {
+        self.record("delete", |repository| repository.delete(id))
+    }
+
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        self.record("fetch_history", |repository| repository.fetch_history(id))
+    }
+
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        self.record("compact", |repository| repository.compact())
+    }
+
+    fn search(&self, query: &str, limit: usize) -> RepositoryResult<Vec<SearchHit>> {
+        self.record("search", |repository| repository.search(query, limit))
+    }
+
+    fn reindex(&self) -> RepositoryResult<Option<usize>> {
+        self.record("reindex", |repository| repository.reindex())
+    }
+
+    fn ping(&self) -> RepositoryResult<()> {
+        self.record("ping", |repository| repository.ping())
+    }
+
+    fn changes(&self, after: u64, limit: usize) -> RepositoryResult<Option<Vec<ChangeEvent>>> {
+        self.record("changes", |repository| repository.changes(after, limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::MetricKind;
+
+    use super::MetricsRepository;
+    use crate::domain::repositories::AddressRepository;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    #[test]
+    fn it_should_count_operations_by_outcome() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let repository = MetricsRepository::new(InMemoryAddressRepository::new());
+
+        metrics::with_local_recorder(&recorder, || {
+            assert!(repository.fetch_all().is_ok());
+            assert!(repository.fetch("unknown").is_err());
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let count = |operation: &str, outcome: &str| {
+            snapshot.iter().find_map(|(key, _, _, value)| {
+                let key = key.key();
+                let labels: Vec<_> = key.labels().map(|l| (l.key(), l.value())).collect();
+                (key.name() == "address_repository_operations_total"
+                    && labels.contains(&("operation", operation))
+                    && labels.contains(&("outcome", outcome)))
+                .then_some(value)
+            })
+        };
+
+        assert_eq!(count("fetch_all", "ok"), Some(&DebugValue::Counter(1)));
+        assert_eq!(count("fetch", "error"), Some(&DebugValue::Counter(1)));
+        assert!(snapshot
+            .iter()
+            .any(|(key, _, _, _)| key.kind() == MetricKind::Histogram
+                && key.key().name() == "address_repository_operation_duration_seconds"));
+    }
+}
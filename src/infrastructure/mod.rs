@@ -1,5 +1,27 @@
+mod change_log_repository;
+#[cfg(feature = "geocoding")]
+pub mod geocoding;
 mod in_memory_repository;
 mod json_repository;
+#[cfg(feature = "metrics")]
+mod metrics_repository;
+#[cfg(feature = "mongodb")]
+mod mongo_repository;
+mod profile;
+#[cfg(feature = "fts")]
+mod search_index_repository;
+mod storage_url;
+mod timeout_repository;
 
+pub use self::change_log_repository::ChangeLogRepository;
 pub use self::in_memory_repository::InMemoryAddressRepository;
-pub use self::json_repository::JsonAddressRepository;
+pub use self::json_repository::{Durability, JsonAddressRepository, StorageLayout};
+#[cfg(feature = "metrics")]
+pub use self::metrics_repository::MetricsRepository;
+#[cfg(feature = "mongodb")]
+pub use self::mongo_repository::MongoAddressRepository;
+pub use self::profile::{Profile, ProfileError};
+#[cfg(feature = "fts")]
+pub use self::search_index_repository::SearchIndexRepository;
+pub use self::storage_url::{StorageUrl, UnsupportedStorage};
+pub use self::timeout_repository::{CancellationToken, TimeoutRepository};
@@ -0,0 +1,314 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{self, doc, Document};
+use mongodb::error::{Error as MongoError, ErrorKind};
+use mongodb::sync::{Client, Collection};
+use mongodb::IndexModel;
+use uuid::Uuid;
+
+use crate::domain::repositories::{
+    check_version, compact, AddressFilter, AddressRepository, AddressRepositoryError,
+    RepositoryResult, Tenant,
+};
+use crate::domain::{Address, Fingerprint};
+
+/// The collection of the addresses stored without tenant.
+const COLLECTION: &str = "addresses";
+/// The server error code of a duplicate `_id`.
+const DUPLICATE_KEY: i32 = 11000;
+
+/// A repository storing the addresses as BSON documents in a MongoDB
+/// collection, for the teams already running Mongo.
+///
+/// Next to the address, each document holds the fields the queries filter
+/// on: the fingerprint for the duplicates, the postcode and the town without
+/// spaces and uppercased, like [`AddressFilter`] compares them, the country,
+/// the kind and the update date. The fingerprint, the postcode, the update
+/// date, the idempotency key and the alias of the address are indexed.
+pub struct MongoAddressRepository {
+    addresses: Collection<Document>,
+}
+
+impl MongoAddressRepository {
+    /// Connects to the server at `uri` (e.g., `mongodb://localhost:27017`)
+    /// and creates the indexes of the collection if needed.
+    pub fn connect(uri: &str, database: &str) -> RepositoryResult<Self> {
+        Self::connect_collection(uri, database, COLLECTION)
+    }
+
+    /// Stores the addresses of `tenant` in its own collection, apart from the
+    /// addresses stored without tenant and the other tenants.
+    pub fn connect_for_tenant(
+        uri: &str,
+        database: &str,
+        tenant: &Tenant,
+    ) -> RepositoryResult<Self> {
+        Self::connect_collection(uri, database, &format!("{COLLECTION}.{}", tenant.as_str()))
+    }
+
+    fn connect_collection(uri: &str, database: &str, collection: &str) -> RepositoryResult<Self> {
+        let client = Client::with_uri_str(uri).map_err(backend)?;
+        let addresses = client.database(database).collection(collection);
+
+        let indexes = [
+            "fingerprint",
+            "postcode",
+            "updated_at",
+            "address.idempotency_key.key",
+            "address.alias",
+        ]
+        .into_iter()
+        .map(|field| IndexModel::builder().keys(doc! { field: 1 }).build());
+        addresses.create_indexes(indexes).run().map_err(backend)?;
+
+        Ok(Self { addresses })
+    }
+
+    fn find(&self, filter: Document) -> RepositoryResult<Vec<Address>> {
+        let mut addresses = Vec::new();
+
+        for document in self.addresses.find(filter).run().map_err(backend)? {
+            addresses.push(address(document.map_err(backend)?)?);
+        }
+        Ok(addresses)
+    }
+}
+
+fn document(addr: &Address) -> RepositoryResult<Document> {
+    Ok(doc! {
+        "_id": addr.id().to_string(),
+        "fingerprint": addr.fingerprint().to_string(),
+        "postcode": compact(&addr.postal_details.postcode),
+        "town": compact(&addr.postal_details.town),
+        "country": bson::to_bson(&addr.country).map_err(backend)?,
+        "kind": bson::to_bson(&addr.kind).map_err(backend)?,
+        "updated_at": bson::DateTime::from_millis(addr.updated_at().timestamp_millis()),
+        "version": addr.version() as i64,
+        "address": bson::to_document(addr).map_err(backend)?,
+    })
+}
+
+fn address(mut document: Document) -> RepositoryResult<Address> {
+    let address = document.get_document_mut("address").map_err(malformed)?;
+
+    bson::from_document(std::mem::take(address)).map_err(backend)
+}
+
+/// A document missing a field, e.g., written by another application.
+fn malformed(e: bson::document::ValueAccessError) -> AddressRepositoryError {
+    AddressRepositoryError::Backend(format!("Malformed address document: {e}"))
+}
+
+/// An unreachable server is reported as unavailable, like the other
+/// backends, the other failures with their message.
+fn backend(e: impl Into<MongoError>) -> AddressRepositoryError {
+    let e = e.into();
+
+    match *e.kind {
+        ErrorKind::ServerSelection { .. } => AddressRepositoryError::Unavailable,
+        _ => AddressRepositoryError::Backend(e.to_string()),
+    }
+}
+
+impl AddressRepository for MongoAddressRepository {
+    fn save(&self, addr: Address) -> RepositoryResult<Uuid> {
+        let id = addr.id();
+
+        // The duplicates are looked up with the fingerprint index.
+        let duplicate = self
+            .addresses
+            .find_one(doc! { "fingerprint": addr.fingerprint().to_string() })
+            .run()
+            .map_err(backend)?;
+        if let Some(duplicate) = duplicate {
+            return Err(AddressRepositoryError::AlreadyExists(
+                duplicate.get_str("_id").map_err(malformed)?.to_string(),
+            ));
+        }
+
+        match self.addresses.insert_one(document(&addr)?).run() {
+            // In case of UUID collision, the `_id` being unique.
+            Err(e)
+                if matches!(
+                    *e.kind,
+                    ErrorKind::Write(mongodb::error::WriteFailure::WriteError(ref write))
+                        if write.code == DUPLICATE_KEY
+                ) =>
+            {
+                Err(AddressRepositoryError::AlreadyExists(id.to_string()))
+            }
+            Err(e) => Err(backend(e)),
+            Ok(_) => Ok(id),
+        }
+    }
+
+    fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+        let id = Uuid::parse_str(id)?;
+        let document = self
+            .addresses
+            .find_one(doc! { "_id": id.to_string() })
+            .run()
+            .map_err(backend)?;
+
+        match document {
+            Some(document) => address(document),
+            None => Err(AddressRepositoryError::NotFound(id.to_string())),
+        }
+    }
+
+    fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+        self.find(doc! {})
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        let mut ids = Vec::new();
+        let documents = self
+            .addresses
+            .find(doc! {})
+            .projection(doc! { "_id": 1 })
+            .run()
+            .map_err(backend)?;
+
+        for document in documents {
+            let document = document.map_err(backend)?;
+            ids.push(Uuid::parse_str(
+                document.get_str("_id").map_err(malformed)?,
+            )?);
+        }
+        Ok(ids)
+    }
+
+    /// The index stores the update dates to the millisecond, the addresses it
+    /// returns are checked again with the exact date.
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        let millis = bson::DateTime::from_millis(since.timestamp_millis());
+
+        Ok(self
+            .find(doc! { "updated_at": { "$gte": millis } })?
+            .into_iter()
+            .filter(|address| address.updated_at() >= since)
+            .collect())
+    }
+
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        let mut query = Document::new();
+
+        match (&filter.postcode, &filter.postcode_prefix) {
+            (Some(postcode), _) => {
+                query.insert("postcode", compact(postcode));
+            }
+            (None, Some(prefix)) => {
+                let pattern = format!("^{}", regex::escape(&compact(prefix)));
+                query.insert("postcode", doc! { "$regex": pattern });
+            }
+            (None, None) => {}
+        }
+        if let Some(town) = &filter.town {
+            query.insert("town", compact(town));
+        }
+        if let Some(country) = &filter.country {
+            query.insert("country", bson::to_bson(country).map_err(backend)?);
+        }
+        if let Some(kind) = &filter.kind {
+            query.insert("kind", bson::to_bson(kind).map_err(backend)?);
+        }
+
+        // A prefix given with an exact postcode is checked here.
+        Ok(self
+            .find(query)?
+            .into_iter()
+            .filter(|address| filter.matches(address))
+            .collect())
+    }
+
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        Ok(self
+            .find(doc! { "address.idempotency_key.key": key })?
+            .into_iter()
+            .max_by_key(|address| address.idempotency_key().map(|k| k.received_at)))
+    }
+
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        Ok(self
+            .find(doc! { "address.alias": alias })?
+            .into_iter()
+            .next())
+    }
+
+    /// The fingerprints are looked up with their index.
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        self.find(doc! { "fingerprint": fingerprint.to_string() })
+    }
+
+    /// The address is replaced only if it is still at the version it was
+    /// checked at, so that a concurrent update isn't lost.
+    fn update(&self, addr: Address) -> RepositoryResult<()> {
+        let id = addr.id().to_string();
+        let stored = self.fetch(&id)?;
+        check_version(&stored, &addr)?;
+
+        let result = self
+            .addresses
+            .replace_one(
+                doc! { "_id": &id, "version": stored.version() as i64 },
+                document(&addr)?,
+            )
+            .run()
+            .map_err(backend)?;
+        if result.matched_count == 0 {
+            return check_version(&self.fetch(&id)?, &addr);
+        }
+
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> RepositoryResult<()> {
+        let id = Uuid::parse_str(id)?;
+        let result = self
+            .addresses
+            .delete_one(doc! { "_id": id.to_string() })
+            .run()
+            .map_err(backend)?;
+
+        match result.deleted_count {
+            0 => Err(AddressRepositoryError::NotFound(id.to_string())),
+            _ => Ok(()),
+        }
+    }
+    /// Counts the documents from the collection metadata, which needs the
+    /// server to answer without scanning the collection.
+    fn ping(&self) -> RepositoryResult<()> {
+        self.addresses
+            .estimated_document_count()
+            .run()
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AddressConvertible, ConvertedAddress, FrenchAddress};
+
+    #[test]
+    fn it_should_store_the_queried_fields_next_to_the_address() {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": "Monsieur Jean DELHOURME",
+            "street": "25 RUE DE L'EGLISE",
+            "postal": "33380 MIOS",
+            "country": "FRANCE",
+        }))
+        .unwrap();
+        let addr = Address::new(ConvertedAddress::from_french(french).unwrap());
+
+        let document = document(&addr).unwrap();
+        assert_eq!(document.get_str("_id"), Ok(&*addr.id().to_string()));
+        assert_eq!(document.get_str("postcode"), Ok("33380"));
+        assert_eq!(document.get_str("town"), Ok("MIOS"));
+        assert_eq!(
+            document.get_str("fingerprint"),
+            Ok(&*addr.fingerprint().to_string())
+        );
+        assert_eq!(address(document).unwrap(), addr);
+    }
+}
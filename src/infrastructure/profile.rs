@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use super::StorageUrl;
+use crate::domain::repositories::Tenant;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ProfileError {
+    #[error("Invalid profile config: {0}")]
+    Invalid(String),
+    #[error("Unknown profile `{name}`: must be one of {}", .available.join(", "))]
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("Undefined environment variable `{0}` in the profile config")]
+    UndefinedVariable(String),
+}
+
+/// The `profile` section of the config.
+#[derive(Debug, Default, Deserialize)]
+struct ProfileConfig {
+    #[serde(default)]
+    profile: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileSection {
+    storage: Option<String>,
+    tenant: Option<String>,
+    timeout: Option<u64>,
+}
+
+/// The settings of an environment (e.g., `dev` or `prod`), named in the
+/// `profile` section of a config file so that one file covers them all:
+///
+/// ```
+/// use address_converter::infrastructure::{Profile, StorageUrl};
+///
+/// let config = r#"{"profile": {
+///     "dev": {"storage": "memory://"},
+///     "prod": {"storage": "json://${DATA_DIR:-/var/lib/addresses}", "tenant": "billing"}
+/// }}"#;
+/// let profile = Profile::from_config(config, "prod", |_| None).unwrap();
+/// assert_eq!(profile.storage, Some("json:///var/lib/addresses".parse().unwrap()));
+/// ```
+///
+/// The values may refer to environment variables, `${VAR}` being replaced
+/// by the value of `VAR`, and `${VAR:-default}` by `default` when `VAR`
+/// isn't set. `$$` stands for `$`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub storage: Option<StorageUrl>,
+    pub tenant: Option<Tenant>,
+    /// Maximum duration of each storage operation, in seconds.
+    pub timeout: Option<u64>,
+}
+
+impl Profile {
+    /// Reads the profile `name` of the JSON config, its environment variables
+    /// being looked up with `var` (e.g., `|name| std::env::var(name).ok()`).
+    pub fn from_config(
+        json: &str,
+        name: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ProfileError> {
+        let invalid = |e: String| ProfileError::Invalid(e);
+        let mut config: ProfileConfig =
+            serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+
+        // The variables of the other profiles don't have to be set.
+        let mut section =
+            config
+                .profile
+                .remove(name)
+                .ok_or_else(|| ProfileError::UnknownProfile {
+                    name: name.to_string(),
+                    available: config.profile.into_keys().collect(),
+                })?;
+        expand_values(&mut section, &var)?;
+        let section: ProfileSection = serde_json::from_value(section)
+            .map_err(|e| invalid(format!("{e} in profile `{name}`")))?;
+
+        Ok(Self {
+            storage: section
+                .storage
+                .map(|storage| storage.parse())
+                .transpose()
+                .map_err(|e| invalid(format!("{e} in profile `{name}`")))?,
+            tenant: section
+                .tenant
+                .map(|tenant| tenant.parse())
+                .transpose()
+                .map_err(|e| invalid(format!("{e} in profile `{name}`")))?,
+            timeout: section.timeout,
+        })
+    }
+}
+
+/// Replaces the environment variables in the strings of `value`.
+fn expand_values(
+    value: &mut Value,
+    var: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ProfileError> {
+    match value {
+        Value::String(text) => *text = expand(text, var)?,
+        Value::Array(values) => {
+            for value in values {
+                expand_values(value, var)?;
+            }
+        }
+        Value::Object(entries) => {
+            for value in entries.values_mut() {
+                expand_values(value, var)?;
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+
+    Ok(())
+}
+
+/// The text with its `${VAR}` and `${VAR:-default}` replaced, and its `$$`
+/// unescaped. A `$` followed by anything else is kept.
+fn expand(text: &str, var: &impl Fn(&str) -> Option<String>) -> Result<String, ProfileError> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = after;
+        } else if let Some((reference, after)) = rest
+            .strip_prefix("${")
+            .and_then(|reference| reference.split_once('}'))
+        {
+            let (name, default) = match reference.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (reference, None),
+            };
+            let value = var(name)
+                .or_else(|| default.map(str::to_string))
+                .ok_or_else(|| ProfileError::UndefinedVariable(name.to_string()))?;
+            expanded.push_str(&value);
+            rest = after;
+        } else {
+            expanded.push('$');
+            rest = &rest[1..];
+        }
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Profile, ProfileError};
+    use crate::infrastructure::StorageUrl;
+
+    const CONFIG: &str = r#"{
+        "validation": {"postcode": "warn"},
+        "profile": {
+            "dev": {"storage": "memory://"},
+            "prod": {
+                "storage": "json://${DATA_DIR}/addresses",
+                "tenant": "${TENANT:-billing}",
+                "timeout": 10
+            }
+        }
+    }"#;
+
+    fn var(name: &str) -> Option<String> {
+        (name == "DATA_DIR").then(|| "/var/lib".to_string())
+    }
+
+    #[test]
+    fn it_should_read_the_profiles() {
+        let prod = Profile::from_config(CONFIG, "prod", var).unwrap();
+        assert_eq!(
+            prod.storage,
+            Some(StorageUrl::Json(PathBuf::from("/var/lib/addresses")))
+        );
+        assert_eq!(prod.tenant.unwrap().as_str(), "billing");
+        assert_eq!(prod.timeout, Some(10));
+
+        let dev = Profile::from_config(CONFIG, "dev", |_| None).unwrap();
+        assert_eq!(dev.storage, Some(StorageUrl::Memory));
+
+        assert_eq!(
+            Profile::from_config(CONFIG, "staging", var)
+                .unwrap_err()
+                .to_string(),
+            "Unknown profile `staging`: must be one of dev, prod"
+        );
+    }
+
+    #[test]
+    fn it_should_expand_the_variables() {
+        let expand = |text: &str| super::expand(text, &var);
+        assert_eq!(expand("${DATA_DIR}/a").unwrap(), "/var/lib/a");
+        assert_eq!(expand("${HOME:-/root}").unwrap(), "/root");
+        assert_eq!(expand("$$5 or $5 ${").unwrap(), "$5 or $5 ${");
+        assert!(matches!(
+            expand("${HOME}"),
+            Err(ProfileError::UndefinedVariable(name)) if name == "HOME"
+        ));
+    }
+}
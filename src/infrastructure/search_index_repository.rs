@@ -0,0 +1,313 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+use uuid::Uuid;
+
+use crate::domain::repositories::{
+    AddressFilter, AddressRepository, AddressRepositoryError, ChangeEvent, CompactionReport,
+    RepositoryResult,
+};
+use crate::domain::{search_terms, Address, Fingerprint, SearchHit};
+
+/// The memory given to the index writer, the minimum accepted by tantivy.
+const WRITER_MEMORY_BUDGET: usize = 15_000_000;
+/// The terms this long or longer also match the words one typo away.
+const FUZZY_MIN_LENGTH: usize = 4;
+/// How much an exact word counts compared to a prefix or a typo.
+const EXACT_BOOST: f32 = 3.0;
+
+/// A repository decorator keeping a full-text index of the stored addresses,
+/// updated on every save, update and delete, so that searches don't load the
+/// whole store.
+///
+/// The words of a search start the words of an address (e.g., `delh` finds
+/// `DELHOURME`), or are one typo away from them when they have 4 letters or
+/// more. The addresses matching every word are ranked by relevance. An index
+/// out of sync with the store, e.g., written by another process, is rebuilt by
+/// [`AddressRepository::reindex`].
+pub struct SearchIndexRepository<R> {
+    repository: R,
+    id: Field,
+    text: Field,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+}
+
+fn backend(error: impl ToString) -> AddressRepositoryError {
+    AddressRepositoryError::Backend(error.to_string())
+}
+
+impl<R: AddressRepository> SearchIndexRepository<R> {
+    /// Opens the index of the directory `dir`, built from the addresses of
+    /// `repository` when created.
+    pub fn open(repository: R, dir: impl AsRef<Path>) -> RepositoryResult<Self> {
+        fs::create_dir_all(&dir)?;
+        let directory = MmapDirectory::open(dir).map_err(backend)?;
+        let index = Index::open_or_create(directory, Self::schema()).map_err(backend)?;
+
+        Self::with_index(repository, index)
+    }
+
+    /// Keeps the index in memory, built from the addresses of `repository`.
+    pub fn in_memory(repository: R) -> RepositoryResult<Self> {
+        Self::with_index(repository, Index::create_in_ram(Self::schema()))
+    }
+
+    fn schema() -> Schema {
+        let mut schema = Schema::builder();
+        schema.add_text_field("id", STRING | STORED);
+        schema.add_text_field("text", TEXT);
+
+        schema.build()
+    }
+
+    fn with_index(repository: R, index: Index) -> RepositoryResult<Self> {
+        let schema = index.schema();
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(backend)?;
+        let writer = index
+            .writer_with_num_threads(1, WRITER_MEMORY_BUDGET)
+            .map_err(backend)?;
+
+        let repository = Self {
+            repository,
+            id: schema.get_field("id").map_err(backend)?,
+            text: schema.get_field("text").map_err(backend)?,
+            reader,
+            writer: Mutex::new(writer),
+        };
+        if repository.reader.searcher().num_docs() == 0 {
+            repository.reindex()?;
+        }
+
+        Ok(repository)
+    }
+
+    /// Replaces the indexed addresses of `ids` with `addresses`, and commits.
+    fn write(&self, ids: &[Uuid], addresses: &[Address]) -> RepositoryResult<()> {
+        let mut writer = self.writer.lock().map_err(|_| backend("poisoned lock"))?;
+
+        for id in ids {
+            writer.delete_term(Term::from_field_text(self.id, &id.to_string()));
+        }
+        for address in addresses {
+            writer
+                .add_document(doc!(
+                    self.id => address.id().to_string(),
+                    self.text => address.search_text(),
+                ))
+                .map_err(backend)?;
+        }
+        writer.commit().map_err(backend)?;
+        self.reader.reload().map_err(backend)
+    }
+
+    /// Every word of the search must start a word of the address, or be one
+    /// typo away from it, the exact words scoring the highest.
+    fn query(&self, terms: &[String]) -> BooleanQuery {
+        let clauses = terms
+            .iter()
+            .map(|term| {
+                // The text field is lowercased by its tokenizer.
+                let term = Term::from_field_text(self.text, &term.to_lowercase());
+                let mut matches: Vec<(Occur, Box<dyn Query>)> = vec![
+                    (
+                        Occur::Should,
+                        Box::new(BoostQuery::new(
+                            Box::new(TermQuery::new(term.clone(), IndexRecordOption::WithFreqs)),
+                            EXACT_BOOST,
+                        )),
+                    ),
+                    (
+                        Occur::Should,
+                        Box::new(FuzzyTermQuery::new_prefix(term.clone(), 0, true)),
+                    ),
+                ];
+                if term.value().as_str().map_or(0, str::len) >= FUZZY_MIN_LENGTH {
+                    matches.push((Occur::Should, Box::new(FuzzyTermQuery::new(term, 1, true))));
+                }
+
+                (
+                    Occur::Must,
+                    Box::new(BooleanQuery::new(matches)) as Box<dyn Query>,
+                )
+            })
+            .collect();
+
+        BooleanQuery::new(clauses)
+    }
+}
+
+impl<R: AddressRepository> AddressRepository for SearchIndexRepository<R> {
+    fn save(&self, addr: Address) -> RepositoryResult<Uuid> {
+        let id = self.repository.save(addr.clone())?;
+        self.write(&[id], &[addr])?;
+
+        Ok(id)
+    }
+
+    fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+        self.repository.fetch(id)
+    }
+
+    fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_all()
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        self.repository.fetch_ids()
+    }
+
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_updated_since(since)
+    }
+
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_filtered(filter)
+    }
+
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        self.repository.fetch_by_idempotency_key(key)
+    }
+
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        self.repository.fetch_by_alias(alias)
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        self.repository.find_by_fingerprint(fingerprint)
+    }
+
+    fn update(&self, addr: Address) -> RepositoryResult<()> {
+        self.repository.update(addr.clone())?;
+        self.write(&[addr.id()], &[addr])
+    }
+
+    fn delete(&self, id: &str) -> RepositoryResult<()> {
+        self.repository.delete(id)?;
+        self.write(&[Uuid::parse_str(id)?], &[])
+    }
+
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        self.repository.fetch_history(id)
+    }
+
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        self.repository.compact()
+    }
+
+    fn ping(&self) -> RepositoryResult<()> {
+        self.repository.ping()
+    }
+
+    fn changes(&self, after: u64, limit: usize) -> RepositoryResult<Option<Vec<ChangeEvent>>> {
+        self.repository.changes(after, limit)
+    }
+
+    fn search(&self, query: &str, limit: usize) -> RepositoryResult<Vec<SearchHit>> {
+        let terms = search_terms(query);
+        if terms.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let searcher = self.reader.searcher();
+        searcher
+            .search(&self.query(&terms), &TopDocs::with_limit(limit))
+            .map_err(backend)?
+            .into_iter()
+            .map(|(score, address)| {
+                let document: TantivyDocument = searcher.doc(address).map_err(backend)?;
+                let id = document
+                    .get_first(self.id)
+                    .and_then(|id| id.as_str())
+                    .ok_or_else(|| backend("indexed address without ID"))?;
+
+                Ok(SearchHit {
+                    id: Uuid::parse_str(id)?,
+                    score,
+                })
+            })
+            .collect()
+    }
+
+    fn reindex(&self) -> RepositoryResult<Option<usize>> {
+        let addresses = self.repository.fetch_all()?;
+        {
+            let writer = self.writer.lock().map_err(|_| backend("poisoned lock"))?;
+            writer.delete_all_documents().map_err(backend)?;
+        }
+        self.write(&[], &addresses)?;
+
+        Ok(Some(addresses.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchIndexRepository;
+    use crate::domain::repositories::AddressRepository;
+    use crate::domain::{Address, AddressConvertible, ConvertedAddress, FrenchAddress};
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    fn address(name: &str, street: &str, postal: &str) -> Address {
+        let french: FrenchAddress = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "street": street,
+            "postal": postal,
+            "country": "FRANCE",
+        }))
+        .unwrap();
+
+        Address::new(ConvertedAddress::from_french(french).unwrap())
+    }
+
+    #[test]
+    fn it_should_rank_the_indexed_addresses() {
+        let store = InMemoryAddressRepository::new();
+        let mios = store
+            .save(address(
+                "Monsieur Jean DELHOURME",
+                "25 RUE DE L'EGLISE",
+                "33380 MIOS",
+            ))
+            .unwrap();
+        // Indexed when the index is created.
+        let repository = SearchIndexRepository::in_memory(store).unwrap();
+        let paris = repository
+            .save(address(
+                "Madame Marie DURAND",
+                "8 RUE DE RIVOLI",
+                "75001 PARIS",
+            ))
+            .unwrap();
+
+        let ids = |query: &str| -> Vec<_> {
+            repository
+                .search(query, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect()
+        };
+        assert_eq!(ids("delh"), [mios]);
+        // One typo away.
+        assert_eq!(ids("rivolli"), [paris]);
+        assert_eq!(ids("rue eglise"), [mios]);
+        assert_eq!(ids("rue").len(), 2);
+
+        repository.delete(&paris.to_string()).unwrap();
+        assert!(ids("rivoli").is_empty());
+        assert_eq!(repository.reindex().unwrap(), Some(1));
+        assert_eq!(ids("mios"), [mios]);
+    }
+}
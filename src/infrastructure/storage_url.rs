@@ -0,0 +1,137 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[cfg(feature = "mongodb")]
+use super::MongoAddressRepository;
+use super::{InMemoryAddressRepository, JsonAddressRepository};
+use crate::domain::repositories::{AddressRepository, RepositoryResult, Tenant};
+
+/// The database of a MongoDB URL naming none.
+#[cfg(feature = "mongodb")]
+const DEFAULT_DATABASE: &str = "address_converter";
+
+/// A storage named by a URL: `json://<dir>` for a directory of JSON files
+/// (e.g., `json:///var/lib/addresses` or `json://./json_storage`),
+/// `memory://` for a storage lost on exit (e.g., to try the commands), and
+/// `mongodb://<host>[/<database>]` for a MongoDB server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StorageUrl {
+    Json(PathBuf),
+    Memory,
+    #[cfg(feature = "mongodb")]
+    Mongo {
+        uri: String,
+        database: String,
+    },
+}
+
+/// A URL naming no supported storage.
+#[derive(Debug, Error, PartialEq)]
+#[error("Unsupported storage `{0}`: must be {}", StorageUrl::SCHEMES)]
+pub struct UnsupportedStorage(pub String);
+
+impl StorageUrl {
+    #[cfg(not(feature = "mongodb"))]
+    const SCHEMES: &'static str = "json://<dir> or memory://";
+    #[cfg(feature = "mongodb")]
+    const SCHEMES: &'static str = "json://<dir>, memory:// or mongodb://<host>[/<database>]";
+
+    /// Opens the storage, the addresses of `tenant` only when given.
+    pub fn open(
+        &self,
+        tenant: Option<&Tenant>,
+    ) -> RepositoryResult<Box<dyn AddressRepository + Send>> {
+        match self {
+            StorageUrl::Json(dir) => Ok(Box::new(match tenant {
+                Some(tenant) => JsonAddressRepository::for_tenant(dir, tenant),
+                None => JsonAddressRepository::new(dir),
+            })),
+            StorageUrl::Memory => Ok(Box::new(InMemoryAddressRepository::new())),
+            #[cfg(feature = "mongodb")]
+            StorageUrl::Mongo { uri, database } => Ok(Box::new(match tenant {
+                Some(tenant) => MongoAddressRepository::connect_for_tenant(uri, database, tenant)?,
+                None => MongoAddressRepository::connect(uri, database)?,
+            })),
+        }
+    }
+}
+
+impl FromStr for StorageUrl {
+    type Err = UnsupportedStorage;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(dir) = s.strip_prefix("json://").filter(|dir| !dir.is_empty()) {
+            return Ok(StorageUrl::Json(PathBuf::from(dir)));
+        }
+        if s == "memory://" {
+            return Ok(StorageUrl::Memory);
+        }
+        #[cfg(feature = "mongodb")]
+        if let Some(location) = s.strip_prefix("mongodb://") {
+            // The database is the path of the URL, before its options.
+            let database = location
+                .split_once('/')
+                .map(|(_, path)| path.split('?').next().unwrap_or_default())
+                .filter(|database| !database.is_empty())
+                .unwrap_or(DEFAULT_DATABASE);
+            return Ok(StorageUrl::Mongo {
+                uri: s.to_string(),
+                database: database.to_string(),
+            });
+        }
+
+        Err(UnsupportedStorage(s.to_string()))
+    }
+}
+
+impl fmt::Display for StorageUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageUrl::Json(dir) => write!(f, "json://{}", dir.display()),
+            StorageUrl::Memory => f.write_str("memory://"),
+            #[cfg(feature = "mongodb")]
+            StorageUrl::Mongo { uri, .. } => f.write_str(uri),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{StorageUrl, UnsupportedStorage};
+
+    #[test]
+    fn it_should_read_the_storage_urls() {
+        assert_eq!(
+            "json:///var/lib/addresses".parse(),
+            Ok(StorageUrl::Json(PathBuf::from("/var/lib/addresses")))
+        );
+        assert_eq!(
+            "json://./json_storage".parse(),
+            Ok(StorageUrl::Json(PathBuf::from("./json_storage")))
+        );
+        assert_eq!(
+            "sqlite://new.db".parse::<StorageUrl>(),
+            Err(UnsupportedStorage("sqlite://new.db".to_string()))
+        );
+        assert!("json://".parse::<StorageUrl>().is_err());
+    }
+
+    #[cfg(feature = "mongodb")]
+    #[test]
+    fn it_should_read_the_mongodb_database() {
+        let database = |url: &str| match url.parse() {
+            Ok(StorageUrl::Mongo { database, .. }) => database,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(database("mongodb://localhost:27017"), "address_converter");
+        assert_eq!(
+            database("mongodb://localhost:27017/crm?retryWrites=true"),
+            "crm"
+        );
+    }
+}
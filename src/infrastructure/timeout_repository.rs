@@ -0,0 +1,320 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::repositories::{
+    AddressFilter, AddressRepository, AddressRepositoryError, ChangeEvent, CompactionReport,
+    RepositoryResult,
+};
+use crate::domain::{Address, Fingerprint, SearchHit};
+
+/// Interval at which a pending call checks its cancellation token.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Job = Box<dyn FnOnce(&dyn AddressRepository) + Send>;
+
+/// A shared flag used to abort the pending and future calls of a
+/// [`TimeoutRepository`].
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A repository decorator bounding the duration of every operation, so that a
+/// hung backend can't block its caller indefinitely, and aborting them once
+/// its [`CancellationToken`] is cancelled (e.g., on Ctrl-C).
+///
+/// The wrapped repository is moved to a dedicated worker thread executing the
+/// operations in order. When an operation times out or is cancelled the caller
+/// gets an error right away, but the operation itself isn't rolled back: it
+/// may still complete later and delays the next operations until it does.
+pub struct TimeoutRepository {
+    jobs: mpsc::Sender<Job>,
+    /// No deadline when unset, the operations only being cancellable.
+    timeout: Option<Duration>,
+    cancellation: CancellationToken,
+}
+
+impl TimeoutRepository {
+    /// The timeout of the API. The CLI has none unless given, its bulk
+    /// operations (e.g., `fetch_all` on a large storage) taking longer.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new<R>(repository: R, timeout: Duration) -> Self
+    where
+        R: AddressRepository + Send + 'static,
+    {
+        Self::spawn(repository, Some(timeout))
+    }
+
+    /// Runs the operations without deadline, so that they can only be
+    /// cancelled.
+    pub fn cancellable<R>(repository: R) -> Self
+    where
+        R: AddressRepository + Send + 'static,
+    {
+        Self::spawn(repository, None)
+    }
+
+    fn spawn<R>(repository: R, timeout: Option<Duration>) -> Self
+    where
+        R: AddressRepository + Send + 'static,
+    {
+        let (jobs, pending_jobs) = mpsc::channel::<Job>();
+
+        thread::spawn(move || {
+            for job in pending_jobs {
+                job(&repository);
+            }
+        });
+
+        Self {
+            jobs,
+            timeout,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Replaces the cancellation token, to share one token between several
+    /// repositories.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    fn call<T, F>(&self, operation: F) -> RepositoryResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn AddressRepository) -> RepositoryResult<T> + Send + 'static,
+    {
+        if self.cancellation.is_cancelled() {
+            return Err(AddressRepositoryError::Cancelled);
+        }
+
+        let (result_tx, result_rx) = mpsc::channel();
+        self.jobs
+            .send(Box::new(move |repository| {
+                // The caller may have given up waiting, ignore the closed channel.
+                let _ = result_tx.send(operation(repository));
+            }))
+            .map_err(|_| AddressRepositoryError::Unavailable)?;
+
+        let deadline = self
+            .timeout
+            .map(|timeout| (Instant::now() + timeout, timeout));
+        loop {
+            let wait = match deadline {
+                Some((deadline, timeout)) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(AddressRepositoryError::Timeout(timeout));
+                    }
+                    remaining.min(CANCELLATION_POLL_INTERVAL)
+                }
+                None => CANCELLATION_POLL_INTERVAL,
+            };
+
+            match result_rx.recv_timeout(wait) {
+                Ok(result) => return result,
+                Err(RecvTimeoutError::Timeout) if self.cancellation.is_cancelled() => {
+                    return Err(AddressRepositoryError::Cancelled)
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                // The worker thread panicked
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(AddressRepositoryError::Unavailable)
+                }
+            }
+        }
+    }
+}
+
+impl AddressRepository for TimeoutRepository {
+    fn save(&self, addr: Address) -> RepositoryResult<Uuid> {
+        self.call(move |repository| repository.save(addr))
+    }
+
+    fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+        let id = id.to_string();
+        self.call(move |repository| repository.fetch(&id))
+    }
+
+    fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+        self.call(|repository| repository.fetch_all())
+    }
+
+    fn fetch_ids(&self) -> RepositoryResult<Vec<Uuid>> {
+        self.call(|repository| repository.fetch_ids())
+    }
+
+    fn fetch_updated_since(&self, since: DateTime<Utc>) -> RepositoryResult<Vec<Address>> {
+        self.call(move |repository| repository.fetch_updated_since(since))
+    }
+
+    fn fetch_filtered(&self, filter: &AddressFilter) -> RepositoryResult<Vec<Address>> {
+        let filter = filter.clone();
+        self.call(move |repository| repository.fetch_filtered(&filter))
+    }
+
+    fn fetch_by_idempotency_key(&self, key: &str) -> RepositoryResult<Option<Address>> {
+        let key = key.to_string();
+        self.call(move |repository| repository.fetch_by_idempotency_key(&key))
+    }
+
+    fn fetch_by_alias(&self, alias: &str) -> RepositoryResult<Option<Address>> {
+        let alias = alias.to_string();
+        self.call(move |repository| repository.fetch_by_alias(&alias))
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &Fingerprint) -> RepositoryResult<Vec<Address>> {
+        let fingerprint = *fingerprint;
+        self.call(move |repository| repository.find_by_fingerprint(&fingerprint))
+    }
+
+    fn update(&self, addr: Address) -> RepositoryResult<()> {
+        self.call(move |repository| repository.update(addr))
+    }
+
+    fn delete(&self, id: &str) -> RepositoryResult<()> {
+        let id = id.to_string();
+        self.call(move |repository| repository.delete(&id))
+    }
+
+    fn fetch_history(&self, id: &str) -> RepositoryResult<Vec<Address>> {
+        let id = id.to_string();
+        self.call(move |repository| repository.fetch_history(&id))
+    }
+
+    fn compact(&self) -> RepositoryResult<CompactionReport> {
+        self.call(|repository| repository.compact())
+    }
+
+    fn search(&self, query: &str, limit: usize) -> RepositoryResult<Vec<SearchHit>> {
+        let query = query.to_string();
+        self.call(move |repository| repository.search(&query, limit))
+    }
+
+    fn reindex(&self) -> RepositoryResult<Option<usize>> {
+        self.call(|repository| repository.reindex())
+    }
+
+    fn ping(&self) -> RepositoryResult<()> {
+        self.call(|repository| repository.ping())
+    }
+
+    fn changes(&self, after: u64, limit: usize) -> RepositoryResult<Option<Vec<ChangeEvent>>> {
+        self.call(move |repository| repository.changes(after, limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::{CancellationToken, TimeoutRepository};
+    use crate::domain::repositories::{
+        AddressRepository, AddressRepositoryError, RepositoryResult,
+    };
+    use crate::domain::Address;
+    use crate::infrastructure::InMemoryAddressRepository;
+
+    /// A repository simulating a hung backend.
+    struct SlowRepository(Duration);
+
+    impl AddressRepository for SlowRepository {
+        fn save(&self, _addr: Address) -> RepositoryResult<Uuid> {
+            thread::sleep(self.0);
+            Ok(Uuid::new_v4())
+        }
+
+        fn fetch(&self, id: &str) -> RepositoryResult<Address> {
+            thread::sleep(self.0);
+            Err(AddressRepositoryError::NotFound(id.to_string()))
+        }
+
+        fn fetch_all(&self) -> RepositoryResult<Vec<Address>> {
+            thread::sleep(self.0);
+            Ok(vec![])
+        }
+
+        fn update(&self, _addr: Address) -> RepositoryResult<()> {
+            thread::sleep(self.0);
+            Ok(())
+        }
+
+        fn delete(&self, _id: &str) -> RepositoryResult<()> {
+            thread::sleep(self.0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn forwards_operations() {
+        let repository =
+            TimeoutRepository::new(InMemoryAddressRepository::new(), Duration::from_secs(5));
+        let id = Uuid::new_v4().to_string();
+
+        assert!(repository.fetch_all().unwrap().is_empty());
+        assert!(matches!(
+            repository.fetch(&id),
+            Err(AddressRepositoryError::NotFound(not_found)) if not_found == id
+        ));
+    }
+
+    #[test]
+    fn times_out_hung_operations() {
+        let repository = TimeoutRepository::new(
+            SlowRepository(Duration::from_secs(2)),
+            Duration::from_millis(100),
+        );
+
+        assert!(matches!(
+            repository.fetch_all(),
+            Err(AddressRepositoryError::Timeout(timeout)) if timeout == Duration::from_millis(100)
+        ));
+    }
+
+    #[test]
+    fn cancels_pending_operations() {
+        let token = CancellationToken::new();
+        let repository = TimeoutRepository::cancellable(SlowRepository(Duration::from_secs(2)))
+            .with_cancellation(token.clone());
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            token.cancel();
+        });
+
+        assert!(matches!(
+            repository.fetch_all(),
+            Err(AddressRepositoryError::Cancelled)
+        ));
+        canceller.join().unwrap();
+
+        // Next operations are rejected right away
+        assert!(matches!(
+            repository.delete("id"),
+            Err(AddressRepositoryError::Cancelled)
+        ));
+    }
+}
@@ -1,4 +1,9 @@
 pub mod application;
 pub mod domain;
+mod error;
 pub mod infrastructure;
 pub mod presentation;
+
+pub use application::service::{AddressServiceError, Format};
+pub use application::simple::{convert_str, validate_str};
+pub use error::{Error, ErrorKind, Result};
@@ -0,0 +1,193 @@
+"use strict";
+
+const FIELDS = {
+  individual: ["name", "internal_delivery", "external_delivery", "street", "distribution_info", "postal", "country"],
+  business: ["business_name", "recipient", "external_delivery", "street", "distribution_info", "postal", "country"],
+};
+
+const message = document.getElementById("message");
+const addressForm = document.getElementById("address-form");
+const deleteButton = document.getElementById("delete-address");
+let editedId = null;
+
+function showMessage(text, isError = false) {
+  message.textContent = text;
+  message.classList.toggle("error", isError);
+  message.hidden = !text;
+}
+
+async function api(method, path, body) {
+  const response = await fetch(`/api${path}`, { method, body });
+  if (response.status === 204) {
+    return null;
+  }
+
+  const json = await response.json();
+  if (!response.ok) {
+    throw new Error(json.error || response.statusText);
+  }
+
+  return json;
+}
+
+function show(view) {
+  for (const section of document.querySelectorAll("main > section")) {
+    section.hidden = section.id !== view;
+  }
+  for (const button of document.querySelectorAll("nav button")) {
+    button.classList.toggle("active", button.dataset.view === view);
+  }
+}
+
+// Browse
+
+function cell(text) {
+  const td = document.createElement("td");
+  td.textContent = text || "";
+  return td;
+}
+
+async function loadAddresses(query = "") {
+  const search = query ? `?q=${encodeURIComponent(query)}` : "";
+  const tbody = document.getElementById("addresses");
+
+  try {
+    const addresses = await api("GET", `/addresses${search}`);
+    tbody.replaceChildren(
+      ...addresses.map(({ id, address, quality, updated_at }) => {
+        const row = document.createElement("tr");
+        row.append(
+          cell(address.name || address.business_name),
+          cell(address.street),
+          cell(address.postal),
+          cell(address.country),
+          cell(`${quality}/100`),
+          cell(new Date(updated_at).toLocaleString()),
+        );
+        row.addEventListener("click", () => editAddress(id));
+        return row;
+      }),
+    );
+  } catch (error) {
+    showMessage(error.message, true);
+  }
+}
+
+document.getElementById("search-form").addEventListener("submit", (event) => {
+  event.preventDefault();
+  loadAddresses(new FormData(event.target).get("q"));
+});
+
+// Edit
+
+function setKind(kind) {
+  addressForm.elements.kind.value = kind;
+  for (const label of addressForm.querySelectorAll("label[data-kind]")) {
+    label.hidden = label.dataset.kind !== kind;
+  }
+}
+
+addressForm.elements.kind.addEventListener("change", (event) => setKind(event.target.value));
+
+function newAddress() {
+  editedId = null;
+  addressForm.reset();
+  setKind("individual");
+  document.getElementById("edit-title").textContent = "New address";
+  document.getElementById("iso-preview").textContent = "";
+  deleteButton.hidden = true;
+  show("edit");
+}
+
+async function editAddress(id) {
+  try {
+    const address = await api("GET", `/addresses/${id}`);
+    const iso = await api("GET", `/addresses/${id}?format=iso20022`);
+
+    editedId = id;
+    addressForm.reset();
+    setKind(address.business_name !== undefined ? "business" : "individual");
+    for (const field of FIELDS[addressForm.elements.kind.value]) {
+      addressForm.elements[field].value = address[field] || "";
+    }
+    document.getElementById("edit-title").textContent = `Address ${id}`;
+    document.getElementById("iso-preview").textContent = JSON.stringify(iso, null, 2);
+    deleteButton.hidden = false;
+    showMessage("");
+    show("edit");
+  } catch (error) {
+    showMessage(error.message, true);
+  }
+}
+
+addressForm.addEventListener("submit", async (event) => {
+  event.preventDefault();
+  const kind = addressForm.elements.kind.value;
+  const address = {};
+  for (const field of FIELDS[kind]) {
+    const value = addressForm.elements[field].value.trim();
+    address[field] = value || null;
+  }
+
+  try {
+    const body = JSON.stringify(address);
+    if (editedId) {
+      await api("PUT", `/addresses/${editedId}?format=french`, body);
+      showMessage("Address updated.");
+    } else {
+      const { id } = await api("POST", `/addresses?format=french&kind=${kind}`, body);
+      editedId = id;
+      showMessage("Address saved.");
+    }
+    await editAddress(editedId);
+    loadAddresses();
+  } catch (error) {
+    showMessage(error.message, true);
+  }
+});
+
+deleteButton.addEventListener("click", async () => {
+  if (!editedId || !confirm("Delete this address?")) {
+    return;
+  }
+
+  try {
+    await api("DELETE", `/addresses/${editedId}`);
+    showMessage("Address deleted.");
+    await loadAddresses();
+    show("browse");
+  } catch (error) {
+    showMessage(error.message, true);
+  }
+});
+
+// Convert
+
+document.getElementById("convert-form").addEventListener("submit", async (event) => {
+  event.preventDefault();
+  const form = new FormData(event.target);
+
+  try {
+    const converted = await api("POST", `/convert?format=${form.get("format")}`, form.get("address"));
+    document.getElementById("converted").textContent = JSON.stringify(converted, null, 2);
+    showMessage("");
+  } catch (error) {
+    showMessage(error.message, true);
+  }
+});
+
+// Navigation
+
+for (const button of document.querySelectorAll("nav button")) {
+  button.addEventListener("click", () => {
+    showMessage("");
+    if (button.id === "new-address") {
+      newAddress();
+    } else {
+      show(button.dataset.view);
+    }
+  });
+}
+
+setKind("individual");
+loadAddresses();
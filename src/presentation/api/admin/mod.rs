@@ -0,0 +1,68 @@
+use axum::http::header;
+use axum::response::{IntoResponse, Redirect};
+use axum::routing::get;
+use axum::Router;
+
+const INDEX_HTML: &str = include_str!("assets/index.html");
+const APP_JS: &str = include_str!("assets/app.js");
+const STYLE_CSS: &str = include_str!("assets/style.css");
+
+/// The routes serving the admin UI under `/admin`. The assets are embedded in
+/// the binary and only call the JSON API, so the UI needs no state.
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(|| async { Redirect::permanent("/admin/") }))
+        .route("/admin", get(|| async { Redirect::permanent("/admin/") }))
+        .route("/admin/", get(index))
+        .route("/admin/app.js", get(app_js))
+        .route("/admin/style.css", get(style_css))
+}
+
+async fn index() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        INDEX_HTML,
+    )
+}
+
+async fn app_js() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        APP_JS,
+    )
+}
+
+async fn style_css() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        STYLE_CSS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::router;
+
+    #[tokio::test]
+    async fn serves_embedded_assets() {
+        for (uri, content_type) in [
+            ("/admin/", "text/html; charset=utf-8"),
+            ("/admin/app.js", "text/javascript; charset=utf-8"),
+            ("/admin/style.css", "text/css; charset=utf-8"),
+        ] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = router().oneshot(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+        }
+
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::LOCATION], "/admin/");
+    }
+}
@@ -0,0 +1,303 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::{HeaderMap, HeaderName, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use strum_macros::{Display, EnumString};
+use thiserror::Error;
+
+use super::routes::ApiError;
+
+/// The header carrying a static API key, as an alternative to the bearer
+/// token.
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// What a key or token allows on the API.
+#[derive(Clone, Copy, Debug, Deserialize, Display, EnumString, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Scope {
+    /// Listing, fetching and converting addresses.
+    Read,
+    /// Saving, updating and deleting addresses.
+    Write,
+}
+
+impl Scope {
+    /// The scope required by a request. Conversions don't touch the storage,
+    /// they only need to read.
+    pub fn required(method: &Method, path: &str) -> Self {
+        if method == Method::GET || method == Method::HEAD || path.ends_with("/convert") {
+            Scope::Read
+        } else {
+            Scope::Write
+        }
+    }
+}
+
+/// A static API key and its scopes.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ApiKey {
+    pub key: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// The validation of JWT bearer tokens signed with HS256. The scopes are
+/// read from the space separated `scope` claim.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct JwtConfig {
+    pub secret: String,
+    /// The expected `iss` claim, if any.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// The expected `aud` claim, if any.
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+/// The credentials accepted by the API, read from a JSON file:
+///
+/// ```json
+/// {
+///     "keys": [{ "key": "0f6c...", "scopes": ["read", "write"] }],
+///     "jwt": { "secret": "...", "issuer": "https://auth.example.com" }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub keys: Vec<ApiKey>,
+    #[serde(default)]
+    pub jwt: Option<JwtConfig>,
+}
+
+#[derive(Debug, Error)]
+pub enum AuthConfigError {
+    #[error("Can't read the authentication config: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid authentication config: {0}")]
+    Invalid(String),
+}
+
+impl AuthConfig {
+    pub fn parse(json: &str) -> Result<Self, AuthConfigError> {
+        let config: Self =
+            serde_json::from_str(json).map_err(|e| AuthConfigError::Invalid(e.to_string()))?;
+
+        if config.keys.iter().any(|key| key.key.is_empty()) {
+            return Err(AuthConfigError::Invalid(
+                "API keys can't be empty".to_string(),
+            ));
+        }
+        if cfg!(not(feature = "jwt")) && config.jwt.is_some() {
+            return Err(AuthConfigError::Invalid(
+                "JWT validation requires the `jwt` feature".to_string(),
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// The scopes granted to a static API key.
+    fn key_scopes(&self, key: &str) -> Option<Vec<Scope>> {
+        self.keys
+            .iter()
+            .find(|api_key| api_key.key == key)
+            .map(|api_key| api_key.scopes.clone())
+    }
+
+    /// The scopes granted to a bearer token, either a static API key or a
+    /// valid JWT.
+    fn token_scopes(&self, token: &str) -> Option<Vec<Scope>> {
+        self.key_scopes(token).or_else(|| self.jwt_scopes(token))
+    }
+
+    #[cfg(feature = "jwt")]
+    fn jwt_scopes(&self, token: &str) -> Option<Vec<Scope>> {
+        use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+
+        #[derive(Deserialize)]
+        struct Claims {
+            #[serde(default)]
+            scope: String,
+        }
+
+        let jwt = self.jwt.as_ref()?;
+        let mut validation = Validation::new(Algorithm::HS256);
+        if let Some(issuer) = &jwt.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &jwt.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        let key = DecodingKey::from_secret(jwt.secret.as_bytes());
+        let claims = decode::<Claims>(token, &key, &validation).ok()?.claims;
+
+        // Unknown scopes are granted by other services, not ours.
+        Some(
+            claims
+                .scope
+                .split_whitespace()
+                .filter_map(|scope| scope.parse().ok())
+                .collect(),
+        )
+    }
+
+    #[cfg(not(feature = "jwt"))]
+    fn jwt_scopes(&self, _token: &str) -> Option<Vec<Scope>> {
+        None
+    }
+}
+
+/// The authentication of the API, shared by the requests. The config is
+/// reloaded from its file without restarting the server.
+#[derive(Clone, Debug)]
+pub struct ApiAuth {
+    config: Arc<RwLock<AuthConfig>>,
+    path: Option<PathBuf>,
+}
+
+impl ApiAuth {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            path: None,
+        }
+    }
+
+    /// Reads the config from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AuthConfigError> {
+        let path = path.as_ref().to_path_buf();
+        let config = AuthConfig::parse(&fs::read_to_string(&path)?)?;
+
+        Ok(Self {
+            config: Arc::new(RwLock::new(config)),
+            path: Some(path),
+        })
+    }
+
+    /// Reads the config file again. The current config is kept when the file
+    /// is invalid, so that a typo doesn't lock every client out.
+    pub fn reload(&self) -> Result<(), AuthConfigError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let config = AuthConfig::parse(&fs::read_to_string(path)?)?;
+        self.replace(config);
+
+        Ok(())
+    }
+
+    /// Replaces the config, e.g., after revoking a key.
+    pub fn replace(&self, config: AuthConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    /// The scopes granted by the credentials of a request, `None` without
+    /// valid credentials.
+    fn scopes(&self, headers: &HeaderMap) -> Option<Vec<Scope>> {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            return config.key_scopes(key.to_str().ok()?);
+        }
+        let token = headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+
+        config.token_scopes(token.trim())
+    }
+}
+
+/// Rejects the requests without credentials granting the scope they need.
+pub async fn authorize(State(auth): State<ApiAuth>, request: Request, next: Next) -> Response {
+    let required = Scope::required(request.method(), request.uri().path());
+
+    match auth.scopes(request.headers()) {
+        Some(scopes) if scopes.contains(&required) => next.run(request).await,
+        Some(_) => ApiError::new(
+            StatusCode::FORBIDDEN,
+            "E_FORBIDDEN",
+            format!("The `{required}` scope is required"),
+        )
+        .into_response(),
+        None => (
+            [(WWW_AUTHENTICATE, "Bearer")],
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "E_UNAUTHORIZED",
+                "Missing or invalid API key or bearer token",
+            ),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_require_the_write_scope_to_change_addresses() {
+        assert_eq!(Scope::required(&Method::GET, "/addresses/1"), Scope::Read);
+        assert_eq!(Scope::required(&Method::POST, "/convert"), Scope::Read);
+        assert_eq!(Scope::required(&Method::POST, "/addresses"), Scope::Write);
+        assert_eq!(
+            Scope::required(&Method::DELETE, "/addresses/1"),
+            Scope::Write
+        );
+    }
+
+    #[test]
+    fn it_should_reject_invalid_configs() {
+        assert!(AuthConfig::parse(r#"{"keys": [{"key": "", "scopes": []}]}"#).is_err());
+        assert!(AuthConfig::parse(r#"{"keys": [{"key": "k", "scopes": ["admin"]}]}"#).is_err());
+        assert!(AuthConfig::parse(r#"{"tokens": []}"#).is_err());
+    }
+
+    #[cfg(feature = "jwt")]
+    #[test]
+    fn it_should_read_the_scopes_of_valid_tokens() {
+        use jsonwebtoken::{encode, EncodingKey, Header};
+
+        let config = AuthConfig {
+            keys: Vec::new(),
+            jwt: Some(JwtConfig {
+                secret: "secret".to_string(),
+                issuer: Some("auth".to_string()),
+                audience: None,
+            }),
+        };
+        let token = |secret: &str, iss: &str| {
+            let claims = serde_json::json!({
+                "sub": "batch",
+                "iss": iss,
+                "exp": chrono::Utc::now().timestamp() + 60,
+                "scope": "read profile",
+            });
+            encode(
+                &Header::default(),
+                &claims,
+                &EncodingKey::from_secret(secret.as_bytes()),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            config.token_scopes(&token("secret", "auth")),
+            Some(vec![Scope::Read])
+        );
+        assert_eq!(config.token_scopes(&token("other", "auth")), None);
+        assert_eq!(config.token_scopes(&token("secret", "other")), None);
+    }
+}
@@ -0,0 +1,399 @@
+//! The bulk routes, moving large datasets as newline delimited JSON (NDJSON)
+//! streamed in both directions, so that neither the client nor the server
+//! holds the whole dataset in a single body.
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::io;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_TYPE, TRAILER};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, StreamExt};
+use http_body::Frame;
+use http_body_util::StreamBody;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::routes::{address_to_json, AddressQuery, ApiError, ApiResult};
+use super::service_handle::ServiceHandle;
+use crate::application::service::{AddressServiceError, ErrorDetails, Format, SaveOutcome};
+use crate::domain::{Fingerprint, PersonName, Routing};
+
+const NDJSON: &str = "application/x-ndjson";
+
+/// The trailers of a batch import, counting its records once it is over.
+const SAVED: HeaderName = HeaderName::from_static("x-batch-saved");
+const UPDATED: HeaderName = HeaderName::from_static("x-batch-updated");
+const FAILED: HeaderName = HeaderName::from_static("x-batch-failed");
+
+/// The outcome of a record of a batch import, written as soon as the record
+/// is imported.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportedRecord {
+    /// The position of the record in the body, starting at 0. Empty lines
+    /// aren't records.
+    record: usize,
+    /// `created` or `updated`, unless the record failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<&'static str>,
+    /// The ID of the saved or updated address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Uuid>,
+    /// Why the record failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorDetails>,
+}
+
+impl ImportedRecord {
+    fn new(record: usize, result: Result<SaveOutcome, AddressServiceError>) -> Self {
+        let (outcome, id, error) = match result {
+            Ok(SaveOutcome::Updated(id)) => (Some("updated"), Some(id), None),
+            Ok(outcome) => (Some("created"), Some(outcome.id()), None),
+            Err(error) => (None, None, Some(ApiError::from(error).into())),
+        };
+
+        Self {
+            record,
+            outcome,
+            id,
+            error,
+        }
+    }
+}
+
+/// An exported address, one per line of the export.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExportedLine {
+    id: Uuid,
+    updated_at: DateTime<Utc>,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "9a3c2f5e1d07b864"))]
+    fingerprint: Fingerprint,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    person_name: Option<PersonName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    routing: Option<Routing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    /// The address in the requested format.
+    #[cfg_attr(feature = "openapi", schema(value_type = super::openapi::AddressDocument))]
+    address: Value,
+}
+
+/// The counts of a batch import, sent as trailers.
+#[derive(Debug, Default)]
+struct ImportProgress {
+    records: usize,
+    saved: usize,
+    updated: usize,
+    failed: usize,
+}
+
+impl ImportProgress {
+    fn trailers(&self) -> HeaderMap {
+        [
+            (SAVED, self.saved),
+            (UPDATED, self.updated),
+            (FAILED, self.failed),
+        ]
+        .into_iter()
+        .map(|(name, count)| (name, HeaderValue::from(count)))
+        .collect()
+    }
+}
+
+/// Imports the addresses of a NDJSON body, one address per line, in the
+/// requested format. The records are imported as their lines arrive, and the
+/// outcome of each record is streamed back as a NDJSON line. The counts of
+/// the import follow as the `X-Batch-Saved`, `X-Batch-Updated` and
+/// `X-Batch-Failed` trailers, for the clients sending `TE: trailers`.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/addresses:batch",
+    params(AddressQuery),
+    request_body(content = String, content_type = "application/x-ndjson",
+        description = "One address per line"),
+    responses(
+        (status = 200, description = "The outcome of each record, one per line",
+            content_type = "application/x-ndjson", body = ImportedRecord),
+        (status = 400, description = "Invalid query parameter", body = ErrorDetails),
+    ),
+))]
+pub async fn import_addresses(
+    State(service): State<ServiceHandle>,
+    Query(query): Query<AddressQuery>,
+    body: Body,
+) -> ApiResult<Response> {
+    let format = query.format()?;
+    let upsert = query.upsert();
+
+    let (frames, pending_frames) = mpsc::channel(16);
+    tokio::spawn(import_stream(service, body, format, upsert, frames));
+    let frames = stream::unfold(pending_frames, |mut pending_frames| async move {
+        let frame = pending_frames.recv().await?;
+        Some((Ok::<_, Infallible>(frame), pending_frames))
+    });
+
+    Ok((
+        [
+            (CONTENT_TYPE, NDJSON),
+            (TRAILER, "x-batch-saved, x-batch-updated, x-batch-failed"),
+        ],
+        Body::new(StreamBody::new(frames)),
+    )
+        .into_response())
+}
+
+/// Reads the body line by line, importing the lines of each chunk received
+/// at once, then sends the trailers. Stops early when the client is gone.
+async fn import_stream(
+    service: ServiceHandle,
+    body: Body,
+    format: Format,
+    upsert: bool,
+    frames: mpsc::Sender<Frame<Bytes>>,
+) {
+    let mut progress = ImportProgress::default();
+    let mut chunks = body.into_data_stream();
+    // The start of a line whose end hasn't arrived yet.
+    let mut pending: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = chunks.next().await {
+        let Ok(chunk) = chunk else {
+            let error = ApiError::bad_request("Unreadable body");
+            let _ = frames.send(error_frame(error)).await;
+            return;
+        };
+        pending.extend_from_slice(&chunk);
+
+        let Some(end) = pending.iter().rposition(|byte| *byte == b'\n') else {
+            continue;
+        };
+        let lines: Vec<u8> = pending.drain(..=end).collect();
+        if !import_lines(&service, &lines, format, upsert, &frames, &mut progress).await {
+            return;
+        }
+    }
+
+    if import_lines(&service, &pending, format, upsert, &frames, &mut progress).await {
+        let _ = frames.send(Frame::trailers(progress.trailers())).await;
+    }
+}
+
+/// Imports the non-empty lines, sending the outcome of each. Returns whether
+/// the import can go on.
+async fn import_lines(
+    service: &ServiceHandle,
+    lines: &[u8],
+    format: Format,
+    upsert: bool,
+    frames: &mpsc::Sender<Frame<Bytes>>,
+    progress: &mut ImportProgress,
+) -> bool {
+    let lines: Vec<String> = String::from_utf8_lossy(lines)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if lines.is_empty() {
+        return true;
+    }
+
+    let Some(results) = service
+        .call(move |service| {
+            lines
+                .iter()
+                .map(|line| service.import_record(line, format, upsert))
+                .collect::<Vec<_>>()
+        })
+        .await
+    else {
+        let _ = frames.send(error_frame(ApiError::unavailable())).await;
+        return false;
+    };
+
+    for result in results {
+        match &result {
+            Ok(SaveOutcome::Updated(_)) => progress.updated += 1,
+            Ok(_) => progress.saved += 1,
+            Err(_) => progress.failed += 1,
+        }
+        let record = ImportedRecord::new(progress.records, result);
+        progress.records += 1;
+
+        if frames.send(Frame::data(json_line(&record))).await.is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// A line stopping the import, e.g., `{"error": {"code": "E_UNAVAILABLE", ...}}`.
+fn error_frame(error: ApiError) -> Frame<Bytes> {
+    Frame::data(json_line(
+        &serde_json::json!({ "error": ErrorDetails::from(error) }),
+    ))
+}
+
+fn json_line(value: &impl Serialize) -> Bytes {
+    let mut line = serde_json::to_vec(value).expect("the lines are serializable");
+    line.push(b'\n');
+
+    Bytes::from(line)
+}
+
+/// Exports the stored addresses as NDJSON, one address per line in the
+/// requested format, the oldest update first. With `since`, only the
+/// addresses updated since this date are exported; the list filters apply
+/// too.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/api/addresses:export",
+    params(AddressQuery),
+    responses(
+        (status = 200, description = "The addresses, one per line",
+            content_type = "application/x-ndjson", body = ExportedLine),
+        (status = 400, description = "Invalid query parameter", body = ErrorDetails),
+    ),
+))]
+pub async fn export_addresses(
+    State(service): State<ServiceHandle>,
+    Query(query): Query<AddressQuery>,
+) -> ApiResult<Response> {
+    let format = query.format()?;
+    let json_keys = query.json_keys()?;
+    let iso_country = query.iso_country()?;
+    let nulls = query.nulls()?;
+    let filter = query.filter()?;
+    let since = query.since()?;
+
+    let exported = service
+        .call(move |service| service.export_with(since, &filter, format))
+        .await
+        .ok_or_else(ApiError::unavailable)??;
+    // The lines are only written as the client reads them.
+    let lines = stream::iter(exported).map(move |exported| {
+        let line = ExportedLine {
+            id: exported.id,
+            updated_at: exported.updated_at,
+            fingerprint: exported.fingerprint,
+            person_name: exported.person_name,
+            routing: exported.routing,
+            notes: exported.notes,
+            metadata: exported.metadata,
+            address: address_to_json(exported.address, json_keys, iso_country, nulls)
+                .map_err(|error| io::Error::other(ErrorDetails::from(error).error))?,
+        };
+
+        Ok::<_, io::Error>(json_line(&line))
+    });
+
+    Ok(([(CONTENT_TYPE, NDJSON)], Body::from_stream(lines)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode};
+    use axum::Router;
+    use futures_util::stream;
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::application::service::BoxedAddressService;
+    use crate::infrastructure::InMemoryAddressRepository;
+    use crate::presentation::api::routes::router;
+    use crate::presentation::api::service_handle::ServiceHandle;
+
+    fn app() -> Router {
+        router(ServiceHandle::spawn(|| {
+            BoxedAddressService::new(Box::new(InMemoryAddressRepository::new()))
+        }))
+    }
+
+    fn lines(body: &[u8]) -> Vec<Value> {
+        body.split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn import_a_streamed_body_then_export_it() {
+        let app = app();
+        // The second address is split between two chunks, the third one is
+        // missing its fields.
+        let chunks = [
+            concat!(
+                r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+                "\n\n",
+                r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE","#,
+            ),
+            concat!(
+                r#" "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+                "\n",
+                r#"{"name": "Monsieur Jean DELHOURME"}"#,
+                "\n",
+            ),
+        ];
+        let body = Body::from_stream(stream::iter(chunks.into_iter().map(Ok::<_, io::Error>)));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/addresses:batch?format=french")
+            .body(body)
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let collected = response.into_body().collect().await.unwrap();
+        let trailers = collected.trailers().unwrap().clone();
+        let records = lines(&collected.to_bytes());
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["record"], 0);
+        assert_eq!(records[0]["outcome"], "created");
+        assert_eq!(records[1]["record"], 1);
+        assert_eq!(records[1]["outcome"], "created");
+        assert_eq!(records[2]["record"], 2);
+        assert_eq!(records[2]["error"]["code"], "E_INVALID_JSON");
+        assert_eq!(trailers["x-batch-saved"], "2");
+        assert_eq!(trailers["x-batch-updated"], "0");
+        assert_eq!(trailers["x-batch-failed"], "1");
+
+        let request = Request::builder()
+            .uri("/api/addresses:export?format=iso20022&postcode_prefix=33")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let exported = lines(&response.into_body().collect().await.unwrap().to_bytes());
+
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0]["id"], records[0]["id"]);
+        assert_eq!(
+            exported[0]["address"]["postal_address"]["town_name"],
+            "MIOS"
+        );
+
+        let request = Request::builder()
+            .uri("/api/addresses:export?since=yesterday")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,95 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use super::routes::ApiError;
+use super::service_handle::ServiceHandle;
+
+/// The probes of the orchestrators (e.g., Kubernetes): `/healthz` for the
+/// liveness, `/readyz` for the readiness.
+pub fn router(service: ServiceHandle) -> Router {
+    Router::new()
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+        .with_state(service)
+}
+
+/// Alive as long as the worker thread of the service runs, only a restart
+/// bringing it back. The calls in progress (e.g., a long import) aren't
+/// waited for.
+async fn liveness(State(service): State<ServiceHandle>) -> Result<Json<Value>, ApiError> {
+    if !service.is_alive() {
+        return Err(ApiError::unavailable());
+    }
+
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// Ready when the storage answers, see
+/// [`crate::domain::repositories::AddressRepository::ping`].
+async fn readiness(State(service): State<ServiceHandle>) -> Result<Json<Value>, ApiError> {
+    service
+        .call(|service| service.ping())
+        .await
+        .ok_or_else(ApiError::unavailable)?
+        .map_err(|error| {
+            ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                error.code(),
+                error.to_string(),
+            )
+        })?;
+
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use super::router;
+    use crate::application::service::BoxedAddressService;
+    use crate::infrastructure::JsonAddressRepository;
+    use crate::presentation::api::service_handle::ServiceHandle;
+
+    async fn probe(service: &ServiceHandle, uri: &str) -> (StatusCode, String) {
+        let response = router(service.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn it_should_answer_the_probes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_dir = temp_dir.path().join("storage");
+        let dir = storage_dir.clone();
+        let service = ServiceHandle::spawn(move || {
+            BoxedAddressService::new(Box::new(JsonAddressRepository::new(dir)))
+        });
+
+        assert_eq!(
+            probe(&service, "/healthz").await,
+            (StatusCode::OK, r#"{"status":"ok"}"#.to_string())
+        );
+        assert_eq!(probe(&service, "/readyz").await.0, StatusCode::OK);
+
+        // The storage is gone, the server stays alive but isn't ready
+        fs::remove_dir_all(&storage_dir).unwrap();
+        assert_eq!(probe(&service, "/healthz").await.0, StatusCode::OK);
+        let (status, body) = probe(&service, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains("E_STORAGE"), "{body}");
+    }
+}
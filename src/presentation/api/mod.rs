@@ -1,3 +1,22 @@
-//! This is just an example file if we want to extend
-//! the presentation layer with an API depending on
-//! the api binary.
+//! HTTP presentation layer, served by the api binary. The admin UI is
+//! embedded behind the `admin-ui` feature, the Prometheus endpoint behind the
+//! `metrics` feature, and the OpenAPI document with its Swagger UI behind the
+//! `openapi` feature. Requests are authenticated with API keys, and with JWT
+//! bearer tokens behind the `jwt` feature. The health probes are always
+//! served, without authentication.
+#[cfg(feature = "admin-ui")]
+pub mod admin;
+#[cfg(feature = "api")]
+pub mod auth;
+#[cfg(feature = "api")]
+pub mod bulk;
+#[cfg(feature = "api")]
+pub mod health;
+#[cfg(all(feature = "api", feature = "metrics"))]
+pub mod metrics;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(feature = "api")]
+pub mod routes;
+#[cfg(feature = "api")]
+pub mod service_handle;
@@ -0,0 +1,90 @@
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+use super::{bulk, routes};
+use crate::application::service::ErrorDetails;
+use crate::domain::{
+    BrazilianAddress, CanonicalAddress, FrenchAddress, IsoAddress, PortugueseAddress,
+};
+
+/// An address in one of the formats of the `format` query parameter. Only
+/// used to describe the bodies, the routes read them with the requested
+/// format.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum AddressDocument {
+    French(FrenchAddress),
+    Iso20022(IsoAddress),
+    Portuguese(PortugueseAddress),
+    Brazilian(BrazilianAddress),
+    Canonical(CanonicalAddress),
+}
+
+/// The OpenAPI document of the JSON API.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Address converter API"),
+    paths(
+        routes::list_addresses,
+        routes::save_address,
+        routes::fetch_address,
+        routes::update_address,
+        routes::delete_address,
+        routes::convert_address,
+        bulk::import_addresses,
+        bulk::export_addresses,
+    ),
+    components(schemas(
+        AddressDocument,
+        routes::AddressListItem,
+        routes::SavedAddress,
+        bulk::ImportedRecord,
+        bulk::ExportedLine,
+        ErrorDetails,
+    ))
+)]
+pub struct ApiDoc;
+
+/// The routes serving the OpenAPI document at `/openapi.json` and the
+/// Swagger UI under `/swagger-ui`.
+pub fn router() -> Router {
+    SwaggerUi::new("/swagger-ui")
+        .url("/openapi.json", ApiDoc::openapi())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::router;
+
+    #[tokio::test]
+    async fn it_should_serve_the_openapi_document() {
+        let response = router()
+            .oneshot(Request::get("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let document: Value = serde_json::from_slice(&body).unwrap();
+        assert!(document["paths"]["/api/addresses/{id}"]["put"].is_object());
+        assert!(document["paths"]["/api/convert"]["post"].is_object());
+
+        let schemas = &document["components"]["schemas"];
+        for schema in [
+            "FrenchAddress",
+            "IsoAddress",
+            "ErrorDetails",
+            "AddressListItem",
+        ] {
+            assert!(schemas[schema].is_object(), "missing schema {schema}");
+        }
+    }
+}
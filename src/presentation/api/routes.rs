@@ -1,3 +1,932 @@
-//! This is just an example file if we want to extend
-//! the presentation layer with an API depending on
-//! the api binary.
\ No newline at end of file
+use std::str::FromStr;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::{ETAG, IF_MATCH, WARNING};
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use axum::response::{AppendHeaders, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{middleware, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::auth::{authorize, ApiAuth};
+use super::bulk;
+use super::health;
+use super::service_handle::ServiceHandle;
+use crate::application::service::{AddressServiceError, Either, ErrorDetails, Format, SaveOutcome};
+use crate::application::stats::ListFilter;
+use crate::domain::repositories::{AddressFilter, AddressRepositoryError};
+use crate::domain::{
+    AddressKind, AddressStatus, Country, CountryRepr, Fingerprint, FrenchAddress, IsoAddress,
+    IsoJsonKeys, KindHint, NullFields, PersonName,
+};
+
+/// The header of the key making a save idempotent, see
+/// [`crate::application::service::AddressService::save_once`].
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// The routes of the JSON API, nested under `/api`.
+pub fn router(service: ServiceHandle) -> Router {
+    with_pages(api(service.clone()), service)
+}
+
+/// Same as [`router`], the JSON API requiring an API key or a bearer token
+/// granting the scope of each request. The admin UI, the OpenAPI document and
+/// the health probes stay public.
+pub fn router_with_auth(service: ServiceHandle, auth: ApiAuth) -> Router {
+    with_pages(
+        api(service.clone()).layer(middleware::from_fn_with_state(auth, authorize)),
+        service,
+    )
+}
+
+fn api(service: ServiceHandle) -> Router {
+    Router::new()
+        .route("/addresses", get(list_addresses).post(save_address))
+        .route(
+            "/addresses/{id}",
+            get(fetch_address)
+                .put(update_address)
+                .delete(delete_address),
+        )
+        .route("/addresses:batch", post(bulk::import_addresses))
+        .route("/addresses:export", get(bulk::export_addresses))
+        .route("/convert", post(convert_address))
+        .with_state(service)
+}
+
+/// Nests the JSON API under `/api`, next to the health probes and the pages
+/// of the enabled features.
+fn with_pages(api: Router, service: ServiceHandle) -> Router {
+    let router = Router::new()
+        .nest("/api", api)
+        .merge(health::router(service));
+    #[cfg(feature = "admin-ui")]
+    let router = router.merge(super::admin::router());
+    #[cfg(feature = "openapi")]
+    let router = router.merge(super::openapi::router());
+
+    router
+}
+
+/// An error response, serialized as `{"code": "...", "error": "..."}` with the
+/// `field` and `value` of the conversion errors.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    details: ErrorDetails,
+}
+
+impl ApiError {
+    pub(super) fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            details: ErrorDetails {
+                code,
+                error: message.into(),
+                field: None,
+                value: None,
+            },
+        }
+    }
+
+    pub(super) fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "E_BAD_REQUEST", message)
+    }
+
+    pub(super) fn unavailable() -> Self {
+        Self::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "E_UNAVAILABLE",
+            "Address service unavailable",
+        )
+    }
+}
+
+impl From<ApiError> for ErrorDetails {
+    fn from(error: ApiError) -> Self {
+        error.details
+    }
+}
+
+impl From<AddressServiceError> for ApiError {
+    fn from(error: AddressServiceError) -> Self {
+        let status = match &error {
+            AddressServiceError::InvalidJson(_)
+            | AddressServiceError::InvalidCsv(_)
+            | AddressServiceError::InvalidXml(_)
+            | AddressServiceError::ConversionError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            #[cfg(feature = "xlsx")]
+            AddressServiceError::InvalidXlsx(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AddressServiceError::StatusError(_) | AddressServiceError::AliasInUse { .. } => {
+                StatusCode::CONFLICT
+            }
+            AddressServiceError::AmbiguousId { .. } => StatusCode::BAD_REQUEST,
+            AddressServiceError::NoHistory { .. } => StatusCode::NOT_FOUND,
+            AddressServiceError::PersistenceError(error) => match error {
+                AddressRepositoryError::NotFound(_) => StatusCode::NOT_FOUND,
+                AddressRepositoryError::AlreadyExists(_) => StatusCode::CONFLICT,
+                AddressRepositoryError::Conflict { .. } => StatusCode::PRECONDITION_FAILED,
+                AddressRepositoryError::InvalidUuid(_) => StatusCode::BAD_REQUEST,
+                AddressRepositoryError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+                AddressRepositoryError::Cancelled | AddressRepositoryError::Unavailable => {
+                    StatusCode::SERVICE_UNAVAILABLE
+                }
+                AddressRepositoryError::IOFailure(_)
+                | AddressRepositoryError::SerializationFailure(_)
+                | AddressRepositoryError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+        };
+
+        Self {
+            status,
+            details: error.details(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.details)).into_response()
+    }
+}
+
+pub(super) type ApiResult<T> = Result<T, ApiError>;
+
+/// The query parameters shared by the routes. Each route only reads the ones
+/// it needs.
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct AddressQuery {
+    /// `french`, `iso20022`, `portuguese`, `brazilian` or `canonical`, or one
+    /// of their aliases (see [`Format::names`]), the input format of a save or
+    /// update, and the output format otherwise. Defaults to `french`.
+    format: Option<String>,
+    /// `individual`, `business` or `auto`. Only the addresses of this kind are
+    /// listed, `auto` listing both.
+    kind: Option<String>,
+    /// `snake`, `iso-tags` or `camel`, for ISO 20022 output.
+    json_keys: Option<String>,
+    /// `alpha2`, `alpha3` or `full-name`, how the country of the ISO 20022
+    /// output is written. Defaults to `alpha2`.
+    iso_country: Option<String>,
+    /// `include` or `omit`, whether the empty fields are written as null.
+    nulls: Option<String>,
+    /// Case insensitive text searched in the listed addresses.
+    q: Option<String>,
+    /// Only the listed addresses with this postcode.
+    postcode: Option<String>,
+    /// Only the listed addresses whose postcode starts with this prefix
+    /// (e.g., `33`).
+    postcode_prefix: Option<String>,
+    /// Only the listed addresses of this town.
+    town: Option<String>,
+    /// Only the listed addresses of this country (e.g., `FR`).
+    country: Option<String>,
+    /// Only the addresses exported updated at or after this RFC 3339 date.
+    since: Option<String>,
+    /// Whether the batch import updates the stored duplicates instead of
+    /// refusing them. Defaults to `false`.
+    upsert: Option<bool>,
+}
+
+impl AddressQuery {
+    pub(super) fn format(&self) -> ApiResult<Format> {
+        match self.format.as_deref() {
+            None => Ok(Format::French),
+            Some(format) => {
+                Format::from_str(format).map_err(|e| ApiError::bad_request(e.to_string()))
+            }
+        }
+    }
+
+    pub(super) fn kind(&self) -> ApiResult<KindHint> {
+        KindHint::from_str(self.kind.as_deref().unwrap_or("auto")).map_err(|_| {
+            ApiError::bad_request("Invalid kind: must be 'individual', 'business' or 'auto'")
+        })
+    }
+
+    pub(super) fn json_keys(&self) -> ApiResult<IsoJsonKeys> {
+        self.json_keys
+            .as_deref()
+            .map_or(Ok(IsoJsonKeys::default()), IsoJsonKeys::from_str)
+            .map_err(|_| {
+                ApiError::bad_request("Invalid json keys: must be 'snake', 'iso-tags' or 'camel'")
+            })
+    }
+
+    pub(super) fn iso_country(&self) -> ApiResult<Option<CountryRepr>> {
+        self.iso_country
+            .as_deref()
+            .map(CountryRepr::from_str)
+            .transpose()
+            .map_err(|_| {
+                ApiError::bad_request(
+                    "Invalid ISO country: must be 'alpha2', 'alpha3' or 'full-name'",
+                )
+            })
+    }
+
+    pub(super) fn filter(&self) -> ApiResult<AddressFilter> {
+        let country = self
+            .country
+            .as_deref()
+            .map(Country::from_str)
+            .transpose()
+            .map_err(|_| ApiError::bad_request("Invalid country: unsupported country"))?;
+        let kind = match self.kind()? {
+            KindHint::Auto => None,
+            KindHint::Individual => Some(AddressKind::Individual),
+            KindHint::Business => Some(AddressKind::Business),
+        };
+
+        Ok(AddressFilter {
+            postcode: self.postcode.clone(),
+            postcode_prefix: self.postcode_prefix.clone(),
+            town: self.town.clone(),
+            country,
+            kind,
+        })
+    }
+
+    pub(super) fn nulls(&self) -> ApiResult<NullFields> {
+        self.nulls
+            .as_deref()
+            .map_or(Ok(NullFields::default()), NullFields::from_str)
+            .map_err(|_| ApiError::bad_request("Invalid nulls: must be 'include' or 'omit'"))
+    }
+
+    pub(super) fn since(&self) -> ApiResult<Option<DateTime<Utc>>> {
+        self.since
+            .as_deref()
+            .map(DateTime::parse_from_rfc3339)
+            .transpose()
+            .map(|since| since.map(|since| since.with_timezone(&Utc)))
+            .map_err(|_| ApiError::bad_request("Invalid since: must be an RFC 3339 date"))
+    }
+
+    pub(super) fn upsert(&self) -> bool {
+        self.upsert.unwrap_or_default()
+    }
+}
+
+/// An address of the listing, with its metadata.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AddressListItem {
+    id: Uuid,
+    updated_at: DateTime<Utc>,
+    version: u64,
+    status: AddressStatus,
+    /// The quality score, from 0 to 100.
+    quality: u8,
+    /// The stable hash of the location of the address.
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "9a3c2f5e1d07b864"))]
+    fingerprint: Fingerprint,
+    /// The name of the recipient, when it is a person.
+    person_name: Option<PersonName>,
+    /// The address in the requested format.
+    #[cfg_attr(feature = "openapi", schema(value_type = super::openapi::AddressDocument))]
+    address: Value,
+}
+
+/// The ID given to a saved address.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SavedAddress {
+    id: Uuid,
+}
+
+pub(super) fn address_to_json(
+    address: Either<FrenchAddress, IsoAddress>,
+    json_keys: IsoJsonKeys,
+    iso_country: Option<CountryRepr>,
+    nulls: NullFields,
+) -> ApiResult<Value> {
+    match address {
+        Either::French(french) => serde_json::to_value(french),
+        Either::Iso20022(mut iso) => {
+            if let Some(repr) = iso_country {
+                iso.set_country_repr(repr);
+            }
+            iso.to_json_value(json_keys)
+        }
+        Either::Portuguese(portuguese) => serde_json::to_value(portuguese),
+        Either::Brazilian(brazilian) => serde_json::to_value(brazilian),
+        Either::Canonical(canonical) => serde_json::to_value(canonical),
+    }
+    .map(|value| nulls.apply(value))
+    .map_err(|e| AddressServiceError::from(e).into())
+}
+
+/// Transliterates the lines of a French address to Latin-1, printable on the
+/// labels of La Poste, with a warning telling which script was rewritten. The
+/// stored address keeps the original lines.
+#[cfg(feature = "translit")]
+fn transliterate(address: &mut Either<FrenchAddress, IsoAddress>) -> Option<(HeaderName, String)> {
+    match address {
+        Either::French(french) => french.transliterate().map(|script| {
+            let warning = format!("299 - \"The {script} lines are transliterated to Latin-1\"");
+            (WARNING, warning)
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "translit"))]
+fn transliterate(_: &mut Either<FrenchAddress, IsoAddress>) -> Option<(HeaderName, String)> {
+    None
+}
+
+/// Lists the stored addresses, most recently updated first, in the requested
+/// format along with their quality score.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/api/addresses",
+    params(AddressQuery),
+    responses(
+        (status = 200, description = "The stored addresses", body = [AddressListItem]),
+        (status = 400, description = "Invalid query parameter", body = ErrorDetails),
+    ),
+))]
+async fn list_addresses(
+    State(service): State<ServiceHandle>,
+    Query(query): Query<AddressQuery>,
+) -> ApiResult<Json<Vec<AddressListItem>>> {
+    let format = query.format()?;
+    let json_keys = query.json_keys()?;
+    let iso_country = query.iso_country()?;
+    let nulls = query.nulls()?;
+    let filter = ListFilter {
+        matching: query.filter()?,
+        ..Default::default()
+    };
+    let search = query.q.as_deref().unwrap_or_default().to_lowercase();
+
+    service
+        .call(move |service| {
+            service
+                .list_with(&filter)?
+                .into_iter()
+                .map(|address| {
+                    let mut formatted = service.format(&address, format)?;
+                    transliterate(&mut formatted);
+                    let converted = address_to_json(formatted, json_keys, iso_country, nulls)?;
+
+                    Ok(AddressListItem {
+                        id: address.id(),
+                        updated_at: address.updated_at(),
+                        version: address.version(),
+                        status: address.status().clone(),
+                        quality: address.quality_score(),
+                        fingerprint: address.fingerprint(),
+                        person_name: address.recipient.person_name(),
+                        address: converted,
+                    })
+                })
+                .filter(|item: &ApiResult<AddressListItem>| {
+                    item.as_ref().map_or(true, |item| {
+                        item.address.to_string().to_lowercase().contains(&search)
+                    })
+                })
+                .collect::<ApiResult<Vec<_>>>()
+        })
+        .await
+        .ok_or_else(ApiError::unavailable)?
+        .map(Json)
+}
+
+/// Parses the ID of the path, refused with the same error as a repository
+/// would give.
+fn parse_id(id: &str) -> ApiResult<Uuid> {
+    Uuid::parse_str(id)
+        .map_err(|e| AddressServiceError::from(AddressRepositoryError::from(e)).into())
+}
+
+/// The version of the address as an entity tag (`"3"`).
+fn etag(version: u64) -> [(HeaderName, String); 1] {
+    [(ETAG, format!("\"{version}\""))]
+}
+
+/// The version expected by the `If-Match` header, none without the header or
+/// for `*`.
+fn if_match(headers: &HeaderMap) -> ApiResult<Option<u64>> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(None);
+    };
+    let invalid = || ApiError::bad_request("Invalid If-Match: must be an address version");
+
+    let tag = value.to_str().map_err(|_| invalid())?.trim();
+    if tag == "*" {
+        return Ok(None);
+    }
+    let tag = tag.strip_prefix("W/").unwrap_or(tag).trim_matches('"');
+
+    tag.parse().map(Some).map_err(|_| invalid())
+}
+
+/// Fetches an address in the requested format. Archived and invalid
+/// addresses, and French lines transliterated to Latin-1, come with a
+/// `Warning` header.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/api/addresses/{id}",
+    params(("id" = String, Path, description = "UUID of the address"), AddressQuery),
+    responses(
+        (status = 200, description = "The address", body = super::openapi::AddressDocument,
+            headers(
+                ("ETag" = String, description = "The version of the address"),
+                ("Warning" = Option<String>, description = "Set for archived and invalid addresses, and transliterated lines"),
+            )),
+        (status = 400, description = "Invalid ID or query parameter", body = ErrorDetails),
+        (status = 404, description = "Unknown address", body = ErrorDetails),
+    ),
+))]
+async fn fetch_address(
+    State(service): State<ServiceHandle>,
+    Path(id): Path<String>,
+    Query(query): Query<AddressQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let id = parse_id(&id)?;
+    let format = query.format()?;
+    let json_keys = query.json_keys()?;
+    let iso_country = query.iso_country()?;
+    let nulls = query.nulls()?;
+
+    let (version, status, mut address) = service
+        .call(move |service| {
+            let address = service.fetch(id)?;
+            let converted = service.format(&address, format)?;
+            Ok::<_, AddressServiceError>((address.version(), address.status().clone(), converted))
+        })
+        .await
+        .ok_or_else(ApiError::unavailable)??;
+    // Archived and invalid addresses are still returned, with a warning.
+    let warning = (status != AddressStatus::Active)
+        .then(|| (WARNING, format!("299 - \"The address is {status}\"")));
+    let transliterated = transliterate(&mut address);
+
+    Ok((
+        etag(version),
+        AppendHeaders(warning.into_iter().chain(transliterated)),
+        Json(address_to_json(address, json_keys, iso_country, nulls)?),
+    ))
+}
+
+/// The key of the `Idempotency-Key` header, none without the header.
+fn idempotency_key(headers: &HeaderMap) -> ApiResult<Option<String>> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+
+    match value.to_str().map(str::trim) {
+        Ok(key) if (1..=255).contains(&key.len()) => Ok(Some(key.to_string())),
+        _ => Err(ApiError::bad_request(
+            "Invalid Idempotency-Key: must be 1 to 255 visible ASCII characters",
+        )),
+    }
+}
+
+/// Saves the address of the body, read in the requested format. A save
+/// retried with the `Idempotency-Key` of a previous one returns the address
+/// it saved.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/addresses",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Key of the save, unique per address saved"),
+        AddressQuery,
+    ),
+    request_body = super::openapi::AddressDocument,
+    responses(
+        (status = 200, description = "The address was already saved with this Idempotency-Key", body = SavedAddress),
+        (status = 201, description = "The address is saved", body = SavedAddress),
+        (status = 400, description = "Invalid query parameter", body = ErrorDetails),
+        (status = 409, description = "Duplicate of a stored address", body = ErrorDetails),
+        (status = 422, description = "Invalid address", body = ErrorDetails),
+    ),
+))]
+async fn save_address(
+    State(service): State<ServiceHandle>,
+    Query(query): Query<AddressQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> ApiResult<(StatusCode, Json<SavedAddress>)> {
+    let format = query.format()?;
+    let kind = query.kind()?;
+    let key = idempotency_key(&headers)?;
+
+    let outcome = service
+        .call(move |service| match key {
+            Some(key) => service.save_once(&body, format, kind, &key),
+            None => service
+                .save_as(&body, format, kind)
+                .map(SaveOutcome::Created),
+        })
+        .await
+        .ok_or_else(ApiError::unavailable)??;
+    let status = match outcome {
+        SaveOutcome::Replayed(_) => StatusCode::OK,
+        _ => StatusCode::CREATED,
+    };
+
+    Ok((status, Json(SavedAddress { id: outcome.id() })))
+}
+
+/// Updates the address, only when it is still at the version of the
+/// `If-Match` header if given.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    put,
+    path = "/api/addresses/{id}",
+    params(
+        ("id" = String, Path, description = "UUID of the address"),
+        ("If-Match" = Option<String>, Header, description = "The expected version, e.g., \"3\""),
+        AddressQuery,
+    ),
+    request_body = super::openapi::AddressDocument,
+    responses(
+        (status = 204, description = "The address is updated",
+            headers(("ETag" = String, description = "The new version of the address"))),
+        (status = 400, description = "Invalid ID, If-Match or query parameter", body = ErrorDetails),
+        (status = 404, description = "Unknown address", body = ErrorDetails),
+        (status = 412, description = "The address has been updated since", body = ErrorDetails),
+        (status = 422, description = "Invalid address", body = ErrorDetails),
+    ),
+))]
+async fn update_address(
+    State(service): State<ServiceHandle>,
+    Path(id): Path<String>,
+    Query(query): Query<AddressQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> ApiResult<impl IntoResponse> {
+    let id = parse_id(&id)?;
+    let format = query.format()?;
+    let expected_version = if_match(&headers)?;
+
+    let version = service
+        .call(move |service| service.update_if_version(id, &body, format, expected_version))
+        .await
+        .ok_or_else(ApiError::unavailable)??;
+
+    Ok((StatusCode::NO_CONTENT, etag(version)))
+}
+
+/// Deletes the address.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = "/api/addresses/{id}",
+    params(("id" = String, Path, description = "UUID of the address")),
+    responses(
+        (status = 204, description = "The address is deleted"),
+        (status = 400, description = "Invalid ID", body = ErrorDetails),
+        (status = 404, description = "Unknown address", body = ErrorDetails),
+    ),
+))]
+async fn delete_address(
+    State(service): State<ServiceHandle>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    let id = parse_id(&id)?;
+    service
+        .call(move |service| service.delete(id))
+        .await
+        .ok_or_else(ApiError::unavailable)??;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Converts the body without saving it to `format`. French and ISO 20022
+/// convert to each other, the other formats are converted from ISO 20022.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/convert",
+    params(AddressQuery),
+    request_body = super::openapi::AddressDocument,
+    responses(
+        (status = 200, description = "The converted address", body = super::openapi::AddressDocument,
+            headers(
+                ("Warning" = Option<String>, description = "Set for the fields dropped or too long in the output format, and transliterated lines"),
+            )),
+        (status = 400, description = "Invalid query parameter", body = ErrorDetails),
+        (status = 422, description = "Invalid address", body = ErrorDetails),
+    ),
+))]
+async fn convert_address(
+    State(service): State<ServiceHandle>,
+    Query(query): Query<AddressQuery>,
+    body: String,
+) -> ApiResult<impl IntoResponse> {
+    let format = query.format()?;
+    let kind = query.kind()?;
+    let json_keys = query.json_keys()?;
+    let iso_country = query.iso_country()?;
+    let nulls = query.nulls()?;
+
+    let (mut address, warnings) = service
+        .call(move |service| service.convert_with_warnings(&body, format, kind))
+        .await
+        .ok_or_else(ApiError::unavailable)??;
+    let transliterated = transliterate(&mut address);
+    let warnings = warnings
+        .into_iter()
+        .map(|warning| (WARNING, format!("299 - \"{warning}\"")));
+
+    Ok((
+        AppendHeaders(warnings.chain(transliterated)),
+        Json(address_to_json(address, json_keys, iso_country, nulls)?),
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::header::{AUTHORIZATION, ETAG, IF_MATCH, WARNING};
+    use axum::http::{Method, Request, StatusCode};
+    use axum::Router;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::{router, router_with_auth, IDEMPOTENCY_KEY};
+    use crate::application::service::AddressService;
+    use crate::infrastructure::InMemoryAddressRepository;
+    use crate::presentation::api::auth::{ApiAuth, ApiKey, AuthConfig, Scope};
+    use crate::presentation::api::service_handle::ServiceHandle;
+
+    const ADDRESS: &str = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    fn app() -> Router {
+        router(ServiceHandle::spawn(|| {
+            AddressService::new(Box::new(InMemoryAddressRepository::new()))
+        }))
+    }
+
+    async fn send(app: &Router, method: Method, uri: &str, body: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn save_search_update_and_delete() {
+        let app = app();
+
+        let (status, saved) = send(&app, Method::POST, "/api/addresses", ADDRESS).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = saved["id"].as_str().unwrap().to_string();
+
+        let (status, duplicate) = send(&app, Method::POST, "/api/addresses", ADDRESS).await;
+        assert_eq!(status, StatusCode::CONFLICT, "{duplicate}");
+
+        let (status, found) = send(&app, Method::GET, "/api/addresses?q=mios", "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(found[0]["id"], id.as_str());
+        assert_eq!(found[0]["address"]["postal"], "33380 MIOS");
+        assert_eq!(found[0]["person_name"]["given_name"], "Jean");
+        assert_eq!(found[0]["fingerprint"].as_str().map(str::len), Some(16));
+        let (_, not_found) = send(&app, Method::GET, "/api/addresses?q=paris", "").await;
+        assert_eq!(not_found, Value::Array(vec![]));
+        let uri = "/api/addresses?postcode_prefix=33&town=mios&country=FR&kind=individual";
+        let (_, filtered) = send(&app, Method::GET, uri, "").await;
+        assert_eq!(filtered[0]["id"], id.as_str());
+        let (_, businesses) = send(&app, Method::GET, "/api/addresses?kind=business", "").await;
+        assert_eq!(businesses, Value::Array(vec![]));
+        let (status, _) = send(&app, Method::GET, "/api/addresses?country=XX", "").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let updated = ADDRESS.replace("25 RUE", "10 RUE");
+        let uri = format!("/api/addresses/{id}");
+        let (status, _) = send(&app, Method::PUT, &uri, &updated).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let iso_uri = format!("{uri}?format=iso20022&json_keys=iso-tags");
+        let (status, iso) = send(&app, Method::GET, &iso_uri, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(iso["PstlAdr"]["BldgNb"], "10");
+
+        let (status, _) = send(&app, Method::DELETE, &uri, "").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, error) = send(&app, Method::GET, &uri, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(error["error"].is_string());
+        assert_eq!(error["code"], "E_NOT_FOUND");
+        assert_eq!(error["value"], id.as_str());
+    }
+
+    #[tokio::test]
+    async fn update_if_match() {
+        let app = app();
+        let (_, saved) = send(&app, Method::POST, "/api/addresses", ADDRESS).await;
+        let uri = format!("/api/addresses/{}", saved["id"].as_str().unwrap());
+
+        let get = Request::get(&uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(get).await.unwrap();
+        assert_eq!(response.headers()[ETAG], "\"1\"");
+
+        let put = |version: &str| {
+            Request::put(&uri)
+                .header(IF_MATCH, version)
+                .body(Body::from(ADDRESS.replace("25 RUE", "10 RUE")))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(put("\"1\"")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[ETAG], "\"2\"");
+
+        let response = app.clone().oneshot(put("\"1\"")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["code"], "E_VERSION_CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn retried_save_with_idempotency_key() {
+        let app = app();
+        let post = |key: &str| {
+            Request::post("/api/addresses")
+                .header(IDEMPOTENCY_KEY, key)
+                .body(Body::from(ADDRESS))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(post("order-42")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let saved: Value = serde_json::from_slice(&body).unwrap();
+
+        let response = app.clone().oneshot(post("order-42")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let retried: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(retried["id"], saved["id"]);
+
+        let response = app.clone().oneshot(post("order-43")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = app.clone().oneshot(post("")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn convert_without_saving() {
+        let app = app();
+
+        let (status, iso) = send(&app, Method::POST, "/api/convert?format=iso20022", ADDRESS).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(iso["postal_address"]["town_name"], "MIOS");
+        assert_eq!(iso["postal_address"]["floor"], Value::Null);
+        assert!(iso["postal_address"].get("floor").is_some());
+
+        let uri = "/api/convert?format=iso20022&nulls=omit";
+        let (_, iso) = send(&app, Method::POST, uri, ADDRESS).await;
+        assert!(iso["postal_address"].get("floor").is_none());
+        assert_eq!(iso["postal_address"]["town_name"], "MIOS");
+
+        let uri = "/api/convert?format=iso20022&iso_country=full-name";
+        let (_, iso) = send(&app, Method::POST, uri, ADDRESS).await;
+        assert_eq!(iso["postal_address"]["country"], "FRANCE");
+
+        let uri = "/api/convert?format=iso20022&iso_country=fr";
+        let (status, _) = send(&app, Method::POST, uri, ADDRESS).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let uri = "/api/convert?format=iso20022&nulls=skip";
+        let (status, _) = send(&app, Method::POST, uri, ADDRESS).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = send(&app, Method::POST, "/api/convert?format=xml", ADDRESS).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = send(&app, Method::POST, "/api/convert?format=french", ADDRESS).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (_, listed) = send(&app, Method::GET, "/api/addresses", "").await;
+        assert_eq!(listed, Value::Array(vec![]));
+    }
+
+    #[tokio::test]
+    async fn convert_warns_about_the_dropped_fields() {
+        let app = app();
+        let (_, mut iso) = send(&app, Method::POST, "/api/convert?format=iso20022", ADDRESS).await;
+        iso["postal_address"]["country_sub_division"] = "GIRONDE".into();
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/convert?format=french")
+            .body(Body::from(iso.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[WARNING],
+            "299 - \"postal_details.country_subdivision dropped in the target format\""
+        );
+    }
+
+    #[cfg(feature = "translit")]
+    #[tokio::test]
+    async fn convert_transliterates_the_french_lines() {
+        let app = app();
+        let (_, mut iso) = send(&app, Method::POST, "/api/convert?format=iso20022", ADDRESS).await;
+        iso["postal_address"]["street_name"] = "ул. Тверская".into();
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/convert?format=french")
+            .body(Body::from(iso.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[WARNING],
+            "299 - \"The Cyrillic lines are transliterated to Latin-1\""
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let french: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(french["street"], "25 ul. Tverskaia");
+    }
+
+    #[tokio::test]
+    async fn conversion_errors_have_a_code_and_a_field() {
+        let app = app();
+        let invalid = ADDRESS.replace("33380 MIOS", "MIOS");
+
+        let (status, error) =
+            send(&app, Method::POST, "/api/convert?format=iso20022", &invalid).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error["code"], "E_POSTAL_FORMAT");
+        assert_eq!(error["field"], "postal");
+        assert_eq!(error["value"], "MIOS");
+        assert!(error["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn requests_need_a_key_granting_their_scope() {
+        let auth = ApiAuth::new(AuthConfig {
+            keys: vec![ApiKey {
+                key: "reader".to_string(),
+                scopes: vec![Scope::Read],
+            }],
+            jwt: None,
+        });
+        let app = router_with_auth(
+            ServiceHandle::spawn(|| {
+                AddressService::new(Box::new(InMemoryAddressRepository::new()))
+            }),
+            auth.clone(),
+        );
+        let send_as = |key: &str, method: Method, uri: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(AUTHORIZATION, format!("Bearer {key}"))
+                .body(Body::from(ADDRESS))
+                .unwrap();
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        let (status, error) = send(&app, Method::GET, "/api/addresses", "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error["code"], "E_UNAUTHORIZED");
+        assert_eq!(
+            send_as("reader", Method::GET, "/api/addresses").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send_as("reader", Method::POST, "/api/convert?format=iso20022").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send_as("reader", Method::POST, "/api/addresses").await,
+            StatusCode::FORBIDDEN
+        );
+
+        // Replacing the config revokes the previous keys.
+        auth.replace(AuthConfig {
+            keys: vec![ApiKey {
+                key: "writer".to_string(),
+                scopes: vec![Scope::Read, Scope::Write],
+            }],
+            jwt: None,
+        });
+        assert_eq!(
+            send_as("reader", Method::GET, "/api/addresses").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send_as("writer", Method::POST, "/api/addresses").await,
+            StatusCode::CREATED
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+use tokio::sync::oneshot;
+
+use crate::application::service::BoxedAddressService;
+
+type Job = Box<dyn FnOnce(&BoxedAddressService) + Send>;
+
+/// A cloneable handle to a [`BoxedAddressService`] shared by the request
+/// handlers.
+///
+/// Repositories aren't required to be thread safe, so the service lives on a
+/// dedicated worker thread executing the calls in order, and the handlers
+/// await their result without blocking the runtime.
+#[derive(Clone)]
+pub struct ServiceHandle {
+    jobs: mpsc::Sender<Job>,
+    worker: Arc<JoinHandle<()>>,
+}
+
+impl ServiceHandle {
+    /// Builds the service with `build` on the worker thread.
+    pub fn spawn<B>(build: B) -> Self
+    where
+        B: FnOnce() -> BoxedAddressService + Send + 'static,
+    {
+        let (jobs, pending_jobs) = mpsc::channel::<Job>();
+
+        let worker = thread::spawn(move || {
+            let service = build();
+            for job in pending_jobs {
+                job(&service);
+            }
+        });
+
+        Self {
+            jobs,
+            worker: Arc::new(worker),
+        }
+    }
+
+    /// Whether the worker thread still runs, without waiting for the calls
+    /// in progress.
+    pub fn is_alive(&self) -> bool {
+        !self.worker.is_finished()
+    }
+
+    /// Runs `operation` on the service. Returns `None` when the worker thread
+    /// is gone (e.g., after a panic).
+    pub async fn call<T, F>(&self, operation: F) -> Option<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&BoxedAddressService) -> T + Send + 'static,
+    {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |service| {
+                // The request may have been dropped, ignore the closed channel.
+                let _ = result_tx.send(operation(service));
+            }))
+            .ok()?;
+
+        result_rx.await.ok()
+    }
+}
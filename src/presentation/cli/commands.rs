@@ -1,65 +1,1485 @@
-use crate::application::service::{AddressService, Either, Format};
-use clap::{Parser, Subcommand};
+use super::compose::Prompter;
+use super::confirm::Destructive;
+use super::inspect;
+use super::progress::ProgressReporter;
+use super::table;
+#[cfg(feature = "xlsx")]
+use crate::application::batch::XlsxOptions;
+use crate::application::batch::{BatchFileKind, BatchReport, ImportAnalysis, InputEncoding};
+use crate::application::dedupe::DedupeStrategy;
+use crate::application::export::ExportColumn;
+use crate::application::generate::AddressGenerator;
+use crate::application::service::{
+    AddressServiceError, BoxedAddressService, Either, Format, SaveOutcome, ServiceResult,
+};
+use crate::application::stats::ListFilter;
+use crate::application::suggest::SuggestField;
+use crate::application::sync;
+use crate::domain::repositories::{AddressFilter, Tenant};
+use crate::domain::{
+    AddressKind, AddressStatus, Alias, ConversionTrace, Country, CountryRepr, Fingerprint,
+    FrenchAddress, FrenchParserRules, IsoAddress, IsoJsonKeys, KindHint, LongFieldPolicy,
+    MailOrigin, Normalizer, NullFields, NumberExtStyle, OverseasCountry, Recipient, Routing,
+    StreetPolicy, StreetReferential, UnstructuredToStructured, ValidationPolicy,
+};
+use crate::infrastructure::{Profile, StorageUrl};
+use chrono::{DateTime, Utc};
+use clap::builder::PossibleValue;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often `tail --follow` checks the change log for new changes.
+const TAIL_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Parser)]
 #[command(
     name = "address_converter",
-    about = "Convert and manage postal addresses (french/iso20022)"
+    about = "Convert and manage postal addresses (french/iso20022/portuguese/brazilian/canonical)"
 )]
 pub struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Maximum duration of each storage operation, in seconds, unlimited by
+    /// default
+    #[arg(long, global = true)]
+    pub timeout: Option<u64>,
+    /// Only work on the addresses of this tenant, isolated from the others
+    #[arg(long, global = true)]
+    pub tenant: Option<Tenant>,
+    /// JSON file whose `profile` section names the settings of each
+    /// environment (e.g., `dev` and `prod`), `AC_CONFIG` by default
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Profile of the config to use, `AC_PROFILE` by default. Its storage,
+    /// tenant and timeout apply unless given on the command line
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Where the mail is sent from: 'france' writes the country line of the
+    /// french addresses in french, 'international' in english
+    #[arg(long, global = true, default_value = "france")]
+    pub mail_origin: MailOrigin,
+    /// How the country of the ISO 20022 addresses is written: 'alpha2'
+    /// (e.g., 'FR'), 'alpha3' ('FRA') or 'full-name' ('FRANCE')
+    #[arg(long, global = true, default_value = "alpha2")]
+    pub iso_country: CountryRepr,
+    /// The ISO 20022 country of the french addresses overseas: 'france'
+    /// ('FR', the territory being the subdivision, e.g., 'FR-974') or
+    /// 'territory' ('RE')
+    #[arg(long, global = true, default_value = "france")]
+    pub iso_overseas: OverseasCountry,
+    /// How the repetition index is written in the ISO 20022 building number:
+    /// 'attached' (e.g., '2BIS') or 'spaced' ('2 BIS')
+    #[arg(long, global = true, default_value = "attached")]
+    pub iso_number_ext: NumberExtStyle,
+    /// What becomes of the ISO 20022 elements longer than their type allows
+    /// (e.g., 70 characters): 'keep' them with a warning, 'error', 'truncate'
+    /// them, or 'wrap' the floor into the room and the department into the
+    /// sub-department
+    #[arg(long, global = true, default_value = "keep")]
+    pub iso_long_fields: LongFieldPolicy,
+    /// Whether business addresses need a street: 'required', 'optional', or
+    /// 'postbox-substitutes' to accept a postbox (BP) instead
+    #[arg(long, global = true, default_value = "postbox-substitutes")]
+    pub street_policy: StreetPolicy,
+    /// Abbreviate the address lines longer than 38 characters
+    #[arg(long, global = true)]
+    pub normalize: bool,
+    /// Reject the addresses whose postcode isn't valid for their country,
+    /// and write the others in the standard form (e.g., 'SW1A 1AA')
+    #[arg(long, global = true)]
+    pub check_postcodes: bool,
+    /// Don't color the tables, colored only when stdout is a terminal
+    #[arg(long, global = true)]
+    pub no_color: bool,
+    /// Leave the empty optional fields out of the JSON addresses
+    #[arg(long, global = true, conflicts_with = "include_nulls")]
+    pub omit_nulls: bool,
+    /// Write the empty optional fields of the JSON addresses as null, the
+    /// default
+    #[arg(long, global = true)]
+    pub include_nulls: bool,
+    /// JSON file whose `abbreviations` section adds custom abbreviations to
+    /// the standard ones, implies --normalize
+    #[arg(long, global = true)]
+    pub abbrev_file: Option<PathBuf>,
+    /// JSON file whose `parser.french` section replaces the patterns reading
+    /// the french addresses (e.g., `postbox_regex`)
+    #[arg(long, global = true)]
+    pub parser_config: Option<PathBuf>,
+    /// Read the street and postal lines with hand-rolled parsers instead of
+    /// the built-in patterns, for the throughput of the bulk loads
+    #[cfg(feature = "fast-parse")]
+    #[arg(long, global = true)]
+    pub fast_parse: bool,
+    /// JSON file whose `validation` section sets, per country, whether an
+    /// invalid postcode, town or country line is an 'error', a 'warn'ing or
+    /// ignored
+    #[arg(long, global = true)]
+    pub validation_config: Option<PathBuf>,
+    /// Write the kind of the french and ISO 20022 addresses in a 'kind'
+    /// field ('individual' or 'business'), as accepted by all the inputs
+    #[arg(long, global = true)]
+    pub tagged: bool,
+    /// Don't ask before running the commands losing stored data (e.g.,
+    /// 'delete', 'dedupe --apply'), required to run the bulk ones
+    /// non-interactively
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+    /// Don't print the informational messages (e.g., 'Saved address with
+    /// ID'), the versions and the warnings, only the results and the errors
+    #[arg(long, global = true)]
+    pub quiet: bool,
+}
+
+/// The sections of the file given to `--abbrev-file`.
+#[derive(Debug, Default, Deserialize)]
+struct AbbreviationConfig {
+    /// Words, or group of words, and their abbreviation.
+    #[serde(default)]
+    abbreviations: HashMap<String, String>,
+}
+
+impl Cli {
+    /// How `--omit-nulls` and `--include-nulls` write the empty fields.
+    pub fn null_fields(&self) -> NullFields {
+        if self.omit_nulls {
+            NullFields::Omit
+        } else {
+            NullFields::Include
+        }
+    }
+
+    /// The normalizer requested by `--normalize` and `--abbrev-file`, if any.
+    pub fn normalizer(&self) -> Result<Option<Normalizer>, String> {
+        let Some(path) = &self.abbrev_file else {
+            return Ok(self.normalize.then(Normalizer::new));
+        };
+
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        let config: AbbreviationConfig = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid abbreviation file {}: {e}", path.display()))?;
+
+        Ok(Some(
+            Normalizer::new().with_dictionary(config.abbreviations),
+        ))
+    }
+
+    /// The french parser rules of `--parser-config`, if any.
+    pub fn french_parser_rules(&self) -> Result<Option<FrenchParserRules>, String> {
+        let rules = match &self.parser_config {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+                FrenchParserRules::from_config(&content)
+                    .map(Some)
+                    .map_err(|e| format!("{e} in {}", path.display()))?
+            }
+            None => None,
+        };
+
+        #[cfg(feature = "fast-parse")]
+        if self.fast_parse {
+            return Ok(Some(rules.unwrap_or_default().with_fast_parse()));
+        }
+
+        Ok(rules)
+    }
+
+    /// The profile selected by `--profile` or `AC_PROFILE`, read from the
+    /// config of `--config` or `AC_CONFIG`, if any. Its tenant and timeout
+    /// are applied unless given, and its storage is returned.
+    pub fn apply_profile(&mut self) -> Result<Option<StorageUrl>, String> {
+        let Some(name) = self.profile.clone().or_else(|| env::var("AC_PROFILE").ok()) else {
+            return Ok(None);
+        };
+        let path = self
+            .config
+            .clone()
+            .or_else(|| env::var_os("AC_CONFIG").map(PathBuf::from))
+            .ok_or_else(|| format!("No config to read the profile `{name}` from: give --config"))?;
+
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        let profile = Profile::from_config(&content, &name, |var| env::var(var).ok())
+            .map_err(|e| format!("{e} in {}", path.display()))?;
+        self.tenant = self.tenant.take().or(profile.tenant);
+        self.timeout = self.timeout.or(profile.timeout);
+
+        Ok(profile.storage)
+    }
+
+    /// The validation policy of `--validation-config`, if any.
+    pub fn validation_policy(&self) -> Result<Option<ValidationPolicy>, String> {
+        let Some(path) = &self.validation_config else {
+            return Ok(None);
+        };
+
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        ValidationPolicy::from_config(&content)
+            .map(Some)
+            .map_err(|e| format!("{e} in {}", path.display()))
+    }
+}
+
+/// How the single address commands write their result and errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum OutputMode {
+    /// Human readable messages, errors on stderr.
+    #[default]
+    Text,
+    /// JSON documents on stdout, including the errors with their code and
+    /// field path.
+    Json,
+}
+
+/// The options of `list` and `export` selecting the addresses by their
+/// location or kind.
+#[derive(Args, Debug, Default)]
+pub struct FilterArgs {
+    #[arg(long, help = "Only the addresses with this postcode")]
+    postcode: Option<String>,
+    #[arg(
+        long,
+        help = "Only the addresses whose postcode starts with this prefix (e.g., 33)"
+    )]
+    postcode_prefix: Option<String>,
+    #[arg(long, help = "Only the addresses of this town")]
+    town: Option<String>,
+    #[arg(long, help = "Only the addresses of this country (e.g., FR, FRANCE)")]
+    country: Option<Country>,
+    #[arg(long, help = "Only the 'individual' or 'business' addresses")]
+    kind: Option<AddressKind>,
+}
+
+impl From<FilterArgs> for AddressFilter {
+    fn from(args: FilterArgs) -> Self {
+        AddressFilter {
+            postcode: args.postcode,
+            postcode_prefix: args.postcode_prefix,
+            town: args.town,
+            country: args.country,
+            kind: args.kind,
+        }
+    }
+}
+
+/// The ID of an address given on the command line: a full UUID, with or
+/// without hyphens and in any case, the beginning of one, matching a single
+/// stored address (see
+/// [`crate::application::service::AddressService::resolve_prefix`]), or the
+/// alias of an address.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AddressId {
+    Full(Uuid),
+    Prefix(String),
+    Alias(Alias),
+}
+
+impl FromStr for AddressId {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        if let Ok(uuid) = Uuid::parse_str(id) {
+            return Ok(AddressId::Full(uuid));
+        }
+
+        let digits = id.replace('-', "");
+        if !digits.is_empty() && digits.len() < 32 && digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            Ok(AddressId::Prefix(id.to_string()))
+        } else {
+            // Aliases can't be read as a prefix, see `Alias::from_str`.
+            id.parse().map(AddressId::Alias).map_err(|_| {
+                format!(
+                    "`{id}` is neither a UUID, the beginning of one (hexadecimal digits), nor an alias"
+                )
+            })
+        }
+    }
+}
+
+impl fmt::Display for AddressId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressId::Full(id) => write!(f, "{id}"),
+            AddressId::Prefix(prefix) => f.write_str(prefix),
+            AddressId::Alias(alias) => write!(f, "{alias}"),
+        }
+    }
+}
+
+impl AddressId {
+    fn resolve(&self, service: &BoxedAddressService) -> ServiceResult<Uuid> {
+        match self {
+            AddressId::Full(id) => Ok(*id),
+            AddressId::Prefix(prefix) => service.resolve_prefix(prefix),
+            AddressId::Alias(alias) => service.resolve_alias(alias),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum AliasCommands {
+    /// Name an address, replacing its previous alias
+    Add {
+        #[arg(help = "ID of the address to name, its beginning or its alias")]
+        id: AddressId,
+        #[arg(help = "Alias, e.g., 'hq-bordeaux'")]
+        alias: Alias,
+    },
+    /// Remove an alias, the address is kept
+    Remove {
+        #[arg(help = "Alias to remove")]
+        alias: Alias,
+    },
+    /// List the aliases and the ID of the address they name
+    List,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Save a new address
     Save {
-        #[arg(long, help = "JSON-formatted address string")]
+        #[arg(
+            long,
+            help = "JSON-formatted address string, or the raw lines of a french address"
+        )]
         address: String,
-        #[arg(long, help = "Input format: 'french' or 'iso20022'")]
-        from_format: String,
+        #[arg(long, value_enum, ignore_case = true, help = "Input format")]
+        from_format: InputFormat,
+        #[arg(long, help = "Update the stored duplicate instead of failing")]
+        upsert: bool,
+        #[arg(
+            long,
+            conflicts_with = "upsert",
+            help = "Key of the save, a retry with the same key returns the address saved the first time"
+        )]
+        idempotency_key: Option<String>,
+        #[arg(long, help = "Name the saved address, accepted in place of its ID")]
+        alias: Option<Alias>,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Address kind: 'individual', 'business' or 'auto' to detect it"
+        )]
+        kind: String,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
+        #[arg(
+            long,
+            conflicts_with = "output",
+            help = "Print only the ID of the address, e.g., to pipe it to another command"
+        )]
+        id_only: bool,
     },
     /// Update an existing address
     Update {
-        #[arg(help = "UUID of the address to update")]
-        id: String,
-        #[arg(long, help = "JSON-formatted address string")]
+        #[arg(help = "UUID of the address to update, its beginning or its alias")]
+        id: AddressId,
+        #[arg(
+            long,
+            help = "JSON-formatted address string, or the raw lines of a french address"
+        )]
         address: String,
-        #[arg(long, help = "Input format: 'french' or 'iso20022'")]
-        from_format: String,
+        #[arg(long, value_enum, ignore_case = true, help = "Input format")]
+        from_format: InputFormat,
+        #[arg(long, help = "Only update when the stored address is at this version")]
+        if_version: Option<u64>,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
     },
     /// Delete an address
     Delete {
-        #[arg(help = "UUID of the address to delete")]
-        id: String,
+        #[arg(help = "UUID of the address to delete, its beginning or its alias")]
+        id: AddressId,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
     },
-    /// Fetch an address in the specified format
+    /// Fetch addresses in the specified format
     Fetch {
-        #[arg(help = "UUID of the address to fetch")]
-        id: String,
-        #[arg(long, help = "Output format: 'french' or 'iso20022'")]
-        format: String,
+        #[arg(
+            required_unless_present = "prefix",
+            help = "UUIDs of the addresses to fetch, their beginning or their alias"
+        )]
+        ids: Vec<AddressId>,
+        #[arg(
+            long,
+            help = "Beginning of the UUID of an address to fetch, when it matches a single address"
+        )]
+        prefix: Vec<String>,
+        #[arg(
+            long,
+            value_enum,
+            ignore_case = true,
+            required = true,
+            value_delimiter = ',',
+            help = "Output format, or several separated by commas (e.g., 'french,iso20022') printed as an object keyed by format"
+        )]
+        format: Vec<Format>,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+        #[arg(
+            long,
+            help = "Fetch the addresses as they were stored at this RFC 3339 date (e.g., 2024-06-01T00:00:00Z)"
+        )]
+        as_of: Option<String>,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
+    },
+    /// Print the stored address as it is held by the domain model, with its
+    /// timestamps, version, status and fingerprint
+    Inspect {
+        #[arg(help = "UUID of the address to inspect, its beginning or its alias")]
+        id: AddressId,
+    },
+    /// Convert an address without saving it
+    Convert {
+        #[arg(
+            long,
+            help = "JSON-formatted address string, or the raw lines of a french address"
+        )]
+        address: String,
+        #[arg(
+            long,
+            value_enum,
+            ignore_case = true,
+            help = "Input format [default: the source of --to-format]"
+        )]
+        from_format: Option<InputFormat>,
+        #[arg(long, value_enum, ignore_case = true, help = "Output format")]
+        to_format: Format,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Address kind: 'individual', 'business' or 'auto' to detect it"
+        )]
+        kind: String,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
+        #[arg(
+            long,
+            help = "Also print which input field populated which output field"
+        )]
+        explain: bool,
+        #[arg(
+            long,
+            conflicts_with = "explain",
+            help = "Print the ISO 20022 address as an XML party block (<Pty>)"
+        )]
+        party_xml: bool,
+    },
+    /// Check an address as it would be before being saved, without saving it
+    Validate {
+        #[arg(
+            long,
+            help = "JSON-formatted address string, or the raw lines of a french address"
+        )]
+        address: String,
+        #[arg(long, value_enum, ignore_case = true, help = "Input format")]
+        from_format: InputFormat,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Address kind: 'individual', 'business' or 'auto' to detect it"
+        )]
+        kind: String,
+        #[arg(
+            long,
+            value_name = "CSV",
+            help = "Official streets of the communes (La Poste or BAN CSV) the street must be one of"
+        )]
+        against_referential: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
+    },
+    /// Cleanse an address (casing, accents, punctuation, abbreviations) without converting it
+    Normalize {
+        #[arg(
+            long,
+            help = "JSON-formatted address string, or the raw lines of a french address"
+        )]
+        address: String,
+        #[arg(long, value_enum, ignore_case = true, help = "Input and output format")]
+        from_format: InputFormat,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Address kind: 'individual', 'business' or 'auto' to detect it"
+        )]
+        kind: String,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
+    },
+    /// Convert the party addresses of an ISO 20022 XML message (pain.001, pacs.008)
+    FromXml {
+        #[arg(long, help = "XML message to read [default: stdin]")]
+        file: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            ignore_case = true,
+            default_value = "iso20022",
+            help = "Output format"
+        )]
+        to_format: Format,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+        #[arg(long, value_enum, default_value_t, help = "Output mode")]
+        output: OutputMode,
+    },
+    /// Prompt for each field of an address and print it as JSON, without saving
+    Compose {
+        #[arg(
+            long,
+            value_enum,
+            ignore_case = true,
+            help = "Address format: 'french' or 'iso20022'"
+        )]
+        format: Format,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Address kind: 'individual', 'business' or 'auto' to ask for it"
+        )]
+        kind: String,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+    },
+    /// Generate synthetic french addresses, one JSON per line
+    Generate {
+        #[arg(long, default_value_t = 10, help = "Number of addresses")]
+        count: usize,
+        #[arg(
+            long,
+            value_enum,
+            ignore_case = true,
+            default_value = "french",
+            help = "Output format: 'french' or 'iso20022'"
+        )]
+        format: Format,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Address kind: 'individual', 'business' or 'auto' for both"
+        )]
+        kind: String,
+        #[arg(long, help = "Seed generating the same addresses on each run")]
+        seed: Option<u64>,
+        #[arg(long, help = "Also save the generated addresses")]
+        save: bool,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+    },
+    /// Suggest stored values starting with the given prefix
+    Suggest {
+        #[arg(help = "Beginning of the value to complete")]
+        prefix: String,
+        #[arg(long, help = "Field to complete: 'street', 'town' or 'recipient'")]
+        field: String,
+        #[arg(long, default_value_t = 10, help = "Maximum number of suggestions")]
+        limit: usize,
+    },
+    /// Import the addresses of a JSON/CSV/XLSX file
+    Import {
+        #[arg(help = "JSON (single object, array or newline delimited), CSV or XLSX file")]
+        file: PathBuf,
+        #[arg(long, value_enum, ignore_case = true, help = "Input format")]
+        from_format: Format,
+        #[arg(long, help = "Update stored duplicates instead of rejecting them")]
+        upsert: bool,
+        #[arg(
+            long,
+            help = "Report new, duplicate and invalid records without writing anything"
+        )]
+        analyze: bool,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Encoding of the JSON/CSV files: 'utf-8', 'latin1' (Windows-1252) or 'auto' to detect it"
+        )]
+        input_encoding: InputEncoding,
+        #[cfg(feature = "xlsx")]
+        #[arg(
+            long,
+            help = "Sheet of the XLSX file to import [default: the first one]"
+        )]
+        sheet: Option<String>,
+        #[cfg(feature = "xlsx")]
+        #[arg(
+            long = "column",
+            value_name = "HEADER=FIELD",
+            help = "Read the XLSX column named HEADER as FIELD (e.g., 'Code postal=postal')"
+        )]
+        columns: Vec<String>,
+    },
+    /// Convert the addresses of a JSON/CSV file, or of every such file of a
+    /// directory, without saving them
+    Reformat {
+        #[arg(
+            help = "JSON (single object, array or newline delimited) or CSV file, or a directory of such files"
+        )]
+        input: PathBuf,
+        #[arg(
+            long = "from",
+            alias = "from-format",
+            value_enum,
+            ignore_case = true,
+            help = "Input format"
+        )]
+        from_format: Format,
+        #[arg(
+            long = "to",
+            alias = "to-format",
+            value_enum,
+            ignore_case = true,
+            help = "Output format"
+        )]
+        to_format: Format,
+        #[arg(
+            long,
+            help = "Directory to write the converted addresses into, one newline delimited JSON file per input file"
+        )]
+        out: PathBuf,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Encoding of the JSON/CSV files: 'utf-8', 'latin1' (Windows-1252) or 'auto' to detect it"
+        )]
+        input_encoding: InputEncoding,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+    },
+    /// Migrate unstructured ISO 20022 addresses (AdrLine) to the structured
+    /// elements, without saving them
+    MigrateIso {
+        #[arg(
+            help = "JSON file (single object, array or newline delimited) of ISO 20022 parties with a name and AdrLine postal lines"
+        )]
+        input: PathBuf,
+        #[arg(
+            long,
+            help = "File to write the structured addresses into as newline delimited JSON, instead of the standard output"
+        )]
+        out: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "File to write the confidence report of each address into as newline delimited JSON"
+        )]
+        report: Option<PathBuf>,
+        #[arg(
+            long,
+            default_value_t = 80,
+            value_parser = clap::value_parser!(u8).range(0..=100),
+            help = "Confidence under which an address needs a manual review"
+        )]
+        review_threshold: u8,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+    },
+    /// Export the stored addresses as a JSON array, or as CSV with --columns
+    Export {
+        #[arg(
+            long,
+            required_unless_present = "columns",
+            conflicts_with = "columns",
+            value_enum,
+            ignore_case = true,
+            help = "Output format"
+        )]
+        format: Option<Format>,
+        #[arg(
+            long,
+            num_args = 0..=1,
+            help = "Export as CSV with these comma separated columns, in this order [default: id,name,street,postcode,town,country]"
+        )]
+        columns: Option<Option<String>>,
+        #[arg(
+            long,
+            help = "Only export addresses changed since this RFC 3339 date (e.g., 2024-01-01T00:00:00Z)"
+        )]
+        since: Option<String>,
+        #[arg(
+            long,
+            default_value = "snake",
+            help = "ISO 20022 output keys: 'snake', 'iso-tags' or 'camel'"
+        )]
+        json_keys: String,
+        #[arg(long, help = "File to write the export to [default: stdout]")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// List the stored addresses with their quality score
+    List {
+        #[arg(
+            long,
+            default_value_t = 0,
+            value_parser = clap::value_parser!(u8).range(0..=100),
+            help = "Only list addresses scoring at least this quality (0-100)"
+        )]
+        min_quality: u8,
+        #[arg(
+            long,
+            help = "Only list the french addresses of this department (e.g., 33, 2A, 971)"
+        )]
+        department: Option<String>,
+        #[arg(long, help = "Also list the archived addresses")]
+        archived: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Select the addresses to mail, one per household, as a print-ready CSV
+    MailingList {
+        #[arg(
+            long,
+            default_value_t = 0,
+            value_parser = clap::value_parser!(u8).range(0..=100),
+            help = "Only select addresses scoring at least this quality (0-100)"
+        )]
+        min_quality: u8,
+        #[arg(
+            long,
+            help = "Only select the french addresses of this department (e.g., 33, 2A, 971)"
+        )]
+        department: Option<String>,
+        #[arg(long, help = "File to write the mailing list to [default: stdout]")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Archive an address, hidden from the listings from then on
+    Archive {
+        #[arg(help = "ID of the address to archive, its beginning or its alias")]
+        id: AddressId,
+    },
+    /// Mark an address as invalid, e.g., when mail was returned
+    MarkInvalid {
+        #[arg(help = "ID of the address to mark, its beginning or its alias")]
+        id: AddressId,
+        #[arg(long, help = "Why the address is invalid")]
+        reason: String,
+    },
+    /// Set the routing metadata of bulk mailers on an address
+    Route {
+        #[arg(help = "ID of the address to annotate, its beginning or its alias")]
+        id: AddressId,
+        #[arg(long, help = "La Poste sorting code (code ROUDIS)")]
+        sort_code: Option<String>,
+        #[arg(long, help = "Mailing batch of the address")]
+        batch_id: Option<String>,
+        #[arg(long, help = "Remove the routing metadata not given")]
+        clear: bool,
+    },
+    /// Change the recipient of an address, e.g., the contact person of a
+    /// business, leaving its postal details as they are
+    SetRecipient {
+        #[arg(help = "ID of the address to update, its beginning or its alias")]
+        id: AddressId,
+        #[arg(
+            long,
+            conflicts_with_all = ["company", "contact", "service", "clear"],
+            help = "Name of the individual receiving the mail (e.g., 'Monsieur Jean DELHOURME')"
+        )]
+        name: Option<String>,
+        #[arg(long, help = "Name of the business, kept when not given")]
+        company: Option<String>,
+        #[arg(
+            long,
+            help = "Contact person at the business (e.g., 'Mademoiselle Lucie MARTIN')"
+        )]
+        contact: Option<String>,
+        #[arg(long, help = "Service of the business (e.g., 'Service achat')")]
+        service: Option<String>,
+        #[arg(long, help = "Remove the contact person and service not given")]
+        clear: bool,
+    },
+    /// Set the free-form notes and metadata of an address
+    Annotate {
+        #[arg(help = "ID of the address to annotate, its beginning or its alias")]
+        id: AddressId,
+        #[arg(long, help = "Notes about the address, replacing the previous ones")]
+        note: Option<String>,
+        #[arg(long, help = "Remove the notes")]
+        clear_note: bool,
+        #[arg(
+            long = "set",
+            value_name = "KEY=VALUE",
+            help = "Metadata to set, can be repeated"
+        )]
+        set: Vec<String>,
+        #[arg(long, value_name = "KEY", help = "Metadata to remove, can be repeated")]
+        unset: Vec<String>,
+    },
+    /// Manage the aliases naming the addresses
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommands,
+    },
+    /// Print statistics about the stored addresses
+    Stats,
+    /// Reclaim the space left by the previous writes in the storage
+    Compact,
+    /// Find the stored addresses matching words, the most relevant first
+    Search {
+        #[arg(help = "Words starting the words of the address (e.g., 'delh mios')")]
+        query: String,
+        #[arg(long, default_value_t = 20, help = "Maximum number of addresses")]
+        limit: usize,
+    },
+    /// Print the IDs of the stored addresses at a street, or with a
+    /// fingerprint, failing when there is none
+    Lookup {
+        #[arg(
+            long,
+            conflicts_with = "street",
+            required_unless_present = "street",
+            help = "Fingerprint of the address, 16 hexadecimal digits"
+        )]
+        fingerprint: Option<Fingerprint>,
+        #[arg(
+            long,
+            requires = "postcode",
+            help = "Street line, e.g., '25 RUE DE L'EGLISE'"
+        )]
+        street: Option<String>,
+        #[arg(long, requires = "street", help = "Postcode of the street")]
+        postcode: Option<String>,
+        #[arg(
+            long,
+            default_value = "FRANCE",
+            help = "Country of the street (e.g., FR, FRANCE)"
+        )]
+        country: Country,
     },
+    /// Rebuild the search index from the stored addresses
+    Reindex,
+    /// Print the logged changes of the addresses as JSON lines, oldest first
+    Tail {
+        #[arg(
+            long,
+            help = "Sequence number of the last change already applied, replacing the one of --cursor-file"
+        )]
+        after: Option<u64>,
+        #[arg(
+            long,
+            help = "File keeping the sequence number of the last printed change, to resume from"
+        )]
+        cursor_file: Option<PathBuf>,
+        #[arg(long, help = "Keep printing the changes as they are logged")]
+        follow: bool,
+        #[arg(
+            long,
+            default_value_t = 1000,
+            help = "Maximum number of changes read at once"
+        )]
+        batch_size: usize,
+    },
+    /// Find the stored duplicates and, with --apply, keep one per group
+    Dedupe {
+        #[arg(
+            long,
+            default_value = "keep-newest",
+            help = "Address kept per group: 'keep-newest', 'keep-oldest' or 'merge' (the newest, completed by the others)"
+        )]
+        strategy: DedupeStrategy,
+        #[arg(long, help = "Delete the duplicates, only reported otherwise")]
+        apply: bool,
+    },
+    /// Print the shell completion script
+    Completions {
+        #[arg(value_enum, help = "Shell to complete")]
+        shell: Shell,
+        #[arg(long, help = "Name of the binary to complete [default: invoked name]")]
+        bin_name: Option<String>,
+    },
+    /// Print the stored IDs, used by the completion scripts
+    #[command(name = "__complete-ids", hide = true)]
+    CompleteIds,
+    /// Write every stored address to a compressed snapshot
+    #[cfg(feature = "backup")]
+    Backup {
+        #[arg(long, help = "Snapshot file to write (e.g., snapshot.tar.zst)")]
+        out: PathBuf,
+    },
+    /// Restore the addresses of a snapshot, skipping the stored ones
+    #[cfg(feature = "backup")]
+    Restore {
+        #[arg(long, help = "Snapshot file to read")]
+        from: PathBuf,
+        #[arg(
+            long,
+            help = "JSON storage directory to restore into [default: the current storage]"
+        )]
+        into: Option<PathBuf>,
+    },
+    /// Copy the addresses of a storage into another, e.g., to migrate to
+    /// another backend, the most recently updated version winning
+    Sync {
+        #[arg(
+            long,
+            help = "Storage to copy from (e.g., 'json:///var/lib/addresses')"
+        )]
+        from: StorageUrl,
+        #[arg(
+            long,
+            help = "Storage to copy into (e.g., 'mongodb://localhost:27017/crm')"
+        )]
+        to: StorageUrl,
+        #[arg(long, help = "Also copy the addresses missing or older in --from")]
+        bidirectional: bool,
+    },
+    /// Watch a directory and import every incoming JSON/CSV address file
+    #[cfg(feature = "watch")]
+    Watch {
+        #[arg(help = "Directory to watch for incoming address files")]
+        dir: PathBuf,
+        #[arg(long, value_enum, ignore_case = true, help = "Input format")]
+        from_format: Format,
+        #[arg(long, help = "Update stored duplicates instead of rejecting them")]
+        upsert: bool,
+    },
+}
+
+impl Commands {
+    /// The confirmation asked before running the command, when it loses
+    /// stored data.
+    fn destructive(&self) -> Option<Destructive> {
+        match self {
+            Commands::Delete { id, .. } => Some(Destructive {
+                action: format!("Delete the address `{id}`"),
+                bulk: false,
+                command: "delete",
+            }),
+            Commands::Dedupe { apply: true, .. } => Some(Destructive {
+                action: "Delete the duplicates of the storage".to_string(),
+                bulk: true,
+                command: "dedupe --apply",
+            }),
+            #[cfg(feature = "backup")]
+            Commands::Restore { from, into } => Some(Destructive {
+                action: match into {
+                    Some(dir) => format!(
+                        "Restore the addresses of {} into {}",
+                        from.display(),
+                        dir.display()
+                    ),
+                    None => format!(
+                        "Restore the addresses of {} into the storage",
+                        from.display()
+                    ),
+                },
+                bulk: true,
+                command: "restore",
+            }),
+            Commands::Sync {
+                from,
+                to,
+                bidirectional: false,
+            } => Some(Destructive {
+                action: format!("Copy the addresses of {from} over the older ones of {to}"),
+                bulk: true,
+                command: "sync",
+            }),
+            Commands::Sync {
+                from,
+                to,
+                bidirectional: true,
+            } => Some(Destructive {
+                action: format!(
+                    "Copy the addresses between {from} and {to}, overwriting the older ones of both"
+                ),
+                bulk: true,
+                command: "sync --bidirectional",
+            }),
+            Commands::Compact => Some(Destructive {
+                action: "Rewrite the addresses of the storage and remove their stale copies"
+                    .to_string(),
+                bulk: true,
+                command: "compact",
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn columns_to_enum(columns: &str) -> Result<Vec<ExportColumn>, String> {
+    columns
+        .split(',')
+        .map(|column| {
+            column.trim().parse().map_err(|_| {
+                format!(
+                    "Invalid column '{column}': must be 'id', 'updated_at', 'fingerprint', 'name', \
+                     'contact', 'service', 'delivery', 'street', 'postcode', 'town', 'country', \
+                     'country_code', 'sort_code' or 'batch_id'"
+                )
+            })
+        })
+        .collect()
 }
 
-fn format_to_enum(format: &str) -> Result<Format, String> {
-    match format.to_lowercase().as_str() {
-        "french" => Ok(Format::French),
-        "iso20022" => Ok(Format::Iso20022),
-        _ => Err("Invalid format: must be 'french' or 'iso20022'".to_string()),
+/// The formats, as read by clap from [`Format::names`].
+static FORMATS: Lazy<Vec<Format>> = Lazy::new(|| Format::all().collect());
+/// The formats of an address given on the command line.
+static INPUT_FORMATS: Lazy<Vec<InputFormat>> = Lazy::new(|| {
+    Format::all()
+        .map(InputFormat::Json)
+        .chain([InputFormat::FrenchLines])
+        .collect()
+});
+
+impl ValueEnum for Format {
+    fn value_variants<'a>() -> &'a [Self] {
+        &FORMATS
+    }
+
+    /// The name of the format, its aliases being accepted but hidden from
+    /// the help.
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        let (name, aliases) = self.names().split_first()?;
+        Some(PossibleValue::new(*name).aliases(aliases.iter().copied()))
     }
 }
 
-pub fn run_command(cli: Cli, service: &AddressService) -> Result<(), String> {
+/// Reads the JSON/CSV `file` in `encoding`, transcoded to UTF-8.
+fn read_encoded(file: &Path, encoding: InputEncoding) -> Result<String, String> {
+    let bytes = fs::read(file).map_err(|e| format!("Can't read {}: {e}", file.display()))?;
+
+    encoding.decode(bytes).map_err(|e| {
+        format!(
+            "Can't read {}: {e}, try --input-encoding=latin1",
+            file.display()
+        )
+    })
+}
+
+/// The format of an address given on the command line: any [`Format`], or
+/// the raw lines of a french address.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    Json(Format),
+    FrenchLines,
+}
+
+impl ValueEnum for InputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &INPUT_FORMATS
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            InputFormat::Json(format) => format.to_possible_value(),
+            InputFormat::FrenchLines => Some(PossibleValue::new("french-lines").alias("lines")),
+        }
+    }
+}
+
+/// Reads the `--address` given in `--from-format`. The raw lines of a french
+/// address are structured into its tagged JSON representation.
+fn read_address(
+    address: String,
+    from_format: InputFormat,
+    output: OutputMode,
+) -> Result<(String, Format), String> {
+    if let InputFormat::Json(format) = from_format {
+        return Ok((address, format));
+    }
+
+    let french = FrenchAddress::from_lines(&address)
+        .map_err(|e| service_error(output)(AddressServiceError::ConversionError(e)))?;
+    let json = serde_json::to_value(&french).map_err(|e| e.to_string())?;
+
+    Ok((french.kind().tag(json).to_string(), Format::French))
+}
+
+fn kind_to_enum(kind: &str) -> Result<KindHint, String> {
+    KindHint::from_str(kind)
+        .map_err(|_| "Invalid kind: must be 'individual', 'business' or 'auto'".to_string())
+}
+
+fn json_keys_to_enum(json_keys: &str) -> Result<IsoJsonKeys, String> {
+    IsoJsonKeys::from_str(json_keys)
+        .map_err(|_| "Invalid json keys: must be 'snake', 'iso-tags' or 'camel'".to_string())
+}
+
+fn rfc3339_date(date: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| {
+            "Invalid date: must be an RFC 3339 timestamp (e.g., 2024-01-01T00:00:00Z)".to_string()
+        })
+}
+
+/// Turns a service error into the message returned by [`run_command`]. In JSON
+/// mode, the error details are printed on stdout first.
+fn service_error(output: OutputMode) -> impl Fn(AddressServiceError) -> String {
+    move |error| write_service_error(output, error, &mut io::stdout())
+}
+
+/// Same as [`service_error`], the error details being written to `out`, e.g.,
+/// to capture them.
+pub fn write_service_error(
+    output: OutputMode,
+    error: AddressServiceError,
+    out: &mut impl Write,
+) -> String {
+    if output == OutputMode::Json {
+        if let Ok(json) = serde_json::to_string_pretty(&error.details()) {
+            // The message is returned all the same.
+            let _ = writeln!(out, "{json}");
+        }
+    }
+
+    error.to_string()
+}
+
+/// Serializes the address, with its `kind` tag when `tagged`.
+fn address_to_json(
+    address: Either<FrenchAddress, IsoAddress>,
+    json_keys: IsoJsonKeys,
+    nulls: NullFields,
+    tagged: bool,
+    quiet: bool,
+) -> Result<Value, String> {
+    let kind = match &address {
+        Either::French(french) => tagged.then(|| french.kind()),
+        Either::Iso20022(iso) => tagged.then(|| iso.kind()),
+        // The canonical address always holds its kind.
+        Either::Portuguese(_) | Either::Brazilian(_) | Either::Canonical(_) => None,
+    };
+    let value = match address {
+        Either::French(french) => serde_json::to_value(transliterated(french, quiet)),
+        Either::Iso20022(iso) => iso.to_json_value(json_keys),
+        Either::Portuguese(portuguese) => serde_json::to_value(portuguese),
+        Either::Brazilian(brazilian) => serde_json::to_value(brazilian),
+        Either::Canonical(canonical) => serde_json::to_value(canonical),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let value = match kind {
+        Some(kind) => kind.tag(value),
+        None => value,
+    };
+
+    Ok(nulls.apply(value))
+}
+
+/// The lines of a French address transliterated to Latin-1, printable on the
+/// labels of La Poste. The stored address keeps the original lines.
+#[cfg(feature = "translit")]
+fn transliterated(mut french: FrenchAddress, quiet: bool) -> FrenchAddress {
+    if let Some(script) = french.transliterate() {
+        warn(
+            quiet,
+            format_args!("the {script} lines of the address are transliterated to Latin-1"),
+        );
+    }
+    french
+}
+
+#[cfg(not(feature = "translit"))]
+fn transliterated(french: FrenchAddress, _: bool) -> FrenchAddress {
+    french
+}
+
+/// Prints an informational message, silenced by `--quiet`.
+fn inform(quiet: bool, message: impl fmt::Display) {
+    if !quiet {
+        println!("\n{message}");
+    }
+}
+
+/// Prints a warning on stderr, silenced by `--quiet`.
+fn warn(quiet: bool, message: impl fmt::Display) {
+    if !quiet {
+        eprintln!("Warning: {message}");
+    }
+}
+
+fn print_address(
+    address: Either<FrenchAddress, IsoAddress>,
+    json_keys: IsoJsonKeys,
+    nulls: NullFields,
+    tagged: bool,
+    quiet: bool,
+) -> Result<(), String> {
+    let output =
+        serde_json::to_string_pretty(&address_to_json(address, json_keys, nulls, tagged, quiet)?)
+            .map_err(|e| e.to_string())?;
+
+    println!("{output}");
+
+    Ok(())
+}
+
+/// Prints the converted address and its mappings, one per line after the
+/// address, or together in a JSON object.
+fn print_trace(
+    address: Either<FrenchAddress, IsoAddress>,
+    trace: &ConversionTrace,
+    json_keys: IsoJsonKeys,
+    nulls: NullFields,
+    tagged: bool,
+    quiet: bool,
+    output: OutputMode,
+) -> Result<(), String> {
+    let address = address_to_json(address, json_keys, nulls, tagged, quiet)?;
+
+    if output == OutputMode::Json {
+        let json = json!({ "address": address, "trace": trace });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&address).map_err(|e| e.to_string())?
+    );
+    println!();
+    for line in trace.lines() {
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+/// Prints the summary of an import analysis, then the records that wouldn't
+/// be saved.
+fn print_import_analysis(analysis: &ImportAnalysis, upsert: bool) {
+    let action = if upsert { "updated" } else { "rejected" };
+
+    println!("Records:                 {}", analysis.total());
+    println!("  new:                   {}", analysis.new.len());
+    println!(
+        "  duplicates of stored:  {}",
+        analysis.stored_duplicates.len()
+    );
+    println!(
+        "  duplicates in file:    {}",
+        analysis.file_duplicates.len()
+    );
+    println!("  invalid:               {}", analysis.invalid.len());
+    for duplicate in &analysis.stored_duplicates {
+        println!(
+            "record {}: {action}, duplicate of stored address {}",
+            duplicate.record, duplicate.existing
+        );
+    }
+    for duplicate in &analysis.file_duplicates {
+        println!(
+            "record {}: {action}, duplicate of record {}",
+            duplicate.record, duplicate.first_record
+        );
+    }
+    for failure in &analysis.invalid {
+        println!("record {}: invalid: {}", failure.record, failure.error);
+    }
+}
+
+/// Prints the outcome of an import. Fails when a record was rejected.
+fn print_import_report(report: &BatchReport, quiet: bool) -> Result<(), String> {
+    if !quiet {
+        println!(
+            "Imported {}/{} addresses ({} updated)",
+            report.saved.len() + report.updated.len(),
+            report.total(),
+            report.updated.len()
+        );
+    }
+    for failure in &report.failures {
+        eprintln!("record {} rejected: {}", failure.record, failure.error);
+    }
+
+    if report.failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} records rejected", report.failures.len()))
+    }
+}
+
+/// The JSON/CSV files to convert with `reformat`: the given file, or the
+/// files of the given directory sorted by name, its subdirectories and other
+/// files being left out.
+fn reformat_inputs(input: &Path) -> Result<Vec<(PathBuf, BatchFileKind)>, String> {
+    let unsupported = "Unsupported file: must be a .json, .ndjson or .csv file";
+    if !input.is_dir() {
+        let kind = BatchFileKind::from_path(input).ok_or(unsupported)?;
+        return Ok(vec![(input.to_path_buf(), kind)]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input).map_err(|e| format!("Can't read {}: {e}", input.display()))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if let Some(kind) = BatchFileKind::from_path(&path).filter(|_| path.is_file()) {
+            files.push((path, kind));
+        }
+    }
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if files.is_empty() {
+        return Err(format!(
+            "No .json, .ndjson or .csv file in {}",
+            input.display()
+        ));
+    }
+    Ok(files)
+}
+
+#[cfg(feature = "xlsx")]
+fn is_xlsx(file: &Path) -> bool {
+    file.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xlsx"))
+}
+
+/// Loads the official streets of `validate --against-referential`.
+fn street_referential(path: &Path) -> Result<StreetReferential, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+
+    StreetReferential::from_reader(io::BufReader::new(file))
+        .map_err(|e| format!("{e} in {}", path.display()))
+}
+
+/// Reads the `--sheet` and `--column HEADER=FIELD` options of the import.
+#[cfg(feature = "xlsx")]
+fn xlsx_options(sheet: Option<String>, columns: &[String]) -> Result<XlsxOptions, String> {
+    let columns = columns
+        .iter()
+        .map(|column| {
+            let (header, field) = column
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid column: '{column}' must be HEADER=FIELD"))?;
+            Ok((header.trim().to_string(), field.trim().to_string()))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(XlsxOptions { sheet, columns })
+}
+
+pub fn run_command(cli: Cli, service: &BoxedAddressService) -> Result<(), String> {
+    let color = table::use_color(cli.no_color);
+    let nulls = cli.null_fields();
+    let tagged = cli.tagged;
+    let quiet = cli.quiet;
+    // The warnings of the validation policy are printed before the saves.
+    let policy_warnings = cli.validation_config.is_some();
+
+    if let Some(destructive) = cli.command.destructive() {
+        destructive.confirm(cli.yes)?;
+    }
+
     match cli.command {
         Commands::Save {
             address,
             from_format,
+            upsert,
+            idempotency_key,
+            alias,
+            kind,
+            output,
+            id_only,
         } => {
-            let format = format_to_enum(&from_format)?;
-            let id = service.save(&address, format).map_err(|e| e.to_string())?;
-            println!("\nSaved address with ID: {}", id);
+            let (address, format) = read_address(address, from_format, output)?;
+            let kind = kind_to_enum(&kind)?;
+            if policy_warnings {
+                let warnings = service
+                    .validate_as(&address, format, kind)
+                    .map_err(service_error(output))?;
+                for warning in &warnings {
+                    warn(quiet, warning);
+                }
+            }
+            // Named by the save itself, so that a taken alias saves nothing.
+            let outcome = match (upsert, idempotency_key) {
+                (true, _) => service.upsert_with_alias(&address, format, kind, alias),
+                (false, Some(key)) => {
+                    service.save_once_with_alias(&address, format, kind, &key, alias)
+                }
+                (false, None) => service
+                    .save_with_alias(&address, format, kind, alias)
+                    .map(SaveOutcome::Created),
+            }
+            .map_err(service_error(output))?;
+
+            if id_only {
+                println!("{}", outcome.id());
+                return Ok(());
+            }
+            match (output, outcome) {
+                (OutputMode::Text, SaveOutcome::Created(id)) => {
+                    inform(quiet, format_args!("Saved address with ID: {}", id))
+                }
+                (OutputMode::Text, SaveOutcome::Updated(id)) => inform(
+                    quiet,
+                    format_args!("Updated existing address with ID: {}", id),
+                ),
+                (OutputMode::Text, SaveOutcome::Replayed(id)) => inform(
+                    quiet,
+                    format_args!("Already saved with this key, address ID: {}", id),
+                ),
+                (OutputMode::Json, SaveOutcome::Created(id)) => {
+                    println!("{}", json!({ "id": id, "outcome": "created" }))
+                }
+                (OutputMode::Json, SaveOutcome::Updated(id)) => {
+                    println!("{}", json!({ "id": id, "outcome": "updated" }))
+                }
+                (OutputMode::Json, SaveOutcome::Replayed(id)) => {
+                    println!("{}", json!({ "id": id, "outcome": "replayed" }))
+                }
+            }
 
             Ok(())
         }
@@ -67,37 +1487,1077 @@ pub fn run_command(cli: Cli, service: &AddressService) -> Result<(), String> {
             id,
             address,
             from_format,
+            if_version,
+            output,
+        } => {
+            let (address, format) = read_address(address, from_format, output)?;
+            if policy_warnings {
+                let warnings = service
+                    .validate(&address, format)
+                    .map_err(service_error(output))?;
+                for warning in &warnings {
+                    warn(quiet, warning);
+                }
+            }
+            let id = id.resolve(service).map_err(service_error(output))?;
+            let version = service
+                .update_if_version(id, &address, format, if_version)
+                .map_err(service_error(output))?;
+            match output {
+                OutputMode::Text => inform(
+                    quiet,
+                    format_args!("Updated address with ID: {id} (version {version})"),
+                ),
+                OutputMode::Json => println!(
+                    "{}",
+                    json!({ "id": id, "outcome": "updated", "version": version })
+                ),
+            }
+
+            Ok(())
+        }
+        Commands::Delete { id, output } => {
+            let id = id.resolve(service).map_err(service_error(output))?;
+            service.delete(id).map_err(service_error(output))?;
+            match output {
+                OutputMode::Text => inform(quiet, format_args!("Deleted address with ID: {}", id)),
+                OutputMode::Json => println!("{}", json!({ "id": id, "outcome": "deleted" })),
+            }
+
+            Ok(())
+        }
+        Commands::Archive { id } => {
+            let id = id.resolve(service).map_err(|e| e.to_string())?;
+            service.archive(id).map_err(|e| e.to_string())?;
+            inform(quiet, format_args!("Archived address with ID: {id}"));
+
+            Ok(())
+        }
+        Commands::MarkInvalid { id, reason } => {
+            let id = id.resolve(service).map_err(|e| e.to_string())?;
+            service
+                .mark_invalid(id, &reason)
+                .map_err(|e| e.to_string())?;
+            inform(
+                quiet,
+                format_args!("Marked address with ID {id} as invalid"),
+            );
+
+            Ok(())
+        }
+        Commands::Route {
+            id,
+            sort_code,
+            batch_id,
+            clear,
         } => {
-            let format = format_to_enum(&from_format)?;
+            let id = id.resolve(service).map_err(|e| e.to_string())?;
+            let mut routing = if clear {
+                Routing::default()
+            } else {
+                service
+                    .fetch(id)
+                    .map_err(|e| e.to_string())?
+                    .routing()
+                    .clone()
+            };
+            routing.sort_code = sort_code.or(routing.sort_code);
+            routing.batch_id = batch_id.or(routing.batch_id);
             service
-                .update(&id, &address, format)
+                .set_routing(id, routing)
                 .map_err(|e| e.to_string())?;
-            println!("\nUpdated address with ID: {}", id);
+            inform(
+                quiet,
+                format_args!("Updated the routing of address with ID: {id}"),
+            );
 
             Ok(())
         }
-        Commands::Delete { id } => {
-            service.delete(&id).map_err(|e| e.to_string())?;
-            println!("\nDeleted address with ID: {}", id);
+        Commands::SetRecipient {
+            id,
+            name,
+            company,
+            contact,
+            service: service_line,
+            clear,
+        } => {
+            let id = id.resolve(service).map_err(|e| e.to_string())?;
+            let recipient = match name {
+                Some(name) => Recipient::Individual { name },
+                None => {
+                    if company.is_none() && contact.is_none() && service_line.is_none() && !clear {
+                        return Err(
+                            "Nothing to set: give --name, --company, --contact or --service"
+                                .to_string(),
+                        );
+                    }
+                    let stored = service.fetch(id).map_err(|e| e.to_string())?.recipient;
+                    let (stored_company, stored_contact, stored_service, identifiers) = match stored
+                    {
+                        Recipient::Business {
+                            company_name,
+                            contact_person,
+                            service,
+                            identifiers,
+                        } => (Some(company_name), contact_person, service, identifiers),
+                        Recipient::Individual { .. } => (None, None, None, vec![]),
+                    };
+                    // The identifiers are the ones of the stored business.
+                    let identifiers = match (&company, &stored_company) {
+                        (Some(company), Some(stored)) if company != stored => vec![],
+                        _ => identifiers,
+                    };
+                    let kept = |stored: Option<String>| if clear { None } else { stored };
+
+                    Recipient::Business {
+                        company_name: company.or(stored_company).ok_or(
+                            "The address is an individual's: give --company to make it a business",
+                        )?,
+                        contact_person: contact.or_else(|| kept(stored_contact)),
+                        service: service_line.or_else(|| kept(stored_service)),
+                        identifiers,
+                    }
+                }
+            };
+            service
+                .update_recipient(id, recipient)
+                .map_err(|e| e.to_string())?;
+            inform(
+                quiet,
+                format_args!("Updated the recipient of address with ID: {id}"),
+            );
 
             Ok(())
         }
-        Commands::Fetch { id, format } => {
-            let format_enum = format_to_enum(&format)?;
-            let result = service
-                .fetch_format(&id, format_enum)
+        Commands::Annotate {
+            id,
+            note,
+            clear_note,
+            set,
+            unset,
+        } => {
+            let id = id.resolve(service).map_err(|e| e.to_string())?;
+            let address = service.fetch(id).map_err(|e| e.to_string())?;
+            let notes = match note {
+                Some(note) => Some(note),
+                None if clear_note => None,
+                None => address.notes().map(str::to_string),
+            };
+            let mut metadata = address.metadata().clone();
+            for key in &unset {
+                metadata.remove(key);
+            }
+            for entry in &set {
+                let (key, value) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid metadata: '{entry}' must be KEY=VALUE"))?;
+                metadata.insert(key.trim().to_string(), value.trim().to_string());
+            }
+            service
+                .annotate(id, notes, metadata)
                 .map_err(|e| e.to_string())?;
+            inform(
+                quiet,
+                format_args!("Updated the annotations of address with ID: {id}"),
+            );
+
+            Ok(())
+        }
+        Commands::Fetch {
+            ids,
+            prefix,
+            format,
+            json_keys,
+            as_of,
+            output,
+        } => {
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let as_of = as_of.as_deref().map(rfc3339_date).transpose()?;
+            let ids = ids
+                .into_iter()
+                .chain(prefix.into_iter().map(AddressId::Prefix))
+                .map(|id| id.resolve(service))
+                .collect::<ServiceResult<Vec<_>>>()
+                .map_err(service_error(output))?;
+
+            let mut results = Vec::with_capacity(ids.len());
+            for id in &ids {
+                let address = match as_of {
+                    Some(as_of) => service.fetch_as_of(*id, as_of),
+                    None => service.fetch(*id),
+                }
+                .map_err(service_error(output))?;
+                let mut rendered = service
+                    .formats(&address, &format)
+                    .map_err(service_error(output))?;
 
-            match result {
-                Either::French(french) => {
-                    println!("{}", serde_json::to_string_pretty(&french).unwrap())
+                // The version goes to stderr, so that stdout only holds the JSON.
+                if !quiet {
+                    if ids.len() == 1 {
+                        eprintln!("Version: {}", address.version());
+                    } else {
+                        eprintln!("{}: version {}", address.id(), address.version());
+                    }
                 }
-                Either::Iso20022(iso) => {
-                    println!("{}", serde_json::to_string_pretty(&iso).unwrap())
+                if *address.status() != AddressStatus::Active {
+                    warn(
+                        quiet,
+                        format_args!("the address {} is {}", address.id(), address.status()),
+                    );
                 }
+                // Several formats are written as an object keyed by format name.
+                let document = if rendered.len() == 1 {
+                    let (_, result) = rendered.remove(0);
+                    address_to_json(result, json_keys, nulls, tagged, quiet)?
+                } else {
+                    let mut formats = Map::new();
+                    for (format, result) in rendered {
+                        let value = address_to_json(result, json_keys, nulls, tagged, quiet)?;
+                        formats.insert(format.name().to_string(), value);
+                    }
+                    Value::Object(formats)
+                };
+                results.push(document);
             }
 
+            // Several addresses are written as a JSON array in JSON mode, and
+            // one after the other otherwise.
+            if output == OutputMode::Json && results.len() > 1 {
+                results = vec![Value::Array(results)];
+            }
+            for document in results {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?
+                );
+            }
+
+            Ok(())
+        }
+        Commands::Inspect { id } => {
+            let id = id.resolve(service).map_err(|e| e.to_string())?;
+            let address = service.fetch(id).map_err(|e| e.to_string())?;
+            println!("{}", inspect::address_tree(&address)?);
+
             Ok(())
         }
+        Commands::Convert {
+            address,
+            from_format,
+            to_format: format,
+            kind,
+            json_keys,
+            output,
+            explain,
+            party_xml,
+        } => {
+            let (address, from_format) = match from_format {
+                Some(from_format) => read_address(address, from_format, output)?,
+                None => (address, format.conversion_source()),
+            };
+            let kind = kind_to_enum(&kind)?;
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            if party_xml && format != Format::Iso20022 {
+                return Err("--party-xml requires --to-format=iso20022".to_string());
+            }
+            if !explain {
+                let (result, warnings) = service
+                    .convert_with_warnings_from(&address, from_format, format, kind)
+                    .map_err(service_error(output))?;
+                for warning in &warnings {
+                    warn(quiet, warning);
+                }
+
+                return match result {
+                    Either::Iso20022(iso) if party_xml => {
+                        print!("{}", iso.to_party_xml());
+                        Ok(())
+                    }
+                    result => print_address(result, json_keys, nulls, tagged, quiet),
+                };
+            }
+
+            let (result, trace) = service
+                .convert_with_trace_from(&address, from_format, format, kind)
+                .map_err(service_error(output))?;
+            print_trace(result, &trace, json_keys, nulls, tagged, quiet, output)
+        }
+        Commands::Validate {
+            address,
+            from_format,
+            kind,
+            against_referential,
+            output,
+        } => {
+            let (address, format) = read_address(address, from_format, output)?;
+            let kind = kind_to_enum(&kind)?;
+            let referential = against_referential
+                .map(|path| street_referential(&path).map(|referential| (path, referential)))
+                .transpose()?;
+            let (warnings, issues) = match &referential {
+                Some((_, referential)) => service
+                    .validate_against(&address, format, kind, referential)
+                    .map_err(service_error(output))?,
+                None => (
+                    service
+                        .validate_as(&address, format, kind)
+                        .map_err(service_error(output))?,
+                    Vec::new(),
+                ),
+            };
+
+            match output {
+                OutputMode::Text => {
+                    for warning in &warnings {
+                        warn(quiet, warning);
+                    }
+                    for issue in &issues {
+                        warn(quiet, issue);
+                    }
+                }
+                OutputMode::Json => {
+                    let mut result = json!({ "valid": issues.is_empty(), "warnings": warnings });
+                    if referential.is_some() {
+                        result["referential"] = json!(issues);
+                    }
+                    println!("{result}")
+                }
+            }
+
+            match referential {
+                Some((path, _)) if !issues.is_empty() => Err(format!(
+                    "Address not found in the street referential {}",
+                    path.display()
+                )),
+                _ => {
+                    if output == OutputMode::Text {
+                        inform(quiet, "Valid address");
+                    }
+                    Ok(())
+                }
+            }
+        }
+        Commands::Normalize {
+            address,
+            from_format,
+            kind,
+            json_keys,
+            output,
+        } => {
+            let (address, format) = read_address(address, from_format, output)?;
+            let kind = kind_to_enum(&kind)?;
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let normalized = service
+                .normalize_as(&address, format, kind)
+                .map_err(service_error(output))?;
+
+            print_address(normalized, json_keys, nulls, tagged, quiet)
+        }
+        Commands::FromXml {
+            file,
+            to_format: format,
+            json_keys,
+            output,
+        } => {
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let xml = match file {
+                Some(path) => fs::read_to_string(&path)
+                    .map_err(|e| format!("Can't read {}: {e}", path.display()))?,
+                None => io::read_to_string(io::stdin()).map_err(|e| e.to_string())?,
+            };
+
+            let addresses = service
+                .convert_xml(&xml, format)
+                .map_err(service_error(output))?
+                .into_iter()
+                .map(|address| address_to_json(address, json_keys, nulls, tagged, quiet))
+                .collect::<Result<Vec<Value>, String>>()?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&addresses).map_err(|e| e.to_string())?
+            );
+
+            Ok(())
+        }
+        Commands::Compose {
+            format,
+            kind,
+            json_keys,
+        } => {
+            let kind = kind_to_enum(&kind)?;
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            // Prompts go to stderr, so that stdout only holds the JSON.
+            let mut prompter = Prompter::new(io::stdin().lock(), io::stderr());
+            let address = super::compose::compose(&mut prompter, format, kind)?;
+
+            print_address(address, json_keys, nulls, tagged, quiet)
+        }
+        Commands::Generate {
+            count,
+            format,
+            kind,
+            seed,
+            save,
+            json_keys,
+        } => {
+            if !matches!(format, Format::French | Format::Iso20022) {
+                return Err("Only french and iso20022 addresses can be generated".to_string());
+            }
+            let kind = kind_to_enum(&kind)?;
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let mut generator =
+                seed.map_or_else(AddressGenerator::from_time, AddressGenerator::new);
+
+            let addresses = service
+                .generate(&mut generator, count, format, kind, save)
+                .map_err(|e| e.to_string())?;
+            for address in addresses {
+                println!(
+                    "{}",
+                    address_to_json(address, json_keys, nulls, tagged, quiet)?
+                );
+            }
+
+            Ok(())
+        }
+        Commands::Suggest {
+            prefix,
+            field,
+            limit,
+        } => {
+            let field = SuggestField::from_str(&field).map_err(|_| {
+                "Invalid field: must be 'street', 'town' or 'recipient'".to_string()
+            })?;
+            let suggestions = service.suggest(&prefix, field).map_err(|e| e.to_string())?;
+
+            for suggestion in suggestions.iter().take(limit) {
+                println!("{} ({})", suggestion.value, suggestion.count);
+            }
+
+            Ok(())
+        }
+        Commands::Import {
+            file,
+            from_format: format,
+            upsert,
+            analyze,
+            input_encoding,
+            #[cfg(feature = "xlsx")]
+            sheet,
+            #[cfg(feature = "xlsx")]
+            columns,
+        } => {
+            #[cfg(feature = "xlsx")]
+            if is_xlsx(&file) {
+                let workbook = fs::read(&file).map_err(|e| e.to_string())?;
+                let options = xlsx_options(sheet, &columns)?;
+
+                if analyze {
+                    let analysis = service
+                        .analyze_xlsx_import(&workbook, &options, format)
+                        .map_err(|e| e.to_string())?;
+                    print_import_analysis(&analysis, upsert);
+                    return Ok(());
+                }
+
+                let progress = ProgressReporter::new("Importing", quiet);
+                let report = service
+                    .import_xlsx_with_progress(
+                        &workbook,
+                        &options,
+                        format,
+                        upsert,
+                        &|done, total| progress.update(done, total),
+                    )
+                    .map_err(|e| e.to_string())?;
+                progress.finish();
+                return print_import_report(&report, quiet);
+            }
+
+            let kind = BatchFileKind::from_path(&file)
+                .ok_or("Unsupported file: must be a .json, .ndjson, .csv or .xlsx file")?;
+            let content = read_encoded(&file, input_encoding)?;
+
+            if analyze {
+                let analysis = service
+                    .analyze_import(&content, kind, format)
+                    .map_err(|e| e.to_string())?;
+                print_import_analysis(&analysis, upsert);
+                return Ok(());
+            }
+
+            let progress = ProgressReporter::new("Importing", quiet);
+            let report = service
+                .import_with_progress(&content, kind, format, upsert, &|done, total| {
+                    progress.update(done, total)
+                })
+                .map_err(|e| e.to_string())?;
+            progress.finish();
+            print_import_report(&report, quiet)
+        }
+        Commands::Reformat {
+            input,
+            from_format,
+            to_format,
+            out,
+            input_encoding,
+            json_keys,
+        } => {
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let files = reformat_inputs(&input)?;
+            fs::create_dir_all(&out).map_err(|e| format!("Can't create {}: {e}", out.display()))?;
+
+            let (mut converted, mut rejected) = (0, 0);
+            for (file, kind) in &files {
+                let content = read_encoded(file, input_encoding)?;
+                let results = service
+                    .reformat(&content, *kind, from_format, to_format)
+                    .map_err(|e| format!("{}: {e}", file.display()))?;
+
+                let mut lines = String::new();
+                for (record, result) in results.into_iter().enumerate() {
+                    match result {
+                        Ok(address) => {
+                            let json = address_to_json(address, json_keys, nulls, tagged, quiet)?;
+                            lines.push_str(&format!("{json}\n"));
+                            converted += 1;
+                        }
+                        Err(error) => {
+                            eprintln!("{} record {record} rejected: {error}", file.display());
+                            rejected += 1;
+                        }
+                    }
+                }
+
+                let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+                let path = out.join(format!("{stem}.ndjson"));
+                fs::write(&path, lines)
+                    .map_err(|e| format!("Can't write {}: {e}", path.display()))?;
+            }
+
+            inform(
+                quiet,
+                format_args!(
+                    "Converted {converted}/{} addresses of {} files to {}",
+                    converted + rejected,
+                    files.len(),
+                    out.display()
+                ),
+            );
+            if rejected == 0 {
+                Ok(())
+            } else {
+                Err(format!("{rejected} records rejected"))
+            }
+        }
+        Commands::MigrateIso {
+            input,
+            out,
+            report,
+            review_threshold,
+            json_keys,
+        } => {
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let content = fs::read_to_string(&input)
+                .map_err(|e| format!("Can't read {}: {e}", input.display()))?;
+            let converter = UnstructuredToStructured::new().with_review_threshold(review_threshold);
+            let results = service
+                .migrate_iso_batch(&content, &converter)
+                .map_err(|e| format!("{}: {e}", input.display()))?;
+
+            let (mut addresses, mut reports) = (String::new(), String::new());
+            let (mut migrated, mut rejected, mut to_review) = (0, 0, 0);
+            for (record, result) in results.into_iter().enumerate() {
+                let migration = match result {
+                    Ok(migration) => migration,
+                    Err(error) => {
+                        eprintln!("record {record} rejected: {error}");
+                        rejected += 1;
+                        continue;
+                    }
+                };
+
+                let report = &migration.report;
+                if report.needs_review {
+                    to_review += 1;
+                    if !report.issues.is_empty() {
+                        let issues: Vec<String> =
+                            report.issues.iter().map(ToString::to_string).collect();
+                        warn(
+                            quiet,
+                            format_args!(
+                                "record {record} needs a review (confidence {}): {}",
+                                report.confidence,
+                                issues.join(", ")
+                            ),
+                        );
+                    }
+                }
+                let mut report = serde_json::to_value(report).map_err(|e| e.to_string())?;
+                report["record"] = json!(record);
+                reports.push_str(&format!("{report}\n"));
+
+                let json = address_to_json(
+                    Either::Iso20022(migration.address),
+                    json_keys,
+                    nulls,
+                    tagged,
+                    quiet,
+                )?;
+                addresses.push_str(&format!("{json}\n"));
+                migrated += 1;
+            }
+
+            match &out {
+                Some(path) => fs::write(path, addresses)
+                    .map_err(|e| format!("Can't write {}: {e}", path.display()))?,
+                None => print!("{addresses}"),
+            }
+            if let Some(path) = &report {
+                fs::write(path, reports)
+                    .map_err(|e| format!("Can't write {}: {e}", path.display()))?;
+            }
+
+            inform(
+                quiet,
+                format_args!(
+                    "Migrated {migrated}/{} addresses, {to_review} to review",
+                    migrated + rejected
+                ),
+            );
+            if rejected == 0 {
+                Ok(())
+            } else {
+                Err(format!("{rejected} records rejected"))
+            }
+        }
+        Commands::Export {
+            columns: Some(columns),
+            since,
+            output,
+            filter,
+            ..
+        } => {
+            let columns = match columns {
+                Some(columns) => columns_to_enum(&columns)?,
+                None => ExportColumn::DEFAULT.to_vec(),
+            };
+            let since = since.as_deref().map(rfc3339_date).transpose()?;
+            let progress = ProgressReporter::new("Exporting", quiet);
+            let csv = service
+                .export_csv_with_progress(since, &filter.into(), &columns, &|done, total| {
+                    progress.update(done, total)
+                })
+                .map_err(|e| e.to_string())?;
+            progress.finish();
+
+            match output {
+                Some(path) => {
+                    fs::write(&path, csv).map_err(|e| e.to_string())?;
+                    inform(
+                        quiet,
+                        format_args!("Exported the addresses to {}", path.display()),
+                    );
+                }
+                None => print!("{csv}"),
+            }
+
+            Ok(())
+        }
+        Commands::Export {
+            format,
+            since,
+            json_keys,
+            output,
+            filter,
+            ..
+        } => {
+            let format = format.ok_or("Missing format: give --format or --columns")?;
+            let since = since.as_deref().map(rfc3339_date).transpose()?;
+            let json_keys = json_keys_to_enum(&json_keys)?;
+            let progress = ProgressReporter::new("Exporting", quiet);
+            let exported = service
+                .export_with_progress(since, &filter.into(), format, &|done, total| {
+                    progress.update(done, total)
+                })
+                .map_err(|e| e.to_string())?;
+            progress.finish();
+
+            let records = exported
+                .into_iter()
+                .map(|exported| {
+                    Ok(nulls.apply(json!({
+                        "id": exported.id,
+                        "updated_at": exported.updated_at,
+                        "fingerprint": exported.fingerprint,
+                        "person_name": exported.person_name,
+                        "routing": exported.routing,
+                        "notes": exported.notes,
+                        "metadata": Some(exported.metadata).filter(|metadata| !metadata.is_empty()),
+                        "address": address_to_json(exported.address, json_keys, nulls, tagged, quiet)?,
+                    })))
+                })
+                .collect::<Result<Vec<Value>, String>>()?;
+            let count = records.len();
+            let json = serde_json::to_string_pretty(&records).map_err(|e| e.to_string())?;
+
+            match output {
+                Some(path) => {
+                    fs::write(&path, json).map_err(|e| e.to_string())?;
+                    inform(
+                        quiet,
+                        format_args!("Exported {count} addresses to {}", path.display()),
+                    );
+                }
+                None => println!("{json}"),
+            }
+
+            Ok(())
+        }
+        Commands::List {
+            min_quality,
+            department,
+            archived,
+            filter,
+        } => {
+            let filter = ListFilter {
+                min_quality,
+                department: department.as_deref(),
+                archived,
+                matching: filter.into(),
+            };
+            let addresses = service.list_with(&filter).map_err(|e| e.to_string())?;
+
+            println!("{}", table::address_table(&addresses, color));
+
+            Ok(())
+        }
+        Commands::MailingList {
+            min_quality,
+            department,
+            output,
+            filter,
+        } => {
+            let list = service
+                .mailing_list(ListFilter {
+                    min_quality,
+                    department: department.as_deref(),
+                    archived: false,
+                    matching: filter.into(),
+                })
+                .map_err(|e| e.to_string())?;
+            let csv = service.mailing_list_csv(&list).map_err(|e| e.to_string())?;
+
+            let mut summary = format!(
+                "Selected {} addresses, {} suppressed",
+                list.recipients.len(),
+                list.suppressed.len()
+            );
+            let counts = list.suppressed_counts();
+            if !counts.is_empty() {
+                let counts: Vec<String> = counts
+                    .iter()
+                    .map(|(reason, count)| format!("{count} {reason}"))
+                    .collect();
+                summary.push_str(&format!(" ({})", counts.join(", ")));
+            }
+            match output {
+                Some(path) => {
+                    fs::write(&path, csv).map_err(|e| e.to_string())?;
+                    inform(
+                        quiet,
+                        format_args!("{summary}, written to {}", path.display()),
+                    );
+                }
+                None => {
+                    print!("{csv}");
+                    // The CSV is on stdout.
+                    if !quiet {
+                        eprintln!("{summary}");
+                    }
+                }
+            }
+
+            Ok(())
+        }
+        Commands::Alias { command } => match command {
+            AliasCommands::Add { id, alias } => {
+                let id = id.resolve(service).map_err(|e| e.to_string())?;
+                service
+                    .set_alias(id, alias.clone())
+                    .map_err(|e| e.to_string())?;
+                inform(
+                    quiet,
+                    format_args!("Address with ID: {id} is named `{alias}`"),
+                );
+
+                Ok(())
+            }
+            AliasCommands::Remove { alias } => {
+                let id = service.remove_alias(&alias).map_err(|e| e.to_string())?;
+                inform(
+                    quiet,
+                    format_args!("Removed the alias `{alias}` of address with ID: {id}"),
+                );
+
+                Ok(())
+            }
+            AliasCommands::List => {
+                for (alias, id) in service.aliases().map_err(|e| e.to_string())? {
+                    println!("{alias}\t{id}");
+                }
+
+                Ok(())
+            }
+        },
+        Commands::Stats => {
+            let stats = service.stats().map_err(|e| e.to_string())?;
+
+            println!("{}", table::stats_table(&stats, color));
+
+            Ok(())
+        }
+        Commands::Compact => {
+            let report = service.compact().map_err(|e| e.to_string())?;
+
+            inform(
+                quiet,
+                format_args!(
+                    "Compacted the storage: {} addresses rewritten, {} orphans restored, {} stale copies and {} empty directories removed, {} bytes reclaimed",
+                report.rewritten,
+                report.orphans_restored,
+                report.orphans_removed,
+                    report.dirs_removed,
+                    report.reclaimed_bytes
+                ),
+            );
+            for path in &report.unreadable {
+                eprintln!("Unreadable file left untouched: {path}");
+            }
+
+            Ok(())
+        }
+        Commands::Search { query, limit } => {
+            let addresses: Vec<_> = service
+                .search(&query, limit)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|result| result.address)
+                .collect();
+
+            println!("{}", table::address_table(&addresses, color));
+
+            Ok(())
+        }
+        Commands::Lookup {
+            fingerprint,
+            street,
+            postcode,
+            country,
+        } => {
+            // Either the fingerprint, or the street and the postcode, are given.
+            let addresses = match fingerprint {
+                Some(fingerprint) => service.find_by_fingerprint(&fingerprint),
+                None => service.lookup(
+                    street.as_deref().unwrap_or_default(),
+                    postcode.as_deref().unwrap_or_default(),
+                    country,
+                ),
+            }
+            .map_err(|e| e.to_string())?;
+            if addresses.is_empty() {
+                return Err("No stored address matches".to_string());
+            }
+
+            for address in addresses {
+                println!("{}", address.id());
+            }
+
+            Ok(())
+        }
+        Commands::Reindex => match service.reindex().map_err(|e| e.to_string())? {
+            Some(count) => {
+                inform(quiet, format_args!("Indexed {count} addresses"));
+                Ok(())
+            }
+            None => Err(
+                "No search index: build with the `fts` feature and set SEARCH_INDEX_DIR"
+                    .to_string(),
+            ),
+        },
+        Commands::Tail {
+            after,
+            cursor_file,
+            follow,
+            batch_size,
+        } => {
+            let saved_cursor = match &cursor_file {
+                Some(path) if path.exists() => {
+                    let cursor = fs::read_to_string(path).map_err(|e| e.to_string())?;
+                    let cursor = cursor.trim().parse::<u64>().map_err(|_| {
+                        format!("Invalid cursor in {}: '{}'", path.display(), cursor.trim())
+                    })?;
+                    Some(cursor)
+                }
+                _ => None,
+            };
+            let mut after = after.or(saved_cursor).unwrap_or(0);
+
+            loop {
+                let events = service
+                    .changes(after, batch_size)
+                    .map_err(|e| e.to_string())?
+                    .ok_or("No change log: set CHANGE_LOG_DIR")?;
+                for event in &events {
+                    println!(
+                        "{}",
+                        serde_json::to_string(event).map_err(|e| e.to_string())?
+                    );
+                }
+                // Saved once printed, so that a restart prints the changes at
+                // least once.
+                if let Some(last) = events.last() {
+                    after = last.sequence;
+                    if let Some(path) = &cursor_file {
+                        fs::write(path, after.to_string()).map_err(|e| e.to_string())?;
+                    }
+                }
+
+                if events.len() < batch_size {
+                    if !follow {
+                        return Ok(());
+                    }
+                    thread::sleep(TAIL_POLL_INTERVAL);
+                }
+            }
+        }
+        Commands::Dedupe { strategy, apply } => {
+            let progress = ProgressReporter::new("Deduplicating", quiet);
+            let report = service
+                .dedupe_with_progress(strategy, apply, &|done, total| progress.update(done, total))
+                .map_err(|e| e.to_string())?;
+            progress.finish();
+
+            for group in &report.groups {
+                println!("{}: keep {}", group.fingerprint, group.kept);
+                for id in &group.removed {
+                    println!("  remove {id}");
+                }
+            }
+            if report.applied {
+                println!(
+                    "\nRemoved {} duplicates in {} groups ({strategy})",
+                    report.removed(),
+                    report.groups.len()
+                );
+            } else {
+                println!(
+                    "\n{} duplicates in {} groups, run again with --apply to remove them",
+                    report.removed(),
+                    report.groups.len()
+                );
+            }
+
+            Ok(())
+        }
+        Commands::Completions { shell, bin_name } => {
+            let bin_name = bin_name
+                .or_else(|| {
+                    std::env::args_os().next().and_then(|arg| {
+                        std::path::Path::new(&arg)
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                    })
+                })
+                .unwrap_or_else(|| "cli".to_string());
+
+            super::completions::generate(shell, &bin_name, &mut io::stdout())
+                .map_err(|e| e.to_string())
+        }
+        Commands::CompleteIds => {
+            for id in service.ids().map_err(|e| e.to_string())? {
+                println!("{id}");
+            }
+            for (alias, _) in service.aliases().map_err(|e| e.to_string())? {
+                println!("{alias}");
+            }
+
+            Ok(())
+        }
+        #[cfg(feature = "backup")]
+        Commands::Backup { out } => {
+            let file = fs::File::create(&out)
+                .map_err(|e| format!("Can't create {}: {e}", out.display()))?;
+            let manifest = crate::application::backup::backup(service.repository.as_ref(), file)
+                .map_err(|e| e.to_string())?;
+
+            println!(
+                "\nBacked up {} addresses to {}",
+                manifest.addresses,
+                out.display()
+            );
+
+            Ok(())
+        }
+        #[cfg(feature = "backup")]
+        Commands::Restore { from, into } => {
+            let file =
+                fs::File::open(&from).map_err(|e| format!("Can't open {}: {e}", from.display()))?;
+            let result = match into {
+                Some(dir) => crate::application::backup::restore(
+                    &crate::infrastructure::JsonAddressRepository::new(dir),
+                    file,
+                ),
+                None => crate::application::backup::restore(service.repository.as_ref(), file),
+            };
+            let (manifest, report) = result.map_err(|e| e.to_string())?;
+
+            println!(
+                "\nRestored {}/{} addresses from a snapshot of {} ({} already stored)",
+                report.restored.len(),
+                manifest.addresses,
+                manifest.created_at.to_rfc3339(),
+                report.skipped.len()
+            );
+
+            Ok(())
+        }
+        Commands::Sync {
+            from,
+            to,
+            bidirectional,
+        } => {
+            let open = |url: &StorageUrl| {
+                url.open(cli.tenant.as_ref())
+                    .map_err(|e| format!("Can't open {url}: {e}"))
+            };
+            let (source, target) = (open(&from)?, open(&to)?);
+
+            let mut passes = vec![(&from, source.as_ref(), &to, target.as_ref())];
+            if bidirectional {
+                passes.push((&to, target.as_ref(), &from, source.as_ref()));
+            }
+            for (from, source, to, target) in passes {
+                let report = sync::sync_with_cancellation(source, target, service.cancellation())
+                    .map_err(|e| e.to_string())?;
+                inform(
+                    quiet,
+                    format_args!(
+                        "Synced {from} to {to}: {} created, {} updated, {} skipped",
+                        report.created.len(),
+                        report.updated.len(),
+                        report.skipped.len()
+                    ),
+                );
+            }
+
+            Ok(())
+        }
+        #[cfg(feature = "watch")]
+        Commands::Watch {
+            dir,
+            from_format,
+            upsert,
+        } => super::watch::watch(&dir, service, from_format, upsert),
     }
 }
@@ -0,0 +1,158 @@
+//! Shell completion scripts. The scripts are generated by clap_complete, then
+//! patched so that the address ID of `fetch`, `update`, `delete` and
+//! `inspect` completes with the stored IDs. The IDs are listed at completion time by the hidden
+//! [`COMPLETE_IDS_COMMAND`] subcommand. Dynamic completion is available for
+//! bash, zsh and fish, other shells only get the static completions.
+
+use std::io::Write;
+
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+
+use super::commands::Cli;
+
+/// Hidden subcommand printing the stored IDs, one per line.
+pub const COMPLETE_IDS_COMMAND: &str = "__complete-ids";
+
+/// Subcommands taking an address ID.
+const ID_SUBCOMMANDS: &[&str] = &["fetch", "update", "delete", "inspect"];
+/// Completion action of the ID argument in the zsh script.
+const ZSH_ID_ACTION: &str = "_default' \\";
+
+/// The CLI without its hidden subcommands, which clap_complete would
+/// otherwise complete.
+fn visible_command() -> Command {
+    let cli = Cli::command();
+    let subcommands: Vec<Command> = cli
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .cloned()
+        .collect();
+
+    Command::new(env!("CARGO_PKG_NAME"))
+        .args(cli.get_arguments().cloned())
+        .subcommands(subcommands)
+}
+
+/// Writes the completion script of `shell` for the binary `bin_name`.
+pub fn generate(shell: Shell, bin_name: &str, out: &mut dyn Write) -> std::io::Result<()> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut visible_command(), bin_name, &mut script);
+    let script = String::from_utf8_lossy(&script);
+
+    let list_ids = format!("{bin_name} {COMPLETE_IDS_COMMAND} 2>/dev/null");
+    let function_name = format!("_{}_address_ids", bin_name.replace('-', "_"));
+
+    match shell {
+        // The options of a subcommand are also offered for its positional
+        // argument. They are expanded in double quotes when completing, so the
+        // IDs are listed at this moment.
+        Shell::Bash => {
+            let mut completes_ids = false;
+            for line in script.lines() {
+                let case = line.trim();
+                if case.ends_with(')') && !case.contains("__help__") {
+                    completes_ids = ID_SUBCOMMANDS
+                        .iter()
+                        .any(|subcommand| case.ends_with(&format!("__subcmd__{subcommand})")));
+                }
+
+                match line.split_once("opts=\"") {
+                    Some((indent, opts)) if completes_ids => {
+                        completes_ids = false;
+                        writeln!(out, "{indent}opts=\"$({list_ids}) {opts}")?;
+                    }
+                    _ => writeln!(out, "{line}")?,
+                }
+            }
+
+            Ok(())
+        }
+        // The function must be defined before the script calls the
+        // completion function when autoloaded.
+        Shell::Zsh => {
+            let mut lines = script.lines();
+            let compdef = lines.next().unwrap_or_default();
+            let rest = lines
+                .map(|line| {
+                    if line.contains(":id -- UUID of the address to")
+                        || line.contains(":ids -- UUIDs of the addresses to")
+                    {
+                        line.replace(ZSH_ID_ACTION, &format!("{function_name}' \\"))
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            writeln!(out, "{compdef}")?;
+            writeln!(
+                out,
+                "\n{function_name}() {{\n    local -a ids\n    ids=(${{(f)\"$({list_ids})\"}})\n    _describe 'address id' ids\n}}"
+            )?;
+            writeln!(out, "{rest}")
+        }
+        Shell::Fish => {
+            out.write_all(script.as_bytes())?;
+            writeln!(
+                out,
+                "complete -c {bin_name} -n \"__fish_seen_subcommand_from {}\" -f -a \"({list_ids})\"",
+                ID_SUBCOMMANDS.join(" ")
+            )
+        }
+        _ => out.write_all(script.as_bytes()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use clap_complete::Shell;
+
+    use super::generate;
+
+    fn script(shell: Shell) -> String {
+        let mut out = Vec::new();
+        generate(shell, "address_converter", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn bash_completes_ids() {
+        let script = script(Shell::Bash);
+        assert_eq!(
+            script
+                .matches("opts=\"$(address_converter __complete-ids 2>/dev/null) -y -h")
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn zsh_completes_ids() {
+        let script = script(Shell::Zsh);
+        assert!(script.starts_with("#compdef address_converter"));
+        assert!(script.contains("_address_converter_address_ids() {"));
+        assert_eq!(
+            script
+                .matches(":_address_converter_address_ids' \\")
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn fish_completes_ids() {
+        let script = script(Shell::Fish);
+        assert!(script.contains("-f -a \"(address_converter __complete-ids 2>/dev/null)\""));
+    }
+
+    #[test]
+    fn hides_ids_command() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            assert!(!script.contains("complete-ids)"));
+            assert!(!script.contains("\"__complete-ids\""));
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! The `compose` wizard, prompting for each field of an address and checking
+//! it right away. The prompts are written apart from the resulting JSON, so
+//! that the JSON can be piped to other commands.
+
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use serde_json::Map;
+
+use crate::application::service::{Either, Format};
+use crate::domain::{
+    AddressConvertible, BusinessFrenchAddress, ConvertedAddress, Country, FrenchAddress,
+    FrenchAddressParser, IndividualFrenchAddress, IsoAddress, IsoOrgIdentification,
+    IsoPostalAddress, KindHint, OrgIdentifier,
+};
+
+/// Reads the answers from `input` and writes the prompts and validation
+/// errors to `prompts`.
+pub struct Prompter<R, W> {
+    input: R,
+    prompts: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, prompts: W) -> Self {
+        Self { input, prompts }
+    }
+
+    /// Prompts until the answer passes `validate`. An empty answer is `None`
+    /// and is refused when the field is `required`.
+    fn ask(
+        &mut self,
+        label: &str,
+        required: bool,
+        validate: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<Option<String>, String> {
+        loop {
+            let hint = if required { "" } else { " (optional)" };
+            write!(self.prompts, "{label}{hint}: ").map_err(|e| e.to_string())?;
+            self.prompts.flush().map_err(|e| e.to_string())?;
+
+            let mut answer = String::new();
+            if self
+                .input
+                .read_line(&mut answer)
+                .map_err(|e| e.to_string())?
+                == 0
+            {
+                return Err("Input closed before the address was complete".to_string());
+            }
+
+            let answer = answer.trim();
+            let error = match answer {
+                "" if required => format!("{label} is required"),
+                "" => return Ok(None),
+                _ => match validate(answer) {
+                    Ok(()) => return Ok(Some(answer.to_string())),
+                    Err(error) => error,
+                },
+            };
+            writeln!(self.prompts, "  {error}").map_err(|e| e.to_string())?;
+        }
+    }
+
+    fn required(
+        &mut self,
+        label: &str,
+        validate: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<String, String> {
+        Ok(self.ask(label, true, validate)?.unwrap_or_default())
+    }
+
+    fn optional(&mut self, label: &str) -> Result<Option<String>, String> {
+        self.ask(label, false, |_| Ok(()))
+    }
+}
+
+fn any(_: &str) -> Result<(), String> {
+    Ok(())
+}
+
+fn country(value: &str) -> Result<Country, String> {
+    Country::from_str(value).map_err(|_| format!("Unknown country `{value}`"))
+}
+
+/// Parses comma separated SIREN or SIRET numbers.
+fn identifiers(value: &str) -> Result<Vec<OrgIdentifier>, String> {
+    value
+        .split(',')
+        .map(|identifier| OrgIdentifier::parse(identifier.trim()).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Prompts for every field of an address in `format`, asking the kind of
+/// address first unless given. The complete address is checked by converting
+/// it, and returned without being saved.
+pub fn compose<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    format: Format,
+    kind: KindHint,
+) -> Result<Either<FrenchAddress, IsoAddress>, String> {
+    if !matches!(format, Format::French | Format::Iso20022) {
+        return Err("Only french and iso20022 addresses can be composed".to_string());
+    }
+
+    let business = match kind {
+        KindHint::Individual => false,
+        KindHint::Business => true,
+        KindHint::Auto => ask_kind(prompter)? == KindHint::Business,
+    };
+    // The country comes first, the postal information depends on it.
+    let country_line = prompter.required("Country", |value| country(value).map(|_| ()))?;
+    let country = country(&country_line)?;
+
+    let address = match format {
+        Format::French => {
+            Either::French(compose_french(prompter, business, country_line, &country)?)
+        }
+        _ => Either::Iso20022(compose_iso(prompter, business, &country)?),
+    };
+
+    match &address {
+        Either::French(french) => ConvertedAddress::from_french(french.clone()).map(|_| ()),
+        Either::Iso20022(iso) => ConvertedAddress::from_iso20022(iso.clone()).map(|_| ()),
+        _ => Ok(()),
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(address)
+}
+
+fn ask_kind<R: BufRead, W: Write>(prompter: &mut Prompter<R, W>) -> Result<KindHint, String> {
+    let kind = prompter.required(
+        "Kind (individual or business)",
+        |value| match KindHint::from_str(value) {
+            Ok(KindHint::Individual | KindHint::Business) => Ok(()),
+            _ => Err("Kind must be 'individual' or 'business'".to_string()),
+        },
+    )?;
+
+    KindHint::from_str(&kind).map_err(|e| e.to_string())
+}
+
+fn compose_french<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    business: bool,
+    country_line: String,
+    country: &Country,
+) -> Result<FrenchAddress, String> {
+    let street = |value: &str| match FrenchAddressParser::parse_locality(value) {
+        Some(_) => Ok(()),
+        None => FrenchAddressParser::parse_street_for(value, country)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    };
+    let postal = |value: &str| {
+        FrenchAddressParser::parse_postal_for(value, country)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    };
+
+    if business {
+        let business_name = prompter.required("Business name", any)?;
+        let identifiers = prompter
+            .ask("SIREN or SIRET, comma separated", false, |value| {
+                identifiers(value).map(|_| ())
+            })?
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|identifier| identifier.trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let recipient = prompter.optional("Recipient or service")?;
+        let external_delivery = prompter.optional("Building, residence, entrance")?;
+        let street = prompter.ask("Street, or lieu-dit", false, street)?;
+        let distribution_info = prompter.optional("Distribution information (BP, lieu-dit)")?;
+        let postal = prompter.required("Postcode and town", postal)?;
+
+        return Ok(FrenchAddress::Business(BusinessFrenchAddress {
+            business_name,
+            identifiers,
+            recipient,
+            external_delivery,
+            street,
+            distribution_info,
+            postal,
+            country: country_line,
+            extras: Map::new(),
+        }));
+    }
+
+    let name = prompter.required("Name", any)?;
+    let internal_delivery = prompter.optional("Apartment, floor, staircase")?;
+    let external_delivery = prompter.optional("Building, residence, entrance")?;
+    let street = prompter.ask("Street, or lieu-dit", false, street)?;
+    let distribution_info = prompter.optional("Distribution information (BP, hamlet)")?;
+    let postal = prompter.required("Postcode and town", postal)?;
+
+    Ok(FrenchAddress::Individual(IndividualFrenchAddress {
+        name,
+        internal_delivery,
+        external_delivery,
+        street,
+        distribution_info,
+        postal,
+        country: country_line,
+        extras: Map::new(),
+    }))
+}
+
+fn compose_iso<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    business: bool,
+    country: &Country,
+) -> Result<IsoAddress, String> {
+    let name = prompter.required(if business { "Business name" } else { "Name" }, any)?;
+    let organisation_identification = if business {
+        prompter
+            .ask("SIREN or SIRET, comma separated", false, |value| {
+                identifiers(value).map(|_| ())
+            })?
+            .map(|value| identifiers(&value))
+            .transpose()?
+            .unwrap_or_default()
+            .iter()
+            .map(|identifier| IsoOrgIdentification {
+                id: identifier.value().to_string(),
+                scheme_name: identifier.scheme().to_string(),
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let (department, sub_department) = if business {
+        (
+            prompter.optional("Department or service")?,
+            prompter.optional("Contact person")?,
+        )
+    } else {
+        (None, None)
+    };
+
+    let care_of = prompter.optional("Care of")?;
+    let street_name = prompter.optional("Street name")?;
+    let building_number = prompter.optional("Building number")?;
+    let floor = prompter.optional("Floor")?;
+    let room = prompter.optional("Room")?;
+    let postbox = prompter.optional("Post box")?;
+    let postcode = prompter.required("Postcode", |value| {
+        let french = value.len() == 5 && value.chars().all(|c| c.is_ascii_digit());
+        if *country == Country::France && !french {
+            return Err("French postcodes have 5 digits".to_string());
+        }
+        Ok(())
+    })?;
+    let town_name = prompter.required("Town", any)?;
+    let town_location_name = prompter.optional("Town location or lieu-dit")?;
+    let country_sub_division = prompter.optional("Country subdivision")?;
+
+    let postal_address = IsoPostalAddress {
+        care_of,
+        street_name,
+        building_number,
+        floor,
+        room,
+        postbox,
+        department,
+        sub_department,
+        postcode,
+        town_name,
+        town_location_name,
+        country_sub_division,
+        country: country.iso_code().to_string(),
+    };
+
+    Ok(if business {
+        IsoAddress::BusinessIsoAddress {
+            business_name: name,
+            organisation_identification,
+            postal_address,
+            extras: Map::new(),
+        }
+    } else {
+        IsoAddress::IndividualIsoAddress {
+            name,
+            postal_address,
+            extras: Map::new(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compose, Prompter};
+    use crate::application::service::{Either, Format};
+    use crate::domain::{FrenchAddress, IsoAddress, KindHint};
+
+    fn run(
+        format: Format,
+        kind: KindHint,
+        answers: &str,
+    ) -> (Result<Either<FrenchAddress, IsoAddress>, String>, String) {
+        let mut prompts = Vec::new();
+        let mut prompter = Prompter::new(answers.as_bytes(), &mut prompts);
+        let result = compose(&mut prompter, format, kind);
+
+        (result, String::from_utf8(prompts).unwrap())
+    }
+
+    #[test]
+    fn it_should_reprompt_invalid_fields() {
+        // The first postal answer lacks the postcode.
+        let answers = "individual\nFRANCE\nMonsieur Jean DELHOURME\n\n\n25 RUE DE L'EGLISE\n\nMIOS\n33380 MIOS\n";
+        let (result, prompts) = run(Format::French, KindHint::Auto, answers);
+
+        let Ok(Either::French(FrenchAddress::Individual(address))) = result else {
+            panic!("expected an individual french address: {result:?}");
+        };
+        assert_eq!(address.postal, "33380 MIOS");
+        assert_eq!(address.street.as_deref(), Some("25 RUE DE L'EGLISE"));
+        assert!(prompts.contains("Invalid postal information `MIOS`"));
+    }
+
+    #[test]
+    fn it_should_compose_iso_business_addresses() {
+        let answers = "fr\nSociété DUPONT\n732 829 320 00074\nService achats\nMademoiselle Lucie MARTIN\n\nRUE EMILE ZOLA\n56\n\n\n\n3409\n34092\nMONTPELLIER CEDEX 5\n\n\n";
+        let (result, prompts) = run(Format::Iso20022, KindHint::Business, answers);
+
+        let Ok(Either::Iso20022(IsoAddress::BusinessIsoAddress {
+            organisation_identification,
+            postal_address,
+            ..
+        })) = result
+        else {
+            panic!("expected a business ISO 20022 address: {result:?}");
+        };
+        assert_eq!(organisation_identification[0].id, "73282932000074");
+        assert_eq!(
+            postal_address.sub_department.as_deref(),
+            Some("Mademoiselle Lucie MARTIN")
+        );
+        assert_eq!(postal_address.postcode, "34092");
+        assert_eq!(postal_address.country, "FR");
+        assert!(prompts.contains("French postcodes have 5 digits"));
+    }
+
+    #[test]
+    fn it_should_fail_on_closed_input() {
+        let (result, _) = run(Format::French, KindHint::Individual, "FRANCE\n");
+
+        assert!(result.is_err());
+    }
+}
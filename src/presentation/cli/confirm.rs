@@ -0,0 +1,98 @@
+//! The confirmation of the commands losing stored data (e.g., `delete`,
+//! `dedupe --apply`), asked on the terminal unless `--yes` is given.
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// A command losing stored data, confirmed before it runs.
+pub struct Destructive {
+    /// What the command does, asked as a question (e.g., "Delete the address
+    /// `3f2a`").
+    pub action: String,
+    /// Whether the command changes many addresses at once. These are refused
+    /// when nobody can be asked, so that the scripts opt in with `--yes`.
+    pub bulk: bool,
+    /// The command line running it, for the error refusing it.
+    pub command: &'static str,
+}
+
+impl Destructive {
+    /// Asks for the confirmation when stdin and stderr are a terminal. Fails
+    /// when refused, or when a bulk command isn't run interactively.
+    pub fn confirm(&self, yes: bool) -> Result<(), String> {
+        let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+
+        self.confirm_with(yes, interactive, io::stdin().lock(), io::stderr())
+    }
+
+    fn confirm_with(
+        &self,
+        yes: bool,
+        interactive: bool,
+        mut input: impl BufRead,
+        mut prompts: impl Write,
+    ) -> Result<(), String> {
+        if yes {
+            return Ok(());
+        }
+        if !interactive && self.bulk {
+            return Err(format!(
+                "`{}` changes the addresses in bulk: give --yes to run it non-interactively",
+                self.command
+            ));
+        }
+        if !interactive {
+            return Ok(());
+        }
+
+        write!(prompts, "{}? [y/N] ", self.action).map_err(|e| e.to_string())?;
+        prompts.flush().map_err(|e| e.to_string())?;
+        let mut answer = String::new();
+        input.read_line(&mut answer).map_err(|e| e.to_string())?;
+
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => Ok(()),
+            _ => Err("Aborted".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Destructive;
+
+    #[test]
+    fn it_should_ask_for_the_confirmation() {
+        let dedupe = Destructive {
+            action: "Delete the duplicates".to_string(),
+            bulk: true,
+            command: "dedupe --apply",
+        };
+        let confirm = |yes, interactive, answer: &str| {
+            let mut prompts = Vec::new();
+            let result = dedupe.confirm_with(yes, interactive, answer.as_bytes(), &mut prompts);
+            (result, String::from_utf8(prompts).unwrap())
+        };
+
+        assert_eq!(
+            confirm(false, true, "Y\n"),
+            (Ok(()), "Delete the duplicates? [y/N] ".to_string())
+        );
+        assert_eq!(confirm(false, true, "\n").0, Err("Aborted".to_string()));
+        assert_eq!(confirm(false, true, "").0, Err("Aborted".to_string()));
+        assert_eq!(confirm(true, false, ""), (Ok(()), String::new()));
+        assert_eq!(
+            confirm(false, false, "y\n").0,
+            Err(
+                "`dedupe --apply` changes the addresses in bulk: give --yes to run it non-interactively"
+                    .to_string()
+            )
+        );
+
+        let delete = Destructive {
+            bulk: false,
+            ..dedupe
+        };
+        assert!(delete
+            .confirm_with(false, false, "".as_bytes(), Vec::new())
+            .is_ok());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::domain::Address;
+use serde_json::Value;
+
+/// The stored address as a tree, one field per line: everything the domain
+/// model holds, including the bookkeeping fields that the formats leave out
+/// (timestamps, version, status, routing, notes and metadata), followed by
+/// its fingerprint.
+pub fn address_tree(address: &Address) -> Result<String, String> {
+    let mut fields = match serde_json::to_value(address).map_err(|e| e.to_string())? {
+        Value::Object(fields) => fields,
+        _ => unreachable!("an address is serialized as an object"),
+    };
+    fields.insert(
+        "fingerprint".to_string(),
+        Value::String(address.fingerprint().to_string()),
+    );
+
+    let mut tree = String::from("Address");
+    write_children(&mut tree, "", &Value::Object(fields));
+
+    Ok(tree)
+}
+
+fn write_children(tree: &mut String, indent: &str, value: &Value) {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(fields) => fields.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{i}]"), v))
+            .collect(),
+        _ => return,
+    };
+
+    for (i, (label, child)) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let (branch, next_indent) = if last {
+            ("└── ", format!("{indent}    "))
+        } else {
+            ("├── ", format!("{indent}│   "))
+        };
+
+        tree.push('\n');
+        tree.push_str(indent);
+        tree.push_str(branch);
+        tree.push_str(label);
+        match child {
+            Value::Object(fields) if fields.is_empty() => tree.push_str(": {}"),
+            Value::Array(items) if items.is_empty() => tree.push_str(": []"),
+            Value::Object(_) | Value::Array(_) => write_children(tree, &next_indent, child),
+            Value::Null => tree.push_str(": none"),
+            Value::String(s) => {
+                tree.push_str(": ");
+                tree.push_str(s);
+            }
+            scalar => {
+                tree.push_str(": ");
+                tree.push_str(&scalar.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::address_tree;
+    use crate::domain::{Address, AddressConvertible, ConvertedAddress, FrenchAddress};
+
+    #[test]
+    fn it_should_print_the_address_as_a_tree() {
+        let french: FrenchAddress = serde_json::from_str(
+            r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Chez Mireille COPEAU Appartement 2", "street": "25BIS RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        )
+        .unwrap();
+        let address = Address::new(ConvertedAddress::from_french(french).unwrap());
+        let tree = address_tree(&address).unwrap();
+        let lines: Vec<&str> = tree.lines().collect();
+
+        assert_eq!(lines[0], "Address");
+        assert_eq!(lines[1], format!("├── id: {}", address.id()));
+        assert!(lines.contains(&"├── version: 1"));
+        assert!(lines.contains(&"│   ├── number: 25"));
+        assert!(lines.contains(&"│   ├── repetition_index: BIS"));
+        assert!(lines.contains(&"│   ├── kind: RUE"));
+        assert!(lines.contains(&"│   ├── external: none"));
+        assert_eq!(
+            lines.last().unwrap(),
+            &format!("└── fingerprint: {}", address.fingerprint())
+        );
+    }
+}
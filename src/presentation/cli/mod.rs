@@ -1,2 +1,16 @@
 #[cfg(feature = "cli")]
 pub mod commands;
+#[cfg(feature = "cli")]
+pub mod completions;
+#[cfg(feature = "cli")]
+pub mod compose;
+#[cfg(feature = "cli")]
+pub mod confirm;
+#[cfg(feature = "cli")]
+pub mod inspect;
+#[cfg(feature = "cli")]
+pub mod progress;
+#[cfg(feature = "cli")]
+pub mod table;
+#[cfg(feature = "watch")]
+pub mod watch;
@@ -0,0 +1,172 @@
+//! The progress of the long batch commands, on stderr: a bar with the
+//! throughput and the remaining time on a terminal, and a line every few
+//! seconds otherwise (e.g., in the logs of a scheduled job).
+use std::cell::{Cell, RefCell};
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// How often a line is printed when stderr isn't a terminal.
+const LINE_INTERVAL: Duration = Duration::from_secs(10);
+const BAR_TEMPLATE: &str = "{msg} [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})";
+
+pub struct ProgressReporter {
+    label: &'static str,
+    output: Output,
+}
+
+enum Output {
+    Hidden,
+    Bar(ProgressBar),
+    Lines {
+        started: Instant,
+        last_line: Cell<Instant>,
+        interval: Duration,
+        writer: RefCell<Box<dyn Write>>,
+    },
+}
+
+impl ProgressReporter {
+    /// Reports the progress of the operation named `label` (e.g.,
+    /// `Importing`), nothing being printed when `quiet`.
+    pub fn new(label: &'static str, quiet: bool) -> Self {
+        let output = if quiet {
+            Output::Hidden
+        } else if io::stderr().is_terminal() {
+            let style = ProgressStyle::with_template(BAR_TEMPLATE)
+                .expect("the template is valid")
+                .progress_chars("=> ");
+            Output::Bar(ProgressBar::new(0).with_style(style).with_message(label))
+        } else {
+            return Self::lines(label, LINE_INTERVAL, io::stderr());
+        };
+
+        Self { label, output }
+    }
+
+    /// Writes a line to `writer` every `interval`.
+    fn lines(label: &'static str, interval: Duration, writer: impl Write + 'static) -> Self {
+        let now = Instant::now();
+        let output = Output::Lines {
+            started: now,
+            last_line: Cell::new(now),
+            interval,
+            writer: RefCell::new(Box::new(writer)),
+        };
+
+        Self { label, output }
+    }
+
+    /// The callback of the batch operations of the service, e.g.,
+    /// [`AddressService::import_with_progress`].
+    ///
+    /// [`AddressService::import_with_progress`]: crate::application::service::AddressService::import_with_progress
+    pub fn update(&self, done: usize, total: usize) {
+        match &self.output {
+            Output::Hidden => {}
+            Output::Bar(bar) => {
+                bar.set_length(total as u64);
+                bar.set_position(done as u64);
+            }
+            Output::Lines {
+                started,
+                last_line,
+                interval,
+                writer,
+            } => {
+                if last_line.get().elapsed() < *interval || done == total {
+                    return;
+                }
+                last_line.set(Instant::now());
+
+                let elapsed = started.elapsed().as_secs_f64();
+                let rate = if elapsed > 0.0 {
+                    done as f64 / elapsed
+                } else {
+                    0.0
+                };
+                let eta = if rate > 0.0 {
+                    format!("{:.0}s", (total - done) as f64 / rate)
+                } else {
+                    "unknown".to_string()
+                };
+                // Like eprintln!, a progress line failing to be written is
+                // ignored.
+                let _ = writeln!(
+                    writer.borrow_mut(),
+                    "{}: {done}/{total} ({rate:.1}/s, ETA {eta})",
+                    self.label
+                );
+            }
+        }
+    }
+
+    /// Removes the bar, so that the summary of the command follows.
+    pub fn finish(&self) {
+        if let Output::Bar(bar) = &self.output {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use super::ProgressReporter;
+
+    /// The lines written, shared with the reporter.
+    #[derive(Clone, Default)]
+    struct Lines(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Lines {
+        fn take(&self) -> String {
+            String::from_utf8(self.0.take()).unwrap()
+        }
+    }
+
+    #[test]
+    fn it_should_print_a_line_every_interval() {
+        let lines = Lines::default();
+        let progress = ProgressReporter::lines("Exporting", Duration::ZERO, lines.clone());
+
+        progress.update(0, 4);
+        assert_eq!(lines.take(), "Exporting: 0/4 (0.0/s, ETA unknown)\n");
+        progress.update(2, 4);
+        let line = lines.take();
+        assert!(line.starts_with("Exporting: 2/4 ("), "{line}");
+        assert!(
+            line.contains("/s, ETA ") && line.ends_with("s)\n"),
+            "{line}"
+        );
+
+        // The summary of the command follows the last one.
+        progress.update(4, 4);
+        assert_eq!(lines.take(), "");
+    }
+
+    #[test]
+    fn it_should_wait_for_the_interval() {
+        let lines = Lines::default();
+        let progress =
+            ProgressReporter::lines("Importing", Duration::from_secs(3600), lines.clone());
+
+        progress.update(0, 4);
+        progress.update(1, 4);
+        assert_eq!(lines.take(), "");
+    }
+}
@@ -0,0 +1,156 @@
+use crate::application::stats::{AddressStats, GOOD_QUALITY, POOR_QUALITY};
+use crate::domain::{Address, Recipient};
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{Attribute, Cell, CellAlignment, Color, Table};
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// Whether the tables are colored: not with `--no-color`, the `NO_COLOR`
+/// environment variable, or when stdout isn't a terminal.
+pub fn use_color(no_color: bool) -> bool {
+    !no_color && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal()
+}
+
+fn table(color: bool) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    if color {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+
+    table
+}
+
+/// One row per address: its ID, quality score, recipient, town, department,
+/// status and fingerprint.
+pub fn address_table(addresses: &[Address], color: bool) -> Table {
+    let mut table = table(color);
+    table.set_header(vec![
+        "ID",
+        "Quality",
+        "Recipient",
+        "Postcode",
+        "Town",
+        "Department",
+        "Status",
+        "Fingerprint",
+    ]);
+    if let Some(column) = table.column_mut(1) {
+        column.set_cell_alignment(CellAlignment::Right);
+    }
+
+    for address in addresses {
+        let name = match &address.recipient {
+            Recipient::Individual { name } => name,
+            Recipient::Business { company_name, .. } => company_name,
+        };
+        let quality = address.quality_score();
+        let quality_color = if quality >= GOOD_QUALITY {
+            Color::Green
+        } else if quality < POOR_QUALITY {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+
+        table.add_row(vec![
+            Cell::new(address.id()).fg(Color::Cyan),
+            Cell::new(quality).fg(quality_color),
+            Cell::new(name).add_attribute(Attribute::Bold),
+            Cell::new(&address.postal_details.postcode),
+            Cell::new(&address.postal_details.town).fg(Color::Magenta),
+            Cell::new(address.department_code().unwrap_or_default()),
+            Cell::new(address.status()),
+            Cell::new(address.fingerprint()),
+        ]);
+    }
+
+    table
+}
+
+/// The statistics, one per row.
+pub fn stats_table(stats: &AddressStats, color: bool) -> Table {
+    let mut table = table(color);
+    table.set_header(vec!["Statistic", "Value"]);
+    if let Some(column) = table.column_mut(1) {
+        column.set_cell_alignment(CellAlignment::Right);
+    }
+
+    let mut rows = vec![
+        ("Addresses".to_string(), stats.total.to_string()),
+        ("  individuals".to_string(), stats.individuals.to_string()),
+        ("  businesses".to_string(), stats.businesses.to_string()),
+    ];
+    if let Some(average_quality) = stats.average_quality {
+        rows.push((
+            "Average quality".to_string(),
+            format!("{average_quality:.1}"),
+        ));
+        rows.push((
+            format!("  {GOOD_QUALITY} and above"),
+            stats.good_quality.to_string(),
+        ));
+        rows.push((
+            format!("  under {POOR_QUALITY}"),
+            stats.poor_quality.to_string(),
+        ));
+    }
+    if !stats.regions.is_empty() {
+        rows.push(("Regions".to_string(), String::new()));
+    }
+    for (region, count) in &stats.regions {
+        rows.push((format!("  {region}"), count.to_string()));
+    }
+    for (statistic, value) in rows {
+        table.add_row(vec![Cell::new(statistic), Cell::new(value)]);
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{address_table, stats_table};
+    use crate::application::stats::AddressStats;
+    use crate::domain::{Address, AddressConvertible, ConvertedAddress, FrenchAddress};
+
+    fn address() -> Address {
+        let french: FrenchAddress = serde_json::from_str(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+        )
+        .unwrap();
+
+        Address::new(ConvertedAddress::from_french(french).unwrap())
+    }
+
+    #[test]
+    fn it_should_align_plain_tables() {
+        let address = address();
+        let rendered = address_table(std::slice::from_ref(&address), false).to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert!(!rendered.contains('\u{1b}'));
+        assert!(lines[1].contains("Recipient"));
+        assert!(lines[3].contains(&address.id().to_string()));
+        assert!(lines[3].contains("Madame Isabelle RICHARD"));
+        assert!(lines[3].contains("AUTERIVE"));
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() == lines[0].chars().count()));
+    }
+
+    #[test]
+    fn it_should_color_on_demand() {
+        let colored = address_table(&[address()], true).to_string();
+        assert!(colored.contains('\u{1b}'));
+
+        let stats = AddressStats {
+            total: 1,
+            individuals: 1,
+            ..Default::default()
+        };
+        assert!(!stats_table(&stats, false).to_string().contains('\u{1b}'));
+    }
+}
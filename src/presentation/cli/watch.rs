@@ -0,0 +1,273 @@
+//! Drop folder integration. Every JSON or CSV file landing in the watched
+//! directory is imported with the batch pipeline, then moved to the `done` or
+//! `failed` subfolder depending on the outcome.
+//!
+//! Producers should write files elsewhere and move them into the watched
+//! directory once complete, otherwise a partially written file could be read.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use chrono::Utc;
+use notify::{RecursiveMode, Watcher};
+
+use crate::application::batch::{BatchFileKind, InputEncoding};
+use crate::application::service::{AddressServiceError, BoxedAddressService, Format};
+use crate::domain::repositories::AddressRepositoryError;
+
+/// Subfolder receiving the files whose records were all imported.
+pub const DONE_DIR: &str = "done";
+/// Subfolder receiving the files that couldn't be read or contained at least
+/// one rejected record.
+pub const FAILED_DIR: &str = "failed";
+
+/// Why a file couldn't be processed.
+enum FileError {
+    /// The storage operations were cancelled (e.g., on Ctrl-C): the watcher
+    /// stops.
+    Cancelled,
+    /// Only this file is affected, the next ones being processed.
+    Failed(String),
+}
+
+/// Blocks and imports incoming files until the watcher or the storage (e.g.,
+/// on Ctrl-C) stops. Files already present in the directory are processed
+/// first. In upsert mode, duplicates of stored addresses are updated instead
+/// of rejected.
+pub fn watch(
+    dir: &Path,
+    service: &BoxedAddressService,
+    format: Format,
+    upsert: bool,
+) -> Result<(), String> {
+    process_pending(dir, service, format, upsert)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| e.to_string())?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    println!("\nWatching {} for incoming address files", dir.display());
+
+    for event in rx {
+        match event {
+            Ok(event) if event.kind.is_create() || event.kind.is_modify() => {
+                process_pending(dir, service, format, upsert)?;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Watch error: {e}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Imports every supported file currently in `dir`. Returns the new location
+/// of the processed files. A file that can't be read or has a rejected record
+/// is reported and moved to the `failed` subfolder. Once the storage is
+/// cancelled, the file being imported is left in place for the next run and
+/// the watcher stops.
+pub fn process_pending(
+    dir: &Path,
+    service: &BoxedAddressService,
+    format: Format,
+    upsert: bool,
+) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir.join(DONE_DIR)).map_err(|e| e.to_string())?;
+    fs::create_dir_all(dir.join(FAILED_DIR)).map_err(|e| e.to_string())?;
+
+    let mut processed = Vec::new();
+
+    for dir_entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = dir_entry.map_err(|e| e.to_string())?.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let Some(kind) = BatchFileKind::from_path(&path) else {
+            continue;
+        };
+        match process_file(dir, &path, kind, service, format, upsert) {
+            Ok(target) => processed.push(target),
+            Err(FileError::Cancelled) => return Err(AddressRepositoryError::Cancelled.to_string()),
+            Err(FileError::Failed(e)) => eprintln!("{}: {e}", path.display()),
+        }
+    }
+
+    Ok(processed)
+}
+
+fn process_file(
+    dir: &Path,
+    path: &Path,
+    kind: BatchFileKind,
+    service: &BoxedAddressService,
+    format: Format,
+    upsert: bool,
+) -> Result<PathBuf, FileError> {
+    let file_name = path
+        .file_name()
+        .ok_or(FileError::Failed("Invalid file path".to_string()))?;
+
+    let result = fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| InputEncoding::Auto.decode(bytes).map_err(|e| e.to_string()))
+        .map(|content| service.import(&content, kind, format, upsert));
+    // Left in place to be imported again, the addresses already saved being
+    // duplicates then.
+    let cancelled = match &result {
+        Ok(Ok(report)) => report
+            .failures
+            .iter()
+            .any(|failure| cancelled(&failure.error)),
+        Ok(Err(e)) => cancelled(e),
+        Err(_) => false,
+    };
+    if cancelled {
+        eprintln!("{}: import cancelled", file_name.to_string_lossy());
+        return Err(FileError::Cancelled);
+    }
+
+    let target_dir = match result.and_then(|imported| imported.map_err(|e| e.to_string())) {
+        Ok(report) => {
+            println!(
+                "{}: imported {}/{} addresses ({} updated)",
+                file_name.to_string_lossy(),
+                report.saved.len() + report.updated.len(),
+                report.total(),
+                report.updated.len()
+            );
+            for failure in &report.failures {
+                eprintln!(
+                    "{}: record {} rejected: {}",
+                    file_name.to_string_lossy(),
+                    failure.record,
+                    failure.error
+                );
+            }
+
+            if report.failures.is_empty() {
+                DONE_DIR
+            } else {
+                FAILED_DIR
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: import failed: {e}", file_name.to_string_lossy());
+            FAILED_DIR
+        }
+    };
+
+    let target = target_path(&dir.join(target_dir), file_name);
+    fs::rename(path, &target).map_err(|e| FileError::Failed(e.to_string()))?;
+
+    Ok(target)
+}
+
+fn cancelled(error: &AddressServiceError) -> bool {
+    matches!(
+        error,
+        AddressServiceError::PersistenceError(AddressRepositoryError::Cancelled)
+    )
+}
+
+/// The location of `file_name` in `target_dir`, suffixed with the time when a
+/// file of the same name is already there (e.g., a daily file dropped again).
+fn target_path(target_dir: &Path, file_name: &OsStr) -> PathBuf {
+    let target = target_dir.join(file_name);
+    if !target.exists() {
+        return target;
+    }
+
+    let file_name = Path::new(file_name);
+    let stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+    let suffix = Utc::now().format("%Y%m%dT%H%M%S%.9f");
+    let renamed = match file_name.extension() {
+        Some(extension) => format!("{stem}-{suffix}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{suffix}"),
+    };
+
+    target_dir.join(renamed)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{process_pending, DONE_DIR, FAILED_DIR};
+    use crate::application::service::{BoxedAddressService, Format};
+    use crate::infrastructure::{CancellationToken, InMemoryAddressRepository, TimeoutRepository};
+
+    const ADDRESS: &str = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    #[test]
+    fn moves_processed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let service = BoxedAddressService::new(Box::new(InMemoryAddressRepository::new()));
+
+        fs::write(dir.join("valid.json"), ADDRESS).unwrap();
+        fs::write(
+            dir.join("invalid.csv"),
+            "name,postal,country\nMadame Isabelle RICHARD,AUTERIVE,FRANCE\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let processed = process_pending(dir, &service, Format::French, false).unwrap();
+
+        assert_eq!(processed.len(), 2);
+        assert!(dir.join(DONE_DIR).join("valid.json").exists());
+        assert!(dir.join(FAILED_DIR).join("invalid.csv").exists());
+        assert!(dir.join("notes.txt").exists());
+        assert_eq!(service.repository.fetch_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keeps_the_files_of_the_same_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let service = BoxedAddressService::new(Box::new(InMemoryAddressRepository::new()));
+
+        fs::write(dir.join("daily.json"), ADDRESS).unwrap();
+        process_pending(dir, &service, Format::French, false).unwrap();
+        fs::write(dir.join("daily.json"), ADDRESS).unwrap();
+        let processed = process_pending(dir, &service, Format::French, true).unwrap();
+
+        assert_eq!(processed.len(), 1);
+        let file_name = processed[0].file_name().unwrap().to_string_lossy();
+        assert!(
+            file_name.starts_with("daily-") && file_name.ends_with(".json"),
+            "{file_name}"
+        );
+        assert_eq!(fs::read_dir(dir.join(DONE_DIR)).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn stops_once_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let cancellation = CancellationToken::new();
+        let repository = TimeoutRepository::cancellable(InMemoryAddressRepository::new())
+            .with_cancellation(cancellation.clone());
+        let service = BoxedAddressService::new(Box::new(repository));
+
+        fs::write(dir.join("first.json"), ADDRESS).unwrap();
+        fs::write(dir.join("second.json"), ADDRESS).unwrap();
+        cancellation.cancel();
+
+        let result = process_pending(dir, &service, Format::French, false);
+        assert_eq!(result, Err("Operation cancelled".to_string()));
+        // Both files wait for the next run.
+        assert!(dir.join("first.json").exists());
+        assert!(dir.join("second.json").exists());
+        assert_eq!(fs::read_dir(dir.join(FAILED_DIR)).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(dir.join(DONE_DIR)).unwrap().count(), 0);
+    }
+}
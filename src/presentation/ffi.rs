@@ -0,0 +1,285 @@
+//! C bindings, behind the `ffi` feature, for the services embedding the
+//! converter in-process (C, C++, C#). The header, `include/address_converter.h`,
+//! is generated from this module with cbindgen.
+//!
+//! The strings given are NUL-terminated UTF-8. The functions write their
+//! result into a buffer of the caller, NUL-terminated: the converted address
+//! on success, the JSON details of the error otherwise (e.g.,
+//! `{"code":"E_POSTAL_FORMAT","error":"...","field":"postal","value":"MIOS"}`).
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_char, CStr};
+use std::panic::{self, UnwindSafe};
+use std::ptr;
+
+use serde_json::{json, Value};
+
+use crate::application::service::{Format, UnknownFormat};
+use crate::{convert_str, validate_str, Error, ErrorKind};
+
+/// The outcome of a call, its value being stable across releases.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcStatus {
+    /// The buffer holds the result.
+    Ok = 0,
+    /// A string is NULL or isn't UTF-8, or a format is unknown.
+    InvalidArgument = 1,
+    /// The input can't be read (e.g., malformed JSON).
+    InvalidInput = 2,
+    /// The input is read, but isn't a valid address (e.g., missing field,
+    /// invalid postal line).
+    InvalidAddress = 3,
+    /// The buffer is too small for the result, whose length is written to
+    /// `out_written`. The buffer receives an empty string.
+    BufferTooSmall = 4,
+    /// Any other failure, the converter included.
+    Internal = 5,
+}
+
+/// A failed call: its status and the details written to the buffer.
+struct Failure(AcStatus, Value);
+
+impl Failure {
+    fn invalid_argument(message: String) -> Self {
+        Failure(
+            AcStatus::InvalidArgument,
+            json!({ "code": "E_INVALID_ARGUMENT", "error": message }),
+        )
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        let status = match error.kind() {
+            ErrorKind::InvalidInput => AcStatus::InvalidInput,
+            ErrorKind::InvalidAddress => AcStatus::InvalidAddress,
+            _ => AcStatus::Internal,
+        };
+        let mut details = json!({ "code": error.code(), "error": error.to_string() });
+        if let Error::Conversion(error) = &error {
+            details["field"] = json!(error.field());
+            if let Some(value) = error.value() {
+                details["value"] = json!(value);
+            }
+        }
+
+        Failure(status, details)
+    }
+}
+
+/// Converts the JSON `input` of the format `from` (e.g., `french`) to the
+/// JSON of the format `to` (e.g., `iso20022`). The formats are read like the
+/// CLI reads them, aliases included.
+///
+/// # Safety
+///
+/// `input`, `from` and `to` must be NUL-terminated strings. `out_buf` must be
+/// valid for `out_len` bytes, or NULL with `out_len` being 0. `out_written`
+/// must be NULL or valid for a write: it receives the length of the result,
+/// without its NUL, so that a caller given [`AcStatus::BufferTooSmall`] can
+/// retry with a large enough buffer.
+#[no_mangle]
+pub unsafe extern "C" fn ac_convert(
+    input: *const c_char,
+    from: *const c_char,
+    to: *const c_char,
+    out_buf: *mut c_char,
+    out_len: usize,
+    out_written: *mut usize,
+) -> AcStatus {
+    let result = catch(|| {
+        let input = string(input, "input")?;
+        let from = format(from, "from")?;
+        let to = format(to, "to")?;
+
+        Ok(convert_str(input, from, to)?)
+    });
+
+    write_result(result, out_buf, out_len, out_written)
+}
+
+/// Checks the JSON `input` of `format` as it would be before being saved.
+/// The buffer receives an empty string when the address is valid.
+///
+/// # Safety
+///
+/// Same as [`ac_convert`].
+#[no_mangle]
+pub unsafe extern "C" fn ac_validate(
+    input: *const c_char,
+    format: *const c_char,
+    out_buf: *mut c_char,
+    out_len: usize,
+    out_written: *mut usize,
+) -> AcStatus {
+    let result = catch(|| {
+        let input = string(input, "input")?;
+        validate_str(input, self::format(format, "format")?)?;
+
+        Ok(String::new())
+    });
+
+    write_result(result, out_buf, out_len, out_written)
+}
+
+/// Runs `f`, a panic being reported as an internal failure instead of
+/// unwinding into the caller, which is undefined behavior.
+fn catch(f: impl FnOnce() -> Result<String, Failure> + UnwindSafe) -> Result<String, Failure> {
+    panic::catch_unwind(f).unwrap_or_else(|_| {
+        Err(Failure(
+            AcStatus::Internal,
+            json!({ "code": "E_INTERNAL", "error": "The converter panicked" }),
+        ))
+    })
+}
+
+/// # Safety
+///
+/// `text` must be NULL or a NUL-terminated string outliving `'a`.
+unsafe fn string<'a>(text: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if text.is_null() {
+        return Err(Failure::invalid_argument(format!("`{name}` is NULL")));
+    }
+
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| Failure::invalid_argument(format!("`{name}` isn't UTF-8")))
+}
+
+/// # Safety
+///
+/// Same as [`string`].
+unsafe fn format(name: *const c_char, argument: &str) -> Result<Format, Failure> {
+    string(name, argument)?
+        .parse()
+        .map_err(|e: UnknownFormat| Failure::invalid_argument(e.to_string()))
+}
+
+/// Writes the result, or the details of the failure, to the buffer.
+///
+/// # Safety
+///
+/// Same as the buffer of [`ac_convert`].
+unsafe fn write_result(
+    result: Result<String, Failure>,
+    out_buf: *mut c_char,
+    out_len: usize,
+    out_written: *mut usize,
+) -> AcStatus {
+    let (status, text) = match result {
+        Ok(text) => (AcStatus::Ok, text),
+        Err(Failure(status, details)) => (status, details.to_string()),
+    };
+
+    if !out_written.is_null() {
+        *out_written = text.len();
+    }
+    if text.len() >= out_len {
+        if out_len > 0 {
+            *out_buf = 0;
+        }
+        // A failure is reported as is, its details being optional, like an
+        // empty result.
+        return match status {
+            AcStatus::Ok if !text.is_empty() => AcStatus::BufferTooSmall,
+            status => status,
+        };
+    }
+
+    ptr::copy_nonoverlapping(text.as_ptr(), out_buf.cast::<u8>(), text.len());
+    *out_buf.add(text.len()) = 0;
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use super::{ac_convert, ac_validate, AcStatus};
+
+    const ADDRESS: &str = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    /// Calls `ac_convert` with a buffer of `len` bytes.
+    fn convert(input: &str, from: &str, to: &str, len: usize) -> (AcStatus, String, usize) {
+        let (input, from, to) = (
+            CString::new(input).unwrap(),
+            CString::new(from).unwrap(),
+            CString::new(to).unwrap(),
+        );
+        let mut buf = vec![0; len.max(1)];
+        let mut written = 0;
+        let status = unsafe {
+            ac_convert(
+                input.as_ptr(),
+                from.as_ptr(),
+                to.as_ptr(),
+                buf.as_mut_ptr(),
+                len,
+                &mut written,
+            )
+        };
+        let text = unsafe { CStr::from_ptr(buf.as_ptr()) };
+
+        (status, text.to_str().unwrap().to_string(), written)
+    }
+
+    #[test]
+    fn it_should_convert_into_the_buffer() {
+        let (status, iso, written) = convert(ADDRESS, "french", "iso", 1024);
+        assert_eq!(status, AcStatus::Ok);
+        assert!(iso.contains(r#""town_name":"MIOS""#));
+        assert_eq!(written, iso.len());
+
+        let (status, text, needed) = convert(ADDRESS, "french", "iso", 16);
+        assert_eq!(
+            (status, text.as_str(), needed),
+            (AcStatus::BufferTooSmall, "", written)
+        );
+        assert_eq!(convert(ADDRESS, "fr", "iso", written + 1).0, AcStatus::Ok);
+    }
+
+    #[test]
+    fn it_should_report_the_failures() {
+        let (status, details, _) =
+            convert(&ADDRESS.replace("33380 MIOS", "MIOS"), "fr", "iso", 1024);
+        assert_eq!(status, AcStatus::InvalidAddress);
+        let details: serde_json::Value = serde_json::from_str(&details).unwrap();
+        assert_eq!(details["code"], "E_POSTAL_FORMAT");
+        assert_eq!(details["field"], "postal");
+
+        let (status, details, _) = convert(ADDRESS, "fr", "xml", 1024);
+        assert_eq!(status, AcStatus::InvalidArgument);
+        assert!(details.contains("E_INVALID_ARGUMENT"));
+        assert_eq!(convert("{", "fr", "iso", 1024).0, AcStatus::InvalidInput);
+
+        let format = CString::new("french").unwrap();
+        let status = unsafe {
+            ac_validate(
+                ptr::null(),
+                format.as_ptr(),
+                ptr::null_mut(),
+                0,
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, AcStatus::InvalidArgument);
+    }
+
+    #[test]
+    fn it_should_validate() {
+        let (input, format) = (CString::new(ADDRESS).unwrap(), CString::new("fr").unwrap());
+        let status = unsafe {
+            ac_validate(
+                input.as_ptr(),
+                format.as_ptr(),
+                ptr::null_mut(),
+                0,
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, AcStatus::Ok);
+    }
+}
@@ -0,0 +1,194 @@
+//! Python bindings, behind the `python` feature, for the data pipelines (e.g.,
+//! pandas) converting or storing addresses in-process. The extension module is
+//! built with maturin, see `pyproject.toml`.
+//!
+//! The failures of the library raise `AddressError`, a `ValueError` whose
+//! `code` attribute is the stable error code (e.g., `E_POSTAL_FORMAT`).
+#![cfg(feature = "python")]
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use uuid::Uuid;
+
+use crate::application::service::{AddressService, Format, UnknownFormat};
+use crate::application::simple::to_json;
+use crate::domain::repositories::{AddressRepository, AddressRepositoryError, Tenant};
+use crate::domain::Country;
+use crate::infrastructure::{StorageUrl, UnsupportedStorage};
+use crate::{convert_str, validate_str, Error};
+
+create_exception!(address_converter, AddressError, PyValueError);
+
+/// Raises `error` as an `AddressError` with its code.
+fn raise(error: impl Into<Error>) -> PyErr {
+    let error = error.into();
+
+    Python::with_gil(|py| {
+        let raised = AddressError::new_err(error.to_string());
+        match raised.value(py).setattr("code", error.code()) {
+            Ok(()) => raised,
+            Err(e) => e,
+        }
+    })
+}
+
+/// Reads a format from its name or one of its aliases, like the CLI.
+fn format(name: &str) -> PyResult<Format> {
+    name.parse()
+        .map_err(|e: UnknownFormat| PyValueError::new_err(e.to_string()))
+}
+
+/// Reads the ID of an address, raising an `AddressError` with the
+/// `E_INVALID_ID` code when it isn't a UUID.
+fn id(id: &str) -> PyResult<Uuid> {
+    id.parse()
+        .map_err(|e: uuid::Error| raise(AddressRepositoryError::from(e)))
+}
+
+/// Converts the JSON `input` of `from_fmt` (e.g., `"french"`) to the JSON of
+/// `to_fmt` (e.g., `"iso20022"`).
+#[pyfunction]
+fn convert(input: &str, from_fmt: &str, to_fmt: &str) -> PyResult<String> {
+    convert_str(input, format(from_fmt)?, format(to_fmt)?).map_err(raise)
+}
+
+/// Checks the JSON `input` of `fmt` as it would be before being saved,
+/// raising an `AddressError` when it isn't valid.
+#[pyfunction]
+#[pyo3(signature = (input, fmt = "french"))]
+fn validate(input: &str, fmt: &str) -> PyResult<()> {
+    validate_str(input, format(fmt)?).map_err(raise)
+}
+
+/// The addresses of a storage, e.g., `Store("json:///var/lib/addresses")`,
+/// the addresses being kept in memory by default. The store is bound to the
+/// thread creating it.
+#[pyclass(unsendable)]
+struct Store {
+    service: AddressService<Box<dyn AddressRepository + Send>>,
+}
+
+#[pymethods]
+impl Store {
+    #[new]
+    #[pyo3(signature = (url = "memory://", tenant = None))]
+    fn new(url: &str, tenant: Option<&str>) -> PyResult<Self> {
+        let url: StorageUrl = url
+            .parse()
+            .map_err(|e: UnsupportedStorage| PyValueError::new_err(e.to_string()))?;
+        let tenant: Option<Tenant> = tenant
+            .map(str::parse)
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        let repository = url.open(tenant.as_ref()).map_err(raise)?;
+
+        Ok(Self {
+            service: AddressService::new(repository),
+        })
+    }
+
+    /// Saves the JSON `input` of `fmt`, returning the ID of the address.
+    #[pyo3(signature = (input, fmt = "french"))]
+    fn save(&self, input: &str, fmt: &str) -> PyResult<String> {
+        let id = self.service.save(input, format(fmt)?).map_err(raise)?;
+
+        Ok(id.to_string())
+    }
+
+    /// The JSON of the address `id` in `fmt`.
+    #[pyo3(signature = (id, fmt = "french"))]
+    fn fetch(&self, id: &str, fmt: &str) -> PyResult<String> {
+        let converted = self
+            .service
+            .fetch_format(self::id(id)?, format(fmt)?)
+            .map_err(raise)?;
+
+        to_json(converted).map_err(raise)
+    }
+
+    /// Replaces the address `id` with the JSON `input` of `fmt`.
+    #[pyo3(signature = (id, input, fmt = "french"))]
+    fn update(&self, id: &str, input: &str, fmt: &str) -> PyResult<()> {
+        self.service
+            .update(self::id(id)?, input, format(fmt)?)
+            .map_err(raise)
+    }
+
+    fn delete(&self, id: &str) -> PyResult<()> {
+        self.service.delete(self::id(id)?).map_err(raise)
+    }
+
+    /// The IDs of the stored addresses.
+    fn ids(&self) -> PyResult<Vec<String>> {
+        let ids = self.service.ids().map_err(raise)?;
+
+        Ok(ids.iter().map(ToString::to_string).collect())
+    }
+
+    /// The IDs of the stored addresses at `street` in `postcode`, whoever
+    /// their recipient is.
+    #[pyo3(signature = (street, postcode, country = "FRANCE"))]
+    fn lookup(&self, street: &str, postcode: &str, country: &str) -> PyResult<Vec<String>> {
+        let country: Country = country
+            .parse()
+            .map_err(|_| PyValueError::new_err(format!("Unknown country `{country}`")))?;
+        let addresses = self
+            .service
+            .lookup(street, postcode, country)
+            .map_err(raise)?;
+
+        Ok(addresses
+            .iter()
+            .map(|address| address.id().to_string())
+            .collect())
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "address_converter")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(convert, module)?)?;
+    module.add_function(wrap_pyfunction!(validate, module)?)?;
+    module.add_class::<Store>()?;
+    module.add("AddressError", module.py().get_type::<AddressError>())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+    use pyo3::types::{IntoPyDict, PyModule};
+
+    use super::python_module;
+
+    #[test]
+    fn it_should_convert_and_store_from_python() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "address_converter").unwrap();
+            python_module(&module).unwrap();
+            let locals = [("ac", module)].into_py_dict(py).unwrap();
+
+            let script = cr#"
+address = '{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L\'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}'
+assert '"town_name":"MIOS"' in ac.convert(address, "fr", "iso20022")
+try:
+    ac.validate(address.replace("33380 MIOS", "MIOS"))
+    assert False
+except ac.AddressError as e:
+    assert e.code == "E_POSTAL_FORMAT"
+
+store = ac.Store()
+id = store.save(address)
+assert store.ids() == [id]
+assert store.lookup("25 rue de l'Eglise", "33380") == [id]
+store.delete(id)
+assert store.ids() == []
+"#;
+            py.run(script, None, Some(&locals)).unwrap();
+        });
+    }
+}
@@ -1,14 +1,23 @@
-use address_converter::application::service::AddressService;
-use address_converter::infrastructure::JsonAddressRepository;
-use address_converter::presentation::cli::commands::{run_command, Cli};
+use address_converter::application::service::{
+    AddressService, AddressServiceError, BoxedAddressService, Either, Format,
+};
+use address_converter::domain::repositories::{AddressRepository, AddressRepositoryError, Tenant};
+use address_converter::domain::{DuplicateScope, Recipient};
+use address_converter::infrastructure::{
+    ChangeLogRepository, Durability, JsonAddressRepository, StorageLayout, StorageUrl,
+};
+use address_converter::presentation::cli::commands::{
+    run_command, write_service_error, Cli, OutputMode,
+};
 use clap::Parser;
 use std::fs;
 use std::path::Path;
+use std::process::{Command, Output, Stdio};
 use tempfile::TempDir;
 
-fn service(temp_dir: &TempDir) -> AddressService {
+fn service(temp_dir: &TempDir) -> BoxedAddressService {
     let repo = JsonAddressRepository::new(temp_dir.path());
-    AddressService::new(Box::new(repo))
+    BoxedAddressService::new(Box::new(repo))
 }
 
 /// Helper function that will retrieve the ID from the file stored in the
@@ -16,8 +25,11 @@ fn service(temp_dir: &TempDir) -> AddressService {
 /// correct name and naming consistency with the overall process.
 /// Will panic if the file information can't be extracted.
 fn get_file_id(path: &Path) -> String {
-    let mut files = fs::read_dir(path).unwrap();
-    let first_file = files.next().unwrap().unwrap().path();
+    let first_file = fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .unwrap();
     let filename_id = first_file
         .file_stem()
         .unwrap()
@@ -28,6 +40,34 @@ fn get_file_id(path: &Path) -> String {
     filename_id
 }
 
+/// Runs the CLI binary on the JSON storage in `storage`, as a script would,
+/// without a terminal to ask on.
+fn run_cli(storage: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(args)
+        .env("STORAGE_DIR", storage)
+        .env_remove("MONGODB_URI")
+        .env_remove("CHANGE_LOG_DIR")
+        .env_remove("STORAGE_LAYOUT")
+        .env_remove("AC_CONFIG")
+        .env_remove("AC_PROFILE")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+/// What the CLI binary prints on stdout, see [`run_cli`]. Panics when it fails.
+fn cli_stdout(storage: &Path, args: &[&str]) -> String {
+    let output = run_cli(storage, args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
 #[test]
 fn cli_save_french() {
     let temp_dir = TempDir::new().unwrap();
@@ -43,7 +83,10 @@ fn cli_save_french() {
     ]);
     run_command(cli, &service).unwrap();
 
-    let files = fs::read_dir(temp_dir.path()).unwrap().count();
+    let files = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().is_file())
+        .count();
     assert_eq!(files, 1);
 }
 
@@ -110,12 +153,51 @@ fn cli_update() {
     run_command(update_cli, &service).unwrap();
 
     // Verify update
-    let fetch_result = service.fetch(&file_id).unwrap();
+    let fetch_result = service.fetch(file_id.parse().unwrap()).unwrap();
     let street = fetch_result.street.unwrap();
     assert_eq!(street.name, "AVENUE DES CHAMPS");
     assert_eq!(street.number.unwrap(), "10");
 }
 
+#[test]
+fn cli_update_rejects_stale_version() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let id = service
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+    let update = |street: &str, version: &str| {
+        let address = format!(
+            r#"{{"name": "Monsieur Jean DELHOURME", "street": "{street}", "postal": "33380 MIOS", "country": "FRANCE"}}"#
+        );
+        let update_cli = Cli::parse_from([
+            "address_converter",
+            "update",
+            &id.to_string(),
+            "--address",
+            &address,
+            "--from-format",
+            "french",
+            "--if-version",
+            version,
+        ]);
+        run_command(update_cli, &service)
+    };
+
+    update("10 AVENUE DES CHAMPS", "1").unwrap();
+    assert_eq!(service.fetch(id).unwrap().version(), 2);
+
+    // An update based on the first version is refused
+    let result = update("12 AVENUE DES CHAMPS", "1");
+    assert!(matches!(result, Err(e) if e.contains("Version conflict")));
+    let stored = service.fetch(id).unwrap();
+    assert_eq!(stored.version(), 2);
+    assert_eq!(stored.street.unwrap().number.as_deref(), Some("10"));
+}
+
 #[test]
 fn cli_fetch() {
     let temp_dir = TempDir::new().unwrap();
@@ -147,6 +229,251 @@ fn cli_fetch() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn cli_fetch_multiple_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let id = service(&temp_dir)
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap()
+        .to_string();
+
+    let stdout = cli_stdout(
+        temp_dir.path(),
+        &["fetch", &id, "--format", "french,iso20022"],
+    );
+    let formats: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let names: Vec<_> = formats.as_object().unwrap().keys().collect();
+    assert_eq!(names, ["french", "iso20022"]);
+    let french = &formats["french"];
+    assert_eq!(french["name"], "Monsieur Jean DELHOURME");
+    assert_eq!(french["street"], "25 RUE DE L'EGLISE");
+    assert_eq!(french["postal"], "33380 MIOS");
+    assert_eq!(french["country"], "FRANCE");
+    let iso = &formats["iso20022"];
+    assert_eq!(iso["name"], "Monsieur Jean DELHOURME");
+    assert_eq!(iso["postal_address"]["street_name"], "RUE DE L'EGLISE");
+    assert_eq!(iso["postal_address"]["building_number"], "25");
+    assert_eq!(iso["postal_address"]["postcode"], "33380");
+    assert_eq!(iso["postal_address"]["town_name"], "MIOS");
+    assert_eq!(iso["postal_address"]["country"], "FR");
+
+    let unknown = Cli::try_parse_from([
+        "address_converter",
+        "fetch",
+        &id,
+        "--format",
+        "french,klingon",
+    ]);
+    assert!(unknown.is_err());
+}
+
+#[test]
+fn cli_fetch_several() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let first = service
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap()
+        .to_string();
+    let second = service
+        .save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap()
+        .to_string();
+
+    let fetch = |args: &[&str]| {
+        let mut cli = vec!["address_converter", "fetch", "--format", "french"];
+        cli.extend(args);
+        run_command(Cli::parse_from(cli), &service)
+    };
+
+    fetch(&[&first, &second, "--output", "json"]).unwrap();
+    // The full ID is an unambiguous prefix
+    fetch(&["--prefix", &second[..8], "--prefix", &first]).unwrap();
+
+    let unknown = uuid::Uuid::new_v4().to_string();
+    let result = fetch(&[&first, &unknown]);
+    assert!(matches!(result, Err(e) if e.contains("not found")));
+    assert!(Cli::try_parse_from(["address_converter", "fetch", "--format", "french"]).is_err());
+}
+
+#[test]
+fn cli_fetch_as_of() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let before_save = chrono::Utc::now().to_rfc3339();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let id = service
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let letter_sent = chrono::Utc::now();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    for street in ["10 AVENUE DES CHAMPS", "12 AVENUE DES CHAMPS"] {
+        service
+            .update(
+                id,
+                &format!(r#"{{"name": "Monsieur Jean DELHOURME", "street": "{street}", "postal": "33380 MIOS", "country": "FRANCE"}}"#),
+                Format::French,
+            )
+            .unwrap();
+    }
+
+    // The versions replaced by the updates are kept in the history
+    let on_file = service.fetch_as_of(id, letter_sent).unwrap();
+    assert_eq!(on_file.version(), 1);
+    assert_eq!(on_file.street.unwrap().name, "RUE DE L'EGLISE");
+    let versions = JsonAddressRepository::new(temp_dir.path())
+        .fetch_history(&id.to_string())
+        .unwrap();
+    assert_eq!(
+        versions.iter().map(|v| v.version()).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+
+    let fetch = |as_of: &str| {
+        run_command(
+            Cli::parse_from([
+                "address_converter",
+                "fetch",
+                &id.to_string(),
+                "--format",
+                "french",
+                "--as-of",
+                as_of,
+            ]),
+            &service,
+        )
+    };
+    fetch(&letter_sent.to_rfc3339()).unwrap();
+    assert!(matches!(fetch(&before_save), Err(e) if e.contains("not found")));
+    assert!(matches!(fetch("2024-06-01"), Err(e) if e.contains("RFC 3339")));
+
+    // The history goes with the address
+    service.delete(id).unwrap();
+    assert!(!temp_dir
+        .path()
+        .join(".history")
+        .join(format!("{id}.jsonl"))
+        .exists());
+}
+
+#[test]
+fn cli_aliases() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let run = |args: &[&str]| {
+        let mut cli = vec!["address_converter"];
+        cli.extend(args);
+        run_command(Cli::parse_from(cli), &service)
+    };
+    let save_with = |name: &str, alias: &str, options: &[&str]| {
+        let address = format!(
+            r#"{{"name": "{name}", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}}"#
+        );
+        let mut args = vec![
+            "save",
+            "--address",
+            &address,
+            "--from-format",
+            "french",
+            "--alias",
+            alias,
+        ];
+        args.extend(options);
+        run(&args)
+    };
+    let save = |name: &str, alias: &str| save_with(name, alias, &[]);
+
+    save("Monsieur Jean DELHOURME", "hq-bordeaux").unwrap();
+    let id = service.ids().unwrap()[0];
+    let stdout = cli_stdout(
+        temp_dir.path(),
+        &["fetch", "hq-bordeaux", "--format", "iso20022"],
+    );
+    let iso: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(iso["name"], "Monsieur Jean DELHOURME");
+    assert_eq!(iso["postal_address"]["building_number"], "25");
+    // A taken alias saves nothing
+    let result = save("Madame Isabelle RICHARD", "hq-bordeaux");
+    assert!(matches!(result, Err(e) if e.contains("already names")));
+    assert_eq!(service.ids().unwrap().len(), 1);
+    for options in [&["--upsert"][..], &["--idempotency-key", "k-1"]] {
+        let result = save_with("Madame Isabelle RICHARD", "hq-bordeaux", options);
+        assert!(matches!(result, Err(e) if e.contains("already names")));
+        assert_eq!(service.ids().unwrap().len(), 1);
+    }
+    // So that the save is retried with another alias
+    save_with(
+        "Madame Isabelle RICHARD",
+        "auterive",
+        &["--idempotency-key", "k-1"],
+    )
+    .unwrap();
+    let richard = service.resolve_alias(&"auterive".parse().unwrap()).unwrap();
+    assert_eq!(service.fetch(richard).unwrap().version(), 1);
+
+    run(&["alias", "add", "hq-bordeaux", "siege"]).unwrap();
+    let richard = richard.to_string();
+    assert_eq!(
+        cli_stdout(temp_dir.path(), &["alias", "list"]),
+        format!("auterive\t{richard}\nsiege\t{id}\n")
+    );
+    let stdout = cli_stdout(temp_dir.path(), &["inspect", "siege"]);
+    assert!(stdout.contains(&format!("├── id: {id}\n")), "{stdout}");
+    assert!(stdout.contains("├── alias: siege\n"), "{stdout}");
+    assert!(run(&["fetch", "hq-bordeaux", "--format", "french"]).is_err());
+    run(&["alias", "remove", "siege"]).unwrap();
+    assert!(run(&["alias", "remove", "siege"]).is_err());
+    // Hexadecimal aliases would be read as ID prefixes
+    assert!(Cli::try_parse_from(["address_converter", "alias", "add", "siege", "cafe"]).is_err());
+}
+
+#[test]
+fn cli_id_only_and_quiet() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let save = [
+        "save",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+        "--id-only",
+    ];
+
+    let cli = Cli::parse_from(["address_converter", "--quiet"].into_iter().chain(save));
+    run_command(cli, &service).unwrap();
+    let id = service.ids().unwrap()[0].to_string();
+    let cli = Cli::parse_from([
+        "address_converter",
+        "--quiet",
+        "fetch",
+        &id,
+        "--format",
+        "french",
+    ]);
+    run_command(cli, &service).unwrap();
+
+    // The ID alone isn't a JSON output
+    let args = ["address_converter"]
+        .into_iter()
+        .chain(save)
+        .chain(["--output", "json"]);
+    assert!(Cli::try_parse_from(args).is_err());
+}
+
 #[test]
 fn cli_delete() {
     let temp_dir = TempDir::new().unwrap();
@@ -175,3 +502,1624 @@ fn cli_delete() {
     let fetch_result = service.repository.fetch(&file_id);
     assert!(fetch_result.is_err());
 }
+
+#[test]
+fn cli_save_upsert_french() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let input = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+    let update_input = r#"{"name": "Monsieur Jean DELHOURME", "internal_delivery": "Appartement 2", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    for address in [input, update_input] {
+        let cli = Cli::parse_from([
+            "address_converter",
+            "save",
+            "--address",
+            address,
+            "--from-format",
+            "french",
+            "--upsert",
+        ]);
+        run_command(cli, &service).unwrap();
+    }
+
+    // The duplicate updated the stored address
+    let file_id = get_file_id(temp_dir.path());
+    let files = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().is_file())
+        .count();
+    assert_eq!(files, 1);
+
+    let fetched = service.fetch(file_id.parse().unwrap()).unwrap();
+    let delivery_point = fetched.delivery_point.unwrap();
+    assert_eq!(delivery_point.internal.unwrap(), "Appartement 2");
+}
+
+#[test]
+fn cli_validate() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let validate = |postal: &str| {
+        let address = format!(
+            r#"{{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "{postal}", "country": "FRANCE"}}"#
+        );
+        run_command(
+            Cli::parse_from([
+                "address_converter",
+                "validate",
+                "--address",
+                &address,
+                "--from-format",
+                "french",
+                "--output",
+                "json",
+            ]),
+            &service,
+        )
+    };
+
+    validate("33380 MIOS").unwrap();
+    assert!(validate("MIOS").is_err());
+    // Nothing is saved
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn cli_validate_against_referential() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let referential_dir = TempDir::new().unwrap();
+    let referential = referential_dir.path().join("voies.csv");
+    fs::write(
+        &referential,
+        "code_postal;nom_commune;nom_voie\n33380;MIOS;RUE DE L EGLISE\n33380;MIOS;AVENUE DE LA GARE\n",
+    )
+    .unwrap();
+    let validate = |street: &str, referential: &str| {
+        let address = format!(
+            r#"{{"name": "Monsieur Jean DELHOURME", "street": "{street}", "postal": "33380 MIOS", "country": "FRANCE"}}"#
+        );
+        run_command(
+            Cli::parse_from([
+                "address_converter",
+                "validate",
+                "--address",
+                &address,
+                "--from-format",
+                "french",
+                "--against-referential",
+                referential,
+            ]),
+            &service,
+        )
+    };
+
+    let path = referential.to_str().unwrap();
+    validate("25 RUE DE L'EGLISE", path).unwrap();
+    validate("3 AV DE LA GARE", path).unwrap();
+    let result = validate("25 RUE DES LILAS", path);
+    assert!(matches!(result, Err(e) if e.contains("not found in the street referential")));
+
+    let result = validate("25 RUE DE L'EGLISE", "missing.csv");
+    assert!(matches!(result, Err(e) if e.contains("Can't read missing.csv")));
+}
+
+#[test]
+fn cli_normalize() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let normalize = |address: &str, from_format: &str| {
+        run_command(
+            Cli::parse_from([
+                "address_converter",
+                "normalize",
+                "--address",
+                address,
+                "--from-format",
+                from_format,
+            ]),
+            &service,
+        )
+    };
+
+    normalize(
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 Rue de l'Église", "postal": "33380 Mios", "country": "France"}"#,
+        "french",
+    )
+    .unwrap();
+    normalize(
+        r#"{"name": "Monsieur Jean DELHOURME", "postal_address": {"street_name": "Rue de l'Église", "postcode": "33380", "town_name": "Mios", "country": "FR"}}"#,
+        "iso20022",
+    )
+    .unwrap();
+    // Nothing is saved.
+    assert_eq!(service.repository.fetch_all().unwrap().len(), 0);
+
+    let result = normalize(r#"{"name": "Monsieur Jean DELHOURME"}"#, "french");
+    assert!(result.is_err());
+}
+
+#[test]
+fn cli_format_aliases() {
+    let temp_dir = TempDir::new().unwrap();
+    let convert = |from_format: &str, to_format: &str| {
+        Cli::try_parse_from([
+            "address_converter",
+            "convert",
+            "--address",
+            "Monsieur Jean DELHOURME\n25 RUE DE L'EGLISE\n33380 MIOS",
+            "--from-format",
+            from_format,
+            "--to-format",
+            to_format,
+        ])
+    };
+
+    for (from_format, to_format) in [("lines", "iso"), ("French-Lines", "ISO20022")] {
+        let stdout = cli_stdout(
+            temp_dir.path(),
+            &[
+                "convert",
+                "--address",
+                "Monsieur Jean DELHOURME\n25 RUE DE L'EGLISE\n33380 MIOS",
+                "--from-format",
+                from_format,
+                "--to-format",
+                to_format,
+            ],
+        );
+        let iso: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(iso["name"], "Monsieur Jean DELHOURME");
+        assert_eq!(iso["postal_address"]["street_name"], "RUE DE L'EGLISE");
+        assert_eq!(iso["postal_address"]["building_number"], "25");
+        assert_eq!(iso["postal_address"]["town_name"], "MIOS");
+    }
+    let error = convert("french-lines", "nf").err().unwrap().to_string();
+    assert!(
+        error.contains("[possible values: french, iso20022, portuguese, brazilian, canonical]"),
+        "{error}"
+    );
+    let error = convert("json", "fr").err().unwrap().to_string();
+    assert!(error.contains("french-lines"), "{error}");
+}
+
+#[test]
+fn cli_convert_iso_tags() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+        "--json-keys",
+        "iso-tags",
+    ]);
+    let result = run_command(cli, &service);
+    assert!(result.is_ok());
+
+    // Nothing is saved
+    let files = fs::read_dir(temp_dir.path()).unwrap().count();
+    assert_eq!(files, 0);
+
+    let invalid_cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+        "--json-keys",
+        "kebab",
+    ]);
+    let result = run_command(invalid_cli, &service);
+    assert!(matches!(result, Err(e) if e.contains("Invalid json keys")));
+}
+
+#[test]
+fn cli_convert_tagged() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        r#"{"kind": "business", "name": "DURAND", "street": "56 RUE EMILE ZOLA", "postal": "34092 MONTPELLIER", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+        "--tagged",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+
+    let invalid_cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        r#"{"kind": "company", "name": "DURAND", "street": "56 RUE EMILE ZOLA", "postal": "34092 MONTPELLIER", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+    ]);
+    assert!(run_command(invalid_cli, &service).is_err());
+}
+
+#[test]
+fn cli_french_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let lines = "Société DUPONT\nMademoiselle Lucie MARTIN\n56 RUE EMILE ZOLA\nBP 90432\n34092 MONTPELLIER CEDEX 5";
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "save",
+        "--address",
+        lines,
+        "--from-format",
+        "french-lines",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        lines,
+        "--from-format",
+        "french-lines",
+        "--to-format",
+        "french",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+
+    let saved = service.repository.fetch_all().unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(
+        saved[0].recipient.denomination().as_deref(),
+        Some("Mademoiselle Lucie MARTIN")
+    );
+    let delivery_point = saved[0].delivery_point.as_ref().unwrap();
+    assert_eq!(delivery_point.postbox.as_deref(), Some("BP 90432"));
+
+    let missing_postal = Cli::parse_from([
+        "address_converter",
+        "save",
+        "--address",
+        "Société DUPONT",
+        "--from-format",
+        "french-lines",
+    ]);
+    assert!(run_command(missing_postal, &service).is_err());
+}
+
+#[test]
+fn cli_convert_explain() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "distribution_info": "CAUDOS", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+        "--explain",
+        "--output",
+        "json",
+    ]);
+    let result = run_command(cli, &service);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn cli_party_xml() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let address = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        address,
+        "--to-format",
+        "iso20022",
+        "--party-xml",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        address,
+        "--to-format",
+        "french",
+        "--party-xml",
+    ]);
+    assert!(run_command(cli, &service).is_err());
+
+    let Either::Iso20022(iso) = service.convert(address, Format::Iso20022).unwrap() else {
+        panic!("expected an ISO 20022 address");
+    };
+    let message_path = temp_dir.path().join("pain.001.xml");
+    fs::write(
+        &message_path,
+        format!(
+            "<Document><CstmrCdtTrfInitn><PmtInf>{}</PmtInf></CstmrCdtTrfInitn></Document>",
+            iso.to_party_xml().replace("Pty>", "Dbtr>")
+        ),
+    )
+    .unwrap();
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "from-xml",
+        "--file",
+        message_path.to_str().unwrap(),
+        "--to-format",
+        "french",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+    let french = service
+        .convert_xml(&fs::read_to_string(&message_path).unwrap(), Format::French)
+        .unwrap();
+    assert_eq!(
+        french,
+        vec![Either::French(serde_json::from_str(address).unwrap())]
+    );
+}
+
+#[test]
+fn cli_omit_nulls() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "--omit-nulls",
+        "convert",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+
+    let conflicting = Cli::try_parse_from([
+        "address_converter",
+        "stats",
+        "--omit-nulls",
+        "--include-nulls",
+    ]);
+    assert!(conflicting.is_err());
+}
+
+#[test]
+fn tenants_are_isolated() {
+    let temp_dir = TempDir::new().unwrap();
+    let billing: Tenant = "billing".parse().unwrap();
+    let shipping: Tenant = "shipping".parse().unwrap();
+    let services = [
+        AddressService::new(JsonAddressRepository::for_tenant(temp_dir.path(), &billing)),
+        AddressService::new(JsonAddressRepository::for_tenant(
+            temp_dir.path(),
+            &shipping,
+        )),
+    ];
+    let address = r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#;
+
+    // The same address isn't a duplicate in another tenant.
+    let ids: Vec<_> = services
+        .iter()
+        .map(|service| service.save(address, Format::French).unwrap())
+        .collect();
+    assert!(services[0].fetch(ids[1]).is_err());
+    assert_eq!(services[1].ids().unwrap(), vec![ids[1]]);
+    assert!(service(&temp_dir).ids().unwrap().is_empty());
+    assert!(temp_dir.path().join("billing").is_dir());
+
+    assert!("../billing".parse::<Tenant>().is_err());
+    assert!(Cli::try_parse_from(["address_converter", "--tenant", "a/b", "stats"]).is_err());
+}
+
+#[test]
+fn sharded_layout_migration() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let id = service
+        .save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+    let flat_path = temp_dir.path().join(format!("{id}.json"));
+    assert!(flat_path.is_file());
+
+    let hex = id.simple().to_string();
+    let sharded_path = temp_dir
+        .path()
+        .join(&hex[..2])
+        .join(&hex[2..4])
+        .join(format!("{id}.json"));
+    let sharded = JsonAddressRepository::new(temp_dir.path())
+        .with_layout(StorageLayout::Sharded)
+        .unwrap();
+    let service = AddressService::new(sharded);
+    assert!(!flat_path.exists());
+    assert!(sharded_path.is_file());
+    assert_eq!(service.ids().unwrap(), vec![id]);
+    assert!(service.fetch(id).is_ok());
+
+    let other_id = service
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+    assert_eq!(service.list(0).unwrap().len(), 2);
+
+    // Back to the flat layout, the shards are removed.
+    JsonAddressRepository::new(temp_dir.path())
+        .with_layout(StorageLayout::Flat)
+        .unwrap();
+    assert!(flat_path.is_file());
+    assert!(temp_dir.path().join(format!("{other_id}.json")).is_file());
+    assert!(!temp_dir.path().join(&hex[..2]).exists());
+    assert_eq!("Sharded".parse(), Ok(StorageLayout::Sharded));
+}
+
+#[test]
+fn json_storage_durability() {
+    let temp_dir = TempDir::new().unwrap();
+    let address = r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#;
+
+    for durability in [Durability::Fast, Durability::Atomic, Durability::Synced] {
+        let dir = temp_dir.path().join(durability.to_string());
+        let repository = JsonAddressRepository::new(&dir).with_durability(durability);
+        let service = AddressService::new(repository);
+        let id = service.save(address, Format::French).unwrap();
+        service
+            .update(
+                id,
+                &address.replace("LE VILLAGE", "LE BOURG"),
+                Format::French,
+            )
+            .unwrap();
+
+        // Only the address file is left, the temporary file being renamed.
+        // The replaced version is in the history folder.
+        let files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name != ".history")
+            .collect();
+        assert_eq!(files, vec![format!("{id}.json")]);
+        let stored = fs::read_to_string(dir.join(&files[0])).unwrap();
+        assert!(stored.contains("LE BOURG"));
+    }
+    assert_eq!("synced".parse(), Ok(Durability::Synced));
+}
+
+#[test]
+fn flatmates_are_not_duplicates() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let building = |name: &str, unit: &str| {
+        serde_json::json!({"name": name, "internal_delivery": unit, "street": "56 RUE EMILE ZOLA", "postal": "34092 MONTPELLIER", "country": "FRANCE"})
+            .to_string()
+    };
+
+    // Flatmates, and the neighbours of another unit of the building.
+    let first = service
+        .save(
+            &building("Madame Lucie MARTIN", "Appartement 12"),
+            Format::French,
+        )
+        .unwrap();
+    for (name, unit) in [
+        ("Monsieur Paul MARTIN", "Appartement 12"),
+        ("Monsieur Jean DELHOURME", "Appartement 3"),
+    ] {
+        assert!(service.save(&building(name, unit), Format::French).is_ok());
+    }
+    let same_person = service.save(
+        &building("Mme Lucie Martin", "Appartement 12"),
+        Format::French,
+    );
+    assert!(
+        matches!(same_person, Err(AddressServiceError::PersistenceError(AddressRepositoryError::AlreadyExists(id))) if id == first.to_string())
+    );
+
+    // Compared by location, the whole building is a single address.
+    let location_dir = TempDir::new().unwrap();
+    let repository = JsonAddressRepository::new(location_dir.path())
+        .with_duplicate_scope(DuplicateScope::Location);
+    let service = AddressService::new(repository);
+    service
+        .save(
+            &building("Madame Lucie MARTIN", "Appartement 12"),
+            Format::French,
+        )
+        .unwrap();
+    assert!(service
+        .save(
+            &building("Monsieur Jean DELHOURME", "Appartement 3"),
+            Format::French
+        )
+        .is_err());
+}
+
+#[test]
+fn compact_json_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let mut ids = Vec::new();
+    for (name, street, postal) in [
+        ("Madame Isabelle RICHARD", "LE VILLAGE", "82500 AUTERIVE"),
+        (
+            "Monsieur Jean DELHOURME",
+            "25 RUE DE L'EGLISE",
+            "33380 MIOS",
+        ),
+        (
+            "Madame Lucie MARTIN",
+            "56 RUE EMILE ZOLA",
+            "34092 MONTPELLIER",
+        ),
+    ] {
+        let address = serde_json::json!({"name": name, "street": street, "postal": postal, "country": "FRANCE"});
+        ids.push(service.save(&address.to_string(), Format::French).unwrap());
+    }
+    let flat_path = |id: &uuid::Uuid| temp_dir.path().join(format!("{id}.json"));
+    let sharded_path = |id: &uuid::Uuid| {
+        let hex = id.simple().to_string();
+        temp_dir
+            .path()
+            .join(&hex[..2])
+            .join(&hex[2..4])
+            .join(format!("{id}.json"))
+    };
+
+    // A hand edited file, a copy and a file left by an interrupted migration.
+    let stored: serde_json::Value =
+        serde_json::from_slice(&fs::read(flat_path(&ids[0])).unwrap()).unwrap();
+    fs::write(
+        flat_path(&ids[0]),
+        serde_json::to_string_pretty(&stored).unwrap(),
+    )
+    .unwrap();
+    fs::create_dir_all(sharded_path(&ids[1]).parent().unwrap()).unwrap();
+    fs::copy(flat_path(&ids[1]), sharded_path(&ids[1])).unwrap();
+    fs::create_dir_all(sharded_path(&ids[2]).parent().unwrap()).unwrap();
+    fs::rename(flat_path(&ids[2]), sharded_path(&ids[2])).unwrap();
+    let unreadable = temp_dir
+        .path()
+        .join(format!("{}.json", uuid::Uuid::new_v4()));
+    fs::write(&unreadable, "{").unwrap();
+
+    let report = service.compact().unwrap();
+    assert_eq!(report.rewritten, 1);
+    assert_eq!(report.orphans_restored, 1);
+    assert_eq!(report.orphans_removed, 1);
+    assert!(report.dirs_removed >= 2);
+    assert_eq!(report.unreadable, vec![unreadable.display().to_string()]);
+    assert!(report.reclaimed_bytes > 0);
+
+    assert!(unreadable.is_file());
+    for id in &ids {
+        assert!(flat_path(id).is_file());
+        assert!(!sharded_path(id).exists());
+        assert!(service.fetch(*id).is_ok());
+    }
+
+    // Nothing left to compact.
+    fs::remove_file(unreadable).unwrap();
+    assert_eq!(service.compact().unwrap(), Default::default());
+}
+
+#[test]
+fn cli_reformat_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let input_dir = TempDir::new().unwrap();
+    fs::write(
+        input_dir.path().join("clients.ndjson"),
+        concat!(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            "\n",
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+        ),
+    )
+    .unwrap();
+    fs::write(
+        input_dir.path().join("suppliers.csv"),
+        "name,street,postal,country\nSociété DUPONT,56 RUE EMILE ZOLA,34092 MONTPELLIER CEDEX 5,FRANCE\n",
+    )
+    .unwrap();
+    fs::write(input_dir.path().join("notes.txt"), "left out").unwrap();
+    let out = input_dir.path().join("converted");
+
+    let reformat = |input: &Path| {
+        run_command(
+            Cli::parse_from([
+                "address_converter",
+                "reformat",
+                input.to_str().unwrap(),
+                "--from",
+                "french",
+                "--to",
+                "iso20022",
+                "--out",
+                out.to_str().unwrap(),
+            ]),
+            &service,
+        )
+    };
+    reformat(input_dir.path()).unwrap();
+
+    let clients = fs::read_to_string(out.join("clients.ndjson")).unwrap();
+    assert_eq!(clients.lines().count(), 2);
+    let first: serde_json::Value = serde_json::from_str(clients.lines().next().unwrap()).unwrap();
+    assert_eq!(first["postal_address"]["town_name"], "MIOS");
+    let suppliers = fs::read_to_string(out.join("suppliers.ndjson")).unwrap();
+    assert!(suppliers.contains("DUPONT"));
+    assert!(!out.join("notes.ndjson").exists());
+    // Nothing is saved
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+    // The invalid records are left out, and make the command fail
+    let invalid = input_dir.path().join("invalid.json");
+    fs::write(
+        &invalid,
+        r#"[{"name": "Madame Isabelle RICHARD", "postal": "AUTERIVE", "country": "FRANCE"}]"#,
+    )
+    .unwrap();
+    let result = reformat(&invalid);
+    assert!(matches!(result, Err(e) if e.contains("1 records rejected")));
+    assert_eq!(fs::read_to_string(out.join("invalid.ndjson")).unwrap(), "");
+}
+
+#[test]
+fn cli_migrate_iso() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let input_dir = TempDir::new().unwrap();
+    let input = input_dir.path().join("parties.json");
+    fs::write(
+        &input,
+        r#"[
+            {"Nm": "Monsieur Jean DELHOURME", "PstlAdr": {"AdrLine": ["25 RUE DE L'EGLISE", "33380 MIOS"], "Ctry": "FR"}},
+            {"Nm": "Madame Isabelle RICHARD", "PstlAdr": {"AdrLine": ["LE VILLAGE", "00500 AUTERIVE"]}}
+        ]"#,
+    )
+    .unwrap();
+    let out = input_dir.path().join("structured.ndjson");
+    let report = input_dir.path().join("report.ndjson");
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "migrate-iso",
+        input.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+        "--report",
+        report.to_str().unwrap(),
+        "--json-keys",
+        "iso-tags",
+    ]);
+    run_command(cli, &service).unwrap();
+
+    let addresses = fs::read_to_string(&out).unwrap();
+    let first: serde_json::Value = serde_json::from_str(addresses.lines().next().unwrap()).unwrap();
+    assert_eq!(first["PstlAdr"]["StrtNm"], "RUE DE L'EGLISE");
+    assert_eq!(first["PstlAdr"]["BldgNb"], "25");
+    let reports: Vec<serde_json::Value> = fs::read_to_string(&report)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["confidence"], 100);
+    assert_eq!(reports[1]["record"], 1);
+    assert_eq!(reports[1]["needs_review"], true);
+    assert_eq!(reports[1]["lines"][1]["role"], "street");
+    assert_eq!(reports[1]["issues"][0]["issue"], "guessed_line");
+    // Nothing is saved
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn cli_archive_and_mark_invalid() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let id = service
+        .save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap()
+        .to_string();
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "mark-invalid",
+        &id,
+        "--reason",
+        "returned mail",
+    ]);
+    assert!(run_command(cli, &service).is_ok());
+    assert_eq!(service.list(0).unwrap().len(), 1);
+
+    let cli = Cli::parse_from(["address_converter", "archive", &id]);
+    assert!(run_command(cli, &service).is_ok());
+    assert!(service.list(0).unwrap().is_empty());
+
+    let cli = Cli::parse_from(["address_converter", "list", "--archived"]);
+    assert!(run_command(cli, &service).is_ok());
+    let cli = Cli::parse_from(["address_converter", "fetch", &id, "--format", "iso20022"]);
+    assert!(run_command(cli, &service).is_ok());
+
+    let cli = Cli::parse_from(["address_converter", "archive", &id]);
+    assert!(matches!(run_command(cli, &service), Err(e) if e.contains("it is archived")));
+}
+
+#[test]
+fn cli_mailing_list() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let run = |args: &[&str]| {
+        let mut cli = vec!["address_converter"];
+        cli.extend(args);
+        run_command(Cli::parse_from(cli), &service)
+    };
+    for name in ["Monsieur Jean DELHOURME", "Madame Marie DELHOURME"] {
+        let address = format!(
+            r#"{{"name": "{name}", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}}"#
+        );
+        run(&["save", "--address", &address, "--from-format", "french"]).unwrap();
+    }
+    run(&[
+        "save",
+        "--address",
+        r#"{"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ])
+    .unwrap();
+
+    let output_dir = TempDir::new().unwrap();
+    let output = output_dir.path().join("mailing.csv");
+    run(&[
+        "mailing-list",
+        "--postcode",
+        "33380",
+        "--output",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+
+    // A single letter for the household of the postcode.
+    let csv = fs::read_to_string(&output).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].ends_with(",25 RUE DE L'EGLISE,33380 MIOS,,,,"));
+}
+
+#[test]
+fn cli_set_recipient() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let run = |args: &[&str]| {
+        let mut cli = vec!["address_converter"];
+        cli.extend(args);
+        run_command(Cli::parse_from(cli), &service)
+    };
+    run(&[
+        "save",
+        "--address",
+        r#"{"business_name": "Société DUPONT", "recipient": "Mademoiselle Lucie MARTIN", "street": "56 RUE EMILE ZOLA", "postal": "34092 MONTPELLIER CEDEX 5", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ])
+    .unwrap();
+    let id = get_file_id(temp_dir.path());
+    let recipient = || service.fetch(id.parse().unwrap()).unwrap().recipient;
+
+    run(&["set-recipient", &id, "--service", "Service achat"]).unwrap();
+    assert_eq!(
+        recipient().denomination().as_deref(),
+        Some("Mademoiselle Lucie MARTIN Service achat")
+    );
+    run(&[
+        "set-recipient",
+        &id,
+        "--contact",
+        "Monsieur Paul MARTIN",
+        "--clear",
+    ])
+    .unwrap();
+    assert!(matches!(
+        recipient(),
+        Recipient::Business { company_name, contact_person: Some(contact), service: None, .. }
+            if company_name == "Société DUPONT" && contact == "Monsieur Paul MARTIN"
+    ));
+
+    let result = run(&["set-recipient", &id]);
+    assert!(matches!(result, Err(e) if e.contains("Nothing to set")));
+    assert!(Cli::try_parse_from([
+        "address_converter",
+        "set-recipient",
+        &id,
+        "--name",
+        "Monsieur Paul MARTIN",
+        "--company",
+        "DURAND SA",
+    ])
+    .is_err());
+
+    run(&["set-recipient", &id, "--name", "Monsieur Paul MARTIN"]).unwrap();
+    let result = run(&["set-recipient", &id, "--contact", "Madame Anne PETIT"]);
+    assert!(matches!(result, Err(e) if e.contains("give --company")));
+}
+
+#[test]
+fn cli_annotate() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let run = |args: &[&str]| {
+        let mut cli = vec!["address_converter"];
+        cli.extend(args);
+        run_command(Cli::parse_from(cli), &service)
+    };
+    run(&[
+        "save",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ])
+    .unwrap();
+    let id = get_file_id(temp_dir.path());
+
+    run(&[
+        "annotate",
+        &id,
+        "--note",
+        "Ring twice",
+        "--set",
+        "crm=C-1042",
+        "--set",
+        "floor=2",
+    ])
+    .unwrap();
+    run(&["annotate", &id, "--unset", "floor"]).unwrap();
+    let result = run(&["annotate", &id, "--set", "floor"]);
+    assert!(matches!(result, Err(e) if e.contains("must be KEY=VALUE")));
+
+    let address = service.fetch(id.parse().unwrap()).unwrap();
+    assert_eq!(address.notes(), Some("Ring twice"));
+    assert_eq!(
+        address.metadata().iter().collect::<Vec<_>>(),
+        vec![(&"crm".to_string(), &"C-1042".to_string())]
+    );
+    run(&["inspect", &id]).unwrap();
+
+    run(&["annotate", &id, "--clear-note"]).unwrap();
+    let address = service.fetch(id.parse().unwrap()).unwrap();
+    assert_eq!(address.notes(), None);
+    assert_eq!(address.metadata().len(), 1);
+}
+
+#[test]
+fn cli_tail_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let cursor_file = log_dir.path().join("cursor");
+    let repo =
+        ChangeLogRepository::open(JsonAddressRepository::new(temp_dir.path()), log_dir.path())
+            .unwrap();
+    let logged = BoxedAddressService::new(Box::new(repo));
+    let run = |args: &[&str]| {
+        let mut cli = vec!["address_converter"];
+        cli.extend(args);
+        run_command(Cli::parse_from(cli), &logged)
+    };
+    let tail = || run(&["tail", "--cursor-file", cursor_file.to_str().unwrap()]);
+
+    run(&[
+        "save",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ])
+    .unwrap();
+    let id = get_file_id(temp_dir.path());
+    run(&["annotate", &id, "--note", "Ring twice"]).unwrap();
+    tail().unwrap();
+    assert_eq!(fs::read_to_string(&cursor_file).unwrap(), "2");
+
+    // Resumed from the cursor, unchanged when nothing was logged since.
+    tail().unwrap();
+    assert_eq!(fs::read_to_string(&cursor_file).unwrap(), "2");
+    run(&["delete", &id]).unwrap();
+    tail().unwrap();
+    assert_eq!(fs::read_to_string(&cursor_file).unwrap(), "3");
+
+    let events = logged.changes(1, 10).unwrap().unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|event| event.sequence)
+            .collect::<Vec<_>>(),
+        [2, 3]
+    );
+    assert_eq!(events[1].id.to_string(), id);
+    assert!(events[1].address.is_none());
+
+    // Without change log, there is nothing to tail.
+    let service = service(&temp_dir);
+    let result = run_command(Cli::parse_from(["address_converter", "tail"]), &service);
+    assert!(matches!(result, Err(e) if e.contains("CHANGE_LOG_DIR")));
+}
+
+#[test]
+fn cli_short_and_unhyphenated_ids() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let id = service
+        .save(
+            r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+
+    let simple = id.simple().to_string().to_uppercase();
+    let cli = Cli::parse_from(["address_converter", "fetch", &simple, "--format", "french"]);
+    assert!(run_command(cli, &service).is_ok());
+
+    let short = &id.to_string()[..8];
+    let cli = Cli::parse_from(["address_converter", "archive", short]);
+    assert!(run_command(cli, &service).is_ok());
+    let unknown = if short.starts_with('0') { "1" } else { "0" };
+    let cli = Cli::parse_from(["address_converter", "delete", unknown]);
+    assert!(run_command(cli, &service).is_err());
+    let cli = Cli::parse_from(["address_converter", "delete", short]);
+    assert!(run_command(cli, &service).is_ok());
+    assert!(service.ids().unwrap().is_empty());
+
+    // Invalid IDs, which aren't aliases either, are usage errors, before
+    // reaching the storage.
+    let error = Cli::try_parse_from(["address_converter", "delete", "not an id"])
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+}
+
+#[test]
+fn cli_convert_json_output_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "convert",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "MIOS", "country": "FRANCE"}"#,
+        "--to-format",
+        "iso20022",
+        "--output",
+        "json",
+    ]);
+    let result = run_command(cli, &service);
+    assert!(matches!(result, Err(e) if e.contains("Invalid postal information `MIOS`")));
+
+    // The details printed before the message
+    let error = service
+        .convert(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "MIOS", "country": "FRANCE"}"#,
+            Format::Iso20022,
+        )
+        .unwrap_err();
+    let mut printed = Vec::new();
+    let message = write_service_error(OutputMode::Json, error, &mut printed);
+    assert!(message.contains("Invalid postal information `MIOS`"));
+    let details: serde_json::Value = serde_json::from_slice(&printed).unwrap();
+    assert_eq!(details["code"], "E_POSTAL_FORMAT");
+    assert_eq!(details["field"], "postal");
+    assert_eq!(details["value"], "MIOS");
+}
+
+#[test]
+fn cli_generate_and_save() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "generate",
+        "--count",
+        "5",
+        "--kind",
+        "individual",
+        "--seed",
+        "12",
+        "--save",
+    ]);
+    run_command(cli, &service).unwrap();
+
+    let files = fs::read_dir(temp_dir.path()).unwrap().count();
+    assert_eq!(files, 5);
+
+    let invalid_cli = Cli::parse_from(["address_converter", "generate", "--format", "brazilian"]);
+    assert!(run_command(invalid_cli, &service).is_err());
+}
+
+#[test]
+fn cli_completions() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let cli = Cli::parse_from(["address_converter", "completions", shell]);
+        assert!(run_command(cli, &service).is_ok());
+    }
+
+    let cli = Cli::try_parse_from(["address_converter", "completions", "tcsh"]);
+    assert!(cli.is_err());
+
+    // Lists the stored IDs for the completion scripts
+    let save_cli = Cli::parse_from([
+        "address_converter",
+        "save",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ]);
+    run_command(save_cli, &service).unwrap();
+
+    let file_id = get_file_id(temp_dir.path());
+    let ids = service.ids().unwrap();
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids[0].to_string(), file_id);
+
+    let ids_cli = Cli::parse_from(["address_converter", "__complete-ids"]);
+    assert!(run_command(ids_cli, &service).is_ok());
+}
+
+#[test]
+fn cli_list_and_stats() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+
+    let save_cli = Cli::parse_from([
+        "address_converter",
+        "save",
+        "--address",
+        r#"{"name": "Madame Isabelle RICHARD", "street": "LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ]);
+    run_command(save_cli, &service).unwrap();
+
+    let list_cli = Cli::parse_from(["address_converter", "list", "--min-quality", "80"]);
+    assert!(run_command(list_cli, &service).is_ok());
+    assert!(service.list(80).unwrap().is_empty());
+
+    let department_cli = Cli::parse_from(["address_converter", "list", "--department", "82"]);
+    assert!(run_command(department_cli, &service).is_ok());
+    assert_eq!(
+        service.list(0).unwrap()[0].department_code().as_deref(),
+        Some("82")
+    );
+
+    let filter_cli = Cli::parse_from([
+        "address_converter",
+        "list",
+        "--town",
+        "Auterive",
+        "--country",
+        "FR",
+        "--kind",
+        "individual",
+    ]);
+    assert!(run_command(filter_cli, &service).is_ok());
+
+    let invalid_cli = Cli::try_parse_from(["address_converter", "list", "--min-quality", "101"]);
+    assert!(invalid_cli.is_err());
+    let invalid_kind = Cli::try_parse_from(["address_converter", "list", "--kind", "company"]);
+    assert!(invalid_kind.is_err());
+
+    let stats_cli = Cli::parse_from(["address_converter", "stats"]);
+    assert!(run_command(stats_cli, &service).is_ok());
+}
+
+#[test]
+fn cli_export_since() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let export_dir = TempDir::new().unwrap();
+    let export_path = export_dir.path().join("export.json");
+
+    let save_cli = Cli::parse_from([
+        "address_converter",
+        "save",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ]);
+    run_command(save_cli, &service).unwrap();
+    let file_id = get_file_id(temp_dir.path());
+
+    let export = |since: &str| {
+        let cli = Cli::parse_from([
+            "address_converter",
+            "export",
+            "--format",
+            "iso20022",
+            "--json-keys",
+            "iso-tags",
+            "--since",
+            since,
+            "--output",
+            export_path.to_str().unwrap(),
+        ]);
+        run_command(cli, &service).unwrap();
+
+        let content = fs::read_to_string(&export_path).unwrap();
+        serde_json::from_str::<Vec<serde_json::Value>>(&content).unwrap()
+    };
+
+    let exported = export("2024-01-01T00:00:00Z");
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0]["id"], file_id);
+    assert_eq!(exported[0]["address"]["PstlAdr"]["TwnNm"], "MIOS");
+
+    assert!(export("2999-01-01T00:00:00+02:00").is_empty());
+
+    let filtered_cli = Cli::parse_from([
+        "address_converter",
+        "export",
+        "--format",
+        "french",
+        "--postcode-prefix",
+        "82",
+        "--output",
+        export_path.to_str().unwrap(),
+    ]);
+    run_command(filtered_cli, &service).unwrap();
+    let content = fs::read_to_string(&export_path).unwrap();
+    assert_eq!(content.trim(), "[]");
+
+    let invalid_cli = Cli::parse_from([
+        "address_converter",
+        "export",
+        "--format",
+        "french",
+        "--since",
+        "2024-01-01",
+    ]);
+    let result = run_command(invalid_cli, &service);
+    assert!(matches!(result, Err(e) if e.contains("Invalid date")));
+
+    let csv = |columns: &[&str]| {
+        let mut args = vec!["address_converter", "export"];
+        args.extend(columns);
+        args.extend(["--output", export_path.to_str().unwrap()]);
+        run_command(Cli::parse_from(args), &service)
+            .map(|_| fs::read_to_string(&export_path).unwrap())
+    };
+    let content = csv(&["--columns"]).unwrap();
+    assert_eq!(
+        content.lines().next(),
+        Some("id,name,street,postcode,town,country")
+    );
+    let content = csv(&["--columns", "postcode,town,id"]).unwrap();
+    assert_eq!(
+        content.lines().collect::<Vec<_>>(),
+        vec!["postcode,town,id", &format!("33380,MIOS,{file_id}")]
+    );
+    let result = csv(&["--columns", "postcode,zipcode"]);
+    assert!(matches!(result, Err(e) if e.contains("Invalid column 'zipcode'")));
+}
+
+#[test]
+fn cli_import_analyze() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let input_dir = TempDir::new().unwrap();
+    let input_path = input_dir.path().join("addresses.json");
+    fs::write(
+        &input_path,
+        r#"[
+            {"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"},
+            {"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"},
+            {"name": "Monsieur Jean Delhourme", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}
+        ]"#,
+    )
+    .unwrap();
+
+    let import = |analyze: bool| {
+        let mut args = vec![
+            "address_converter",
+            "import",
+            input_path.to_str().unwrap(),
+            "--from-format",
+            "french",
+        ];
+        if analyze {
+            args.push("--analyze");
+        }
+        run_command(Cli::parse_from(args), &service)
+    };
+
+    import(true).unwrap();
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+    // The duplicate within the file is rejected
+    assert!(import(false).is_err());
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn cli_import_latin1() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let input_dir = TempDir::new().unwrap();
+    let input_path = input_dir.path().join("addresses.csv");
+    // A legacy export in Windows-1252, the apostrophe being 0x92 and the É 0xC9.
+    fs::write(
+        &input_path,
+        b"name,street,postal,country\nMonsieur Jean DELHOURME,25 RUE DE L\x92EGLISE,33700 M\xc9RIGNAC,FRANCE\n",
+    )
+    .unwrap();
+    let import = |encoding: &str| {
+        run_command(
+            Cli::parse_from([
+                "address_converter",
+                "import",
+                input_path.to_str().unwrap(),
+                "--from-format",
+                "french",
+                "--input-encoding",
+                encoding,
+            ]),
+            &service,
+        )
+    };
+
+    let result = import("utf-8");
+    assert!(matches!(result, Err(e) if e.contains("--input-encoding=latin1")));
+
+    import("latin1").unwrap();
+    let saved = service.repository.fetch_all().unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].street.as_ref().unwrap().name, "RUE DE L’EGLISE");
+    assert_eq!(saved[0].postal_details.town, "MÉRIGNAC");
+}
+
+#[cfg(feature = "xlsx")]
+#[test]
+fn cli_import_xlsx() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let input_dir = TempDir::new().unwrap();
+    let input_path = input_dir.path().join("clients.xlsx");
+
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let sheet = workbook.add_worksheet();
+    for (column, header) in (0..).zip(["Destinataire", "Adresse", "Ville", "Pays"]) {
+        sheet.write_string(0, column, header).unwrap();
+    }
+    for (column, cell) in (0..).zip([
+        "Monsieur Jean DELHOURME",
+        "25 RUE DE L'EGLISE",
+        "33380 MIOS",
+        "FRANCE",
+    ]) {
+        sheet.write_string(1, column, cell).unwrap();
+    }
+    workbook.save(&input_path).unwrap();
+
+    let import = |columns: &[&str]| {
+        let mut args = vec![
+            "address_converter",
+            "import",
+            input_path.to_str().unwrap(),
+            "--from-format",
+            "french",
+        ];
+        for column in columns {
+            args.extend(["--column", column]);
+        }
+        run_command(Cli::parse_from(args), &service)
+    };
+
+    let result = import(&["Destinataire"]);
+    assert!(matches!(result, Err(e) if e.contains("must be HEADER=FIELD")));
+
+    import(&[
+        "Destinataire=name",
+        "Adresse=street",
+        "Ville=postal",
+        "Pays=country",
+    ])
+    .unwrap();
+    let addresses = service.list(0).unwrap();
+    assert_eq!(addresses.len(), 1);
+    assert_eq!(addresses[0].postal_details.postcode, "33380");
+}
+
+#[test]
+fn cli_abbrev_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = TempDir::new().unwrap();
+    let config_path = config_dir.path().join("abbreviations.json");
+    fs::write(
+        &config_path,
+        r#"{"abbreviations": {"ZONE DES PINS": "ZDP"}}"#,
+    )
+    .unwrap();
+
+    let cli = Cli::parse_from([
+        "address_converter",
+        "--abbrev-file",
+        config_path.to_str().unwrap(),
+        "save",
+        "--address",
+        r#"{"business_name": "Société DUPONT", "street": "12 AVENUE DE LA ZONE DES PINS PROLONGEE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ]);
+    let normalizer = cli
+        .normalizer()
+        .unwrap()
+        .expect("--abbrev-file implies --normalize");
+    let service = service(&temp_dir).with_normalizer(normalizer);
+    run_command(cli, &service).unwrap();
+
+    let address = service
+        .fetch(get_file_id(temp_dir.path()).parse().unwrap())
+        .unwrap();
+    assert_eq!(address.street.unwrap().name, "AV DE LA ZDP PROLONGEE");
+
+    let cli = Cli::parse_from(["address_converter", "stats"]);
+    assert!(cli.normalizer().unwrap().is_none());
+}
+
+#[cfg(feature = "backup")]
+#[test]
+fn cli_backup_restore_into_another_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let save_cli = Cli::parse_from([
+        "address_converter",
+        "save",
+        "--address",
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        "--from-format",
+        "french",
+    ]);
+    run_command(save_cli, &service).unwrap();
+
+    let backup_dir = TempDir::new().unwrap();
+    let snapshot = backup_dir.path().join("snapshot.tar.zst");
+    let backup_cli = Cli::parse_from([
+        "address_converter",
+        "backup",
+        "--out",
+        snapshot.to_str().unwrap(),
+    ]);
+    run_command(backup_cli, &service).unwrap();
+
+    let target_dir = backup_dir.path().join("restored");
+    let restore_cli = Cli::parse_from([
+        "address_converter",
+        "restore",
+        "--from",
+        snapshot.to_str().unwrap(),
+        "--into",
+        target_dir.to_str().unwrap(),
+        "--yes",
+    ]);
+    run_command(restore_cli, &service).unwrap();
+
+    assert_eq!(get_file_id(&target_dir), get_file_id(temp_dir.path()));
+}
+
+#[test]
+fn cli_sync_storages() {
+    let (old_dir, new_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let (old, new) = (service(&old_dir), service(&new_dir));
+    old.save(
+        r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+        Format::French,
+    )
+    .unwrap();
+    new.save(
+        r#"{"name": "Madame Isabelle RICHARD", "street": "10 LE VILLAGE", "postal": "82500 AUTERIVE", "country": "FRANCE"}"#,
+        Format::French,
+    )
+    .unwrap();
+
+    let old_url = format!("json://{}", old_dir.path().display());
+    let new_url = format!("json://{}", new_dir.path().display());
+    let sync_cli = Cli::parse_from([
+        "address_converter",
+        "sync",
+        "--from",
+        &old_url,
+        "--to",
+        &new_url,
+        "--bidirectional",
+        "--yes",
+    ]);
+    run_command(sync_cli, &old).unwrap();
+
+    assert_eq!(old.repository.fetch_all().unwrap().len(), 2);
+    assert_eq!(new.repository.fetch_all().unwrap().len(), 2);
+
+    let unsupported = Cli::try_parse_from([
+        "address_converter",
+        "sync",
+        "--from",
+        &old_url,
+        "--to",
+        "sqlite://new.db",
+    ]);
+    assert!(unsupported.is_err());
+}
+
+#[test]
+fn cli_profile() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = temp_dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{"profile": {
+            "dev": {"storage": "memory://", "timeout": 2},
+            "prod": {"storage": "json://${AC_TEST_UNSET:-/var/lib/addresses}", "tenant": "billing"}
+        }}"#,
+    )
+    .unwrap();
+    let parse = |args: &[&str]| {
+        let mut cli = vec!["address_converter", "--config", config.to_str().unwrap()];
+        cli.extend(args);
+        Cli::parse_from(cli)
+    };
+
+    let mut cli = parse(&["--profile", "prod", "--tenant", "team-a", "stats"]);
+    assert_eq!(
+        cli.apply_profile().unwrap(),
+        Some(StorageUrl::Json("/var/lib/addresses".into()))
+    );
+    // The command line wins over the profile.
+    assert_eq!(cli.tenant.unwrap().as_str(), "team-a");
+
+    let mut cli = parse(&["--profile", "dev", "stats"]);
+    assert_eq!(cli.apply_profile().unwrap(), Some(StorageUrl::Memory));
+    assert_eq!(cli.timeout, Some(2));
+
+    let mut cli = parse(&["--profile", "staging", "stats"]);
+    assert!(matches!(cli.apply_profile(), Err(e) if e.contains("Unknown profile `staging`")));
+}
+
+#[test]
+fn cli_lookup() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let id = service
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+    let fingerprint = service.fetch(id).unwrap().fingerprint().to_string();
+
+    let lookup = |args: &[&str]| {
+        let cli = Cli::parse_from(["address_converter", "lookup"].iter().chain(args));
+        run_command(cli, &service)
+    };
+    for args in [
+        vec!["lookup", "--fingerprint", &fingerprint],
+        vec![
+            "lookup",
+            "--street",
+            "25 rue de l'Église",
+            "--postcode",
+            "33380",
+        ],
+    ] {
+        assert_eq!(cli_stdout(temp_dir.path(), &args), format!("{id}\n"));
+    }
+    assert_eq!(
+        lookup(&["--street", "27 RUE DE L'EGLISE", "--postcode", "33380"]).unwrap_err(),
+        "No stored address matches"
+    );
+
+    for args in [
+        vec!["lookup"],
+        vec!["lookup", "--street", "25 RUE DE L'EGLISE"],
+        vec!["lookup", "--fingerprint", "not-hex"],
+        vec![
+            "lookup",
+            "--fingerprint",
+            &fingerprint,
+            "--street",
+            "25 RUE DE L'EGLISE",
+        ],
+    ] {
+        assert!(Cli::try_parse_from(["address_converter"].into_iter().chain(args)).is_err());
+    }
+}
+
+#[test]
+fn cli_confirm_with_yes() {
+    let temp_dir = TempDir::new().unwrap();
+    let service = service(&temp_dir);
+    let id = service
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+
+    let dedupe_cli = Cli::parse_from(["address_converter", "dedupe", "--apply", "--yes"]);
+    run_command(dedupe_cli, &service).unwrap();
+    let delete_cli = Cli::parse_from(["address_converter", "-y", "delete", &id.to_string()]);
+    run_command(delete_cli, &service).unwrap();
+
+    assert!(service.ids().unwrap().is_empty());
+}
+
+#[test]
+fn cli_bulk_commands_need_yes() {
+    let (dir, replica_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    service(&dir)
+        .save(
+            r#"{"name": "Monsieur Jean DELHOURME", "street": "25 RUE DE L'EGLISE", "postal": "33380 MIOS", "country": "FRANCE"}"#,
+            Format::French,
+        )
+        .unwrap();
+    let url = format!("json://{}", dir.path().display());
+    let replica_url = format!("json://{}", replica_dir.path().display());
+    let run = |args: &[&str]| run_cli(dir.path(), args);
+
+    for (args, command) in [
+        (vec!["compact"], "compact"),
+        (vec!["sync", "--from", &url, "--to", &replica_url], "sync"),
+        (
+            vec![
+                "sync",
+                "--from",
+                &url,
+                "--to",
+                &replica_url,
+                "--bidirectional",
+            ],
+            "sync --bidirectional",
+        ),
+    ] {
+        let output = run(&args);
+        assert!(!output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            format!(
+                "Error: `{command}` changes the addresses in bulk: give --yes to run it non-interactively\n"
+            )
+        );
+    }
+    assert_eq!(fs::read_dir(replica_dir.path()).unwrap().count(), 0);
+
+    let output = run(&["sync", "--from", &url, "--to", &replica_url, "--yes"]);
+    assert!(output.status.success());
+    assert_eq!(service(&replica_dir).ids().unwrap().len(), 1);
+    assert!(run(&["-y", "compact"]).status.success());
+}